@@ -88,7 +88,10 @@ pub struct BuilderArgs {
     )]
     aws_kms_key_ids: Vec<String>,
 
-    /// Redis URI to use for KMS leasing
+    /// Redis URI to use for signer key leasing
+    ///
+    /// If empty, keys are leased in memory, which only guards against collisions
+    /// between builders in this process.
     #[arg(
         long = "builder.redis_uri",
         name = "builder.redis_uri",
@@ -97,7 +100,7 @@ pub struct BuilderArgs {
     )]
     redis_uri: String,
 
-    /// Redis lock TTL in milliseconds
+    /// Signer key lease TTL in milliseconds
     #[arg(
         long = "builder.redis_lock_ttl_millis",
         name = "builder.redis_lock_ttl_millis",
@@ -245,6 +248,46 @@ pub struct BuilderArgs {
     )]
     max_replacement_underpriced_blocks: u64,
 
+    /// The maximum number of bundle transactions each signer may have in flight at once.
+    ///
+    /// When greater than 1, new bundles are sent with the following nonces while earlier
+    /// bundle transactions are pending. Ops from senders in an in-flight bundle are left
+    /// out of new bundles.
+    #[arg(
+        long = "builder.max_in_flight_transactions",
+        name = "builder.max_in_flight_transactions",
+        env = "BUILDER_MAX_IN_FLIGHT_TRANSACTIONS",
+        default_value = "1"
+    )]
+    max_in_flight_transactions: u64,
+
+    /// The minimum expected profit margin of a bundle, in percent of its expected
+    /// transaction cost. The least profitable ops are dropped from a bundle until it
+    /// meets the margin.
+    ///
+    /// If not set, bundles are not checked for profitability.
+    #[arg(
+        long = "builder.min_profit_margin_percent",
+        name = "builder.min_profit_margin_percent",
+        env = "BUILDER_MIN_PROFIT_MARGIN_PERCENT"
+    )]
+    min_profit_margin_percent: Option<u32>,
+
+    /// If set, candidate bundles are simulated on top of a pending state rather than
+    /// the latest block: the builder's in-flight bundle transactions, followed by the
+    /// node's pending mempool transactions from or to the bundle's senders and paymasters.
+    /// Ops that would fail in the pending state are rejected.
+    ///
+    /// Requires the node to support `eth_simulateV1`, and `txpool_content` to include
+    /// mempool transactions.
+    #[arg(
+        long = "builder.pending_simulation",
+        name = "builder.pending_simulation",
+        env = "BUILDER_PENDING_SIMULATION",
+        default_value = "false"
+    )]
+    pending_simulation: bool,
+
     /// The index offset to apply to the builder index
     #[arg(
         long = "builder_index_offset",
@@ -303,6 +346,17 @@ impl BuilderArgs {
             });
             num_builders += common.num_builders_v0_7;
         }
+        if !common.disable_entry_point_v0_8 {
+            entry_points.push(EntryPointBuilderSettings {
+                address: chain_spec.entry_point_address_v0_8,
+                version: EntryPointVersion::V0_8,
+                num_bundle_builders: common.num_builders_v0_8,
+                bundle_builder_index_offset: self.builder_index_offset,
+                mempool_configs: mempool_configs
+                    .get_for_entry_point(chain_spec.entry_point_address_v0_8),
+            });
+            num_builders += common.num_builders_v0_8;
+        }
 
         if (self.private_key.is_some() || !self.private_keys.is_empty())
             && !self.aws_kms_key_ids.is_empty()
@@ -358,9 +412,13 @@ impl BuilderArgs {
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
             max_cancellation_fee_increases: self.max_cancellation_fee_increases,
             max_replacement_underpriced_blocks: self.max_replacement_underpriced_blocks,
+            max_in_flight_transactions: self.max_in_flight_transactions,
             remote_address,
             da_gas_tracking_enabled,
+            min_profit_margin_percent: self.min_profit_margin_percent,
+            pending_simulation: self.pending_simulation,
             provider_client_timeout_seconds,
+            aggregators: super::load_aggregator_configs(common).await?,
         })
     }
 
@@ -438,14 +496,20 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         pool_url,
     } = builder_args;
 
-    let (event_sender, event_rx) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let (event_sender, event_rx) =
+        broadcast::channel::<WithEntryPoint<BuilderEvent>>(EVENT_CHANNEL_CAPACITY);
     task_spawner.spawn_critical(
         "recv and log events",
         Box::pin(emit::receive_and_log_events_with_filter(
             event_rx,
-            is_nonspammy_event,
+            |event| is_nonspammy_event(&event.event),
         )),
     );
+    common_args
+        .event_sink
+        .spawn_sinks(&task_spawner, &event_sender, |event| {
+            is_nonspammy_event(&event.event).then(|| event.clone().into())
+        })?;
 
     let task_args = builder_args
         .to_args(
@@ -468,7 +532,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         event_sender,
         LocalBuilderBuilder::new(REQUEST_CHANNEL_CAPACITY),
         pool,
-        super::construct_providers(&common_args, &chain_spec)?,
+        super::construct_providers(&common_args, &chain_spec).await?,
     )
     .spawn(task_spawner)
     .await?;
@@ -476,12 +540,12 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     Ok(())
 }
 
-pub fn is_nonspammy_event(event: &WithEntryPoint<BuilderEvent>) -> bool {
+pub fn is_nonspammy_event(event: &BuilderEvent) -> bool {
     if let BuilderEventKind::FormedBundle {
         tx_details,
         fee_increase_count,
         ..
-    } = &event.event.kind
+    } = &event.kind
     {
         if tx_details.is_none() && *fee_increase_count == 0 {
             return false;
@@ -123,5 +123,9 @@ define_hardcoded_chain_specs!(
     polygon_amoy,
     avax,
     bera_bartio,
-    avax_fuji
+    avax_fuji,
+    scroll,
+    scroll_sepolia,
+    mantle,
+    mantle_sepolia
 );
@@ -0,0 +1,183 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Args;
+use rundler_builder::BuilderEvent;
+use rundler_pool::PoolEvent;
+use rundler_task::TaskSpawnerExt;
+use rundler_utils::{
+    emit::WithEntryPoint,
+    event_sink::{self, EventSinkSettings, JsonLinesFileSink, WebhookSink},
+    retry::RetryOpts,
+};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use url::Url;
+
+/// CLI options for exporting pool and builder events
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Event Sink")]
+pub struct EventSinkArgs {
+    /// File to append events to as JSON lines
+    #[arg(
+        long = "event_sink.file",
+        name = "event_sink.file",
+        env = "EVENT_SINK_FILE",
+        global = true
+    )]
+    file: Option<PathBuf>,
+
+    /// URL to post batches of events to as JSON arrays
+    #[arg(
+        long = "event_sink.webhook_url",
+        name = "event_sink.webhook_url",
+        env = "EVENT_SINK_WEBHOOK_URL",
+        global = true
+    )]
+    webhook_url: Option<Url>,
+
+    /// Timeout for each webhook request
+    #[arg(
+        long = "event_sink.webhook_timeout_millis",
+        name = "event_sink.webhook_timeout_millis",
+        env = "EVENT_SINK_WEBHOOK_TIMEOUT_MILLIS",
+        default_value = "5000",
+        global = true
+    )]
+    webhook_timeout_millis: u64,
+
+    /// Maximum number of events written to a sink at once
+    #[arg(
+        long = "event_sink.batch_size",
+        name = "event_sink.batch_size",
+        env = "EVENT_SINK_BATCH_SIZE",
+        default_value = "100",
+        global = true
+    )]
+    batch_size: usize,
+
+    /// Maximum time an event is held before it is written to a sink
+    #[arg(
+        long = "event_sink.flush_interval_millis",
+        name = "event_sink.flush_interval_millis",
+        env = "EVENT_SINK_FLUSH_INTERVAL_MILLIS",
+        default_value = "1000",
+        global = true
+    )]
+    flush_interval_millis: u64,
+
+    /// Maximum number of batches waiting to be written before new batches are dropped
+    #[arg(
+        long = "event_sink.max_queued_batches",
+        name = "event_sink.max_queued_batches",
+        env = "EVENT_SINK_MAX_QUEUED_BATCHES",
+        default_value = "10",
+        global = true
+    )]
+    max_queued_batches: usize,
+
+    /// Maximum number of attempts to write a batch before it is dropped
+    #[arg(
+        long = "event_sink.max_attempts",
+        name = "event_sink.max_attempts",
+        env = "EVENT_SINK_MAX_ATTEMPTS",
+        default_value = "5",
+        global = true
+    )]
+    max_attempts: u64,
+}
+
+impl EventSinkArgs {
+    fn settings(&self) -> EventSinkSettings {
+        EventSinkSettings {
+            batch_size: self.batch_size,
+            flush_interval: Duration::from_millis(self.flush_interval_millis),
+            max_queued_batches: self.max_queued_batches,
+            retry: RetryOpts {
+                max_attempts: self.max_attempts,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Spawns a task for each configured sink, exporting the events for which
+    /// `to_record` returns a record.
+    pub fn spawn_sinks<T, E>(
+        &self,
+        task_spawner: &T,
+        events: &broadcast::Sender<E>,
+        to_record: fn(&E) -> Option<EventRecord>,
+    ) -> anyhow::Result<()>
+    where
+        T: TaskSpawnerExt,
+        E: Clone + Send + 'static,
+    {
+        if let Some(path) = &self.file {
+            task_spawner.spawn_critical(
+                "export events to file",
+                Box::pin(event_sink::receive_events_to_sink(
+                    "file sink",
+                    events.subscribe(),
+                    to_record,
+                    JsonLinesFileSink::new(path.clone()),
+                    self.settings(),
+                )),
+            );
+        }
+
+        if let Some(url) = &self.webhook_url {
+            let sink = WebhookSink::new(
+                url.clone(),
+                Duration::from_millis(self.webhook_timeout_millis),
+            )
+            .context("event sink webhook should be valid")?;
+            task_spawner.spawn_critical(
+                "export events to webhook",
+                Box::pin(event_sink::receive_events_to_sink(
+                    "webhook sink",
+                    events.subscribe(),
+                    to_record,
+                    sink,
+                    self.settings(),
+                )),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// An exported event, tagged with the component that emitted it
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum EventRecord {
+    /// Event emitted by the pool
+    Pool(WithEntryPoint<PoolEvent>),
+    /// Event emitted by a builder
+    Builder(WithEntryPoint<BuilderEvent>),
+}
+
+impl From<WithEntryPoint<PoolEvent>> for EventRecord {
+    fn from(event: WithEntryPoint<PoolEvent>) -> Self {
+        Self::Pool(event)
+    }
+}
+
+impl From<WithEntryPoint<BuilderEvent>> for EventRecord {
+    fn from(event: WithEntryPoint<BuilderEvent>) -> Self {
+        Self::Builder(event)
+    }
+}
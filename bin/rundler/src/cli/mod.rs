@@ -19,6 +19,7 @@ use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
 
 mod builder;
 mod chain_spec;
+mod event_sink;
 mod json;
 mod metrics;
 mod node;
@@ -27,21 +28,23 @@ mod rpc;
 mod tracing;
 
 use builder::BuilderCliArgs;
+use event_sink::EventSinkArgs;
 use node::NodeCliArgs;
 use pool::PoolCliArgs;
 use reth_tasks::TaskManager;
 use rpc::RpcCliArgs;
 use rundler_provider::{
     AlloyEntryPointV0_6, AlloyEntryPointV0_7, AlloyEvmProvider, DAGasOracleSync,
-    EntryPointProvider, EvmProvider, Providers,
+    EntryPointProvider, EvmProvider, MultiNodeConfig, Providers,
 };
 use rundler_rpc::{EthApiSettings, RundlerApiSettings};
 use rundler_sim::{
-    EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
+    EstimationBackend, EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings,
+    ValidationTracer, MIN_CALL_GAS_LIMIT,
 };
 use rundler_types::{
-    chain::ChainSpec, da::DAGasOracleType, v0_6::UserOperation as UserOperationV0_6,
-    v0_7::UserOperation as UserOperationV0_7,
+    aggregator::AggregatorConfigs, chain::ChainSpec, da::DAGasOracleType,
+    v0_6::UserOperation as UserOperationV0_6, v0_7::UserOperation as UserOperationV0_7,
 };
 
 /// Main entry point for the CLI
@@ -69,6 +72,16 @@ pub async fn run() -> anyhow::Result<()> {
     let cs = chain_spec::resolve_chain_spec(&opt.common.network, &opt.common.chain_spec);
     tracing::info!("Chain spec: {:#?}", cs);
 
+    if !cs.embedded_evm_supported
+        && (opt.common.validation_tracer == ValidationTracer::Local
+            || opt.common.estimation_backend == EstimationBackend::Local)
+    {
+        bail!(
+            "{} is not supported by the embedded EVM, validation_tracer and estimation_backend must not be local",
+            cs.name
+        );
+    }
+
     match opt.command {
         Command::Node(args) => {
             node::spawn_tasks(task_spawner.clone(), cs, *args, opt.common).await?
@@ -159,6 +172,16 @@ pub struct CommonArgs {
     )]
     node_http: Option<String>,
 
+    /// Path to a JSON multi-node configuration. If set, providers spread requests over the
+    /// configured endpoints instead of using `node_http` alone.
+    #[arg(
+        long = "node_config_path",
+        name = "node_config_path",
+        env = "NODE_CONFIG_PATH",
+        global = true
+    )]
+    pub node_config_path: Option<String>,
+
     /// Flag for turning unsafe bundling mode on
     #[arg(long = "unsafe", env = "UNSAFE", global = true)]
     unsafe_mode: bool,
@@ -210,6 +233,18 @@ pub struct CommonArgs {
     )]
     tracer_timeout: String,
 
+    /// How user operation validation is traced, either `javascript` (the bundler's
+    /// tracer on the node), `native` (the node's ERC-7562 tracer) or `local`
+    /// (embedded EVM, entry point v0.7 only)
+    #[arg(
+        long = "validation_tracer",
+        name = "validation_tracer",
+        env = "VALIDATION_TRACER",
+        default_value = "javascript",
+        global = true
+    )]
+    validation_tracer: ValidationTracer,
+
     /// Amount of blocks to search when calling eth_getUserOperationByHash.
     /// Defaults from 0 to latest block
     #[arg(
@@ -238,6 +273,17 @@ pub struct CommonArgs {
     )]
     verification_estimation_gas_fee: u128,
 
+    /// Where the simulations of gas estimation are run, either `rpc` (`eth_call` to the
+    /// node) or `local` (embedded EVM, falling back to `rpc` on failure)
+    #[arg(
+        long = "estimation_backend",
+        name = "estimation_backend",
+        env = "ESTIMATION_BACKEND",
+        default_value = "rpc",
+        global = true
+    )]
+    estimation_backend: EstimationBackend,
+
     #[arg(
         long = "bundle_base_fee_overhead_percent",
         name = "bundle_base_fee_overhead_percent",
@@ -301,6 +347,16 @@ pub struct CommonArgs {
     )]
     pub mempool_config_path: Option<String>,
 
+    /// Path to a JSON list of trusted signature aggregators. Operations using any
+    /// other aggregator are rejected.
+    #[arg(
+        long = "aggregator_config_path",
+        name = "aggregator_config_path",
+        env = "AGGREGATOR_CONFIG_PATH",
+        global = true
+    )]
+    pub aggregator_config_path: Option<String>,
+
     #[arg(
         long = "disable_entry_point_v0_6",
         name = "disable_entry_point_v0_6",
@@ -339,6 +395,25 @@ pub struct CommonArgs {
     )]
     pub num_builders_v0_7: u64,
 
+    #[arg(
+        long = "disable_entry_point_v0_8",
+        name = "disable_entry_point_v0_8",
+        env = "DISABLE_ENTRY_POINT_V0_8",
+        default_value = "false",
+        global = true
+    )]
+    pub disable_entry_point_v0_8: bool,
+
+    // Ignored if entry_point_v0_8_enabled is false
+    #[arg(
+        long = "num_builders_v0_8",
+        name = "num_builders_v0_8",
+        env = "NUM_BUILDERS_V0_8",
+        default_value = "1",
+        global = true
+    )]
+    pub num_builders_v0_8: u64,
+
     #[arg(
         long = "da_gas_tracking_enabled",
         name = "da_gas_tracking_enabled",
@@ -354,6 +429,9 @@ pub struct CommonArgs {
         default_value = "10"
     )]
     pub provider_client_timeout_seconds: u64,
+
+    #[command(flatten)]
+    pub event_sink: EventSinkArgs,
 }
 
 const SIMULATION_GAS_OVERHEAD: u64 = 100_000;
@@ -389,6 +467,7 @@ impl TryFrom<&CommonArgs> for EstimationSettings {
             max_total_execution_gas: value.max_bundle_gas,
             max_simulate_handle_ops_gas: value.max_simulate_handle_ops_gas,
             verification_estimation_gas_fee: value.verification_estimation_gas_fee,
+            estimation_backend: value.estimation_backend,
         })
     }
 }
@@ -419,11 +498,15 @@ impl TryFrom<&CommonArgs> for SimulationSettings {
         if go_parse_duration::parse_duration(&value.tracer_timeout).is_err() {
             bail!("Invalid value for tracer_timeout, must be parsable by the ParseDuration function. See docs https://pkg.go.dev/time#ParseDuration")
         }
+        if value.validation_tracer == ValidationTracer::Local && !value.disable_entry_point_v0_6 {
+            bail!("validation_tracer local only supports entry point v0.7, entry point v0.6 must be disabled with --disable_entry_point_v0_6")
+        }
 
         Ok(Self::new(
             value.min_unstake_delay,
             U256::from(value.min_stake_value),
             value.tracer_timeout.clone(),
+            value.validation_tracer,
         ))
     }
 }
@@ -564,6 +647,7 @@ pub struct RundlerProviders<P, EP06, EP07, D> {
     provider: P,
     ep_v0_6: Option<EP06>,
     ep_v0_7: Option<EP07>,
+    ep_v0_8: Option<EP07>,
     da_gas_oracle_sync: Option<D>,
 }
 
@@ -577,6 +661,7 @@ where
     type Evm = P;
     type EntryPointV0_6 = EP06;
     type EntryPointV0_7 = EP07;
+    type EntryPointV0_8 = EP07;
     type DAGasOracleSync = D;
 
     fn evm(&self) -> &Self::Evm {
@@ -591,17 +676,22 @@ where
         &self.ep_v0_7
     }
 
+    fn ep_v0_8(&self) -> &Option<Self::EntryPointV0_8> {
+        &self.ep_v0_8
+    }
+
     fn da_gas_oracle_sync(&self) -> &Option<Self::DAGasOracleSync> {
         &self.da_gas_oracle_sync
     }
 }
 
-pub fn construct_providers(
+pub async fn construct_providers(
     args: &CommonArgs,
     chain_spec: &ChainSpec,
 ) -> anyhow::Result<impl Providers> {
-    let provider = Arc::new(rundler_provider::new_alloy_provider(
-        args.node_http.as_ref().context("must provide node_http")?,
+    let node_config = load_node_config(args).await?;
+    let provider = Arc::new(rundler_provider::new_alloy_multi_node_provider(
+        &node_config,
         args.provider_client_timeout_seconds,
     )?);
     let (da_gas_oracle, da_gas_oracle_sync) =
@@ -633,14 +723,53 @@ pub fn construct_providers(
         ))
     };
 
+    let ep_v0_8 = if args.disable_entry_point_v0_8 {
+        None
+    } else {
+        Some(AlloyEntryPointV0_7::new_v0_8(
+            chain_spec.clone(),
+            args.max_verification_gas,
+            args.max_simulate_handle_ops_gas,
+            args.max_simulate_handle_ops_gas,
+            provider.clone(),
+            da_gas_oracle.clone(),
+        ))
+    };
+
     Ok(RundlerProviders {
         provider: AlloyEvmProvider::new(provider),
         ep_v0_6,
         ep_v0_7,
+        ep_v0_8,
         da_gas_oracle_sync,
     })
 }
 
+async fn load_node_config(common: &CommonArgs) -> anyhow::Result<MultiNodeConfig> {
+    match &common.node_config_path {
+        Some(path) => json::get_json_config::<MultiNodeConfig>(path)
+            .await
+            .with_context(|| format!("should load node configuration from {path}")),
+        None => Ok(MultiNodeConfig::single(
+            common
+                .node_http
+                .as_ref()
+                .context("must provide node_http")?,
+        )),
+    }
+}
+
+async fn load_aggregator_configs(common: &CommonArgs) -> anyhow::Result<AggregatorConfigs> {
+    let aggregators = match &common.aggregator_config_path {
+        Some(path) => json::get_json_config::<AggregatorConfigs>(path)
+            .await
+            .with_context(|| format!("should load aggregator configurations from {path}"))?,
+        None => AggregatorConfigs::default(),
+    };
+    tracing::info!("Trusted aggregators: {:?}", aggregators);
+    Ok(aggregators)
+}
+
 fn lint_da_gas_tracking(da_gas_tracking_enabled: bool, chain_spec: &ChainSpec) -> bool {
     if !da_gas_tracking_enabled {
         return false;
@@ -649,9 +778,14 @@ fn lint_da_gas_tracking(da_gas_tracking_enabled: bool, chain_spec: &ChainSpec) -
     if !chain_spec.da_pre_verification_gas {
         tracing::warn!("DA tracking is disabled because DA pre-verification gas is not enabled");
         false
-    } else if !(chain_spec.da_gas_oracle_type == DAGasOracleType::CachedNitro
-        || chain_spec.da_gas_oracle_type == DAGasOracleType::LocalBedrock)
-    {
+    } else if !matches!(
+        chain_spec.da_gas_oracle_type,
+        DAGasOracleType::CachedNitro
+            | DAGasOracleType::LocalBedrock
+            | DAGasOracleType::LocalScroll
+            | DAGasOracleType::LocalMantle
+            | DAGasOracleType::ZkStackPubdata
+    ) {
         tracing::warn!("DA tracking is disabled because DA gas oracle contract type {:?} does not support caching", chain_spec.da_gas_oracle_type);
         false
     } else {
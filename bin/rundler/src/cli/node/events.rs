@@ -15,6 +15,9 @@ use std::fmt::Display;
 
 use rundler_builder::BuilderEvent;
 use rundler_pool::PoolEvent;
+use rundler_utils::emit::WithEntryPoint;
+
+use crate::cli::{builder, event_sink::EventRecord};
 
 #[derive(Clone, Debug)]
 pub enum Event {
@@ -22,6 +25,14 @@ pub enum Event {
     BuilderEvent(BuilderEvent),
 }
 
+/// Filters out the builder events that builder-only mode does not log or export
+pub fn is_nonspammy_event(event: &WithEntryPoint<Event>) -> bool {
+    match &event.event {
+        Event::PoolEvent(_) => true,
+        Event::BuilderEvent(event) => builder::is_nonspammy_event(event),
+    }
+}
+
 impl From<PoolEvent> for Event {
     fn from(event: PoolEvent) -> Self {
         Self::PoolEvent(event)
@@ -34,6 +45,16 @@ impl From<BuilderEvent> for Event {
     }
 }
 
+impl From<WithEntryPoint<Event>> for EventRecord {
+    fn from(event: WithEntryPoint<Event>) -> Self {
+        let entry_point = event.entry_point;
+        match event.event {
+            Event::PoolEvent(event) => WithEntryPoint { entry_point, event }.into(),
+            Event::BuilderEvent(event) => WithEntryPoint { entry_point, event }.into(),
+        }
+    }
+}
+
 impl Display for Event {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
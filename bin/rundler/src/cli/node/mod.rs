@@ -21,12 +21,7 @@ use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
 
 use self::events::Event;
-use crate::cli::{
-    builder::{self, BuilderArgs},
-    pool::PoolArgs,
-    rpc::RpcArgs,
-    CommonArgs,
-};
+use crate::cli::{builder::BuilderArgs, pool::PoolArgs, rpc::RpcArgs, CommonArgs};
 mod events;
 
 const REQUEST_CHANNEL_CAPACITY: usize = 1024;
@@ -62,14 +57,16 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     let builder_task_args = builder_args
         .to_args(chain_spec.clone(), &common_args, None)
         .await?;
-    let rpc_task_args = rpc_args.to_args(
-        chain_spec.clone(),
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-        (&common_args).try_into()?,
-    )?;
+    let rpc_task_args = rpc_args
+        .to_args(
+            chain_spec.clone(),
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+            (&common_args).try_into()?,
+        )
+        .await?;
 
     let (event_sender, event_rx) =
         broadcast::channel::<WithEntryPoint<Event>>(EVENT_CHANNEL_CAPACITY);
@@ -80,8 +77,16 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
 
     task_spawner.spawn_critical(
         "recv and log events",
-        Box::pin(emit::receive_and_log_events_with_filter(event_rx, |_| true)),
+        Box::pin(emit::receive_and_log_events_with_filter(
+            event_rx,
+            events::is_nonspammy_event,
+        )),
     );
+    common_args
+        .event_sink
+        .spawn_sinks(&task_spawner, &event_sender, |event| {
+            events::is_nonspammy_event(event).then(|| event.clone().into())
+        })?;
     task_spawner.spawn_critical(
         "recv op pool events",
         Box::pin(emit::receive_events("op pool", op_pool_event_rx, {
@@ -96,9 +101,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         Box::pin(emit::receive_events("builder", builder_event_rx, {
             let event_sender = event_sender.clone();
             move |event| {
-                if builder::is_nonspammy_event(&event) {
-                    let _ = event_sender.send(WithEntryPoint::of(event));
-                }
+                let _ = event_sender.send(WithEntryPoint::of(event));
             }
         })),
     );
@@ -109,7 +112,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     let builder_builder = LocalBuilderBuilder::new(REQUEST_CHANNEL_CAPACITY);
     let builder_handle = builder_builder.get_handle();
 
-    let providers = super::construct_providers(&common_args, &chain_spec)?;
+    let providers = super::construct_providers(&common_args, &chain_spec).await?;
 
     PoolTask::new(
         pool_task_args,
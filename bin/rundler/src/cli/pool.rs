@@ -11,16 +11,24 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
-
-use alloy_primitives::Address;
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use alloy_primitives::{Address, B256};
 use anyhow::Context;
 use clap::Args;
-use rundler_pool::{LocalPoolBuilder, PoolConfig, PoolTask, PoolTaskArgs};
+use rundler_p2p::P2pConfig;
+use rundler_pool::{
+    LocalPoolBuilder, OrderingStrategy, PoolConfig, PoolEvent, PoolTask, PoolTaskArgs,
+};
 use rundler_sim::MempoolConfigs;
 use rundler_task::TaskSpawnerExt;
 use rundler_types::{chain::ChainSpec, EntryPointVersion};
-use rundler_utils::emit::{self, EVENT_CHANNEL_CAPACITY};
+use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
 
 use super::CommonArgs;
@@ -99,6 +107,16 @@ pub struct PoolArgs {
     )]
     pub chain_poll_interval_millis: u64,
 
+    /// Websocket URL of an Eth node to subscribe to new heads on. If set, the pool
+    /// receives new blocks from the subscription, and only polls while it is disconnected.
+    #[arg(
+        long = "pool.chain_ws_url",
+        name = "pool.chain_ws_url",
+        env = "POOL_CHAIN_WS_URL",
+        global = true
+    )]
+    pub chain_ws_url: Option<String>,
+
     /// The amount of times to retry syncing the chain before giving up and
     /// waiting for the next block.
     #[arg(
@@ -179,6 +197,134 @@ pub struct PoolArgs {
         default_value = "0.0"
     )]
     pub gas_limit_efficiency_reject_threshold: f32,
+
+    /// How operations are ranked when building bundles, either `effective_tip`
+    /// or `max_fee`
+    #[arg(
+        long = "pool.ordering",
+        name = "pool.ordering",
+        env = "POOL_ORDERING",
+        default_value = "effective_tip"
+    )]
+    pub ordering: OrderingStrategy,
+
+    #[arg(
+        long = "pool.store_path",
+        name = "pool.store_path",
+        env = "POOL_STORE_PATH"
+    )]
+    pub store_path: Option<PathBuf>,
+
+    #[arg(
+        long = "pool.store_snapshot_interval_secs",
+        name = "pool.store_snapshot_interval_secs",
+        env = "POOL_STORE_SNAPSHOT_INTERVAL_SECS",
+        default_value = "60"
+    )]
+    pub store_snapshot_interval_secs: u64,
+
+    #[arg(
+        long = "pool.receipt_index_path",
+        name = "pool.receipt_index_path",
+        env = "POOL_RECEIPT_INDEX_PATH"
+    )]
+    pub receipt_index_path: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub p2p: P2pArgs,
+}
+
+/// CLI options for the p2p network
+#[derive(Args, Debug)]
+#[command(next_help_heading = "P2P")]
+pub struct P2pArgs {
+    /// Enable sharing user operations with other bundlers over the p2p network
+    #[arg(
+        long = "p2p.enabled",
+        name = "p2p.enabled",
+        env = "P2P_ENABLED",
+        default_value = "false"
+    )]
+    pub enabled: bool,
+
+    /// Address to listen on for p2p connections and discovery
+    #[arg(
+        long = "p2p.listen_address",
+        name = "p2p.listen_address",
+        env = "P2P_LISTEN_ADDRESS",
+        default_value = "0.0.0.0"
+    )]
+    pub listen_address: Ipv4Addr,
+
+    /// TCP port to listen on for p2p connections
+    #[arg(
+        long = "p2p.tcp_port",
+        name = "p2p.tcp_port",
+        env = "P2P_TCP_PORT",
+        default_value = "4337"
+    )]
+    pub tcp_port: u16,
+
+    /// UDP port to listen on for discovery
+    #[arg(
+        long = "p2p.udp_port",
+        name = "p2p.udp_port",
+        env = "P2P_UDP_PORT",
+        default_value = "4337"
+    )]
+    pub udp_port: u16,
+
+    /// Externally reachable address to advertise to peers
+    #[arg(
+        long = "p2p.enr_address",
+        name = "p2p.enr_address",
+        env = "P2P_ENR_ADDRESS"
+    )]
+    pub enr_address: Option<Ipv4Addr>,
+
+    /// ENRs of the nodes to bootstrap discovery from
+    #[arg(
+        long = "p2p.bootnodes",
+        name = "p2p.bootnodes",
+        env = "P2P_BOOTNODES",
+        value_delimiter = ','
+    )]
+    pub bootnodes: Vec<String>,
+
+    /// Private key of the node identity, a random identity is used if not set
+    #[arg(
+        long = "p2p.private_key",
+        name = "p2p.private_key",
+        env = "P2P_PRIVATE_KEY"
+    )]
+    pub private_key: Option<B256>,
+
+    /// Number of peers to maintain connections to
+    #[arg(
+        long = "p2p.target_peers",
+        name = "p2p.target_peers",
+        env = "P2P_TARGET_PEERS",
+        default_value = "25"
+    )]
+    pub target_peers: usize,
+}
+
+impl P2pArgs {
+    fn to_config(&self) -> Option<P2pConfig> {
+        if !self.enabled {
+            return None;
+        }
+
+        Some(P2pConfig {
+            listen_address: self.listen_address,
+            tcp_port: self.tcp_port,
+            udp_port: self.udp_port,
+            enr_address: self.enr_address,
+            bootnodes: self.bootnodes.clone(),
+            private_key: self.private_key,
+            target_peers: self.target_peers,
+        })
+    }
 }
 
 impl PoolArgs {
@@ -235,6 +381,8 @@ impl PoolArgs {
             drop_min_num_blocks: self.drop_min_num_blocks,
             da_gas_tracking_enabled,
             gas_limit_efficiency_reject_threshold: self.gas_limit_efficiency_reject_threshold,
+            ordering: self.ordering,
+            aggregators: super::load_aggregator_configs(common).await?,
         };
 
         let mut pool_configs = vec![];
@@ -259,16 +407,31 @@ impl PoolArgs {
                 ..pool_config_base.clone()
             });
         }
+        if !common.disable_entry_point_v0_8 {
+            pool_configs.push(PoolConfig {
+                entry_point: chain_spec.entry_point_address_v0_8,
+                entry_point_version: EntryPointVersion::V0_8,
+                num_shards: common.num_builders_v0_8,
+                mempool_channel_configs: mempool_channel_configs
+                    .get_for_entry_point(chain_spec.entry_point_address_v0_8),
+                ..pool_config_base.clone()
+            });
+        }
 
         Ok(PoolTaskArgs {
             chain_spec,
             unsafe_mode: common.unsafe_mode,
             http_url: common.node_http.clone().context("must provide node_http")?,
             chain_poll_interval: Duration::from_millis(self.chain_poll_interval_millis),
+            chain_ws_url: self.chain_ws_url.clone(),
             chain_max_sync_retries: self.chain_sync_max_retries,
             pool_configs,
             remote_address,
             chain_update_channel_capacity: self.chain_update_channel_capacity.unwrap_or(1024),
+            p2p_config: self.p2p.to_config(),
+            store_path: self.store_path.clone(),
+            store_snapshot_interval: Duration::from_secs(self.store_snapshot_interval_secs),
+            receipt_index_path: self.receipt_index_path.clone(),
         })
     }
 }
@@ -287,7 +450,8 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     common_args: CommonArgs,
 ) -> anyhow::Result<()> {
     let PoolCliArgs { pool: pool_args } = pool_args;
-    let (event_sender, event_rx) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let (event_sender, event_rx) =
+        broadcast::channel::<WithEntryPoint<PoolEvent>>(EVENT_CHANNEL_CAPACITY);
     let task_args = pool_args
         .to_args(
             chain_spec.clone(),
@@ -300,12 +464,17 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         "recv and log events",
         Box::pin(emit::receive_and_log_events_with_filter(event_rx, |_| true)),
     );
+    common_args
+        .event_sink
+        .spawn_sinks(&task_spawner, &event_sender, |event| {
+            Some(event.clone().into())
+        })?;
 
     PoolTask::new(
         task_args,
         event_sender,
         LocalPoolBuilder::new(REQUEST_CHANNEL_CAPACITY, BLOCK_CHANNEL_CAPACITY),
-        super::construct_providers(&common_args, &chain_spec)?,
+        super::construct_providers(&common_args, &chain_spec).await?,
     )
     .spawn(task_spawner)
     .await?;
@@ -18,11 +18,11 @@ use clap::Args;
 use rundler_builder::RemoteBuilderClient;
 use rundler_pool::RemotePoolClient;
 use rundler_rpc::{EthApiSettings, RpcTask, RpcTaskArgs, RundlerApiSettings};
-use rundler_sim::{EstimationSettings, PrecheckSettings};
+use rundler_sim::{EstimationSettings, MempoolConfigs, PrecheckSettings};
 use rundler_task::{server::connect_with_retries_shutdown, TaskSpawnerExt};
-use rundler_types::chain::ChainSpec;
+use rundler_types::{chain::ChainSpec, revert::RevertDecoder};
 
-use super::CommonArgs;
+use super::{json::get_json_config, CommonArgs};
 
 /// CLI options for the RPC server
 #[derive(Args, Debug)]
@@ -90,12 +90,24 @@ pub struct RpcArgs {
         value_delimiter = ','
     )]
     pub corsdomain: Option<Vec<http::HeaderValue>>,
+
+    /// Path to a JSON list of custom error signatures, e.g.
+    /// `"error InvalidSigner(address signer)"`, used to decode reverts in
+    /// gas estimation errors in addition to the built-in errors
+    ///
+    /// This path can either be a local file path or an S3 url.
+    #[arg(
+        long = "rpc.revert_errors_path",
+        name = "rpc.revert_errors_path",
+        env = "RPC_REVERT_ERRORS_PATH"
+    )]
+    revert_errors_path: Option<String>,
 }
 
 impl RpcArgs {
     /// Convert the CLI arguments into the arguments for the RPC server combining
     /// common and rpc specific arguments.
-    pub fn to_args(
+    pub async fn to_args(
         &self,
         chain_spec: ChainSpec,
         common: &CommonArgs,
@@ -110,6 +122,13 @@ impl RpcArgs {
             .map(|api| api.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
+        let mempool_configs = match &common.mempool_config_path {
+            Some(path) => get_json_config::<MempoolConfigs>(path)
+                .await
+                .with_context(|| format!("should load mempool configurations from {path}"))?,
+            None => MempoolConfigs::default(),
+        };
+
         Ok(RpcTaskArgs {
             chain_spec,
             unsafe_mode: common.unsafe_mode,
@@ -125,9 +144,24 @@ impl RpcArgs {
             max_connections: self.max_connections,
             entry_point_v0_6_enabled: !common.disable_entry_point_v0_6,
             entry_point_v0_7_enabled: !common.disable_entry_point_v0_7,
+            entry_point_v0_8_enabled: !common.disable_entry_point_v0_8,
             corsdomain: self.corsdomain.clone(),
+            aggregators: super::load_aggregator_configs(common).await?,
+            revert_decoder: self.load_revert_decoder().await?,
+            sim_settings: common.try_into()?,
+            mempool_configs,
         })
     }
+
+    async fn load_revert_decoder(&self) -> anyhow::Result<RevertDecoder> {
+        let custom_errors = match &self.revert_errors_path {
+            Some(path) => get_json_config::<Vec<String>>(path)
+                .await
+                .with_context(|| format!("should load revert error signatures from {path}"))?,
+            None => vec![],
+        };
+        RevertDecoder::new(&custom_errors)
+    }
 }
 
 /// CLI options for the RPC server standalone
@@ -167,14 +201,16 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         builder_url,
     } = rpc_args;
 
-    let task_args = rpc_args.to_args(
-        chain_spec.clone(),
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-        (&common_args).try_into()?,
-    )?;
+    let task_args = rpc_args
+        .to_args(
+            chain_spec.clone(),
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+            (&common_args).try_into()?,
+        )
+        .await?;
 
     let pool = connect_with_retries_shutdown(
         "op pool from rpc",
@@ -196,7 +232,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         task_args,
         pool,
         builder,
-        super::construct_providers(&common_args, &chain_spec)?,
+        super::construct_providers(&common_args, &chain_spec).await?,
     )
     .spawn(task_spawner)
     .await?;
@@ -0,0 +1,200 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Off-chain aggregation of BN254 BLS signatures.
+//!
+//! Matches `aggregateSignatures` of the reference `BLSSignatureAggregator`: each op's
+//! signature is an abi-encoded G1 point `(x, y)`, and the aggregated signature is the
+//! abi-encoded sum of those points.
+
+use alloy_primitives::{uint, Bytes, U256};
+
+/// The BN254 base field modulus
+const FIELD_MODULUS: U256 =
+    uint!(0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47_U256);
+
+/// A G1 point in affine coordinates, with `(0, 0)` as the point at infinity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct G1Point {
+    x: U256,
+    y: U256,
+}
+
+impl G1Point {
+    const INFINITY: Self = Self {
+        x: U256::ZERO,
+        y: U256::ZERO,
+    };
+
+    fn decode(signature: &[u8]) -> Option<Self> {
+        if signature.len() < 64 {
+            return None;
+        }
+        let x = U256::from_be_slice(&signature[..32]);
+        let y = U256::from_be_slice(&signature[32..64]);
+        if x >= FIELD_MODULUS || y >= FIELD_MODULUS {
+            return None;
+        }
+        let point = Self { x, y };
+        point.is_on_curve().then_some(point)
+    }
+
+    /// Checks `y^2 = x^3 + 3`, the point at infinity is also on the curve
+    fn is_on_curve(&self) -> bool {
+        if self.is_infinity() {
+            return true;
+        }
+        let y_squared = self.y.mul_mod(self.y, FIELD_MODULUS);
+        let x_cubed = self
+            .x
+            .mul_mod(self.x, FIELD_MODULUS)
+            .mul_mod(self.x, FIELD_MODULUS);
+        y_squared == x_cubed.add_mod(U256::from(3), FIELD_MODULUS)
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut out = Vec::with_capacity(64);
+        out.extend_from_slice(&self.x.to_be_bytes::<32>());
+        out.extend_from_slice(&self.y.to_be_bytes::<32>());
+        out.into()
+    }
+
+    fn is_infinity(&self) -> bool {
+        *self == Self::INFINITY
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+
+        let lambda = if self.x == other.x {
+            // P + (-P) is the point at infinity
+            if self.y.add_mod(other.y, FIELD_MODULUS).is_zero() {
+                return Self::INFINITY;
+            }
+            // doubling: 3x^2 / 2y
+            let numerator =
+                U256::from(3).mul_mod(self.x.mul_mod(self.x, FIELD_MODULUS), FIELD_MODULUS);
+            let denominator = U256::from(2).mul_mod(self.y, FIELD_MODULUS);
+            div_mod(numerator, denominator)
+        } else {
+            div_mod(sub_mod(other.y, self.y), sub_mod(other.x, self.x))
+        };
+
+        let x = sub_mod(
+            sub_mod(lambda.mul_mod(lambda, FIELD_MODULUS), self.x),
+            other.x,
+        );
+        let y = sub_mod(lambda.mul_mod(sub_mod(self.x, x), FIELD_MODULUS), self.y);
+        Self { x, y }
+    }
+}
+
+fn sub_mod(a: U256, b: U256) -> U256 {
+    a.add_mod(FIELD_MODULUS - b, FIELD_MODULUS)
+}
+
+fn div_mod(a: U256, b: U256) -> U256 {
+    // b is never zero here, and the modulus is prime, so the inverse exists
+    let inv = b
+        .inv_mod(FIELD_MODULUS)
+        .expect("nonzero element should have an inverse");
+    a.mul_mod(inv, FIELD_MODULUS)
+}
+
+/// Aggregates the BLS signatures of a group of operations.
+///
+/// Returns `None` if any signature is not an abi-encoded point on the curve, in which case
+/// the aggregator contract would revert or the aggregated signature would fail validation.
+pub(crate) fn aggregate_signatures<'a>(
+    signatures: impl IntoIterator<Item = &'a [u8]>,
+) -> Option<Bytes> {
+    let mut sum = G1Point::INFINITY;
+    for signature in signatures {
+        sum = sum.add(&G1Point::decode(signature)?);
+    }
+    Some(sum.encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G1: G1Point = G1Point {
+        x: uint!(1_U256),
+        y: uint!(2_U256),
+    };
+    const G1_DOUBLE: G1Point = G1Point {
+        x: uint!(0x030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3_U256),
+        y: uint!(0x15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4_U256),
+    };
+    const G1_TRIPLE: G1Point = G1Point {
+        x: uint!(0x0769bf9ac56bea3ff40232bcb1b6bd159315d84715b8e679f2d355961915abf0_U256),
+        y: uint!(0x2ab799bee0489429554fdb7c8d086475319e63b40b9c5b57cdf1ff3dd9fe2261_U256),
+    };
+
+    #[test]
+    fn test_add() {
+        assert_eq!(G1.add(&G1), G1_DOUBLE);
+        assert_eq!(G1.add(&G1_DOUBLE), G1_TRIPLE);
+        assert_eq!(G1_DOUBLE.add(&G1), G1_TRIPLE);
+        assert_eq!(G1.add(&G1Point::INFINITY), G1);
+        assert_eq!(G1Point::INFINITY.add(&G1), G1);
+
+        let neg = G1Point {
+            x: G1.x,
+            y: FIELD_MODULUS - G1.y,
+        };
+        assert_eq!(G1.add(&neg), G1Point::INFINITY);
+    }
+
+    #[test]
+    fn test_aggregate_signatures() {
+        let signatures = [G1.encode(), G1.encode(), G1.encode()];
+        let aggregated = aggregate_signatures(signatures.iter().map(|s| s.as_ref())).unwrap();
+        assert_eq!(aggregated, G1_TRIPLE.encode());
+    }
+
+    #[test]
+    fn test_aggregate_signatures_invalid() {
+        let short = Bytes::from(vec![1; 63]);
+        assert!(aggregate_signatures([G1.encode().as_ref(), short.as_ref()]).is_none());
+
+        let out_of_field = G1Point {
+            x: FIELD_MODULUS,
+            y: uint!(2_U256),
+        };
+        assert!(aggregate_signatures([out_of_field.encode().as_ref()]).is_none());
+    }
+
+    #[test]
+    fn test_aggregate_signatures_off_curve() {
+        let off_curve = G1Point {
+            x: uint!(1_U256),
+            y: uint!(3_U256),
+        };
+        assert!(
+            aggregate_signatures([G1.encode().as_ref(), off_curve.encode().as_ref()]).is_none()
+        );
+
+        // the point at infinity is a valid signature
+        let aggregated =
+            aggregate_signatures([G1.encode().as_ref(), G1Point::INFINITY.encode().as_ref()])
+                .unwrap();
+        assert_eq!(aggregated, G1.encode());
+    }
+}
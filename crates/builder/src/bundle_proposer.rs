@@ -19,37 +19,44 @@ use std::{
     sync::Arc,
 };
 
-use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_eips::eip7702::{constants::PER_EMPTY_ACCOUNT_COST, SignedAuthorization};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
 use anyhow::Context;
 use async_trait::async_trait;
 use futures::future;
 use futures_util::TryFutureExt;
 use linked_hash_map::LinkedHashMap;
-use metrics::Histogram;
+use metrics::{Counter, Histogram};
 use metrics_derive::Metrics;
 #[cfg(test)]
 use mockall::automock;
 use rundler_provider::{
     BundleHandler, DAGasOracleSync, DAGasProvider, EntryPoint, EvmProvider, HandleOpsOut,
-    ProvidersWithEntryPointT, SignatureAggregator,
+    ProvidersWithEntryPointT, SignatureAggregator, StateOverride, TransactionRequest,
 };
 use rundler_sim::{
     ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError, SimulationResult, Simulator,
     ViolationError,
 };
 use rundler_types::{
+    aggregator::{AggregatorConfigs, AggregatorType},
+    authorization::Authorization,
     chain::ChainSpec,
     da::DAGasBlockData,
     pool::{Pool, PoolOperation, SimulationViolation},
     Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateType, GasFees,
-    Timestamp, UserOperation, UserOperationVariant, UserOpsPerAggregator, ValidationRevert,
-    BUNDLE_BYTE_OVERHEAD, TIME_RANGE_BUFFER, USER_OP_OFFSET_WORD_SIZE,
+    Timestamp, UserOperation, UserOperationId, UserOperationVariant, UserOpsPerAggregator,
+    ValidationRevert, BUNDLE_BYTE_OVERHEAD, TIME_RANGE_BUFFER, USER_OP_OFFSET_WORD_SIZE,
 };
-use rundler_utils::{emit::WithEntryPoint, guard_timer::CustomTimerGuard, math};
+use rundler_utils::{emit::WithEntryPoint, guard_timer::CustomTimerGuard, math, nonce_utils};
+use serde::Deserialize;
 use tokio::{sync::broadcast, try_join};
 use tracing::{debug, error, info, warn};
 
-use crate::emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason};
+use crate::{
+    bls,
+    emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason},
+};
 
 /// Extra buffer percent to add on the bundle transaction gas estimate to be sure it will be enough
 const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u32 = 5;
@@ -119,6 +126,18 @@ pub(crate) trait BundleProposer: Send + Sync {
 
     /// Notifies the proposer that a condition was not met during the last bundle proposal
     fn notify_condition_not_met(&mut self);
+
+    /// Sets the beneficiary of subsequent bundles, the address of the signer that sends them
+    fn set_beneficiary(&mut self, beneficiary: Address);
+
+    /// Sets the senders of operations in bundles that are sent but not yet mined.
+    /// Operations from these senders are left out of subsequent bundles.
+    fn set_in_flight_senders(&mut self, senders: HashSet<Address>);
+
+    /// Sets the hashes and transactions of the sent bundles that land ahead of the next
+    /// bundle, in nonce order. With pending simulation, the next bundle is simulated on
+    /// top of them.
+    fn set_in_flight_transactions(&mut self, txs: Vec<(B256, TransactionRequest)>);
 }
 
 pub(crate) type BundleProposerResult<T> = std::result::Result<T, BundleProposerError>;
@@ -143,6 +162,10 @@ pub(crate) struct BundleProposerImpl<EP, BP> {
     bundle_providers: BP,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     condition_not_met_notified: bool,
+    // set once the node is found not to support simulating after pending transactions
+    pending_simulation_unsupported: bool,
+    in_flight_senders: HashSet<Address>,
+    in_flight_txs: Vec<(B256, TransactionRequest)>,
     metric: BuilderProposerMetric,
 }
 
@@ -156,6 +179,9 @@ pub(crate) struct Settings {
     pub(crate) bundle_priority_fee_overhead_percent: u32,
     pub(crate) priority_fee_mode: PriorityFeeMode,
     pub(crate) da_gas_tracking_enabled: bool,
+    pub(crate) min_profit_margin_percent: Option<u32>,
+    pub(crate) aggregators: AggregatorConfigs,
+    pub(crate) pending_simulation: bool,
 }
 
 #[async_trait]
@@ -181,6 +207,18 @@ where
         self.condition_not_met_notified = true;
     }
 
+    fn set_beneficiary(&mut self, beneficiary: Address) {
+        self.settings.beneficiary = beneficiary;
+    }
+
+    fn set_in_flight_senders(&mut self, senders: HashSet<Address>) {
+        self.in_flight_senders = senders;
+    }
+
+    fn set_in_flight_transactions(&mut self, txs: Vec<(B256, TransactionRequest)>) {
+        self.in_flight_txs = txs;
+    }
+
     async fn make_bundle(
         &mut self,
         required_fees: Option<GasFees>,
@@ -198,6 +236,26 @@ where
         if ops.is_empty() {
             return Err(BundleProposerError::NoOperationsInitially);
         }
+        let pool_op_ids = ops.iter().map(|op| op.uo.id()).collect::<HashSet<_>>();
+
+        // Ops from senders with ops in an in-flight bundle would conflict with that bundle
+        let ops = ops
+            .into_iter()
+            .filter(|op| {
+                if self.in_flight_senders.contains(&op.uo.sender()) {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_index,
+                        self.op_hash(&op.uo),
+                        SkipReason::SenderInFlight,
+                    ));
+                    return false;
+                }
+                true
+            })
+            .collect::<Vec<_>>();
+        if ops.is_empty() {
+            return Err(BundleProposerError::NoOperationsInitially);
+        }
 
         // (0) Determine fees required for ops to be included in a bundle
         // if replacing, just require bundle fees increase chances of unsticking
@@ -241,10 +299,15 @@ where
                 )
             })
             .collect::<Vec<_>>();
+        let mut da_gas_by_op = HashMap::new();
         let ops = future::join_all(fee_futs)
             .await
             .into_iter()
             .flatten()
+            .map(|(op, da_gas)| {
+                da_gas_by_op.insert(self.op_hash(&op.uo), da_gas);
+                op
+            })
             .collect::<Vec<_>>();
 
         tracing::debug!("Bundle proposal after fee limit had {} ops", ops.len());
@@ -254,6 +317,8 @@ where
 
         // (2) Limit the amount of operations for simulation
         let (ops, gas_limit) = self.limit_user_operations_for_simulation(ops);
+        let (ops, chained_op_ids) = self.filter_nonce_chains(ops, &pool_op_ids);
+        let (ops, atomic_groups) = self.filter_atomic_groups(ops);
 
         debug!(
             "Bundle proposal after gas limit had {} ops and {:?} gas limit",
@@ -261,10 +326,21 @@ where
             gas_limit
         );
 
-        // (3) simulate ops
-        let simulation_futures = ops
+        // (3) simulate ops, the ops of each atomic group in order
+        let mut op_runs: Vec<Vec<PoolOperation>> = vec![];
+        for op in ops {
+            match op_runs.last_mut() {
+                Some(run)
+                    if op.atomic_group.is_some() && run[0].atomic_group == op.atomic_group =>
+                {
+                    run.push(op)
+                }
+                _ => op_runs.push(vec![op]),
+            }
+        }
+        let simulation_futures = op_runs
             .into_iter()
-            .map(|op| self.simulate_op(op, block_hash))
+            .map(|ops| self.simulate_ops_in_order(ops, block_hash, &chained_op_ids))
             .collect::<Vec<_>>();
 
         let ops_with_simulations_future = future::join_all(simulation_futures);
@@ -280,10 +356,24 @@ where
             .flatten()
             .collect::<Vec<_>>();
         let mut context = self
-            .assemble_context(ops_with_simulations, balances_by_paymaster)
+            .assemble_context(
+                ops_with_simulations,
+                balances_by_paymaster,
+                &chained_op_ids,
+                atomic_groups,
+            )
             .await;
+        // With no pending transactions the bundle is simulated at the latest block
+        let pending_state =
+            if self.settings.pending_simulation && !self.pending_simulation_unsupported {
+                Some(self.get_pending_state(&context).await).filter(|state| !state.txs.is_empty())
+            } else {
+                None
+            };
         while !context.is_empty() {
-            let gas_estimate = self.estimate_gas_rejecting_failed_ops(&mut context).await?;
+            let gas_estimate = self
+                .estimate_gas_rejecting_failed_ops(&mut context, pending_state.as_ref())
+                .await?;
             if let Some(gas_estimate) = gas_estimate {
                 tracing::debug!(
                     "Bundle proposal succeeded with {} ops and {:?} gas limit",
@@ -301,6 +391,31 @@ where
                     }
                 }
 
+                // Drop the least profitable ops until the bundle meets the minimum profit margin,
+                // and estimate the gas again for the remaining ops.
+                let gas_shares = self.op_gas_shares(&context, gas_estimate);
+                let profit =
+                    self.bundle_profit(&context, base_fee, bundle_fees, &gas_shares, &da_gas_by_op);
+                if let Some(min_margin) = self.settings.min_profit_margin_percent {
+                    if !profit.meets_margin(min_margin) {
+                        self.drop_unprofitable_ops(
+                            &mut context,
+                            min_margin,
+                            base_fee,
+                            bundle_fees,
+                            &gas_shares,
+                            &da_gas_by_op,
+                        )
+                        .await;
+                        continue;
+                    }
+                }
+                self.metric.bundle_revenue.record(profit.revenue as f64);
+                self.metric.bundle_cost.record(profit.cost as f64);
+                if let Some(margin) = profit.margin_percent() {
+                    self.metric.bundle_profit_margin_percent.record(margin);
+                }
+
                 let mut expected_storage = ExpectedStorage::default();
                 for op in context.iter_ops_with_simulations() {
                     expected_storage.merge(&op.simulation.expected_storage)?;
@@ -333,6 +448,14 @@ struct BuilderProposerMetric {
     bundle_build_ms: Histogram,
     #[metric(describe = "the distribution of op simulation time of a bundle.")]
     op_simulation_ms: Histogram,
+    #[metric(describe = "the distribution of expected beneficiary revenue of a bundle in wei.")]
+    bundle_revenue: Histogram,
+    #[metric(describe = "the distribution of expected transaction cost of a bundle in wei.")]
+    bundle_cost: Histogram,
+    #[metric(describe = "the distribution of expected profit margin of a bundle in percent.")]
+    bundle_profit_margin_percent: Histogram,
+    #[metric(describe = "the count of ops dropped from bundles for being unprofitable.")]
+    unprofitable_ops: Counter,
 }
 
 impl<EP, BP> BundleProposerImpl<EP, BP>
@@ -355,11 +478,15 @@ where
             settings,
             event_sender,
             condition_not_met_notified: false,
+            pending_simulation_unsupported: false,
+            in_flight_senders: HashSet::new(),
+            in_flight_txs: vec![],
             metric: BuilderProposerMetric::default(),
         }
     }
 
-    // Check fees for a single user op. Returns None if the op should be skipped.
+    // Check fees for a single user op. Returns None if the op should be skipped, otherwise
+    // returns the op along with its required DA gas.
     //
     // Filters on:
     // - Insufficient gas fees
@@ -372,7 +499,7 @@ where
         da_block_data: Option<&DAGasBlockData>,
         base_fee: u128,
         required_op_fees: GasFees,
-    ) -> Option<PoolOperation> {
+    ) -> Option<(PoolOperation, u128)> {
         let op_hash = self.op_hash(&op.uo);
 
         // filter by fees
@@ -395,7 +522,7 @@ where
 
         if !self.settings.chain_spec.da_pre_verification_gas {
             // Skip PVG check if no da pre-verification gas as this is checked on entry to the mempool.
-            return Some(op);
+            return Some((op, 0));
         }
 
         let required_da_gas = if self.settings.da_gas_tracking_enabled
@@ -461,21 +588,98 @@ where
             return None;
         }
 
-        Some(op)
+        Some((op, required_da_gas))
+    }
+
+    // Simulate ops in order, each on top of the state left by the ops before it. Used for
+    // the ops of an atomic group, which may depend on each other. Stops at the first op that
+    // fails or is skipped, as the ops after it can't be included without it.
+    async fn simulate_ops_in_order(
+        &self,
+        ops: Vec<PoolOperation>,
+        block_hash: B256,
+        chained_op_ids: &HashSet<UserOperationId>,
+    ) -> Vec<(PoolOperation, Result<SimulationResult, SimulationError>)> {
+        let mut simulations = vec![];
+        let mut state_override = StateOverride::default();
+        let num_ops = ops.len();
+        for (i, op) in ops.into_iter().enumerate() {
+            let chained = chained_op_ids.contains(&op.uo.id());
+            let Some((op, simulation)) = self
+                .simulate_op(op, block_hash, chained, state_override.clone())
+                .await
+            else {
+                break;
+            };
+            if simulation.is_err() {
+                simulations.push((op, simulation));
+                break;
+            }
+
+            if i + 1 < num_ops {
+                let ret = self
+                    .ep_providers
+                    .entry_point()
+                    .get_handle_ops_state_override(
+                        vec![UserOpsPerAggregator {
+                            user_ops: vec![op.uo.clone().into()],
+                            aggregator: Address::ZERO,
+                            signature: Bytes::new(),
+                        }],
+                        self.settings.beneficiary,
+                        None,
+                        block_hash.into(),
+                        state_override,
+                    )
+                    .await;
+                state_override = match ret {
+                    Ok(state_override) => state_override,
+                    Err(error) => {
+                        self.emit(BuilderEvent::skipped_op(
+                            self.builder_index,
+                            self.op_hash(&op.uo),
+                            SkipReason::Other {
+                                reason: Arc::new(format!(
+                                    "Failed to apply op for the rest of its atomic group: {error:?}, skipping"
+                                )),
+                            },
+                        ));
+                        break;
+                    }
+                };
+            }
+            simulations.push((op, simulation));
+        }
+        simulations
     }
 
-    // Simulate a single op. Returns None if the op should be skipped.
+    // Simulate a single op on top of `state_override`. Returns None if the op should be
+    // skipped.
+    //
+    // Ops that follow the op with their previous nonce in the bundle are simulated as if
+    // that op was already executed.
     //
     // Filters on any errors
     async fn simulate_op(
         &self,
         op: PoolOperation,
         block_hash: B256,
+        chained: bool,
+        mut state_override: StateOverride,
     ) -> Option<(PoolOperation, Result<SimulationResult, SimulationError>)> {
         let _timer_guard =
             rundler_utils::guard_timer::CustomTimerGuard::new(self.metric.op_simulation_ms.clone());
         let op_hash = self.op_hash(&op.uo);
 
+        if chained {
+            nonce_utils::apply_nonce_override(
+                &mut state_override,
+                *self.ep_providers.entry_point().address(),
+                op.uo.sender(),
+                op.uo.nonce(),
+            );
+        }
+
         // Simulate
         let result = self
             .bundle_providers
@@ -484,6 +688,7 @@ where
                 op.uo.clone().into(),
                 block_hash,
                 Some(op.expected_code_hash),
+                state_override,
             )
             .await;
         let result = match result {
@@ -516,18 +721,42 @@ where
         &self,
         ops_with_simulations: Vec<(PoolOperation, Result<SimulationResult, SimulationError>)>,
         mut balances_by_paymaster: HashMap<Address, U256>,
+        chained_op_ids: &HashSet<UserOperationId>,
+        atomic_groups: Vec<Vec<UserOperationId>>,
     ) -> ProposalContext<<Self as BundleProposer>::UO> {
         let all_sender_addresses: HashSet<Address> = ops_with_simulations
             .iter()
             .map(|(op, _)| op.uo.sender())
             .collect();
         let mut context = ProposalContext::<<Self as BundleProposer>::UO>::new();
+        context.atomic_groups = atomic_groups;
         let mut paymasters_to_reject = Vec::<EntityInfo>::new();
+        let account_nonces = self
+            .get_authority_nonces(ops_with_simulations.iter().map(|(po, _)| &po.uo))
+            .await;
+        // EIP-7702 authorizations in the bundle, by authority
+        let mut authorizations = HashMap::<Address, Authorization>::new();
 
         let mut gas_spent = rundler_types::bundle_shared_gas(&self.settings.chain_spec);
         let mut constructed_bundle_size = BUNDLE_BYTE_OVERHEAD;
         for (po, simulation) in ops_with_simulations {
             let op = po.clone().uo;
+            let id = op.id();
+            // Ops simulated on top of the op with their previous nonce can only be included after it
+            let chained = chained_op_ids.contains(&id);
+            if chained
+                && !id
+                    .previous()
+                    .is_some_and(|previous| context.contains_op_id(&previous))
+            {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op),
+                    SkipReason::PreviousNonceNotIncluded,
+                ));
+                continue;
+            }
+
             let simulation = match simulation {
                 Ok(simulation) => simulation,
                 Err(error) => {
@@ -567,6 +796,40 @@ where
                 continue;
             }
 
+            let new_authorization =
+                match self.check_authorization(&op, &account_nonces, &authorizations) {
+                    AuthorizationCheck::New(authorization) => Some(authorization),
+                    AuthorizationCheck::Included => None,
+                    AuthorizationCheck::Skip(reason) => {
+                        self.emit(BuilderEvent::skipped_op(
+                            self.builder_index,
+                            self.op_hash(&op),
+                            reason,
+                        ));
+                        continue;
+                    }
+                    AuthorizationCheck::Reject(reason) => {
+                        info!(
+                            "Rejected op from {:?} with invalid authorization: {reason}",
+                            op.sender()
+                        );
+                        self.emit(BuilderEvent::rejected_op(
+                            self.builder_index,
+                            self.op_hash(&op),
+                            OpRejectionReason::InvalidAuthorization {
+                                reason: Arc::new(reason),
+                            },
+                        ));
+                        context.rejected_ops.push((op.into(), po.entity_infos));
+                        continue;
+                    }
+                };
+            let authorization_gas = if new_authorization.is_some() {
+                PER_EMPTY_ACCOUNT_COST as u128
+            } else {
+                0
+            };
+
             let op_size_bytes: usize = op.abi_encoded_size();
 
             let op_size_with_offset_word = op_size_bytes.saturating_add(USER_OP_OFFSET_WORD_SIZE);
@@ -578,7 +841,9 @@ where
             }
 
             // Skip this op if the bundle does not have enough remaining gas to execute it.
-            let required_gas = gas_spent + op.execution_gas_limit(&self.settings.chain_spec, None);
+            let required_gas = gas_spent
+                + op.execution_gas_limit(&self.settings.chain_spec, None)
+                + authorization_gas;
             if required_gas > self.settings.max_bundle_gas {
                 continue;
             }
@@ -614,11 +879,18 @@ where
             }
 
             // Update the running gas that would need to be be spent to execute the bundle so far.
-            gas_spent += op.execution_gas_limit(&self.settings.chain_spec, None);
+            gas_spent +=
+                op.execution_gas_limit(&self.settings.chain_spec, None) + authorization_gas;
+            if let Some(authorization) = new_authorization {
+                authorizations.insert(op.sender(), authorization);
+            }
 
             constructed_bundle_size =
                 constructed_bundle_size.saturating_add(op_size_with_offset_word);
 
+            if chained {
+                context.chained_op_ids.insert(id);
+            }
             context
                 .groups_by_aggregator
                 .entry(simulation.aggregator_address())
@@ -633,10 +905,99 @@ where
             // No need to update aggregator signatures because we haven't computed them yet.
             let _ = context.reject_entity(paymaster.entity, paymaster.is_staked);
         }
+        let _ = context.remove_broken_dependencies();
         self.compute_all_aggregator_signatures(&mut context).await;
         context
     }
 
+    // Loads the current nonce of the senders of ops with EIP-7702 authorizations.
+    // Senders whose nonce fails to load are missing from the result.
+    async fn get_authority_nonces<'a>(
+        &self,
+        ops: impl Iterator<Item = &'a UserOperationVariant>,
+    ) -> HashMap<Address, u64> {
+        let authorities: HashSet<Address> = ops
+            .filter(|op| op.authorization_tuple().is_some())
+            .map(|op| op.sender())
+            .collect();
+        let futs = authorities.into_iter().map(|authority| async move {
+            match self
+                .ep_providers
+                .evm()
+                .get_transaction_count(authority)
+                .await
+            {
+                Ok(nonce) => Some((authority, nonce)),
+                Err(e) => {
+                    error!("Failed to load nonce of authority {authority:?}: {e:?}");
+                    None
+                }
+            }
+        });
+        future::join_all(futs).await.into_iter().flatten().collect()
+    }
+
+    // Checks that the EIP-7702 authorization of an op, if any, will be applied when the
+    // bundle is executed. The authorization must be signed by the sender for this chain,
+    // match the sender's current nonce, and not conflict with another authorization
+    // in the bundle.
+    fn check_authorization(
+        &self,
+        op: &UserOperationVariant,
+        account_nonces: &HashMap<Address, u64>,
+        authorizations: &HashMap<Address, Authorization>,
+    ) -> AuthorizationCheck {
+        let Some(authorization) = op.authorization_tuple() else {
+            return AuthorizationCheck::Included;
+        };
+
+        if let Some(included) = authorizations.get(&op.sender()) {
+            return if *included == authorization {
+                AuthorizationCheck::Included
+            } else {
+                AuthorizationCheck::Skip(SkipReason::ConflictingAuthorization)
+            };
+        }
+
+        if authorization.chain_id != 0 && authorization.chain_id != self.settings.chain_spec.id {
+            return AuthorizationCheck::Reject(format!(
+                "authorization is for chain {}",
+                authorization.chain_id
+            ));
+        }
+        match authorization.recover_authority() {
+            Some(authority) if authority == op.sender() => {}
+            Some(authority) => {
+                return AuthorizationCheck::Reject(format!(
+                    "authorization is signed by {authority:?}, not the sender"
+                ))
+            }
+            None => {
+                return AuthorizationCheck::Reject("authorization signature is invalid".to_string())
+            }
+        }
+
+        let Some(&account_nonce) = account_nonces.get(&op.sender()) else {
+            return AuthorizationCheck::Skip(SkipReason::Other {
+                reason: Arc::new("Failed to load nonce of authorization signer".to_string()),
+            });
+        };
+        match authorization.nonce.cmp(&account_nonce) {
+            std::cmp::Ordering::Equal => AuthorizationCheck::New(authorization),
+            // The sender's nonce only increases, so the authorization is permanently invalid
+            std::cmp::Ordering::Less => AuthorizationCheck::Reject(format!(
+                "authorization nonce {} is below sender nonce {account_nonce}",
+                authorization.nonce
+            )),
+            std::cmp::Ordering::Greater => {
+                AuthorizationCheck::Skip(SkipReason::AuthorizationNonceTooHigh {
+                    account_nonce,
+                    authorization_nonce: authorization.nonce,
+                })
+            }
+        }
+    }
+
     async fn check_conditions_met(
         &self,
         context: &mut ProposalContext<<Self as BundleProposer>::UO>,
@@ -714,8 +1075,12 @@ where
         i: usize,
         paymaster_amendment: bool,
     ) {
-        let changed_aggregator = context.reject_index(i, paymaster_amendment);
-        self.compute_aggregator_signatures(context, &changed_aggregator)
+        let mut changed_aggregators: Vec<_> = context
+            .reject_index(i, paymaster_amendment)
+            .into_iter()
+            .collect();
+        changed_aggregators.extend(context.remove_broken_dependencies());
+        self.compute_aggregator_signatures(context, &changed_aggregators)
             .await;
     }
 
@@ -725,7 +1090,8 @@ where
         entity: Entity,
         is_staked: bool,
     ) {
-        let changed_aggregators = context.reject_entity(entity, is_staked);
+        let mut changed_aggregators = context.reject_entity(entity, is_staked);
+        changed_aggregators.extend(context.remove_broken_dependencies());
         self.compute_aggregator_signatures(context, &changed_aggregators)
             .await;
     }
@@ -764,9 +1130,15 @@ where
     /// Estimates the gas needed to send this bundle. If successful, returns the
     /// amount of gas, but if not then mutates the context to remove whichever
     /// op(s) caused the failure.
+    ///
+    /// If `pending_state` is set, the bundle is simulated after its pending
+    /// transactions rather than at the latest block. If the node doesn't support
+    /// simulating after pending transactions, the bundle is simulated at the latest
+    /// block, and pending simulation is not attempted again.
     async fn estimate_gas_rejecting_failed_ops(
-        &self,
+        &mut self,
         context: &mut ProposalContext<<Self as BundleProposer>::UO>,
+        pending_state: Option<&PendingState>,
     ) -> BundleProposerResult<Option<u64>> {
         // sum up the gas needed for all the ops in the bundle
         // and apply an overhead multiplier
@@ -780,25 +1152,63 @@ where
             .context("estimated bundle gas limit is larger than u64::MAX")?;
 
         // call handle ops with the bundle to filter any rejected ops before sending
-        let handle_ops_out = self
-            .ep_providers
-            .entry_point()
-            .call_handle_ops(
-                context.to_ops_per_aggregator(),
-                self.settings.beneficiary,
-                Some(gas),
-            )
-            .await
-            .context("should call handle ops with candidate bundle")?;
+        let mut pending_state = pending_state.filter(|_| !self.pending_simulation_unsupported);
+        let mut pending_out = None;
+        if let Some(state) = pending_state {
+            match self
+                .ep_providers
+                .entry_point()
+                .call_handle_ops_pending(
+                    context.to_ops_per_aggregator(),
+                    self.settings.beneficiary,
+                    Some(gas),
+                    state.txs.clone(),
+                )
+                .await
+            {
+                Ok(out) => pending_out = Some(out),
+                Err(e) if e.is_method_unsupported() => {
+                    warn!("Node does not support eth_simulateV1, simulating bundles at the latest block instead: {e:?}");
+                    self.pending_simulation_unsupported = true;
+                    pending_state = None;
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::from(e)
+                        .context("should call handle ops with candidate bundle in pending state")
+                        .into())
+                }
+            }
+        }
+        let handle_ops_out = match pending_out {
+            Some(out) => out,
+            None => self
+                .ep_providers
+                .entry_point()
+                .call_handle_ops(
+                    context.to_ops_per_aggregator(),
+                    self.settings.beneficiary,
+                    Some(gas),
+                )
+                .await
+                .context("should call handle ops with candidate bundle")?,
+        };
         match handle_ops_out {
             HandleOpsOut::Success => Ok(Some(gas)),
             HandleOpsOut::FailedOp(index, message) => {
-                self.emit(BuilderEvent::rejected_op(
-                    self.builder_index,
-                    self.op_hash(&context.get_op_at(index)?.op),
-                    OpRejectionReason::FailedInBundle {
+                let op = &context.get_op_at(index)?.op;
+                let reason = match pending_state {
+                    Some(pending_state) => OpRejectionReason::FailedInPendingBundle {
+                        message: Arc::new(message.clone()),
+                        pending_tx_hashes: Arc::new(pending_state.related_tx_hashes(op)),
+                    },
+                    None => OpRejectionReason::FailedInBundle {
                         message: Arc::new(message.clone()),
                     },
+                };
+                self.emit(BuilderEvent::rejected_op(
+                    self.builder_index,
+                    self.op_hash(op),
+                    reason,
                 ));
                 self.process_failed_op(context, index, message).await?;
                 Ok(None)
@@ -817,6 +1227,187 @@ where
         }
     }
 
+    /// Constructs the pending state to simulate the bundle in: the builder's in-flight
+    /// bundle transactions, followed by the node's pending mempool transactions from or to
+    /// any of the senders or paymasters of the bundle.
+    ///
+    /// If the node doesn't support `txpool_content`, only the in-flight transactions are used.
+    async fn get_pending_state(
+        &self,
+        context: &ProposalContext<<Self as BundleProposer>::UO>,
+    ) -> PendingState {
+        let mut pending_state = PendingState::default();
+        for (tx_hash, tx) in &self.in_flight_txs {
+            pending_state.txs.push(tx.clone());
+            pending_state.in_flight_tx_hashes.push(*tx_hash);
+        }
+
+        let addresses = context
+            .iter_ops()
+            .flat_map(|op| [Some(op.sender()), op.paymaster()])
+            .flatten()
+            .collect::<HashSet<_>>();
+        let content = match self
+            .ep_providers
+            .evm()
+            .request::<_, TxpoolContent>("txpool_content", ())
+            .await
+        {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to load txpool content, simulating bundle after in-flight transactions only: {e:?}");
+                return pending_state;
+            }
+        };
+
+        pending_state.add_mempool_txs(content, &addresses);
+        pending_state
+    }
+
+    /// Drops the least profitable ops from the bundle until it meets the minimum profit
+    /// margin. The dropped ops are skipped rather than rejected, as they may still be
+    /// included in a later bundle.
+    ///
+    /// Each dropped op takes its share of the bundle's gas estimate with it, the gas is
+    /// estimated again for the remaining ops by the caller.
+    async fn drop_unprofitable_ops(
+        &self,
+        context: &mut ProposalContext<<Self as BundleProposer>::UO>,
+        min_margin_percent: u32,
+        base_fee: u128,
+        bundle_fees: GasFees,
+        gas_shares: &HashMap<B256, u128>,
+        da_gas_by_op: &HashMap<B256, u128>,
+    ) {
+        let bundle_gas_price = bundle_gas_price(bundle_fees, base_fee);
+        let mut changed_aggregators = vec![];
+        while !self
+            .bundle_profit(context, base_fee, bundle_fees, gas_shares, da_gas_by_op)
+            .meets_margin(min_margin_percent)
+        {
+            let Some((id, profit)) = context
+                .iter_ops()
+                .map(|op| {
+                    (
+                        op.id(),
+                        self.op_profit(op, base_fee, bundle_gas_price, gas_shares, da_gas_by_op),
+                    )
+                })
+                .min_by(|(_, a), (_, b)| a.cmp_profit(b))
+            else {
+                break;
+            };
+
+            let (removed, changed) = context.filter_remove(|op| op.id() == id);
+            for op in removed {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op.op),
+                    SkipReason::Unprofitable {
+                        revenue: profit.revenue,
+                        cost: profit.cost,
+                    },
+                ));
+                self.metric.unprofitable_ops.increment(1);
+            }
+            changed_aggregators.extend(changed);
+            changed_aggregators.extend(context.remove_broken_dependencies());
+        }
+        self.compute_aggregator_signatures(context, &changed_aggregators)
+            .await;
+    }
+
+    /// Splits the bundle's gas estimate across its ops in proportion to each op's gas limit,
+    /// so that the gas shared by all ops in the bundle is paid for by each of them.
+    fn op_gas_shares(
+        &self,
+        context: &ProposalContext<<Self as BundleProposer>::UO>,
+        gas_estimate: u64,
+    ) -> HashMap<B256, u128> {
+        let gas_limits = context
+            .iter_ops()
+            .map(|op| {
+                (
+                    self.op_hash(op),
+                    op.gas_limit(&self.settings.chain_spec, None),
+                )
+            })
+            .collect::<Vec<_>>();
+        let total_gas_limit = gas_limits
+            .iter()
+            .map(|(_, gas_limit)| gas_limit)
+            .sum::<u128>();
+
+        gas_limits
+            .into_iter()
+            .map(|(hash, gas_limit)| {
+                let share = u128::from(gas_estimate)
+                    .saturating_mul(gas_limit)
+                    .checked_div(total_gas_limit)
+                    .unwrap_or_default();
+                (hash, share)
+            })
+            .collect()
+    }
+
+    /// Expected revenue of the beneficiary and cost of the bundle transaction.
+    fn bundle_profit(
+        &self,
+        context: &ProposalContext<<Self as BundleProposer>::UO>,
+        base_fee: u128,
+        bundle_fees: GasFees,
+        gas_shares: &HashMap<B256, u128>,
+        da_gas_by_op: &HashMap<B256, u128>,
+    ) -> BundleProfit {
+        let bundle_gas_price = bundle_gas_price(bundle_fees, base_fee);
+        let mut profit = BundleProfit {
+            revenue: 0,
+            cost: 0,
+        };
+        for op in context.iter_ops() {
+            let op_profit =
+                self.op_profit(op, base_fee, bundle_gas_price, gas_shares, da_gas_by_op);
+            profit.revenue = profit.revenue.saturating_add(op_profit.revenue);
+            profit.cost = profit.cost.saturating_add(op_profit.cost);
+        }
+        profit
+    }
+
+    /// Expected revenue and cost of a single op in the bundle, from the op's share of the
+    /// bundle's gas estimate.
+    ///
+    /// The op pays its own gas price for its share of the gas, while the bundle transaction pays
+    /// the bundle gas price for it, plus the op's DA cost if the chain doesn't charge it through
+    /// the transaction gas.
+    fn op_profit(
+        &self,
+        op: &<Self as BundleProposer>::UO,
+        base_fee: u128,
+        bundle_gas_price: u128,
+        gas_shares: &HashMap<B256, u128>,
+        da_gas_by_op: &HashMap<B256, u128>,
+    ) -> BundleProfit {
+        let hash = self.op_hash(op);
+        let gas_price = op.gas_price(base_fee);
+        let gas_share = gas_shares.get(&hash).copied().unwrap_or_default();
+        let da_cost = if self.settings.chain_spec.include_da_gas_in_gas_limit {
+            0
+        } else {
+            da_gas_by_op
+                .get(&hash)
+                .copied()
+                .unwrap_or_default()
+                .saturating_mul(gas_price)
+        };
+
+        BundleProfit {
+            revenue: gas_share.saturating_mul(gas_price),
+            cost: gas_share
+                .saturating_mul(bundle_gas_price)
+                .saturating_add(da_cost),
+        }
+    }
+
     async fn get_ops_from_pool(&self) -> BundleProposerResult<Vec<PoolOperation>> {
         // Use builder's index as the shard index to ensure that two builders don't
         // attempt to bundle the same operations.
@@ -861,6 +1452,21 @@ where
         aggregator: Address,
         group: &AggregatorGroup<<Self as BundleProposer>::UO>,
     ) -> (Address, anyhow::Result<Option<Bytes>>) {
+        let aggregator_type = self
+            .settings
+            .aggregators
+            .get(&aggregator)
+            .map(|config| config.aggregator_type)
+            .unwrap_or_default();
+        if aggregator_type == AggregatorType::Bls {
+            // aggregate off-chain to avoid an eth_call per bundle
+            let signatures = group
+                .ops_with_simulations
+                .iter()
+                .map(|op_with_simulation| op_with_simulation.op.signature().as_ref());
+            return (aggregator, Ok(bls::aggregate_signatures(signatures)));
+        }
+
         let ops = group
             .ops_with_simulations
             .iter()
@@ -1084,6 +1690,87 @@ where
         )
     }
 
+    // Skips ops whose previous nonce was returned by the pool but didn't make it through the
+    // earlier filters, as they can't be executed without it. The pool returns ops after the op
+    // with their previous nonce. Returns the remaining ops and the ids of the ops that follow
+    // the op with their previous nonce.
+    fn filter_nonce_chains(
+        &self,
+        ops: Vec<PoolOperation>,
+        pool_op_ids: &HashSet<UserOperationId>,
+    ) -> (Vec<PoolOperation>, HashSet<UserOperationId>) {
+        let mut remaining_ids = HashSet::new();
+        let mut chained_op_ids = HashSet::new();
+        let ops = ops
+            .into_iter()
+            .filter(|op| {
+                let id = op.uo.id();
+                match id
+                    .previous()
+                    .filter(|previous| pool_op_ids.contains(previous))
+                {
+                    Some(previous) if !remaining_ids.contains(&previous) => {
+                        self.emit(BuilderEvent::skipped_op(
+                            self.builder_index,
+                            self.op_hash(&op.uo),
+                            SkipReason::PreviousNonceNotIncluded,
+                        ));
+                        return false;
+                    }
+                    Some(_) => {
+                        chained_op_ids.insert(id);
+                    }
+                    None => {}
+                }
+                remaining_ids.insert(id);
+                true
+            })
+            .collect();
+        (ops, chained_op_ids)
+    }
+
+    // Skips the ops of atomic groups that didn't make it through the earlier filters as a
+    // whole, as the ops of a group must be bundled together. The pool returns the ops of a
+    // group consecutively and in order. Returns the remaining ops and the ids of the ops of
+    // each remaining group.
+    fn filter_atomic_groups(
+        &self,
+        ops: Vec<PoolOperation>,
+    ) -> (Vec<PoolOperation>, Vec<Vec<UserOperationId>>) {
+        // ids of the remaining ops of each group, keyed by the hash of the group's first op
+        let mut groups = HashMap::<B256, Vec<UserOperationId>>::new();
+        for op in &ops {
+            if let Some(group) = &op.atomic_group {
+                groups.entry(group[0]).or_default().push(op.uo.id());
+            }
+        }
+
+        let ops = ops
+            .into_iter()
+            .filter(|op| {
+                let Some(group) = &op.atomic_group else {
+                    return true;
+                };
+                if groups[&group[0]].len() == group.len() {
+                    return true;
+                }
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op.uo),
+                    SkipReason::AtomicGroupNotIncluded,
+                ));
+                false
+            })
+            .collect::<Vec<_>>();
+
+        let remaining_ids = ops.iter().map(|op| op.uo.id()).collect::<HashSet<_>>();
+        let atomic_groups = groups
+            .into_values()
+            .filter(|ids| ids.iter().all(|id| remaining_ids.contains(id)))
+            .collect();
+        (ops, atomic_groups)
+    }
+
     fn emit(&self, event: BuilderEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: *self.ep_providers.entry_point().address(),
@@ -1158,6 +1845,19 @@ where
     }
 }
 
+/// Outcome of checking the EIP-7702 authorization of an op
+#[derive(Debug)]
+enum AuthorizationCheck {
+    /// The authorization is valid and must be added to the bundle's authorization list
+    New(Authorization),
+    /// The op has no authorization, or the same authorization is already in the bundle
+    Included,
+    /// The op can't be included in this bundle
+    Skip(SkipReason),
+    /// The op's authorization can't be applied in any bundle
+    Reject(String),
+}
+
 #[derive(Debug)]
 struct OpWithSimulation<UO> {
     op: UO,
@@ -1167,27 +1867,151 @@ struct OpWithSimulation<UO> {
 impl<UO: UserOperation> OpWithSimulation<UO> {
     fn op_with_replaced_sig(&self) -> UO {
         let mut op = self.op.clone();
-        if self.simulation.aggregator.is_some() {
-            // if using an aggregator, clear out the user op signature
-            op.clear_signature();
+        if let Some(aggregator) = &self.simulation.aggregator {
+            // if using an aggregator, use the signature the aggregator returned for the op
+            op.replace_signature(aggregator.signature.clone());
         }
         op
     }
 }
 
-/// A struct used internally to represent the current state of a proposed bundle
-/// as it goes through iterations. Contains similar data to the
-/// `Vec<UserOpsPerAggregator>` that will eventually be passed to the entry
-/// point, but contains extra context needed for the computation.
-#[derive(Debug)]
-struct ProposalContext<UO> {
-    groups_by_aggregator: LinkedHashMap<Option<Address>, AggregatorGroup<UO>>,
-    rejected_ops: Vec<(UO, EntityInfos)>,
-    // This is a BTreeMap so that the conversion to a Vec<EntityUpdate> is deterministic, mainly for tests
-    entity_updates: BTreeMap<Address, EntityUpdate>,
+/// Expected revenue of the beneficiary and cost of the bundle transaction, in wei
+#[derive(Debug, Clone, Copy)]
+struct BundleProfit {
+    revenue: u128,
+    cost: u128,
 }
 
-#[derive(Debug)]
+impl BundleProfit {
+    fn meets_margin(&self, min_margin_percent: u32) -> bool {
+        self.revenue.saturating_mul(100)
+            >= self
+                .cost
+                .saturating_mul(100 + u128::from(min_margin_percent))
+    }
+
+    fn margin_percent(&self) -> Option<f64> {
+        (self.cost > 0).then(|| (self.revenue as f64 - self.cost as f64) * 100.0 / self.cost as f64)
+    }
+
+    /// Compares the profit (revenue minus cost) of two ops without underflowing
+    fn cmp_profit(&self, other: &Self) -> std::cmp::Ordering {
+        self.revenue
+            .saturating_add(other.cost)
+            .cmp(&other.revenue.saturating_add(self.cost))
+    }
+}
+
+fn bundle_gas_price(bundle_fees: GasFees, base_fee: u128) -> u128 {
+    bundle_fees
+        .max_fee_per_gas
+        .min(base_fee + bundle_fees.max_priority_fee_per_gas)
+}
+
+/// Transactions expected to land ahead of the bundle, which it is simulated after
+#[derive(Debug, Default)]
+struct PendingState {
+    txs: Vec<TransactionRequest>,
+    // hashes of the builder's own in-flight bundle transactions
+    in_flight_tx_hashes: Vec<B256>,
+    // hashes of the mempool transactions from or to each sender or paymaster
+    mempool_tx_hashes: HashMap<Address, Vec<B256>>,
+}
+
+impl PendingState {
+    /// Adds the pending mempool transactions from or to any of `addresses`, in nonce
+    /// order for each transaction sender
+    fn add_mempool_txs(&mut self, content: TxpoolContent, addresses: &HashSet<Address>) {
+        for txs in content.pending.into_values() {
+            let mut txs = txs.into_values().collect::<Vec<_>>();
+            txs.sort_by_key(|tx| tx.nonce);
+            for tx in txs {
+                let touched = [Some(tx.from), tx.to]
+                    .into_iter()
+                    .flatten()
+                    .filter(|address| addresses.contains(address))
+                    .collect::<HashSet<_>>();
+                if touched.is_empty() {
+                    continue;
+                }
+                for address in touched {
+                    self.mempool_tx_hashes
+                        .entry(address)
+                        .or_default()
+                        .push(tx.hash);
+                }
+                self.txs.push(tx.into_request());
+            }
+        }
+    }
+
+    /// Hashes of the pending transactions that may have caused the op to fail
+    fn related_tx_hashes<UO: UserOperation>(&self, op: &UO) -> Vec<B256> {
+        let mut tx_hashes = self.in_flight_tx_hashes.clone();
+        for address in [Some(op.sender()), op.paymaster()].into_iter().flatten() {
+            for tx_hash in self.mempool_tx_hashes.get(&address).into_iter().flatten() {
+                if !tx_hashes.contains(tx_hash) {
+                    tx_hashes.push(*tx_hash);
+                }
+            }
+        }
+        tx_hashes
+    }
+}
+
+/// Response of `txpool_content`, only the pending transactions are used
+#[derive(Debug, Deserialize)]
+struct TxpoolContent {
+    pending: HashMap<Address, HashMap<String, TxpoolTransaction>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TxpoolTransaction {
+    hash: B256,
+    nonce: U64,
+    from: Address,
+    to: Option<Address>,
+    gas: U64,
+    value: U256,
+    input: Bytes,
+    #[serde(default)]
+    authorization_list: Option<Vec<SignedAuthorization>>,
+}
+
+impl TxpoolTransaction {
+    // Fees and nonce are left out, the pending state is simulated without validation
+    fn into_request(self) -> TransactionRequest {
+        let mut tx = TransactionRequest::default()
+            .from(self.from)
+            .gas_limit(self.gas.to())
+            .value(self.value)
+            .input(self.input.into());
+        if let Some(to) = self.to {
+            tx = tx.to(to);
+        }
+        tx.authorization_list = self.authorization_list;
+        tx
+    }
+}
+
+/// A struct used internally to represent the current state of a proposed bundle
+/// as it goes through iterations. Contains similar data to the
+/// `Vec<UserOpsPerAggregator>` that will eventually be passed to the entry
+/// point, but contains extra context needed for the computation.
+#[derive(Debug)]
+struct ProposalContext<UO> {
+    groups_by_aggregator: LinkedHashMap<Option<Address>, AggregatorGroup<UO>>,
+    rejected_ops: Vec<(UO, EntityInfos)>,
+    // This is a BTreeMap so that the conversion to a Vec<EntityUpdate> is deterministic, mainly for tests
+    entity_updates: BTreeMap<Address, EntityUpdate>,
+    // Ids of ops that were simulated on top of the op with their previous nonce
+    chained_op_ids: HashSet<UserOperationId>,
+    // Ids of the ops of each atomic group, which are bundled together or not at all
+    atomic_groups: Vec<Vec<UserOperationId>>,
+}
+
+#[derive(Debug)]
 struct AggregatorGroup<UO> {
     ops_with_simulations: Vec<OpWithSimulation<UO>>,
     signature: Bytes,
@@ -1208,6 +2032,8 @@ impl<UO: UserOperation> ProposalContext<UO> {
             groups_by_aggregator: LinkedHashMap::<Option<Address>, AggregatorGroup<UO>>::new(),
             rejected_ops: Vec::<(UO, EntityInfos)>::new(),
             entity_updates: BTreeMap::new(),
+            chained_op_ids: HashSet::new(),
+            atomic_groups: Vec::new(),
         }
     }
 
@@ -1340,9 +2166,76 @@ impl<UO: UserOperation> ProposalContext<UO> {
         paymaster_amendment: bool,
         filter: impl Fn(&UO) -> bool,
     ) -> Vec<Address> {
+        let (rejected, changed_aggregators) = self.filter_remove(filter);
+        let mut paymasters_to_amend: HashMap<Address, u64> = HashMap::new();
+        for op in rejected {
+            if paymaster_amendment {
+                if let Some(paymaster) = op.op.paymaster() {
+                    *paymasters_to_amend.entry(paymaster).or_default() += 1;
+                }
+            }
+            self.rejected_ops.push((op.op, op.simulation.entity_infos));
+        }
+        for (paymaster, count) in paymasters_to_amend {
+            self.add_erep_015_paymaster_amendment(paymaster, count);
+        }
+        changed_aggregators
+    }
+
+    /// Remove ops that were simulated on top of an op with their previous nonce that is no
+    /// longer in the bundle, and ops of atomic groups that are no longer in the bundle as a
+    /// whole, without rejecting them. Returns the addresses of any aggregators whose signature
+    /// may need to be recomputed.
+    fn remove_broken_dependencies(&mut self) -> Vec<Address> {
+        let mut changed_aggregators = vec![];
+        loop {
+            let op_ids: HashSet<_> = self.iter_ops().map(|op| op.id()).collect();
+            let mut broken: HashSet<_> = self
+                .chained_op_ids
+                .iter()
+                .filter(|id| {
+                    op_ids.contains(id)
+                        && !id
+                            .previous()
+                            .is_some_and(|previous| op_ids.contains(&previous))
+                })
+                .copied()
+                .collect();
+            for id in &broken {
+                info!(
+                    "Removing op from {:?} because the op with its previous nonce was removed from the bundle.",
+                    id.sender
+                );
+            }
+            for group in &self.atomic_groups {
+                if group.iter().all(|id| op_ids.contains(id)) {
+                    continue;
+                }
+                for id in group.iter().filter(|id| op_ids.contains(id)) {
+                    info!(
+                        "Removing op from {:?} because another op of its atomic group was removed from the bundle.",
+                        id.sender
+                    );
+                    broken.insert(*id);
+                }
+            }
+            if broken.is_empty() {
+                return changed_aggregators;
+            }
+            let (_, changed) = self.filter_remove(|op| broken.contains(&op.id()));
+            changed_aggregators.extend(changed);
+        }
+    }
+
+    /// Remove all ops that match the filter. Returns the removed ops and the addresses of
+    /// any aggregators whose signature may need to be recomputed.
+    fn filter_remove(
+        &mut self,
+        filter: impl Fn(&UO) -> bool,
+    ) -> (Vec<OpWithSimulation<UO>>, Vec<Address>) {
+        let mut removed = vec![];
         let mut changed_aggregators: Vec<Address> = vec![];
         let mut aggregators_to_remove: Vec<Option<Address>> = vec![];
-        let mut paymasters_to_amend: HashMap<Address, u64> = HashMap::new();
         for (&aggregator, group) in &mut self.groups_by_aggregator {
             // I sure wish `Vec::drain_filter` were stable.
             let group_uses_rejected_entity =
@@ -1352,12 +2245,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                     if !filter(&op.op) {
                         group.ops_with_simulations.push(op);
                     } else {
-                        if paymaster_amendment {
-                            if let Some(paymaster) = op.op.paymaster() {
-                                *paymasters_to_amend.entry(paymaster).or_default() += 1;
-                            }
-                        }
-                        self.rejected_ops.push((op.op, op.simulation.entity_infos));
+                        removed.push(op);
                     }
                 }
                 if group.ops_with_simulations.is_empty() {
@@ -1370,10 +2258,11 @@ impl<UO: UserOperation> ProposalContext<UO> {
         for aggregator in aggregators_to_remove {
             self.groups_by_aggregator.remove(&aggregator);
         }
-        for (paymaster, count) in paymasters_to_amend {
-            self.add_erep_015_paymaster_amendment(paymaster, count);
-        }
-        changed_aggregators
+        (removed, changed_aggregators)
+    }
+
+    fn contains_op_id(&self, id: &UserOperationId) -> bool {
+        self.iter_ops().any(|op| op.id() == *id)
     }
 
     fn to_ops_per_aggregator(&self) -> Vec<UserOpsPerAggregator<UO>> {
@@ -1404,6 +2293,19 @@ impl<UO: UserOperation> ProposalContext<UO> {
             .map(|sim_op| sim_op.op.gas_limit(chain_spec, None))
             .sum::<u128>()
             + rundler_types::bundle_shared_gas(chain_spec)
+            + self.authorization_gas()
+    }
+
+    // Intrinsic gas charged for the bundle transaction's authorization list, which has one
+    // authorization per sender. Assumes that every authority is an empty account, as
+    // the refund for existing accounts is applied after execution.
+    fn authorization_gas(&self) -> u128 {
+        let authorities: HashSet<Address> = self
+            .iter_ops()
+            .filter(|op| op.authorization_tuple().is_some())
+            .map(|op| op.sender())
+            .collect();
+        authorities.len() as u128 * PER_EMPTY_ACCOUNT_COST as u128
     }
 
     fn iter_ops_with_simulations(&self) -> impl Iterator<Item = &OpWithSimulation<UO>> + '_ {
@@ -1627,13 +2529,16 @@ mod tests {
     use std::time::Duration;
 
     use alloy_primitives::{utils::parse_units, Address, B256};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
     use anyhow::anyhow;
     use rundler_provider::{
-        AggregatorSimOut, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider,
-        ProvidersWithEntryPoint,
+        AccountOverride, AggregatorSimOut, MockDAGasOracleSync, MockEntryPointV0_6,
+        MockEvmProvider, ProvidersWithEntryPoint,
     };
     use rundler_sim::{MockFeeEstimator, MockSimulator};
     use rundler_types::{
+        aggregator::AggregatorConfig,
         da::BedrockDAGasBlockData,
         pool::{MockPool, SimulationViolation},
         v0_6::UserOperation,
@@ -1815,6 +2720,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
         assert_eq!(
@@ -1852,6 +2760,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
         assert_eq!(
@@ -1900,6 +2811,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             true,
+            None,
+            None,
+            vec![],
         )
         .await;
         assert_eq!(
@@ -1980,10 +2894,12 @@ mod tests {
             vec![
                 MockAggregator {
                     address: aggregator_a_address,
+                    aggregator_type: AggregatorType::Contract,
                     signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
                 },
                 MockAggregator {
                     address: aggregator_b_address,
+                    aggregator_type: AggregatorType::Contract,
                     signature: Box::new(move || Ok(Some(bytes(aggregator_b_signature)))),
                 },
             ],
@@ -1994,10 +2910,14 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
         // Ops should be grouped by aggregator. Further, the `signature` field
-        // of each op with an aggregator should be empty.
+        // of each op with an aggregator should be replaced by the signature the
+        // aggregator returned for it.
 
         bundle
             .ops_per_aggregator
@@ -2013,11 +2933,11 @@ mod tests {
                 UserOpsPerAggregator {
                     user_ops: vec![
                         UserOperation {
-                            signature: Bytes::new(),
+                            signature: bytes(op_a1_aggregated_sig),
                             ..aggregated_op_a1
                         },
                         UserOperation {
-                            signature: Bytes::new(),
+                            signature: bytes(op_a2_aggregated_sig),
                             ..aggregated_op_a2
                         }
                     ],
@@ -2026,7 +2946,7 @@ mod tests {
                 },
                 UserOpsPerAggregator {
                     user_ops: vec![UserOperation {
-                        signature: Bytes::new(),
+                        signature: bytes(op_b_aggregated_sig),
                         ..aggregated_op_b
                     }],
                     aggregator: aggregator_b_address,
@@ -2036,6 +2956,88 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_bls_aggregator() {
+        let aggregator_address = address(10);
+        // each op is signed with the G1 generator (1, 2)
+        let g1_signature: Bytes = [U256::from(1), U256::from(2)]
+            .iter()
+            .flat_map(|n| n.to_be_bytes::<32>())
+            .collect();
+        let aggregated_op_1 = UserOperation {
+            signature: g1_signature.clone(),
+            ..op_with_sender(address(1))
+        };
+        let aggregated_op_2 = UserOperation {
+            signature: g1_signature,
+            ..op_with_sender(address(2))
+        };
+        let simulation_result = move || {
+            Ok::<_, SimulationError>(SimulationResult {
+                aggregator: Some(AggregatorSimOut {
+                    address: aggregator_address,
+                    signature: Bytes::new(),
+                }),
+                ..Default::default()
+            })
+        };
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: aggregated_op_1.clone(),
+                    simulation_result: Box::new(simulation_result),
+                },
+                MockOp {
+                    op: aggregated_op_2.clone(),
+                    simulation_result: Box::new(simulation_result),
+                },
+            ],
+            vec![MockAggregator {
+                address: aggregator_address,
+                aggregator_type: AggregatorType::Bls,
+                signature: Box::new(|| Ok(None)),
+            }],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            None,
+            None,
+            vec![],
+        )
+        .await;
+
+        // the aggregated signature is the encoded G1 point 2 * (1, 2), computed without
+        // calling the aggregator contract
+        let expected_signature: Bytes = [
+            "0x030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3",
+            "0x15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4",
+        ]
+        .iter()
+        .flat_map(|n| n.parse::<U256>().unwrap().to_be_bytes::<32>())
+        .collect();
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![
+                    UserOperation {
+                        signature: Bytes::new(),
+                        ..aggregated_op_1
+                    },
+                    UserOperation {
+                        signature: Bytes::new(),
+                        ..aggregated_op_2
+                    },
+                ],
+                aggregator: aggregator_address,
+                signature: expected_signature,
+            }],
+        );
+    }
+
     #[tokio::test]
     async fn test_reject_entities() {
         let op1 = op_with_sender_paymaster(address(1), address(1));
@@ -2085,6 +3087,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2113,6 +3118,98 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_pending_simulation_rejects_failed_op() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        // a mempool transaction to the first op's sender lands ahead of the bundle
+        let txpool_content = serde_json::json!({
+            "pending": {
+                address(9).to_string(): {
+                    "0": mempool_tx(hash(10), 0, address(9), address(1)),
+                },
+            },
+            "queued": {},
+        });
+
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA25 invalid account nonce".to_string()),
+                HandleOpsOut::Success,
+            ],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            None,
+            Some(txpool_content),
+            vec![],
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op1]);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pending_state_mempool_txs() {
+        let txpool_content: TxpoolContent = serde_json::from_value(serde_json::json!({
+            "pending": {
+                address(9).to_string(): {
+                    "10": mempool_tx(hash(11), 10, address(9), address(2)),
+                    "9": mempool_tx(hash(10), 9, address(9), address(1)),
+                    "11": mempool_tx(hash(12), 11, address(9), address(3)),
+                },
+            },
+        }))
+        .unwrap();
+
+        let mut pending_state = PendingState {
+            txs: vec![TransactionRequest::default()],
+            in_flight_tx_hashes: vec![hash(20)],
+            ..Default::default()
+        };
+        pending_state.add_mempool_txs(txpool_content, &HashSet::from([address(1), address(2)]));
+
+        // the unrelated transaction is left out, the others are in nonce order
+        assert_eq!(pending_state.txs.len(), 3);
+        assert_eq!(
+            pending_state.txs[1].to.and_then(|to| to.to().copied()),
+            Some(address(1))
+        );
+        assert_eq!(
+            pending_state.txs[2].to.and_then(|to| to.to().copied()),
+            Some(address(2))
+        );
+        assert_eq!(
+            pending_state.related_tx_hashes(&op_with_sender_paymaster(address(1), address(2))),
+            vec![hash(20), hash(10), hash(11)]
+        );
+        assert_eq!(
+            pending_state.related_tx_hashes(&op_with_sender(address(3))),
+            vec![hash(20)]
+        );
+    }
+
     #[tokio::test]
     async fn test_paymaster_amended_by_staked_factory_revert() {
         let sender = address(1);
@@ -2149,6 +3246,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2208,6 +3308,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2306,6 +3409,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2357,6 +3463,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2414,6 +3523,8 @@ mod tests {
             groups_by_aggregator,
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
+            chained_op_ids: HashSet::new(),
+            atomic_groups: Vec::new(),
         };
 
         let expected_gas_limit = op1.gas_limit(&cs, None)
@@ -2455,6 +3566,8 @@ mod tests {
             groups_by_aggregator,
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
+            chained_op_ids: HashSet::new(),
+            atomic_groups: Vec::new(),
         };
         let gas_limit = context.get_bundle_gas_limit(&cs);
 
@@ -2466,27 +3579,396 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_post_op_revert() {
-        let op1 = op_with_sender(address(1));
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op1.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
-            }],
-            vec![],
-            vec![HandleOpsOut::PostOpRevert, HandleOpsOut::PostOpRevert],
-            vec![],
-            0,
-            0,
-            false,
-            ExpectedStorage::default(),
-            false,
-        )
-        .await;
+    async fn test_bundle_gas_limit_with_authorizations() {
+        let cs = ChainSpec::default();
+        let signer = signer(1);
+        let authorization = signed_authorization(&signer, 0);
+        let op1 = op_with_authorization(&signer, authorization.clone());
+        let op2 = UserOperation {
+            nonce: U256::from(1),
+            ..op_with_authorization(&signer, authorization)
+        };
+        let op3 = op_with_sender(address(1));
+        let mut groups_by_aggregator = LinkedHashMap::new();
+        groups_by_aggregator.insert(
+            None,
+            AggregatorGroup {
+                ops_with_simulations: [&op1, &op2, &op3]
+                    .into_iter()
+                    .map(|op| OpWithSimulation {
+                        op: op.clone(),
+                        simulation: SimulationResult::default(),
+                    })
+                    .collect(),
+                signature: Default::default(),
+            },
+        );
+        let context = ProposalContext {
+            groups_by_aggregator,
+            rejected_ops: vec![],
+            entity_updates: BTreeMap::new(),
+            chained_op_ids: HashSet::new(),
+            atomic_groups: Vec::new(),
+        };
 
-        assert_eq!(bundle.rejected_ops, vec![op1]);
-        assert!(bundle.ops_per_aggregator.is_empty());
-    }
+        // ops from the same sender share an authorization
+        let expected_gas_limit = op1.gas_limit(&cs, None)
+            + op2.gas_limit(&cs, None)
+            + op3.gas_limit(&cs, None)
+            + rundler_types::bundle_shared_gas(&cs)
+            + PER_EMPTY_ACCOUNT_COST as u128;
+
+        assert_eq!(context.get_bundle_gas_limit(&cs), expected_gas_limit);
+    }
+
+    #[tokio::test]
+    async fn test_authorizations() {
+        // every sender has nonce AUTHORITY_NONCE
+        let stale = op_with_authorization(
+            &signer(1),
+            signed_authorization(&signer(1), AUTHORITY_NONCE - 1),
+        );
+        let valid = op_with_authorization(
+            &signer(2),
+            signed_authorization(&signer(2), AUTHORITY_NONCE),
+        );
+        let future = op_with_authorization(
+            &signer(3),
+            signed_authorization(&signer(3), AUTHORITY_NONCE + 1),
+        );
+        let wrong_signer = op_with_authorization(
+            &signer(4),
+            signed_authorization(&signer(5), AUTHORITY_NONCE),
+        );
+
+        let bundle = simple_make_bundle(
+            [&stale, &valid, &future, &wrong_signer]
+                .into_iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
+        )
+        .await;
+
+        let cs = ChainSpec::default();
+        let expected_gas: u64 = math::increase_by_percent(
+            valid.gas_limit(&cs, Some(1)) + PER_EMPTY_ACCOUNT_COST as u128,
+            BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
+        )
+        .try_into()
+        .unwrap();
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![valid],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.rejected_ops, vec![stale, wrong_signer]);
+        assert_eq!(bundle.gas_estimate, expected_gas);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_chain() {
+        let op0 = op_with_sender(address(1));
+        let op1 = UserOperation {
+            nonce: U256::from(1),
+            ..op0.clone()
+        };
+        let op2 = UserOperation {
+            nonce: U256::from(2),
+            ..op0.clone()
+        };
+        // the previous nonce isn't in the pool, so it's not chained
+        let gap = UserOperation {
+            nonce: U256::from(5),
+            ..op_with_sender(address(2))
+        };
+
+        let bundle = simple_make_bundle(
+            [&op0, &op1, &op2, &gap]
+                .into_iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0, op1, op2, gap],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_chain_broken() {
+        let op0 = op_with_sender(address(1));
+        let op1 = UserOperation {
+            nonce: U256::from(1),
+            ..op0.clone()
+        };
+        let op2 = UserOperation {
+            nonce: U256::from(2),
+            ..op0.clone()
+        };
+        let other = op_with_sender(address(2));
+
+        // op0 fails its simulation, so the ops following it are skipped but not rejected
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: op0.clone(),
+                simulation_result: Box::new(|| {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Violations(vec![]),
+                        entity_infos: None,
+                    })
+                }),
+            },
+            MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+            MockOp {
+                op: op2.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+            MockOp {
+                op: other.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+        ])
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![other.clone()],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.rejected_ops, vec![op0.clone()]);
+
+        // op0 fails in the bundle, so the ops following it are removed but not rejected
+        let bundle = mock_make_bundle(
+            [&op0, &op1, &op2, &other]
+                .into_iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
+            vec![],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA23 reverted".to_string()),
+                HandleOpsOut::Success,
+            ],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            None,
+            None,
+            vec![],
+        )
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![other],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.rejected_ops, vec![op0]);
+    }
+
+    #[tokio::test]
+    async fn test_atomic_group_dependent() {
+        let op0 = op_with_sender(address(1));
+        let op1 = op_with_sender(address(2));
+        let other = op_with_sender(address(3));
+
+        // op1 is only valid on top of the state left by op0
+        let bundle = mock_make_bundle(
+            [&op0, &op1, &other]
+                .into_iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            None,
+            None,
+            vec![vec![0, 1]],
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0, op1, other],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_atomic_group_broken() {
+        let op0 = op_with_sender(address(1));
+        let op1 = op_with_sender(address(2));
+        let other = op_with_sender(address(3));
+        let mut groups_by_aggregator = LinkedHashMap::new();
+        groups_by_aggregator.insert(
+            None,
+            AggregatorGroup {
+                ops_with_simulations: [&op0, &op1, &other]
+                    .into_iter()
+                    .map(|op| OpWithSimulation {
+                        op: op.clone(),
+                        simulation: SimulationResult::default(),
+                    })
+                    .collect(),
+                signature: Default::default(),
+            },
+        );
+        let mut context = ProposalContext {
+            groups_by_aggregator,
+            rejected_ops: vec![],
+            entity_updates: BTreeMap::new(),
+            chained_op_ids: HashSet::new(),
+            atomic_groups: vec![vec![op0.id(), op1.id()]],
+        };
+
+        // op0 is rejected, so the rest of its group is removed but not rejected
+        assert_eq!(context.reject_index(0, false), None);
+        assert!(context.remove_broken_dependencies().is_empty());
+        assert_eq!(context.iter_ops().cloned().collect::<Vec<_>>(), vec![other]);
+        assert_eq!(
+            context
+                .rejected_ops
+                .into_iter()
+                .map(|(op, _)| op)
+                .collect::<Vec<_>>(),
+            vec![op0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drops_unprofitable_op_with_large_gas_limit() {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        // pays a higher gas price than the bundle transaction
+        let op1 = op_with_sender_and_fees(address(1), 1200, 200);
+        // has a large gas limit that it may not use, but only pays the bundle's gas price for
+        // its share of the gas
+        let op2 = UserOperation {
+            call_gas_limit: 5_000_000,
+            ..op_with_sender_and_fees(address(2), 1050, 50)
+        };
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success, HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+            false,
+            ExpectedStorage::default(),
+            false,
+            Some(10),
+            None,
+            vec![],
+        )
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1],
+                ..Default::default()
+            }],
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drops_all_ops_below_profit_margin() {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        let op = op_with_sender_and_fees(address(1), 1050, 50);
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+            false,
+            ExpectedStorage::default(),
+            false,
+            Some(1_000_000),
+            None,
+            vec![],
+        )
+        .await;
+        assert!(bundle.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_op_revert() {
+        let op1 = op_with_sender(address(1));
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            }],
+            vec![],
+            vec![HandleOpsOut::PostOpRevert, HandleOpsOut::PostOpRevert],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            None,
+            None,
+            vec![],
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op1]);
+        assert!(bundle.ops_per_aggregator.is_empty());
+    }
 
     #[tokio::test]
     async fn test_post_op_revert_two() {
@@ -2516,6 +3998,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2571,6 +4056,7 @@ mod tests {
             ],
             vec![MockAggregator {
                 address: aggregator_a_address,
+                aggregator_type: AggregatorType::Contract,
                 signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
             }],
             vec![
@@ -2585,6 +4071,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2627,6 +4116,9 @@ mod tests {
             true,
             actual_storage,
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2666,6 +4158,9 @@ mod tests {
             true,
             actual_storage,
             false,
+            None,
+            None,
+            vec![],
         )
         .await;
 
@@ -2680,6 +4175,7 @@ mod tests {
 
     struct MockAggregator {
         address: Address,
+        aggregator_type: AggregatorType,
         signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
     }
 
@@ -2694,6 +4190,9 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
+            None,
+            None,
+            vec![],
         )
         .await
     }
@@ -2709,15 +4208,36 @@ mod tests {
         notify_condition_not_met: bool,
         actual_storage: ExpectedStorage,
         da_gas_tracking_enabled: bool,
+        min_profit_margin_percent: Option<u32>,
+        txpool_content: Option<serde_json::Value>,
+        atomic_groups: Vec<Vec<usize>>,
     ) -> Bundle<UserOperation> {
         let entry_point_address = address(123);
         let beneficiary = address(124);
         let current_block_hash = hash(125);
         let expected_code_hash = hash(126);
         let max_bundle_size = mock_ops.len() as u64;
+        let hashes: Vec<_> = mock_ops
+            .iter()
+            .map(|op| op.op.hash(entry_point_address, 0))
+            .collect();
+        // the atomic group of each op, and the senders of the ops before it in the group
+        let mut groups_by_op = HashMap::new();
+        let mut earlier_senders_by_op = HashMap::<_, Vec<_>>::new();
+        for group in &atomic_groups {
+            let group_hashes: Vec<_> = group.iter().map(|&i| hashes[i]).collect();
+            for (k, &i) in group.iter().enumerate() {
+                groups_by_op.insert(i, group_hashes.clone());
+                earlier_senders_by_op.insert(
+                    hashes[i],
+                    group[..k].iter().map(|&j| mock_ops[j].op.sender).collect(),
+                );
+            }
+        }
         let ops: Vec<_> = mock_ops
             .iter()
-            .map(|MockOp { op, .. }| PoolOperation {
+            .enumerate()
+            .map(|(i, MockOp { op, .. })| PoolOperation {
                 uo: op.clone().into(),
                 expected_code_hash,
                 entry_point: entry_point_address,
@@ -2728,6 +4248,8 @@ mod tests {
                 entity_infos: EntityInfos::default(),
                 aggregator: None,
                 da_gas_data: Default::default(),
+                atomic_group: groups_by_op.get(&i).cloned(),
+                mempools: vec![],
             })
             .collect();
 
@@ -2736,6 +4258,7 @@ mod tests {
             .expect_get_ops()
             .returning(move |_, _, _| Ok(ops.clone()));
 
+        let op_ids: HashSet<_> = mock_ops.iter().map(|op| op.op.id()).collect();
         let simulations_by_op: HashMap<_, _> = mock_ops
             .into_iter()
             .map(|op| (op.op.hash(entry_point_address, 0), op.simulation_result))
@@ -2743,20 +4266,70 @@ mod tests {
         let mut simulator = MockSimulator::new();
         simulator
             .expect_simulate_validation()
-            .withf(move |_, &block_hash, &code_hash| {
+            .withf(move |_, &block_hash, &code_hash, _| {
                 block_hash == current_block_hash && code_hash == Some(expected_code_hash)
             })
-            .returning(move |op, _, _| simulations_by_op[&op.hash(entry_point_address, 0)]());
+            .returning(move |op, _, _, state_override| {
+                // ops following another op from the sender, or earlier ops of their atomic
+                // group, must be simulated on top of them
+                let mut expected_override = StateOverride::default();
+                if op
+                    .id()
+                    .previous()
+                    .is_some_and(|previous| op_ids.contains(&previous))
+                {
+                    nonce_utils::apply_nonce_override(
+                        &mut expected_override,
+                        entry_point_address,
+                        op.sender,
+                        op.nonce,
+                    );
+                }
+                for &sender in earlier_senders_by_op
+                    .get(&op.hash(entry_point_address, 0))
+                    .into_iter()
+                    .flatten()
+                {
+                    expected_override.insert(sender, applied_op_override());
+                }
+                if state_override != expected_override {
+                    return Err(SimulationError {
+                        violation_error: ViolationError::Other(anyhow!(
+                            "AA25 invalid account nonce"
+                        )),
+                        entity_infos: None,
+                    });
+                }
+                simulations_by_op[&op.hash(entry_point_address, 0)]()
+            });
         let mut entry_point = MockEntryPointV0_6::new();
         entry_point
             .expect_address()
             .return_const(entry_point_address);
+        entry_point
+            .expect_get_handle_ops_state_override()
+            .withf(move |_, &b, _, _, _| b == beneficiary)
+            .returning(|ops_per_aggregator, _, _, _, mut state_override| {
+                for op in ops_per_aggregator.iter().flat_map(|ops| &ops.user_ops) {
+                    state_override.insert(op.sender, applied_op_override());
+                }
+                Ok(state_override)
+            });
+        let pending_simulation = txpool_content.is_some();
         for call_res in mock_handle_ops_call_results {
-            entry_point
-                .expect_call_handle_ops()
-                .times(..=1)
-                .withf(move |_, &b, _| b == beneficiary)
-                .return_once(|_, _, _| Ok(call_res));
+            if pending_simulation {
+                entry_point
+                    .expect_call_handle_ops_pending()
+                    .times(..=1)
+                    .withf(move |_, &b, _, _| b == beneficiary)
+                    .return_once(|_, _, _, _| Ok(call_res));
+            } else {
+                entry_point
+                    .expect_call_handle_ops()
+                    .times(..=1)
+                    .withf(move |_, &b, _| b == beneficiary)
+                    .return_once(|_, _, _| Ok(call_res));
+            }
         }
         for deposit in mock_paymaster_deposits {
             entry_point
@@ -2765,8 +4338,20 @@ mod tests {
                 .return_once(move |_, _| Ok(deposit));
         }
 
+        let aggregators = AggregatorConfigs::from(
+            mock_aggregators
+                .iter()
+                .map(|agg| AggregatorConfig {
+                    address: agg.address,
+                    aggregator_type: agg.aggregator_type,
+                    validation_gas_per_op: 0,
+                })
+                .collect::<Vec<_>>(),
+        );
+        // only contract aggregators are called for their signatures
         let signatures_by_aggregator: HashMap<_, _> = mock_aggregators
             .into_iter()
+            .filter(|agg| agg.aggregator_type == AggregatorType::Contract)
             .map(|agg| (agg.address, agg.signature))
             .collect();
 
@@ -2774,6 +4359,16 @@ mod tests {
         provider
             .expect_get_latest_block_hash_and_number()
             .returning(move || Ok((current_block_hash, 0)));
+        provider
+            .expect_get_transaction_count()
+            .returning(|_| Ok(AUTHORITY_NONCE));
+        if let Some(txpool_content) = txpool_content {
+            let txpool_content: TxpoolContent = serde_json::from_value(txpool_content).unwrap();
+            provider
+                .expect_request::<(), TxpoolContent>()
+                .withf(|method, _| *method == "txpool_content")
+                .return_once(move |_, _| Ok(txpool_content));
+        }
 
         let mut fee_estimator = MockFeeEstimator::new();
         fee_estimator
@@ -2848,6 +4443,9 @@ mod tests {
                 bundle_base_fee_overhead_percent: 27,
                 bundle_priority_fee_overhead_percent: 0,
                 da_gas_tracking_enabled,
+                min_profit_margin_percent,
+                aggregators,
+                pending_simulation,
             },
             event_sender,
         );
@@ -2862,6 +4460,14 @@ mod tests {
             .expect("should make a bundle")
     }
 
+    // Marks an op as applied in the state override of the ops after it
+    fn applied_op_override() -> AccountOverride {
+        AccountOverride {
+            nonce: Some(1),
+            ..Default::default()
+        }
+    }
+
     fn address(n: u8) -> Address {
         let mut bytes = [0_u8; 20];
         bytes[0] = n;
@@ -2881,6 +4487,55 @@ mod tests {
     // UOs require PVG to pass the PVG check even when fees are 0
     const DEFAULT_PVG: u128 = 1_000_000;
 
+    const AUTHORITY_NONCE: u64 = 5;
+
+    fn signer(n: u8) -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::repeat_byte(n)).unwrap()
+    }
+
+    fn signed_authorization(signer: &PrivateKeySigner, nonce: u64) -> Authorization {
+        let authorization = alloy_eips::eip7702::Authorization {
+            chain_id: 0,
+            address: address(200),
+            nonce,
+        };
+        let signature = signer
+            .sign_hash_sync(&authorization.signature_hash())
+            .unwrap();
+        Authorization {
+            chain_id: authorization.chain_id,
+            address: authorization.address,
+            nonce,
+            y_parity: signature.v().y_parity_byte(),
+            r: signature.r(),
+            s: signature.s(),
+        }
+    }
+
+    fn op_with_authorization(
+        signer: &PrivateKeySigner,
+        authorization: Authorization,
+    ) -> UserOperation {
+        UserOperation {
+            sender: signer.address(),
+            authorization_tuple: Some(authorization),
+            pre_verification_gas: DEFAULT_PVG,
+            ..Default::default()
+        }
+    }
+
+    fn mempool_tx(hash: B256, nonce: u64, from: Address, to: Address) -> serde_json::Value {
+        serde_json::json!({
+            "hash": hash,
+            "nonce": U64::from(nonce),
+            "from": from,
+            "to": to,
+            "gas": U64::from(21_000),
+            "value": U256::ZERO,
+            "input": Bytes::new(),
+        })
+    }
+
     fn op_with_sender(sender: Address) -> UserOperation {
         UserOperation {
             sender,
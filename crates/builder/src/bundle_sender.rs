@@ -11,10 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use alloy_primitives::{Address, B256};
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
 use futures::Stream;
 use futures_util::StreamExt;
@@ -45,6 +51,7 @@ use tracing::{debug, error, info, instrument, warn};
 use crate::{
     bundle_proposer::{Bundle, BundleProposer, BundleProposerError},
     emit::{BuilderEvent, BundleTxDetails},
+    signer::LeaseHandle,
     transaction_tracker::{TrackerUpdate, TransactionTracker, TransactionTrackerError},
 };
 
@@ -58,6 +65,7 @@ pub(crate) struct Settings {
     pub(crate) max_replacement_underpriced_blocks: u64,
     pub(crate) max_cancellation_fee_increases: u64,
     pub(crate) max_blocks_to_wait_for_mine: u64,
+    pub(crate) max_in_flight_transactions: u64,
 }
 
 #[derive(Debug)]
@@ -65,13 +73,17 @@ pub(crate) struct BundleSenderImpl<UO, P, E, T, C> {
     builder_index: u64,
     bundle_action_receiver: Option<mpsc::Receiver<BundleSenderAction>>,
     chain_spec: ChainSpec,
-    beneficiary: Address,
     proposer: P,
     entry_point: E,
     transaction_tracker: Option<T>,
+    lease: LeaseHandle,
     pool: C,
     settings: Settings,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    // senders of the ops in each sent bundle transaction, by nonce
+    in_flight_senders: HashMap<u64, HashSet<Address>>,
+    // hash and transaction of each sent bundle transaction, by nonce
+    in_flight_txs: BTreeMap<u64, (B256, TransactionRequest)>,
     metrics: BuilderMetric,
     _uo_type: PhantomData<UO>,
 }
@@ -81,6 +93,7 @@ struct BundleTx {
     tx: TransactionRequest,
     expected_storage: ExpectedStorage,
     op_hashes: Vec<B256>,
+    senders: HashSet<Address>,
 }
 
 pub enum BundleSenderAction {
@@ -127,6 +140,8 @@ enum SendBundleAttemptResult {
     Rejected,
     // Nonce too low
     NonceTooLow,
+    // The signer lease was lost before the bundle could be sent
+    LeaseLost,
 }
 
 #[async_trait]
@@ -180,10 +195,10 @@ where
         builder_index: u64,
         bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
         chain_spec: ChainSpec,
-        beneficiary: Address,
         proposer: P,
         entry_point: E,
         transaction_tracker: T,
+        lease: LeaseHandle,
         pool: C,
         settings: Settings,
         event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
@@ -192,12 +207,14 @@ where
             builder_index,
             bundle_action_receiver: Some(bundle_action_receiver),
             chain_spec,
-            beneficiary,
             proposer,
             transaction_tracker: Some(transaction_tracker),
+            lease,
             pool,
             settings,
             event_sender,
+            in_flight_senders: HashMap::new(),
+            in_flight_txs: BTreeMap::new(),
             metrics: BuilderMetric::new_with_labels(&[
                 ("entry_point", entry_point.address().to_string()),
                 ("builder_index", builder_index.to_string()),
@@ -211,14 +228,18 @@ where
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
     ) -> anyhow::Result<()> {
-        let tracker_update = state.wait_for_trigger().await?;
+        if !self.lease.is_held() {
+            return self.handle_lease_lost(state).await;
+        }
+
+        let tracker_updates = state.wait_for_trigger().await?;
 
         match state.inner {
             InnerState::Building(building_state) => {
                 self.handle_building_state(state, building_state).await?;
             }
             InnerState::Pending(pending_state) => {
-                self.handle_pending_state(state, pending_state, tracker_update)
+                self.handle_pending_state(state, pending_state, tracker_updates)
                     .await?;
             }
             InnerState::Cancelling(cancelling_state) => {
@@ -226,7 +247,7 @@ where
                     .await?;
             }
             InnerState::CancelPending(cancel_pending_state) => {
-                self.handle_cancel_pending_state(state, cancel_pending_state, tracker_update)
+                self.handle_cancel_pending_state(state, cancel_pending_state, tracker_updates)
                     .await?;
             }
         }
@@ -234,15 +255,55 @@ where
         Ok(())
     }
 
+    /// Stops all submission while the signer lease is not held.
+    ///
+    /// Another holder of the key may now be sending transactions with our nonce, so any
+    /// transaction state is abandoned rather than replaced or cancelled. Once the lease
+    /// is re-acquired, or has failed over to another free key, the tracker is reset from
+    /// the on-chain nonce of the signer.
+    async fn handle_lease_lost<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+    ) -> anyhow::Result<()> {
+        warn!("Signer lease lost, waiting for a lease on a free key");
+        self.metrics.signer_lease_lost.increment(1);
+        state.transaction_tracker.abandon();
+        state.send_result(SendBundleResult::Error(anyhow!("signer lease lost")));
+
+        self.lease.wait_for_lease().await?;
+
+        info!("Signer lease acquired, resuming bundle submission");
+        state.reset();
+        Ok(())
+    }
+
     async fn handle_building_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         inner: BuildingState,
     ) -> anyhow::Result<()> {
-        // send bundle
         let block_number = state.block_number();
+
+        // A pipelined transaction that has become the current transaction is waited on
+        // like any other sent bundle, it is only replaced if it doesn't mine in time.
+        if self.pipelining_enabled() && inner.fee_increase_count == 0 {
+            let (nonce, _) = state.transaction_tracker.get_nonce_and_required_fees()?;
+            if state.transaction_tracker.in_flight_nonces().first() == Some(&nonce) {
+                info!(
+                    "Pipelined bundle with nonce {nonce} is now the latest, waiting for it to mine"
+                );
+                state.update(InnerState::Pending(inner.to_pending(
+                    block_number + self.settings.max_blocks_to_wait_for_mine,
+                )));
+                return Ok(());
+            }
+        }
+
+        // send bundle
         debug!("Building bundle on block {}", block_number);
-        let result = self.send_bundle(state, inner.fee_increase_count).await;
+        let result = self
+            .send_bundle(state, inner.fee_increase_count, false)
+            .await;
 
         // handle result
         match result {
@@ -300,6 +361,10 @@ where
                 info!("Nonce too low, starting new bundle attempt");
                 state.reset();
             }
+            Ok(SendBundleAttemptResult::LeaseLost) => {
+                // handled on the next step
+                info!("Signer lease lost before sending bundle");
+            }
             Ok(SendBundleAttemptResult::Underpriced) => {
                 info!(
                     "Bundle underpriced, marking as underpriced. Num fee increases {:?}",
@@ -342,9 +407,12 @@ where
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         inner: PendingState,
-        tracker_update: Option<TrackerUpdate>,
+        tracker_updates: Vec<TrackerUpdate>,
     ) -> anyhow::Result<()> {
-        if let Some(update) = tracker_update {
+        // With pipelining, an update is received for each in-flight nonce that mined,
+        // the state follows the last of them.
+        let has_updates = !tracker_updates.is_empty();
+        for update in tracker_updates {
             match update {
                 TrackerUpdate::Mined {
                     block_number,
@@ -386,7 +454,13 @@ where
                     state.reset();
                 }
             }
-        } else if state.block_number() >= inner.until {
+        }
+
+        if has_updates {
+            return Ok(());
+        }
+
+        if state.block_number() >= inner.until {
             // start replacement, don't wait for trigger. Continue
             // to attempt until there are no longer any UOs priced high enough
             // to bundle.
@@ -397,11 +471,53 @@ where
             );
             self.metrics.bundle_txn_fee_increases.increment(1);
             state.update(InnerState::Building(inner.to_building()))
+        } else if self.pipelining_enabled() && !state.trigger.builder_must_wait_for_trigger() {
+            self.send_pipelined_bundle(state).await;
         }
 
         Ok(())
     }
 
+    /// Sends a bundle with the next nonce behind the pending transactions, if there is
+    /// room for another transaction in flight. The state machine stays pending on the
+    /// earliest transaction.
+    async fn send_pipelined_bundle<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+    ) {
+        if state
+            .transaction_tracker
+            .get_pipelined_nonce_and_required_fees()
+            .is_none()
+        {
+            return;
+        }
+
+        match self.send_bundle(state, 0, true).await {
+            Ok(SendBundleAttemptResult::Success) => {
+                info!("Pipelined bundle sent successfully");
+            }
+            Ok(SendBundleAttemptResult::NoOperationsInitially)
+            | Ok(SendBundleAttemptResult::NoOperationsAfterFeeFilter)
+            | Ok(SendBundleAttemptResult::NoOperationsAfterSimulation) => {
+                debug!("No operations available for pipelined bundle");
+            }
+            Ok(SendBundleAttemptResult::ConditionNotMet) => {
+                info!("Condition not met for pipelined bundle, notifying proposer");
+                self.proposer.notify_condition_not_met();
+            }
+            // Any nonce change is picked up on the next tracker update, and fee related
+            // failures are retried on the next block.
+            Ok(_) => {
+                info!("Pipelined bundle not sent, retrying on next block");
+            }
+            Err(error) => {
+                error!("Pipelined bundle send error {error:?}");
+                self.metrics.bundle_txns_failed.increment(1);
+            }
+        }
+    }
+
     async fn handle_cancelling_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
@@ -481,38 +597,55 @@ where
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         inner: CancelPendingState,
-        tracker_update: Option<TrackerUpdate>,
+        tracker_updates: Vec<TrackerUpdate>,
     ) -> anyhow::Result<()> {
-        // check for transaction update
-        if let Some(update) = tracker_update {
-            match update {
-                TrackerUpdate::Mined {
-                    gas_used,
-                    gas_price,
-                    ..
-                } => {
-                    // mined
-                    let fee = gas_used.zip(gas_price).map(|(used, price)| used * price);
-                    info!("Cancellation transaction mined. Price (wei) {fee:?}");
-                    self.metrics.cancellation_txns_mined.increment(1);
-                    if let Some(fee) = fee {
-                        self.metrics
-                            .cancellation_txns_total_fee
-                            .increment(fee as u64);
-                    };
-                }
-                TrackerUpdate::LatestTxDropped { .. } => {
-                    // If a cancellation gets dropped, move to bundling state as there is no
-                    // longer a pending transaction
-                    info!("Cancellation transaction dropped, starting new bundle attempt");
-                }
-                TrackerUpdate::NonceUsedForOtherTx { .. } => {
-                    // If a nonce is used externally, move to bundling state as there is no longer
-                    // a pending transaction
-                    info!("Nonce used externally while cancelling, starting new bundle attempt");
+        // check for transaction updates
+        if !tracker_updates.is_empty() {
+            let mut all_mined = true;
+            for update in tracker_updates {
+                match update {
+                    TrackerUpdate::Mined {
+                        gas_used,
+                        gas_price,
+                        ..
+                    } => {
+                        // mined
+                        let fee = gas_used.zip(gas_price).map(|(used, price)| used * price);
+                        info!("Cancellation transaction mined. Price (wei) {fee:?}");
+                        self.metrics.cancellation_txns_mined.increment(1);
+                        if let Some(fee) = fee {
+                            self.metrics
+                                .cancellation_txns_total_fee
+                                .increment(fee as u64);
+                        };
+                    }
+                    TrackerUpdate::LatestTxDropped { .. } => {
+                        // If a cancellation gets dropped, move to bundling state as there is no
+                        // longer a pending transaction
+                        info!("Cancellation transaction dropped, starting new bundle attempt");
+                        all_mined = false;
+                    }
+                    TrackerUpdate::NonceUsedForOtherTx { .. } => {
+                        // If a nonce is used externally, move to bundling state as there is no longer
+                        // a pending transaction
+                        info!(
+                            "Nonce used externally while cancelling, starting new bundle attempt"
+                        );
+                        all_mined = false;
+                    }
                 }
             }
-            state.reset();
+
+            if all_mined && !state.transaction_tracker.in_flight_nonces().is_empty() {
+                // cancellations of later pipelined nonces are still pending
+                info!("Cancellation transaction mined, waiting for cancellations of later nonces");
+                state.update(InnerState::CancelPending(CancelPendingState {
+                    until: state.block_number() + self.settings.max_blocks_to_wait_for_mine,
+                    fee_increase_count: inner.fee_increase_count,
+                }));
+            } else {
+                state.reset();
+            }
         } else if state.block_number() >= inner.until {
             if inner.fee_increase_count >= self.settings.max_cancellation_fee_increases {
                 // abandon the cancellation
@@ -539,16 +672,34 @@ where
     ///  - There are no ops available to bundle initially.
     ///  - The gas fees are high enough that the bundle is empty because there
     ///    are no ops that meet the fee requirements.
+    ///
+    /// If `pipelined` is set, the bundle is sent with the tracker's pipelined nonce
+    /// rather than replacing the current transaction.
     async fn send_bundle<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         fee_increase_count: u64,
+        pipelined: bool,
     ) -> anyhow::Result<SendBundleAttemptResult> {
-        let (nonce, required_fees) = state.transaction_tracker.get_nonce_and_required_fees()?;
+        let (nonce, required_fees) = if pipelined {
+            state
+                .transaction_tracker
+                .get_pipelined_nonce_and_required_fees()
+                .context("pipelined bundle should have a nonce available")?
+        } else {
+            state.transaction_tracker.get_nonce_and_required_fees()?
+        };
+        if self.pipelining_enabled() {
+            self.update_in_flight_bundles(&state.transaction_tracker, nonce);
+        }
         let _timer_guard = rundler_utils::guard_timer::CustomTimerGuard::new(
             self.metrics.bundle_build_time_ms.clone(),
         );
 
+        // The beneficiary follows the signer, so that the key paying for the bundle
+        // transaction also receives its revenue
+        let beneficiary = state.transaction_tracker.address();
+        self.proposer.set_beneficiary(beneficiary);
         let bundle = match self
             .proposer
             .make_bundle(required_fees, fee_increase_count > 0)
@@ -564,7 +715,7 @@ where
             Err(e) => bail!("Failed to make bundle: {e:?}"),
         };
 
-        let Some(bundle_tx) = self.get_bundle_tx(nonce, bundle).await? else {
+        let Some(bundle_tx) = self.get_bundle_tx(nonce, bundle, beneficiary).await? else {
             self.emit(BuilderEvent::formed_bundle(
                 self.builder_index,
                 None,
@@ -578,8 +729,13 @@ where
             tx,
             expected_storage,
             op_hashes,
+            senders,
         } = bundle_tx;
 
+        if !self.lease.is_held() {
+            return Ok(SendBundleAttemptResult::LeaseLost);
+        }
+
         self.metrics.bundle_txns_sent.increment(1);
 
         let send_result = state
@@ -589,6 +745,10 @@ where
 
         match send_result {
             Ok(tx_hash) => {
+                if self.pipelining_enabled() {
+                    self.in_flight_senders.insert(nonce, senders);
+                    self.in_flight_txs.insert(nonce, (tx_hash, tx.clone()));
+                }
                 self.emit(BuilderEvent::formed_bundle(
                     self.builder_index,
                     Some(BundleTxDetails {
@@ -641,6 +801,7 @@ where
         &mut self,
         nonce: u64,
         bundle: Bundle<UO>,
+        beneficiary: Address,
     ) -> anyhow::Result<Option<BundleTx>> {
         let remove_ops_future = async {
             if bundle.rejected_ops.is_empty() {
@@ -683,9 +844,10 @@ where
             bundle.entity_updates.len()
         );
         let op_hashes: Vec<_> = bundle.iter_ops().map(|op| self.op_hash(op)).collect();
+        let senders = bundle.iter_ops().map(|op| op.sender()).collect();
         let mut tx = self.entry_point.get_send_bundle_transaction(
             bundle.ops_per_aggregator,
-            self.beneficiary,
+            beneficiary,
             bundle.gas_estimate,
             bundle.gas_fees,
         );
@@ -694,9 +856,39 @@ where
             tx,
             expected_storage: bundle.expected_storage,
             op_hashes,
+            senders,
         }))
     }
 
+    fn pipelining_enabled(&self) -> bool {
+        self.settings.max_in_flight_transactions > 1
+    }
+
+    // Drops the bundles no longer in flight and passes the senders of the other in-flight
+    // bundles to the proposer, ops in the bundle with `nonce` can be replaced. The
+    // transactions with nonces before `nonce` are passed as well, as they land ahead of
+    // the next bundle.
+    fn update_in_flight_bundles(&mut self, tracker: &T, nonce: u64) {
+        let in_flight_nonces = tracker.in_flight_nonces();
+        self.in_flight_senders
+            .retain(|n, _| in_flight_nonces.contains(n));
+        self.in_flight_txs
+            .retain(|n, _| in_flight_nonces.contains(n));
+        let txs = self
+            .in_flight_txs
+            .range(..nonce)
+            .map(|(_, tx)| tx.clone())
+            .collect();
+        self.proposer.set_in_flight_transactions(txs);
+        let senders = self
+            .in_flight_senders
+            .iter()
+            .filter(|(&n, _)| n != nonce)
+            .flat_map(|(_, senders)| senders.iter().copied())
+            .collect();
+        self.proposer.set_in_flight_senders(senders);
+    }
+
     async fn remove_ops_from_pool(&self, ops: &[UO]) -> anyhow::Result<()> {
         self.pool
             .remove_ops(
@@ -821,7 +1013,7 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
      * Helpers
      */
 
-    async fn wait_for_trigger(&mut self) -> anyhow::Result<Option<TrackerUpdate>> {
+    async fn wait_for_trigger(&mut self) -> anyhow::Result<Vec<TrackerUpdate>> {
         if self.requires_reset {
             self.transaction_tracker.reset().await;
             self.requires_reset = false;
@@ -830,7 +1022,7 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
         match &self.inner {
             InnerState::Building(s) => {
                 if !s.wait_for_trigger {
-                    return Ok(None);
+                    return Ok(vec![]);
                 }
 
                 self.send_bundle_response = self.trigger.wait_for_trigger().await?;
@@ -846,7 +1038,7 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
                     .await
                     .map_err(|e| anyhow::anyhow!("transaction tracker update error {e:?}"))
             }
-            InnerState::Cancelling(..) => Ok(None),
+            InnerState::Cancelling(..) => Ok(vec![]),
         }
     }
 
@@ -1241,6 +1433,8 @@ struct BuilderMetric {
     cancellation_txns_failed: Counter,
     #[metric(describe = "the count of state machine errors.")]
     state_machine_errors: Counter,
+    #[metric(describe = "the count of times the signer lease was lost.")]
+    signer_lease_lost: Counter,
     #[metric(describe = "the timespan a bundle is build.")]
     bundle_build_time_ms: Histogram,
 }
@@ -1266,12 +1460,13 @@ mod tests {
     use rundler_types::{
         chain::ChainSpec, pool::MockPool, v0_6::UserOperation, GasFees, UserOpsPerAggregator,
     };
-    use tokio::sync::{broadcast, mpsc};
+    use tokio::sync::{broadcast, mpsc, watch};
 
     use super::*;
     use crate::{
         bundle_proposer::{Bundle, MockBundleProposer},
         bundle_sender::{BundleSenderImpl, MockTrigger},
+        signer::LeaseStatus,
         transaction_tracker::MockTransactionTracker,
     };
 
@@ -1375,6 +1570,55 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_send_beneficiary_follows_signer() {
+        let Mocks {
+            mut mock_entry_point,
+            mut mock_trigger,
+            ..
+        } = new_mocks();
+        // the signer's address after a failover to another key
+        let signer = Address::random();
+        let mut mock_proposer = MockBundleProposer::new();
+        let mut mock_tracker = MockTransactionTracker::new();
+        mock_tracker.expect_address().return_const(signer);
+
+        add_trigger_no_update_last_block(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+            0,
+        );
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((0, None)));
+
+        // the bundle is made and sent with the signer as its beneficiary
+        mock_proposer
+            .expect_set_beneficiary()
+            .withf(move |&beneficiary| beneficiary == signer)
+            .times(1)
+            .return_const(());
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .withf(move |_, &beneficiary, _, _| beneficiary == signer)
+            .times(1)
+            .returning(|_, _, _, _| TransactionRequest::default());
+        mock_tracker
+            .expect_send_transaction()
+            .returning(|_, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Pending(_)));
+    }
+
     #[tokio::test]
     async fn test_wait_for_mine_success() {
         let Mocks {
@@ -1405,14 +1649,14 @@ mod tests {
             .expect_check_for_update()
             .once()
             .in_sequence(&mut seq)
-            .returning(|| Box::pin(async { Ok(None) }));
+            .returning(|| Box::pin(async { Ok(vec![]) }));
         mock_tracker
             .expect_check_for_update()
             .once()
             .in_sequence(&mut seq)
             .returning(|| {
                 Box::pin(async {
-                    Ok(Some(TrackerUpdate::Mined {
+                    Ok(vec![TrackerUpdate::Mined {
                         block_number: 2,
                         nonce: 0,
                         gas_limit: None,
@@ -1420,7 +1664,7 @@ mod tests {
                         gas_price: None,
                         tx_hash: B256::ZERO,
                         attempt_number: 0,
-                    }))
+                    }])
                 })
             });
 
@@ -1474,7 +1718,7 @@ mod tests {
         mock_tracker
             .expect_check_for_update()
             .times(3)
-            .returning(|| Box::pin(async { Ok(None) }));
+            .returning(|| Box::pin(async { Ok(vec![]) }));
 
         let mut sender = new_sender(mock_proposer, mock_entry_point);
 
@@ -1627,7 +1871,7 @@ mod tests {
         mock_tracker
             .expect_check_for_update()
             .times(3)
-            .returning(|| Box::pin(async { Ok(None) }));
+            .returning(|| Box::pin(async { Ok(vec![]) }));
 
         let mut state = SenderMachineState {
             trigger: mock_trigger,
@@ -1662,6 +1906,58 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_cancel_pending_waits_for_later_nonces() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut Sequence::new(), 2);
+
+        // the cancellation of the current nonce mined, the one of the next nonce is pending
+        mock_tracker.expect_check_for_update().once().returning(|| {
+            Box::pin(async {
+                Ok(vec![TrackerUpdate::Mined {
+                    block_number: 2,
+                    nonce: 0,
+                    gas_limit: None,
+                    gas_used: None,
+                    gas_price: None,
+                    tx_hash: B256::ZERO,
+                    attempt_number: 0,
+                }])
+            })
+        });
+        mock_tracker.expect_in_flight_nonces().returning(|| vec![1]);
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 1,
+            }),
+            requires_reset: false,
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_in_flight_transactions = 2;
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::CancelPending(CancelPendingState {
+                until: 5,
+                fee_increase_count: 1,
+            })
+        ));
+        assert!(!state.requires_reset);
+    }
+
     #[tokio::test]
     async fn test_condition_not_met() {
         let Mocks {
@@ -1728,6 +2024,169 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_lease_lost() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+        } = new_mocks();
+
+        // in-flight transaction is abandoned, not replaced or cancelled
+        mock_tracker.expect_abandon().once().return_const(());
+        mock_tracker.expect_check_for_update().never();
+
+        let (lease_tx, lease_rx) = watch::channel(LeaseStatus::Lost);
+        let mut sender =
+            new_sender_with_lease(mock_proposer, mock_entry_point, LeaseHandle::new(lease_rx));
+
+        // start in pending state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+
+        tokio::spawn(async move {
+            lease_tx.send_replace(LeaseStatus::Held(0));
+        });
+
+        // step waits for the lease to be re-acquired and resets
+        sender.step_state(&mut state).await.unwrap();
+        assert!(state.requires_reset);
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 0,
+                underpriced_info: None,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_send() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut Sequence::new(), 1);
+        mock_tracker
+            .expect_check_for_update()
+            .returning(|| Box::pin(async { Ok(vec![]) }));
+
+        // room for a second transaction behind the pending one
+        mock_tracker
+            .expect_get_pipelined_nonce_and_required_fees()
+            .returning(|| Some((1, None)));
+        mock_tracker.expect_in_flight_nonces().returning(|| vec![0]);
+        mock_proposer
+            .expect_set_in_flight_senders()
+            .once()
+            .return_const(());
+        mock_proposer
+            .expect_set_in_flight_transactions()
+            .withf(|txs| txs.is_empty())
+            .once()
+            .return_const(());
+
+        // new bundle, not a replacement
+        mock_proposer
+            .expect_make_bundle()
+            .withf(|fees, is_replacement| fees.is_none() && !is_replacement)
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TransactionRequest::default());
+        mock_tracker
+            .expect_send_transaction()
+            .withf(|tx, _| tx.nonce == Some(1))
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_in_flight_transactions = 2;
+
+        // start in pending state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+
+        // stays pending on the first transaction
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState { until: 3, .. })
+        ));
+        assert_eq!(
+            sender.in_flight_senders.get(&1),
+            Some(&HashSet::from([Address::ZERO]))
+        );
+        assert_eq!(
+            sender.in_flight_txs.get(&1).map(|(_, tx)| tx.nonce),
+            Some(Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_transaction_becomes_latest() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 2,
+            block_hash: B256::ZERO,
+        });
+
+        // the previous transaction mined and the pipelined one is now the latest
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((1, Some(GasFees::default()))));
+        mock_tracker.expect_in_flight_nonces().returning(|| vec![1]);
+        mock_proposer.expect_make_bundle().never();
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_in_flight_transactions = 2;
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        state.update(InnerState::Building(BuildingState {
+            wait_for_trigger: false,
+            fee_increase_count: 0,
+            underpriced_info: None,
+        }));
+
+        // waits for it to mine instead of replacing it
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 5,
+                fee_increase_count: 0,
+            })
+        ));
+    }
+
     struct Mocks {
         mock_proposer: MockBundleProposer,
         mock_entry_point: MockEntryPointV0_6,
@@ -1741,10 +2200,17 @@ mod tests {
             .expect_address()
             .return_const(Address::default());
 
+        let mut mock_proposer = MockBundleProposer::new();
+        mock_proposer.expect_set_beneficiary().return_const(());
+        let mut mock_tracker = MockTransactionTracker::new();
+        mock_tracker
+            .expect_address()
+            .return_const(Address::default());
+
         Mocks {
-            mock_proposer: MockBundleProposer::new(),
+            mock_proposer,
             mock_entry_point,
-            mock_tracker: MockTransactionTracker::new(),
+            mock_tracker,
             mock_trigger: MockTrigger::new(),
         }
     }
@@ -1758,20 +2224,39 @@ mod tests {
         MockEntryPointV0_6,
         MockTransactionTracker,
         MockPool,
+    > {
+        new_sender_with_lease(
+            mock_proposer,
+            mock_entry_point,
+            LeaseHandle::new(watch::channel(LeaseStatus::Held(0)).1),
+        )
+    }
+
+    fn new_sender_with_lease(
+        mock_proposer: MockBundleProposer,
+        mock_entry_point: MockEntryPointV0_6,
+        lease: LeaseHandle,
+    ) -> BundleSenderImpl<
+        UserOperation,
+        MockBundleProposer,
+        MockEntryPointV0_6,
+        MockTransactionTracker,
+        MockPool,
     > {
         BundleSenderImpl::new(
             0,
             mpsc::channel(1000).1,
             ChainSpec::default(),
-            Address::default(),
             mock_proposer,
             mock_entry_point,
             MockTransactionTracker::new(),
+            lease,
             MockPool::new(),
             Settings {
                 max_cancellation_fee_increases: 3,
                 max_blocks_to_wait_for_mine: 3,
                 max_replacement_underpriced_blocks: 3,
+                max_in_flight_transactions: 1,
             },
             broadcast::channel(1000).0,
         )
@@ -1790,7 +2275,7 @@ mod tests {
             .returning(move || Box::pin(async move { Ok(None) }));
         mock_tracker
             .expect_check_for_update()
-            .returning(|| Box::pin(async { Ok(None) }));
+            .returning(|| Box::pin(async { Ok(vec![]) }));
         mock_trigger
             .expect_last_block()
             .once()
@@ -18,9 +18,11 @@ use rundler_provider::TransactionRequest;
 use rundler_sim::SimulationError;
 use rundler_types::{GasFees, ValidTimeRange};
 use rundler_utils::strs;
+use serde::{Serialize, Serializer};
 
 /// Builder event
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BuilderEvent {
     /// Builder index that emitted the event
     pub builder_index: u64,
@@ -104,7 +106,12 @@ impl BuilderEvent {
 }
 
 /// BuilderEventKind
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
 pub enum BuilderEventKind {
     /// A bundle was formed
     FormedBundle {
@@ -155,7 +162,8 @@ pub enum BuilderEventKind {
 }
 
 /// Details of a bundle transaction
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BundleTxDetails {
     /// Transaction hash
     pub tx_hash: B256,
@@ -166,7 +174,12 @@ pub struct BundleTxDetails {
 }
 
 /// Reason for skipping an operation in a bundle
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
 pub enum SkipReason {
     /// Operation accessed another sender account included earlier in the bundle
     AccessedOtherSender { other_sender: Address },
@@ -186,23 +199,58 @@ pub enum SkipReason {
     },
     /// Bundle ran out of space by gas limit to include the operation
     GasLimit,
+    /// The nonce of the operation's EIP-7702 authorization is ahead of the sender's nonce
+    AuthorizationNonceTooHigh {
+        account_nonce: u64,
+        authorization_nonce: u64,
+    },
+    /// Another operation in the bundle has a different EIP-7702 authorization for the same sender
+    ConflictingAuthorization,
+    /// The operation with the previous nonce from the same sender is not in the bundle
+    PreviousNonceNotIncluded,
+    /// Another operation of the operation's atomic group is not in the bundle
+    AtomicGroupNotIncluded,
+    /// Operation was dropped to bring the bundle up to the minimum profit margin. The
+    /// expected revenue and cost of the operation are in wei.
+    Unprofitable { revenue: u128, cost: u128 },
+    /// The sender has an operation in a bundle transaction that has not yet mined
+    SenderInFlight,
     /// Other reason, typically internal errors
     Other { reason: Arc<String> },
 }
 
 /// Reason for rejecting an operation from a bundle
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
 pub enum OpRejectionReason {
     /// Operation failed its 2nd validation simulation attempt
-    FailedRevalidation { error: SimulationError },
+    FailedRevalidation {
+        #[serde(serialize_with = "serialize_simulation_error")]
+        error: SimulationError,
+    },
     /// Operation reverted during bundle formation simulation with message
     FailedInBundle { message: Arc<String> },
+    /// Operation reverted when the bundle was simulated on top of the pending state with
+    /// message. `pending_tx_hashes` are the hashes of the pending transactions that may have
+    /// caused the revert: the builder's in-flight bundles and the mempool transactions from
+    /// or to the operation's sender or paymaster.
+    FailedInPendingBundle {
+        message: Arc<String>,
+        pending_tx_hashes: Arc<Vec<B256>>,
+    },
     /// Operation's storage slot condition was not met
     ConditionNotMet(ConditionNotMetReason),
+    /// Operation's EIP-7702 authorization can't be applied in any bundle
+    InvalidAuthorization { reason: Arc<String> },
 }
 
 /// Reason for a condition not being met
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConditionNotMetReason {
     pub address: Address,
     pub slot: B256,
@@ -210,6 +258,15 @@ pub struct ConditionNotMetReason {
     pub actual: B256,
 }
 
+/// Simulation errors are exported as their message, the entity infos are only used
+/// internally to apply reputation penalties.
+fn serialize_simulation_error<S: Serializer>(
+    error: &SimulationError,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&error.violation_error)
+}
+
 impl Display for BuilderEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.kind {
@@ -306,3 +363,75 @@ impl Display for BuilderEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_sim::ViolationError;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_serialize_skipped_op() {
+        let event = BuilderEvent::skipped_op(
+            1,
+            B256::ZERO,
+            SkipReason::InsufficientFees {
+                required_fees: GasFees {
+                    max_fee_per_gas: 2,
+                    max_priority_fee_per_gas: 1,
+                },
+                actual_fees: GasFees::default(),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "builderIndex": 1,
+                "kind": {
+                    "type": "skippedOp",
+                    "opHash": B256::ZERO,
+                    "reason": {
+                        "type": "insufficientFees",
+                        "requiredFees": {"maxFeePerGas": 2, "maxPriorityFeePerGas": 1},
+                        "actualFees": {"maxFeePerGas": 0, "maxPriorityFeePerGas": 0},
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_rejection_reason() {
+        let reason = OpRejectionReason::FailedRevalidation {
+            error: ViolationError::Other(anyhow::anyhow!("boom")).into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&reason).unwrap(),
+            json!({"type": "failedRevalidation", "error": "boom"})
+        );
+
+        let reason = OpRejectionReason::ConditionNotMet(ConditionNotMetReason {
+            address: Address::ZERO,
+            slot: B256::ZERO,
+            expected: B256::ZERO,
+            actual: B256::ZERO,
+        });
+        let value = serde_json::to_value(&reason).unwrap();
+        assert_eq!(value["type"], "conditionNotMet");
+        assert_eq!(value["slot"], json!(B256::ZERO));
+
+        let reason = OpRejectionReason::FailedInPendingBundle {
+            message: Arc::new("AA25 invalid account nonce".to_string()),
+            pending_tx_hashes: Arc::new(vec![B256::ZERO]),
+        };
+        assert_eq!(
+            serde_json::to_value(&reason).unwrap(),
+            json!({
+                "type": "failedInPendingBundle",
+                "message": "AA25 invalid account nonce",
+                "pendingTxHashes": [B256::ZERO],
+            })
+        );
+    }
+}
@@ -19,6 +19,8 @@
 ))]
 //! Bundle builder implementation for the Rundler.
 
+mod bls;
+
 mod bundle_proposer;
 mod bundle_sender;
 
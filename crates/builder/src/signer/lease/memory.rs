@@ -0,0 +1,113 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{Lease, LeaseBackend};
+
+/// An in-memory lease backend.
+///
+/// Only guards against collisions between bundle senders in this process. Used when no
+/// shared backend is configured.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryLeaseBackend {
+    leases: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+    next_token: AtomicU64,
+}
+
+#[async_trait::async_trait]
+impl LeaseBackend for MemoryLeaseBackend {
+    async fn acquire(&self, key: &str, ttl: Duration) -> anyhow::Result<Option<Lease>> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock().unwrap();
+        if let Some((_, expires_at)) = leases.get(key) {
+            if *expires_at > now {
+                return Ok(None);
+            }
+        }
+
+        let token = self
+            .next_token
+            .fetch_add(1, Ordering::Relaxed)
+            .to_be_bytes()
+            .to_vec();
+        leases.insert(key.to_string(), (token.clone(), now + ttl));
+
+        Ok(Some(Lease {
+            key: key.to_string(),
+            token,
+        }))
+    }
+
+    async fn extend(&self, lease: &Lease, ttl: Duration) -> anyhow::Result<bool> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock().unwrap();
+        match leases.get_mut(&lease.key) {
+            Some((token, expires_at)) if *token == lease.token && *expires_at > now => {
+                *expires_at = now + ttl;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_held() {
+        let backend = MemoryLeaseBackend::default();
+        let ttl = Duration::from_secs(60);
+
+        let lease = backend.acquire("key", ttl).await.unwrap().unwrap();
+        assert!(backend.acquire("key", ttl).await.unwrap().is_none());
+        assert!(backend.acquire("other", ttl).await.unwrap().is_some());
+        assert!(backend.extend(&lease, ttl).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_expired() {
+        let backend = MemoryLeaseBackend::default();
+
+        let lease = backend
+            .acquire("key", Duration::ZERO)
+            .await
+            .unwrap()
+            .unwrap();
+        let new_lease = backend
+            .acquire("key", Duration::from_secs(60))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(lease.token, new_lease.token);
+        // the stale holder must not be able to extend the new lease
+        assert!(!backend
+            .extend(&lease, Duration::from_secs(60))
+            .await
+            .unwrap());
+        assert!(backend
+            .extend(&new_lease, Duration::from_secs(60))
+            .await
+            .unwrap());
+    }
+}
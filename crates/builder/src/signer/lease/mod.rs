@@ -0,0 +1,382 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+mod memory;
+mod redis;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+pub(crate) use memory::*;
+use metrics::Counter;
+use metrics_derive::Metrics;
+#[cfg(test)]
+use mockall::automock;
+pub(crate) use redis::*;
+use rundler_task::TaskSpawner;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, error, info, warn};
+
+/// A lease held on a signer key.
+///
+/// The token identifies this holder of the lease, so that a lease that has expired
+/// and been taken by another holder cannot be extended by accident.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Lease {
+    pub(crate) key: String,
+    pub(crate) token: Vec<u8>,
+}
+
+/// Backend used to lease signer keys.
+///
+/// Implementations must guarantee that at most one holder owns the lease for a key
+/// at any time.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub(crate) trait LeaseBackend: Send + Sync + 'static {
+    /// Attempts to acquire the lease for `key`.
+    ///
+    /// Returns `None` if the lease is currently held by someone else.
+    async fn acquire(&self, key: &str, ttl: Duration) -> anyhow::Result<Option<Lease>>;
+
+    /// Extends a held lease by `ttl`.
+    ///
+    /// Returns `false` if the lease is no longer held by the caller.
+    async fn extend(&self, lease: &Lease, ttl: Duration) -> anyhow::Result<bool>;
+}
+
+/// Status of a signer lease
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LeaseStatus {
+    /// The lease is held on the key at the given index, the signer for that key may be used
+    Held(usize),
+    /// The lease was lost, no signer may be used until a lease on a free key is acquired
+    Lost,
+}
+
+/// A handle used to observe the status of a signer lease.
+#[derive(Clone, Debug)]
+pub(crate) struct LeaseHandle {
+    status: watch::Receiver<LeaseStatus>,
+}
+
+impl LeaseHandle {
+    pub(crate) fn new(status: watch::Receiver<LeaseStatus>) -> Self {
+        Self { status }
+    }
+
+    /// Returns the current status of the lease
+    pub(crate) fn status(&self) -> LeaseStatus {
+        *self.status.borrow()
+    }
+
+    /// Returns true if the lease is currently held
+    pub(crate) fn is_held(&self) -> bool {
+        matches!(self.status(), LeaseStatus::Held(_))
+    }
+
+    /// Waits until the lease is held
+    pub(crate) async fn wait_for_lease(&mut self) -> anyhow::Result<()> {
+        self.status
+            .wait_for(|s| matches!(s, LeaseStatus::Held(_)))
+            .await
+            .context("lease status channel closed")?;
+        Ok(())
+    }
+
+    /// Waits for the status of the lease to change, returning the new status
+    pub(crate) async fn changed(&mut self) -> anyhow::Result<LeaseStatus> {
+        self.status
+            .changed()
+            .await
+            .context("lease status channel closed")?;
+        Ok(*self.status.borrow_and_update())
+    }
+}
+
+/// Leases signer keys to bundle senders.
+///
+/// Every key that a signer can be created from, local or KMS, must be leased before
+/// use so that no two bundle senders, in this process or any other sharing the
+/// backend, sign with the same key at the same time.
+#[derive(Clone)]
+pub(crate) struct SignerLeaser {
+    backend: Arc<dyn LeaseBackend>,
+    chain_id: u64,
+    ttl: Duration,
+}
+
+impl SignerLeaser {
+    pub(crate) fn new(backend: Arc<dyn LeaseBackend>, chain_id: u64, ttl_millis: u64) -> Self {
+        Self {
+            backend,
+            chain_id,
+            ttl: Duration::from_millis(ttl_millis),
+        }
+    }
+
+    /// Attempts to lease the first free key in `key_ids`, in order.
+    ///
+    /// On success returns the index of the leased key along with a handle to observe the
+    /// lease. A task is spawned to keep the lease alive. If the lease is lost the task
+    /// re-acquires it, or fails over to any other free key in `key_ids`.
+    ///
+    /// Returns `None` if all keys are leased by other holders.
+    pub(crate) async fn lease_any<T: TaskSpawner>(
+        &self,
+        task_spawner: &T,
+        key_ids: &[String],
+    ) -> anyhow::Result<Option<(usize, LeaseHandle)>> {
+        let lease_keys = key_ids
+            .iter()
+            .map(|key_id| format!("{}:{key_id}", self.chain_id))
+            .collect::<Vec<_>>();
+
+        for (i, lease_key) in lease_keys.iter().enumerate() {
+            match self.backend.acquire(lease_key, self.ttl).await? {
+                Some(lease) => {
+                    info!("Leased signer key {lease_key}");
+                    let (tx, rx) = watch::channel(LeaseStatus::Held(i));
+                    task_spawner.spawn_critical(
+                        "signer lease loop",
+                        Box::pin(Self::lease_loop(
+                            self.backend.clone(),
+                            lease_keys,
+                            i,
+                            lease,
+                            self.ttl,
+                            tx,
+                        )),
+                    );
+                    return Ok(Some((i, LeaseHandle::new(rx))));
+                }
+                None => {
+                    debug!("Signer key {lease_key} is leased elsewhere, trying next key");
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn lease_loop(
+        backend: Arc<dyn LeaseBackend>,
+        lease_keys: Vec<String>,
+        mut key_index: usize,
+        mut lease: Lease,
+        ttl: Duration,
+        status_tx: watch::Sender<LeaseStatus>,
+    ) {
+        let mut metrics = LeaseMetrics::new_with_labels(&[("key", lease.key.clone())]);
+        let mut held = true;
+
+        loop {
+            sleep(ttl / 10).await;
+
+            if held {
+                match backend.extend(&lease, ttl).await {
+                    Ok(true) => {
+                        debug!("extended lease on {}", lease.key);
+                        continue;
+                    }
+                    Ok(false) => {
+                        error!("lease on {} was taken by another holder", lease.key);
+                    }
+                    Err(e) => {
+                        // We cannot be sure that the lease is still held, assume it is lost
+                        error!("could not extend lease on {}: {e:?}", lease.key);
+                    }
+                }
+                held = false;
+                metrics.leases_lost.increment(1);
+                status_tx.send_replace(LeaseStatus::Lost);
+                continue;
+            }
+
+            // Prefer the lost key so that the signer does not need to change, otherwise
+            // fail over to the first other free key.
+            let candidates =
+                std::iter::once(key_index).chain((0..lease_keys.len()).filter(|i| *i != key_index));
+            for i in candidates {
+                match backend.acquire(&lease_keys[i], ttl).await {
+                    Ok(Some(l)) => {
+                        if i == key_index {
+                            info!("re-acquired lease on {}", l.key);
+                            metrics.leases_reacquired.increment(1);
+                        } else {
+                            info!("failed over from lease on {} to {}", lease.key, l.key);
+                            metrics = LeaseMetrics::new_with_labels(&[("key", l.key.clone())]);
+                            metrics.leases_failed_over.increment(1);
+                        }
+                        lease = l;
+                        key_index = i;
+                        held = true;
+                        status_tx.send_replace(LeaseStatus::Held(i));
+                        break;
+                    }
+                    Ok(None) => {
+                        debug!("could not lease {}, held elsewhere", lease_keys[i]);
+                    }
+                    Err(e) => {
+                        warn!("could not lease {}: {e:?}", lease_keys[i]);
+                    }
+                }
+            }
+
+            if !held {
+                warn!(
+                    "no free signer key to fail over to after losing lease on {}",
+                    lease.key
+                );
+            }
+        }
+    }
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "builder_signer_lease")]
+struct LeaseMetrics {
+    #[metric(describe = "the count of signer leases lost.")]
+    leases_lost: Counter,
+    #[metric(describe = "the count of signer leases re-acquired after being lost.")]
+    leases_reacquired: Counter,
+    #[metric(describe = "the count of failovers to a free signer key after a lease was lost.")]
+    leases_failed_over: Counter,
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_tasks::TaskManager;
+
+    use super::*;
+
+    fn keys() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_lease_fails_over_to_free_key() {
+        let tm = TaskManager::current();
+        let ts = tm.executor();
+        let leaser = SignerLeaser::new(Arc::new(MemoryLeaseBackend::default()), 1, 60_000);
+
+        let (i, handle) = leaser.lease_any(&ts, &keys()).await.unwrap().unwrap();
+        assert_eq!(i, 0);
+        assert!(handle.is_held());
+
+        let (i, _) = leaser.lease_any(&ts, &keys()).await.unwrap().unwrap();
+        assert_eq!(i, 1);
+
+        assert!(leaser.lease_any(&ts, &keys()).await.unwrap().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_lost_and_reacquired() {
+        let mut backend = MockLeaseBackend::new();
+        backend.expect_acquire().times(1).returning(|k, _| {
+            Ok(Some(Lease {
+                key: k.to_string(),
+                token: vec![1],
+            }))
+        });
+        backend.expect_extend().times(1).returning(|_, _| Ok(false));
+        backend.expect_acquire().returning(|k, _| {
+            Ok(Some(Lease {
+                key: k.to_string(),
+                token: vec![2],
+            }))
+        });
+        backend.expect_extend().returning(|_, _| Ok(true));
+
+        let tm = TaskManager::current();
+        let leaser = SignerLeaser::new(Arc::new(backend), 1, 1_000);
+        let (_, mut handle) = leaser
+            .lease_any(&tm.executor(), &keys())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(handle.is_held());
+
+        handle
+            .status
+            .wait_for(|s| *s == LeaseStatus::Lost)
+            .await
+            .unwrap();
+        assert!(!handle.is_held());
+
+        handle.wait_for_lease().await.unwrap();
+        assert_eq!(handle.status(), LeaseStatus::Held(0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_lost_fails_over_to_free_key() {
+        let mut backend = MockLeaseBackend::new();
+        backend
+            .expect_acquire()
+            .withf(|k, _| k == "1:a")
+            .times(1)
+            .returning(|k, _| {
+                Ok(Some(Lease {
+                    key: k.to_string(),
+                    token: vec![1],
+                }))
+            });
+        backend
+            .expect_extend()
+            .withf(|l, _| l.key == "1:a")
+            .times(1)
+            .returning(|_, _| Ok(false));
+        backend
+            .expect_acquire()
+            .withf(|k, _| k == "1:a")
+            .returning(|_, _| Ok(None));
+        backend
+            .expect_acquire()
+            .withf(|k, _| k == "1:b")
+            .returning(|k, _| {
+                Ok(Some(Lease {
+                    key: k.to_string(),
+                    token: vec![2],
+                }))
+            });
+        backend
+            .expect_extend()
+            .withf(|l, _| l.key == "1:b")
+            .returning(|_, _| Ok(true));
+
+        let tm = TaskManager::current();
+        let leaser = SignerLeaser::new(Arc::new(backend), 1, 1_000);
+        let (i, mut handle) = leaser
+            .lease_any(&tm.executor(), &keys())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(i, 0);
+
+        assert_eq!(handle.changed().await.unwrap(), LeaseStatus::Lost);
+        assert_eq!(handle.changed().await.unwrap(), LeaseStatus::Held(1));
+    }
+
+    #[tokio::test]
+    async fn test_lease_backend_error() {
+        let mut backend = MockLeaseBackend::new();
+        backend
+            .expect_acquire()
+            .times(1)
+            .returning(|_, _| Err(anyhow::anyhow!("connection refused")));
+
+        let tm = TaskManager::current();
+        let leaser = SignerLeaser::new(Arc::new(backend), 1, 1_000);
+        assert!(leaser.lease_any(&tm.executor(), &keys()).await.is_err());
+    }
+}
@@ -0,0 +1,69 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use rslock::{Lock, LockError, LockManager};
+use tracing::debug;
+
+use super::{Lease, LeaseBackend};
+
+/// A Redis lease backend.
+///
+/// Guards against collisions between all bundle senders sharing the Redis instance.
+pub(crate) struct RedisLeaseBackend {
+    lm: LockManager,
+}
+
+impl RedisLeaseBackend {
+    pub(crate) fn new(redis_uri: String) -> Self {
+        Self {
+            lm: LockManager::new(vec![redis_uri]),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LeaseBackend for RedisLeaseBackend {
+    async fn acquire(&self, key: &str, ttl: Duration) -> anyhow::Result<Option<Lease>> {
+        match self.lm.lock(key.as_bytes(), ttl).await {
+            Ok(lock) => Ok(Some(Lease {
+                key: key.to_string(),
+                token: lock.val,
+            })),
+            // rslock also reports unreachable servers as unavailable, these are retried
+            // the same as a lease held elsewhere.
+            Err(LockError::Unavailable) => {
+                debug!("could not lock {key}, unavailable");
+                Ok(None)
+            }
+            Err(e) => Err(e).context(format!("could not lock {key}")),
+        }
+    }
+
+    async fn extend(&self, lease: &Lease, ttl: Duration) -> anyhow::Result<bool> {
+        let lock = Lock {
+            resource: lease.key.as_bytes().to_vec(),
+            val: lease.token.clone(),
+            validity_time: 0,
+            lock_manager: &self.lm,
+        };
+
+        match self.lm.extend(&lock, ttl).await {
+            Ok(_) => Ok(true),
+            Err(LockError::Unavailable) => Ok(false),
+            Err(e) => Err(e).context(format!("could not extend lock {}", lease.key)),
+        }
+    }
+}
@@ -14,8 +14,6 @@
 use alloy_signer::Signer as _;
 use alloy_signer_local::PrivateKeySigner;
 use anyhow::Context;
-use rundler_provider::EvmProvider;
-use rundler_task::TaskSpawner;
 /// A local signer handle
 #[derive(Debug)]
 pub(crate) struct LocalSigner {
@@ -23,21 +21,11 @@ pub(crate) struct LocalSigner {
 }
 
 impl LocalSigner {
-    pub(crate) async fn connect<P: EvmProvider + 'static, T: TaskSpawner>(
-        task_spawner: &T,
-        provider: P,
-        chain_id: u64,
-        private_key: String,
-    ) -> anyhow::Result<Self> {
+    pub(crate) async fn connect(chain_id: u64, private_key: String) -> anyhow::Result<Self> {
         let signer = private_key
             .parse::<PrivateKeySigner>()
             .context("should create signer")?;
 
-        task_spawner.spawn(Box::pin(super::monitor_account_balance(
-            signer.address(),
-            provider,
-        )));
-
         Ok(Self {
             signer: signer.with_chain_id(Some(chain_id)),
         })
@@ -12,14 +12,18 @@
 // If not, see https://www.gnu.org/licenses/.
 
 mod aws;
+mod lease;
 mod local;
 
+use std::sync::{Arc, RwLock};
+
 use alloy_consensus::{SignableTransaction, TxEnvelope, TypedTransaction};
 use alloy_eips::eip2718::Encodable2718;
 use alloy_primitives::{Address, Bytes, B256};
 use alloy_signer::{Signature, Signer as _};
 use anyhow::{bail, Context};
 pub(crate) use aws::*;
+pub(crate) use lease::*;
 pub(crate) use local::*;
 use metrics::Gauge;
 use metrics_derive::Metrics;
@@ -150,3 +154,48 @@ impl Signer for BundlerSigner {
         }
     }
 }
+
+/// A `Signer` for a leased key, which is swapped for the signer of another key when
+/// the lease fails over.
+#[derive(Clone, Debug)]
+pub(crate) struct LeasedSigner {
+    inner: Arc<RwLock<Arc<BundlerSigner>>>,
+}
+
+impl LeasedSigner {
+    pub(crate) fn new(signer: BundlerSigner) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(signer))),
+        }
+    }
+
+    /// Replaces the underlying signer, all clones of this signer use the new signer
+    pub(crate) fn swap(&self, signer: BundlerSigner) {
+        *self
+            .inner
+            .write()
+            .expect("signer lock should not be poisoned") = Arc::new(signer);
+    }
+
+    fn current(&self) -> Arc<BundlerSigner> {
+        self.inner
+            .read()
+            .expect("signer lock should not be poisoned")
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for LeasedSigner {
+    fn address(&self) -> Address {
+        self.current().address()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.current().chain_id()
+    }
+
+    async fn sign_hash(&self, hash: &B256) -> anyhow::Result<Signature> {
+        self.current().sign_hash(hash).await
+    }
+}
@@ -11,11 +11,12 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use alloy_primitives::{Address, B256};
+use alloy_signer_local::PrivateKeySigner;
 use anyhow::Context;
-use rundler_provider::{Providers as ProvidersT, ProvidersWithEntryPointT};
+use rundler_provider::{EvmProvider, Providers as ProvidersT, ProvidersWithEntryPointT};
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl},
     simulation::{self, UnsafeSimulator},
@@ -23,14 +24,16 @@ use rundler_sim::{
 };
 use rundler_task::TaskSpawnerExt;
 use rundler_types::{
-    chain::ChainSpec, pool::Pool as PoolT, EntryPointVersion, UserOperation, UserOperationVariant,
+    aggregator::AggregatorConfigs, chain::ChainSpec, pool::Pool as PoolT, EntryPointVersion,
+    UserOperation, UserOperationVariant,
 };
 use rundler_utils::emit::WithEntryPoint;
 use tokio::{
-    sync::{broadcast, mpsc},
+    sync::{broadcast, mpsc, watch},
+    task::JoinHandle,
     time,
 };
-use tracing::info;
+use tracing::{error, info};
 
 use crate::{
     bundle_proposer::{self, BundleProposerImpl, BundleProposerProviders},
@@ -38,7 +41,10 @@ use crate::{
     emit::BuilderEvent,
     sender::TransactionSenderArgs,
     server::{self, LocalBuilderBuilder},
-    signer::{BundlerSigner, KmsSigner, LocalSigner, Signer},
+    signer::{
+        monitor_account_balance, BundlerSigner, KmsSigner, LeaseBackend, LeaseHandle, LeaseStatus,
+        LeasedSigner, LocalSigner, MemoryLeaseBackend, RedisLeaseBackend, Signer, SignerLeaser,
+    },
     transaction_tracker::{self, TransactionTrackerImpl},
 };
 
@@ -57,9 +63,10 @@ pub struct Args {
     /// AWS KMS key ids to use for signing transactions
     /// Only used if private_key is not provided
     pub aws_kms_key_ids: Vec<String>,
-    /// Redis URI for key leasing
+    /// Redis URI for signer key leasing
+    /// If empty, keys are leased in memory and only guarded within this process
     pub redis_uri: String,
-    /// Redis lease TTL in milliseconds
+    /// Signer key lease TTL in milliseconds
     pub redis_lock_ttl_millis: u64,
     /// Maximum bundle size in number of operations
     pub max_bundle_size: u64,
@@ -83,14 +90,25 @@ pub struct Args {
     pub max_cancellation_fee_increases: u64,
     /// Maximum amount of blocks to spend in a replacement underpriced state before moving to cancel
     pub max_replacement_underpriced_blocks: u64,
+    /// Maximum number of bundle transactions, with consecutive nonces, each signer may have
+    /// in flight at once. 1 disables pipelining.
+    pub max_in_flight_transactions: u64,
     /// Address to bind the remote builder server to, if any. If none, no server is starter.
     pub remote_address: Option<SocketAddr>,
     /// Entry points to start builders for
     pub entry_points: Vec<EntryPointBuilderSettings>,
     /// Enable DA tracking
     pub da_gas_tracking_enabled: bool,
+    /// Minimum expected profit margin of a bundle, in percent of its expected cost.
+    /// If `None`, bundles are not checked for profitability.
+    pub min_profit_margin_percent: Option<u32>,
+    /// Simulate bundles on top of the pending state, built from the in-flight bundle
+    /// transactions and the related mempool transactions, instead of the latest block
+    pub pending_simulation: bool,
     /// Provider client timeout
     pub provider_client_timeout_seconds: u64,
+    /// Trusted signature aggregators
+    pub aggregators: AggregatorConfigs,
 }
 
 /// Builder settings for an entrypoint
@@ -145,19 +163,36 @@ where
     /// Spawn the builder task on the given task spawner
     pub async fn spawn<T: TaskSpawnerExt>(self, task_spawner: T) -> anyhow::Result<()> {
         let mut bundle_sender_actions = vec![];
-        let mut pk_iter = self.args.private_keys.clone().into_iter();
+
+        let lease_backend: Arc<dyn LeaseBackend> = if self.args.redis_uri.is_empty() {
+            info!("No Redis URI provided, leasing signer keys in memory");
+            Arc::new(MemoryLeaseBackend::default())
+        } else {
+            Arc::new(RedisLeaseBackend::new(self.args.redis_uri.clone()))
+        };
+        let leaser = SignerLeaser::new(
+            lease_backend,
+            self.args.chain_spec.id,
+            self.args.redis_lock_ttl_millis,
+        );
 
         for ep in &self.args.entry_points {
             match ep.version {
                 EntryPointVersion::V0_6 => {
                     let actions = self
-                        .create_builders_v0_6(&task_spawner, ep, &mut pk_iter)
+                        .create_builders_v0_6(&task_spawner, ep, &leaser)
                         .await?;
                     bundle_sender_actions.extend(actions);
                 }
                 EntryPointVersion::V0_7 => {
                     let actions = self
-                        .create_builders_v0_7(&task_spawner, ep, &mut pk_iter)
+                        .create_builders_v0_7(&task_spawner, ep, &leaser)
+                        .await?;
+                    bundle_sender_actions.extend(actions);
+                }
+                EntryPointVersion::V0_8 => {
+                    let actions = self
+                        .create_builders_v0_8(&task_spawner, ep, &leaser)
                         .await?;
                     bundle_sender_actions.extend(actions);
                 }
@@ -198,15 +233,14 @@ where
         Ok(())
     }
 
-    async fn create_builders_v0_6<T, I>(
+    async fn create_builders_v0_6<T>(
         &self,
         task_spawner: &T,
         ep: &EntryPointBuilderSettings,
-        pk_iter: &mut I,
+        leaser: &SignerLeaser,
     ) -> anyhow::Result<Vec<mpsc::Sender<BundleSenderAction>>>
     where
         T: TaskSpawnerExt,
-        I: Iterator<Item = String>,
     {
         info!("Mempool config for ep v0.6: {:?}", ep.mempool_configs);
         let ep_providers = self
@@ -222,7 +256,7 @@ where
                     i + ep.bundle_builder_index_offset,
                     ep_providers.clone(),
                     UnsafeSimulator::new(ep_providers.entry_point().clone()),
-                    pk_iter,
+                    leaser,
                 )
                 .await?
             } else {
@@ -236,7 +270,7 @@ where
                         self.args.sim_settings.clone(),
                         ep.mempool_configs.clone(),
                     ),
-                    pk_iter,
+                    leaser,
                 )
                 .await?
             };
@@ -245,15 +279,14 @@ where
         Ok(bundle_sender_actions)
     }
 
-    async fn create_builders_v0_7<T, I>(
+    async fn create_builders_v0_7<T>(
         &self,
         task_spawner: &T,
         ep: &EntryPointBuilderSettings,
-        pk_iter: &mut I,
+        leaser: &SignerLeaser,
     ) -> anyhow::Result<Vec<mpsc::Sender<BundleSenderAction>>>
     where
         T: TaskSpawnerExt,
-        I: Iterator<Item = String>,
     {
         info!("Mempool config for ep v0.7: {:?}", ep.mempool_configs);
         let ep_providers = self
@@ -269,7 +302,54 @@ where
                     i + ep.bundle_builder_index_offset,
                     ep_providers.clone(),
                     UnsafeSimulator::new(ep_providers.entry_point().clone()),
-                    pk_iter,
+                    leaser,
+                )
+                .await?
+            } else {
+                self.create_bundle_builder(
+                    task_spawner,
+                    i + ep.bundle_builder_index_offset,
+                    ep_providers.clone(),
+                    simulation::new_v0_7_simulator(
+                        ep_providers.evm().clone(),
+                        ep_providers.entry_point().clone(),
+                        &self.args.chain_spec,
+                        self.args.sim_settings.clone(),
+                        ep.mempool_configs.clone(),
+                    ),
+                    leaser,
+                )
+                .await?
+            };
+            bundle_sender_actions.push(bundle_sender_action);
+        }
+        Ok(bundle_sender_actions)
+    }
+
+    async fn create_builders_v0_8<T>(
+        &self,
+        task_spawner: &T,
+        ep: &EntryPointBuilderSettings,
+        leaser: &SignerLeaser,
+    ) -> anyhow::Result<Vec<mpsc::Sender<BundleSenderAction>>>
+    where
+        T: TaskSpawnerExt,
+    {
+        info!("Mempool config for ep v0.8: {:?}", ep.mempool_configs);
+        let ep_providers = self
+            .providers
+            .ep_v0_8_providers()
+            .clone()
+            .context("entry point v0.8 not supplied")?;
+        let mut bundle_sender_actions = vec![];
+        for i in 0..ep.num_bundle_builders {
+            let bundle_sender_action = if self.args.unsafe_mode {
+                self.create_bundle_builder(
+                    task_spawner,
+                    i + ep.bundle_builder_index_offset,
+                    ep_providers.clone(),
+                    UnsafeSimulator::new(ep_providers.entry_point().clone()),
+                    leaser,
                 )
                 .await?
             } else {
@@ -280,10 +360,11 @@ where
                     simulation::new_v0_7_simulator(
                         ep_providers.evm().clone(),
                         ep_providers.entry_point().clone(),
+                        &self.args.chain_spec,
                         self.args.sim_settings.clone(),
                         ep.mempool_configs.clone(),
                     ),
-                    pk_iter,
+                    leaser,
                 )
                 .await?
             };
@@ -292,13 +373,13 @@ where
         Ok(bundle_sender_actions)
     }
 
-    async fn create_bundle_builder<T, UO, EP, S, I>(
+    async fn create_bundle_builder<T, UO, EP, S>(
         &self,
         task_spawner: &T,
         index: u64,
         ep_providers: EP,
         simulator: S,
-        pk_iter: &mut I,
+        leaser: &SignerLeaser,
     ) -> anyhow::Result<mpsc::Sender<BundleSenderAction>>
     where
         T: TaskSpawnerExt,
@@ -306,55 +387,22 @@ where
         UserOperationVariant: AsRef<UO>,
         EP: ProvidersWithEntryPointT + 'static,
         S: Simulator<UO = UO> + 'static,
-        I: Iterator<Item = String>,
     {
         let (send_bundle_tx, send_bundle_rx) = mpsc::channel(1);
 
-        let signer = if let Some(pk) = pk_iter.next() {
-            info!("Using local signer");
-            BundlerSigner::Local(
-                LocalSigner::connect(
-                    &task_spawner,
-                    self.providers.evm().clone(),
-                    self.args.chain_spec.id,
-                    pk.to_owned(),
-                )
-                .await?,
-            )
-        } else {
-            info!("Using AWS KMS signer");
-            let signer = time::timeout(
-                // timeout must be < than the lock TTL to avoid a
-                // bug in the redis lock implementation that panics if connection
-                // takes longer than the TTL. Generally the TLL should be on the order of 10s of seconds
-                // so this should give ample time for the connection to establish.
-                Duration::from_millis(self.args.redis_lock_ttl_millis / 4),
-                KmsSigner::connect(
-                    &task_spawner,
-                    self.providers.evm().clone(),
-                    self.args.chain_spec.id,
-                    self.args.aws_kms_key_ids.clone(),
-                    self.args.redis_uri.clone(),
-                    self.args.redis_lock_ttl_millis,
-                ),
-            )
-            .await
-            .context("timeout connecting to KMS")?
-            .context("failure connecting to KMS")?;
-            let ret = BundlerSigner::Kms(signer);
-            info!("Created AWS KMS signer");
-            ret
-        };
-        let beneficiary = signer.address();
+        let (signer, lease) = self.create_signer(task_spawner, leaser).await?;
         let proposer_settings = bundle_proposer::Settings {
             chain_spec: self.args.chain_spec.clone(),
             max_bundle_size: self.args.max_bundle_size,
             max_bundle_gas: self.args.max_bundle_gas,
-            beneficiary,
+            beneficiary: signer.address(),
             priority_fee_mode: self.args.priority_fee_mode,
             bundle_base_fee_overhead_percent: self.args.bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
             da_gas_tracking_enabled: self.args.da_gas_tracking_enabled,
+            min_profit_margin_percent: self.args.min_profit_margin_percent,
+            aggregators: self.args.aggregators.clone(),
+            pending_simulation: self.args.pending_simulation,
         };
 
         let transaction_sender = self.args.sender_args.clone().into_sender(
@@ -365,6 +413,7 @@ where
 
         let tracker_settings = transaction_tracker::Settings {
             replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
+            max_in_flight_transactions: self.args.max_in_flight_transactions,
         };
 
         let transaction_tracker = TransactionTrackerImpl::new(
@@ -379,6 +428,7 @@ where
             max_replacement_underpriced_blocks: self.args.max_replacement_underpriced_blocks,
             max_cancellation_fee_increases: self.args.max_cancellation_fee_increases,
             max_blocks_to_wait_for_mine: self.args.max_blocks_to_wait_for_mine,
+            max_in_flight_transactions: self.args.max_in_flight_transactions,
         };
 
         let fee_oracle = gas::get_fee_oracle(&self.args.chain_spec, ep_providers.evm().clone());
@@ -402,10 +452,10 @@ where
             index,
             send_bundle_rx,
             self.args.chain_spec.clone(),
-            beneficiary,
             proposer,
             ep_providers.entry_point().clone(),
             transaction_tracker,
+            lease,
             self.pool.clone(),
             builder_settings,
             self.event_sender.clone(),
@@ -417,4 +467,152 @@ where
 
         Ok(send_bundle_tx)
     }
+
+    /// Leases the first free signer key and creates a signer for it.
+    ///
+    /// Local private keys are leased by their address, KMS keys by their key id.
+    ///
+    /// If the lease is lost and fails over to another free key, the returned signer is
+    /// swapped for a signer of the new key before the returned handle reports the lease
+    /// as held again. The balance of the key that is currently held is monitored.
+    async fn create_signer<T: TaskSpawnerExt>(
+        &self,
+        task_spawner: &T,
+        leaser: &SignerLeaser,
+    ) -> anyhow::Result<(LeasedSigner, LeaseHandle)> {
+        let use_kms = self.args.private_keys.is_empty();
+        let key_ids = if use_kms {
+            self.args.aws_kms_key_ids.clone()
+        } else {
+            self.args
+                .private_keys
+                .iter()
+                .map(|pk| {
+                    pk.parse::<PrivateKeySigner>()
+                        .map(|s| s.address().to_string())
+                        .context("should parse private key")
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+
+        let (key_index, lease) = time::timeout(
+            // timeout must be < than the lock TTL to avoid a
+            // bug in the redis lock implementation that panics if connection
+            // takes longer than the TTL. Generally the TLL should be on the order of 10s of seconds
+            // so this should give ample time for the connection to establish.
+            Duration::from_millis(self.args.redis_lock_ttl_millis / 4),
+            leaser.lease_any(task_spawner, &key_ids),
+        )
+        .await
+        .context("timeout leasing signer key")??
+        .context("no free signer key to lease")?;
+
+        let keys = if use_kms {
+            key_ids
+        } else {
+            self.args.private_keys.clone()
+        };
+        let signer = LeasedSigner::new(
+            connect_signer(self.args.chain_spec.id, use_kms, keys[key_index].clone()).await?,
+        );
+        let balance_monitor = task_spawner.spawn(Box::pin(monitor_account_balance(
+            signer.address(),
+            self.providers.evm().clone(),
+        )));
+
+        let (status_tx, status_rx) = watch::channel(lease.status());
+        task_spawner.spawn_critical(
+            "signer failover",
+            Box::pin(follow_lease(
+                task_spawner.clone(),
+                self.providers.evm().clone(),
+                self.args.chain_spec.id,
+                use_kms,
+                keys,
+                key_index,
+                signer.clone(),
+                balance_monitor,
+                lease,
+                status_tx,
+            )),
+        );
+
+        Ok((signer, LeaseHandle::new(status_rx)))
+    }
+}
+
+async fn connect_signer(
+    chain_id: u64,
+    use_kms: bool,
+    key: String,
+) -> anyhow::Result<BundlerSigner> {
+    if use_kms {
+        info!("Using AWS KMS signer");
+        let signer = KmsSigner::connect(chain_id, key)
+            .await
+            .context("failure connecting to KMS")?;
+        info!("Created AWS KMS signer");
+        Ok(BundlerSigner::Kms(signer))
+    } else {
+        info!("Using local signer");
+        Ok(BundlerSigner::Local(
+            LocalSigner::connect(chain_id, key).await?,
+        ))
+    }
+}
+
+/// Forwards the status of `lease` to `status_tx`, swapping `signer` for a signer of the
+/// newly leased key whenever the lease fails over to another key.
+///
+/// The balance monitor of the released key is stopped and the new key is monitored instead.
+/// The bundle beneficiary follows the signer, see `BundleSenderImpl`.
+#[allow(clippy::too_many_arguments)]
+async fn follow_lease<T: TaskSpawnerExt, P: EvmProvider + Clone + 'static>(
+    task_spawner: T,
+    provider: P,
+    chain_id: u64,
+    use_kms: bool,
+    keys: Vec<String>,
+    mut key_index: usize,
+    signer: LeasedSigner,
+    mut balance_monitor: JoinHandle<()>,
+    mut lease: LeaseHandle,
+    status_tx: watch::Sender<LeaseStatus>,
+) {
+    loop {
+        let status = match lease.changed().await {
+            Ok(status) => status,
+            Err(e) => {
+                error!("signer lease failed: {e:?}");
+                balance_monitor.abort();
+                return;
+            }
+        };
+
+        if let LeaseStatus::Held(i) = status {
+            if i != key_index {
+                match connect_signer(chain_id, use_kms, keys[i].clone()).await {
+                    Ok(s) => {
+                        info!("Signer failed over to {:?}", s.address());
+                        balance_monitor.abort();
+                        balance_monitor = task_spawner.spawn(Box::pin(monitor_account_balance(
+                            s.address(),
+                            provider.clone(),
+                        )));
+                        signer.swap(s);
+                        key_index = i;
+                    }
+                    Err(e) => {
+                        // Dropping the status sender stops the bundle sender rather than
+                        // leaving it waiting on a key that can never be used
+                        error!("could not connect signer for failover key: {e:?}");
+                        balance_monitor.abort();
+                        return;
+                    }
+                }
+            }
+        }
+
+        status_tx.send_replace(status);
+    }
 }
@@ -11,6 +11,8 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::collections::BTreeMap;
+
 use alloy_consensus::Transaction;
 use alloy_primitives::{Address, B256};
 use anyhow::{bail, Context};
@@ -35,16 +37,37 @@ use crate::sender::{TransactionSender, TxSenderError, TxStatus};
 /// until it returns a `TrackerUpdate` to indicate whether a transaction has
 /// succeeded (potentially not the most recent one) or whether circumstances
 /// have changed so that it is worth making another attempt.
+///
+/// When more than one transaction may be in flight, additional transactions can
+/// be sent with the nonces following the current one while it is pending. These
+/// are tracked until the current nonce reaches them, at which point they become
+/// the current transaction and can be replaced as usual. Replacing or cancelling
+/// the current transaction re-sends or cancels the ones queued behind it.
 #[async_trait]
 #[cfg_attr(test, automock)]
 pub(crate) trait TransactionTracker: Send + Sync {
+    /// Returns the address that sends the transactions, which changes when the signer
+    /// fails over to another key.
+    fn address(&self) -> Address;
+
     /// Returns the current nonce and the required fees for the next transaction.
     fn get_nonce_and_required_fees(&self) -> TransactionTrackerResult<(u64, Option<GasFees>)>;
 
+    /// Returns the nonce and the required fees for an additional transaction to
+    /// send behind the pending ones, if there is room for another transaction in
+    /// flight.
+    fn get_pipelined_nonce_and_required_fees(&self) -> Option<(u64, Option<GasFees>)>;
+
+    /// Returns the nonces of the pending transactions, in order.
+    fn in_flight_nonces(&self) -> Vec<u64>;
+
     /// Sends the provided transaction and typically returns its transaction
     /// hash, but if the transaction failed to send because another transaction
     /// with the same nonce mined first, then returns information about that
     /// transaction instead.
+    ///
+    /// Transactions queued behind a replaced current transaction are re-sent with
+    /// at least its fees, so that they don't stall behind it.
     async fn send_transaction(
         &mut self,
         tx: TransactionRequest,
@@ -53,6 +76,9 @@ pub(crate) trait TransactionTracker: Send + Sync {
 
     /// Cancel the abandoned transaction in the tracker.
     ///
+    /// Transactions queued behind it with later nonces were built on top of it, so
+    /// they are cancelled as well.
+    ///
     /// Returns: An option containing the hash of the transaction that was used to cancel. If the option
     /// is empty, then either no transaction was cancelled or the cancellation was a "soft-cancel."
     async fn cancel_transaction(
@@ -68,7 +94,10 @@ pub(crate) trait TransactionTracker: Send + Sync {
     ///    that a transaction from our account other than one of the ones we are
     ///    tracking has mined. This should not normally happen.
     /// 4. Several new blocks have passed.
-    async fn check_for_update(&mut self) -> TransactionTrackerResult<Option<TrackerUpdate>>;
+    ///
+    /// If the nonce has moved past several in-flight nonces, an update is returned
+    /// for each of them, in nonce order.
+    async fn check_for_update(&mut self) -> TransactionTrackerResult<Vec<TrackerUpdate>>;
 
     /// Resets the tracker to its initial state
     async fn reset(&mut self);
@@ -128,6 +157,10 @@ pub(crate) struct TransactionTrackerImpl<P, T> {
     settings: Settings,
     nonce: u64,
     transactions: Vec<PendingTransaction>,
+    // transactions sent with the nonces following `nonce`
+    queued: BTreeMap<u64, QueuedTransaction>,
+    // fees of the last pipelined transaction rejected as a replacement underpriced
+    pipelined_underpriced: Option<(u64, GasFees)>,
     has_abandoned: bool,
     attempt_count: u64,
     metrics: TransactionTrackerMetrics,
@@ -136,6 +169,7 @@ pub(crate) struct TransactionTrackerImpl<P, T> {
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct Settings {
     pub(crate) replacement_fee_percent_increase: u32,
+    pub(crate) max_in_flight_transactions: u64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -145,6 +179,13 @@ struct PendingTransaction {
     attempt_number: u64,
 }
 
+#[derive(Clone, Debug)]
+struct QueuedTransaction {
+    pending: PendingTransaction,
+    // the transaction to re-send if the current one is replaced, unset once cancelled
+    tx: Option<(TransactionRequest, ExpectedStorage)>,
+}
+
 impl<P, T> TransactionTrackerImpl<P, T>
 where
     P: EvmProvider,
@@ -166,6 +207,8 @@ where
             settings,
             nonce,
             transactions: vec![],
+            queued: BTreeMap::new(),
+            pipelined_underpriced: None,
             has_abandoned: false,
             attempt_count: 0,
             metrics: TransactionTrackerMetrics::new_with_labels(&[(
@@ -175,14 +218,31 @@ where
         })
     }
 
+    // A transaction already sent with the new nonce becomes the current transaction,
+    // those with later nonces stay queued behind it.
     fn set_nonce_and_clear_state(&mut self, nonce: u64) {
         self.nonce = nonce;
-        self.transactions.clear();
-        self.attempt_count = 0;
+        self.queued.retain(|&queued_nonce, _| queued_nonce >= nonce);
+        self.transactions = self
+            .queued
+            .remove(&nonce)
+            .map(|queued| queued.pending)
+            .into_iter()
+            .collect();
+        self.pipelined_underpriced = None;
+        self.attempt_count = self.transactions.len() as u64;
         self.has_abandoned = false;
         self.update_metrics();
     }
 
+    fn has_pending_transaction(&self) -> bool {
+        !self.has_abandoned
+            && self
+                .transactions
+                .last()
+                .is_some_and(|tx| tx.tx_hash != B256::ZERO)
+    }
+
     async fn get_external_nonce(&self) -> anyhow::Result<u64> {
         self.provider
             .get_transaction_count(self.sender.address())
@@ -198,7 +258,13 @@ where
             max_fee_per_gas: tx.max_fee_per_gas.unwrap_or(0),
             max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or(0),
         };
-        let (required_nonce, required_gas_fees) = self.get_nonce_and_required_fees()?;
+        let (required_nonce, required_gas_fees) = match self.get_pipelined_nonce_and_required_fees()
+        {
+            Some((pipelined_nonce, required_gas_fees)) if nonce == pipelined_nonce => {
+                (pipelined_nonce, required_gas_fees)
+            }
+            _ => self.get_nonce_and_required_fees()?,
+        };
         if nonce != required_nonce {
             bail!("tried to send transaction with nonce {nonce}, but should match tracker's nonce of {required_nonce}");
         }
@@ -216,6 +282,9 @@ where
         self.metrics
             .num_pending_transactions
             .set(self.transactions.len() as f64);
+        self.metrics
+            .num_in_flight_transactions
+            .set(self.in_flight_nonces().len() as f64);
         self.metrics.nonce.set(self.nonce as f64);
         self.metrics.attempt_count.set(self.attempt_count as f64);
 
@@ -258,6 +327,153 @@ where
         };
         Ok((gas_limit, gas_used, gas_price))
     }
+
+    // Returns the update for the first of `txs`, sent with `nonce`, found to have mined.
+    async fn get_mined_update(
+        &self,
+        nonce: u64,
+        txs: &[PendingTransaction],
+    ) -> anyhow::Result<Option<TrackerUpdate>> {
+        for tx in txs.iter().rev() {
+            let status = self
+                .sender
+                .get_transaction_status(tx.tx_hash)
+                .await
+                .context("tracker should check transaction status when the nonce changes")?;
+            info!("Status of tx {:?}: {:?}", tx.tx_hash, status);
+            if let TxStatus::Mined { block_number } = status {
+                let (gas_limit, gas_used, gas_price) =
+                    self.get_mined_tx_gas_info(tx.tx_hash).await?;
+                return Ok(Some(TrackerUpdate::Mined {
+                    tx_hash: tx.tx_hash,
+                    nonce,
+                    block_number,
+                    attempt_number: tx.attempt_number,
+                    gas_limit,
+                    gas_used,
+                    gas_price,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    // The queued transactions were sent behind the replaced current transaction, and
+    // are re-sent so that they aren't priced below its replacement. A transaction that
+    // fails to re-send stays in flight as it was.
+    async fn resend_queued(&mut self, min_fees: GasFees) {
+        let nonces: Vec<_> = self.queued.keys().copied().collect();
+        for nonce in nonces {
+            let queued = &self.queued[&nonce];
+            let Some((tx, expected_storage)) = queued.tx.clone() else {
+                continue;
+            };
+            let gas_fees = max_fees(
+                queued
+                    .pending
+                    .gas_fees
+                    .increase_by_percent(self.settings.replacement_fee_percent_increase),
+                min_fees,
+            );
+            let attempt_number = queued.pending.attempt_number + 1;
+            let tx = tx
+                .max_fee_per_gas(gas_fees.max_fee_per_gas)
+                .max_priority_fee_per_gas(gas_fees.max_priority_fee_per_gas);
+
+            match self
+                .sender
+                .send_transaction(tx.clone(), &expected_storage)
+                .await
+            {
+                Ok(sent_tx) => {
+                    info!(
+                        "Re-sent queued transaction {:?} nonce: {:?} fees: {:?}",
+                        sent_tx.tx_hash, nonce, gas_fees
+                    );
+                    self.queued.insert(
+                        nonce,
+                        QueuedTransaction {
+                            pending: PendingTransaction {
+                                tx_hash: sent_tx.tx_hash,
+                                gas_fees,
+                                attempt_number,
+                            },
+                            tx: Some((tx, expected_storage)),
+                        },
+                    );
+                }
+                Err(TxSenderError::NonceTooLow) => {
+                    // picked up by the next update
+                    info!("Queued transaction with nonce {nonce} already mined");
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to re-send queued transaction with nonce {nonce}: {e:?}");
+                }
+            }
+        }
+    }
+
+    // The queued transactions were built on top of the cancelled current transaction,
+    // so they are cancelled too. A soft-cancelled transaction is no longer tracked.
+    async fn cancel_queued(&mut self, to: Address, min_fees: GasFees) {
+        let nonces: Vec<_> = self.queued.keys().copied().collect();
+        for nonce in nonces {
+            let queued = &self.queued[&nonce];
+            let tx_hash = queued.pending.tx_hash;
+            let gas_fees = max_fees(
+                queued
+                    .pending
+                    .gas_fees
+                    .increase_by_percent(self.settings.replacement_fee_percent_increase),
+                min_fees,
+            );
+            let attempt_number = queued.pending.attempt_number + 1;
+
+            match self
+                .sender
+                .cancel_transaction(tx_hash, nonce, to, gas_fees)
+                .await
+            {
+                Ok(cancel_info) if cancel_info.soft_cancelled => {
+                    info!("Soft-cancelled queued transaction {tx_hash:?} nonce: {nonce:?}");
+                    self.queued.remove(&nonce);
+                }
+                Ok(cancel_info) => {
+                    info!(
+                        "Sent cancellation tx {:?} for queued nonce: {:?} fees: {:?}",
+                        cancel_info.tx_hash, nonce, gas_fees
+                    );
+                    self.queued.insert(
+                        nonce,
+                        QueuedTransaction {
+                            pending: PendingTransaction {
+                                tx_hash: cancel_info.tx_hash,
+                                gas_fees,
+                                attempt_number,
+                            },
+                            tx: None,
+                        },
+                    );
+                }
+                Err(TxSenderError::NonceTooLow) => {
+                    info!("Queued transaction with nonce {nonce} already mined");
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to cancel queued transaction with nonce {nonce}: {e:?}");
+                }
+            }
+        }
+        self.update_metrics();
+    }
+}
+
+fn max_fees(a: GasFees, b: GasFees) -> GasFees {
+    GasFees {
+        max_fee_per_gas: a.max_fee_per_gas.max(b.max_fee_per_gas),
+        max_priority_fee_per_gas: a.max_priority_fee_per_gas.max(b.max_priority_fee_per_gas),
+    }
 }
 
 #[async_trait]
@@ -266,6 +482,10 @@ where
     P: EvmProvider,
     T: TransactionSender,
 {
+    fn address(&self) -> Address {
+        self.sender.address()
+    }
+
     fn get_nonce_and_required_fees(&self) -> TransactionTrackerResult<(u64, Option<GasFees>)> {
         let gas_fees = if self.has_abandoned {
             None
@@ -278,6 +498,30 @@ where
         Ok((self.nonce, gas_fees))
     }
 
+    fn get_pipelined_nonce_and_required_fees(&self) -> Option<(u64, Option<GasFees>)> {
+        let in_flight = self.queued.len() as u64 + 1;
+        if !self.has_pending_transaction() || in_flight >= self.settings.max_in_flight_transactions
+        {
+            return None;
+        }
+        let nonce = self.nonce + in_flight;
+        let gas_fees = self
+            .pipelined_underpriced
+            .filter(|&(underpriced_nonce, _)| underpriced_nonce == nonce)
+            .map(|(_, gas_fees)| {
+                gas_fees.increase_by_percent(self.settings.replacement_fee_percent_increase)
+            });
+        Some((nonce, gas_fees))
+    }
+
+    fn in_flight_nonces(&self) -> Vec<u64> {
+        self.has_pending_transaction()
+            .then_some(self.nonce)
+            .into_iter()
+            .chain(self.queued.keys().copied())
+            .collect()
+    }
+
     async fn send_transaction(
         &mut self,
         tx: TransactionRequest,
@@ -288,13 +532,54 @@ where
             max_fee_per_gas: tx.max_fee_per_gas.unwrap_or(0),
             max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or(0),
         };
+        let nonce = tx.nonce.unwrap_or(self.nonce);
         info!(
             "Sending transaction with nonce: {:?} gas fees: {:?} gas limit: {:?}",
-            self.nonce,
+            nonce,
             gas_fees,
             tx.gas.unwrap_or(0),
         );
-        let sent_tx = self.sender.send_transaction(tx, expected_storage).await;
+        let sent_tx = self
+            .sender
+            .send_transaction(tx.clone(), expected_storage)
+            .await;
+
+        if nonce != self.nonce {
+            // A pipelined transaction isn't replaced until it becomes the current one
+            return match sent_tx {
+                Ok(sent_tx) => {
+                    info!(
+                        "Sent pipelined transaction {:?} nonce: {:?}",
+                        sent_tx.tx_hash, sent_tx.nonce
+                    );
+                    self.queued.insert(
+                        nonce,
+                        QueuedTransaction {
+                            pending: PendingTransaction {
+                                tx_hash: sent_tx.tx_hash,
+                                gas_fees,
+                                attempt_number: 0,
+                            },
+                            tx: Some((tx, expected_storage.clone())),
+                        },
+                    );
+                    self.pipelined_underpriced = None;
+                    self.update_metrics();
+                    Ok(sent_tx.tx_hash)
+                }
+                Err(TxSenderError::ReplacementUnderpriced) => {
+                    // An unknown transaction is pending with this nonce, the next attempt
+                    // must increase on these fees.
+                    warn!(
+                        "Pipelined replacement underpriced: nonce: {:?}, fees: {:?}",
+                        nonce, gas_fees
+                    );
+                    self.pipelined_underpriced = Some((nonce, gas_fees));
+                    Err(TransactionTrackerError::ReplacementUnderpriced)
+                }
+                Err(e) => Err(e.into()),
+            };
+        }
 
         self.update_metrics();
 
@@ -311,6 +596,7 @@ where
                 });
                 self.has_abandoned = false;
                 self.attempt_count += 1;
+                self.resend_queued(gas_fees).await;
                 self.update_metrics();
                 Ok(sent_tx.tx_hash)
             }
@@ -374,6 +660,8 @@ where
 
         match cancel_res {
             Ok(cancel_info) => {
+                self.cancel_queued(to, gas_fees).await;
+
                 if cancel_info.soft_cancelled {
                     // If the transaction was soft-cancelled. Reset internal state.
                     self.reset().await;
@@ -420,52 +708,44 @@ where
         }
     }
 
-    async fn check_for_update(&mut self) -> TransactionTrackerResult<Option<TrackerUpdate>> {
+    async fn check_for_update(&mut self) -> TransactionTrackerResult<Vec<TrackerUpdate>> {
         let external_nonce = self.get_external_nonce().await?;
         if self.nonce < external_nonce {
             // The nonce has changed. Check to see which of our transactions has
-            // mined, if any.
+            // mined, if any, for the current nonce and each queued nonce it passed.
             info!(
                 "Nonce has changed from {:?} to {:?}",
                 self.nonce, external_nonce
             );
 
-            let mut out = TrackerUpdate::NonceUsedForOtherTx { nonce: self.nonce };
-            for tx in self.transactions.iter().rev() {
-                let status = self
-                    .sender
-                    .get_transaction_status(tx.tx_hash)
-                    .await
-                    .context("tracker should check transaction status when the nonce changes")?;
-                info!("Status of tx {:?}: {:?}", tx.tx_hash, status);
-                if let TxStatus::Mined { block_number } = status {
-                    let (gas_limit, gas_used, gas_price) =
-                        self.get_mined_tx_gas_info(tx.tx_hash).await?;
-                    out = TrackerUpdate::Mined {
-                        tx_hash: tx.tx_hash,
-                        nonce: self.nonce,
-                        block_number,
-                        attempt_number: tx.attempt_number,
-                        gas_limit,
-                        gas_used,
-                        gas_price,
-                    };
-                    break;
-                }
+            let passed: Vec<_> = std::iter::once((self.nonce, self.transactions.clone()))
+                .chain(
+                    self.queued
+                        .range(..external_nonce)
+                        .map(|(&nonce, queued)| (nonce, vec![queued.pending])),
+                )
+                .collect();
+            let mut out = vec![];
+            for (nonce, txs) in passed {
+                let update = self
+                    .get_mined_update(nonce, &txs)
+                    .await?
+                    .unwrap_or(TrackerUpdate::NonceUsedForOtherTx { nonce });
+                out.push(update);
             }
             self.set_nonce_and_clear_state(external_nonce);
-            return Ok(Some(out));
+            return Ok(out);
         }
 
         let Some(&last_tx) = self.transactions.last() else {
             // If there are no pending transactions, there's no update either.
-            return Ok(None);
+            return Ok(vec![]);
         };
 
         if last_tx.tx_hash == B256::ZERO {
             // If the last transaction was a replacement that failed to send, we
             // don't need to check for updates.
-            return Ok(None);
+            return Ok(vec![]);
         }
 
         let status = self
@@ -474,13 +754,13 @@ where
             .await
             .context("tracker should check for transaction status")?;
         Ok(match status {
-            TxStatus::Pending => None,
+            TxStatus::Pending => vec![],
             TxStatus::Mined { block_number } => {
                 let nonce = self.nonce;
                 self.set_nonce_and_clear_state(nonce + 1);
                 let (gas_limit, gas_used, gas_price) =
                     self.get_mined_tx_gas_info(last_tx.tx_hash).await?;
-                Some(TrackerUpdate::Mined {
+                vec![TrackerUpdate::Mined {
                     tx_hash: last_tx.tx_hash,
                     nonce,
                     block_number,
@@ -488,9 +768,9 @@ where
                     gas_limit,
                     gas_used,
                     gas_price,
-                })
+                }]
             }
-            TxStatus::Dropped => Some(TrackerUpdate::LatestTxDropped { nonce: self.nonce }),
+            TxStatus::Dropped => vec![TrackerUpdate::LatestTxDropped { nonce: self.nonce }],
         })
     }
 
@@ -537,6 +817,8 @@ impl From<TxSenderError> for TransactionTrackerError {
 struct TransactionTrackerMetrics {
     #[metric(describe = "the number of pending transactions.")]
     num_pending_transactions: Gauge,
+    #[metric(describe = "the number of nonces with a pending transaction.")]
+    num_in_flight_transactions: Gauge,
     #[metric(describe = "the current account‘s nonce.")]
     nonce: Gauge,
     #[metric(describe = "the number of pending transactions.")]
@@ -561,7 +843,7 @@ mod tests {
     };
 
     use super::*;
-    use crate::sender::{MockTransactionSender, SentTxInfo};
+    use crate::sender::{CancelTxInfo, MockTransactionSender, SentTxInfo};
 
     fn create_base_config() -> (MockTransactionSender, MockEvmProvider) {
         let sender = MockTransactionSender::new();
@@ -576,6 +858,7 @@ mod tests {
     ) -> TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> {
         let settings = Settings {
             replacement_fee_percent_increase: 5,
+            max_in_flight_transactions: 1,
         };
 
         let tracker: TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> =
@@ -761,11 +1044,11 @@ mod tests {
 
         let mut tracker = create_tracker(sender, provider).await;
 
-        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        let tracker_updates = tracker.check_for_update().await.unwrap();
 
         assert!(matches!(
-            tracker_update,
-            TrackerUpdate::NonceUsedForOtherTx { .. }
+            tracker_updates[..],
+            [TrackerUpdate::NonceUsedForOtherTx { .. }]
         ));
     }
 
@@ -788,27 +1071,7 @@ mod tests {
             from: Address::default(),
         }
     }
-    #[tokio::test]
-    async fn test_check_for_update_mined() {
-        let (mut sender, mut provider) = create_base_config();
-        sender.expect_address().return_const(Address::ZERO);
-        sender
-            .expect_get_transaction_status()
-            .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
-
-        sender.expect_send_transaction().returning(move |_a, _b| {
-            Box::pin(async {
-                Ok(SentTxInfo {
-                    nonce: 0,
-                    tx_hash: B256::random(),
-                })
-            })
-        });
-
-        provider
-            .expect_get_transaction_count()
-            .returning(move |_a| Ok(0));
-
+    fn expect_mined_tx_info(provider: &mut MockEvmProvider) {
         provider
             .expect_get_transaction_by_hash()
             .returning(|hash: B256| Ok(Some(sign_transaction(hash))));
@@ -834,6 +1097,30 @@ mod tests {
                     authorization_list: None,
                 }))
             });
+    }
+
+    #[tokio::test]
+    async fn test_check_for_update_mined() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
+
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: 0,
+                    tx_hash: B256::random(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        expect_mined_tx_info(&mut provider);
 
         let mut tracker = create_tracker(sender, provider).await;
 
@@ -842,8 +1129,391 @@ mod tests {
 
         // send dummy transaction
         let _sent = tracker.send_transaction(tx, &exp).await;
-        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        let tracker_updates = tracker.check_for_update().await.unwrap();
+
+        assert!(matches!(tracker_updates[..], [TrackerUpdate::Mined { .. }]));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_nonce() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender.expect_send_transaction().returning(move |tx, _b| {
+            let nonce = tx.nonce.unwrap();
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce,
+                    tx_hash: B256::random(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 2;
+        let exp = ExpectedStorage::default();
+
+        // nothing to pipeline behind until a transaction is pending
+        assert_eq!(tracker.get_pipelined_nonce_and_required_fees(), None);
+
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        assert_eq!(
+            tracker.get_pipelined_nonce_and_required_fees(),
+            Some((1, None))
+        );
+
+        // pipelined transactions don't need to increase fees
+        let tx = TransactionRequest::default().nonce(1);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        assert_eq!(tracker.in_flight_nonces(), vec![0, 1]);
+        assert_eq!(tracker.get_pipelined_nonce_and_required_fees(), None);
+
+        // the current transaction is still the one being replaced
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (
+                0,
+                Some(GasFees {
+                    max_fee_per_gas: 10500,
+                    max_priority_fee_per_gas: 0,
+                })
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_transaction_promoted_when_mined() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
+        sender.expect_send_transaction().returning(move |tx, _b| {
+            let nonce = tx.nonce.unwrap();
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce,
+                    tx_hash: B256::random(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+        expect_mined_tx_info(&mut provider);
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 3;
+        let exp = ExpectedStorage::default();
+
+        for (nonce, max_fee_per_gas) in [(0, 10000), (1, 20000), (2, 30000)] {
+            let tx = TransactionRequest::default()
+                .nonce(nonce)
+                .max_fee_per_gas(max_fee_per_gas);
+            tracker.send_transaction(tx, &exp).await.unwrap();
+        }
+        assert_eq!(tracker.in_flight_nonces(), vec![0, 1, 2]);
+
+        let tracker_updates = tracker.check_for_update().await.unwrap();
+        assert!(matches!(
+            tracker_updates[..],
+            [TrackerUpdate::Mined { nonce: 0, .. }]
+        ));
+
+        // the next transaction becomes the current one, and is replaced from its own fees
+        assert_eq!(tracker.in_flight_nonces(), vec![1, 2]);
+        assert_eq!(
+            tracker.get_pipelined_nonce_and_required_fees(),
+            Some((3, None))
+        );
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (
+                1,
+                Some(GasFees {
+                    max_fee_per_gas: 21000,
+                    max_priority_fee_per_gas: 0,
+                })
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_for_update_reports_each_mined_nonce() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
+        sender.expect_send_transaction().returning(move |tx, _b| {
+            let nonce = tx.nonce.unwrap();
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce,
+                    tx_hash: B256::random(),
+                })
+            })
+        });
 
-        assert!(matches!(tracker_update, TrackerUpdate::Mined { .. }));
+        // the nonce moves past all three transactions at once
+        let mut provider_seq = Sequence::new();
+        provider
+            .expect_get_transaction_count()
+            .times(1)
+            .in_sequence(&mut provider_seq)
+            .returning(move |_a| Ok(0));
+        provider
+            .expect_get_transaction_count()
+            .times(1)
+            .in_sequence(&mut provider_seq)
+            .returning(move |_a| Ok(3));
+        expect_mined_tx_info(&mut provider);
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 3;
+        let exp = ExpectedStorage::default();
+
+        for nonce in 0..3 {
+            let tx = TransactionRequest::default().nonce(nonce);
+            tracker.send_transaction(tx, &exp).await.unwrap();
+        }
+
+        let tracker_updates = tracker.check_for_update().await.unwrap();
+        assert!(matches!(
+            tracker_updates[..],
+            [
+                TrackerUpdate::Mined { nonce: 0, .. },
+                TrackerUpdate::Mined { nonce: 1, .. },
+                TrackerUpdate::Mined { nonce: 2, .. },
+            ]
+        ));
+        assert!(tracker.in_flight_nonces().is_empty());
+        assert_eq!(tracker.get_nonce_and_required_fees().unwrap(), (3, None));
+    }
+
+    #[tokio::test]
+    async fn test_replaced_earlier_nonce_resends_queued() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+
+        let mut sender_seq = Sequence::new();
+        for (nonce, max_fee_per_gas) in [(0, 10000), (1, 5000), (0, 10500), (1, 10500)] {
+            sender
+                .expect_send_transaction()
+                .withf(move |tx, _| {
+                    tx.nonce == Some(nonce) && tx.max_fee_per_gas == Some(max_fee_per_gas)
+                })
+                .times(1)
+                .in_sequence(&mut sender_seq)
+                .returning(move |_a, _b| {
+                    Box::pin(async move {
+                        Ok(SentTxInfo {
+                            nonce,
+                            tx_hash: B256::random(),
+                        })
+                    })
+                });
+        }
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 2;
+        let exp = ExpectedStorage::default();
+
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        let tx = TransactionRequest::default().nonce(1).max_fee_per_gas(5000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+
+        // replacing the earlier nonce re-sends the later one at its fees
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10500);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        assert_eq!(tracker.in_flight_nonces(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_earlier_nonce_resends_queued() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
+
+        let mut sender_seq = Sequence::new();
+        for (nonce, max_fee_per_gas) in [(0, 10000), (1, 20000), (0, 10500), (1, 21000)] {
+            sender
+                .expect_send_transaction()
+                .withf(move |tx, _| {
+                    tx.nonce == Some(nonce) && tx.max_fee_per_gas == Some(max_fee_per_gas)
+                })
+                .times(1)
+                .in_sequence(&mut sender_seq)
+                .returning(move |_a, _b| {
+                    Box::pin(async move {
+                        Ok(SentTxInfo {
+                            nonce,
+                            tx_hash: B256::random(),
+                        })
+                    })
+                });
+        }
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 2;
+        let exp = ExpectedStorage::default();
+
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        let tx = TransactionRequest::default()
+            .nonce(1)
+            .max_fee_per_gas(20000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+
+        let tracker_updates = tracker.check_for_update().await.unwrap();
+        assert!(matches!(
+            tracker_updates[..],
+            [TrackerUpdate::LatestTxDropped { nonce: 0 }]
+        ));
+
+        // re-sending the dropped nonce re-sends the later one, increasing on its own fees
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10500);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        assert_eq!(tracker.in_flight_nonces(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_cascades_to_queued() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender.expect_send_transaction().returning(move |tx, _b| {
+            let nonce = tx.nonce.unwrap();
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce,
+                    tx_hash: B256::random(),
+                })
+            })
+        });
+
+        let mut sender_seq = Sequence::new();
+        for nonce in 0..2 {
+            sender
+                .expect_cancel_transaction()
+                .withf(move |_, n, _, fees| *n == nonce && fees.max_fee_per_gas == 21000)
+                .times(1)
+                .in_sequence(&mut sender_seq)
+                .returning(move |_, _, _, _| {
+                    Box::pin(async move {
+                        Ok(CancelTxInfo {
+                            tx_hash: B256::random(),
+                            soft_cancelled: false,
+                        })
+                    })
+                });
+        }
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 2;
+        let exp = ExpectedStorage::default();
+
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        let tx = TransactionRequest::default()
+            .nonce(1)
+            .max_fee_per_gas(20000);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+
+        // the later nonce is cancelled at no less than the cancellation of the earlier one
+        let estimated_fees = GasFees {
+            max_fee_per_gas: 21000,
+            max_priority_fee_per_gas: 0,
+        };
+        tracker
+            .cancel_transaction(Address::ZERO, estimated_fees)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tracker.in_flight_nonces(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replacement_underpriced() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::ZERO);
+        sender.expect_send_transaction().returning(move |tx, _b| {
+            let nonce = tx.nonce.unwrap();
+            Box::pin(async move {
+                if nonce == 0 {
+                    Ok(SentTxInfo {
+                        nonce,
+                        tx_hash: B256::random(),
+                    })
+                } else {
+                    Err(TxSenderError::ReplacementUnderpriced)
+                }
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(0));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        tracker.settings.max_in_flight_transactions = 2;
+        let exp = ExpectedStorage::default();
+
+        let tx = TransactionRequest::default().nonce(0);
+        tracker.send_transaction(tx, &exp).await.unwrap();
+        let tx = TransactionRequest::default()
+            .nonce(1)
+            .max_fee_per_gas(10000);
+        assert!(matches!(
+            tracker.send_transaction(tx, &exp).await,
+            Err(TransactionTrackerError::ReplacementUnderpriced)
+        ));
+
+        // an unknown transaction holds the nonce, so the next attempt must replace it
+        assert_eq!(
+            tracker.get_pipelined_nonce_and_required_fees(),
+            Some((
+                1,
+                Some(GasFees {
+                    max_fee_per_gas: 10500,
+                    max_priority_fee_per_gas: 0,
+                })
+            ))
+        );
+        let tx = TransactionRequest::default()
+            .nonce(1)
+            .max_fee_per_gas(10000);
+        assert!(tracker.send_transaction(tx, &exp).await.is_err());
     }
 }
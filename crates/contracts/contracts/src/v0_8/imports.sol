@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.28;
+
+// Simply importing a dependency is enough for Forge to include it in builds.
+
+import "account-abstraction/v0_8/interfaces/IEntryPoint.sol";
+import "account-abstraction/v0_8/interfaces/IAccount.sol";
+import "account-abstraction/v0_8/interfaces/IPaymaster.sol";
+import "account-abstraction/v0_8/interfaces/IAggregator.sol";
+import "account-abstraction/v0_8/interfaces/IStakeManager.sol";
+import "account-abstraction/v0_8/interfaces/PackedUserOperation.sol";
+import "account-abstraction/v0_8/core/EntryPointSimulations.sol";
+import "account-abstraction/v0_8/core/SenderCreator.sol";
@@ -0,0 +1,32 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+// Contracts from https://github.com/eth-infinitism/account-abstraction/tree/releases/v0.8/contracts
+//
+// The v0.8 entry point keeps the v0.7 ABI, use the `v0_7` bindings to call it.
+// Only the simulations bytecode differs, as v0.8 hashes user operations with EIP-712.
+
+use std::sync::LazyLock;
+
+use alloy_primitives::Bytes;
+
+// EntryPointSimulations deployed bytecode
+const __ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE_HEX: &[u8] = include_bytes!(
+    "../contracts/out/v0_8/EntryPointSimulations.sol/EntryPointSimulations_deployedBytecode.txt"
+);
+
+pub static ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE: LazyLock<Bytes> = LazyLock::new(|| {
+    const_hex::decode(__ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE_HEX)
+        .expect("Failed to decode entry point simulations hex")
+        .into()
+});
@@ -0,0 +1,375 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! `ssz_snappy` encoding for gossip and request/response messages.
+
+use std::io::{self, Read, Write};
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libp2p::{
+    gossipsub::{self, DataTransform, MessageId, RawMessage, TopicHash},
+    request_response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::types::{
+    PooledUserOpHashesRequest, PooledUserOpHashesResponse, PooledUserOpsByHashRequest,
+    PooledUserOpsByHashResponse,
+};
+
+/// Maximum size of an uncompressed gossip message
+pub(crate) const MAX_GOSSIP_SIZE: usize = 1 << 20;
+/// Maximum size of an uncompressed request or response
+const MAX_CHUNK_SIZE: usize = 1 << 20;
+
+const MESSAGE_DOMAIN_INVALID_SNAPPY: [u8; 4] = [0, 0, 0, 0];
+const MESSAGE_DOMAIN_VALID_SNAPPY: [u8; 4] = [1, 0, 0, 0];
+
+const RESPONSE_SUCCESS: u8 = 0;
+const RESPONSE_SERVER_ERROR: u8 = 2;
+
+/// Computes the gossip message ID.
+///
+/// `SHA256(MESSAGE_DOMAIN_VALID_SNAPPY + decompressed data)[:20]`. Messages are
+/// decompressed by [`SnappyTransform`] before their ID is computed.
+pub(crate) fn message_id(message: &gossipsub::Message) -> MessageId {
+    let mut hasher = Sha256::new();
+    hasher.update(MESSAGE_DOMAIN_VALID_SNAPPY);
+    hasher.update(&message.data);
+    MessageId::new(&hasher.finalize()[..20])
+}
+
+/// Computes the gossip message ID of a message that failed to decompress
+fn invalid_message_id(data: &[u8]) -> MessageId {
+    let mut hasher = Sha256::new();
+    hasher.update(MESSAGE_DOMAIN_INVALID_SNAPPY);
+    hasher.update(data);
+    MessageId::new(&hasher.finalize()[..20])
+}
+
+/// Snappy block compression of gossip message data
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct SnappyTransform;
+
+impl DataTransform for SnappyTransform {
+    fn inbound_transform(&self, raw_message: RawMessage) -> Result<gossipsub::Message, io::Error> {
+        let len = snap::raw::decompress_len(&raw_message.data)?;
+        if len > MAX_GOSSIP_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "message {} too large: {len}",
+                    invalid_message_id(&raw_message.data)
+                ),
+            ));
+        }
+
+        let data = snap::raw::Decoder::new().decompress_vec(&raw_message.data)?;
+        Ok(gossipsub::Message {
+            source: raw_message.source,
+            data,
+            sequence_number: raw_message.sequence_number,
+            topic: raw_message.topic,
+        })
+    }
+
+    fn outbound_transform(&self, _topic: &TopicHash, data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+        if data.len() > MAX_GOSSIP_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message too large: {}", data.len()),
+            ));
+        }
+        Ok(snap::raw::Encoder::new().compress_vec(&data)?)
+    }
+}
+
+/// Request/response protocols
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    PooledUserOpHashes,
+    PooledUserOpsByHash,
+}
+
+impl AsRef<str> for Protocol {
+    fn as_ref(&self) -> &str {
+        match self {
+            Protocol::PooledUserOpHashes => {
+                "/account_abstraction/req/pooled_user_op_hashes/1/ssz_snappy"
+            }
+            Protocol::PooledUserOpsByHash => {
+                "/account_abstraction/req/pooled_user_ops_by_hash/1/ssz_snappy"
+            }
+        }
+    }
+}
+
+/// Request messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Request {
+    PooledUserOpHashes(PooledUserOpHashesRequest),
+    PooledUserOpsByHash(PooledUserOpsByHashRequest),
+}
+
+impl Request {
+    pub(crate) fn protocol(&self) -> Protocol {
+        match self {
+            Request::PooledUserOpHashes(_) => Protocol::PooledUserOpHashes,
+            Request::PooledUserOpsByHash(_) => Protocol::PooledUserOpsByHash,
+        }
+    }
+}
+
+/// Response messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Response {
+    PooledUserOpHashes(PooledUserOpHashesResponse),
+    PooledUserOpsByHash(PooledUserOpsByHashResponse),
+    /// The request could not be served
+    ServerError(String),
+}
+
+/// `ssz_snappy` request/response codec.
+///
+/// Each chunk is `<varint length of SSZ bytes> | <snappy framed SSZ bytes>`. Responses
+/// are prefixed with a single result code byte.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Codec;
+
+#[async_trait::async_trait]
+impl request_response::Codec for Codec {
+    type Protocol = Protocol;
+    type Request = Request;
+    type Response = Response;
+
+    async fn read_request<T>(&mut self, protocol: &Protocol, io: &mut T) -> io::Result<Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = decode_chunk(&read_all(io).await?)?;
+        match protocol {
+            Protocol::PooledUserOpHashes => PooledUserOpHashesRequest::decode(&bytes)
+                .map(Request::PooledUserOpHashes)
+                .map_err(invalid_data),
+            Protocol::PooledUserOpsByHash => PooledUserOpsByHashRequest::decode(&bytes)
+                .map(Request::PooledUserOpsByHash)
+                .map_err(invalid_data),
+        }
+    }
+
+    async fn read_response<T>(&mut self, protocol: &Protocol, io: &mut T) -> io::Result<Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_all(io).await?;
+        let Some((code, chunk)) = bytes.split_first() else {
+            return Err(invalid_data("empty response"));
+        };
+        let bytes = decode_chunk(chunk)?;
+
+        if *code != RESPONSE_SUCCESS {
+            return Ok(Response::ServerError(
+                String::from_utf8_lossy(&bytes).into_owned(),
+            ));
+        }
+
+        match protocol {
+            Protocol::PooledUserOpHashes => PooledUserOpHashesResponse::decode(&bytes)
+                .map(Response::PooledUserOpHashes)
+                .map_err(invalid_data),
+            Protocol::PooledUserOpsByHash => PooledUserOpsByHashResponse::decode(&bytes)
+                .map(Response::PooledUserOpsByHash)
+                .map_err(invalid_data),
+        }
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _protocol: &Protocol,
+        io: &mut T,
+        req: Request,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let bytes = match req {
+            Request::PooledUserOpHashes(r) => r.encode(),
+            Request::PooledUserOpsByHash(r) => r.encode(),
+        };
+        io.write_all(&encode_chunk(&bytes)?).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _protocol: &Protocol,
+        io: &mut T,
+        res: Response,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let (code, bytes) = match res {
+            Response::PooledUserOpHashes(r) => (RESPONSE_SUCCESS, r.encode()),
+            Response::PooledUserOpsByHash(r) => (RESPONSE_SUCCESS, r.encode()),
+            Response::ServerError(msg) => (RESPONSE_SERVER_ERROR, msg.into_bytes()),
+        };
+        io.write_all(&[code]).await?;
+        io.write_all(&encode_chunk(&bytes)?).await
+    }
+}
+
+async fn read_all<T: AsyncRead + Unpin + Send>(io: &mut T) -> io::Result<Vec<u8>> {
+    let mut buf = vec![];
+    // allow for the result code, varint and snappy framing overhead
+    io.take(2 * MAX_CHUNK_SIZE as u64)
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+fn encode_chunk(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = encode_varint(bytes.len() as u64);
+    let mut encoder = snap::write::FrameEncoder::new(&mut out);
+    encoder.write_all(bytes)?;
+    encoder.flush()?;
+    drop(encoder);
+    Ok(out)
+}
+
+fn decode_chunk(chunk: &[u8]) -> io::Result<Vec<u8>> {
+    let (len, rest) = decode_varint(chunk)?;
+    if len > MAX_CHUNK_SIZE as u64 {
+        return Err(invalid_data(format!("chunk too large: {len}")));
+    }
+
+    let mut bytes = Vec::with_capacity(len as usize);
+    snap::read::FrameDecoder::new(rest)
+        .take(len)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(invalid_data(format!(
+            "chunk length mismatch: expected {len}, got {}",
+            bytes.len()
+        )));
+    }
+    Ok(bytes)
+}
+
+fn encode_varint(mut value: u64) -> Vec<u8> {
+    let mut out = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return out;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn decode_varint(bytes: &[u8]) -> io::Result<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(invalid_data("invalid varint"))
+}
+
+fn invalid_data<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use futures::io::Cursor;
+    use request_response::Codec as _;
+
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in [0, 1, 127, 128, 300, u64::MAX] {
+            let encoded = encode_varint(value);
+            assert_eq!(decode_varint(&encoded).unwrap(), (value, &[][..]));
+        }
+    }
+
+    #[test]
+    fn test_chunk_roundtrip() {
+        let bytes = vec![7u8; 1000];
+        assert_eq!(decode_chunk(&encode_chunk(&bytes).unwrap()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_gossip_transform_roundtrip() {
+        let topic = TopicHash::from_raw("topic");
+        let data = vec![3u8; 100];
+        let compressed = SnappyTransform
+            .outbound_transform(&topic, data.clone())
+            .unwrap();
+
+        let message = SnappyTransform
+            .inbound_transform(RawMessage {
+                source: None,
+                data: compressed,
+                sequence_number: None,
+                topic,
+                signature: None,
+                key: None,
+                validated: false,
+            })
+            .unwrap();
+        assert_eq!(message.data, data);
+    }
+
+    #[tokio::test]
+    async fn test_request_response_roundtrip() {
+        let mut codec = Codec;
+        let protocol = Protocol::PooledUserOpHashes;
+
+        let req = Request::PooledUserOpHashes(PooledUserOpHashesRequest {
+            mempool: B256::repeat_byte(1),
+            offset: 0,
+        });
+        let mut buf = vec![];
+        codec
+            .write_request(&protocol, &mut Cursor::new(&mut buf), req.clone())
+            .await
+            .unwrap();
+        let decoded = codec
+            .read_request(&protocol, &mut Cursor::new(buf))
+            .await
+            .unwrap();
+        assert_eq!(decoded, req);
+
+        let resp = Response::PooledUserOpHashes(PooledUserOpHashesResponse {
+            more_flag: false,
+            hashes: vec![B256::repeat_byte(2)],
+        });
+        let mut buf = vec![];
+        codec
+            .write_response(&protocol, &mut Cursor::new(&mut buf), resp.clone())
+            .await
+            .unwrap();
+        let decoded = codec
+            .read_response(&protocol, &mut Cursor::new(buf))
+            .await
+            .unwrap();
+        assert_eq!(decoded, resp);
+    }
+}
@@ -0,0 +1,37 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::net::Ipv4Addr;
+
+use alloy_primitives::B256;
+
+/// Configuration for the p2p network
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Address to listen on for p2p connections and discovery
+    pub listen_address: Ipv4Addr,
+    /// TCP port to listen on for p2p connections
+    pub tcp_port: u16,
+    /// UDP port to listen on for discovery
+    pub udp_port: u16,
+    /// Externally reachable address to advertise in the local ENR.
+    /// If not set, the listen address is advertised unless it is unspecified.
+    pub enr_address: Option<Ipv4Addr>,
+    /// Base64 encoded ENRs of the nodes to bootstrap discovery from
+    pub bootnodes: Vec<String>,
+    /// Secp256k1 private key of the node identity.
+    /// If not set, a random identity is generated on startup.
+    pub private_key: Option<B256>,
+    /// Number of peers to maintain connections to. Discovery stops when reached.
+    pub target_peers: usize,
+}
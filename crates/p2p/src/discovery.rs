@@ -0,0 +1,144 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Peer discovery via discv5.
+
+use std::net::IpAddr;
+
+use discv5::{
+    enr::{CombinedKey, CombinedPublicKey, EnrPublicKey, NodeId},
+    ConfigBuilder, Discv5, Enr, ListenConfig,
+};
+use futures::{future::BoxFuture, FutureExt};
+use libp2p::{identity, multiaddr::Protocol, Multiaddr, PeerId};
+use tracing::{debug, info};
+
+use crate::config::Config;
+
+/// Discovers peers to connect to using discv5
+pub(crate) struct Discovery {
+    discv5: Discv5,
+}
+
+impl Discovery {
+    /// Starts the discv5 service, using the secp256k1 `secret` as the node key
+    pub(crate) async fn start(config: &Config, mut secret: [u8; 32]) -> anyhow::Result<Self> {
+        let enr_key = CombinedKey::secp256k1_from_bytes(&mut secret)
+            .map_err(|e| anyhow::anyhow!("invalid node key: {e:?}"))?;
+
+        let mut builder = Enr::builder();
+        if let Some(ip) = config.enr_address {
+            builder.ip4(ip);
+        } else if !config.listen_address.is_unspecified() {
+            builder.ip4(config.listen_address);
+        }
+        let enr = builder
+            .tcp4(config.tcp_port)
+            .udp4(config.udp_port)
+            .build(&enr_key)
+            .map_err(|e| anyhow::anyhow!("failed to build local ENR: {e:?}"))?;
+        info!("Local ENR: {}", enr.to_base64());
+
+        let listen_config =
+            ListenConfig::from_ip(IpAddr::V4(config.listen_address), config.udp_port);
+        let mut discv5 = Discv5::new(enr, enr_key, ConfigBuilder::new(listen_config).build())
+            .map_err(anyhow::Error::msg)?;
+
+        for bootnode in &config.bootnodes {
+            let enr: Enr = bootnode
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid bootnode ENR {bootnode}: {e}"))?;
+            discv5.add_enr(enr).map_err(anyhow::Error::msg)?;
+        }
+
+        discv5
+            .start()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to start discv5: {e:?}"))?;
+
+        Ok(Self { discv5 })
+    }
+
+    /// Queries the DHT for a random node ID, returning the dialable peers found
+    pub(crate) fn find_peers(&self) -> BoxFuture<'static, Vec<(PeerId, Multiaddr)>> {
+        self.discv5
+            .find_node(NodeId::random())
+            .map(|res| match res {
+                Ok(enrs) => enrs.iter().filter_map(dial_address).collect(),
+                Err(e) => {
+                    debug!("discovery query failed: {e:?}");
+                    vec![]
+                }
+            })
+            .boxed()
+    }
+}
+
+/// Returns the peer ID and TCP address of the node described by an ENR, if it has one
+fn dial_address(enr: &Enr) -> Option<(PeerId, Multiaddr)> {
+    let ip = enr.ip4()?;
+    let tcp = enr.tcp4()?;
+    let public_key = match enr.public_key() {
+        pk @ CombinedPublicKey::Secp256k1(_) => {
+            identity::secp256k1::PublicKey::try_from_bytes(&pk.encode()).ok()?
+        }
+        _ => return None,
+    };
+    let peer_id = PeerId::from_public_key(&public_key.into());
+
+    let addr = Multiaddr::empty()
+        .with(Protocol::Ip4(ip))
+        .with(Protocol::Tcp(tcp))
+        .with(Protocol::P2p(peer_id));
+    Some((peer_id, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_dial_address() {
+        let keypair = identity::secp256k1::Keypair::generate();
+        let enr_key = CombinedKey::secp256k1_from_bytes(&mut keypair.secret().to_bytes()).unwrap();
+        let enr = Enr::builder()
+            .ip4(Ipv4Addr::new(10, 0, 0, 1))
+            .tcp4(4337)
+            .build(&enr_key)
+            .unwrap();
+
+        let (peer_id, addr) = dial_address(&enr).unwrap();
+        assert_eq!(
+            peer_id,
+            PeerId::from_public_key(&identity::PublicKey::from(keypair.public().clone()))
+        );
+        assert_eq!(
+            addr.to_string(),
+            format!("/ip4/10.0.0.1/tcp/4337/p2p/{peer_id}")
+        );
+    }
+
+    #[test]
+    fn test_dial_address_no_tcp() {
+        let enr_key = CombinedKey::generate_secp256k1();
+        let enr = Enr::builder()
+            .ip4(Ipv4Addr::new(10, 0, 0, 1))
+            .udp4(4337)
+            .build(&enr_key)
+            .unwrap();
+
+        assert!(dial_address(&enr).is_none());
+    }
+}
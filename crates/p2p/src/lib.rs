@@ -0,0 +1,44 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+#![warn(missing_docs, unreachable_pub)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![doc(test(
+    no_crate_inject,
+    attr(deny(warnings, rust_2018_idioms), allow(dead_code, unused_variables))
+))]
+//! ERC-4337 bundler p2p network for the Rundler.
+//!
+//! Implements peer discovery, gossip of user operations on a topic per shared mempool,
+//! and the pooled user operation request/response protocols used to sync mempools
+//! between peers.
+
+mod codec;
+
+mod config;
+pub use config::Config as P2pConfig;
+
+mod discovery;
+
+mod network;
+pub use network::{spawn, P2pHandle};
+
+mod pool;
+pub use pool::{GossipPool, GossipValidation};
+
+mod ssz;
+
+mod topics;
+pub use topics::MempoolTopic;
+
+mod types;
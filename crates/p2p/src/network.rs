@@ -0,0 +1,652 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy_primitives::B256;
+use anyhow::Context;
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use libp2p::{
+    gossipsub::{
+        self, MessageAcceptance, MessageAuthenticity, MessageId, TopicHash, ValidationMode,
+    },
+    identity,
+    request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel},
+    swarm::{NetworkBehaviour, SwarmEvent},
+    Multiaddr, PeerId, Swarm,
+};
+use metrics::{Counter, Gauge};
+use metrics_derive::Metrics;
+use rundler_task::{GracefulShutdown, TaskSpawnerExt};
+use rundler_types::{chain::ChainSpec, UserOperationVariant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::{
+    codec::{self, Codec, Protocol, Request, Response, SnappyTransform, MAX_GOSSIP_SIZE},
+    config::Config,
+    discovery::Discovery,
+    types::{
+        PooledUserOpHashesRequest, PooledUserOpHashesResponse, PooledUserOpsByHashRequest,
+        PooledUserOpsByHashResponse, UserOperationsWithEntryPoint, MAX_OPS_PER_REQUEST,
+    },
+    GossipPool, GossipValidation, MempoolTopic,
+};
+
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+const COMMAND_CHANNEL_CAPACITY: usize = 1024;
+
+/// A handle to the p2p network
+#[derive(Clone, Debug)]
+pub struct P2pHandle {
+    sender: mpsc::Sender<Command>,
+}
+
+impl P2pHandle {
+    /// Publishes user operations, verified at `verified_at_block_hash`, to the gossip
+    /// topic of a mempool.
+    ///
+    /// Publishing is best effort, the operations are dropped if the network is backed up.
+    pub fn publish(
+        &self,
+        mempool_id: B256,
+        verified_at_block_hash: B256,
+        user_operations: Vec<UserOperationVariant>,
+    ) {
+        let command = Command::Publish {
+            mempool_id,
+            verified_at_block_hash,
+            user_operations,
+        };
+        if let Err(e) = self.sender.try_send(command) {
+            warn!("failed to publish user operations to mempool {mempool_id}: {e}");
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Publish {
+        mempool_id: B256,
+        verified_at_block_hash: B256,
+        user_operations: Vec<UserOperationVariant>,
+    },
+}
+
+/// Starts the p2p network on the given task spawner, gossiping user operations on the
+/// topics of `mempools`.
+///
+/// Operations received from peers are validated by, and added to, `pool`.
+pub async fn spawn<T, P>(
+    task_spawner: &T,
+    config: Config,
+    chain_spec: ChainSpec,
+    mempools: Vec<MempoolTopic>,
+    pool: Arc<P>,
+) -> anyhow::Result<P2pHandle>
+where
+    T: TaskSpawnerExt,
+    P: GossipPool,
+{
+    let secret = match config.private_key {
+        Some(key) => identity::secp256k1::SecretKey::try_from_bytes(key.0)
+            .context("invalid p2p private key")?,
+        None => identity::secp256k1::SecretKey::generate(),
+    };
+    let keypair = identity::secp256k1::Keypair::from(secret);
+
+    let discovery = Discovery::start(&config, keypair.secret().to_bytes()).await?;
+    let mut swarm = build_swarm(keypair.into())?;
+    info!("Local peer id: {}", swarm.local_peer_id());
+
+    swarm
+        .listen_on(
+            format!("/ip4/{}/tcp/{}", config.listen_address, config.tcp_port)
+                .parse()
+                .context("invalid p2p listen address")?,
+        )
+        .context("failed to listen for p2p connections")?;
+
+    let mut topics = HashMap::new();
+    for mempool in mempools {
+        info!(
+            "Subscribing to mempool {} for entry point {}",
+            mempool.mempool_id, mempool.entry_point
+        );
+        swarm
+            .behaviour_mut()
+            .gossipsub
+            .subscribe(&mempool.topic())
+            .context("failed to subscribe to mempool topic")?;
+        topics.insert(mempool.topic_hash(), mempool);
+    }
+
+    let (sender, commands) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+    let network = Network {
+        target_peers: config.target_peers,
+        chain_spec,
+        topics,
+        pool,
+        swarm,
+        discovery,
+        commands,
+        tasks: FuturesUnordered::new(),
+        pending_requests: HashMap::new(),
+        discovering: false,
+        metrics: P2pMetrics::default(),
+    };
+
+    task_spawner.spawn_critical_with_graceful_shutdown_signal("p2p network", |shutdown| {
+        network.run(shutdown)
+    });
+
+    Ok(P2pHandle { sender })
+}
+
+fn build_swarm(keypair: identity::Keypair) -> anyhow::Result<Swarm<Behaviour>> {
+    let gossipsub_config = gossipsub::ConfigBuilder::default()
+        .validation_mode(ValidationMode::Anonymous)
+        .validate_messages()
+        .message_id_fn(codec::message_id)
+        .max_transmit_size(MAX_GOSSIP_SIZE)
+        .heartbeat_interval(Duration::from_millis(700))
+        .build()
+        .context("invalid gossipsub config")?;
+    let gossipsub = gossipsub::Behaviour::new_with_transform(
+        MessageAuthenticity::Anonymous,
+        gossipsub_config,
+        None,
+        SnappyTransform,
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    let request_response = request_response::Behaviour::new(
+        [
+            (Protocol::PooledUserOpHashes, ProtocolSupport::Full),
+            (Protocol::PooledUserOpsByHash, ProtocolSupport::Full),
+        ],
+        request_response::Config::default().with_request_timeout(REQUEST_TIMEOUT),
+    );
+
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_tcp(
+            libp2p::tcp::Config::default(),
+            libp2p::noise::Config::new,
+            libp2p::yamux::Config::default,
+        )?
+        .with_behaviour(|_| Behaviour {
+            gossipsub,
+            request_response,
+        })?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(IDLE_CONNECTION_TIMEOUT))
+        .build();
+
+    Ok(swarm)
+}
+
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    gossipsub: gossipsub::Behaviour<SnappyTransform>,
+    request_response: request_response::Behaviour<Codec>,
+}
+
+/// Results of work done off of the network loop
+enum TaskResult {
+    /// Operations received from a peer were validated by the pool.
+    /// Gossiped operations carry the message to report the validation result for.
+    Validated {
+        message: Option<(MessageId, PeerId)>,
+        result: GossipValidation,
+    },
+    /// A response to an inbound request is ready
+    Respond {
+        channel: ResponseChannel<Response>,
+        response: Response,
+    },
+    /// Hashes of a peer's pooled operations that are missing from the local pool
+    MissingHashes {
+        peer: PeerId,
+        mempool: MempoolTopic,
+        hashes: Vec<B256>,
+    },
+    /// Peers found by discovery
+    Discovered(Vec<(PeerId, Multiaddr)>),
+}
+
+struct Network<P> {
+    target_peers: usize,
+    chain_spec: ChainSpec,
+    topics: HashMap<TopicHash, MempoolTopic>,
+    pool: Arc<P>,
+    swarm: Swarm<Behaviour>,
+    discovery: Discovery,
+    commands: mpsc::Receiver<Command>,
+    tasks: FuturesUnordered<BoxFuture<'static, TaskResult>>,
+    pending_requests: HashMap<OutboundRequestId, (MempoolTopic, u64)>,
+    discovering: bool,
+    metrics: P2pMetrics,
+}
+
+impl<P: GossipPool> Network<P> {
+    async fn run(mut self, shutdown: GracefulShutdown) {
+        let mut discovery_interval = tokio::time::interval(DISCOVERY_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.clone() => {
+                    info!("Shutting down p2p network");
+                    break;
+                }
+                event = self.swarm.select_next_some() => {
+                    self.handle_swarm_event(event);
+                }
+                Some(command) = self.commands.recv() => {
+                    self.handle_command(command);
+                }
+                Some(result) = self.tasks.next() => {
+                    self.handle_task_result(result);
+                }
+                _ = discovery_interval.tick() => {
+                    self.discover();
+                }
+            }
+        }
+    }
+
+    fn handle_swarm_event(&mut self, event: SwarmEvent<BehaviourEvent>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                info!("Listening for p2p connections on {address}");
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                debug!("Connected to peer {peer_id}");
+                self.metrics
+                    .connected_peers
+                    .set(self.swarm.connected_peers().count() as f64);
+            }
+            SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                debug!("Disconnected from peer {peer_id}");
+                self.metrics
+                    .connected_peers
+                    .set(self.swarm.connected_peers().count() as f64);
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(event)) => {
+                self.handle_gossip_event(event);
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(event)) => {
+                self.handle_request_response_event(event);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_gossip_event(&mut self, event: gossipsub::Event) {
+        match event {
+            gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            } => {
+                self.metrics.gossip_messages_received.increment(1);
+                let Some(mempool) = self.topics.get(&message.topic).cloned() else {
+                    self.report(&message_id, &propagation_source, GossipValidation::Ignore);
+                    return;
+                };
+
+                let msg = match UserOperationsWithEntryPoint::decode(
+                    &message.data,
+                    mempool.entry_point_version,
+                    &self.chain_spec,
+                ) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        debug!("invalid gossip message from {propagation_source}: {e}");
+                        self.report(&message_id, &propagation_source, GossipValidation::Reject);
+                        return;
+                    }
+                };
+                if msg.chain_id != self.chain_spec.id || msg.entry_point != mempool.entry_point {
+                    debug!(
+                        "gossip message from {propagation_source} for wrong chain or entry point"
+                    );
+                    self.report(&message_id, &propagation_source, GossipValidation::Reject);
+                    return;
+                }
+
+                self.validate(
+                    mempool,
+                    msg.user_operations,
+                    Some((message_id, propagation_source)),
+                );
+            }
+            gossipsub::Event::Subscribed { peer_id, topic } => {
+                // Sync the peer's pool when it joins one of our mempools
+                if let Some(mempool) = self.topics.get(&topic).cloned() {
+                    self.request_hashes(peer_id, mempool, 0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_request_response_event(&mut self, event: request_response::Event<Request, Response>) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+            } => {
+                debug!("Received {:?} request from {peer}", request.protocol());
+                self.respond(request, channel);
+            }
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+            } => {
+                let Some((mempool, offset)) = self.pending_requests.remove(&request_id) else {
+                    return;
+                };
+                self.handle_response(peer, mempool, offset, response);
+            }
+            request_response::Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                self.pending_requests.remove(&request_id);
+                debug!("Request to {peer} failed: {error}");
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                debug!("Request from {peer} failed: {error}");
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+
+    fn handle_response(
+        &mut self,
+        peer: PeerId,
+        mempool: MempoolTopic,
+        offset: u64,
+        response: Response,
+    ) {
+        match response {
+            Response::PooledUserOpHashes(PooledUserOpHashesResponse { more_flag, hashes }) => {
+                if more_flag {
+                    self.request_hashes(peer, mempool.clone(), offset + hashes.len() as u64);
+                }
+
+                let pool = Arc::clone(&self.pool);
+                self.tasks.push(
+                    async move {
+                        let known: HashSet<B256> = match pool
+                            .pooled_op_hashes(mempool.entry_point, mempool.mempool_id)
+                            .await
+                        {
+                            Ok(known) => known.into_iter().collect(),
+                            Err(e) => {
+                                warn!("failed to get pooled op hashes: {e:?}");
+                                HashSet::new()
+                            }
+                        };
+                        TaskResult::MissingHashes {
+                            peer,
+                            mempool,
+                            hashes: hashes.into_iter().filter(|h| !known.contains(h)).collect(),
+                        }
+                    }
+                    .boxed(),
+                );
+            }
+            Response::PooledUserOpsByHash(response) => {
+                match response.user_operations(mempool.entry_point_version, &self.chain_spec) {
+                    Ok(ops) => self.validate(mempool, ops, None),
+                    Err(e) => debug!("invalid pooled ops response from {peer}: {e}"),
+                }
+            }
+            Response::ServerError(msg) => {
+                debug!("Request to {peer} failed with server error: {msg}");
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Publish {
+                mempool_id,
+                verified_at_block_hash,
+                user_operations,
+            } => {
+                let Some((topic, mempool)) =
+                    self.topics.iter().find(|(_, m)| m.mempool_id == mempool_id)
+                else {
+                    debug!("Not publishing to unknown mempool {mempool_id}");
+                    return;
+                };
+
+                let msg = UserOperationsWithEntryPoint {
+                    entry_point: mempool.entry_point,
+                    verified_at_block_hash,
+                    chain_id: self.chain_spec.id,
+                    user_operations,
+                };
+                match self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(topic.clone(), msg.encode())
+                {
+                    Ok(_) => self.metrics.gossip_messages_published.increment(1),
+                    Err(gossipsub::PublishError::InsufficientPeers) => {
+                        debug!("No peers to publish to on mempool {mempool_id}");
+                    }
+                    Err(e) => warn!("failed to publish to mempool {mempool_id}: {e}"),
+                }
+            }
+        }
+    }
+
+    fn handle_task_result(&mut self, result: TaskResult) {
+        match result {
+            TaskResult::Validated { message, result } => {
+                if let Some((message_id, peer)) = message {
+                    self.report(&message_id, &peer, result);
+                }
+            }
+            TaskResult::Respond { channel, response } => {
+                if self
+                    .swarm
+                    .behaviour_mut()
+                    .request_response
+                    .send_response(channel, response)
+                    .is_err()
+                {
+                    debug!("failed to send response, connection closed");
+                }
+            }
+            TaskResult::MissingHashes {
+                peer,
+                mempool,
+                hashes,
+            } => {
+                if hashes.is_empty() {
+                    return;
+                }
+                let request_id = self.swarm.behaviour_mut().request_response.send_request(
+                    &peer,
+                    Request::PooledUserOpsByHash(PooledUserOpsByHashRequest { hashes }),
+                );
+                self.pending_requests.insert(request_id, (mempool, 0));
+            }
+            TaskResult::Discovered(peers) => {
+                self.discovering = false;
+                let local_peer_id = *self.swarm.local_peer_id();
+                for (peer_id, addr) in peers {
+                    if peer_id == local_peer_id || self.swarm.is_connected(&peer_id) {
+                        continue;
+                    }
+                    debug!("Dialing discovered peer {addr}");
+                    if let Err(e) = self.swarm.dial(addr) {
+                        debug!("failed to dial {peer_id}: {e}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn discover(&mut self) {
+        if self.discovering || self.swarm.connected_peers().count() >= self.target_peers {
+            return;
+        }
+        self.discovering = true;
+        self.tasks.push(
+            self.discovery
+                .find_peers()
+                .map(TaskResult::Discovered)
+                .boxed(),
+        );
+    }
+
+    fn request_hashes(&mut self, peer: PeerId, mempool: MempoolTopic, offset: u64) {
+        let request_id = self.swarm.behaviour_mut().request_response.send_request(
+            &peer,
+            Request::PooledUserOpHashes(PooledUserOpHashesRequest {
+                mempool: mempool.mempool_id,
+                offset,
+            }),
+        );
+        self.pending_requests.insert(request_id, (mempool, offset));
+    }
+
+    /// Validates operations with the pool, reporting the result to gossipsub if they
+    /// were received in a gossip message.
+    fn validate(
+        &mut self,
+        mempool: MempoolTopic,
+        ops: Vec<UserOperationVariant>,
+        message: Option<(MessageId, PeerId)>,
+    ) {
+        let pool = Arc::clone(&self.pool);
+        self.tasks.push(
+            async move {
+                let mut result = GossipValidation::Ignore;
+                for op in ops {
+                    match pool.add_external_op(mempool.entry_point, op).await {
+                        GossipValidation::Reject => {
+                            result = GossipValidation::Reject;
+                            break;
+                        }
+                        GossipValidation::Accept => result = GossipValidation::Accept,
+                        GossipValidation::Ignore => {}
+                    }
+                }
+                TaskResult::Validated { message, result }
+            }
+            .boxed(),
+        );
+    }
+
+    fn respond(&mut self, request: Request, channel: ResponseChannel<Response>) {
+        let pool = Arc::clone(&self.pool);
+        let mempool = match &request {
+            Request::PooledUserOpHashes(r) => self
+                .topics
+                .values()
+                .find(|m| m.mempool_id == r.mempool)
+                .cloned(),
+            Request::PooledUserOpsByHash(_) => None,
+        };
+
+        self.tasks.push(
+            async move {
+                let response = match request {
+                    Request::PooledUserOpHashes(PooledUserOpHashesRequest {
+                        mempool: id,
+                        offset,
+                    }) => match mempool {
+                        Some(mempool) => match pool
+                            .pooled_op_hashes(mempool.entry_point, mempool.mempool_id)
+                            .await
+                        {
+                            Ok(hashes) => {
+                                let start = (offset as usize).min(hashes.len());
+                                let end = (start + MAX_OPS_PER_REQUEST).min(hashes.len());
+                                Response::PooledUserOpHashes(PooledUserOpHashesResponse {
+                                    more_flag: end < hashes.len(),
+                                    hashes: hashes[start..end].to_vec(),
+                                })
+                            }
+                            Err(e) => Response::ServerError(e.to_string()),
+                        },
+                        None => Response::ServerError(format!("unknown mempool {id}")),
+                    },
+                    Request::PooledUserOpsByHash(PooledUserOpsByHashRequest { hashes }) => {
+                        match pool.pooled_ops_by_hash(hashes).await {
+                            Ok(ops) => Response::PooledUserOpsByHash(
+                                PooledUserOpsByHashResponse::new(&ops),
+                            ),
+                            Err(e) => Response::ServerError(e.to_string()),
+                        }
+                    }
+                };
+                TaskResult::Respond { channel, response }
+            }
+            .boxed(),
+        );
+    }
+
+    fn report(&mut self, message_id: &MessageId, peer: &PeerId, result: GossipValidation) {
+        let acceptance = match result {
+            GossipValidation::Accept => MessageAcceptance::Accept,
+            GossipValidation::Reject => {
+                self.metrics.gossip_messages_rejected.increment(1);
+                MessageAcceptance::Reject
+            }
+            GossipValidation::Ignore => MessageAcceptance::Ignore,
+        };
+        if let Err(e) = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(message_id, peer, acceptance)
+        {
+            debug!("failed to report validation result for {message_id}: {e}");
+        }
+    }
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "p2p")]
+struct P2pMetrics {
+    #[metric(describe = "the number of connected peers.")]
+    connected_peers: Gauge,
+    #[metric(describe = "the count of gossip messages received.")]
+    gossip_messages_received: Counter,
+    #[metric(describe = "the count of gossip messages rejected as invalid.")]
+    gossip_messages_rejected: Counter,
+    #[metric(describe = "the count of gossip messages published.")]
+    gossip_messages_published: Counter,
+}
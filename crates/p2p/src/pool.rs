@@ -0,0 +1,52 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, B256};
+use rundler_types::UserOperationVariant;
+
+/// Result of validating a user operation received from a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GossipValidation {
+    /// The operation was added to the pool, and should be propagated
+    Accept,
+    /// The operation is invalid, the peer that sent it should be penalized
+    Reject,
+    /// The operation was not added to the pool, but the peer should not be penalized.
+    /// For example, the operation is already known.
+    Ignore,
+}
+
+/// The mempool that the p2p network feeds operations into.
+#[async_trait::async_trait]
+pub trait GossipPool: Send + Sync + 'static {
+    /// Validates an operation received from a peer and adds it to the pool
+    async fn add_external_op(
+        &self,
+        entry_point: Address,
+        op: UserOperationVariant,
+    ) -> GossipValidation;
+
+    /// Returns the hashes of the operations in the pool for an entry point that
+    /// belong to the given mempool
+    async fn pooled_op_hashes(
+        &self,
+        entry_point: Address,
+        mempool_id: B256,
+    ) -> anyhow::Result<Vec<B256>>;
+
+    /// Returns the operations in the pool with the given hashes, skipping unknown hashes
+    async fn pooled_ops_by_hash(
+        &self,
+        hashes: Vec<B256>,
+    ) -> anyhow::Result<Vec<UserOperationVariant>>;
+}
@@ -0,0 +1,342 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Minimal SSZ encoding for the types used by the bundler p2p protocol.
+//!
+//! Only the subset of SSZ needed for the protocol messages is supported: fixed size
+//! basic types, byte lists, and containers/lists built from them.
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+
+const OFFSET_SIZE: usize = 4;
+
+/// SSZ decoding error
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub(crate) enum SszError {
+    /// Input length does not match the expected length
+    #[error("invalid length: expected {expected}, got {actual}")]
+    InvalidLength {
+        /// Expected length
+        expected: usize,
+        /// Actual length
+        actual: usize,
+    },
+    /// A variable field offset is out of bounds or out of order
+    #[error("invalid offset {0}")]
+    InvalidOffset(usize),
+    /// A list contains more items than allowed
+    #[error("too many items: max {max}, got {actual}")]
+    TooManyItems {
+        /// Maximum number of items
+        max: usize,
+        /// Actual number of items
+        actual: usize,
+    },
+}
+
+/// A field of an SSZ container, used for encoding
+pub(crate) enum Field {
+    /// A fixed size field, encoded inline
+    Fixed(Vec<u8>),
+    /// A variable size field, encoded as an offset with its data appended
+    Variable(Vec<u8>),
+}
+
+/// The layout of a field of an SSZ container, used for decoding
+#[derive(Clone, Copy)]
+pub(crate) enum Kind {
+    /// A fixed size field of the given length
+    Fixed(usize),
+    /// A variable size field
+    Variable,
+}
+
+/// Encodes a container from its fields, in order
+pub(crate) fn encode_container(fields: Vec<Field>) -> Vec<u8> {
+    let fixed_len: usize = fields
+        .iter()
+        .map(|f| match f {
+            Field::Fixed(b) => b.len(),
+            Field::Variable(_) => OFFSET_SIZE,
+        })
+        .sum();
+
+    let mut fixed = Vec::with_capacity(fixed_len);
+    let mut variable = vec![];
+    for field in fields {
+        match field {
+            Field::Fixed(b) => fixed.extend_from_slice(&b),
+            Field::Variable(b) => {
+                fixed.extend_from_slice(&encode_offset(fixed_len + variable.len()));
+                variable.extend_from_slice(&b);
+            }
+        }
+    }
+
+    fixed.extend_from_slice(&variable);
+    fixed
+}
+
+/// Decodes a container into the byte slices of its fields, in order
+pub(crate) fn decode_container<'a>(
+    bytes: &'a [u8],
+    layout: &[Kind],
+) -> Result<Vec<&'a [u8]>, SszError> {
+    let fixed_len: usize = layout
+        .iter()
+        .map(|k| match k {
+            Kind::Fixed(len) => *len,
+            Kind::Variable => OFFSET_SIZE,
+        })
+        .sum();
+    if bytes.len() < fixed_len {
+        return Err(SszError::InvalidLength {
+            expected: fixed_len,
+            actual: bytes.len(),
+        });
+    }
+
+    // first pass, collect fixed fields and offsets
+    let mut pos = 0;
+    let mut offsets = vec![];
+    let mut fields = vec![];
+    for (i, kind) in layout.iter().enumerate() {
+        match kind {
+            Kind::Fixed(len) => {
+                fields.push(&bytes[pos..pos + len]);
+                pos += len;
+            }
+            Kind::Variable => {
+                offsets.push((i, decode_offset(&bytes[pos..pos + OFFSET_SIZE])));
+                fields.push(&[][..]);
+                pos += OFFSET_SIZE;
+            }
+        }
+    }
+
+    if offsets.is_empty() && bytes.len() != fixed_len {
+        return Err(SszError::InvalidLength {
+            expected: fixed_len,
+            actual: bytes.len(),
+        });
+    }
+
+    // second pass, slice variable fields between consecutive offsets
+    for (j, (i, start)) in offsets.iter().enumerate() {
+        let end = offsets.get(j + 1).map(|(_, o)| *o).unwrap_or(bytes.len());
+        if (j == 0 && *start != fixed_len) || *start > end || end > bytes.len() {
+            return Err(SszError::InvalidOffset(*start));
+        }
+        fields[*i] = &bytes[*start..end];
+    }
+
+    Ok(fields)
+}
+
+/// Encodes a list of variable size items
+pub(crate) fn encode_variable_list(items: Vec<Vec<u8>>) -> Vec<u8> {
+    encode_container(items.into_iter().map(Field::Variable).collect())
+}
+
+/// Decodes a list of variable size items into the byte slices of its items
+pub(crate) fn decode_variable_list(bytes: &[u8], max: usize) -> Result<Vec<&[u8]>, SszError> {
+    if bytes.is_empty() {
+        return Ok(vec![]);
+    }
+    if bytes.len() < OFFSET_SIZE {
+        return Err(SszError::InvalidLength {
+            expected: OFFSET_SIZE,
+            actual: bytes.len(),
+        });
+    }
+
+    let first = decode_offset(&bytes[..OFFSET_SIZE]);
+    if first % OFFSET_SIZE != 0 || first == 0 {
+        return Err(SszError::InvalidOffset(first));
+    }
+    let count = first / OFFSET_SIZE;
+    if count > max {
+        return Err(SszError::TooManyItems { max, actual: count });
+    }
+
+    decode_container(bytes, &vec![Kind::Variable; count])
+}
+
+/// Encodes a list of fixed size items
+pub(crate) fn encode_fixed_list(items: Vec<Vec<u8>>) -> Vec<u8> {
+    items.concat()
+}
+
+/// Decodes a list of fixed size items into the byte slices of its items
+pub(crate) fn decode_fixed_list(
+    bytes: &[u8],
+    item_len: usize,
+    max: usize,
+) -> Result<Vec<&[u8]>, SszError> {
+    if bytes.len() % item_len != 0 {
+        return Err(SszError::InvalidLength {
+            expected: bytes.len() - bytes.len() % item_len,
+            actual: bytes.len(),
+        });
+    }
+    let count = bytes.len() / item_len;
+    if count > max {
+        return Err(SszError::TooManyItems { max, actual: count });
+    }
+
+    Ok(bytes.chunks_exact(item_len).collect())
+}
+
+pub(crate) fn encode_u64(value: u64) -> Vec<u8> {
+    value.to_le_bytes().to_vec()
+}
+
+pub(crate) fn decode_u64(bytes: &[u8]) -> Result<u64, SszError> {
+    let bytes: [u8; 8] = bytes.try_into().map_err(|_| SszError::InvalidLength {
+        expected: 8,
+        actual: bytes.len(),
+    })?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn encode_u256(value: U256) -> Vec<u8> {
+    value.to_le_bytes::<32>().to_vec()
+}
+
+pub(crate) fn decode_u256(bytes: &[u8]) -> Result<U256, SszError> {
+    check_len(bytes, 32)?;
+    Ok(U256::from_le_slice(bytes))
+}
+
+pub(crate) fn encode_address(value: Address) -> Vec<u8> {
+    value.to_vec()
+}
+
+pub(crate) fn decode_address(bytes: &[u8]) -> Result<Address, SszError> {
+    check_len(bytes, 20)?;
+    Ok(Address::from_slice(bytes))
+}
+
+pub(crate) fn encode_b256(value: B256) -> Vec<u8> {
+    value.to_vec()
+}
+
+pub(crate) fn decode_b256(bytes: &[u8]) -> Result<B256, SszError> {
+    check_len(bytes, 32)?;
+    Ok(B256::from_slice(bytes))
+}
+
+pub(crate) fn decode_bytes(bytes: &[u8]) -> Bytes {
+    Bytes::copy_from_slice(bytes)
+}
+
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), SszError> {
+    if bytes.len() != expected {
+        return Err(SszError::InvalidLength {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    Ok(())
+}
+
+fn encode_offset(offset: usize) -> [u8; OFFSET_SIZE] {
+    (offset as u32).to_le_bytes()
+}
+
+fn decode_offset(bytes: &[u8]) -> usize {
+    u32::from_le_bytes(bytes.try_into().unwrap()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_roundtrip() {
+        let encoded = encode_container(vec![
+            Field::Fixed(encode_u64(7)),
+            Field::Variable(vec![1, 2, 3]),
+            Field::Fixed(encode_address(Address::repeat_byte(0xaa))),
+            Field::Variable(vec![]),
+            Field::Variable(vec![4]),
+        ]);
+
+        let fields = decode_container(
+            &encoded,
+            &[
+                Kind::Fixed(8),
+                Kind::Variable,
+                Kind::Fixed(20),
+                Kind::Variable,
+                Kind::Variable,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(decode_u64(fields[0]).unwrap(), 7);
+        assert_eq!(fields[1], &[1, 2, 3]);
+        assert_eq!(
+            decode_address(fields[2]).unwrap(),
+            Address::repeat_byte(0xaa)
+        );
+        assert!(fields[3].is_empty());
+        assert_eq!(fields[4], &[4]);
+    }
+
+    #[test]
+    fn test_container_invalid_offset() {
+        let mut encoded = encode_container(vec![
+            Field::Fixed(encode_u64(7)),
+            Field::Variable(vec![1, 2, 3]),
+        ]);
+        // point the offset past the end of the input
+        encoded[8] = 0xff;
+
+        assert_eq!(
+            decode_container(&encoded, &[Kind::Fixed(8), Kind::Variable]),
+            Err(SszError::InvalidOffset(0xff))
+        );
+    }
+
+    #[test]
+    fn test_u256_little_endian() {
+        let encoded = encode_u256(U256::from(1));
+        assert_eq!(encoded[0], 1);
+        assert_eq!(decode_u256(&encoded).unwrap(), U256::from(1));
+    }
+
+    #[test]
+    fn test_variable_list_roundtrip() {
+        let items = vec![vec![1], vec![], vec![2, 3]];
+        let encoded = encode_variable_list(items.clone());
+        let decoded = decode_variable_list(&encoded, 3).unwrap();
+        assert_eq!(decoded, items.iter().map(|i| &i[..]).collect::<Vec<_>>());
+
+        assert_eq!(
+            decode_variable_list(&encoded, 2),
+            Err(SszError::TooManyItems { max: 2, actual: 3 })
+        );
+        assert!(decode_variable_list(&[], 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fixed_list_roundtrip() {
+        let items = vec![encode_b256(B256::ZERO), encode_b256(B256::repeat_byte(1))];
+        let encoded = encode_fixed_list(items);
+        let decoded = decode_fixed_list(&encoded, 32, 10).unwrap();
+        assert_eq!(decode_b256(decoded[1]).unwrap(), B256::repeat_byte(1));
+
+        assert!(decode_fixed_list(&encoded[1..], 32, 10).is_err());
+    }
+}
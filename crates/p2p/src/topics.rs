@@ -0,0 +1,70 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{hex, Address, B256};
+use libp2p::gossipsub::{IdentTopic, TopicHash};
+use rundler_types::EntryPointVersion;
+
+const TOPIC_PREFIX: &str = "account_abstraction";
+const USER_OPERATIONS_WITH_ENTRY_POINT: &str = "user_operations_with_entry_point";
+const ENCODING: &str = "ssz_snappy";
+
+/// A mempool shared over the p2p network.
+///
+/// Each mempool maps to its own gossip topic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MempoolTopic {
+    /// The mempool ID
+    pub mempool_id: B256,
+    /// The entry point the mempool is associated with
+    pub entry_point: Address,
+    /// The version of the entry point
+    pub entry_point_version: EntryPointVersion,
+}
+
+impl MempoolTopic {
+    /// Returns the gossip topic for user operations in this mempool
+    pub(crate) fn topic(&self) -> IdentTopic {
+        IdentTopic::new(format!(
+            "/{TOPIC_PREFIX}/{}/{USER_OPERATIONS_WITH_ENTRY_POINT}/{ENCODING}",
+            hex::encode(self.mempool_id)
+        ))
+    }
+
+    /// Returns the hash of the gossip topic for user operations in this mempool
+    pub(crate) fn topic_hash(&self) -> TopicHash {
+        self.topic().hash()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic() {
+        let topic = MempoolTopic {
+            mempool_id: B256::repeat_byte(0xab),
+            entry_point: Address::ZERO,
+            entry_point_version: EntryPointVersion::V0_7,
+        };
+
+        assert_eq!(
+            topic.topic_hash().as_str(),
+            format!(
+                "/account_abstraction/{}/user_operations_with_entry_point/ssz_snappy",
+                "ab".repeat(32)
+            )
+        );
+    }
+}
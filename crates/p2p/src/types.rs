@@ -0,0 +1,471 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Bundler p2p protocol messages.
+
+use alloy_primitives::{ruint::FromUintError, Address, Bytes, B256, U256};
+use rundler_contracts::v0_7::PackedUserOperation;
+use rundler_types::{
+    chain::ChainSpec,
+    v0_6::{self, ContractUserOperation},
+    v0_7, EntryPointVersion, UserOperationVariant,
+};
+
+use crate::ssz::{self, Field, Kind, SszError};
+
+/// Maximum number of user operations in a single message
+pub(crate) const MAX_OPS_PER_REQUEST: usize = 4096;
+
+/// Error decoding a protocol message
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DecodeError {
+    /// Invalid SSZ encoding
+    #[error(transparent)]
+    Ssz(#[from] SszError),
+    /// A field value is out of range
+    #[error("field out of range: {0}")]
+    OutOfRange(#[from] FromUintError<u128>),
+    /// The entry point version is not supported
+    #[error("unsupported entry point version {0:?}")]
+    UnsupportedVersion(EntryPointVersion),
+}
+
+/// Gossip message carrying user operations for a single entry point
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct UserOperationsWithEntryPoint {
+    /// Entry point of the user operations
+    pub(crate) entry_point: Address,
+    /// Block hash the user operations were verified at
+    pub(crate) verified_at_block_hash: B256,
+    /// Chain ID of the user operations
+    pub(crate) chain_id: u64,
+    /// The user operations
+    pub(crate) user_operations: Vec<UserOperationVariant>,
+}
+
+const USER_OPERATIONS_WITH_ENTRY_POINT_LAYOUT: [Kind; 4] = [
+    Kind::Fixed(20),
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Variable,
+];
+
+impl UserOperationsWithEntryPoint {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        ssz::encode_container(vec![
+            Field::Fixed(ssz::encode_address(self.entry_point)),
+            Field::Fixed(ssz::encode_u256(self.verified_at_block_hash.into())),
+            Field::Fixed(ssz::encode_u256(U256::from(self.chain_id))),
+            Field::Variable(ssz::encode_variable_list(
+                self.user_operations.iter().map(encode_user_op).collect(),
+            )),
+        ])
+    }
+
+    /// Decodes a message, interpreting the user operations as `version` operations
+    pub(crate) fn decode(
+        bytes: &[u8],
+        version: EntryPointVersion,
+        chain_spec: &ChainSpec,
+    ) -> Result<Self, DecodeError> {
+        let fields = ssz::decode_container(bytes, &USER_OPERATIONS_WITH_ENTRY_POINT_LAYOUT)?;
+        let chain_id = ssz::decode_u256(fields[2])?;
+
+        Ok(Self {
+            entry_point: ssz::decode_address(fields[0])?,
+            verified_at_block_hash: ssz::decode_u256(fields[1])?.into(),
+            chain_id: chain_id.try_into().unwrap_or(u64::MAX),
+            user_operations: ssz::decode_variable_list(fields[3], MAX_OPS_PER_REQUEST)?
+                .into_iter()
+                .map(|b| decode_user_op(b, version, chain_spec))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Request for the hashes of the user operations in a peer's mempool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PooledUserOpHashesRequest {
+    /// The mempool ID
+    pub(crate) mempool: B256,
+    /// Offset into the peer's list of hashes
+    pub(crate) offset: u64,
+}
+
+impl PooledUserOpHashesRequest {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        ssz::encode_container(vec![
+            Field::Fixed(ssz::encode_b256(self.mempool)),
+            Field::Fixed(ssz::encode_u64(self.offset)),
+        ])
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = ssz::decode_container(bytes, &[Kind::Fixed(32), Kind::Fixed(8)])?;
+        Ok(Self {
+            mempool: ssz::decode_b256(fields[0])?,
+            offset: ssz::decode_u64(fields[1])?,
+        })
+    }
+}
+
+/// Response with the hashes of the user operations in a mempool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PooledUserOpHashesResponse {
+    /// True if there are more hashes after this page
+    pub(crate) more_flag: bool,
+    /// The user operation hashes
+    pub(crate) hashes: Vec<B256>,
+}
+
+impl PooledUserOpHashesResponse {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        ssz::encode_container(vec![
+            Field::Fixed(ssz::encode_u64(self.more_flag as u64)),
+            Field::Variable(encode_hashes(&self.hashes)),
+        ])
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = ssz::decode_container(bytes, &[Kind::Fixed(8), Kind::Variable])?;
+        Ok(Self {
+            more_flag: ssz::decode_u64(fields[0])? != 0,
+            hashes: decode_hashes(fields[1])?,
+        })
+    }
+}
+
+/// Request for user operations by hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PooledUserOpsByHashRequest {
+    /// The user operation hashes
+    pub(crate) hashes: Vec<B256>,
+}
+
+impl PooledUserOpsByHashRequest {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        ssz::encode_container(vec![Field::Variable(encode_hashes(&self.hashes))])
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = ssz::decode_container(bytes, &[Kind::Variable])?;
+        Ok(Self {
+            hashes: decode_hashes(fields[0])?,
+        })
+    }
+}
+
+/// Response with user operations by hash
+///
+/// The user operations are left encoded, as the version of the operations is only
+/// known to the requester.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PooledUserOpsByHashResponse {
+    /// The SSZ encoded user operations
+    pub(crate) user_ops: Vec<Bytes>,
+}
+
+impl PooledUserOpsByHashResponse {
+    pub(crate) fn new(user_ops: &[UserOperationVariant]) -> Self {
+        Self {
+            user_ops: user_ops
+                .iter()
+                .map(|op| encode_user_op(op).into())
+                .collect(),
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        ssz::encode_container(vec![Field::Variable(ssz::encode_variable_list(
+            self.user_ops.iter().map(|op| op.to_vec()).collect(),
+        ))])
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = ssz::decode_container(bytes, &[Kind::Variable])?;
+        Ok(Self {
+            user_ops: ssz::decode_variable_list(fields[0], MAX_OPS_PER_REQUEST)?
+                .into_iter()
+                .map(ssz::decode_bytes)
+                .collect(),
+        })
+    }
+
+    /// Decodes the user operations as `version` operations
+    pub(crate) fn user_operations(
+        &self,
+        version: EntryPointVersion,
+        chain_spec: &ChainSpec,
+    ) -> Result<Vec<UserOperationVariant>, DecodeError> {
+        self.user_ops
+            .iter()
+            .map(|b| decode_user_op(b, version, chain_spec))
+            .collect()
+    }
+}
+
+fn encode_hashes(hashes: &[B256]) -> Vec<u8> {
+    ssz::encode_fixed_list(hashes.iter().map(|h| ssz::encode_b256(*h)).collect())
+}
+
+fn decode_hashes(bytes: &[u8]) -> Result<Vec<B256>, DecodeError> {
+    Ok(ssz::decode_fixed_list(bytes, 32, MAX_OPS_PER_REQUEST)?
+        .into_iter()
+        .map(ssz::decode_b256)
+        .collect::<Result<_, _>>()?)
+}
+
+const USER_OPERATION_V0_6_LAYOUT: [Kind; 11] = [
+    Kind::Fixed(20),
+    Kind::Fixed(32),
+    Kind::Variable,
+    Kind::Variable,
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Variable,
+    Kind::Variable,
+];
+
+const USER_OPERATION_V0_7_LAYOUT: [Kind; 9] = [
+    Kind::Fixed(20),
+    Kind::Fixed(32),
+    Kind::Variable,
+    Kind::Variable,
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Fixed(32),
+    Kind::Variable,
+    Kind::Variable,
+];
+
+/// Encodes a user operation in its entry point's ABI struct layout
+pub(crate) fn encode_user_op(op: &UserOperationVariant) -> Vec<u8> {
+    match op {
+        UserOperationVariant::V0_6(op) => {
+            let uo = ContractUserOperation::from(op.clone());
+            ssz::encode_container(vec![
+                Field::Fixed(ssz::encode_address(uo.sender)),
+                Field::Fixed(ssz::encode_u256(uo.nonce)),
+                Field::Variable(uo.initCode.to_vec()),
+                Field::Variable(uo.callData.to_vec()),
+                Field::Fixed(ssz::encode_u256(uo.callGasLimit)),
+                Field::Fixed(ssz::encode_u256(uo.verificationGasLimit)),
+                Field::Fixed(ssz::encode_u256(uo.preVerificationGas)),
+                Field::Fixed(ssz::encode_u256(uo.maxFeePerGas)),
+                Field::Fixed(ssz::encode_u256(uo.maxPriorityFeePerGas)),
+                Field::Variable(uo.paymasterAndData.to_vec()),
+                Field::Variable(uo.signature.to_vec()),
+            ])
+        }
+        UserOperationVariant::V0_7(op) => {
+            let puo = op.packed();
+            ssz::encode_container(vec![
+                Field::Fixed(ssz::encode_address(puo.sender)),
+                Field::Fixed(ssz::encode_u256(puo.nonce)),
+                Field::Variable(puo.initCode.to_vec()),
+                Field::Variable(puo.callData.to_vec()),
+                Field::Fixed(ssz::encode_b256(puo.accountGasLimits)),
+                Field::Fixed(ssz::encode_u256(puo.preVerificationGas)),
+                Field::Fixed(ssz::encode_b256(puo.gasFees)),
+                Field::Variable(puo.paymasterAndData.to_vec()),
+                Field::Variable(puo.signature.to_vec()),
+            ])
+        }
+    }
+}
+
+/// Decodes a user operation from its entry point's ABI struct layout
+pub(crate) fn decode_user_op(
+    bytes: &[u8],
+    version: EntryPointVersion,
+    chain_spec: &ChainSpec,
+) -> Result<UserOperationVariant, DecodeError> {
+    match version {
+        EntryPointVersion::V0_6 => {
+            let f = ssz::decode_container(bytes, &USER_OPERATION_V0_6_LAYOUT)?;
+            let uo = ContractUserOperation {
+                sender: ssz::decode_address(f[0])?,
+                nonce: ssz::decode_u256(f[1])?,
+                initCode: ssz::decode_bytes(f[2]),
+                callData: ssz::decode_bytes(f[3]),
+                callGasLimit: ssz::decode_u256(f[4])?,
+                verificationGasLimit: ssz::decode_u256(f[5])?,
+                preVerificationGas: ssz::decode_u256(f[6])?,
+                maxFeePerGas: ssz::decode_u256(f[7])?,
+                maxPriorityFeePerGas: ssz::decode_u256(f[8])?,
+                paymasterAndData: ssz::decode_bytes(f[9]),
+                signature: ssz::decode_bytes(f[10]),
+            };
+
+            Ok(v0_6::UserOperationBuilder::from_contract(
+                chain_spec,
+                uo,
+                v0_6::ExtendedUserOperation {
+                    authorization_tuple: None,
+                },
+            )?
+            .build()
+            .into())
+        }
+        EntryPointVersion::V0_7 | EntryPointVersion::V0_8 => {
+            let f = ssz::decode_container(bytes, &USER_OPERATION_V0_7_LAYOUT)?;
+            let puo = PackedUserOperation {
+                sender: ssz::decode_address(f[0])?,
+                nonce: ssz::decode_u256(f[1])?,
+                initCode: ssz::decode_bytes(f[2]),
+                callData: ssz::decode_bytes(f[3]),
+                accountGasLimits: ssz::decode_b256(f[4])?,
+                preVerificationGas: ssz::decode_u256(f[5])?,
+                gasFees: ssz::decode_b256(f[6])?,
+                paymasterAndData: ssz::decode_bytes(f[7]),
+                signature: ssz::decode_bytes(f[8]),
+            };
+
+            Ok(v0_7::UserOperationBuilder::from_packed(puo, chain_spec)?
+                .entry_point_version(version)
+                .build()
+                .into())
+        }
+        EntryPointVersion::Unspecified => Err(DecodeError::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, bytes};
+
+    use super::*;
+
+    fn v0_6_op(chain_spec: &ChainSpec) -> UserOperationVariant {
+        v0_6::UserOperationBuilder::new(
+            chain_spec,
+            v0_6::UserOperationRequiredFields {
+                sender: address!("1111111111111111111111111111111111111111"),
+                nonce: U256::from(5),
+                init_code: bytes!("aabb"),
+                call_data: bytes!("ccddee"),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 50_000,
+                max_fee_per_gas: 10,
+                max_priority_fee_per_gas: 2,
+                paymaster_and_data: bytes!(""),
+                signature: bytes!("ff"),
+            },
+            v0_6::ExtendedUserOperation {
+                authorization_tuple: None,
+            },
+        )
+        .build()
+        .into()
+    }
+
+    fn v0_7_op(chain_spec: &ChainSpec) -> UserOperationVariant {
+        v0_7::UserOperationBuilder::new(
+            chain_spec,
+            v0_7::UserOperationRequiredFields {
+                sender: address!("2222222222222222222222222222222222222222"),
+                nonce: U256::from(6),
+                call_data: bytes!("0102"),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 50_000,
+                max_priority_fee_per_gas: 2,
+                max_fee_per_gas: 10,
+                signature: bytes!("ee"),
+            },
+        )
+        .build()
+        .into()
+    }
+
+    #[test]
+    fn test_user_op_roundtrip_v0_6() {
+        let cs = ChainSpec::default();
+        let op = v0_6_op(&cs);
+        let decoded = decode_user_op(&encode_user_op(&op), EntryPointVersion::V0_6, &cs).unwrap();
+        assert_eq!(decoded, op);
+    }
+
+    #[test]
+    fn test_user_op_roundtrip_v0_7() {
+        let cs = ChainSpec::default();
+        let op = v0_7_op(&cs);
+        let decoded = decode_user_op(&encode_user_op(&op), EntryPointVersion::V0_7, &cs).unwrap();
+        assert_eq!(decoded, op);
+    }
+
+    #[test]
+    fn test_user_operations_with_entry_point_roundtrip() {
+        let cs = ChainSpec::default();
+        let msg = UserOperationsWithEntryPoint {
+            entry_point: cs.entry_point_address_v0_6,
+            verified_at_block_hash: B256::repeat_byte(3),
+            chain_id: cs.id,
+            user_operations: vec![v0_6_op(&cs), v0_6_op(&cs)],
+        };
+
+        let decoded =
+            UserOperationsWithEntryPoint::decode(&msg.encode(), EntryPointVersion::V0_6, &cs)
+                .unwrap();
+        assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn test_pooled_hashes_roundtrip() {
+        let req = PooledUserOpHashesRequest {
+            mempool: B256::repeat_byte(1),
+            offset: 10,
+        };
+        assert_eq!(
+            PooledUserOpHashesRequest::decode(&req.encode()).unwrap(),
+            req
+        );
+
+        let resp = PooledUserOpHashesResponse {
+            more_flag: true,
+            hashes: vec![B256::repeat_byte(2), B256::repeat_byte(3)],
+        };
+        assert_eq!(
+            PooledUserOpHashesResponse::decode(&resp.encode()).unwrap(),
+            resp
+        );
+    }
+
+    #[test]
+    fn test_pooled_ops_by_hash_roundtrip() {
+        let cs = ChainSpec::default();
+        let req = PooledUserOpsByHashRequest {
+            hashes: vec![B256::repeat_byte(2)],
+        };
+        assert_eq!(
+            PooledUserOpsByHashRequest::decode(&req.encode()).unwrap(),
+            req
+        );
+
+        let ops = vec![v0_7_op(&cs)];
+        let resp = PooledUserOpsByHashResponse::new(&ops);
+        let decoded = PooledUserOpsByHashResponse::decode(&resp.encode()).unwrap();
+        assert_eq!(decoded, resp);
+        assert_eq!(
+            decoded
+                .user_operations(EntryPointVersion::V0_7, &cs)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
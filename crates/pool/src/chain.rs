@@ -34,7 +34,10 @@ use rundler_contracts::{
     },
 };
 use rundler_provider::{Block, EvmProvider, Filter, Log};
-use rundler_task::{block_watcher, GracefulShutdown};
+use rundler_task::{
+    block_watcher::{self, NewHeadsWatcher},
+    GracefulShutdown,
+};
 use rundler_types::{EntryPointVersion, Timestamp, UserOperationId};
 use tokio::{
     select,
@@ -61,6 +64,9 @@ pub(crate) struct Chain<P: EvmProvider> {
     /// Semaphore to limit the number of concurrent `eth_getLogs` calls.
     load_ops_semaphore: Semaphore,
     sync_error_count: usize,
+    /// Watcher of the node's `newHeads` subscription, if configured. Otherwise the
+    /// node is polled for new blocks.
+    new_heads: Option<NewHeadsWatcher>,
     /// Filter template.
     filter_template: Filter,
     /// Metrics of chain events.
@@ -87,7 +93,7 @@ pub struct ChainUpdate {
     pub reorg_larger_than_history: bool,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct MinedOp {
     pub hash: B256,
     pub entry_point: Address,
@@ -95,6 +101,12 @@ pub struct MinedOp {
     pub nonce: U256,
     pub actual_gas_cost: U256,
     pub paymaster: Option<Address>,
+    /// Transaction the operation was mined in
+    pub tx_hash: B256,
+    /// Block the operation was mined in, which may be earlier than the latest
+    /// block of the update that contains it
+    pub block_number: u64,
+    pub block_hash: B256,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -118,6 +130,8 @@ impl MinedOp {
 pub(crate) struct Settings {
     pub(crate) history_size: u64,
     pub(crate) poll_interval: Duration,
+    /// Websocket URL to subscribe to new heads on, instead of polling
+    pub(crate) new_heads_ws_url: Option<String>,
     pub(crate) entry_point_addresses: HashMap<Address, EntryPointVersion>,
     pub(crate) max_sync_retries: u64,
 }
@@ -151,7 +165,7 @@ impl<P: EvmProvider> Chain<P> {
         if settings
             .entry_point_addresses
             .values()
-            .any(|v| *v == EntryPointVersion::V0_7)
+            .any(|v| matches!(v, EntryPointVersion::V0_7 | EntryPointVersion::V0_8))
         {
             events.push(UserOperationEventV07::SIGNATURE_HASH);
             events.push(DepositedV07::SIGNATURE_HASH);
@@ -168,11 +182,14 @@ impl<P: EvmProvider> Chain<P> {
             )
             .event_signature(events);
 
+        let new_heads = settings.new_heads_ws_url.clone().map(NewHeadsWatcher::new);
+
         Self {
             provider,
             settings,
             blocks: VecDeque::new(),
             sync_error_count: 0,
+            new_heads,
             load_ops_semaphore: Semaphore::new(MAX_LOAD_OPS_CONCURRENCY),
             filter_template,
             metrics: ChainMetrics::default(),
@@ -204,12 +221,22 @@ impl<P: EvmProvider> Chain<P> {
             .map(|block| block.hash)
             .unwrap_or_default();
         loop {
-            let (hash, block) = block_watcher::wait_for_new_block(
-                &self.provider,
-                block_hash,
-                self.settings.poll_interval,
-            )
-            .await;
+            // gaps between the known chain and the new head are backfilled when syncing
+            let (hash, block) = match &mut self.new_heads {
+                Some(new_heads) => {
+                    new_heads
+                        .wait_for_new_block(&self.provider, block_hash, self.settings.poll_interval)
+                        .await
+                }
+                None => {
+                    block_watcher::wait_for_new_block(
+                        &self.provider,
+                        block_hash,
+                        self.settings.poll_interval,
+                    )
+                    .await
+                }
+            };
             block_hash = hash;
 
             for i in 0..=self.settings.max_sync_retries {
@@ -505,7 +532,8 @@ impl<P: EvmProvider> Chain<P> {
                 Some(EntryPointVersion::V0_6) => {
                     Self::load_v0_6(log, &mut mined_ops, &mut entity_balance_updates)
                 }
-                Some(EntryPointVersion::V0_7) => {
+                // v0.8 emits the same events as v0.7
+                Some(EntryPointVersion::V0_7 | EntryPointVersion::V0_8) => {
                     Self::load_v0_7(log, &mut mined_ops, &mut entity_balance_updates)
                 }
                 Some(EntryPointVersion::Unspecified) | None => {
@@ -543,6 +571,9 @@ impl<P: EvmProvider> Chain<P> {
                     nonce: event.nonce,
                     actual_gas_cost: event.actualGasCost,
                     paymaster,
+                    tx_hash: log.transaction_hash.unwrap_or_default(),
+                    block_number: log.block_number.unwrap_or_default(),
+                    block_hash: log.block_hash.unwrap_or_default(),
                 };
                 mined_ops.push(mined);
             }
@@ -605,6 +636,9 @@ impl<P: EvmProvider> Chain<P> {
                     nonce: event.nonce,
                     actual_gas_cost: event.actualGasCost,
                     paymaster,
+                    tx_hash: log.transaction_hash.unwrap_or_default(),
+                    block_number: log.block_number.unwrap_or_default(),
+                    block_hash: log.block_hash.unwrap_or_default(),
                 };
                 mined_ops.push(mined);
             }
@@ -1475,6 +1509,7 @@ mod tests {
             Settings {
                 history_size: HISTORY_SIZE,
                 poll_interval: Duration::from_secs(250), // Not used in tests.
+                new_heads_ws_url: None,
                 entry_point_addresses: HashMap::from([
                     (ENTRY_POINT_ADDRESS_V0_6, EntryPointVersion::V0_6),
                     (ENTRY_POINT_ADDRESS_V0_7, EntryPointVersion::V0_7),
@@ -1665,6 +1700,7 @@ mod tests {
             nonce: U256::ZERO,
             actual_gas_cost: U256::ZERO,
             paymaster: None,
+            ..Default::default()
         }
     }
 
@@ -13,20 +13,27 @@
 
 use std::fmt::Display;
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use rundler_types::{Entity, EntityType, Timestamp, UserOperation, UserOperationVariant};
 use rundler_utils::strs;
+use serde::{Serialize, Serializer};
 
 use crate::mempool::OperationOrigin;
 
 /// Event type for the pool
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
 pub enum OpPoolEvent {
     /// An operation was received and added to the pool
     ReceivedOp {
         /// Operation hash
         op_hash: B256,
         /// The full operation
+        #[serde(serialize_with = "serialize_op")]
         op: UserOperationVariant,
         /// Block number the operation was added to the pool
         block_number: u64,
@@ -38,6 +45,8 @@ pub enum OpPoolEvent {
         valid_until: Timestamp,
         /// Operation entities
         entities: EntitySummary,
+        /// IDs of the mempool channels the operation was accepted into
+        mempools: Vec<B256>,
     },
     /// An operation was removed from the pool
     RemovedOp {
@@ -67,10 +76,17 @@ pub enum OpPoolEvent {
         /// The actual pre_verification_gas
         actual_pvg: u128,
     },
+    /// A parked operation became valid and is now offered to builders
+    PromotedOp {
+        /// The operation hash
+        op_hash: B256,
+        /// Operation valid after timestamp
+        valid_after: Timestamp,
+    },
 }
 
 /// Summary of the entities associated with an operation
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct EntitySummary {
     /// Sender entity status
     pub sender: EntityStatus,
@@ -83,7 +99,7 @@ pub struct EntitySummary {
 }
 
 /// Status of an entity
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct EntityStatus {
     /// Address of the entity
     pub address: Address,
@@ -92,7 +108,8 @@ pub struct EntityStatus {
 }
 
 /// Reputation of an entity
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum EntityReputation {
     #[default]
     Ok,
@@ -102,7 +119,12 @@ pub enum EntityReputation {
 }
 
 /// Reason an operation was removed from the pool
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
 pub enum OpRemovalReason {
     /// Removal was requested
     Requested,
@@ -140,6 +162,11 @@ pub enum OpRemovalReason {
         valid_until: Timestamp,
     },
     PoolSizeExceeded,
+    /// Op was removed because another op of its atomic group was removed
+    AtomicGroupRemoved {
+        /// The removed op of the group
+        op_hash: B256,
+    },
 }
 
 impl EntitySummary {
@@ -222,10 +249,116 @@ impl Display for OpPoolEvent {
                     op_hash, eligible, required_pvg, actual_pvg,
                 )
             }
+            OpPoolEvent::PromotedOp {
+                op_hash,
+                valid_after,
+            } => {
+                write!(
+                    f,
+                    concat!(
+                        "Promoted parked op.",
+                        "    Op hash: {:?}",
+                        "    Valid after: {}",
+                    ),
+                    op_hash, valid_after,
+                )
+            }
         }
     }
 }
 
+/// Operations are exported as a summary of the fields relevant to the pool, the
+/// full operation can be fetched by hash while it is pooled.
+fn serialize_op<S: Serializer>(
+    op: &UserOperationVariant,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct OpSummary {
+        sender: Address,
+        nonce: U256,
+        factory: Option<Address>,
+        paymaster: Option<Address>,
+        call_gas_limit: u128,
+        verification_gas_limit: u128,
+        pre_verification_gas: u128,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    }
+
+    OpSummary {
+        sender: op.sender(),
+        nonce: op.nonce(),
+        factory: op.factory(),
+        paymaster: op.paymaster(),
+        call_gas_limit: op.call_gas_limit(),
+        verification_gas_limit: op.verification_gas_limit(),
+        pre_verification_gas: op.pre_verification_gas(),
+        max_fee_per_gas: op.max_fee_per_gas(),
+        max_priority_fee_per_gas: op.max_priority_fee_per_gas(),
+    }
+    .serialize(serializer)
+}
+
 fn format_entity_status(name: &str, status: Option<&EntityStatus>) -> String {
     strs::to_string_or_empty(status.map(|status| format!("    {name}: {:?}", status.address)))
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::v0_6;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_serialize_received_op() {
+        let sender = Address::random();
+        let event = OpPoolEvent::ReceivedOp {
+            op_hash: B256::ZERO,
+            op: UserOperationVariant::V0_6(v0_6::UserOperation {
+                sender,
+                nonce: U256::from(1),
+                max_fee_per_gas: 10,
+                ..Default::default()
+            }),
+            block_number: 2,
+            origin: OperationOrigin::Local,
+            valid_after: 0.into(),
+            valid_until: 3.into(),
+            entities: EntitySummary::default(),
+            mempools: vec![],
+        };
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "receivedOp");
+        assert_eq!(value["blockNumber"], 2);
+        assert_eq!(value["origin"], "local");
+        assert_eq!(value["validUntil"], "0x3");
+        assert_eq!(value["entities"]["sender"]["reputation"], "ok");
+        assert_eq!(value["op"]["sender"], json!(sender));
+        assert_eq!(value["op"]["maxFeePerGas"], 10);
+    }
+
+    #[test]
+    fn test_serialize_removal_reason() {
+        let event = OpPoolEvent::RemovedOp {
+            op_hash: B256::ZERO,
+            reason: OpRemovalReason::Expired {
+                valid_until: 5.into(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "removedOp",
+                "opHash": B256::ZERO,
+                "reason": {"type": "expired", "validUntil": "0x5"},
+            })
+        );
+
+        let value = serde_json::to_value(OpRemovalReason::PoolSizeExceeded).unwrap();
+        assert_eq!(value, json!({"type": "poolSizeExceeded"}));
+    }
+}
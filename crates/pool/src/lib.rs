@@ -25,10 +25,16 @@ mod emit;
 pub use emit::OpPoolEvent as PoolEvent;
 
 mod mempool;
-pub use mempool::PoolConfig;
+pub use mempool::{OperationOrigin, OrderingStrategy, PoolConfig};
+
+mod p2p;
+
+mod receipt_index;
 
 mod server;
 pub use server::{LocalPoolBuilder, LocalPoolHandle, RemotePoolClient};
 
+mod store;
+
 mod task;
 pub use task::{Args as PoolTaskArgs, PoolTask};
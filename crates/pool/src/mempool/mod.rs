@@ -33,12 +33,14 @@ use alloy_primitives::{Address, B256};
 use mockall::automock;
 use rundler_sim::{MempoolConfig, PrecheckSettings, SimulationSettings};
 use rundler_types::{
+    aggregator::AggregatorConfigs,
     chain::ChainSpec,
     pool::{
         MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
     },
     EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
 };
+use serde::Serialize;
 use tonic::async_trait;
 pub(crate) use uo_pool::{UoPool, UoPoolProviders};
 
@@ -66,6 +68,17 @@ pub trait Mempool: Send + Sync {
         op: UserOperationVariant,
     ) -> MempoolResult<B256>;
 
+    /// Adds an ordered group of user operations to the pool as an atomic group.
+    ///
+    /// All operations are validated before any is added, and either all are added
+    /// or none are. The operations of a group are only returned by `best_operations`
+    /// together, in the given order, and removing any of them removes the whole group.
+    async fn add_operation_group(
+        &self,
+        origin: OperationOrigin,
+        ops: Vec<UserOperationVariant>,
+    ) -> MempoolResult<Vec<B256>>;
+
     /// Removes a set of operations from the pool.
     fn remove_operations(&self, hashes: &[B256]);
 
@@ -78,7 +91,10 @@ pub trait Mempool: Send + Sync {
     /// Returns the best operations from the pool.
     ///
     /// Returns the best operations from the pool based on their gas bids up to
-    /// the specified maximum number of operations, limiting to one per sender.
+    /// the specified maximum number of operations. Operations are returned after the
+    /// operation with their previous nonce, if it is in the pool. Unstaked senders
+    /// are limited to a single chain of consecutive nonces. The operations of an
+    /// atomic group are returned together, in order, or not at all.
     ///
     /// The `shard_index` is used to divide the mempool into disjoint shards to ensure
     /// that two bundle builders don't attempt to but bundle the same operations. If
@@ -119,6 +135,29 @@ pub trait Mempool: Send + Sync {
 
     /// Turns on and off tracking errors
     fn set_tracking(&self, paymaster: bool, reputation: bool);
+
+    /// Returns a snapshot of the pool's state to be persisted across restarts
+    fn snapshot(&self) -> MempoolSnapshot;
+
+    /// Restores the pool's state from a snapshot taken by a previous run.
+    ///
+    /// Operations are re-validated against the current head before being added
+    /// back to the pool. Operations that are no longer valid are dropped.
+    async fn restore(&self, snapshot: MempoolSnapshot);
+}
+
+/// Snapshot of a mempool's state
+#[derive(Debug, Clone, Default)]
+pub struct MempoolSnapshot {
+    /// Operations in the pool, best first
+    pub ops: Vec<Arc<PoolOperation>>,
+    /// Mined operations kept in case their block is reorged away, along with
+    /// the block number at which they were mined
+    pub mined_ops: Vec<(Arc<PoolOperation>, u64)>,
+    /// Reputation of the entities tracked by the pool
+    pub reputations: Vec<Reputation>,
+    /// Addresses of the paymasters with tracked balances
+    pub paymasters: Vec<Address>,
 }
 
 /// Config for the mempool
@@ -170,11 +209,29 @@ pub struct PoolConfig {
     /// Gas limit efficiency is defined as the ratio of the gas limit to the gas used.
     /// This applies to all the verification, call, and paymaster gas limits.
     pub gas_limit_efficiency_reject_threshold: f32,
+    /// How operations are ranked when selecting the best operations for a bundle
+    pub ordering: OrderingStrategy,
+    /// Trusted signature aggregators. Operations using any other aggregator are rejected.
+    pub aggregators: AggregatorConfigs,
+}
+
+/// Strategy used to rank operations in the mempool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum OrderingStrategy {
+    /// Rank by max fee per gas, regardless of the base fee
+    MaxFee,
+    /// Rank by the tip paid to the bundler at the latest base fee, including
+    /// any pre-verification gas paid above what is required.
+    ///
+    /// Operations are re-sorted on every new block.
+    #[default]
+    EffectiveTip,
 }
 
 /// Origin of an operation.
-#[derive(Debug, Clone, Copy)]
-#[allow(dead_code)] // TODO(danc): remove once implemented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum OperationOrigin {
     /// The operation was submitted via a local RPC call.
     Local,
@@ -183,6 +240,8 @@ pub enum OperationOrigin {
     /// The operation was returned to the pool when the block it was in was
     /// reorged away.
     ReturnedAfterReorg,
+    /// The operation was restored from the pool store on startup.
+    Restored,
 }
 
 #[cfg(test)]
@@ -234,6 +293,8 @@ mod tests {
                 }),
             },
             da_gas_data: Default::default(),
+            atomic_group: None,
+            mempools: vec![],
         };
 
         let entities = po.entities().collect::<Vec<_>>();
@@ -20,7 +20,7 @@ use std::{
 
 use alloy_primitives::{Address, B256};
 use anyhow::Context;
-use metrics::{Gauge, Histogram};
+use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 use parking_lot::RwLock;
 use rundler_provider::DAGasOracleSync;
@@ -35,7 +35,9 @@ use rundler_utils::{emit::WithEntryPoint, math};
 use tokio::sync::broadcast;
 use tracing::{info, warn};
 
-use super::{entity_tracker::EntityCounter, size::SizeTracker, MempoolResult, PoolConfig};
+use super::{
+    entity_tracker::EntityCounter, size::SizeTracker, MempoolResult, OrderingStrategy, PoolConfig,
+};
 use crate::{chain::MinedOp, emit::OpRemovalReason, PoolEvent};
 
 #[derive(Debug, Clone)]
@@ -47,6 +49,7 @@ pub(crate) struct PoolInnerConfig {
     throttled_entity_mempool_count: u64,
     throttled_entity_live_blocks: u64,
     da_gas_tracking_enabled: bool,
+    ordering: OrderingStrategy,
 }
 
 impl From<PoolConfig> for PoolInnerConfig {
@@ -59,6 +62,7 @@ impl From<PoolConfig> for PoolInnerConfig {
             throttled_entity_mempool_count: config.throttled_entity_mempool_count,
             throttled_entity_live_blocks: config.throttled_entity_live_blocks,
             da_gas_tracking_enabled: config.da_gas_tracking_enabled,
+            ordering: config.ordering,
         }
     }
 }
@@ -76,6 +80,9 @@ pub(crate) struct PoolInner<D> {
     by_id: HashMap<UserOperationId, Arc<OrderedPoolOperation>>,
     /// Best operations, sorted by gas price
     best: BTreeSet<Arc<OrderedPoolOperation>>,
+    /// Operations that are not valid yet, kept out of `best` until the chain reaches
+    /// their `valid_after` timestamp. Sorted by `valid_after`.
+    parked: BTreeSet<(Timestamp, B256)>,
     /// Time to mine info
     time_to_mine: HashMap<B256, TimeToMineInfo>,
     /// Removed operations, temporarily kept around in case their blocks are
@@ -97,6 +104,10 @@ pub(crate) struct PoolInner<D> {
     prev_sys_block_time: Duration,
     /// The number of the previous block
     prev_block_number: u64,
+    /// The timestamp of the previous block
+    prev_block_timestamp: Timestamp,
+    /// The base fee of the previous block, used to compute effective tips
+    prev_base_fee: u128,
     /// The metrics of pool.
     metrics: PoolMetrics,
     /// Event sender
@@ -119,6 +130,7 @@ where
             by_hash: HashMap::new(),
             by_id: HashMap::new(),
             best: BTreeSet::new(),
+            parked: BTreeSet::new(),
             time_to_mine: HashMap::new(),
             mined_at_block_number_by_hash: HashMap::new(),
             mined_hashes_with_block_numbers: BTreeSet::new(),
@@ -128,6 +140,8 @@ where
             cache_size: SizeTracker::default(),
             prev_sys_block_time: Duration::default(),
             prev_block_number: 0,
+            prev_block_timestamp: Timestamp::default(),
+            prev_base_fee: 0,
             metrics: PoolMetrics::new_with_labels(&[("entry_point", entry_point)]),
             event_sender,
         }
@@ -154,6 +168,11 @@ where
         }
 
         if let Some(pool_op) = self.by_id.get(&op.id()) {
+            // Replacing a single op would break its atomic group
+            if pool_op.po.atomic_group.is_some() {
+                return Err(MempoolError::AtomicGroupReplacement);
+            }
+
             let (replacement_priority_fee, replacement_fee) =
                 self.get_min_replacement_fees(pool_op.uo());
 
@@ -201,10 +220,13 @@ where
         };
 
         // only eligibility requirement is if the op has required pvg
+        let score = self.score(&op.uo, required_pvg);
         let pool_op = Arc::new(OrderedPoolOperation::new(
             Arc::new(op),
             self.next_submission_id(),
             is_eligible,
+            required_pvg,
+            score,
         ));
 
         let hash = self.add_operation_internal(pool_op)?;
@@ -221,10 +243,28 @@ where
         })
     }
 
+    /// Returns the operations of an atomic group in order, if all of them are among
+    /// the best operations.
+    pub(crate) fn atomic_group_operations(
+        &self,
+        hashes: &[B256],
+    ) -> Option<Vec<Arc<PoolOperation>>> {
+        hashes
+            .iter()
+            .map(|hash| {
+                self.by_hash
+                    .get(hash)
+                    .filter(|op| op.eligible() && self.best.contains(*op))
+                    .map(|op| op.po.clone())
+            })
+            .collect()
+    }
+
     /// Does maintenance on the pool.
     ///
     /// 1) Removes all operations using the given entity, returning the hashes of the removed operations.
-    /// 2) Updates time to mine stats for all operations in the pool.
+    /// 2) Promotes parked operations that became valid at the block's timestamp.
+    /// 3) Updates time to mine stats for all operations in the pool.
     ///
     /// NOTE: This method is O(n) where n is the number of operations in the pool.
     /// It should be called sparingly (e.g. when a block is mined).
@@ -244,6 +284,24 @@ where
         let mut expired = Vec::new();
         let mut num_candidates = 0;
         let mut events = vec![];
+        let mut required_pvgs = HashMap::new();
+
+        self.prev_block_timestamp = block_timestamp;
+        while let Some(&(valid_after, hash)) = self
+            .parked
+            .first()
+            .filter(|(valid_after, _)| *valid_after <= block_timestamp)
+        {
+            self.parked.remove(&(valid_after, hash));
+            if let Some(op) = self.by_hash.get(&hash) {
+                self.best.insert(op.clone());
+                self.metrics.promoted_ops.increment(1);
+                events.push(PoolEvent::PromotedOp {
+                    op_hash: hash,
+                    valid_after,
+                });
+            }
+        }
 
         for (hash, op) in &mut self.by_hash {
             if op.po.valid_time_range.valid_until < block_timestamp {
@@ -273,6 +331,7 @@ where
                     required_da_gas,
                 );
                 let actual_pvg = op.uo().pre_verification_gas();
+                required_pvgs.insert(*hash, required_pvg);
 
                 if actual_pvg < required_pvg {
                     if op.eligible() {
@@ -298,6 +357,9 @@ where
 
             if op.uo().max_fee_per_gas() < gas_fees.uo_fees.max_fee_per_gas
                 || op.uo().max_priority_fee_per_gas() < gas_fees.uo_fees.max_priority_fee_per_gas
+                || self
+                    .parked
+                    .contains(&(op.po.valid_time_range.valid_after, *hash))
             {
                 // don't mark as ineligible, but also not a candidate
                 continue;
@@ -317,12 +379,62 @@ where
             self.emit(event);
         }
 
+        self.prev_base_fee = gas_fees.base_fee;
+        // Effective tips change with the base fee, max fee ordering never changes
+        if self.config.ordering == OrderingStrategy::EffectiveTip {
+            self.reorder(&required_pvgs);
+        }
+
         self.metrics.num_candidates.set(num_candidates as f64);
         self.prev_block_number = block_number;
         self.prev_sys_block_time = sys_block_time;
         self.update_metrics();
     }
 
+    /// Returns all operations in the pool, including ineligible ones, best first,
+    /// followed by the parked operations
+    pub(crate) fn operations(&self) -> impl Iterator<Item = Arc<PoolOperation>> + '_ {
+        self.best.iter().map(|p| p.po.clone()).chain(
+            self.parked
+                .iter()
+                .filter_map(|(_, hash)| self.by_hash.get(hash).map(|p| p.po.clone())),
+        )
+    }
+
+    /// Returns the mined operations kept in case of a reorg, along with the
+    /// block number at which they were mined
+    pub(crate) fn mined_operations(&self) -> impl Iterator<Item = (Arc<PoolOperation>, u64)> + '_ {
+        self.mined_at_block_number_by_hash
+            .values()
+            .map(|(op, block_number)| (op.po.clone(), *block_number))
+    }
+
+    /// Adds a mined operation from a previous run back to the reorg cache
+    pub(crate) fn restore_mined_operation(&mut self, op: Arc<PoolOperation>, block_number: u64) {
+        let hash = op
+            .uo
+            .hash(self.config.entry_point, self.config.chain_spec.id);
+        if self.mined_at_block_number_by_hash.contains_key(&hash) {
+            return;
+        }
+
+        let required_pvg = op.uo.pre_verification_gas();
+        let score = self.score(&op.uo, required_pvg);
+        let op = Arc::new(OrderedPoolOperation::new(
+            op,
+            self.next_submission_id(),
+            true,
+            required_pvg,
+            score,
+        ));
+        self.cache_size += op.mem_size();
+        self.mined_at_block_number_by_hash
+            .insert(hash, (op, block_number));
+        self.mined_hashes_with_block_numbers
+            .insert((block_number, hash));
+        self.update_metrics();
+    }
+
     pub(crate) fn address_count(&self, address: &Address) -> usize {
         if let Some(entity) = self.count_by_address.get(address) {
             return entity.total();
@@ -339,6 +451,13 @@ where
         self.by_id.get(id).map(|o| o.po.clone())
     }
 
+    /// Returns the id of the operation with the previous sequence number of `id`'s
+    /// nonce key, if it is in the pool
+    pub(crate) fn previous_operation_id(&self, id: &UserOperationId) -> Option<UserOperationId> {
+        id.previous()
+            .filter(|previous| self.by_id.contains_key(previous))
+    }
+
     pub(crate) fn remove_operation_by_hash(&mut self, hash: B256) -> Option<Arc<PoolOperation>> {
         self.remove_operation_internal(hash, None)
     }
@@ -432,6 +551,8 @@ where
         self.mined_hashes_with_block_numbers
             .remove(&(block_number, hash));
 
+        // the op was scored at the base fee when it was added, rescore it at the current one
+        let op = Arc::new(op.rescored(self.score(op.uo(), op.required_pvg)));
         if let Err(error) = self.add_operation_internal(op.clone()) {
             info!("Could not put back unmined operation: {error}");
         };
@@ -505,6 +626,7 @@ where
         self.by_hash.clear();
         self.by_id.clear();
         self.best.clear();
+        self.parked.clear();
         self.time_to_mine.clear();
         self.mined_at_block_number_by_hash.clear();
         self.mined_hashes_with_block_numbers.clear();
@@ -518,17 +640,23 @@ where
         let mut removed = Vec::new();
 
         while self.pool_size > self.config.max_size_of_pool_bytes {
-            if let Some(worst) = self.best.pop_last() {
-                let hash = worst
+            // Parked operations can't be bundled yet, so they are removed before any
+            // valid operation, starting with the one that becomes valid last
+            let hash = if let Some(&(_, hash)) = self.parked.last() {
+                hash
+            } else if let Some(worst) = self.best.last() {
+                worst
                     .uo()
-                    .hash(self.config.entry_point, self.config.chain_spec.id);
+                    .hash(self.config.entry_point, self.config.chain_spec.id)
+            } else {
+                break;
+            };
 
-                let _ = self
-                    .remove_operation_internal(hash, None)
-                    .context("should have removed the worst operation")?;
+            let _ = self
+                .remove_operation_internal(hash, None)
+                .context("should have removed the worst operation")?;
 
-                removed.push(hash);
-            }
+            removed.push(hash);
         }
 
         Ok(removed)
@@ -559,7 +687,12 @@ where
         self.pool_size += pool_op.mem_size();
         self.by_hash.insert(hash, pool_op.clone());
         self.by_id.insert(pool_op.uo().id(), pool_op.clone());
-        self.best.insert(pool_op);
+        let valid_after = pool_op.po.valid_time_range.valid_after;
+        if valid_after > self.prev_block_timestamp {
+            self.parked.insert((valid_after, hash));
+        } else {
+            self.best.insert(pool_op);
+        }
         self.time_to_mine.insert(hash, TimeToMineInfo::new());
 
         let removed = self
@@ -589,6 +722,8 @@ where
         let id = &op.po.uo.id();
         self.by_id.remove(id);
         self.best.remove(&op);
+        self.parked
+            .remove(&(op.po.valid_time_range.valid_after, hash));
         self.time_to_mine.remove(&hash);
 
         if let Some(block_number) = block_number {
@@ -604,6 +739,20 @@ where
         }
 
         self.pool_size -= op.mem_size();
+
+        // The rest of an atomic group can't be bundled without this op, unless it was
+        // mined along with it
+        if block_number.is_none() {
+            for &member in op.po.atomic_group.iter().flatten() {
+                if self.remove_operation_internal(member, None).is_some() {
+                    self.emit(PoolEvent::RemovedOp {
+                        op_hash: member,
+                        reason: OpRemovalReason::AtomicGroupRemoved { op_hash: hash },
+                    });
+                }
+            }
+        }
+
         self.update_metrics();
         Some(op.po.clone())
     }
@@ -617,6 +766,42 @@ where
         }
     }
 
+    /// Returns the value used to rank an operation against the others in the pool, higher is better
+    fn score(&self, op: &UserOperationVariant, required_pvg: u128) -> u128 {
+        match self.config.ordering {
+            OrderingStrategy::MaxFee => op.max_fee_per_gas(),
+            OrderingStrategy::EffectiveTip => effective_tip(
+                op,
+                &self.config.chain_spec,
+                self.prev_base_fee,
+                required_pvg,
+            ),
+        }
+    }
+
+    /// Rescores every operation and re-sorts the best operations.
+    ///
+    /// `required_pvgs` overrides the required pre-verification gas of the given operations.
+    fn reorder(&mut self, required_pvgs: &HashMap<B256, u128>) {
+        let ops = std::mem::take(&mut self.by_hash);
+        self.best.clear();
+        for (hash, op) in ops {
+            let required_pvg = required_pvgs.get(&hash).copied().unwrap_or(op.required_pvg);
+            let op = Arc::new(
+                op.with_required_pvg(required_pvg)
+                    .rescored(self.score(op.uo(), required_pvg)),
+            );
+            self.by_id.insert(op.uo().id(), op.clone());
+            if !self
+                .parked
+                .contains(&(op.po.valid_time_range.valid_after, hash))
+            {
+                self.best.insert(op.clone());
+            }
+            self.by_hash.insert(hash, op);
+        }
+    }
+
     fn next_submission_id(&mut self) -> u64 {
         let id = self.submission_id;
         self.submission_id += 1;
@@ -643,27 +828,79 @@ where
             .num_ops_in_cache
             .set(self.mined_hashes_with_block_numbers.len() as f64);
         self.metrics.cache_size_bytes.set(self.cache_size.0 as f64);
+        self.metrics.num_parked_ops.set(self.parked.len() as f64);
     }
 }
 
-/// Wrapper around PoolOperation that adds a submission ID to implement
+/// Returns the tip per unit of execution gas that the bundler receives for an operation
+/// at the given base fee.
+///
+/// Pre-verification gas paid above `required_pvg` is also profit to the bundler, so it is
+/// spread across the execution gas limit of the operation and added to the tip. This
+/// accounts for DA costs, as they are part of the required pre-verification gas.
+fn effective_tip(
+    op: &UserOperationVariant,
+    chain_spec: &ChainSpec,
+    base_fee: u128,
+    required_pvg: u128,
+) -> u128 {
+    let gas_price = op.gas_price(base_fee);
+    let tip = gas_price.saturating_sub(base_fee);
+    let excess_pvg = op.pre_verification_gas().saturating_sub(required_pvg);
+    let execution_gas_limit = op.execution_gas_limit(chain_spec, None).max(1);
+    tip.saturating_add(excess_pvg.saturating_mul(gas_price) / execution_gas_limit)
+}
+
+/// Wrapper around PoolOperation that adds a submission ID and a score to implement
 /// a custom ordering for the best operations
 #[derive(Debug)]
 struct OrderedPoolOperation {
     po: Arc<PoolOperation>,
     submission_id: u64,
     eligible: RwLock<bool>,
+    /// Pre-verification gas required at the time the operation was last scored
+    required_pvg: u128,
+    /// Must not change while the operation is in the best set, rescoring creates a new operation
+    score: u128,
 }
 
 impl OrderedPoolOperation {
-    fn new(po: Arc<PoolOperation>, submission_id: u64, eligible: bool) -> Self {
+    fn new(
+        po: Arc<PoolOperation>,
+        submission_id: u64,
+        eligible: bool,
+        required_pvg: u128,
+        score: u128,
+    ) -> Self {
         Self {
             po,
             submission_id,
             eligible: RwLock::new(eligible),
+            required_pvg,
+            score,
         }
     }
 
+    fn rescored(&self, score: u128) -> Self {
+        Self::new(
+            self.po.clone(),
+            self.submission_id,
+            self.eligible(),
+            self.required_pvg,
+            score,
+        )
+    }
+
+    fn with_required_pvg(&self, required_pvg: u128) -> Self {
+        Self::new(
+            self.po.clone(),
+            self.submission_id,
+            self.eligible(),
+            required_pvg,
+            self.score,
+        )
+    }
+
     fn uo(&self) -> &UserOperationVariant {
         &self.po.uo
     }
@@ -689,11 +926,10 @@ impl Eq for OrderedPoolOperation {}
 
 impl Ord for OrderedPoolOperation {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Sort by gas price descending then by id ascending
+        // Sort by score descending then by id ascending
         other
-            .uo()
-            .max_fee_per_gas()
-            .cmp(&self.uo().max_fee_per_gas())
+            .score
+            .cmp(&self.score)
             .then_with(|| self.submission_id.cmp(&other.submission_id))
     }
 }
@@ -743,6 +979,10 @@ struct PoolMetrics {
     cache_size_bytes: Gauge,
     #[metric(describe = "the number of candidates.")]
     num_candidates: Gauge,
+    #[metric(describe = "the number of ops in mempool that are not valid yet.")]
+    num_parked_ops: Gauge,
+    #[metric(describe = "the count of parked ops that became valid.")]
+    promoted_ops: Counter,
     #[metric(describe = "the duration distribution of a bundle mined.")]
     time_to_mine: Histogram,
     #[metric(describe = "the duration distribution of a blocked mined.")]
@@ -801,6 +1041,22 @@ mod tests {
         assert_eq!(pool.get_operation_by_id(&bad_id), None);
     }
 
+    #[test]
+    fn test_previous_operation_id() {
+        let mut pool = pool();
+        let sender = Address::random();
+        let op = create_op(sender, 1, 1);
+        pool.add_operation(op.clone(), 0).unwrap();
+
+        let next = create_op(sender, 2, 1).uo.id();
+        assert_eq!(pool.previous_operation_id(&next), Some(op.uo.id()));
+        // gap in the nonces
+        let gap = create_op(sender, 3, 1).uo.id();
+        assert_eq!(pool.previous_operation_id(&gap), None);
+        // first nonce of the key
+        assert_eq!(pool.previous_operation_id(&op.uo.id()), None);
+    }
+
     #[test]
     fn add_multiple_ops() {
         let mut pool = pool();
@@ -848,6 +1104,78 @@ mod tests {
         check_map_entry(pool.best.iter().nth(2), Some(&ops[2]));
     }
 
+    #[test]
+    fn best_by_effective_tip() {
+        let mut pool = pool_with_conf(PoolInnerConfig {
+            ordering: OrderingStrategy::EffectiveTip,
+            ..conf()
+        });
+        pool.do_maintenance(0, 0.into(), None, fee_update(10));
+
+        // higher max fee, but lower tip at base fee 10
+        let low_tip = create_op_with_fees(Address::random(), 100, 1, 50_000);
+        let high_tip = create_op_with_fees(Address::random(), 20, 5, 50_000);
+        pool.add_operation(low_tip.clone(), 50_000).unwrap();
+        pool.add_operation(high_tip.clone(), 50_000).unwrap();
+
+        check_map_entry(pool.best.iter().next(), Some(&high_tip));
+        check_map_entry(pool.best.iter().nth(1), Some(&low_tip));
+    }
+
+    #[test]
+    fn best_by_effective_tip_excess_pvg() {
+        let mut pool = pool_with_conf(PoolInnerConfig {
+            ordering: OrderingStrategy::EffectiveTip,
+            ..conf()
+        });
+        pool.do_maintenance(0, 0.into(), None, fee_update(10));
+
+        let exact_pvg = create_op_with_fees(Address::random(), 20, 5, 50_000);
+        let excess_pvg = create_op_with_fees(Address::random(), 20, 5, 250_000);
+        pool.add_operation(exact_pvg.clone(), 50_000).unwrap();
+        pool.add_operation(excess_pvg.clone(), 50_000).unwrap();
+
+        check_map_entry(pool.best.iter().next(), Some(&excess_pvg));
+        check_map_entry(pool.best.iter().nth(1), Some(&exact_pvg));
+    }
+
+    #[test]
+    fn best_by_effective_tip_reorders_on_base_fee() {
+        let mut pool = pool_with_conf(PoolInnerConfig {
+            ordering: OrderingStrategy::EffectiveTip,
+            ..conf()
+        });
+
+        let op0 = create_op_with_fees(Address::random(), 30, 30, 50_000);
+        let op1 = create_op_with_fees(Address::random(), 100, 10, 50_000);
+        pool.add_operation(op0.clone(), 50_000).unwrap();
+        pool.add_operation(op1.clone(), 50_000).unwrap();
+
+        // at base fee 0: tips are 30 and 10
+        check_map_entry(pool.best.iter().next(), Some(&op0));
+
+        // at base fee 25: tips are 5 and 10
+        pool.do_maintenance(1, 0.into(), None, fee_update(25));
+        assert_eq!(pool.best.len(), 2);
+        check_map_entry(pool.best.iter().next(), Some(&op1));
+        check_map_entry(pool.best.iter().nth(1), Some(&op0));
+        check_map_entry(pool.by_id.get(&op0.uo.id()), Some(&op0));
+    }
+
+    #[test]
+    fn best_by_max_fee_ignores_base_fee() {
+        let mut pool = pool();
+        pool.do_maintenance(0, 0.into(), None, fee_update(10));
+
+        let low_tip = create_op_with_fees(Address::random(), 100, 1, 50_000);
+        let high_tip = create_op_with_fees(Address::random(), 20, 5, 50_000);
+        pool.add_operation(low_tip.clone(), 50_000).unwrap();
+        pool.add_operation(high_tip.clone(), 50_000).unwrap();
+
+        check_map_entry(pool.best.iter().next(), Some(&low_tip));
+        check_map_entry(pool.best.iter().nth(1), Some(&high_tip));
+    }
+
     #[test]
     fn remove_op() {
         let mut pool = pool();
@@ -921,6 +1249,7 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            ..Default::default()
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -953,6 +1282,7 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            ..Default::default()
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -962,6 +1292,42 @@ mod tests {
         assert!(pool.best.is_empty());
     }
 
+    #[test]
+    fn restore_mined_op() {
+        let mut pool = pool();
+        let sender = Address::random();
+        let op = create_op(sender, 0, 1);
+        let hash = op
+            .uo
+            .hash(pool.config.entry_point, pool.config.chain_spec.id);
+
+        pool.add_operation(op.clone(), 0).unwrap();
+        let mined_op = MinedOp {
+            paymaster: None,
+            actual_gas_cost: U256::ZERO,
+            hash,
+            entry_point: pool.config.entry_point,
+            sender,
+            nonce: U256::ZERO,
+            ..Default::default()
+        };
+        pool.mine_operation(&mined_op, 1);
+
+        let mined = pool.mined_operations().collect::<Vec<_>>();
+        assert_eq!(mined.len(), 1);
+
+        // a new pool picks up the reorg cache of the previous run
+        let mut restored = pool_with_conf(pool.config.clone());
+        for (op, block_number) in mined {
+            restored.restore_mined_operation(op, block_number);
+        }
+        assert!(restored.by_hash.is_empty());
+
+        restored.unmine_operation(&mined_op).unwrap();
+        check_map_entry(restored.by_hash.get(&hash), Some(&op));
+        assert_eq!(restored.mined_operations().count(), 0);
+    }
+
     #[test]
     fn remove_aggregator() {
         let mut pool = pool();
@@ -1121,7 +1487,7 @@ mod tests {
         assert_eq!(pool.address_count(&sender), 1);
         assert_eq!(
             pool.pool_size,
-            OrderedPoolOperation::new(Arc::new(po1), 0, true).mem_size(),
+            OrderedPoolOperation::new(Arc::new(po1), 0, true, 0, 0).mem_size(),
         );
     }
 
@@ -1157,7 +1523,7 @@ mod tests {
         assert_eq!(pool.address_count(&paymaster2), 1);
         assert_eq!(
             pool.pool_size,
-            OrderedPoolOperation::new(Arc::new(po2), 0, true).mem_size()
+            OrderedPoolOperation::new(Arc::new(po2), 0, true, 0, 0).mem_size()
         );
     }
 
@@ -1214,6 +1580,84 @@ mod tests {
         assert_eq!(None, pool.get_operation_by_hash(hash3));
     }
 
+    #[test]
+    fn test_parked_until_valid() {
+        let mut pool = pool();
+        let mut po1 = create_op(Address::random(), 0, 10);
+        po1.valid_time_range.valid_after = 5.into();
+        let hash1 = pool.add_operation(po1.clone(), 0).unwrap();
+        let hash2 = pool
+            .add_operation(create_op(Address::random(), 0, 10), 0)
+            .unwrap();
+
+        assert!(pool.get_operation_by_hash(hash1).is_some());
+        assert_eq!(pool.operations().count(), 2);
+        assert_eq!(pool.parked.len(), 1);
+        assert_eq!(
+            pool.best_operations()
+                .map(|o| o
+                    .uo
+                    .hash(pool.config.entry_point, pool.config.chain_spec.id))
+                .collect::<Vec<_>>(),
+            vec![hash2]
+        );
+
+        pool.do_maintenance(0, Timestamp::from(4), None, FeeUpdate::default());
+        assert_eq!(pool.best_operations().count(), 1);
+
+        pool.do_maintenance(1, Timestamp::from(5), None, FeeUpdate::default());
+        assert!(pool.parked.is_empty());
+        assert_eq!(pool.best_operations().count(), 2);
+    }
+
+    #[test]
+    fn test_parked_valid_on_add() {
+        let mut pool = pool();
+        pool.do_maintenance(0, Timestamp::from(10), None, FeeUpdate::default());
+
+        let mut po1 = create_op(Address::random(), 0, 10);
+        po1.valid_time_range.valid_after = 10.into();
+        pool.add_operation(po1.clone(), 0).unwrap();
+
+        assert!(pool.parked.is_empty());
+        assert_eq!(pool.best_operations().count(), 1);
+    }
+
+    #[test]
+    fn test_parked_remove() {
+        let mut pool = pool();
+        let mut po1 = create_op(Address::random(), 0, 10);
+        po1.valid_time_range.valid_after = 5.into();
+        let hash = pool.add_operation(po1.clone(), 0).unwrap();
+
+        assert!(pool.remove_operation_by_hash(hash).is_some());
+        assert!(pool.parked.is_empty());
+        assert_eq!(pool.operations().count(), 0);
+
+        pool.do_maintenance(0, Timestamp::from(5), None, FeeUpdate::default());
+        assert_eq!(pool.best_operations().count(), 0);
+    }
+
+    #[test]
+    fn test_parked_evicted_first() {
+        let mut conf = conf();
+        let mut po1 = create_op(Address::random(), 0, 10);
+        po1.valid_time_range.valid_after = 6.into();
+        let mut po2 = create_op(Address::random(), 0, 10);
+        po2.valid_time_range.valid_after = 5.into();
+        let po3 = create_op(Address::random(), 0, 1);
+        conf.max_size_of_pool_bytes = 2 * mem_size_of_ordered_pool_op();
+        let mut pool = pool_with_conf(conf);
+
+        let hash1 = pool.add_operation(po1, 0).unwrap();
+        let hash2 = pool.add_operation(po2, 0).unwrap();
+        let hash3 = pool.add_operation(po3, 0).unwrap();
+        assert!(pool.get_operation_by_hash(hash1).is_none());
+        assert!(pool.get_operation_by_hash(hash2).is_some());
+        assert!(pool.get_operation_by_hash(hash3).is_some());
+        assert_eq!(pool.parked.len(), 1);
+    }
+
     #[test]
     fn test_add_operation_ineligible_initially() {
         let mut conf = conf();
@@ -1380,6 +1824,7 @@ mod tests {
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
             da_gas_tracking_enabled: false,
+            ordering: OrderingStrategy::MaxFee,
         }
     }
 
@@ -1399,7 +1844,34 @@ mod tests {
     }
 
     fn mem_size_of_ordered_pool_op() -> usize {
-        OrderedPoolOperation::new(Arc::new(create_op(Address::random(), 1, 1)), 1, true).mem_size()
+        OrderedPoolOperation::new(Arc::new(create_op(Address::random(), 1, 1)), 1, true, 0, 0)
+            .mem_size()
+    }
+
+    fn create_op_with_fees(
+        sender: Address,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+        pre_verification_gas: u128,
+    ) -> PoolOperation {
+        let mut op = create_op(sender, 0, max_fee_per_gas);
+        op.uo = UserOperation {
+            sender,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            pre_verification_gas,
+            call_gas_limit: 100_000,
+            ..UserOperation::default()
+        }
+        .into();
+        op
+    }
+
+    fn fee_update(base_fee: u128) -> FeeUpdate {
+        FeeUpdate {
+            base_fee,
+            ..Default::default()
+        }
     }
 
     fn create_op(sender: Address, nonce: usize, max_fee_per_gas: u128) -> PoolOperation {
@@ -1429,6 +1901,8 @@ mod tests {
             sim_block_number: 0,
             account_is_staked: false,
             da_gas_data: Default::default(),
+            atomic_group: None,
+            mempools: vec![],
         }
     }
 
@@ -11,9 +11,13 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashSet, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
-use alloy_primitives::{utils::format_units, Address, Bytes, B256, U256};
+use alloy_primitives::{address, utils::format_units, Address, Bytes, B256, U256};
 use anyhow::Context;
 use futures::TryFutureExt;
 use itertools::Itertools;
@@ -21,24 +25,26 @@ use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 use parking_lot::RwLock;
 use rundler_provider::{
-    DAGasOracleSync, EvmProvider, ProvidersWithEntryPointT, SimulationProvider, StateOverride,
+    BundleHandler, DAGasOracleSync, EvmProvider, ProvidersWithEntryPointT, SimulationProvider,
+    StateOverride,
 };
 use rundler_sim::{FeeUpdate, Prechecker, Simulator};
 use rundler_types::{
     pool::{
-        MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+        MempoolError, PaymasterMetadata, PoolOperation, PrecheckViolation, Reputation,
+        ReputationStatus, StakeStatus,
     },
     Entity, EntityUpdate, EntityUpdateType, EntryPointVersion, UserOperation, UserOperationId,
-    UserOperationVariant,
+    UserOperationVariant, UserOpsPerAggregator,
 };
-use rundler_utils::emit::WithEntryPoint;
+use rundler_utils::{emit::WithEntryPoint, math, nonce_utils};
 use tokio::sync::broadcast;
 use tonic::async_trait;
 use tracing::info;
 
 use super::{
     paymaster::PaymasterTracker, pool::PoolInner, reputation::AddressReputation, Mempool,
-    MempoolResult, OperationOrigin, PoolConfig,
+    MempoolResult, MempoolSnapshot, OperationOrigin, PoolConfig,
 };
 use crate::{
     chain::ChainUpdate,
@@ -62,6 +68,13 @@ pub(crate) struct UoPool<UP: UoPoolProvidersT, EP: ProvidersWithEntryPointT> {
     metrics: UoPoolMetrics,
 }
 
+/// Maximum number of operations in an atomic group
+const MAX_ATOMIC_GROUP_SIZE: usize = 16;
+
+/// Beneficiary of the `handleOps` calls that apply the earlier ops of an atomic group
+/// when validating the later ones
+const ATOMIC_GROUP_BENEFICIARY: Address = address!("ffffffffffffffffffffffffffffffffffffffff");
+
 struct UoPoolState<D> {
     pool: PoolInner<D>,
     throttled_ops: HashSet<B256>,
@@ -70,6 +83,17 @@ struct UoPoolState<D> {
     gas_fees: FeeUpdate,
 }
 
+/// An operation that passed validation, along with the data needed to add it to the pool
+struct ValidatedOperation {
+    pool_op: PoolOperation,
+    required_pvg: u128,
+    /// Hash of the operation it replaces, if any
+    replacement: Option<B256>,
+    throttled: bool,
+    entity_summary: EntitySummary,
+    mempools: Vec<B256>,
+}
+
 impl<UP, EP> UoPool<UP, EP>
 where
     EP: ProvidersWithEntryPointT,
@@ -154,6 +178,7 @@ where
         &self,
         op: UserOperationVariant,
         block_hash: B256,
+        state_override: StateOverride,
     ) -> MempoolResult<()> {
         // Check call gas limit efficiency only if needed
         if self.config.gas_limit_efficiency_reject_threshold > 0.0 {
@@ -181,7 +206,7 @@ where
                     Address::ZERO,
                     Bytes::new(),
                     block_hash.into(),
-                    StateOverride::default(),
+                    state_override,
                 )
                 .await;
             match sim_result {
@@ -214,6 +239,290 @@ where
 
         Ok(())
     }
+
+    /// Validates an operation against the state at the given block with `state_override`
+    /// applied, without adding it to the pool
+    async fn validate_operation(
+        &self,
+        op: UserOperationVariant,
+        block_hash: B256,
+        block_number: u64,
+        mut state_override: StateOverride,
+    ) -> MempoolResult<ValidatedOperation> {
+        // TODO(danc) aggregator reputation is not implemented
+        // TODO(danc) catch ops with aggregators prior to simulation and reject
+
+        // Check reputation of entities in involved in the operation
+        // If throttled, entity can have THROTTLED_ENTITY_MEMPOOL_COUNT inflight operation at a time, else reject
+        // If banned, reject
+        let mut entity_summary = EntitySummary::default();
+        let mut throttled = false;
+
+        for entity in op.entities() {
+            let address = entity.address;
+            let reputation = match self.reputation.status(address) {
+                ReputationStatus::Ok => EntityReputation::Ok,
+                ReputationStatus::Throttled => {
+                    if self.state.read().pool.address_count(&address)
+                        >= self.config.throttled_entity_mempool_count as usize
+                    {
+                        return Err(MempoolError::EntityThrottled(entity));
+                    } else {
+                        throttled = true;
+                        EntityReputation::ThrottledButOk
+                    }
+                }
+                ReputationStatus::Banned => {
+                    return Err(MempoolError::EntityThrottled(entity));
+                }
+            };
+
+            entity_summary.set_status(
+                entity.kind,
+                EntityStatus {
+                    address,
+                    reputation,
+                },
+            );
+        }
+
+        // Check if op is already known or replacing another, and if so, ensure its fees are high enough
+        // do this before simulation to save resources
+        let replacement = self.state.read().pool.check_replacement(&op)?;
+        // Check if op violates the STO-040 spec rule
+        self.state.read().pool.check_multiple_roles_violation(&op)?;
+
+        // check if paymaster is present and exists in pool
+        // this is optimistic and could potentially lead to
+        // multiple user operations call this before they are
+        // added to the pool and can lead to an overdraft
+        self.paymaster.check_operation_cost(&op).await?;
+
+        // Prechecks
+        let versioned_op = op.clone().into();
+
+        let precheck_ret = self
+            .pool_providers
+            .prechecker()
+            .check(&versioned_op, block_hash.into(), state_override.clone())
+            .await?;
+
+        // An op that follows another op from the same sender in the pool is simulated
+        // as if the earlier ops of its nonce key were already executed
+        if self
+            .state
+            .read()
+            .pool
+            .previous_operation_id(&op.id())
+            .is_some()
+        {
+            nonce_utils::apply_nonce_override(
+                &mut state_override,
+                self.config.entry_point,
+                op.sender(),
+                op.nonce(),
+            );
+        }
+
+        // Only let ops with successful simulations through
+        // Run simulation and call gas limit efficiency check in parallel
+        let sim_fut = self
+            .pool_providers
+            .simulator()
+            .simulate_validation(versioned_op, block_hash, None, state_override.clone())
+            .map_err(Into::into);
+        let call_gas_check_future =
+            self.check_call_gas_limit_efficiency(op.clone(), block_hash, state_override);
+        let (sim_result, _) = tokio::try_join!(sim_fut, call_gas_check_future)?;
+
+        // Only trusted aggregators are supported. An aggregated op must also pay for its share
+        // of the aggregator's signature validation through its pre-verification gas.
+        let mut required_pvg = precheck_ret.required_pre_verification_gas;
+        let aggregator = match &sim_result.aggregator {
+            Some(agg) => {
+                let Some(agg_config) = self.config.aggregators.get(&agg.address) else {
+                    return Err(MempoolError::UnsupportedAggregator(agg.address));
+                };
+                let min_pvg = math::percent(
+                    required_pvg,
+                    self.config
+                        .precheck_settings
+                        .pre_verification_gas_accept_percent,
+                )
+                .saturating_add(agg_config.validation_gas_per_op);
+                if op.pre_verification_gas() < min_pvg {
+                    return Err(MempoolError::PrecheckViolation(
+                        PrecheckViolation::PreVerificationGasTooLow(
+                            op.pre_verification_gas(),
+                            min_pvg,
+                        ),
+                    ));
+                }
+                required_pvg = required_pvg.saturating_add(agg_config.validation_gas_per_op);
+                Some(agg.address)
+            }
+            None => None,
+        };
+
+        // Check if op violates the STO-041 spec rule
+        self.state
+            .read()
+            .pool
+            .check_associated_storage(&sim_result.associated_addresses, &op)?;
+
+        // Check pre op gas limit efficiency
+        let pre_op_gas_efficiency = sim_result.pre_op_gas as f32 / op.pre_op_gas_limit() as f32;
+        if pre_op_gas_efficiency < self.config.gas_limit_efficiency_reject_threshold {
+            return Err(MempoolError::PreOpGasLimitEfficiencyTooLow(
+                self.config.gas_limit_efficiency_reject_threshold,
+                pre_op_gas_efficiency,
+            ));
+        }
+
+        let valid_time_range = sim_result.valid_time_range;
+        let pool_op = PoolOperation {
+            uo: op,
+            entry_point: self.config.entry_point,
+            aggregator,
+            valid_time_range,
+            expected_code_hash: sim_result.code_hash,
+            sim_block_hash: block_hash,
+            sim_block_number: block_number,
+            account_is_staked: sim_result.account_is_staked,
+            entity_infos: sim_result.entity_infos,
+            da_gas_data: precheck_ret.da_gas_data,
+            atomic_group: None,
+            mempools: sim_result.mempools.clone(),
+        };
+
+        // Check sender count in mempool. If sender has too many operations, must be staked
+        {
+            let state = self.state.read();
+            if !pool_op.account_is_staked
+                && state.pool.address_count(&pool_op.uo.sender())
+                    >= self.config.same_sender_mempool_count
+            {
+                return Err(MempoolError::MaxOperationsReached(
+                    self.config.same_sender_mempool_count,
+                    Entity::account(pool_op.uo.sender()),
+                ));
+            }
+
+            // Check unstaked non-sender entity counts in the mempool
+            for entity in pool_op
+                .unstaked_entities()
+                .filter(|e| e.address != pool_op.entity_infos.sender.address())
+            {
+                let ops_allowed = self.reputation.get_ops_allowed(entity.address);
+                if state.pool.address_count(&entity.address) >= ops_allowed as usize {
+                    return Err(MempoolError::MaxOperationsReached(
+                        ops_allowed as usize,
+                        entity,
+                    ));
+                }
+            }
+        }
+
+        Ok(ValidatedOperation {
+            pool_op,
+            required_pvg,
+            replacement,
+            throttled,
+            entity_summary,
+            mempools: sim_result.mempools,
+        })
+    }
+
+    /// Updates the paymaster balances and reputations for an operation once it has
+    /// been added to the pool, and announces it
+    async fn on_operation_added(
+        &self,
+        origin: OperationOrigin,
+        op: ValidatedOperation,
+    ) -> MempoolResult<()> {
+        let ValidatedOperation {
+            pool_op,
+            replacement,
+            entity_summary,
+            mempools,
+            ..
+        } = op;
+
+        // Add op cost to pending paymaster balance
+        // once the operation has been added to the pool
+        self.paymaster.add_or_update_balance(&pool_op).await?;
+
+        // Update reputation, restored operations were already counted by the previous run
+        if replacement.is_none() && origin != OperationOrigin::Restored {
+            pool_op.entities().unique().for_each(|e| {
+                self.reputation.add_seen(e.address);
+                if self.reputation.status(e.address) == ReputationStatus::Throttled {
+                    self.throttle_entity(e);
+                } else if self.reputation.status(e.address) == ReputationStatus::Banned {
+                    self.remove_entity(e);
+                }
+            });
+        }
+
+        // Emit event
+        let op_hash = pool_op
+            .uo
+            .hash(self.config.entry_point, self.config.chain_spec.id);
+        self.emit(OpPoolEvent::ReceivedOp {
+            op_hash,
+            op: pool_op.uo,
+            block_number: pool_op.sim_block_number,
+            origin,
+            valid_after: pool_op.valid_time_range.valid_after,
+            valid_until: pool_op.valid_time_range.valid_until,
+            entities: entity_summary,
+            mempools,
+        });
+
+        Ok(())
+    }
+
+    /// Returns whether the sender's operations belong to the given shard
+    fn in_shard(&self, sender: Address, shard_index: u64) -> bool {
+        // short-circuit the mod if there is only 1 shard
+        self.config.num_shards <= 1
+            || U256::from_be_bytes(sender.into_word().into()) % U256::from(self.config.num_shards)
+                == U256::from(shard_index)
+    }
+
+    /// Checks the shape of an atomic group before its operations are validated
+    fn check_atomic_group(&self, ops: &[UserOperationVariant]) -> MempoolResult<()> {
+        if ops.is_empty() || ops.len() > MAX_ATOMIC_GROUP_SIZE {
+            return Err(MempoolError::InvalidAtomicGroup(format!(
+                "must have between 1 and {MAX_ATOMIC_GROUP_SIZE} operations, found {}",
+                ops.len()
+            )));
+        }
+        if !ops.iter().map(|op| op.sender()).all_unique() {
+            return Err(MempoolError::InvalidAtomicGroup(
+                "operations must have different senders".to_string(),
+            ));
+        }
+
+        let state = self.state.read();
+        for op in ops {
+            // A group must be bundleable on its own, so it can't depend on other
+            // ops in the pool
+            if state.pool.check_replacement(op)?.is_some() {
+                return Err(MempoolError::InvalidAtomicGroup(format!(
+                    "operation from {:?} would replace an operation in the pool",
+                    op.sender()
+                )));
+            }
+            if state.pool.previous_operation_id(&op.id()).is_some() {
+                return Err(MempoolError::InvalidAtomicGroup(format!(
+                    "operation from {:?} follows an operation with its previous nonce in the pool",
+                    op.sender()
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -468,43 +777,6 @@ where
         origin: OperationOrigin,
         op: UserOperationVariant,
     ) -> MempoolResult<B256> {
-        // TODO(danc) aggregator reputation is not implemented
-        // TODO(danc) catch ops with aggregators prior to simulation and reject
-
-        // Check reputation of entities in involved in the operation
-        // If throttled, entity can have THROTTLED_ENTITY_MEMPOOL_COUNT inflight operation at a time, else reject
-        // If banned, reject
-        let mut entity_summary = EntitySummary::default();
-        let mut throttled = false;
-
-        for entity in op.entities() {
-            let address = entity.address;
-            let reputation = match self.reputation.status(address) {
-                ReputationStatus::Ok => EntityReputation::Ok,
-                ReputationStatus::Throttled => {
-                    if self.state.read().pool.address_count(&address)
-                        >= self.config.throttled_entity_mempool_count as usize
-                    {
-                        return Err(MempoolError::EntityThrottled(entity));
-                    } else {
-                        throttled = true;
-                        EntityReputation::ThrottledButOk
-                    }
-                }
-                ReputationStatus::Banned => {
-                    return Err(MempoolError::EntityThrottled(entity));
-                }
-            };
-
-            entity_summary.set_status(
-                entity.kind,
-                EntityStatus {
-                    address,
-                    reputation,
-                },
-            );
-        }
-
         // NOTE: We get the latest block from the provider here to avoid a race condition
         // where the pool is still processing the previous block, but the user may have been
         // notified of a new block.
@@ -518,145 +790,127 @@ where
             .await
             .map_err(anyhow::Error::from)?;
 
-        // Check if op is already known or replacing another, and if so, ensure its fees are high enough
-        // do this before simulation to save resources
-        let replacement = self.state.read().pool.check_replacement(&op)?;
-        // Check if op violates the STO-040 spec rule
-        self.state.read().pool.check_multiple_roles_violation(&op)?;
-
-        // check if paymaster is present and exists in pool
-        // this is optimistic and could potentially lead to
-        // multiple user operations call this before they are
-        // added to the pool and can lead to an overdraft
-        self.paymaster.check_operation_cost(&op).await?;
-
-        // Prechecks
-        let versioned_op = op.clone().into();
-
-        let precheck_ret = self
-            .pool_providers
-            .prechecker()
-            .check(&versioned_op, block_hash.into())
-            .await?;
-
-        // Only let ops with successful simulations through
-        // Run simulation and call gas limit efficiency check in parallel
-        let sim_fut = self
-            .pool_providers
-            .simulator()
-            .simulate_validation(versioned_op, block_hash, None)
-            .map_err(Into::into);
-        let call_gas_check_future = self.check_call_gas_limit_efficiency(op.clone(), block_hash);
-        let (sim_result, _) = tokio::try_join!(sim_fut, call_gas_check_future)?;
-
-        // No aggregators supported for now
-        if let Some(agg) = &sim_result.aggregator {
-            return Err(MempoolError::UnsupportedAggregator(agg.address));
-        }
-
-        // Check if op violates the STO-041 spec rule
-        self.state
-            .read()
-            .pool
-            .check_associated_storage(&sim_result.associated_addresses, &op)?;
-
-        // Check pre op gas limit efficiency
-        let pre_op_gas_efficiency = sim_result.pre_op_gas as f32 / op.pre_op_gas_limit() as f32;
-        if pre_op_gas_efficiency < self.config.gas_limit_efficiency_reject_threshold {
-            return Err(MempoolError::PreOpGasLimitEfficiencyTooLow(
-                self.config.gas_limit_efficiency_reject_threshold,
-                pre_op_gas_efficiency,
-            ));
-        }
-
-        let valid_time_range = sim_result.valid_time_range;
-        let pool_op = PoolOperation {
-            uo: op,
-            entry_point: self.config.entry_point,
-            aggregator: None,
-            valid_time_range,
-            expected_code_hash: sim_result.code_hash,
-            sim_block_hash: block_hash,
-            sim_block_number: block_number,
-            account_is_staked: sim_result.account_is_staked,
-            entity_infos: sim_result.entity_infos,
-            da_gas_data: precheck_ret.da_gas_data,
-        };
-
-        // Check sender count in mempool. If sender has too many operations, must be staked
-        {
-            let state = self.state.read();
-            if !pool_op.account_is_staked
-                && state.pool.address_count(&pool_op.uo.sender())
-                    >= self.config.same_sender_mempool_count
-            {
-                return Err(MempoolError::MaxOperationsReached(
-                    self.config.same_sender_mempool_count,
-                    Entity::account(pool_op.uo.sender()),
-                ));
-            }
-
-            // Check unstaked non-sender entity counts in the mempool
-            for entity in pool_op
-                .unstaked_entities()
-                .filter(|e| e.address != pool_op.entity_infos.sender.address())
-            {
-                let ops_allowed = self.reputation.get_ops_allowed(entity.address);
-                if state.pool.address_count(&entity.address) >= ops_allowed as usize {
-                    return Err(MempoolError::MaxOperationsReached(
-                        ops_allowed as usize,
-                        entity,
-                    ));
-                }
-            }
-        }
-
+        let validated = self
+            .validate_operation(op, block_hash, block_number, StateOverride::default())
+            .await?;
+
         // Add op to pool
-        let hash = {
+        {
             let mut state = self.state.write();
             let hash = state
                 .pool
-                .add_operation(pool_op.clone(), precheck_ret.required_pre_verification_gas)?;
+                .add_operation(validated.pool_op.clone(), validated.required_pvg)?;
 
-            if throttled {
+            if validated.throttled {
                 state.throttled_ops.insert(hash);
             }
-            hash
-        };
-
-        // Add op cost to pending paymaster balance
-        // once the operation has been added to the pool
-        self.paymaster.add_or_update_balance(&pool_op).await?;
-
-        // Update reputation
-        if replacement.is_none() {
-            pool_op.entities().unique().for_each(|e| {
-                self.reputation.add_seen(e.address);
-                if self.reputation.status(e.address) == ReputationStatus::Throttled {
-                    self.throttle_entity(e);
-                } else if self.reputation.status(e.address) == ReputationStatus::Banned {
-                    self.remove_entity(e);
-                }
-            });
         }
 
-        // Emit event
-        let op_hash = pool_op
+        let hash = validated
+            .pool_op
             .uo
             .hash(self.config.entry_point, self.config.chain_spec.id);
-        self.emit(OpPoolEvent::ReceivedOp {
-            op_hash,
-            op: pool_op.uo,
-            block_number: pool_op.sim_block_number,
-            origin,
-            valid_after: pool_op.valid_time_range.valid_after,
-            valid_until: pool_op.valid_time_range.valid_until,
-            entities: entity_summary,
-        });
+        self.on_operation_added(origin, validated).await?;
 
         Ok(hash)
     }
 
+    async fn add_operation_group(
+        &self,
+        origin: OperationOrigin,
+        ops: Vec<UserOperationVariant>,
+    ) -> MempoolResult<Vec<B256>> {
+        self.check_atomic_group(&ops)?;
+
+        let (block_hash, block_number) = self
+            .ep_providers
+            .evm()
+            .get_latest_block_hash_and_number()
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        let hashes = ops
+            .iter()
+            .map(|op| op.hash(self.config.entry_point, self.config.chain_spec.id))
+            .collect::<Vec<_>>();
+
+        // Validate all ops at the same block before adding any of them. Each op is validated
+        // on top of the state left by the ops before it in the group, as it may depend on them.
+        let mut validated = Vec::with_capacity(ops.len());
+        let mut state_override = StateOverride::default();
+        for (i, op) in ops.into_iter().enumerate() {
+            let mut op = self
+                .validate_operation(op, block_hash, block_number, state_override.clone())
+                .await?;
+            op.pool_op.atomic_group = Some(hashes.clone());
+
+            if i + 1 < hashes.len() {
+                // The ops are applied without their aggregated signatures
+                if op.pool_op.aggregator.is_some() {
+                    return Err(MempoolError::InvalidAtomicGroup(
+                        "only the last operation may use an aggregator".to_string(),
+                    ));
+                }
+                state_override = self
+                    .ep_providers
+                    .entry_point()
+                    .get_handle_ops_state_override(
+                        vec![UserOpsPerAggregator {
+                            user_ops: vec![op.pool_op.uo.clone().into()],
+                            aggregator: Address::ZERO,
+                            signature: Bytes::new(),
+                        }],
+                        ATOMIC_GROUP_BENEFICIARY,
+                        None,
+                        block_hash.into(),
+                        state_override,
+                    )
+                    .await
+                    .map_err(anyhow::Error::from)?;
+            }
+            validated.push(op);
+        }
+
+        // Add all ops to the pool under the same lock, so that the best operations never
+        // see part of the group
+        {
+            let mut state = self.state.write();
+            for op in &validated {
+                if let Err(error) = state
+                    .pool
+                    .add_operation(op.pool_op.clone(), op.required_pvg)
+                {
+                    // Removing any op of the group removes the ops added before it
+                    state.pool.remove_operation_by_hash(hashes[0]);
+                    return Err(error);
+                }
+            }
+
+            // Enforcing the pool size may have evicted an op added earlier
+            if hashes
+                .iter()
+                .any(|hash| state.pool.get_operation_by_hash(*hash).is_none())
+            {
+                for hash in &hashes {
+                    state.pool.remove_operation_by_hash(*hash);
+                }
+                return Err(MempoolError::DiscardedOnInsert);
+            }
+
+            for (op, hash) in validated.iter().zip(&hashes) {
+                if op.throttled {
+                    state.throttled_ops.insert(*hash);
+                }
+            }
+        }
+
+        for op in validated {
+            self.on_operation_added(origin, op).await?;
+        }
+
+        Ok(hashes)
+    }
+
     fn remove_operations(&self, hashes: &[B256]) {
         let mut count: u64 = 0;
         let mut removed_hashes = vec![];
@@ -760,30 +1014,78 @@ where
 
         // get the best operations from the pool
         let state = self.state.read();
-        let ordered_ops = state.pool.best_operations();
-        // keep track of senders to avoid sending multiple ops from the same sender
+        // keep track of senders to limit unstaked senders to a single chain of consecutive nonces
         let mut senders = HashSet::<Address>::new();
+        let mut included = HashSet::<UserOperationId>::new();
+        // ops waiting for the op with their previous nonce to be included, keyed by its id
+        let mut waiting = HashMap::<UserOperationId, Arc<PoolOperation>>::new();
+        // atomic groups already considered, keyed by the hash of their first op
+        let mut groups = HashSet::<B256>::new();
+        let mut best = Vec::new();
+
+        for op in state.pool.best_operations() {
+            if best.len() >= max {
+                break;
+            }
 
-        Ok(ordered_ops
-            .into_iter()
-            .filter(|op| {
-                let sender_num = U256::from_be_bytes(op.uo.sender().into_word().into());
-
-                // short-circuit the mod if there is only 1 shard
-                ((self.config.num_shards == 1) ||
-                (sender_num
-                        % U256::from(self.config.num_shards)
-                        == U256::from(shard_index))) &&
-                // filter out ops from unstaked senders we've already seen
-                if !op.account_is_staked {
-                    senders.insert(op.uo.sender())
-                } else {
-                    true
+            // the ops of an atomic group are included together, in order, when its
+            // highest priority op is reached. Groups are sharded by their first op's sender.
+            if let Some(group) = &op.atomic_group {
+                if !groups.insert(group[0]) {
+                    continue;
                 }
-            })
-            .take(max)
-            .map(Into::into)
-            .collect())
+                let Some(group_ops) = state.pool.atomic_group_operations(group) else {
+                    continue;
+                };
+                if !self.in_shard(group_ops[0].uo.sender(), shard_index)
+                    || best.len() + group_ops.len() > max
+                    || group_ops
+                        .iter()
+                        .any(|op| !op.account_is_staked && senders.contains(&op.uo.sender()))
+                {
+                    continue;
+                }
+                for op in group_ops {
+                    senders.insert(op.uo.sender());
+                    included.insert(op.uo.id());
+                    best.push(op);
+                }
+                continue;
+            }
+
+            if !self.in_shard(op.uo.sender(), shard_index) {
+                continue;
+            }
+
+            // ops are only included after the op with their previous nonce, if it is in the pool,
+            // so that they can be simulated and executed in order
+            let follows_included = match state.pool.previous_operation_id(&op.uo.id()) {
+                Some(previous) if !included.contains(&previous) => {
+                    waiting.insert(previous, op);
+                    continue;
+                }
+                Some(_) => true,
+                None => false,
+            };
+
+            // filter out ops from unstaked senders we've already seen, unless they
+            // continue the sender's chain of nonces
+            if !op.account_is_staked && !follows_included && !senders.insert(op.uo.sender()) {
+                continue;
+            }
+
+            let mut next = Some(op);
+            while let Some(op) = next {
+                if best.len() >= max {
+                    break;
+                }
+                included.insert(op.uo.id());
+                next = waiting.remove(&op.uo.id());
+                best.push(op);
+            }
+        }
+
+        Ok(best)
     }
 
     fn all_operations(&self, max: usize) -> Vec<Arc<PoolOperation>> {
@@ -839,6 +1141,92 @@ where
         self.paymaster.set_tracking(paymaster);
         self.reputation.set_tracking(reputation);
     }
+
+    fn snapshot(&self) -> MempoolSnapshot {
+        let (ops, mined_ops) = {
+            let state = self.state.read();
+            (
+                state.pool.operations().collect(),
+                state.pool.mined_operations().collect(),
+            )
+        };
+
+        MempoolSnapshot {
+            ops,
+            mined_ops,
+            reputations: self.reputation.dump_reputation(),
+            paymasters: self.paymaster.paymaster_addresses(),
+        }
+    }
+
+    async fn restore(&self, snapshot: MempoolSnapshot) {
+        // Restore reputation first so that operations from banned entities are not restored
+        for rep in &snapshot.reputations {
+            self.reputation
+                .set_reputation(rep.address, rep.ops_seen, rep.ops_included);
+        }
+
+        // Balances are re-read from the entry point as they may have changed while stopped.
+        // Pending balances are rebuilt as the operations are added back below.
+        for &paymaster in &snapshot.paymasters {
+            if let Err(e) = self.paymaster.paymaster_balance(paymaster).await {
+                tracing::warn!("Failed to restore balance for paymaster {paymaster:?}: {e:?}");
+            }
+        }
+
+        {
+            let mut state = self.state.write();
+            for (op, block_number) in snapshot.mined_ops {
+                state.pool.restore_mined_operation(op, block_number);
+            }
+        }
+
+        let total = snapshot.ops.len();
+        let mut restored = 0;
+        // atomic groups are restored together, keyed by the hash of their first op
+        let mut groups = HashMap::<B256, Vec<Arc<PoolOperation>>>::new();
+        for op in snapshot.ops {
+            if let Some(group) = &op.atomic_group {
+                groups.entry(group[0]).or_default().push(op);
+                continue;
+            }
+            match self
+                .add_operation(OperationOrigin::Restored, op.uo.clone())
+                .await
+            {
+                Ok(_) => restored += 1,
+                Err(e) => tracing::debug!("Dropped restored operation: {e}"),
+            }
+        }
+        for mut ops in groups.into_values() {
+            let Some(group) = ops[0].atomic_group.clone() else {
+                continue;
+            };
+            ops.sort_by_key(|op| {
+                let hash = op
+                    .uo
+                    .hash(self.config.entry_point, self.config.chain_spec.id);
+                group.iter().position(|h| *h == hash)
+            });
+            let count = ops.len();
+            match self
+                .add_operation_group(
+                    OperationOrigin::Restored,
+                    ops.into_iter().map(|op| op.uo.clone()).collect(),
+                )
+                .await
+            {
+                Ok(_) => restored += count,
+                Err(e) => tracing::debug!("Dropped restored atomic group: {e}"),
+            }
+        }
+
+        info!(
+            "Restored {restored} of {total} op(s) and {} reputation(s) on entry point {:?}",
+            snapshot.reputations.len(),
+            self.config.entry_point,
+        );
+    }
 }
 
 // Type erasure for UoPool providers
@@ -918,26 +1306,27 @@ mod tests {
     use alloy_primitives::{uint, Bytes};
     use mockall::Sequence;
     use rundler_provider::{
-        DepositInfo, ExecutionResult, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider,
-        ProvidersWithEntryPoint,
+        AccountOverride, AggregatorSimOut, DepositInfo, ExecutionResult, MockDAGasOracleSync,
+        MockEntryPointV0_6, MockEvmProvider, ProvidersWithEntryPoint,
     };
     use rundler_sim::{
         MockPrechecker, MockSimulator, PrecheckError, PrecheckReturn, PrecheckSettings,
         SimulationError, SimulationResult, SimulationSettings, ViolationError,
     };
     use rundler_types::{
+        aggregator::{AggregatorConfig, AggregatorConfigs, AggregatorType},
         chain::ChainSpec,
         da::DAGasUOData,
         pool::{PrecheckViolation, SimulationViolation},
         v0_6::UserOperation,
         EntityInfo, EntityInfos, EntityType, EntryPointVersion,
-        UserOperation as UserOperationTrait, ValidTimeRange,
+        UserOperation as UserOperationTrait, ValidTimeRange, ValidationRevert,
     };
 
     use super::*;
     use crate::{
         chain::{BalanceUpdate, MinedOp},
-        mempool::{PaymasterConfig, ReputationParams},
+        mempool::{OrderingStrategy, PaymasterConfig, ReputationParams},
     };
 
     const THROTTLE_SLACK: u64 = 5;
@@ -1003,6 +1392,184 @@ mod tests {
         assert_eq!(pool.best_operations(3, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn add_op_group() {
+        let ops = vec![
+            create_op(Address::random(), 0, 1, None),
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ];
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+        let pool = create_pool(ops);
+
+        let hashes = pool
+            .add_operation_group(OperationOrigin::Local, uos[..2].to_vec())
+            .await
+            .unwrap();
+        pool.add_operation(OperationOrigin::Local, uos[2].clone())
+            .await
+            .unwrap();
+
+        // the group is returned in order at the position of its best op
+        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+        // the group is not split when there is no room for all of it
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![uos[2].clone()]);
+
+        // removing an op of the group removes the whole group
+        pool.remove_operations(&hashes[..1]);
+        check_ops(pool.best_operations(3, 0).unwrap(), vec![uos[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn add_op_group_dependent() {
+        let ops = vec![
+            create_op(Address::random(), 0, 1, None),
+            create_op(Address::random(), 0, 1, None),
+        ];
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+
+        // the state left by the first op, e.g. a deposit the second op relies on
+        let mut group_state = StateOverride::default();
+        group_state.insert(
+            Address::random(),
+            AccountOverride {
+                balance: Some(U256::from(1)),
+                ..Default::default()
+            },
+        );
+
+        let mut entrypoint = MockEntryPointV0_6::new();
+        let first: UserOperation = uos[0].clone().into();
+        let state = group_state.clone();
+        entrypoint
+            .expect_get_handle_ops_state_override()
+            .withf(move |ops, _, _, _, state_override| {
+                ops.len() == 1
+                    && ops[0].user_ops == vec![first.clone()]
+                    && state_override.is_empty()
+            })
+            .times(1)
+            .returning(move |_, _, _, _, _| Ok(state.clone()));
+
+        // the second op fails validation unless it is validated on top of the first
+        let mut simulator = MockSimulator::new();
+        let dependent = uos[1].sender();
+        simulator
+            .expect_simulate_validation()
+            .withf(move |op, _, _, state_override| {
+                op.sender == dependent && *state_override != group_state
+            })
+            .returning(|_, _, _, _| {
+                Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        SimulationViolation::ValidationRevert(ValidationRevert::EntryPoint(
+                            "AA31 paymaster deposit too low".to_string(),
+                        )),
+                    ]),
+                    entity_infos: None,
+                })
+            });
+        let pool = create_pool_with_simulator(default_config(), ops, entrypoint, simulator);
+
+        let ret = pool
+            .add_operation(OperationOrigin::Local, uos[1].clone())
+            .await;
+        assert!(matches!(ret, Err(MempoolError::SimulationViolation(_))));
+
+        pool.add_operation_group(OperationOrigin::Local, uos.clone())
+            .await
+            .unwrap();
+        check_ops(pool.best_operations(2, 0).unwrap(), uos);
+    }
+
+    #[tokio::test]
+    async fn add_op_group_same_sender() {
+        let sender = Address::random();
+        let ops = vec![create_op(sender, 0, 1, None), create_op(sender, 1, 1, None)];
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+        let pool = create_pool(ops);
+
+        let ret = pool.add_operation_group(OperationOrigin::Local, uos).await;
+        assert!(matches!(ret, Err(MempoolError::InvalidAtomicGroup(_))));
+        assert_eq!(pool.best_operations(2, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn add_op_group_replacement() {
+        let sender = Address::random();
+        let ops = vec![
+            create_op(sender, 0, 1, None),
+            create_op(Address::random(), 0, 1, None),
+            create_op(sender, 0, 2, None),
+        ];
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+        let pool = create_pool(ops);
+
+        pool.add_operation_group(OperationOrigin::Local, uos[..2].to_vec())
+            .await
+            .unwrap();
+        let ret = pool
+            .add_operation(OperationOrigin::Local, uos[2].clone())
+            .await;
+        assert!(matches!(ret, Err(MempoolError::AtomicGroupReplacement)));
+        check_ops(pool.best_operations(2, 0).unwrap(), uos[..2].to_vec());
+    }
+
+    #[tokio::test]
+    async fn add_op_untrusted_aggregator() {
+        let aggregator = Address::random();
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.aggregator = Some(aggregator);
+        let pool = create_pool(vec![op.clone()]);
+
+        let ret = pool.add_operation(OperationOrigin::Local, op.op).await;
+        assert!(matches!(ret, Err(MempoolError::UnsupportedAggregator(a)) if a == aggregator));
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn add_op_trusted_aggregator() {
+        let aggregator = Address::random();
+        let mut config = default_config();
+        config.aggregators = AggregatorConfigs::from(vec![AggregatorConfig {
+            address: aggregator,
+            aggregator_type: AggregatorType::Bls,
+            validation_gas_per_op: 10_000,
+        }]);
+
+        // required pvg from precheck is 100_000, the aggregator adds 10_000 per op
+        let mut underpaid = create_op_from_op_v0_6(UserOperation {
+            sender: Address::random(),
+            pre_verification_gas: 100_000,
+            ..UserOperation::default()
+        });
+        underpaid.aggregator = Some(aggregator);
+        let mut op = create_op_from_op_v0_6(UserOperation {
+            sender: Address::random(),
+            pre_verification_gas: 110_000,
+            ..UserOperation::default()
+        });
+        op.aggregator = Some(aggregator);
+        let pool = create_pool_with_config(config, vec![underpaid.clone(), op.clone()]);
+
+        let ret = pool
+            .add_operation(OperationOrigin::Local, underpaid.op)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::PreVerificationGasTooLow(100_000, 110_000)
+            ))
+        ));
+
+        pool.add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+        let best = pool.best_operations(1, 0).unwrap();
+        check_ops(best.clone(), vec![op.op]);
+        assert_eq!(best[0].aggregator, Some(aggregator));
+    }
+
     #[tokio::test]
     async fn chain_update_mine() {
         let paymaster = Address::random();
@@ -1041,6 +1608,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::ZERO,
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![BalanceUpdate {
@@ -1126,6 +1694,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::from(10),
                 paymaster: Some(paymaster),
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![BalanceUpdate {
@@ -1166,6 +1735,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::from(10),
                 paymaster: None,
+                ..Default::default()
             }],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![BalanceUpdate {
@@ -1207,6 +1777,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::ZERO,
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![],
@@ -1249,6 +1820,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::ZERO,
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![],
@@ -1325,6 +1897,7 @@ mod tests {
                 nonce: uos[0].nonce(),
                 actual_gas_cost: U256::ZERO,
                 paymaster: None,
+                ..Default::default()
             }],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![],
@@ -1693,6 +2266,40 @@ mod tests {
         check_ops(pool.best_operations(3, 0).unwrap(), uos);
     }
 
+    #[tokio::test]
+    async fn test_best_nonce_chain() {
+        let address = Address::random();
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(address, 0, 2, None),
+            create_op(address, 1, 5, None),
+            create_op(address, 2, 4, None),
+            // different nonce key
+            create_op_from_op_v0_6(UserOperation {
+                sender: address,
+                nonce: U256::from(1) << 64,
+                max_fee_per_gas: 1,
+                ..UserOperation::default()
+            }),
+            create_op(Address::random(), 0, 3, None),
+        ])
+        .await;
+
+        // unstaked, so only the chain starting at the first nonce is included, in nonce order
+        check_ops(
+            pool.best_operations(5, 0).unwrap(),
+            vec![
+                uos[4].clone(),
+                uos[0].clone(),
+                uos[1].clone(),
+                uos[2].clone(),
+            ],
+        );
+        check_ops(
+            pool.best_operations(2, 0).unwrap(),
+            vec![uos[4].clone(), uos[0].clone()],
+        );
+    }
+
     #[tokio::test]
     async fn test_pre_op_gas_limit_reject() {
         let mut config = default_config();
@@ -1805,6 +2412,28 @@ mod tests {
         assert_eq!(best.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_snapshot_restore() {
+        let ops = vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+            create_op(Address::random(), 0, 1, None),
+        ];
+        let (pool, uos) = create_pool_insert_ops(ops.clone()).await;
+        let snapshot = pool.snapshot();
+        assert_eq!(snapshot.ops.len(), 3);
+        assert_eq!(snapshot.reputations.len(), 3);
+
+        let restored = create_pool(ops);
+        restored.restore(snapshot).await;
+        check_ops(restored.best_operations(3, 0).unwrap(), uos);
+
+        // reputation is restored without counting the restored ops again
+        let rep = restored.dump_reputation();
+        assert_eq!(rep.len(), 3);
+        assert!(rep.iter().all(|r| r.ops_seen == 1));
+    }
+
     #[derive(Clone, Debug)]
     struct OpWithErrors {
         op: UserOperationVariant,
@@ -1812,6 +2441,7 @@ mod tests {
         precheck_error: Option<PrecheckViolation>,
         simulation_error: Option<SimulationViolation>,
         staked: bool,
+        aggregator: Option<Address>,
     }
 
     fn default_config() -> PoolConfig {
@@ -1836,6 +2466,8 @@ mod tests {
             reputation_tracking_enabled: true,
             drop_min_num_blocks: 10,
             gas_limit_efficiency_reject_threshold: 0.0,
+            ordering: OrderingStrategy::MaxFee,
+            aggregators: AggregatorConfigs::default(),
         }
     }
 
@@ -1866,6 +2498,16 @@ mod tests {
         args: PoolConfig,
         ops: Vec<OpWithErrors>,
         entrypoint: MockEntryPointV0_6,
+    ) -> UoPool<impl UoPoolProvidersT, impl ProvidersWithEntryPointT> {
+        create_pool_with_simulator(args, ops, entrypoint, MockSimulator::new())
+    }
+
+    // Expectations already set on `simulator` take precedence over those of `ops`
+    fn create_pool_with_simulator(
+        args: PoolConfig,
+        ops: Vec<OpWithErrors>,
+        entrypoint: MockEntryPointV0_6,
+        mut simulator: MockSimulator,
     ) -> UoPool<impl UoPoolProvidersT, impl ProvidersWithEntryPointT> {
         let entrypoint = Arc::new(entrypoint);
 
@@ -1873,7 +2515,6 @@ mod tests {
         evm.expect_get_latest_block_hash_and_number()
             .returning(|| Ok((B256::ZERO, 0)));
 
-        let mut simulator = MockSimulator::new();
         let mut prechecker = MockPrechecker::new();
         let entry_point = Arc::new(entrypoint);
 
@@ -1898,7 +2539,7 @@ mod tests {
             .returning(|| Ok(FeeUpdate::default()));
 
         for op in ops {
-            prechecker.expect_check().returning(move |_, _| {
+            prechecker.expect_check().returning(move |_, _, _| {
                 if let Some(error) = &op.precheck_error {
                     Err(PrecheckError::Violations(vec![error.clone()]))
                 } else {
@@ -1910,7 +2551,7 @@ mod tests {
             });
             simulator
                 .expect_simulate_validation()
-                .returning(move |_, _, _| {
+                .returning(move |_, _, _, _| {
                     if let Some(error) = &op.simulation_error {
                         Err(SimulationError {
                             violation_error: ViolationError::Violations(vec![error.clone()]),
@@ -1928,6 +2569,10 @@ mod tests {
                                 ..EntityInfos::default()
                             },
                             pre_op_gas: 100_000,
+                            aggregator: op.aggregator.map(|address| AggregatorSimOut {
+                                address,
+                                signature: Bytes::new(),
+                            }),
                             ..SimulationResult::default()
                         })
                     }
@@ -2001,6 +2646,7 @@ mod tests {
             precheck_error: None,
             simulation_error: None,
             staked: false,
+            aggregator: None,
         }
     }
 
@@ -2024,6 +2670,7 @@ mod tests {
             precheck_error,
             simulation_error,
             staked,
+            aggregator: None,
         }
     }
 
@@ -2034,6 +2681,7 @@ mod tests {
             precheck_error: None,
             simulation_error: None,
             staked: false,
+            aggregator: None,
         }
     }
 
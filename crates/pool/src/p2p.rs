@@ -0,0 +1,149 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, B256};
+use rundler_p2p::{GossipPool, GossipValidation, P2pHandle};
+use rundler_types::{
+    pool::{MempoolError, Pool, PoolError},
+    UserOperation, UserOperationVariant,
+};
+use rundler_utils::emit::WithEntryPoint;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::{emit::OpPoolEvent, mempool::OperationOrigin, server::LocalPoolHandle};
+
+/// Connects the p2p network to the local pool.
+///
+/// Operations received from peers are added with `External` origin, so that they
+/// are not published back to the network.
+pub(crate) struct P2pPool {
+    pool: LocalPoolHandle,
+    chain_id: u64,
+}
+
+impl P2pPool {
+    pub(crate) fn new(pool: LocalPoolHandle, chain_id: u64) -> Self {
+        Self { pool, chain_id }
+    }
+}
+
+#[async_trait::async_trait]
+impl GossipPool for P2pPool {
+    async fn add_external_op(
+        &self,
+        entry_point: Address,
+        op: UserOperationVariant,
+    ) -> GossipValidation {
+        match self
+            .pool
+            .add_op_with_origin(entry_point, op, OperationOrigin::External)
+            .await
+        {
+            Ok(_) => GossipValidation::Accept,
+            // Only penalize the peer for operations that can never be valid, others
+            // may have been valid when they were sent, or be rejected by local policy.
+            Err(PoolError::MempoolError(
+                e @ (MempoolError::SimulationViolation(_) | MempoolError::UnsupportedAggregator(_)),
+            )) => {
+                debug!("rejected external op: {e}");
+                GossipValidation::Reject
+            }
+            Err(e) => {
+                debug!("ignored external op: {e}");
+                GossipValidation::Ignore
+            }
+        }
+    }
+
+    async fn pooled_op_hashes(
+        &self,
+        entry_point: Address,
+        mempool_id: B256,
+    ) -> anyhow::Result<Vec<B256>> {
+        Ok(self
+            .pool
+            .debug_dump_mempool(entry_point)
+            .await?
+            .into_iter()
+            .filter(|op| op.mempools.contains(&mempool_id))
+            .map(|op| op.uo.hash(op.entry_point, self.chain_id))
+            .collect())
+    }
+
+    async fn pooled_ops_by_hash(
+        &self,
+        hashes: Vec<B256>,
+    ) -> anyhow::Result<Vec<UserOperationVariant>> {
+        let mut ops = vec![];
+        for hash in hashes {
+            if let Some(op) = self.pool.get_op_by_hash(hash).await? {
+                ops.push(op.uo);
+            }
+        }
+        Ok(ops)
+    }
+}
+
+/// Publishes operations accepted via local RPC to the p2p network, on the topics of
+/// the mempools that they were accepted into.
+pub(crate) async fn publish_local_ops(
+    p2p: P2pHandle,
+    pool: LocalPoolHandle,
+    mut events: broadcast::Receiver<WithEntryPoint<OpPoolEvent>>,
+) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(c)) => {
+                error!("p2p publisher lagged {c} pool events");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                info!("pool event channel closed, stopping p2p publisher");
+                return;
+            }
+        };
+
+        let OpPoolEvent::ReceivedOp {
+            op_hash,
+            op,
+            origin: OperationOrigin::Local,
+            mempools,
+            ..
+        } = event.event
+        else {
+            continue;
+        };
+
+        // EIP-7702 authorizations are not part of the p2p user operation encoding
+        if op.authorization_tuple().is_some() {
+            continue;
+        }
+
+        // Atomic groups are not part of the p2p protocol, gossiping their ops alone would
+        // let peers bundle them separately
+        let verified_at_block_hash = match pool.get_op_by_hash(op_hash).await {
+            Ok(Some(pool_op)) if pool_op.atomic_group.is_none() => pool_op.sim_block_hash,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("failed to get op {op_hash:?} to publish: {e:?}");
+                continue;
+            }
+        };
+
+        for mempool_id in mempools {
+            p2p.publish(mempool_id, verified_at_block_hash, vec![op.clone()]);
+        }
+    }
+}
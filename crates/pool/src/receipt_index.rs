@@ -0,0 +1,258 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{path::Path, sync::Arc};
+
+use alloy_primitives::{Address, B256};
+use anyhow::{ensure, Context};
+use redb::{Database, ReadableTable, TableDefinition};
+use rundler_task::GracefulShutdown;
+use rundler_types::pool::MinedOpLocation;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::chain::{ChainUpdate, MinedOp};
+
+const MINED_OPS: TableDefinition<'_, &[u8; 32], &[u8]> = TableDefinition::new("mined_ops");
+
+/// On-disk index of where user operations were mined, keyed by operation hash.
+///
+/// Follows the mined and unmined operations of chain updates, so entries are only
+/// removed by reorgs that the chain tracker can see. Reorgs deeper than the chain
+/// history can leave stale entries, readers should check the location against the
+/// chain before using it.
+///
+/// The index is never backfilled, operations mined before it was enabled or while the
+/// pool was stopped are missing from it.
+pub(crate) struct ReceiptIndex {
+    db: Database,
+}
+
+impl ReceiptIndex {
+    /// Opens the index at `path`, creating it if it doesn't exist
+    pub(crate) fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::create(path)
+            .with_context(|| format!("should open receipt index at {}", path.display()))?;
+        Ok(Self { db })
+    }
+
+    #[cfg(test)]
+    fn in_memory() -> Self {
+        let db = Database::builder()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .unwrap();
+        Self { db }
+    }
+
+    /// Applies the mined and unmined operations of a chain update
+    pub(crate) fn apply(&self, update: &ChainUpdate) -> anyhow::Result<()> {
+        if update.mined_ops.is_empty() && update.unmined_ops.is_empty() {
+            return Ok(());
+        }
+
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(MINED_OPS)?;
+            // Not deduped, an operation unmined and mined again in the same update
+            // has moved to a different block.
+            for op in &update.unmined_ops {
+                let stored = table
+                    .get(&op.hash.0)?
+                    .map(|value| decode(op.hash, value.value()))
+                    .transpose()?;
+                if stored.is_some_and(|stored| stored.block_hash == op.block_hash) {
+                    table.remove(&op.hash.0)?;
+                }
+            }
+            for op in &update.mined_ops {
+                table.insert(&op.hash.0, encode(op).as_slice())?;
+            }
+        }
+        txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Returns where the operation with `hash` was mined, if it is indexed
+    pub(crate) fn get(&self, hash: B256) -> anyhow::Result<Option<MinedOpLocation>> {
+        let txn = self.db.begin_read()?;
+        let table = match txn.open_table(MINED_OPS) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        table
+            .get(&hash.0)?
+            .map(|value| decode(hash, value.value()))
+            .transpose()
+    }
+}
+
+const ENCODED_LEN: usize = 20 + 32 + 8 + 32;
+
+fn encode(op: &MinedOp) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(ENCODED_LEN);
+    bytes.extend_from_slice(op.entry_point.as_slice());
+    bytes.extend_from_slice(op.tx_hash.as_slice());
+    bytes.extend_from_slice(&op.block_number.to_be_bytes());
+    bytes.extend_from_slice(op.block_hash.as_slice());
+    bytes
+}
+
+fn decode(hash: B256, bytes: &[u8]) -> anyhow::Result<MinedOpLocation> {
+    ensure!(
+        bytes.len() == ENCODED_LEN,
+        "indexed mined op {hash:?} should be {ENCODED_LEN} bytes, found {}",
+        bytes.len()
+    );
+    let (entry_point, rest) = bytes.split_at(20);
+    let (transaction_hash, rest) = rest.split_at(32);
+    let (block_number, block_hash) = rest.split_at(8);
+    Ok(MinedOpLocation {
+        hash,
+        entry_point: Address::from_slice(entry_point),
+        transaction_hash: B256::from_slice(transaction_hash),
+        block_number: u64::from_be_bytes(block_number.try_into()?),
+        block_hash: B256::from_slice(block_hash),
+    })
+}
+
+/// Applies chain updates to the index until shutdown
+pub(crate) async fn run(
+    index: Arc<ReceiptIndex>,
+    mut chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+    shutdown: GracefulShutdown,
+) {
+    loop {
+        let update = tokio::select! {
+            _ = shutdown.clone() => {
+                info!("Stopping receipt index");
+                break;
+            }
+            update = chain_updates.recv() => update,
+        };
+        let update = match update {
+            Ok(update) => update,
+            Err(broadcast::error::RecvError::Lagged(c)) => {
+                warn!("Receipt index lagged {c} chain updates, operations mined in them are not indexed");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                info!("Chain update channel closed, stopping receipt index");
+                break;
+            }
+        };
+
+        let index = Arc::clone(&index);
+        match tokio::task::spawn_blocking(move || index.apply(&update)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to update receipt index: {e:?}"),
+            Err(e) => error!("Receipt index update task panicked: {e:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mined_op(hash: B256, block_number: u64, block_hash: B256) -> MinedOp {
+        MinedOp {
+            hash,
+            entry_point: Address::random(),
+            tx_hash: B256::random(),
+            block_number,
+            block_hash,
+            ..Default::default()
+        }
+    }
+
+    fn location(op: &MinedOp) -> MinedOpLocation {
+        MinedOpLocation {
+            hash: op.hash,
+            entry_point: op.entry_point,
+            transaction_hash: op.tx_hash,
+            block_number: op.block_number,
+            block_hash: op.block_hash,
+        }
+    }
+
+    #[test]
+    fn test_mined_and_unmined() {
+        let index = ReceiptIndex::in_memory();
+        let hash = B256::random();
+        assert_eq!(index.get(hash).unwrap(), None);
+
+        let op = mined_op(hash, 10, B256::random());
+        index
+            .apply(&ChainUpdate {
+                mined_ops: vec![op],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(index.get(hash).unwrap(), Some(location(&op)));
+
+        index
+            .apply(&ChainUpdate {
+                unmined_ops: vec![op],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(index.get(hash).unwrap(), None);
+    }
+
+    #[test]
+    fn test_remined_in_same_update() {
+        let index = ReceiptIndex::in_memory();
+        let hash = B256::random();
+        let old = mined_op(hash, 10, B256::random());
+        let new = mined_op(hash, 11, B256::random());
+
+        index
+            .apply(&ChainUpdate {
+                mined_ops: vec![old],
+                ..Default::default()
+            })
+            .unwrap();
+        index
+            .apply(&ChainUpdate {
+                mined_ops: vec![new],
+                unmined_ops: vec![old],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(index.get(hash).unwrap(), Some(location(&new)));
+    }
+
+    #[test]
+    fn test_unmined_from_other_block_is_ignored() {
+        let index = ReceiptIndex::in_memory();
+        let hash = B256::random();
+        let current = mined_op(hash, 11, B256::random());
+        let stale = mined_op(hash, 10, B256::random());
+
+        index
+            .apply(&ChainUpdate {
+                mined_ops: vec![current],
+                ..Default::default()
+            })
+            .unwrap();
+        index
+            .apply(&ChainUpdate {
+                unmined_ops: vec![stale],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(index.get(hash).unwrap(), Some(location(&current)));
+    }
+}
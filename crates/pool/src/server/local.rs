@@ -24,17 +24,21 @@ use rundler_task::{
 };
 use rundler_types::{
     pool::{
-        MempoolError, NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult,
-        Reputation, ReputationStatus, StakeStatus,
+        MempoolError, MinedOpLocation, NewHead, OpStatusUpdate, PaymasterMetadata, Pool, PoolError,
+        PoolOperation, PoolResult, Reputation, ReputationStatus, StakeStatus,
     },
     EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
 };
+use rundler_utils::emit::{WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tracing::{error, info};
 
+use super::op_status::OpStatusTracker;
 use crate::{
     chain::ChainUpdate,
+    emit::OpPoolEvent,
     mempool::{Mempool, OperationOrigin},
+    receipt_index::ReceiptIndex,
 };
 
 /// Local pool server builder
@@ -43,6 +47,7 @@ pub struct LocalPoolBuilder {
     req_sender: mpsc::Sender<ServerRequest>,
     req_receiver: mpsc::Receiver<ServerRequest>,
     block_sender: broadcast::Sender<NewHead>,
+    op_status_sender: broadcast::Sender<OpStatusUpdate>,
 }
 
 impl LocalPoolBuilder {
@@ -50,10 +55,12 @@ impl LocalPoolBuilder {
     pub fn new(request_capacity: usize, block_capacity: usize) -> Self {
         let (req_sender, req_receiver) = mpsc::channel(request_capacity);
         let (block_sender, _) = broadcast::channel(block_capacity);
+        let (op_status_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
             req_sender,
             req_receiver,
             block_sender,
+            op_status_sender,
         }
     }
 
@@ -70,13 +77,18 @@ impl LocalPoolBuilder {
         task_spawner: Box<dyn TaskSpawner>,
         mempools: HashMap<Address, Arc<dyn Mempool>>,
         chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+        events: broadcast::Receiver<WithEntryPoint<OpPoolEvent>>,
+        receipt_index: Option<Arc<ReceiptIndex>>,
         shutdown: GracefulShutdown,
     ) -> BoxFuture<'static, ()> {
         let runner = LocalPoolServerRunner::new(
             self.req_receiver,
             self.block_sender,
+            self.op_status_sender,
             mempools,
             chain_updates,
+            events,
+            receipt_index,
             task_spawner,
         );
         Box::pin(runner.run(shutdown))
@@ -94,12 +106,35 @@ pub struct LocalPoolHandle {
 struct LocalPoolServerRunner {
     req_receiver: mpsc::Receiver<ServerRequest>,
     block_sender: broadcast::Sender<NewHead>,
+    op_status_sender: broadcast::Sender<OpStatusUpdate>,
+    op_status: OpStatusTracker,
     mempools: HashMap<Address, Arc<dyn Mempool>>,
     chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+    events: broadcast::Receiver<WithEntryPoint<OpPoolEvent>>,
+    receipt_index: Option<Arc<ReceiptIndex>>,
     task_spawner: Box<dyn TaskSpawner>,
 }
 
 impl LocalPoolHandle {
+    /// Adds a user operation to the pool, recording where it came from
+    pub(crate) async fn add_op_with_origin(
+        &self,
+        entry_point: Address,
+        op: UserOperationVariant,
+        origin: OperationOrigin,
+    ) -> PoolResult<B256> {
+        let req = ServerRequestKind::AddOp {
+            entry_point,
+            op,
+            origin,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::AddOp { hash } => Ok(hash),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn send(&self, request: ServerRequestKind) -> PoolResult<ServerResponse> {
         let (send, recv) = oneshot::channel();
         self.req_sender
@@ -131,14 +166,23 @@ impl Pool for LocalPoolHandle {
     }
 
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256> {
-        let req = ServerRequestKind::AddOp {
+        self.add_op_with_origin(entry_point, op, OperationOrigin::Local)
+            .await
+    }
+
+    async fn add_op_group(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+    ) -> PoolResult<Vec<B256>> {
+        let req = ServerRequestKind::AddOpGroup {
             entry_point,
-            op,
+            ops,
             origin: OperationOrigin::Local,
         };
         let resp = self.send(req).await?;
         match resp {
-            ServerResponse::AddOp { hash } => Ok(hash),
+            ServerResponse::AddOpGroup { hashes } => Ok(hashes),
             _ => Err(PoolError::UnexpectedResponse),
         }
     }
@@ -170,6 +214,15 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn get_mined_op_location(&self, hash: B256) -> PoolResult<Option<MinedOpLocation>> {
+        let req = ServerRequestKind::GetMinedOpLocation { hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetMinedOpLocation { location } => Ok(location),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let req = ServerRequestKind::RemoveOps { entry_point, ops };
         let resp = self.send(req).await?;
@@ -343,6 +396,30 @@ impl Pool for LocalPoolHandle {
             _ => Err(PoolError::UnexpectedResponse),
         }
     }
+
+    async fn subscribe_op_status(
+        &self,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = OpStatusUpdate> + Send>>> {
+        let req = ServerRequestKind::SubscribeOpStatus;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::SubscribeOpStatus { mut updates } => Ok(Box::pin(stream! {
+                loop {
+                    match updates.recv().await {
+                        Ok(update) => yield update,
+                        Err(broadcast::error::RecvError::Lagged(c)) => {
+                            error!("op_status_receiver lagged {c} updates");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            info!("op_status_receiver closed, ending subscription");
+                            break;
+                        }
+                    }
+                }
+            })),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
 }
 
 #[async_trait]
@@ -364,19 +441,33 @@ impl LocalPoolServerRunner {
     fn new(
         req_receiver: mpsc::Receiver<ServerRequest>,
         block_sender: broadcast::Sender<NewHead>,
+        op_status_sender: broadcast::Sender<OpStatusUpdate>,
         mempools: HashMap<Address, Arc<dyn Mempool>>,
         chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+        events: broadcast::Receiver<WithEntryPoint<OpPoolEvent>>,
+        receipt_index: Option<Arc<ReceiptIndex>>,
         task_spawner: Box<dyn TaskSpawner>,
     ) -> Self {
         Self {
             req_receiver,
             block_sender,
+            op_status_sender,
+            op_status: OpStatusTracker::default(),
             mempools,
             chain_updates,
+            events,
+            receipt_index,
             task_spawner,
         }
     }
 
+    fn send_op_status(&self, updates: Vec<OpStatusUpdate>) {
+        for update in updates {
+            // no receivers is not an error
+            let _ = self.op_status_sender.send(update);
+        }
+    }
+
     fn get_pool(&self, entry_point: Address) -> PoolResult<&Arc<dyn Mempool>> {
         self.mempools
             .get(&entry_point)
@@ -522,6 +613,7 @@ impl LocalPoolServerRunner {
     }
 
     async fn run(mut self, shutdown: GracefulShutdown) {
+        let mut events_open = true;
         loop {
             tokio::select! {
                 _ = shutdown.clone() => {
@@ -529,6 +621,9 @@ impl LocalPoolServerRunner {
                 }
                 chain_update = self.chain_updates.recv() => {
                     if let Ok(chain_update) = chain_update {
+                        let updates = self.op_status.on_chain_update(&chain_update);
+                        self.send_op_status(updates);
+
                         // Update each mempool before notifying listeners of the chain update
                         // This allows the mempools to update their state before the listeners
                         // pull information from the mempool.
@@ -550,6 +645,20 @@ impl LocalPoolServerRunner {
                         }));
                     }
                 }
+                event = self.events.recv(), if events_open => {
+                    match event {
+                        Ok(event) => {
+                            let updates = self.op_status.on_pool_event(&event);
+                            self.send_op_status(updates);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(c)) => {
+                            error!("op status tracker lagged {c} pool events");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            events_open = false;
+                        }
+                    }
+                }
                 Some(req) = self.req_receiver.recv() => {
                     let resp = match req.request {
                         // Async methods
@@ -557,20 +666,8 @@ impl LocalPoolServerRunner {
                         ServerRequestKind::AddOp { entry_point, op, origin } => {
                             let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
                                 let resp = 'resp: {
-                                    match mempool.entry_point_version() {
-                                        EntryPointVersion::V0_6 => {
-                                            if !matches!(&op, UserOperationVariant::V0_6(_)){
-                                                 break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.6 {:?}", op.uo_type()).into());
-                                            }
-                                        }
-                                        EntryPointVersion::V0_7 => {
-                                            if !matches!(&op, UserOperationVariant::V0_7(_)){
-                                                break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.7 {:?}", op.uo_type()).into());
-                                            }
-                                        }
-                                        EntryPointVersion::Unspecified => {
-                                            panic!("Found mempool with unspecified entry point version")
-                                        }
+                                    if let Err(e) = check_op_version(mempool.entry_point_version(), &op) {
+                                        break 'resp Err(e.into());
                                     }
 
                                     match mempool.add_operation(origin, op).await {
@@ -587,6 +684,29 @@ impl LocalPoolServerRunner {
                             self.get_pool_and_spawn(entry_point, req.response, fut);
                             continue;
                         },
+                        ServerRequestKind::AddOpGroup { entry_point, ops, origin } => {
+                            let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
+                                let resp = 'resp: {
+                                    for op in &ops {
+                                        if let Err(e) = check_op_version(mempool.entry_point_version(), op) {
+                                            break 'resp Err(e.into());
+                                        }
+                                    }
+
+                                    match mempool.add_operation_group(origin, ops).await {
+                                        Ok(hashes) => Ok(ServerResponse::AddOpGroup { hashes }),
+                                        Err(e) => Err(e.into()),
+                                    }
+                                };
+
+                                if let Err(e) = response.send(resp) {
+                                    tracing::error!("Failed to send response: {:?}", e);
+                                }
+                            };
+
+                            self.get_pool_and_spawn(entry_point, req.response, fut);
+                            continue;
+                        },
                         ServerRequestKind::GetStakeStatus { entry_point, address }=> {
                             let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
                                 let resp = match mempool.get_stake_status(address).await {
@@ -600,6 +720,22 @@ impl LocalPoolServerRunner {
                             self.get_pool_and_spawn(entry_point, req.response, fut);
                             continue;
                         },
+                        ServerRequestKind::GetMinedOpLocation { hash } => {
+                            let index = self.receipt_index.clone();
+                            let response = req.response;
+                            // Reads from disk, so runs on a blocking thread
+                            self.task_spawner.spawn_blocking(Box::pin(async move {
+                                let resp = match index.as_deref().map(|index| index.get(hash)) {
+                                    Some(Ok(location)) => Ok(ServerResponse::GetMinedOpLocation { location }),
+                                    Some(Err(e)) => Err(e.into()),
+                                    None => Ok(ServerResponse::GetMinedOpLocation { location: None }),
+                                };
+                                if let Err(e) = response.send(resp) {
+                                    tracing::error!("Failed to send response: {:?}", e);
+                                }
+                            }));
+                            continue;
+                        },
 
                         // Sync methods
                         // Responses are sent in the main loop below
@@ -683,6 +819,9 @@ impl LocalPoolServerRunner {
                         ServerRequestKind::SubscribeNewHeads => {
                             Ok(ServerResponse::SubscribeNewHeads { new_heads: self.block_sender.subscribe() } )
                         }
+                        ServerRequestKind::SubscribeOpStatus => {
+                            Ok(ServerResponse::SubscribeOpStatus { updates: self.op_status_sender.subscribe() } )
+                        }
                     };
                     if let Err(e) = req.response.send(resp) {
                         tracing::error!("Failed to send response: {:?}", e);
@@ -693,6 +832,25 @@ impl LocalPoolServerRunner {
     }
 }
 
+/// Checks that the operation's version is the version of the mempool's entry point
+fn check_op_version(version: EntryPointVersion, op: &UserOperationVariant) -> anyhow::Result<()> {
+    let (matches, name) = match version {
+        EntryPointVersion::V0_6 => (matches!(op, UserOperationVariant::V0_6(_)), "v0.6"),
+        EntryPointVersion::V0_7 => (matches!(op, UserOperationVariant::V0_7(_)), "v0.7"),
+        EntryPointVersion::V0_8 => (matches!(op, UserOperationVariant::V0_7(_)), "v0.8"),
+        EntryPointVersion::Unspecified => {
+            panic!("Found mempool with unspecified entry point version")
+        }
+    };
+    if !matches {
+        anyhow::bail!(
+            "Invalid user operation version for mempool {name} {:?}",
+            op.uo_type()
+        );
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct ServerRequest {
     request: ServerRequestKind,
@@ -707,6 +865,11 @@ enum ServerRequestKind {
         op: UserOperationVariant,
         origin: OperationOrigin,
     },
+    AddOpGroup {
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+        origin: OperationOrigin,
+    },
     GetOps {
         entry_point: Address,
         max_ops: u64,
@@ -715,6 +878,9 @@ enum ServerRequestKind {
     GetOpByHash {
         hash: B256,
     },
+    GetMinedOpLocation {
+        hash: B256,
+    },
     RemoveOps {
         entry_point: Address,
         ops: Vec<B256>,
@@ -759,6 +925,7 @@ enum ServerRequestKind {
         address: Address,
     },
     SubscribeNewHeads,
+    SubscribeOpStatus,
 }
 
 #[derive(Debug)]
@@ -769,12 +936,18 @@ enum ServerResponse {
     AddOp {
         hash: B256,
     },
+    AddOpGroup {
+        hashes: Vec<B256>,
+    },
     GetOps {
         ops: Vec<PoolOperation>,
     },
     GetOpByHash {
         op: Option<PoolOperation>,
     },
+    GetMinedOpLocation {
+        location: Option<MinedOpLocation>,
+    },
     RemoveOps,
     RemoveOpById {
         hash: Option<B256>,
@@ -801,18 +974,25 @@ enum ServerResponse {
     SubscribeNewHeads {
         new_heads: broadcast::Receiver<NewHead>,
     },
+    SubscribeOpStatus {
+        updates: broadcast::Receiver<OpStatusUpdate>,
+    },
 }
 
 #[cfg(test)]
 mod tests {
     use std::{iter::zip, sync::Arc};
 
+    use alloy_primitives::U256;
     use futures_util::StreamExt;
     use reth_tasks::TaskManager;
-    use rundler_types::v0_6::UserOperation;
+    use rundler_types::{pool::OpStatus, v0_6::UserOperation, UserOperation as _};
 
     use super::*;
-    use crate::{chain::ChainUpdate, mempool::MockMempool};
+    use crate::{
+        chain::{ChainUpdate, MinedOp},
+        mempool::MockMempool,
+    };
 
     #[tokio::test]
     async fn test_add_op() {
@@ -860,6 +1040,71 @@ mod tests {
         assert_eq!(number, new_block.block_number);
     }
 
+    #[tokio::test]
+    async fn test_op_status() {
+        let mut mock_pool = MockMempool::new();
+        mock_pool.expect_on_chain_update().returning(|_| ());
+
+        let ep = Address::random();
+        let pool: Arc<dyn Mempool> = Arc::new(mock_pool);
+        let state = setup(HashMap::from([(ep, pool)]));
+
+        let mut sub = state.handle.subscribe_op_status().await.unwrap();
+
+        let op_hash = B256::random();
+        let op = mock_op();
+        state
+            .event_tx
+            .send(WithEntryPoint {
+                entry_point: ep,
+                event: OpPoolEvent::ReceivedOp {
+                    op_hash,
+                    op: op.clone(),
+                    block_number: 0,
+                    origin: OperationOrigin::Local,
+                    valid_after: 0.into(),
+                    valid_until: 0.into(),
+                    entities: Default::default(),
+                    mempools: vec![],
+                },
+            })
+            .unwrap();
+
+        let update = sub.next().await.unwrap();
+        assert_eq!(update.hash, op_hash);
+        assert_eq!(update.entry_point, ep);
+        assert_eq!(update.status, OpStatus::Pending);
+
+        let block_hash = B256::random();
+        state
+            .chain_update_tx
+            .send(Arc::new(ChainUpdate {
+                latest_block_hash: block_hash,
+                latest_block_number: 1,
+                mined_ops: vec![MinedOp {
+                    hash: op_hash,
+                    entry_point: ep,
+                    sender: op.sender(),
+                    nonce: op.nonce(),
+                    actual_gas_cost: U256::ZERO,
+                    paymaster: None,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }))
+            .unwrap();
+
+        let update = sub.next().await.unwrap();
+        assert_eq!(update.hash, op_hash);
+        assert_eq!(
+            update.status,
+            OpStatus::Mined {
+                block_number: 1,
+                block_hash
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_get_supported_entry_points() {
         let mut eps0 = vec![Address::random(), Address::random(), Address::random()];
@@ -924,6 +1169,7 @@ mod tests {
     struct State {
         handle: LocalPoolHandle,
         chain_update_tx: broadcast::Sender<Arc<ChainUpdate>>,
+        event_tx: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
         _task_manager: TaskManager,
     }
 
@@ -931,17 +1177,19 @@ mod tests {
         let builder = LocalPoolBuilder::new(10, 10);
         let handle = builder.get_handle();
         let (tx, rx) = broadcast::channel(10);
+        let (event_tx, event_rx) = broadcast::channel(10);
         let tm = TaskManager::current();
         let ts = tm.executor();
         let ts_box = Box::new(ts.clone());
 
         ts.spawn_critical_with_graceful_shutdown_signal("test pool", |shutdown| {
-            builder.run(ts_box, pools, rx, shutdown)
+            builder.run(ts_box, pools, rx, event_rx, None, shutdown)
         });
 
         State {
             handle,
             chain_update_tx: tx,
+            event_tx,
             _task_manager: tm,
         }
     }
@@ -14,6 +14,8 @@
 mod local;
 pub use local::{LocalPoolBuilder, LocalPoolHandle};
 
+mod op_status;
+
 mod remote;
-pub(crate) use remote::remote_mempool_server_task;
 pub use remote::RemotePoolClient;
+pub(crate) use remote::{protos, remote_mempool_server_task};
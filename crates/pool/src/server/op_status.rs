@@ -0,0 +1,294 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::HashMap;
+
+use alloy_primitives::{Address, B256};
+use rundler_types::{
+    pool::{OpDropReason, OpStatus, OpStatusUpdate},
+    UserOperation, UserOperationId,
+};
+use rundler_utils::emit::WithEntryPoint;
+
+use crate::{
+    chain::{ChainUpdate, MinedOp},
+    emit::{OpPoolEvent, OpRemovalReason},
+};
+
+/// Derives operation status updates from pool events and chain updates.
+///
+/// Pool events only identify removed operations by hash, so the tracker remembers the
+/// entry point, sender and nonce of each operation in the pool.
+#[derive(Debug, Default)]
+pub(crate) struct OpStatusTracker {
+    ops: HashMap<B256, (Address, UserOperationId)>,
+    hashes_by_id: HashMap<(Address, UserOperationId), B256>,
+}
+
+impl OpStatusTracker {
+    pub(crate) fn on_pool_event(
+        &mut self,
+        event: &WithEntryPoint<OpPoolEvent>,
+    ) -> Vec<OpStatusUpdate> {
+        let entry_point = event.entry_point;
+        match &event.event {
+            OpPoolEvent::ReceivedOp { op_hash, op, .. } => {
+                let id = op.id();
+                let mut updates = vec![];
+                // Replacements are not emitted as pool events, detect them here
+                if let Some(replaced) = self.hashes_by_id.insert((entry_point, id), *op_hash) {
+                    if replaced != *op_hash {
+                        self.ops.remove(&replaced);
+                        updates.push(status_update(
+                            replaced,
+                            entry_point,
+                            id,
+                            OpStatus::Dropped(OpDropReason::Replaced),
+                        ));
+                    }
+                }
+                self.ops.insert(*op_hash, (entry_point, id));
+                updates.push(status_update(*op_hash, entry_point, id, OpStatus::Pending));
+                updates
+            }
+            OpPoolEvent::RemovedOp { op_hash, reason } => {
+                // Mined operations are reported from chain updates, which include operations
+                // that were never in this pool.
+                let Some(reason) = drop_reason(reason) else {
+                    return vec![];
+                };
+                self.untrack(*op_hash)
+                    .map(|(entry_point, id)| {
+                        status_update(*op_hash, entry_point, id, OpStatus::Dropped(reason))
+                    })
+                    .into_iter()
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub(crate) fn on_chain_update(&mut self, update: &ChainUpdate) -> Vec<OpStatusUpdate> {
+        let deduped_ops = update.deduped_ops();
+        let mut updates = vec![];
+
+        for op in &deduped_ops.unmined_ops {
+            // Unmined operations are returned to the pool if they are still valid
+            self.ops.insert(op.hash, (op.entry_point, op.id()));
+            self.hashes_by_id.insert((op.entry_point, op.id()), op.hash);
+            updates.push(mined_op_update(op, OpStatus::Unmined));
+        }
+
+        for op in &deduped_ops.mined_ops {
+            self.untrack(op.hash);
+            updates.push(mined_op_update(
+                op,
+                OpStatus::Mined {
+                    block_number: update.latest_block_number,
+                    block_hash: update.latest_block_hash,
+                },
+            ));
+        }
+
+        updates
+    }
+
+    fn untrack(&mut self, hash: B256) -> Option<(Address, UserOperationId)> {
+        let (entry_point, id) = self.ops.remove(&hash)?;
+        if self.hashes_by_id.get(&(entry_point, id)) == Some(&hash) {
+            self.hashes_by_id.remove(&(entry_point, id));
+        }
+        Some((entry_point, id))
+    }
+}
+
+fn drop_reason(reason: &OpRemovalReason) -> Option<OpDropReason> {
+    match reason {
+        OpRemovalReason::Requested => Some(OpDropReason::Requested),
+        OpRemovalReason::Mined { .. } => None,
+        OpRemovalReason::ThrottledAndOld { .. }
+        | OpRemovalReason::EntityRemoved { .. }
+        | OpRemovalReason::EntityThrottled { .. } => Some(OpDropReason::Entity),
+        OpRemovalReason::Expired { .. } => Some(OpDropReason::Expired),
+        OpRemovalReason::PoolSizeExceeded => Some(OpDropReason::PoolSizeExceeded),
+        OpRemovalReason::AtomicGroupRemoved { .. } => Some(OpDropReason::AtomicGroup),
+    }
+}
+
+fn status_update(
+    hash: B256,
+    entry_point: Address,
+    id: UserOperationId,
+    status: OpStatus,
+) -> OpStatusUpdate {
+    OpStatusUpdate {
+        hash,
+        entry_point,
+        sender: id.sender,
+        nonce: id.nonce,
+        status,
+    }
+}
+
+fn mined_op_update(op: &MinedOp, status: OpStatus) -> OpStatusUpdate {
+    status_update(op.hash, op.entry_point, op.id(), status)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use rundler_types::{v0_6, UserOperationVariant};
+
+    use super::*;
+    use crate::{emit::EntitySummary, mempool::OperationOrigin};
+
+    const ENTRY_POINT: Address = Address::ZERO;
+
+    fn received(hash: B256, sender: Address, nonce: u64) -> WithEntryPoint<OpPoolEvent> {
+        WithEntryPoint {
+            entry_point: ENTRY_POINT,
+            event: OpPoolEvent::ReceivedOp {
+                op_hash: hash,
+                op: UserOperationVariant::V0_6(v0_6::UserOperation {
+                    sender,
+                    nonce: U256::from(nonce),
+                    ..Default::default()
+                }),
+                block_number: 0,
+                origin: OperationOrigin::Local,
+                valid_after: 0.into(),
+                valid_until: 0.into(),
+                entities: EntitySummary::default(),
+                mempools: vec![],
+            },
+        }
+    }
+
+    fn removed(hash: B256, reason: OpRemovalReason) -> WithEntryPoint<OpPoolEvent> {
+        WithEntryPoint {
+            entry_point: ENTRY_POINT,
+            event: OpPoolEvent::RemovedOp {
+                op_hash: hash,
+                reason,
+            },
+        }
+    }
+
+    fn mined_op(hash: B256, sender: Address, nonce: u64) -> MinedOp {
+        MinedOp {
+            hash,
+            entry_point: ENTRY_POINT,
+            sender,
+            nonce: U256::from(nonce),
+            actual_gas_cost: U256::ZERO,
+            paymaster: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_received_and_removed() {
+        let mut tracker = OpStatusTracker::default();
+        let hash = B256::random();
+        let sender = Address::random();
+
+        let updates = tracker.on_pool_event(&received(hash, sender, 1));
+        assert_eq!(
+            updates,
+            vec![OpStatusUpdate {
+                hash,
+                entry_point: ENTRY_POINT,
+                sender,
+                nonce: U256::from(1),
+                status: OpStatus::Pending,
+            }]
+        );
+
+        let updates = tracker.on_pool_event(&removed(hash, OpRemovalReason::Requested));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].sender, sender);
+        assert_eq!(
+            updates[0].status,
+            OpStatus::Dropped(OpDropReason::Requested)
+        );
+        assert!(tracker.ops.is_empty());
+        assert!(tracker.hashes_by_id.is_empty());
+
+        // unknown operations are ignored
+        let updates = tracker.on_pool_event(&removed(hash, OpRemovalReason::Requested));
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_replaced() {
+        let mut tracker = OpStatusTracker::default();
+        let sender = Address::random();
+        let old = B256::random();
+        let new = B256::random();
+
+        tracker.on_pool_event(&received(old, sender, 1));
+        let updates = tracker.on_pool_event(&received(new, sender, 1));
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].hash, old);
+        assert_eq!(updates[0].status, OpStatus::Dropped(OpDropReason::Replaced));
+        assert_eq!(updates[1].hash, new);
+        assert_eq!(updates[1].status, OpStatus::Pending);
+        assert_eq!(tracker.ops.len(), 1);
+    }
+
+    #[test]
+    fn test_mined_and_unmined() {
+        let mut tracker = OpStatusTracker::default();
+        let sender = Address::random();
+        let hash = B256::random();
+        let block_hash = B256::random();
+
+        tracker.on_pool_event(&received(hash, sender, 1));
+        let updates = tracker.on_chain_update(&ChainUpdate {
+            latest_block_number: 10,
+            latest_block_hash: block_hash,
+            mined_ops: vec![mined_op(hash, sender, 1)],
+            ..Default::default()
+        });
+        assert_eq!(
+            updates[0].status,
+            OpStatus::Mined {
+                block_number: 10,
+                block_hash
+            }
+        );
+        assert!(tracker.ops.is_empty());
+
+        // the mined removal event does not produce a second update
+        let updates = tracker.on_pool_event(&removed(
+            hash,
+            OpRemovalReason::Mined {
+                block_number: 10,
+                block_hash,
+                tx_hash: B256::random(),
+            },
+        ));
+        assert!(updates.is_empty());
+
+        let updates = tracker.on_chain_update(&ChainUpdate {
+            latest_block_number: 11,
+            unmined_ops: vec![mined_op(hash, sender, 1)],
+            ..Default::default()
+        });
+        assert_eq!(updates[0].status, OpStatus::Unmined);
+
+        // the operation is tracked again after being unmined
+        let updates = tracker.on_pool_event(&removed(hash, OpRemovalReason::Requested));
+        assert_eq!(updates.len(), 1);
+    }
+}
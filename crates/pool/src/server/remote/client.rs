@@ -24,8 +24,8 @@ use rundler_task::{
 use rundler_types::{
     chain::ChainSpec,
     pool::{
-        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult, Reputation,
-        ReputationStatus, StakeStatus,
+        MinedOpLocation, NewHead, OpStatusUpdate, PaymasterMetadata, Pool, PoolError,
+        PoolOperation, PoolResult, Reputation, ReputationStatus, StakeStatus,
     },
     EntityUpdate, UserOperationId, UserOperationVariant,
 };
@@ -42,16 +42,18 @@ use tonic_health::{
 };
 
 use super::protos::{
-    self, add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_paymaster_balances_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
+    self, add_op_group_response, add_op_response, admin_set_tracking_response,
+    debug_clear_state_response, debug_dump_mempool_response,
+    debug_dump_paymaster_balances_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_mined_op_location_response, get_op_by_hash_response,
     get_ops_response, get_reputation_status_response, get_stake_status_response,
     op_pool_client::OpPoolClient, remove_op_by_id_response, remove_ops_response,
-    update_entities_response, AddOpRequest, AdminSetTrackingRequest, DebugClearStateRequest,
-    DebugDumpMempoolRequest, DebugDumpPaymasterBalancesRequest, DebugDumpReputationRequest,
-    DebugSetReputationRequest, GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest,
-    RemoveOpsRequest, ReputationStatus as ProtoReputationStatus, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
+    update_entities_response, AddOpGroupRequest, AddOpRequest, AdminSetTrackingRequest,
+    DebugClearStateRequest, DebugDumpMempoolRequest, DebugDumpPaymasterBalancesRequest,
+    DebugDumpReputationRequest, DebugSetReputationRequest, GetOpsRequest,
+    GetReputationStatusRequest, GetStakeStatusRequest, RemoveOpsRequest,
+    ReputationStatus as ProtoReputationStatus, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse,
+    SubscribeOpStatusRequest, SubscribeOpStatusResponse, TryUoFromProto, UpdateEntitiesRequest,
 };
 
 /// Remote pool client
@@ -133,6 +135,54 @@ impl RemotePoolClient {
             }
         }
     }
+
+    // Handler for the op status subscription. This will attempt to resubscribe if the gRPC
+    // connection disconnects using exponential backoff.
+    async fn op_status_subscription_handler(
+        client: OpPoolClient<Channel>,
+        tx: mpsc::UnboundedSender<OpStatusUpdate>,
+    ) {
+        let mut stream = None;
+
+        loop {
+            if stream.is_none() {
+                stream = Some(
+                    retry::with_unlimited_retries(
+                        "subscribe op status",
+                        || {
+                            let mut c = client.clone();
+                            async move { c.subscribe_op_status(SubscribeOpStatusRequest {}).await }
+                        },
+                        UnlimitedRetryOpts::default(),
+                    )
+                    .await
+                    .into_inner(),
+                );
+            }
+
+            match stream.as_mut().unwrap().message().await {
+                Ok(Some(SubscribeOpStatusResponse { update: Some(u) })) => match u.try_into() {
+                    Ok(update) => {
+                        if tx.send(update).is_err() {
+                            // recv handle dropped
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("error parsing op status update: {:?}", e);
+                    }
+                },
+                Ok(Some(SubscribeOpStatusResponse { update: None })) | Ok(None) => {
+                    tracing::debug!("op status subscription closed, resubscribing");
+                    stream.take();
+                }
+                Err(e) => {
+                    tracing::error!("error in op status subscription: {:?}", e);
+                    stream.take();
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -174,6 +224,34 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn add_op_group(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+    ) -> PoolResult<Vec<B256>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .add_op_group(AddOpGroupRequest {
+                entry_point: entry_point.to_vec(),
+                ops: ops.iter().map(protos::UserOperation::from).collect(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(add_op_group_response::Result::Success(s)) => {
+                Ok(s.hashes.iter().map(|hash| B256::from_slice(hash)).collect())
+            }
+            Some(add_op_group_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn get_ops(
         &self,
         entry_point: Address,
@@ -242,6 +320,36 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn get_mined_op_location(&self, hash: B256) -> PoolResult<Option<MinedOpLocation>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_mined_op_location(protos::GetMinedOpLocationRequest {
+                hash: hash.to_proto_bytes(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_mined_op_location_response::Result::Success(s)) => Ok(s
+                .location
+                .map(MinedOpLocation::try_from)
+                .transpose()
+                .context("should convert proto mined op location")?),
+            Some(get_mined_op_location_response::Result::Failure(e)) => match e.error {
+                Some(_) => Err(e.try_into()?),
+                None => Err(PoolError::Other(anyhow::anyhow!(
+                    "should have received error from op pool"
+                )))?,
+            },
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -562,6 +670,17 @@ impl Pool for RemotePoolClient {
             .spawn(Box::pin(Self::new_heads_subscription_handler(client, tx)));
         Ok(Box::pin(UnboundedReceiverStream::new(rx)))
     }
+
+    async fn subscribe_op_status(
+        &self,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = OpStatusUpdate> + Send>>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = self.op_pool_client.clone();
+
+        self.task_spawner
+            .spawn(Box::pin(Self::op_status_subscription_handler(client, tx)));
+        Ok(Box::pin(UnboundedReceiverStream::new(rx)))
+    }
 }
 
 #[async_trait]
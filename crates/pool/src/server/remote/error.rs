@@ -24,16 +24,17 @@ use rundler_types::{
 use super::protos::{
     mempool_error, precheck_violation_error, simulation_violation_error, validation_revert,
     AccessedUndeployedContract, AccessedUnsupportedContractType, AggregatorValidationFailed,
-    AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender,
+    AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender, AtomicGroupReplacement,
     CallGasLimitEfficiencyTooLow, CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod,
     CodeHashChanged, DidNotRevert, DiscardedOnInsertError, Entity, EntityThrottledError,
     EntityType, EntryPointRevert, ExistingSenderWithInitCode, FactoryCalledCreate2Twice,
-    FactoryIsNotContract, FactoryMustBeEmpty, InvalidAccountSignature, InvalidPaymasterSignature,
-    InvalidSignature, InvalidStorageAccess, InvalidTimeRange, MaxFeePerGasTooLow,
-    MaxOperationsReachedError, MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError,
-    MultipleRolesViolation, NotStaked, OperationAlreadyKnownError, OperationDropTooSoon,
-    OperationRevert, OutOfGas, PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow,
-    PaymasterIsNotContract, PreOpGasLimitEfficiencyTooLow, PreVerificationGasTooLow,
+    FactoryIsNotContract, FactoryMustBeEmpty, InvalidAccountSignature, InvalidAtomicGroup,
+    InvalidPaymasterSignature, InvalidSignature, InvalidStorageAccess, InvalidTimeRange,
+    MaxFeePerGasTooLow, MaxOperationsReachedError, MaxPriorityFeePerGasTooLow,
+    MempoolError as ProtoMempoolError, MissingEip7702Authorization, MultipleRolesViolation,
+    NotStaked, OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas,
+    PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract,
+    PreOpGasLimitEfficiencyTooLow, PreVerificationGasTooLow,
     PrecheckViolationError as ProtoPrecheckViolationError, ReplacementUnderpricedError,
     SenderAddressUsedAsAlternateEntity, SenderFundsTooLow, SenderIsNotContractAndNoInitCode,
     SimulationViolationError as ProtoSimulationViolationError, TotalGasLimitTooHigh,
@@ -133,6 +134,12 @@ impl TryFrom<ProtoMempoolError> for MempoolError {
             Some(mempool_error::Error::CallGasLimitEfficiencyTooLow(e)) => {
                 MempoolError::CallGasLimitEfficiencyTooLow(e.required, e.actual)
             }
+            Some(mempool_error::Error::InvalidAtomicGroup(e)) => {
+                MempoolError::InvalidAtomicGroup(e.reason)
+            }
+            Some(mempool_error::Error::AtomicGroupReplacement(_)) => {
+                MempoolError::AtomicGroupReplacement
+            }
             None => bail!("unknown proto mempool error"),
         })
     }
@@ -247,6 +254,16 @@ impl From<MempoolError> for ProtoMempoolError {
                     CallGasLimitEfficiencyTooLow { required, actual },
                 )),
             },
+            MempoolError::InvalidAtomicGroup(reason) => ProtoMempoolError {
+                error: Some(mempool_error::Error::InvalidAtomicGroup(
+                    InvalidAtomicGroup { reason },
+                )),
+            },
+            MempoolError::AtomicGroupReplacement => ProtoMempoolError {
+                error: Some(mempool_error::Error::AtomicGroupReplacement(
+                    AtomicGroupReplacement {},
+                )),
+            },
         }
     }
 }
@@ -371,6 +388,15 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::MissingEip7702Authorization(addr) => ProtoPrecheckViolationError {
+                violation: Some(
+                    precheck_violation_error::Violation::MissingEip7702Authorization(
+                        MissingEip7702Authorization {
+                            sender_address: addr.to_proto_bytes(),
+                        },
+                    ),
+                ),
+            },
         }
     }
 }
@@ -443,6 +469,9 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
             Some(precheck_violation_error::Violation::FactoryMustBeEmpty(e)) => {
                 PrecheckViolation::FactoryMustBeEmpty(from_bytes(&e.factory_address)?)
             }
+            Some(precheck_violation_error::Violation::MissingEip7702Authorization(e)) => {
+                PrecheckViolation::MissingEip7702Authorization(from_bytes(&e.sender_address)?)
+            }
             None => {
                 bail!("unknown proto mempool precheck violation")
             }
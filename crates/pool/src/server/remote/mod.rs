@@ -14,7 +14,7 @@
 mod client;
 mod error;
 #[allow(non_snake_case, unreachable_pub, clippy::large_enum_variant)]
-mod protos;
+pub(crate) mod protos;
 mod server;
 
 pub use client::*;
@@ -19,17 +19,21 @@ use rundler_types::{
     chain::ChainSpec,
     da::{
         BedrockDAGasUOData as RundlerBedrockDAGasUOData, DAGasUOData as RundlerDAGasUOData,
-        NitroDAGasUOData as RundlerNitroDAGasUOData,
+        MantleDAGasUOData as RundlerMantleDAGasUOData, NitroDAGasUOData as RundlerNitroDAGasUOData,
+        ScrollDAGasUOData as RundlerScrollDAGasUOData,
+        ZkStackDAGasUOData as RundlerZkStackDAGasUOData,
     },
     pool::{
-        NewHead as PoolNewHead, PaymasterMetadata as PoolPaymasterMetadata, PoolOperation,
-        Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
+        MinedOpLocation as PoolMinedOpLocation, NewHead as PoolNewHead,
+        OpDropReason as PoolOpDropReason, OpStatus as PoolOpStatus,
+        OpStatusUpdate as PoolOpStatusUpdate, PaymasterMetadata as PoolPaymasterMetadata,
+        PoolOperation, Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
         StakeStatus as RundlerStakeStatus,
     },
     v0_6::{self, ExtendedUserOperation},
-    v0_7, Entity as RundlerEntity, EntityInfos, EntityType as RundlerEntityType,
+    v0_7, Entity as RundlerEntity, EntityInfo, EntityInfos, EntityType as RundlerEntityType,
     EntityUpdate as RundlerEntityUpdate, EntityUpdateType as RundlerEntityUpdateType,
-    StakeInfo as RundlerStakeInfo, UserOperationVariant, ValidTimeRange,
+    EntryPointVersion, StakeInfo as RundlerStakeInfo, UserOperationVariant, ValidTimeRange,
 };
 
 tonic::include_proto!("op_pool");
@@ -130,6 +134,18 @@ impl TryUoFromProto<UserOperationV06> for v0_6::UserOperation {
 
 impl From<&v0_7::UserOperation> for UserOperation {
     fn from(op: &v0_7::UserOperation) -> Self {
+        // Required for the v0.8 hash of operations initializing an EIP-7702 account
+        let authorization_tuple =
+            op.authorization_tuple
+                .as_ref()
+                .map(|authorization| AuthorizationTuple {
+                    chain_id: authorization.chain_id,
+                    address: authorization.address.to_proto_bytes(),
+                    nonce: authorization.nonce,
+                    y_parity: authorization.y_parity.into(),
+                    r: authorization.r.to_proto_bytes(),
+                    s: authorization.s.to_proto_bytes(),
+                });
         let op = UserOperationV07 {
             sender: op.sender.to_proto_bytes(),
             nonce: op.nonce.to_proto_bytes(),
@@ -148,7 +164,7 @@ impl From<&v0_7::UserOperation> for UserOperation {
             factory_data: op.factory_data.to_proto_bytes(),
             entry_point: op.entry_point.to_proto_bytes(),
             chain_id: op.chain_id,
-            authorization_tuple: None,
+            authorization_tuple,
         };
         UserOperation {
             uo: Some(user_operation::Uo::V07(op)),
@@ -198,6 +214,10 @@ impl TryUoFromProto<UserOperationV07> for v0_7::UserOperation {
             builder = builder.factory(from_bytes(&op.factory)?, op.factory_data.into());
         }
 
+        if from_bytes::<Address>(&op.entry_point)? == chain_spec.entry_point_address_v0_8 {
+            builder = builder.entry_point_version(EntryPointVersion::V0_8);
+        }
+
         Ok(builder.build())
     }
 }
@@ -414,6 +434,13 @@ impl From<&PoolOperation> for MempoolOp {
             sim_block_hash: op.sim_block_hash.to_proto_bytes(),
             account_is_staked: op.account_is_staked,
             da_gas_data: Some(DaGasUoData::from(&op.da_gas_data)),
+            atomic_group: op
+                .atomic_group
+                .iter()
+                .flatten()
+                .map(|hash| hash.to_proto_bytes())
+                .collect(),
+            mempools: op.mempools.iter().map(|id| id.to_proto_bytes()).collect(),
         }
     }
 }
@@ -434,6 +461,21 @@ impl From<&RundlerDAGasUOData> for DaGasUoData {
                     uo_units: data.uo_units,
                 })),
             },
+            RundlerDAGasUOData::Scroll(data) => DaGasUoData {
+                data: Some(da_gas_uo_data::Data::Scroll(ScrollDaGasUoData {
+                    uo_units: data.uo_units,
+                })),
+            },
+            RundlerDAGasUOData::Mantle(data) => DaGasUoData {
+                data: Some(da_gas_uo_data::Data::Mantle(MantleDaGasUoData {
+                    uo_units: data.uo_units,
+                })),
+            },
+            RundlerDAGasUOData::ZkStack(data) => DaGasUoData {
+                data: Some(da_gas_uo_data::Data::ZkStack(ZkStackDaGasUoData {
+                    uo_units: data.uo_units,
+                })),
+            },
         }
     }
 }
@@ -452,6 +494,15 @@ impl TryFrom<DaGasUoData> for RundlerDAGasUOData {
             Some(da_gas_uo_data::Data::Bedrock(BedrockDaGasUoData { uo_units })) => {
                 RundlerDAGasUOData::Bedrock(RundlerBedrockDAGasUOData { uo_units })
             }
+            Some(da_gas_uo_data::Data::Scroll(ScrollDaGasUoData { uo_units })) => {
+                RundlerDAGasUOData::Scroll(RundlerScrollDAGasUOData { uo_units })
+            }
+            Some(da_gas_uo_data::Data::Mantle(MantleDaGasUoData { uo_units })) => {
+                RundlerDAGasUOData::Mantle(RundlerMantleDAGasUOData { uo_units })
+            }
+            Some(da_gas_uo_data::Data::ZkStack(ZkStackDaGasUoData { uo_units })) => {
+                RundlerDAGasUOData::ZkStack(RundlerZkStackDAGasUOData { uo_units })
+            }
             None => RundlerDAGasUOData::Empty,
         };
 
@@ -479,6 +530,16 @@ impl TryUoFromProto<MempoolOp> for PoolOperation {
 
         let expected_code_hash = B256::from_slice(&op.expected_code_hash);
         let sim_block_hash = B256::from_slice(&op.sim_block_hash);
+        let atomic_group = if op.atomic_group.is_empty() {
+            None
+        } else {
+            Some(
+                op.atomic_group
+                    .iter()
+                    .map(|hash| from_bytes(hash))
+                    .collect::<Result<_, _>>()?,
+            )
+        };
 
         Ok(PoolOperation {
             uo,
@@ -494,6 +555,51 @@ impl TryUoFromProto<MempoolOp> for PoolOperation {
                 .da_gas_data
                 .context("DA gas data should be set")?
                 .try_into()?,
+            atomic_group,
+            mempools: op
+                .mempools
+                .iter()
+                .map(|id| from_bytes(id))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<&PoolOperation> for StoredOp {
+    fn from(op: &PoolOperation) -> Self {
+        StoredOp {
+            op: Some(MempoolOp::from(op)),
+            sim_block_number: op.sim_block_number,
+            entity_infos: op
+                .entity_infos
+                .entities()
+                .map(|(_, info)| StoredEntityInfo {
+                    entity: Some(Entity::from(&info.entity)),
+                    is_staked: info.is_staked,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryUoFromProto<StoredOp> for PoolOperation {
+    fn try_uo_from_proto(op: StoredOp, chain_spec: &ChainSpec) -> Result<Self, ConversionError> {
+        let mut entity_infos = EntityInfos::default();
+        for info in op.entity_infos {
+            let entity = RundlerEntity::try_from(&info.entity.context("entity should be set")?)?;
+            let info = EntityInfo::new(entity, info.is_staked);
+            match entity.kind {
+                RundlerEntityType::Account => entity_infos.sender = info,
+                RundlerEntityType::Factory => entity_infos.factory = Some(info),
+                RundlerEntityType::Paymaster => entity_infos.paymaster = Some(info),
+                RundlerEntityType::Aggregator => entity_infos.aggregator = Some(info),
+            }
+        }
+
+        Ok(PoolOperation {
+            sim_block_number: op.sim_block_number,
+            entity_infos,
+            ..PoolOperation::try_uo_from_proto(op.op.context(MISSING_USER_OP_ERR_STR)?, chain_spec)?
         })
     }
 }
@@ -518,6 +624,116 @@ impl From<PoolNewHead> for NewHead {
     }
 }
 
+impl TryFrom<MinedOpLocation> for PoolMinedOpLocation {
+    type Error = ConversionError;
+
+    fn try_from(location: MinedOpLocation) -> Result<Self, Self::Error> {
+        Ok(Self {
+            hash: from_bytes(&location.hash)?,
+            entry_point: from_bytes(&location.entry_point)?,
+            transaction_hash: from_bytes(&location.transaction_hash)?,
+            block_number: location.block_number,
+            block_hash: from_bytes(&location.block_hash)?,
+        })
+    }
+}
+
+impl From<PoolMinedOpLocation> for MinedOpLocation {
+    fn from(location: PoolMinedOpLocation) -> Self {
+        Self {
+            hash: location.hash.to_proto_bytes(),
+            entry_point: location.entry_point.to_proto_bytes(),
+            transaction_hash: location.transaction_hash.to_proto_bytes(),
+            block_number: location.block_number,
+            block_hash: location.block_hash.to_proto_bytes(),
+        }
+    }
+}
+
+impl From<PoolOpDropReason> for OpDropReason {
+    fn from(reason: PoolOpDropReason) -> Self {
+        match reason {
+            PoolOpDropReason::Requested => OpDropReason::Requested,
+            PoolOpDropReason::Replaced => OpDropReason::Replaced,
+            PoolOpDropReason::Expired => OpDropReason::Expired,
+            PoolOpDropReason::Entity => OpDropReason::Entity,
+            PoolOpDropReason::PoolSizeExceeded => OpDropReason::PoolSizeExceeded,
+            PoolOpDropReason::AtomicGroup => OpDropReason::AtomicGroup,
+        }
+    }
+}
+
+impl TryFrom<OpDropReason> for PoolOpDropReason {
+    type Error = ConversionError;
+
+    fn try_from(reason: OpDropReason) -> Result<Self, Self::Error> {
+        match reason {
+            OpDropReason::Requested => Ok(PoolOpDropReason::Requested),
+            OpDropReason::Replaced => Ok(PoolOpDropReason::Replaced),
+            OpDropReason::Expired => Ok(PoolOpDropReason::Expired),
+            OpDropReason::Entity => Ok(PoolOpDropReason::Entity),
+            OpDropReason::PoolSizeExceeded => Ok(PoolOpDropReason::PoolSizeExceeded),
+            OpDropReason::AtomicGroup => Ok(PoolOpDropReason::AtomicGroup),
+            OpDropReason::Unspecified => Err(ConversionError::InvalidEnumValue(reason as i32)),
+        }
+    }
+}
+
+impl From<PoolOpStatusUpdate> for OpStatusUpdate {
+    fn from(update: PoolOpStatusUpdate) -> Self {
+        let status = match update.status {
+            PoolOpStatus::Pending => op_status_update::Status::Pending(OpStatusPending {}),
+            PoolOpStatus::Dropped(reason) => op_status_update::Status::Dropped(OpStatusDropped {
+                reason: OpDropReason::from(reason) as i32,
+            }),
+            PoolOpStatus::Mined {
+                block_number,
+                block_hash,
+            } => op_status_update::Status::Mined(OpStatusMined {
+                block_number,
+                block_hash: block_hash.to_proto_bytes(),
+            }),
+            PoolOpStatus::Unmined => op_status_update::Status::Unmined(OpStatusUnmined {}),
+        };
+
+        Self {
+            hash: update.hash.to_proto_bytes(),
+            entry_point: update.entry_point.to_proto_bytes(),
+            sender: update.sender.to_proto_bytes(),
+            nonce: update.nonce.to_proto_bytes(),
+            status: Some(status),
+        }
+    }
+}
+
+impl TryFrom<OpStatusUpdate> for PoolOpStatusUpdate {
+    type Error = ConversionError;
+
+    fn try_from(update: OpStatusUpdate) -> Result<Self, Self::Error> {
+        let status = match update.status.context("status should be set")? {
+            op_status_update::Status::Pending(_) => PoolOpStatus::Pending,
+            op_status_update::Status::Dropped(dropped) => {
+                let reason = OpDropReason::try_from(dropped.reason)
+                    .map_err(|_| ConversionError::InvalidEnumValue(dropped.reason))?;
+                PoolOpStatus::Dropped(reason.try_into()?)
+            }
+            op_status_update::Status::Mined(mined) => PoolOpStatus::Mined {
+                block_number: mined.block_number,
+                block_hash: from_bytes(&mined.block_hash)?,
+            },
+            op_status_update::Status::Unmined(_) => PoolOpStatus::Unmined,
+        };
+
+        Ok(Self {
+            hash: from_bytes(&update.hash)?,
+            entry_point: from_bytes(&update.entry_point)?,
+            sender: from_bytes(&update.sender)?,
+            nonce: from_bytes(&update.nonce)?,
+            status,
+        })
+    }
+}
+
 impl TryFrom<PaymasterBalance> for PoolPaymasterMetadata {
     type Error = ConversionError;
 
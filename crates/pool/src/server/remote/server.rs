@@ -36,30 +36,35 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use tonic::{transport::Server, Request, Response, Result, Status};
 
 use super::protos::{
-    add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_paymaster_balances_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
+    add_op_group_response, add_op_response, admin_set_tracking_response,
+    debug_clear_state_response, debug_dump_mempool_response,
+    debug_dump_paymaster_balances_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_mined_op_location_response, get_op_by_hash_response,
     get_ops_response, get_reputation_status_response, get_stake_status_response,
     op_pool_server::{OpPool, OpPoolServer},
-    remove_op_by_id_response, remove_ops_response, update_entities_response, AddOpRequest,
-    AddOpResponse, AddOpSuccess, AdminSetTrackingRequest, AdminSetTrackingResponse,
-    AdminSetTrackingSuccess, DebugClearStateRequest, DebugClearStateResponse,
-    DebugClearStateSuccess, DebugDumpMempoolRequest, DebugDumpMempoolResponse,
-    DebugDumpMempoolSuccess, DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
+    remove_op_by_id_response, remove_ops_response, update_entities_response, AddOpGroupRequest,
+    AddOpGroupResponse, AddOpGroupSuccess, AddOpRequest, AddOpResponse, AddOpSuccess,
+    AdminSetTrackingRequest, AdminSetTrackingResponse, AdminSetTrackingSuccess,
+    DebugClearStateRequest, DebugClearStateResponse, DebugClearStateSuccess,
+    DebugDumpMempoolRequest, DebugDumpMempoolResponse, DebugDumpMempoolSuccess,
+    DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
     DebugDumpPaymasterBalancesSuccess, DebugDumpReputationRequest, DebugDumpReputationResponse,
     DebugDumpReputationSuccess, DebugSetReputationRequest, DebugSetReputationResponse,
-    DebugSetReputationSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
+    DebugSetReputationSuccess, GetMinedOpLocationRequest, GetMinedOpLocationResponse,
+    GetMinedOpLocationSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
     GetOpsRequest, GetOpsResponse, GetOpsSuccess, GetReputationStatusRequest,
     GetReputationStatusResponse, GetReputationStatusSuccess, GetStakeStatusRequest,
     GetStakeStatusResponse, GetStakeStatusSuccess, GetSupportedEntryPointsRequest,
     GetSupportedEntryPointsResponse, MempoolOp, RemoveOpByIdRequest, RemoveOpByIdResponse,
     RemoveOpByIdSuccess, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess, ReputationStatus,
-    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
-    UpdateEntitiesResponse, UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
+    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, SubscribeOpStatusRequest,
+    SubscribeOpStatusResponse, TryUoFromProto, UpdateEntitiesRequest, UpdateEntitiesResponse,
+    UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
 };
 use crate::server::local::LocalPoolHandle;
 
 const MAX_REMOTE_BLOCK_SUBSCRIPTIONS: usize = 32;
+const MAX_REMOTE_OP_STATUS_SUBSCRIPTIONS: usize = 32;
 
 pub(crate) async fn remote_mempool_server_task(
     task_spawner: Box<dyn TaskSpawner>,
@@ -102,6 +107,7 @@ struct OpPoolImpl {
     chain_spec: ChainSpec,
     local_pool: LocalPoolHandle,
     num_block_subscriptions: Arc<AtomicUsize>,
+    num_op_status_subscriptions: Arc<AtomicUsize>,
     task_spawner: Box<dyn TaskSpawner>,
 }
 
@@ -115,6 +121,7 @@ impl OpPoolImpl {
             chain_spec,
             local_pool,
             num_block_subscriptions: Arc::new(AtomicUsize::new(0)),
+            num_op_status_subscriptions: Arc::new(AtomicUsize::new(0)),
             task_spawner,
         }
     }
@@ -174,6 +181,36 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn add_op_group(
+        &self,
+        request: Request<AddOpGroupRequest>,
+    ) -> Result<Response<AddOpGroupResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let ops = req
+            .ops
+            .into_iter()
+            .map(|op| UserOperationVariant::try_uo_from_proto(op, &self.chain_spec))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
+            })?;
+
+        let resp = match self.local_pool.add_op_group(ep, ops).await {
+            Ok(hashes) => AddOpGroupResponse {
+                result: Some(add_op_group_response::Result::Success(AddOpGroupSuccess {
+                    hashes: hashes.iter().map(|hash| hash.to_vec()).collect(),
+                })),
+            },
+            Err(error) => AddOpGroupResponse {
+                result: Some(add_op_group_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn get_ops(&self, request: Request<GetOpsRequest>) -> Result<Response<GetOpsResponse>> {
         let req = request.into_inner();
         let ep = self.get_entry_point(&req.entry_point)?;
@@ -222,6 +259,34 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_mined_op_location(
+        &self,
+        request: Request<GetMinedOpLocationRequest>,
+    ) -> Result<Response<GetMinedOpLocationResponse>> {
+        let req = request.into_inner();
+
+        let hash = from_bytes(&req.hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in GetMinedOpLocationRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.get_mined_op_location(hash).await {
+            Ok(location) => GetMinedOpLocationResponse {
+                result: Some(get_mined_op_location_response::Result::Success(
+                    GetMinedOpLocationSuccess {
+                        location: location.map(Into::into),
+                    },
+                )),
+            },
+            Err(error) => GetMinedOpLocationResponse {
+                result: Some(get_mined_op_location_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn remove_ops(
         &self,
         request: Request<RemoveOpsRequest>,
@@ -579,4 +644,62 @@ impl OpPool for OpPoolImpl {
 
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
+
+    type SubscribeOpStatusStream = UnboundedReceiverStream<Result<SubscribeOpStatusResponse>>;
+
+    async fn subscribe_op_status(
+        &self,
+        _request: Request<SubscribeOpStatusRequest>,
+    ) -> Result<Response<Self::SubscribeOpStatusStream>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        if self
+            .num_op_status_subscriptions
+            .fetch_add(1, Ordering::Relaxed)
+            >= MAX_REMOTE_OP_STATUS_SUBSCRIPTIONS
+        {
+            self.num_op_status_subscriptions
+                .fetch_sub(1, Ordering::Relaxed);
+            return Err(Status::resource_exhausted(
+                "Too many op status subscriptions",
+            ));
+        }
+
+        let num_op_status_subscriptions = Arc::clone(&self.num_op_status_subscriptions);
+        let mut updates = match self.local_pool.subscribe_op_status().await {
+            Ok(updates) => updates,
+            Err(error) => {
+                self.num_op_status_subscriptions
+                    .fetch_sub(1, Ordering::Relaxed);
+                tracing::error!("Failed to subscribe to op status: {error}");
+                return Err(Status::internal(format!(
+                    "Failed to subscribe to op status: {error}"
+                )));
+            }
+        };
+
+        self.task_spawner.spawn(Box::pin(async move {
+            loop {
+                match updates.next().await {
+                    Some(update) => {
+                        if tx
+                            .send(Ok(SubscribeOpStatusResponse {
+                                update: Some(update.into()),
+                            }))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    None => {
+                        tracing::warn!("op status subscription closed");
+                        break;
+                    }
+                }
+            }
+            num_op_status_subscriptions.fetch_sub(1, Ordering::Relaxed);
+        }));
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
 }
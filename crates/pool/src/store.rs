@@ -0,0 +1,389 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+
+use alloy_primitives::Address;
+use anyhow::Context;
+use prost::Message;
+use redb::{
+    Database, Key, ReadOnlyTable, ReadTransaction, ReadableTable, TableDefinition, TableError,
+    Value,
+};
+use rundler_task::GracefulShutdown;
+use rundler_types::{
+    chain::ChainSpec,
+    pool::{PoolOperation, Reputation},
+};
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::{
+    mempool::{Mempool, MempoolSnapshot},
+    server::protos::{StoredMinedOp, StoredOp, TryUoFromProto},
+};
+
+/// On-disk store for the state of the mempools, used to persist it across restarts.
+///
+/// Each entry point has its own set of tables. A save rewrites all of them in a single
+/// transaction, so a crash during a save leaves the previous snapshot intact.
+pub(crate) struct PoolStore {
+    db: Database,
+    chain_spec: ChainSpec,
+}
+
+impl PoolStore {
+    /// Opens the store at `path`, creating it if it doesn't exist
+    pub(crate) fn open(path: &Path, chain_spec: ChainSpec) -> anyhow::Result<Self> {
+        let db = Database::create(path)
+            .with_context(|| format!("should open pool store at {}", path.display()))?;
+        Ok(Self { db, chain_spec })
+    }
+
+    #[cfg(test)]
+    fn in_memory(chain_spec: ChainSpec) -> Self {
+        let db = Database::builder()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .unwrap();
+        Self { db, chain_spec }
+    }
+
+    /// Replaces the stored snapshot for `entry_point`
+    pub(crate) fn save(
+        &self,
+        entry_point: Address,
+        snapshot: &MempoolSnapshot,
+    ) -> anyhow::Result<()> {
+        let names = TableNames::new(entry_point);
+        let txn = self.db.begin_write()?;
+        {
+            txn.delete_table(names.ops())?;
+            let mut ops = txn.open_table(names.ops())?;
+            // Keyed by position to keep the best first order of the pool
+            for (i, op) in snapshot.ops.iter().enumerate() {
+                let value = StoredOp::from(&**op).encode_to_vec();
+                ops.insert(i as u64, value.as_slice())?;
+            }
+
+            txn.delete_table(names.mined_ops())?;
+            let mut mined_ops = txn.open_table(names.mined_ops())?;
+            for (i, (op, block_number)) in snapshot.mined_ops.iter().enumerate() {
+                let value = StoredMinedOp {
+                    op: Some(StoredOp::from(&**op)),
+                    block_number: *block_number,
+                }
+                .encode_to_vec();
+                mined_ops.insert(i as u64, value.as_slice())?;
+            }
+
+            txn.delete_table(names.reputations())?;
+            let mut reputations = txn.open_table(names.reputations())?;
+            for rep in &snapshot.reputations {
+                reputations.insert(&rep.address.0 .0, (rep.ops_seen, rep.ops_included))?;
+            }
+
+            txn.delete_table(names.paymasters())?;
+            let mut paymasters = txn.open_table(names.paymasters())?;
+            for paymaster in &snapshot.paymasters {
+                paymasters.insert(&paymaster.0 .0, ())?;
+            }
+        }
+        txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Loads the stored snapshot for `entry_point`, empty if none was saved.
+    ///
+    /// Operations that fail to decode are skipped.
+    pub(crate) fn load(&self, entry_point: Address) -> anyhow::Result<MempoolSnapshot> {
+        let names = TableNames::new(entry_point);
+        let txn = self.db.begin_read()?;
+        let mut snapshot = MempoolSnapshot::default();
+
+        if let Some(ops) = open_table(&txn, names.ops())? {
+            for entry in ops.iter()? {
+                let (_, value) = entry?;
+                match self.decode_op(value.value()) {
+                    Ok(op) => snapshot.ops.push(Arc::new(op)),
+                    Err(e) => warn!("Skipping invalid stored op: {e:?}"),
+                }
+            }
+        }
+
+        if let Some(mined_ops) = open_table(&txn, names.mined_ops())? {
+            for entry in mined_ops.iter()? {
+                let (_, value) = entry?;
+                let decoded = StoredMinedOp::decode(value.value())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|mined| {
+                        let op = mined.op.context("stored mined op should contain op")?;
+                        Ok((self.decode_stored_op(op)?, mined.block_number))
+                    });
+                match decoded {
+                    Ok((op, block_number)) => snapshot.mined_ops.push((Arc::new(op), block_number)),
+                    Err(e) => warn!("Skipping invalid stored mined op: {e:?}"),
+                }
+            }
+        }
+
+        if let Some(reputations) = open_table(&txn, names.reputations())? {
+            for entry in reputations.iter()? {
+                let (address, counts) = entry?;
+                let (ops_seen, ops_included) = counts.value();
+                snapshot.reputations.push(Reputation {
+                    address: Address::from(*address.value()),
+                    ops_seen,
+                    ops_included,
+                });
+            }
+        }
+
+        if let Some(paymasters) = open_table(&txn, names.paymasters())? {
+            for entry in paymasters.iter()? {
+                let (address, _) = entry?;
+                snapshot.paymasters.push(Address::from(*address.value()));
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    fn decode_op(&self, bytes: &[u8]) -> anyhow::Result<PoolOperation> {
+        self.decode_stored_op(StoredOp::decode(bytes)?)
+    }
+
+    fn decode_stored_op(&self, op: StoredOp) -> anyhow::Result<PoolOperation> {
+        Ok(PoolOperation::try_uo_from_proto(op, &self.chain_spec)?)
+    }
+}
+
+/// Restores the state of each mempool from the store
+pub(crate) async fn restore(
+    store: &PoolStore,
+    mempools: &HashMap<Address, Arc<dyn Mempool>>,
+) -> anyhow::Result<()> {
+    for (entry_point, mempool) in mempools {
+        let snapshot = store
+            .load(*entry_point)
+            .with_context(|| format!("should load snapshot for entry point {entry_point:?}"))?;
+        mempool.restore(snapshot).await;
+    }
+    Ok(())
+}
+
+/// Saves the state of each mempool to the store every `interval`, and a final time on shutdown
+pub(crate) async fn run(
+    store: PoolStore,
+    mempools: HashMap<Address, Arc<dyn Mempool>>,
+    interval: Duration,
+    shutdown: GracefulShutdown,
+) {
+    let store = Arc::new(store);
+    let mut tick = time::interval(interval);
+    // the first tick completes immediately, nothing has changed since the restore
+    tick.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                save_all(&store, &mempools).await;
+            }
+            guard = shutdown.clone() => {
+                // hold the guard so that shutdown waits for the final save
+                info!("Saving pool state before shutdown");
+                save_all(&store, &mempools).await;
+                drop(guard);
+                break;
+            }
+        }
+    }
+}
+
+async fn save_all(store: &Arc<PoolStore>, mempools: &HashMap<Address, Arc<dyn Mempool>>) {
+    for (&entry_point, mempool) in mempools {
+        let snapshot = mempool.snapshot();
+        let store = Arc::clone(store);
+        let res = tokio::task::spawn_blocking(move || store.save(entry_point, &snapshot)).await;
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to save pool state for {entry_point:?}: {e:?}"),
+            Err(e) => error!("Pool state save task for {entry_point:?} panicked: {e:?}"),
+        }
+    }
+}
+
+fn open_table<K: Key + 'static, V: Value + 'static>(
+    txn: &ReadTransaction,
+    definition: TableDefinition<'_, K, V>,
+) -> anyhow::Result<Option<ReadOnlyTable<K, V>>> {
+    match txn.open_table(definition) {
+        Ok(table) => Ok(Some(table)),
+        Err(TableError::TableDoesNotExist(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+struct TableNames {
+    ops: String,
+    mined_ops: String,
+    reputations: String,
+    paymasters: String,
+}
+
+impl TableNames {
+    fn new(entry_point: Address) -> Self {
+        Self {
+            ops: format!("{entry_point:?}/ops"),
+            mined_ops: format!("{entry_point:?}/mined_ops"),
+            reputations: format!("{entry_point:?}/reputations"),
+            paymasters: format!("{entry_point:?}/paymasters"),
+        }
+    }
+
+    fn ops(&self) -> TableDefinition<'_, u64, &'static [u8]> {
+        TableDefinition::new(&self.ops)
+    }
+
+    fn mined_ops(&self) -> TableDefinition<'_, u64, &'static [u8]> {
+        TableDefinition::new(&self.mined_ops)
+    }
+
+    fn reputations(&self) -> TableDefinition<'_, &'static [u8; 20], (u64, u64)> {
+        TableDefinition::new(&self.reputations)
+    }
+
+    fn paymasters(&self) -> TableDefinition<'_, &'static [u8; 20], ()> {
+        TableDefinition::new(&self.paymasters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Bytes, B256, U256};
+    use rundler_types::{
+        v0_6, Entity, EntityInfo, EntityInfos, UserOperation as _, ValidTimeRange,
+    };
+
+    use super::*;
+
+    fn create_op(sender: Address, entry_point: Address) -> PoolOperation {
+        let paymaster = Address::random();
+        PoolOperation {
+            uo: v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender,
+                    nonce: U256::from(1),
+                    init_code: Bytes::default(),
+                    call_data: Bytes::default(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 50_000,
+                    max_fee_per_gas: 2_000,
+                    max_priority_fee_per_gas: 1_000,
+                    paymaster_and_data: paymaster.to_vec().into(),
+                    signature: Bytes::default(),
+                },
+                v0_6::ExtendedUserOperation {
+                    authorization_tuple: None,
+                },
+            )
+            .build()
+            .into(),
+            entry_point,
+            aggregator: None,
+            valid_time_range: ValidTimeRange::new(1.into(), 100.into()),
+            expected_code_hash: B256::random(),
+            sim_block_hash: B256::random(),
+            sim_block_number: 10,
+            account_is_staked: false,
+            entity_infos: EntityInfos {
+                sender: EntityInfo::new(Entity::account(sender), false),
+                paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), true)),
+                ..Default::default()
+            },
+            da_gas_data: Default::default(),
+            atomic_group: None,
+            mempools: vec![],
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let store = PoolStore::in_memory(ChainSpec::default());
+        let entry_point = Address::random();
+        let op = create_op(Address::random(), entry_point);
+        let mined_op = create_op(Address::random(), entry_point);
+        let reputation = Reputation {
+            address: Address::random(),
+            ops_seen: 5,
+            ops_included: 2,
+        };
+        let paymaster = Address::random();
+
+        let snapshot = MempoolSnapshot {
+            ops: vec![Arc::new(op.clone())],
+            mined_ops: vec![(Arc::new(mined_op.clone()), 12)],
+            reputations: vec![reputation.clone()],
+            paymasters: vec![paymaster],
+        };
+        store.save(entry_point, &snapshot).unwrap();
+
+        let loaded = store.load(entry_point).unwrap();
+        assert_eq!(loaded.ops.len(), 1);
+        assert_eq!(*loaded.ops[0], op);
+        assert_eq!(
+            loaded.ops[0].uo.hash(entry_point, 0),
+            op.uo.hash(entry_point, 0)
+        );
+        assert_eq!(loaded.mined_ops.len(), 1);
+        assert_eq!(*loaded.mined_ops[0].0, mined_op);
+        assert_eq!(loaded.mined_ops[0].1, 12);
+        assert_eq!(loaded.reputations.len(), 1);
+        assert_eq!(loaded.reputations[0].address, reputation.address);
+        assert_eq!(loaded.reputations[0].ops_seen, 5);
+        assert_eq!(loaded.reputations[0].ops_included, 2);
+        assert_eq!(loaded.paymasters, vec![paymaster]);
+    }
+
+    #[test]
+    fn test_save_replaces_previous() {
+        let store = PoolStore::in_memory(ChainSpec::default());
+        let entry_point = Address::random();
+        let first = create_op(Address::random(), entry_point);
+        let second = create_op(Address::random(), entry_point);
+
+        for op in [&first, &second] {
+            let snapshot = MempoolSnapshot {
+                ops: vec![Arc::new(op.clone())],
+                ..Default::default()
+            };
+            store.save(entry_point, &snapshot).unwrap();
+        }
+
+        let loaded = store.load(entry_point).unwrap();
+        assert_eq!(loaded.ops.len(), 1);
+        assert_eq!(*loaded.ops[0], second);
+    }
+
+    #[test]
+    fn test_load_unknown_entry_point() {
+        let store = PoolStore::in_memory(ChainSpec::default());
+        let loaded = store.load(Address::random()).unwrap();
+        assert!(loaded.ops.is_empty());
+        assert!(loaded.mined_ops.is_empty());
+        assert!(loaded.reputations.is_empty());
+        assert!(loaded.paymasters.is_empty());
+    }
+}
@@ -11,10 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::{bail, Context};
 use futures::FutureExt;
+use rundler_p2p::{MempoolTopic, P2pConfig};
 use rundler_provider::{Providers, ProvidersWithEntryPointT};
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl},
@@ -34,7 +35,10 @@ use crate::{
         AddressReputation, Mempool, PaymasterConfig, PaymasterTracker, ReputationParams, UoPool,
         UoPoolProviders,
     },
+    p2p::{self, P2pPool},
+    receipt_index::{self, ReceiptIndex},
     server::{self, LocalPoolBuilder},
+    store::{self, PoolStore},
 };
 
 /// Arguments for the pool task.
@@ -48,6 +52,9 @@ pub struct Args {
     pub http_url: String,
     /// Interval to poll the chain for updates.
     pub chain_poll_interval: Duration,
+    /// Websocket URL of the full node to subscribe to new heads on, if any.
+    /// If not provided, the chain is polled for new blocks.
+    pub chain_ws_url: Option<String>,
     /// Number of times to retry a block sync at the `chain_poll_interval` before abandoning
     pub chain_max_sync_retries: u64,
     /// Pool configurations.
@@ -57,6 +64,17 @@ pub struct Args {
     pub remote_address: Option<SocketAddr>,
     /// Channel capacity for the chain update channel.
     pub chain_update_channel_capacity: usize,
+    /// Configuration for the p2p network, if any.
+    /// If not provided, operations are not shared with other bundlers.
+    pub p2p_config: Option<P2pConfig>,
+    /// Path to the database used to persist the pool's state across restarts, if any.
+    /// If not provided, the pool starts empty on every restart.
+    pub store_path: Option<PathBuf>,
+    /// Interval at which the pool's state is saved to the store.
+    pub store_snapshot_interval: Duration,
+    /// Path to the database indexing where user operations were mined, if any.
+    /// If not provided, receipt lookups fall back to scanning logs.
+    pub receipt_index_path: Option<PathBuf>,
 }
 
 /// Mempool task.
@@ -98,6 +116,7 @@ where
         let chain_settings = chain::Settings {
             history_size: self.args.chain_spec.chain_history_size,
             poll_interval: self.args.chain_poll_interval,
+            new_heads_ws_url: self.args.chain_ws_url.clone(),
             max_sync_retries: self.args.chain_max_sync_retries,
             entry_point_addresses: self
                 .args
@@ -144,23 +163,109 @@ where
 
                     mempools.insert(pool_config.entry_point, pool);
                 }
+                EntryPointVersion::V0_8 => {
+                    let pool = self
+                        .create_mempool_v0_8(
+                            &task_spawner,
+                            self.args.chain_spec.clone(),
+                            pool_config,
+                            self.args.unsafe_mode,
+                            self.event_sender.clone(),
+                        )
+                        .context("should have created mempool")?;
+
+                    mempools.insert(pool_config.entry_point, pool);
+                }
                 EntryPointVersion::Unspecified => {
                     bail!("Unsupported entry point version");
                 }
             }
         }
 
+        if let Some(path) = &self.args.store_path {
+            let store = PoolStore::open(path, self.args.chain_spec.clone())?;
+            // Restored operations are re-validated before the pool starts serving requests
+            store::restore(&store, &mempools)
+                .await
+                .context("should have restored pool state")?;
+
+            let store_mempools = mempools.clone();
+            let interval = self.args.store_snapshot_interval;
+            task_spawner.spawn_critical_with_graceful_shutdown_signal("pool store", |shutdown| {
+                store::run(store, store_mempools, interval, shutdown)
+            });
+        }
+
+        let receipt_index = match &self.args.receipt_index_path {
+            Some(path) => {
+                let index = Arc::new(ReceiptIndex::open(path)?);
+                let index_updates = update_sender.subscribe();
+                let run_index = Arc::clone(&index);
+                task_spawner
+                    .spawn_critical_with_graceful_shutdown_signal("receipt index", |shutdown| {
+                        receipt_index::run(run_index, index_updates, shutdown)
+                    });
+                Some(index)
+            }
+            None => None,
+        };
+
         let pool_handle = self.pool_builder.get_handle();
 
         let ts_box = Box::new(task_spawner.clone());
         task_spawner.spawn_critical_with_graceful_shutdown_signal(
             "local pool server",
             |shutdown| {
-                self.pool_builder
-                    .run(ts_box, mempools, update_sender.subscribe(), shutdown)
+                self.pool_builder.run(
+                    ts_box,
+                    mempools,
+                    update_sender.subscribe(),
+                    self.event_sender.subscribe(),
+                    receipt_index,
+                    shutdown,
+                )
             },
         );
 
+        if let Some(p2p_config) = self.args.p2p_config.clone() {
+            // Each mempool channel maps to its own gossip topic
+            let topics = self
+                .args
+                .pool_configs
+                .iter()
+                .flat_map(|config| {
+                    config
+                        .mempool_channel_configs
+                        .keys()
+                        .map(|id| MempoolTopic {
+                            mempool_id: *id,
+                            entry_point: config.entry_point,
+                            entry_point_version: config.entry_point_version,
+                        })
+                })
+                .collect();
+
+            let p2p_handle = rundler_p2p::spawn(
+                &task_spawner,
+                p2p_config,
+                self.args.chain_spec.clone(),
+                topics,
+                Arc::new(P2pPool::new(pool_handle.clone(), chain_id)),
+            )
+            .await
+            .context("should have started p2p network")?;
+
+            task_spawner.spawn_critical(
+                "p2p publisher",
+                p2p::publish_local_ops(
+                    p2p_handle,
+                    pool_handle.clone(),
+                    self.event_sender.subscribe(),
+                )
+                .boxed(),
+            );
+        }
+
         if let Some(addr) = self.args.remote_address {
             let ts_box = Box::new(task_spawner.clone());
             task_spawner.spawn_critical_with_graceful_shutdown_signal(
@@ -252,6 +357,50 @@ where
             let simulator = simulation::new_v0_7_simulator(
                 self.providers.evm().clone(),
                 ep_providers.entry_point().clone(),
+                &chain_spec,
+                pool_config.sim_settings.clone(),
+                pool_config.mempool_channel_configs.clone(),
+            );
+            self.create_mempool(
+                task_spawner,
+                chain_spec,
+                pool_config,
+                event_sender,
+                ep_providers,
+                simulator,
+            )
+        }
+    }
+
+    fn create_mempool_v0_8<T: TaskSpawnerExt>(
+        &self,
+        task_spawner: &T,
+        chain_spec: ChainSpec,
+        pool_config: &PoolConfig,
+        unsafe_mode: bool,
+        event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
+    ) -> anyhow::Result<Arc<dyn Mempool + 'static>> {
+        let ep_providers = self
+            .providers
+            .ep_v0_8_providers()
+            .clone()
+            .context("entry point v0.8 not supplied")?;
+
+        if unsafe_mode {
+            let simulator = UnsafeSimulator::new(ep_providers.entry_point().clone());
+            self.create_mempool(
+                task_spawner,
+                chain_spec,
+                pool_config,
+                event_sender,
+                ep_providers,
+                simulator,
+            )
+        } else {
+            let simulator = simulation::new_v0_7_simulator(
+                self.providers.evm().clone(),
+                ep_providers.entry_point().clone(),
+                &chain_spec,
                 pool_config.sim_settings.clone(),
                 pool_config.mempool_channel_configs.clone(),
             );
@@ -0,0 +1,290 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_rpc_types_eth::state::{AccountOverride, StateOverride};
+use alloy_sol_types::sol;
+use alloy_transport::Transport;
+use anyhow::Context;
+use rundler_types::da::{DAGasBlockData, DAGasUOData, MantleDAGasBlockData, MantleDAGasUOData};
+use rundler_utils::cache::LruMap;
+use tokio::sync::Mutex as TokioMutex;
+use GasPriceOracle::{
+    decimalsCall, l1BaseFeeCall, overheadCall, scalarCall, tokenRatioCall, GasPriceOracleCalls,
+};
+
+use super::multicall::{self, Multicall::MulticallInstance, MULTICALL_BYTECODE};
+use crate::{BlockHashOrNumber, DAGasOracle, DAGasOracleSync, ProviderResult};
+
+// From https://github.com/mantlenetworkio/mantle-v2/blob/develop/packages/contracts-bedrock/contracts/L2/GasPriceOracle.sol
+sol! {
+    #[sol(rpc)]
+    interface GasPriceOracle {
+        function l1BaseFee() public view returns (uint256);
+        function overhead() public view returns (uint256);
+        function scalar() public view returns (uint256);
+        function decimals() public view returns (uint256);
+        function tokenRatio() public view returns (uint256);
+    }
+}
+
+/// Calldata gas of the signature and RLP fields of the unsigned transaction, added to every
+/// transaction by the oracle.
+const TX_SIGNATURE_PADDING_GAS: u64 = 68 * 16;
+
+/// Local Mantle DA gas oracle
+///
+/// Mantle prices L1 data with the pre-Ecotone Bedrock formula, converted from ETH to MNT by
+/// the oracle's token ratio:
+/// `(calldataGas(data) + overhead) * l1BaseFee * scalar / 10^decimals * tokenRatio`
+///
+/// Details: https://docs.mantle.xyz/network/system-information/fee-mechanism
+#[derive(Debug)]
+pub(crate) struct LocalMantleDAGasOracle<AP, T> {
+    oracle_address: Address,
+    multicaller: MulticallInstance<T, AP>,
+    block_data_cache: TokioMutex<LruMap<BlockHashOrNumber, MantleDAGasBlockData>>,
+}
+
+impl<AP, T> LocalMantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(oracle_address: Address, provider: AP) -> Self {
+        Self {
+            oracle_address,
+            multicaller: MulticallInstance::new(Address::random(), provider),
+            block_data_cache: TokioMutex::new(LruMap::new(100)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for LocalMantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let block_data = self.block_data(block).await?;
+        let uo_data = self.uo_data(data, to, block).await?;
+        let da_gas = self.calc_da_gas_sync(&uo_data, &block_data, gas_price);
+        Ok((da_gas, uo_data, block_data))
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracleSync for LocalMantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn block_data(&self, block: BlockHashOrNumber) -> ProviderResult<DAGasBlockData> {
+        let mut cache = self.block_data_cache.lock().await;
+        match cache.get(&block) {
+            Some(block_data) => Ok(DAGasBlockData::Mantle(block_data.clone())),
+            None => {
+                let block_data = self.get_block_data(block).await?;
+                cache.insert(block, block_data.clone());
+                Ok(DAGasBlockData::Mantle(block_data))
+            }
+        }
+    }
+
+    async fn uo_data(
+        &self,
+        uo_data: Bytes,
+        _to: Address,
+        _block: BlockHashOrNumber,
+    ) -> ProviderResult<DAGasUOData> {
+        Ok(DAGasUOData::Mantle(MantleDAGasUOData {
+            uo_units: calldata_gas(&uo_data),
+        }))
+    }
+
+    fn calc_da_gas_sync(
+        &self,
+        uo_data: &DAGasUOData,
+        block_data: &DAGasBlockData,
+        gas_price: u128,
+    ) -> u128 {
+        let block_da_data = match block_data {
+            DAGasBlockData::Mantle(block_da_data) => block_da_data,
+            _ => panic!("LocalMantleDAGasOracle only supports Mantle block data"),
+        };
+        let uo_data = match uo_data {
+            DAGasUOData::Mantle(uo_data) => uo_data,
+            _ => panic!("LocalMantleDAGasOracle only supports Mantle user operation data"),
+        };
+
+        calc_l1_fee(block_da_data, uo_data)
+            .checked_div(gas_price)
+            .unwrap_or(u128::MAX)
+    }
+}
+
+impl<AP, T> LocalMantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn get_block_data(
+        &self,
+        block: BlockHashOrNumber,
+    ) -> ProviderResult<MantleDAGasBlockData> {
+        let calls = vec![
+            multicall::create_call(
+                self.oracle_address,
+                GasPriceOracleCalls::l1BaseFee(l1BaseFeeCall {}),
+            ),
+            multicall::create_call(
+                self.oracle_address,
+                GasPriceOracleCalls::overhead(overheadCall {}),
+            ),
+            multicall::create_call(
+                self.oracle_address,
+                GasPriceOracleCalls::scalar(scalarCall {}),
+            ),
+            multicall::create_call(
+                self.oracle_address,
+                GasPriceOracleCalls::decimals(decimalsCall {}),
+            ),
+            multicall::create_call(
+                self.oracle_address,
+                GasPriceOracleCalls::tokenRatio(tokenRatioCall {}),
+            ),
+        ];
+
+        let mut overrides = StateOverride::default();
+        let account = AccountOverride {
+            code: Some(MULTICALL_BYTECODE.clone()),
+            ..Default::default()
+        };
+        overrides.insert(*self.multicaller.address(), account);
+
+        let result = self
+            .multicaller
+            .aggregate3(calls)
+            .call()
+            .overrides(&overrides)
+            .block(block.into())
+            .await?;
+
+        if result.returnData.len() != 5 {
+            Err(anyhow::anyhow!(
+                "multicall returned unexpected number of results"
+            ))?;
+        } else if result.returnData.iter().any(|r| !r.success) {
+            Err(anyhow::anyhow!("multicall returned some failed results"))?;
+        }
+
+        let l1_base_fee =
+            multicall::decode_result::<l1BaseFeeCall>(&result.returnData[0].returnData)?
+                ._0
+                .try_into()
+                .context("l1_base_fee too large for u128")?;
+        let overhead = multicall::decode_result::<overheadCall>(&result.returnData[1].returnData)?
+            ._0
+            .try_into()
+            .context("overhead too large for u64")?;
+        let scalar = multicall::decode_result::<scalarCall>(&result.returnData[2].returnData)?
+            ._0
+            .try_into()
+            .context("scalar too large for u64")?;
+        let decimals = multicall::decode_result::<decimalsCall>(&result.returnData[3].returnData)?
+            ._0
+            .try_into()
+            .context("decimals too large for u32")?;
+        let token_ratio =
+            multicall::decode_result::<tokenRatioCall>(&result.returnData[4].returnData)?
+                ._0
+                .try_into()
+                .context("token_ratio too large for u128")?;
+
+        Ok(MantleDAGasBlockData {
+            l1_base_fee,
+            overhead,
+            scalar,
+            decimals,
+            token_ratio,
+        })
+    }
+}
+
+fn calldata_gas(data: &[u8]) -> u64 {
+    let zero_bytes = data.iter().filter(|b| **b == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+    zero_bytes * 4 + non_zero_bytes * 16 + TX_SIGNATURE_PADDING_GAS
+}
+
+fn calc_l1_fee(block_data: &MantleDAGasBlockData, uo_data: &MantleDAGasUOData) -> u128 {
+    let l1_gas = (uo_data.uo_units + block_data.overhead) as u128;
+    let l1_fee_eth = l1_gas
+        .saturating_mul(block_data.l1_base_fee)
+        .saturating_mul(block_data.scalar as u128)
+        / 10u128.saturating_pow(block_data.decimals);
+    l1_fee_eth.saturating_mul(block_data.token_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_calldata_gas() {
+        assert_eq!(calldata_gas(&[]), 1088);
+        assert_eq!(calldata_gas(&bytes!("00000102")), 1088 + 4 * 2 + 16 * 2);
+    }
+
+    #[test]
+    fn test_calc_l1_fee() {
+        let block_data = MantleDAGasBlockData {
+            l1_base_fee: 10_000_000_000,
+            overhead: 188,
+            scalar: 1_000_000,
+            decimals: 6,
+            token_ratio: 4_000,
+        };
+        let uo_data = MantleDAGasUOData { uo_units: 10_000 };
+
+        // (10000 + 188) * 10 gwei * 1.0, in ETH, then converted to MNT
+        assert_eq!(
+            calc_l1_fee(&block_data, &uo_data),
+            10_188 * 10_000_000_000 * 4_000
+        );
+    }
+
+    #[test]
+    fn test_calc_l1_fee_scaled() {
+        let block_data = MantleDAGasBlockData {
+            l1_base_fee: 10_000_000_000,
+            overhead: 0,
+            scalar: 500_000,
+            decimals: 6,
+            token_ratio: 1,
+        };
+        let uo_data = MantleDAGasUOData { uo_units: 1_000 };
+
+        assert_eq!(calc_l1_fee(&block_data, &uo_data), 5_000_000_000_000);
+    }
+}
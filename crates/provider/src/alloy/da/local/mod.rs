@@ -14,7 +14,16 @@
 mod bedrock;
 pub(crate) use bedrock::LocalBedrockDAGasOracle;
 
+mod mantle;
+pub(crate) use mantle::LocalMantleDAGasOracle;
+
 mod nitro;
 pub(crate) use nitro::CachedNitroDAGasOracle;
 
+mod scroll;
+pub(crate) use scroll::LocalScrollDAGasOracle;
+
+mod zk_stack;
+pub(crate) use zk_stack::ZkStackDAGasOracle;
+
 mod multicall;
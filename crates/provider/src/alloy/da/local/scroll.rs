@@ -0,0 +1,272 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_rpc_types_eth::state::{AccountOverride, StateOverride};
+use alloy_transport::Transport;
+use anyhow::Context;
+use rundler_types::da::{DAGasBlockData, DAGasUOData, ScrollDAGasBlockData, ScrollDAGasUOData};
+use rundler_utils::cache::LruMap;
+use tokio::sync::Mutex as TokioMutex;
+use tracing::error;
+
+use super::multicall::{self, Multicall::MulticallInstance, MULTICALL_BYTECODE};
+use crate::{
+    alloy::da::scroll::L1GasPriceOracle::{
+        blobScalarCall, commitScalarCall, l1BaseFeeCall, l1BlobBaseFeeCall, L1GasPriceOracleCalls,
+        L1GasPriceOracleInstance,
+    },
+    BlockHashOrNumber, DAGasOracle, DAGasOracleSync, ProviderResult,
+};
+
+// From https://github.com/scroll-tech/scroll/blob/develop/contracts/src/L2/predeploys/L1GasPriceOracle.sol
+const PRECISION: u128 = 1_000_000_000;
+
+/// Local Scroll DA gas oracle
+///
+/// Implements the fee formula of the Curie upgrade:
+/// `(commitScalar * l1BaseFee + blobScalar * len(data) * l1BlobBaseFee) / PRECISION`
+///
+/// Details: https://docs.scroll.io/en/developers/transaction-fees-on-scroll/
+#[derive(Debug)]
+pub(crate) struct LocalScrollDAGasOracle<AP, T> {
+    oracle: L1GasPriceOracleInstance<T, AP>,
+    multicaller: MulticallInstance<T, AP>,
+    block_data_cache: TokioMutex<LruMap<BlockHashOrNumber, ScrollDAGasBlockData>>,
+}
+
+impl<AP, T> LocalScrollDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T> + Clone,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(oracle_address: Address, provider: AP) -> Self {
+        let oracle = L1GasPriceOracleInstance::new(oracle_address, provider.clone());
+        let multicaller = MulticallInstance::new(Address::random(), provider);
+        Self {
+            oracle,
+            multicaller,
+            block_data_cache: TokioMutex::new(LruMap::new(100)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for LocalScrollDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let block_data = self.block_data(block).await?;
+        let uo_data = self.uo_data(data, to, block).await?;
+        let da_gas = self.calc_da_gas_sync(&uo_data, &block_data, gas_price);
+        Ok((da_gas, uo_data, block_data))
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracleSync for LocalScrollDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn block_data(&self, block: BlockHashOrNumber) -> ProviderResult<DAGasBlockData> {
+        let mut cache = self.block_data_cache.lock().await;
+        match cache.get(&block) {
+            Some(block_data) => Ok(DAGasBlockData::Scroll(block_data.clone())),
+            None => {
+                let block_data = self.get_block_data(block).await?;
+                cache.insert(block, block_data.clone());
+                Ok(DAGasBlockData::Scroll(block_data))
+            }
+        }
+    }
+
+    async fn uo_data(
+        &self,
+        uo_data: Bytes,
+        _to: Address,
+        _block: BlockHashOrNumber,
+    ) -> ProviderResult<DAGasUOData> {
+        Ok(DAGasUOData::Scroll(ScrollDAGasUOData {
+            uo_units: uo_data.len() as u64,
+        }))
+    }
+
+    fn calc_da_gas_sync(
+        &self,
+        uo_data: &DAGasUOData,
+        block_data: &DAGasBlockData,
+        gas_price: u128,
+    ) -> u128 {
+        let block_da_data = match block_data {
+            DAGasBlockData::Scroll(block_da_data) => block_da_data,
+            _ => panic!("LocalScrollDAGasOracle only supports Scroll block data"),
+        };
+        let uo_data = match uo_data {
+            DAGasUOData::Scroll(uo_data) => uo_data,
+            _ => panic!("LocalScrollDAGasOracle only supports Scroll user operation data"),
+        };
+
+        calc_l1_fee(block_da_data, uo_data)
+            .checked_div(gas_price)
+            .unwrap_or(u128::MAX)
+    }
+}
+
+impl<AP, T> LocalScrollDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn is_curie(&self) -> bool {
+        self.oracle
+            .isCurie()
+            .call()
+            .await
+            .map(|r| r.isCurie)
+            .map_err(|e| error!("failed to check if curie: {:?}", e))
+            .unwrap_or(true) // Fail-open, same as the bedrock oracle's fjord check.
+    }
+
+    async fn get_block_data(
+        &self,
+        block: BlockHashOrNumber,
+    ) -> ProviderResult<ScrollDAGasBlockData> {
+        assert!(self.is_curie().await);
+
+        let calls = vec![
+            multicall::create_call(
+                *self.oracle.address(),
+                L1GasPriceOracleCalls::l1BaseFee(l1BaseFeeCall {}),
+            ),
+            multicall::create_call(
+                *self.oracle.address(),
+                L1GasPriceOracleCalls::l1BlobBaseFee(l1BlobBaseFeeCall {}),
+            ),
+            multicall::create_call(
+                *self.oracle.address(),
+                L1GasPriceOracleCalls::commitScalar(commitScalarCall {}),
+            ),
+            multicall::create_call(
+                *self.oracle.address(),
+                L1GasPriceOracleCalls::blobScalar(blobScalarCall {}),
+            ),
+        ];
+
+        let mut overrides = StateOverride::default();
+        let account = AccountOverride {
+            code: Some(MULTICALL_BYTECODE.clone()),
+            ..Default::default()
+        };
+        overrides.insert(*self.multicaller.address(), account);
+
+        let result = self
+            .multicaller
+            .aggregate3(calls)
+            .call()
+            .overrides(&overrides)
+            .block(block.into())
+            .await?;
+
+        if result.returnData.len() != 4 {
+            Err(anyhow::anyhow!(
+                "multicall returned unexpected number of results"
+            ))?;
+        } else if result.returnData.iter().any(|r| !r.success) {
+            Err(anyhow::anyhow!("multicall returned some failed results"))?;
+        }
+
+        let l1_base_fee =
+            multicall::decode_result::<l1BaseFeeCall>(&result.returnData[0].returnData)?
+                ._0
+                .try_into()
+                .context("l1_base_fee too large for u128")?;
+        let l1_blob_base_fee =
+            multicall::decode_result::<l1BlobBaseFeeCall>(&result.returnData[1].returnData)?
+                ._0
+                .try_into()
+                .context("l1_blob_base_fee too large for u128")?;
+        let commit_scalar =
+            multicall::decode_result::<commitScalarCall>(&result.returnData[2].returnData)?
+                ._0
+                .try_into()
+                .context("commit_scalar too large for u128")?;
+        let blob_scalar =
+            multicall::decode_result::<blobScalarCall>(&result.returnData[3].returnData)?
+                ._0
+                .try_into()
+                .context("blob_scalar too large for u128")?;
+
+        Ok(ScrollDAGasBlockData {
+            l1_base_fee,
+            l1_blob_base_fee,
+            commit_scalar,
+            blob_scalar,
+        })
+    }
+}
+
+fn calc_l1_fee(block_data: &ScrollDAGasBlockData, uo_data: &ScrollDAGasUOData) -> u128 {
+    let commit_fee = block_data
+        .commit_scalar
+        .saturating_mul(block_data.l1_base_fee);
+    let blob_fee = block_data
+        .blob_scalar
+        .saturating_mul(uo_data.uo_units as u128)
+        .saturating_mul(block_data.l1_blob_base_fee);
+    commit_fee.saturating_add(blob_fee) / PRECISION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_l1_fee() {
+        // scroll mainnet oracle values
+        let block_data = ScrollDAGasBlockData {
+            l1_base_fee: 5_000_000_000,
+            l1_blob_base_fee: 1,
+            commit_scalar: 230_759_955_285,
+            blob_scalar: 417_565_260,
+        };
+        let uo_data = ScrollDAGasUOData { uo_units: 1_000 };
+
+        // (230759955285 * 5e9 + 417565260 * 1000 * 1) / 1e9
+        assert_eq!(calc_l1_fee(&block_data, &uo_data), 1_153_799_776_842);
+    }
+
+    #[test]
+    fn test_calc_l1_fee_blob_dominated() {
+        let block_data = ScrollDAGasBlockData {
+            l1_base_fee: 0,
+            l1_blob_base_fee: 2_000_000_000,
+            commit_scalar: 230_759_955_285,
+            blob_scalar: 417_565_260,
+        };
+
+        let short = calc_l1_fee(&block_data, &ScrollDAGasUOData { uo_units: 100 });
+        let long = calc_l1_fee(&block_data, &ScrollDAGasUOData { uo_units: 200 });
+        assert_eq!(short, 83_513_052_000);
+        assert_eq!(long, 2 * short);
+    }
+}
@@ -0,0 +1,226 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::marker::PhantomData;
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_transport::Transport;
+use rundler_types::da::{DAGasBlockData, DAGasUOData, ZkStackDAGasBlockData, ZkStackDAGasUOData};
+use rundler_utils::cache::LruMap;
+use serde::Deserialize;
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::{BlockHashOrNumber, DAGasOracle, DAGasOracleSync, ProviderResult};
+
+/// L1 gas per byte of pubdata, used to price pubdata before the V2 fee model
+const L1_GAS_PER_PUBDATA_BYTE: u128 = 17;
+
+/// ZK Stack (zkSync Era style) DA gas oracle
+///
+/// ZK Stack chains charge for each byte of pubdata published to L1 at the pubdata price of
+/// the node's fee parameters, from `zks_getFeeParams`. Those parameters are not available at
+/// past blocks, so the block data of a block is the fee parameters when it was first requested.
+///
+/// Details: https://docs.zksync.io/zksync-protocol/rollup/fee-model
+pub(crate) struct ZkStackDAGasOracle<AP, T> {
+    provider: AP,
+    block_data_cache: TokioMutex<LruMap<BlockHashOrNumber, ZkStackDAGasBlockData>>,
+    _marker: PhantomData<T>,
+}
+
+impl<AP, T> ZkStackDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(provider: AP) -> Self {
+        Self {
+            provider,
+            block_data_cache: TokioMutex::new(LruMap::new(100)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for ZkStackDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let block_data = self.block_data(block).await?;
+        let uo_data = self.uo_data(data, to, block).await?;
+        let da_gas = self.calc_da_gas_sync(&uo_data, &block_data, gas_price);
+        Ok((da_gas, uo_data, block_data))
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracleSync for ZkStackDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn block_data(&self, block: BlockHashOrNumber) -> ProviderResult<DAGasBlockData> {
+        let mut cache = self.block_data_cache.lock().await;
+        match cache.get(&block) {
+            Some(block_data) => Ok(DAGasBlockData::ZkStack(block_data.clone())),
+            None => {
+                let fee_params: FeeParams = self
+                    .provider
+                    .raw_request("zks_getFeeParams".into(), ())
+                    .await?;
+                let block_data = fee_params.block_data();
+                cache.insert(block, block_data.clone());
+                Ok(DAGasBlockData::ZkStack(block_data))
+            }
+        }
+    }
+
+    async fn uo_data(
+        &self,
+        uo_data: Bytes,
+        _to: Address,
+        _block: BlockHashOrNumber,
+    ) -> ProviderResult<DAGasUOData> {
+        Ok(DAGasUOData::ZkStack(ZkStackDAGasUOData {
+            uo_units: uo_data.len() as u64,
+        }))
+    }
+
+    fn calc_da_gas_sync(
+        &self,
+        uo_data: &DAGasUOData,
+        block_data: &DAGasBlockData,
+        gas_price: u128,
+    ) -> u128 {
+        let block_da_data = match block_data {
+            DAGasBlockData::ZkStack(block_da_data) => block_da_data,
+            _ => panic!("ZkStackDAGasOracle only supports ZK Stack block data"),
+        };
+        let uo_data = match uo_data {
+            DAGasUOData::ZkStack(uo_data) => uo_data,
+            _ => panic!("ZkStackDAGasOracle only supports ZK Stack user operation data"),
+        };
+
+        (uo_data.uo_units as u128)
+            .saturating_mul(block_da_data.pubdata_price)
+            .checked_div(gas_price)
+            .unwrap_or(u128::MAX)
+    }
+}
+
+/// Fee parameters returned by `zks_getFeeParams`
+#[derive(Debug, Deserialize)]
+enum FeeParams {
+    V1(FeeParamsV1),
+    V2(FeeParamsV2),
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeParamsV1 {
+    l1_gas_price: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeParamsV2 {
+    l1_pubdata_price: u64,
+    /// Price of ETH in the chain's base token, absent on older nodes and ETH based chains
+    #[serde(default)]
+    conversion_ratio: Option<ConversionRatio>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversionRatio {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl FeeParams {
+    fn block_data(&self) -> ZkStackDAGasBlockData {
+        let pubdata_price = match self {
+            FeeParams::V1(params) => params.l1_gas_price as u128 * L1_GAS_PER_PUBDATA_BYTE,
+            FeeParams::V2(params) => match &params.conversion_ratio {
+                Some(ratio) if ratio.denominator != 0 => {
+                    params.l1_pubdata_price as u128 * ratio.numerator as u128
+                        / ratio.denominator as u128
+                }
+                _ => params.l1_pubdata_price as u128,
+            },
+        };
+        ZkStackDAGasBlockData { pubdata_price }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_params_v2() {
+        let fee_params: FeeParams = serde_json::from_str(
+            r#"{
+                "V2": {
+                    "config": {
+                        "minimal_l2_gas_price": 45250000,
+                        "compute_overhead_part": 0.0,
+                        "pubdata_overhead_part": 1.0,
+                        "batch_overhead_l1_gas": 800000,
+                        "max_gas_per_batch": 200000000,
+                        "max_pubdata_per_batch": 500000
+                    },
+                    "l1_gas_price": 9552478150,
+                    "l1_pubdata_price": 8574001,
+                    "conversion_ratio": {
+                        "numerator": 1,
+                        "denominator": 1
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fee_params.block_data(),
+            ZkStackDAGasBlockData {
+                pubdata_price: 8574001
+            }
+        );
+    }
+
+    #[test]
+    fn test_fee_params_v2_custom_base_token() {
+        let fee_params: FeeParams = serde_json::from_str(
+            r#"{"V2":{"l1_gas_price":1000,"l1_pubdata_price":2000,"conversion_ratio":{"numerator":3000,"denominator":2}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(fee_params.block_data().pubdata_price, 3_000_000);
+    }
+
+    #[test]
+    fn test_fee_params_v1() {
+        let fee_params: FeeParams =
+            serde_json::from_str(r#"{"V1":{"config":{},"l1_gas_price":1000}}"#).unwrap();
+
+        assert_eq!(fee_params.block_data().pubdata_price, 17_000);
+    }
+}
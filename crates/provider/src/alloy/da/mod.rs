@@ -27,8 +27,13 @@ mod arbitrum;
 use arbitrum::ArbitrumNitroDAGasOracle;
 mod optimism;
 use optimism::OptimismBedrockDAGasOracle;
+mod scroll;
+use scroll::ScrollL1DAGasOracle;
 mod local;
-use local::{CachedNitroDAGasOracle, LocalBedrockDAGasOracle};
+use local::{
+    CachedNitroDAGasOracle, LocalBedrockDAGasOracle, LocalMantleDAGasOracle,
+    LocalScrollDAGasOracle, ZkStackDAGasOracle,
+};
 
 struct ZeroDAGasOracle;
 
@@ -86,6 +91,31 @@ where
             ));
             (oracle.clone(), Some(oracle))
         }
+        DAGasOracleType::ScrollL1 => {
+            let oracle = Arc::new(ScrollL1DAGasOracle::new(
+                chain_spec.da_gas_oracle_contract_address,
+                provider,
+            ));
+            (oracle, None)
+        }
+        DAGasOracleType::LocalScroll => {
+            let oracle = Arc::new(LocalScrollDAGasOracle::new(
+                chain_spec.da_gas_oracle_contract_address,
+                provider,
+            ));
+            (oracle.clone(), Some(oracle))
+        }
+        DAGasOracleType::LocalMantle => {
+            let oracle = Arc::new(LocalMantleDAGasOracle::new(
+                chain_spec.da_gas_oracle_contract_address,
+                provider,
+            ));
+            (oracle.clone(), Some(oracle))
+        }
+        DAGasOracleType::ZkStackPubdata => {
+            let oracle = Arc::new(ZkStackDAGasOracle::new(provider));
+            (oracle.clone(), Some(oracle))
+        }
         DAGasOracleType::None => (Arc::new(ZeroDAGasOracle), None),
     }
 }
@@ -0,0 +1,88 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_sol_types::sol;
+use alloy_transport::Transport;
+use anyhow::Context;
+use rundler_types::da::{DAGasBlockData, DAGasUOData};
+use L1GasPriceOracle::L1GasPriceOracleInstance;
+
+use super::DAGasOracle;
+use crate::{BlockHashOrNumber, ProviderResult};
+
+// From https://github.com/scroll-tech/scroll/blob/develop/contracts/src/L2/predeploys/L1GasPriceOracle.sol
+sol! {
+    #[sol(rpc)]
+    interface L1GasPriceOracle {
+        bool public isCurie;
+
+        function l1BaseFee() external view returns (uint256);
+        function l1BlobBaseFee() external view returns (uint256);
+        function commitScalar() external view returns (uint256);
+        function blobScalar() external view returns (uint256);
+
+        function getL1Fee(bytes memory _data) external view returns (uint256);
+    }
+}
+
+pub(super) struct ScrollL1DAGasOracle<AP, T> {
+    oracle: L1GasPriceOracleInstance<T, AP>,
+}
+
+impl<AP, T> ScrollL1DAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(oracle_address: Address, provider: AP) -> Self {
+        let oracle = L1GasPriceOracleInstance::new(oracle_address, provider);
+        Self { oracle }
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for ScrollL1DAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        _to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        if gas_price == 0 {
+            Err(anyhow::anyhow!("gas price cannot be zero"))?;
+        }
+
+        let l1_fee: u128 = self
+            .oracle
+            .getL1Fee(data)
+            .block(block.into())
+            .call()
+            .await?
+            ._0
+            .try_into()
+            .context("failed to convert DA fee to u128")?;
+
+        Ok((
+            l1_fee.checked_div(gas_price).unwrap_or(u128::MAX),
+            DAGasUOData::Empty,
+            DAGasBlockData::Empty,
+        ))
+    }
+}
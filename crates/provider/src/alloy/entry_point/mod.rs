@@ -12,13 +12,151 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_consensus::{transaction::SignableTransaction, TxEnvelope, TypedTransaction};
-use alloy_primitives::{address, Address, Bytes, PrimitiveSignature, U256};
+use alloy_primitives::{address, Address, Bytes, PrimitiveSignature, B256, U256};
+use alloy_provider::{ext::DebugApi, Provider as AlloyProvider};
 use alloy_rlp::Encodable;
-use alloy_rpc_types_eth::TransactionRequest;
+use alloy_rpc_types_eth::{
+    simulate::{SimBlock, SimulatePayload, SimulatedBlock},
+    state::{AccountOverride, StateOverride},
+    Block, BlockId, TransactionRequest,
+};
+use alloy_rpc_types_trace::geth::{
+    DiffMode, GethDebugTracingCallOptions, GethDebugTracingOptions, PreStateConfig, PreStateFrame,
+};
+use alloy_transport::Transport;
+use anyhow::Context;
+
+use crate::ProviderResult;
 
 pub(crate) mod v0_6;
 pub(crate) mod v0_7;
 
+/// Simulates `tx` with `eth_simulateV1` on top of the latest block, after executing
+/// `pending_txs` in order. Returns the revert data of `tx` if it reverted.
+///
+/// Validation is disabled, so the pending transactions don't need valid nonces or
+/// balances for their fees.
+///
+/// Fails with an error for which `ProviderError::is_method_unsupported` is true if the
+/// node doesn't support `eth_simulateV1`.
+async fn simulate_after_pending_txs<AP: AlloyProvider<T>, T: Transport + Clone>(
+    provider: &AP,
+    pending_txs: Vec<TransactionRequest>,
+    tx: TransactionRequest,
+) -> ProviderResult<Option<Bytes>> {
+    let mut calls = pending_txs;
+    calls.push(tx);
+    let payload = SimulatePayload {
+        block_state_calls: vec![SimBlock {
+            block_overrides: None,
+            state_overrides: None,
+            calls,
+        }],
+        trace_transfers: false,
+        validation: false,
+        return_full_transactions: false,
+    };
+
+    let blocks: Vec<SimulatedBlock<Block>> = provider
+        .raw_request("eth_simulateV1".into(), (payload, BlockId::latest()))
+        .await?;
+    let result = blocks
+        .into_iter()
+        .next()
+        .and_then(|block| block.calls.into_iter().last())
+        .context("eth_simulateV1 should return the result of the last call")?;
+
+    Ok((!result.status).then_some(result.return_data))
+}
+
+/// Traces `tx` with the `prestateTracer` of `debug_traceCall` on top of `state_override` at
+/// `block_id`, and returns `state_override` updated with the state changes of `tx`.
+async fn trace_state_changes<AP: AlloyProvider<T>, T: Transport + Clone>(
+    provider: &AP,
+    tx: TransactionRequest,
+    block_id: BlockId,
+    state_override: StateOverride,
+) -> ProviderResult<StateOverride> {
+    let options = GethDebugTracingCallOptions::default()
+        .with_tracing_options(GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+            diff_mode: Some(true),
+            ..Default::default()
+        }))
+        .with_state_overrides(state_override.clone());
+    let frame = provider
+        .debug_trace_call(tx, block_id, options)
+        .await?
+        .try_into_pre_state_frame()
+        .context("debug_traceCall should return a prestate frame")?;
+    let PreStateFrame::Diff(diff) = frame else {
+        Err(anyhow::anyhow!(
+            "debug_traceCall should return a prestate frame in diff mode"
+        ))?
+    };
+
+    Ok(apply_state_diff(state_override, diff))
+}
+
+/// Applies the post-state of `diff` on top of `state_override`.
+///
+/// Accounts and storage slots that are in the pre-state but not in the post-state were
+/// cleared.
+fn apply_state_diff(mut state_override: StateOverride, diff: DiffMode) -> StateOverride {
+    let DiffMode { pre, post } = diff;
+    for (address, pre_account) in pre {
+        let Some(post_account) = post.get(&address) else {
+            state_override.insert(
+                address,
+                AccountOverride {
+                    balance: Some(U256::ZERO),
+                    nonce: Some(0),
+                    code: Some(Bytes::new()),
+                    state: Some(Default::default()),
+                    ..Default::default()
+                },
+            );
+            continue;
+        };
+        let cleared = pre_account
+            .storage
+            .into_keys()
+            .filter(|slot| !post_account.storage.contains_key(slot))
+            .map(|slot| (slot, B256::ZERO));
+        override_storage(state_override.entry(address).or_default(), cleared);
+    }
+
+    for (address, post_account) in post {
+        let account = state_override.entry(address).or_default();
+        if let Some(balance) = post_account.balance {
+            account.balance = Some(balance);
+        }
+        if let Some(nonce) = post_account.nonce {
+            account.nonce = Some(nonce);
+        }
+        if let Some(code) = post_account.code {
+            account.code = Some(code);
+        }
+        override_storage(account, post_account.storage);
+    }
+
+    state_override
+}
+
+/// Sets storage slots of an account override, in its full storage override if it has one
+fn override_storage(account: &mut AccountOverride, slots: impl IntoIterator<Item = (B256, B256)>) {
+    let mut slots = slots.into_iter().peekable();
+    if slots.peek().is_none() {
+        return;
+    }
+    match &mut account.state {
+        Some(state) => state.extend(slots),
+        None => account
+            .state_diff
+            .get_or_insert_with(Default::default)
+            .extend(slots),
+    }
+}
+
 fn max_bundle_transaction_data(to_address: Address, data: Bytes, gas_price: u128) -> Bytes {
     // Fill in max values for unknown or varying fields
     let gas_price_ceil = gas_price.next_power_of_two() - 1; // max out bits of gas price, assume same power of 2
@@ -56,3 +194,218 @@ fn max_bundle_transaction_data(to_address: Address, data: Bytes, gas_price: u128
 
     encoded.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use alloy_primitives::bytes;
+    use alloy_provider::ProviderBuilder;
+    use alloy_rpc_types_eth::simulate::SimCallResult;
+    use alloy_rpc_types_trace::geth::AccountState;
+    use tiny_http::{Response, Server};
+    use url::Url;
+
+    use super::*;
+
+    // Serves `responses` in order, one per request, and returns the requests received
+    fn serve(
+        responses: Vec<serde_json::Value>,
+    ) -> (Url, thread::JoinHandle<Vec<serde_json::Value>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap())
+            .parse()
+            .unwrap();
+        let handle = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|mut response| {
+                    let mut request = server.recv().unwrap();
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).unwrap();
+                    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+                    response["jsonrpc"] = "2.0".into();
+                    response["id"] = body["id"].clone();
+                    request
+                        .respond(Response::from_string(response.to_string()))
+                        .unwrap();
+                    body
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    fn simulated_block(calls: Vec<SimCallResult>) -> serde_json::Value {
+        serde_json::json!({
+            "result": [SimulatedBlock {
+                inner: Block::<alloy_rpc_types_eth::Transaction>::default(),
+                calls,
+            }],
+        })
+    }
+
+    fn call_result(status: bool, return_data: Bytes) -> SimCallResult {
+        SimCallResult {
+            return_data,
+            logs: vec![],
+            gas_used: 21_000,
+            status,
+            error: None,
+        }
+    }
+
+    fn tx(to: Address) -> TransactionRequest {
+        TransactionRequest::default().to(to)
+    }
+
+    #[tokio::test]
+    async fn test_simulate_after_pending_txs() {
+        let (url, server) = serve(vec![simulated_block(vec![
+            call_result(true, Bytes::new()),
+            call_result(false, bytes!("deadbeef")),
+        ])]);
+        let provider = ProviderBuilder::new().on_http(url);
+
+        let revert_data = simulate_after_pending_txs(
+            &provider,
+            vec![tx(Address::repeat_byte(1))],
+            tx(Address::repeat_byte(2)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(revert_data, Some(bytes!("deadbeef")));
+
+        // the pending transaction is executed before the simulated transaction
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0]["method"], "eth_simulateV1");
+        let calls = &requests[0]["params"][0]["blockStateCalls"][0]["calls"];
+        assert_eq!(calls[0]["to"], serde_json::json!(Address::repeat_byte(1)));
+        assert_eq!(calls[1]["to"], serde_json::json!(Address::repeat_byte(2)));
+        assert_eq!(requests[0]["params"][0]["validation"], false);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_after_pending_txs_success() {
+        let (url, _server) = serve(vec![simulated_block(vec![call_result(true, Bytes::new())])]);
+        let provider = ProviderBuilder::new().on_http(url);
+
+        let revert_data = simulate_after_pending_txs(&provider, vec![], tx(Address::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(revert_data, None);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_after_pending_txs_unsupported() {
+        let (url, _server) = serve(vec![serde_json::json!({
+            "error": {
+                "code": -32601,
+                "message": "the method eth_simulateV1 does not exist/is not available",
+            },
+        })]);
+        let provider = ProviderBuilder::new().on_http(url);
+
+        let err = simulate_after_pending_txs(&provider, vec![], tx(Address::ZERO))
+            .await
+            .unwrap_err();
+        assert!(err.is_method_unsupported());
+    }
+
+    #[tokio::test]
+    async fn test_trace_state_changes() {
+        let account = Address::repeat_byte(1);
+        let deleted = Address::repeat_byte(2);
+        let slot = B256::with_last_byte;
+        let diff = DiffMode {
+            pre: [
+                (
+                    account,
+                    AccountState {
+                        balance: Some(U256::from(1)),
+                        storage: [(slot(1), slot(1)), (slot(2), slot(1))].into(),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    deleted,
+                    AccountState {
+                        balance: Some(U256::from(1)),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+            post: [(
+                account,
+                AccountState {
+                    balance: Some(U256::from(2)),
+                    storage: [(slot(1), slot(2))].into(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+        let (url, server) = serve(vec![serde_json::json!({ "result": diff })]);
+        let provider = ProviderBuilder::new().on_http(url);
+
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            account,
+            AccountOverride {
+                nonce: Some(7),
+                state_diff: Some([(slot(3), slot(3))].into_iter().collect()),
+                ..Default::default()
+            },
+        );
+        let state_override = trace_state_changes(
+            &provider,
+            tx(Address::repeat_byte(3)),
+            BlockId::latest(),
+            state_override,
+        )
+        .await
+        .unwrap();
+
+        // the post-state is applied on top of the earlier overrides, and cleared slots and
+        // accounts are reset
+        assert_eq!(
+            state_override[&account],
+            AccountOverride {
+                balance: Some(U256::from(2)),
+                nonce: Some(7),
+                state_diff: Some(
+                    [
+                        (slot(1), slot(2)),
+                        (slot(2), B256::ZERO),
+                        (slot(3), slot(3)),
+                    ]
+                    .into_iter()
+                    .collect()
+                ),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            state_override[&deleted],
+            AccountOverride {
+                balance: Some(U256::ZERO),
+                nonce: Some(0),
+                code: Some(Bytes::new()),
+                state: Some(Default::default()),
+                ..Default::default()
+            }
+        );
+
+        // the earlier overrides are sent with the trace
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0]["method"], "debug_traceCall");
+        let options = &requests[0]["params"][2];
+        assert_eq!(options["tracer"], "prestateTracer");
+        assert_eq!(options["tracerConfig"]["diffMode"], true);
+        assert_eq!(
+            options["stateOverrides"][account.to_string().to_lowercase()]["nonce"],
+            "0x7"
+        );
+    }
+}
@@ -244,54 +244,63 @@ where
         let res = self.i_entry_point.provider().call(&tx).await;
 
         match res {
-            Ok(_) => return Ok(HandleOpsOut::Success),
+            Ok(_) => Ok(HandleOpsOut::Success),
             Err(TransportError::ErrorResp(resp)) => {
-                if let Some(err) =
-                    resp.as_decoded_error::<SolContractError<IEntryPointErrors>>(false)
-                {
-                    match err {
-                        SolContractError::CustomError(IEntryPointErrors::FailedOp(FailedOp {
-                            opIndex,
-                            reason,
-                        })) => {
-                            match &reason[..4] {
-                                // This revert is a bundler issue, not a user op issue, handle it differently
-                                "AA95" => {
-                                    Err(anyhow::anyhow!("Handle ops called with insufficient gas")
-                                        .into())
-                                }
-                                _ => Ok(HandleOpsOut::FailedOp(
-                                    opIndex
-                                        .try_into()
-                                        .context("returned opIndex out of bounds")?,
-                                    reason,
-                                )),
-                            }
-                        }
-                        SolContractError::CustomError(
-                            IEntryPointErrors::SignatureValidationFailed(err),
-                        ) => Ok(HandleOpsOut::SignatureValidationFailed(err.aggregator)),
-                        SolContractError::Revert(r) => {
-                            // Special handling for a bug in the 0.6 entry point contract to detect the bug where
-                            // the `returndatacopy` opcode reverts due to a postOp revert and the revert data is too short.
-                            // See https://github.com/eth-infinitism/account-abstraction/pull/325 for more details.
-                            // NOTE: this error message is copied directly from Geth and assumes it will not change.
-                            if r.reason.contains("return data out of bounds") {
-                                Ok(HandleOpsOut::PostOpRevert)
-                            } else {
-                                Err(TransportError::ErrorResp(resp).into())
-                            }
-                        }
-                        _ => Err(TransportError::ErrorResp(resp).into()),
-                    }
-                } else {
-                    Err(TransportError::ErrorResp(resp).into())
-                }
+                let decoded = match resp.as_revert_data() {
+                    Some(revert_data) => decode_handle_ops_revert(&revert_data)?,
+                    None => None,
+                };
+                decoded.ok_or_else(|| TransportError::ErrorResp(resp).into())
             }
             Err(error) => Err(error.into()),
         }
     }
 
+    async fn call_handle_ops_pending(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        pending_txs: Vec<TransactionRequest>,
+    ) -> ProviderResult<HandleOpsOut> {
+        let gas_limit = gas_limit.unwrap_or(self.max_simulate_handle_op_gas);
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            gas_limit,
+        );
+        let revert_data =
+            super::simulate_after_pending_txs(self.i_entry_point.provider(), pending_txs, tx)
+                .await?;
+
+        match revert_data {
+            None => Ok(HandleOpsOut::Success),
+            Some(revert_data) => decode_handle_ops_revert(&revert_data)?.ok_or_else(|| {
+                anyhow::anyhow!("handle ops reverted in pending state: {revert_data}").into()
+            }),
+        }
+    }
+
+    async fn get_handle_ops_state_override(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> ProviderResult<StateOverride> {
+        let gas_limit = gas_limit.unwrap_or(self.max_simulate_handle_op_gas);
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            gas_limit,
+        );
+        super::trace_state_changes(self.i_entry_point.provider(), tx, block_id, state_override)
+            .await
+    }
+
     fn get_send_bundle_transaction(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
@@ -357,6 +366,7 @@ where
     fn get_tracer_simulate_validation_call(
         &self,
         user_op: UserOperation,
+        state_override: StateOverride,
     ) -> ProviderResult<(TransactionRequest, StateOverride)> {
         let da_gas: u64 = user_op
             .pre_verification_da_gas_limit(&self.chain_spec, Some(1))
@@ -367,13 +377,14 @@ where
             .simulateValidation(user_op.into())
             .gas(self.max_verification_gas.saturating_add(da_gas))
             .into_transaction_request();
-        Ok((call, StateOverride::default()))
+        Ok((call, state_override))
     }
 
     async fn simulate_validation(
         &self,
         user_op: UserOperation,
         block_id: Option<BlockId>,
+        state_override: StateOverride,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>> {
         let da_gas: u64 = user_op
             .pre_verification_da_gas_limit(&self.chain_spec, Some(1))
@@ -382,7 +393,8 @@ where
         let blockless = self
             .i_entry_point
             .simulateValidation(user_op.into())
-            .gas(self.max_verification_gas.saturating_add(da_gas));
+            .gas(self.max_verification_gas.saturating_add(da_gas))
+            .state(state_override);
         let call = match block_id {
             Some(block_id) => blockless.block(block_id),
             None => blockless,
@@ -418,11 +430,25 @@ where
         }
     }
 
-    fn get_simulate_handle_op_call(&self, op: Self::UO, state_override: StateOverride) -> EvmCall {
+    fn get_simulate_handle_op_call(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        mut state_override: StateOverride,
+    ) -> EvmCall {
+        if let Some(authorization) = &op.authorization_tuple {
+            authorization_utils::apply_7702_overrides(
+                &mut state_override,
+                op.sender(),
+                authorization.address,
+            );
+        }
+
         let data = IEntryPoint::simulateHandleOpCall {
             op: op.into(),
-            target: Address::ZERO,
-            targetCallData: Bytes::new(),
+            target,
+            targetCallData: target_call_data,
         }
         .abi_encode()
         .into();
@@ -512,6 +538,14 @@ where
         Ok(ret)
     }
 
+    fn decode_simulate_handle_op_output(
+        _output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        Err(anyhow::anyhow!(
+            "simulateHandleOp succeeded, but should always revert"
+        ))?
+    }
+
     fn simulation_should_revert(&self) -> bool {
         true
     }
@@ -525,6 +559,39 @@ where
 {
 }
 
+// Decodes the revert data of a `handleOps` call, returning `None` if it is not an
+// entry point error about an operation or aggregator.
+fn decode_handle_ops_revert(revert_data: &[u8]) -> ProviderResult<Option<HandleOpsOut>> {
+    match SolContractError::<IEntryPointErrors>::abi_decode(revert_data, false) {
+        Ok(SolContractError::CustomError(IEntryPointErrors::FailedOp(FailedOp {
+            opIndex,
+            reason,
+        }))) => {
+            match &reason[..4] {
+                // This revert is a bundler issue, not a user op issue, handle it differently
+                "AA95" => Err(anyhow::anyhow!("Handle ops called with insufficient gas").into()),
+                _ => Ok(Some(HandleOpsOut::FailedOp(
+                    opIndex
+                        .try_into()
+                        .context("returned opIndex out of bounds")?,
+                    reason,
+                ))),
+            }
+        }
+        Ok(SolContractError::CustomError(IEntryPointErrors::SignatureValidationFailed(err))) => Ok(
+            Some(HandleOpsOut::SignatureValidationFailed(err.aggregator)),
+        ),
+        // Special handling for a bug in the 0.6 entry point contract to detect the bug where
+        // the `returndatacopy` opcode reverts due to a postOp revert and the revert data is too short.
+        // See https://github.com/eth-infinitism/account-abstraction/pull/325 for more details.
+        // NOTE: this error message is copied directly from Geth and assumes it will not change.
+        Ok(SolContractError::Revert(r)) if r.reason.contains("return data out of bounds") => {
+            Ok(Some(HandleOpsOut::PostOpRevert))
+        }
+        _ => Ok(None),
+    }
+}
+
 fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
     entry_point: &IEntryPointInstance<T, AP>,
     ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
@@ -540,7 +607,11 @@ fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
                 .into_iter()
                 .map(|op| {
                     if let Some(authorization) = &op.authorization_tuple {
-                        authorization_list.push(SignedAuthorization::from(authorization.clone()));
+                        let authorization = SignedAuthorization::from(authorization.clone());
+                        // Ops from the same sender share its authorization
+                        if !authorization_list.contains(&authorization) {
+                            authorization_list.push(authorization);
+                        }
                     }
                     op.into()
                 })
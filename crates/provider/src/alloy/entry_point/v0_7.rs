@@ -16,10 +16,7 @@ use alloy_eips::eip7702::SignedAuthorization;
 use alloy_json_rpc::ErrorPayload;
 use alloy_primitives::{Address, Bytes, U256};
 use alloy_provider::{network::TransactionBuilder7702, Provider as AlloyProvider};
-use alloy_rpc_types_eth::{
-    state::{AccountOverride, StateOverride},
-    BlockId, TransactionRequest,
-};
+use alloy_rpc_types_eth::{state::StateOverride, BlockId, TransactionRequest};
 use alloy_sol_types::{
     ContractError as SolContractError, SolCall, SolError, SolInterface, SolValue,
 };
@@ -36,12 +33,14 @@ use rundler_contracts::v0_7::{
     UserOpsPerAggregator as UserOpsPerAggregatorV0_7, ValidationResult as ValidationResultV0_7,
     ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE,
 };
+use rundler_contracts::v0_8::ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE;
 use rundler_types::{
     authorization::Authorization,
     chain::ChainSpec,
     da::{DAGasBlockData, DAGasUOData},
     v0_7::UserOperation,
-    GasFees, UserOperation as _, UserOpsPerAggregator, ValidationOutput, ValidationRevert,
+    EntryPointVersion, GasFees, UserOperation as _, UserOpsPerAggregator, ValidationOutput,
+    ValidationRevert,
 };
 use rundler_utils::authorization_utils;
 
@@ -51,9 +50,12 @@ use crate::{
     ExecutionResult, HandleOpsOut, ProviderResult, SignatureAggregator, SimulationProvider,
 };
 /// Entry point provider for v0.7
+///
+/// Also serves entry point v0.8, which keeps the v0.7 ABI.
 #[derive(Clone)]
 pub struct EntryPointProvider<AP, T, D> {
     i_entry_point: IEntryPointInstance<T, AP>,
+    simulations_bytecode: Bytes,
     da_gas_oracle: D,
     max_verification_gas: u64,
     max_simulate_handle_ops_gas: u64,
@@ -75,11 +77,60 @@ where
         provider: AP,
         da_gas_oracle: D,
     ) -> Self {
-        Self {
-            i_entry_point: IEntryPointInstance::new(
+        Self::new_with_version(
+            EntryPointVersion::V0_7,
+            chain_spec,
+            max_verification_gas,
+            max_simulate_handle_ops_gas,
+            max_aggregation_gas,
+            provider,
+            da_gas_oracle,
+        )
+    }
+
+    /// Create a new `EntryPoint` instance for v0.8
+    pub fn new_v0_8(
+        chain_spec: ChainSpec,
+        max_verification_gas: u64,
+        max_simulate_handle_ops_gas: u64,
+        max_aggregation_gas: u64,
+        provider: AP,
+        da_gas_oracle: D,
+    ) -> Self {
+        Self::new_with_version(
+            EntryPointVersion::V0_8,
+            chain_spec,
+            max_verification_gas,
+            max_simulate_handle_ops_gas,
+            max_aggregation_gas,
+            provider,
+            da_gas_oracle,
+        )
+    }
+
+    fn new_with_version(
+        version: EntryPointVersion,
+        chain_spec: ChainSpec,
+        max_verification_gas: u64,
+        max_simulate_handle_ops_gas: u64,
+        max_aggregation_gas: u64,
+        provider: AP,
+        da_gas_oracle: D,
+    ) -> Self {
+        let (address, simulations_bytecode) = match version {
+            EntryPointVersion::V0_8 => (
+                chain_spec.entry_point_address_v0_8,
+                ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE.clone(),
+            ),
+            _ => (
                 chain_spec.entry_point_address_v0_7,
-                provider.clone(),
+                ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone(),
             ),
+        };
+
+        Self {
+            i_entry_point: IEntryPointInstance::new(address, provider.clone()),
+            simulations_bytecode,
             da_gas_oracle,
             max_verification_gas,
             max_simulate_handle_ops_gas,
@@ -253,38 +304,63 @@ where
         let res = self.i_entry_point.provider().call(&tx).await;
 
         match res {
-            Ok(_) => return Ok(HandleOpsOut::Success),
+            Ok(_) => Ok(HandleOpsOut::Success),
             Err(TransportError::ErrorResp(resp)) => {
-                if let Some(err) = resp.as_decoded_error::<IEntryPointErrors>(false) {
-                    match err {
-                        IEntryPointErrors::FailedOp(FailedOp { opIndex, reason }) => {
-                            match &reason[..4] {
-                                // This revert is a bundler issue, not a user op issue, handle it differently
-                                "AA95" => {
-                                    Err(anyhow::anyhow!("Handle ops called with insufficient gas")
-                                        .into())
-                                }
-                                _ => Ok(HandleOpsOut::FailedOp(
-                                    opIndex
-                                        .try_into()
-                                        .context("returned opIndex out of bounds")?,
-                                    reason,
-                                )),
-                            }
-                        }
-                        IEntryPointErrors::SignatureValidationFailed(failure) => {
-                            Ok(HandleOpsOut::SignatureValidationFailed(failure.aggregator))
-                        }
-                        _ => Err(TransportError::ErrorResp(resp).into()),
-                    }
-                } else {
-                    Err(TransportError::ErrorResp(resp).into())
-                }
+                let decoded = match resp.as_revert_data() {
+                    Some(revert_data) => decode_handle_ops_revert(&revert_data)?,
+                    None => None,
+                };
+                decoded.ok_or_else(|| TransportError::ErrorResp(resp).into())
             }
             Err(error) => Err(error.into()),
         }
     }
 
+    async fn call_handle_ops_pending(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        pending_txs: Vec<TransactionRequest>,
+    ) -> ProviderResult<HandleOpsOut> {
+        let gas_limit = gas_limit.unwrap_or(self.max_simulate_handle_ops_gas);
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            gas_limit,
+        );
+        let revert_data =
+            super::simulate_after_pending_txs(self.i_entry_point.provider(), pending_txs, tx)
+                .await?;
+
+        match revert_data {
+            None => Ok(HandleOpsOut::Success),
+            Some(revert_data) => decode_handle_ops_revert(&revert_data)?.ok_or_else(|| {
+                anyhow::anyhow!("handle ops reverted in pending state: {revert_data}").into()
+            }),
+        }
+    }
+
+    async fn get_handle_ops_state_override(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> ProviderResult<StateOverride> {
+        let gas_limit = gas_limit.unwrap_or(self.max_simulate_handle_ops_gas);
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            gas_limit,
+        );
+        super::trace_state_changes(self.i_entry_point.provider(), tx, block_id, state_override)
+            .await
+    }
+
     fn get_send_bundle_transaction(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
@@ -345,6 +421,7 @@ where
     fn get_tracer_simulate_validation_call(
         &self,
         user_op: Self::UO,
+        state_override: StateOverride,
     ) -> ProviderResult<(TransactionRequest, StateOverride)> {
         let addr = *self.i_entry_point.address();
         let da_gas: u64 = user_op
@@ -352,8 +429,8 @@ where
             .try_into()
             .unwrap_or(u64::MAX);
 
-        let mut override_ep = StateOverride::default();
-        add_simulations_override(&mut override_ep, addr);
+        let mut override_ep = state_override;
+        add_simulations_override(&mut override_ep, addr, &self.simulations_bytecode);
 
         add_authorization_tuple(
             user_op.sender(),
@@ -376,8 +453,9 @@ where
         &self,
         user_op: Self::UO,
         block_id: Option<BlockId>,
+        state_override: StateOverride,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>> {
-        let (tx, overrides) = self.get_tracer_simulate_validation_call(user_op)?;
+        let (tx, overrides) = self.get_tracer_simulate_validation_call(user_op, state_override)?;
         let mut call = self.i_entry_point.provider().call(&tx);
         if let Some(block_id) = block_id {
             call = call.block(block_id);
@@ -399,14 +477,22 @@ where
     fn get_simulate_handle_op_call(
         &self,
         op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
         mut state_override: StateOverride,
     ) -> EvmCall {
-        add_simulations_override(&mut state_override, *self.i_entry_point.address());
+        add_simulations_override(
+            &mut state_override,
+            *self.i_entry_point.address(),
+            &self.simulations_bytecode,
+        );
+
+        add_authorization_tuple(op.sender(), &op.authorization_tuple, &mut state_override);
 
         let data = IEntryPointSimulations::simulateHandleOpCall {
             op: op.pack(),
-            target: Address::ZERO,
-            targetCallData: Bytes::new(),
+            target,
+            targetCallData: target_call_data,
         }
         .abi_encode()
         .into();
@@ -432,7 +518,11 @@ where
             .try_into()
             .unwrap_or(u64::MAX);
 
-        add_simulations_override(&mut state_override, *self.i_entry_point.address());
+        add_simulations_override(
+            &mut state_override,
+            *self.i_entry_point.address(),
+            &self.simulations_bytecode,
+        );
 
         add_authorization_tuple(op.sender(), &op.authorization_tuple, &mut state_override);
 
@@ -465,6 +555,14 @@ where
         Ok(Err(decode_validation_revert(revert_data)))
     }
 
+    fn decode_simulate_handle_op_output(
+        output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        let ret = IEntryPointSimulations::simulateHandleOpCall::abi_decode_returns(output, false)
+            .context("failed to decode simulateHandleOp output")?;
+        Ok(Ok(ret._0.try_into()?))
+    }
+
     fn simulation_should_revert(&self) -> bool {
         false
     }
@@ -478,17 +576,19 @@ where
 {
 }
 
-fn add_simulations_override(state_override: &mut StateOverride, addr: Address) {
+fn add_simulations_override(
+    state_override: &mut StateOverride,
+    addr: Address,
+    simulations_bytecode: &Bytes,
+) {
     // Do nothing if the caller has already overridden the entry point code.
     // We'll trust they know what they're doing and not replace their code.
     // This is needed for call gas estimation, where the entry point is
     // replaced with a proxy and the simulations bytecode is elsewhere.
-    state_override
-        .entry(addr)
-        .or_insert_with(|| AccountOverride {
-            code: Some(ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone()),
-            ..Default::default()
-        });
+    let account = state_override.entry(addr).or_default();
+    if account.code.is_none() {
+        account.code = Some(simulations_bytecode.clone());
+    }
 }
 
 fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
@@ -506,7 +606,11 @@ fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
                 .into_iter()
                 .map(|op| {
                     if let Some(authorization) = &op.authorization_tuple {
-                        authorization_list.push(SignedAuthorization::from(authorization.clone()));
+                        let authorization = SignedAuthorization::from(authorization.clone());
+                        // Ops from the same sender share its authorization
+                        if !authorization_list.contains(&authorization) {
+                            authorization_list.push(authorization);
+                        }
                     }
                     op.pack()
                 })
@@ -533,6 +637,29 @@ fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
     txn_request
 }
 
+// Decodes the revert data of a `handleOps` call, returning `None` if it is not an
+// entry point error about an operation or aggregator.
+fn decode_handle_ops_revert(revert_data: &[u8]) -> ProviderResult<Option<HandleOpsOut>> {
+    match IEntryPointErrors::abi_decode(revert_data, false) {
+        Ok(IEntryPointErrors::FailedOp(FailedOp { opIndex, reason })) => {
+            match &reason[..4] {
+                // This revert is a bundler issue, not a user op issue, handle it differently
+                "AA95" => Err(anyhow::anyhow!("Handle ops called with insufficient gas").into()),
+                _ => Ok(Some(HandleOpsOut::FailedOp(
+                    opIndex
+                        .try_into()
+                        .context("returned opIndex out of bounds")?,
+                    reason,
+                ))),
+            }
+        }
+        Ok(IEntryPointErrors::SignatureValidationFailed(failure)) => Ok(Some(
+            HandleOpsOut::SignatureValidationFailed(failure.aggregator),
+        )),
+        _ => Ok(None),
+    }
+}
+
 fn decode_validation_revert_payload(err: ErrorPayload) -> ValidationRevert {
     match err.as_revert_data() {
         Some(err_bytes) => decode_validation_revert(&err_bytes),
@@ -540,7 +667,7 @@ fn decode_validation_revert_payload(err: ErrorPayload) -> ValidationRevert {
     }
 }
 
-/// Decodes raw validation revert bytes from a v0.7 entry point
+/// Decodes raw validation revert bytes from a v0.7 or v0.8 entry point
 pub fn decode_validation_revert(err_bytes: &Bytes) -> ValidationRevert {
     if let Ok(rev) = SolContractError::<IEntryPointErrors>::abi_decode(err_bytes, false) {
         match rev {
@@ -175,6 +175,33 @@ where
         Ok(self.inner.get_transaction_count(address).await?)
     }
 
+    async fn get_transaction_count_at(
+        &self,
+        address: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<u64> {
+        let mut call = self.inner.get_transaction_count(address);
+        if let Some(block) = block {
+            call = call.block_id(block);
+        }
+
+        Ok(call.await?)
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: U256,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let mut call = self.inner.get_storage_at(address, slot);
+        if let Some(block) = block {
+            call = call.block_id(block);
+        }
+
+        Ok(call.await?)
+    }
+
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
         Ok(self.inner.get_logs(filter).await?)
     }
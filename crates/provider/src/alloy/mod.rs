@@ -13,24 +13,31 @@
 
 use std::time::Duration;
 
-use alloy_provider::{Provider as AlloyProvider, ProviderBuilder};
+use alloy_provider::{Provider as AlloyProvider, ProviderBuilder, WsConnect};
 use alloy_rpc_client::ClientBuilder;
 use alloy_transport::layers::RetryBackoffService;
 use alloy_transport_http::Http;
 use anyhow::Context;
 use evm::AlloyEvmProvider;
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use metrics::{AlloyMetricLayer, AlloyMetricMiddleware};
+use multi_node::{MultiNodeConfig, MultiNodeTransport};
 use provider_timeout::{ProviderTimeout, ProviderTimeoutLayer};
 use reqwest::Client;
+use tower::Layer;
 use url::Url;
 
-use crate::EvmProvider;
+use crate::{BlockHeader, EvmProvider};
 
 mod da;
 pub use da::new_alloy_da_gas_oracle;
 pub(crate) mod entry_point;
 pub(crate) mod evm;
 pub(crate) mod metrics;
+pub(crate) mod multi_node;
 mod provider_timeout;
 
 /// Create a new alloy evm provider from a given RPC URL
@@ -66,6 +73,69 @@ pub fn new_alloy_provider(
     Ok(provider)
 }
 
+type MultiNodeService =
+    RetryBackoffService<AlloyMetricMiddleware<MultiNodeTransport<ProviderTimeout<Http<Client>>>>>;
+
+/// Create a new alloy provider that spreads requests over multiple node endpoints, with
+/// failover, head lag detection, method-based routing and optional quorum reads.
+///
+/// The client timeout applies to each request to a single endpoint, so a request that times
+/// out on one endpoint fails over to the next.
+pub fn new_alloy_multi_node_provider(
+    config: &MultiNodeConfig,
+    provider_client_timeout_seconds: u64,
+) -> anyhow::Result<impl AlloyProvider<MultiNodeService> + Clone> {
+    config.validate()?;
+    let timeout_layer =
+        ProviderTimeoutLayer::new(Duration::from_secs(provider_client_timeout_seconds));
+    let services = config
+        .endpoints
+        .iter()
+        .map(|endpoint| {
+            let url = Url::parse(&endpoint.url).context("invalid rpc url")?;
+            // the full url may contain an API key, only the host is logged
+            let name = url.host_str().unwrap_or_default().to_string();
+            Ok((name, timeout_layer.layer(Http::new(url))))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let transport = MultiNodeTransport::new(config, services);
+
+    let metric_layer = AlloyMetricLayer::default();
+    // TODO: make this configurable: use a large number for CUPS for now
+    let retry_layer = alloy_transport::layers::RetryBackoffLayer::new(10, 500, 1_000_000);
+    let client = ClientBuilder::default()
+        .layer(retry_layer)
+        .layer(metric_layer)
+        .transport(transport, false);
+    let provider = ProviderBuilder::new().on_client(client);
+    Ok(provider)
+}
+
+/// Subscribe to new block headers with `eth_subscribe("newHeads")` over a websocket
+/// connection to the node at `ws_url`.
+///
+/// The stream ends when the subscription is closed. Headers that the consumer falls behind
+/// on are skipped, so consumers must backfill any gaps.
+pub async fn subscribe_new_heads(ws_url: &str) -> anyhow::Result<BoxStream<'static, BlockHeader>> {
+    let provider = ProviderBuilder::new()
+        .on_ws(WsConnect::new(ws_url))
+        .await
+        .context("should connect to node websocket")?;
+    let subscription = provider
+        .subscribe_blocks()
+        .await
+        .context("should subscribe to new heads")?;
+    // the provider owns the connection, so it must live as long as the stream
+    let heads = stream::unfold(
+        (provider, subscription.into_stream()),
+        |(provider, mut heads)| async move {
+            let header = heads.next().await?;
+            Some((header, (provider, heads)))
+        },
+    );
+    Ok(heads.boxed())
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
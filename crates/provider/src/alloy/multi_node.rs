@@ -0,0 +1,811 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Transport that spreads requests over multiple node endpoints.
+//!
+//! Requests are sent to the first endpoint, in configured order, that supports the
+//! request's method and is healthy. An endpoint is unhealthy for a cooldown period after
+//! a transport error, and while its head lags behind the highest head of the other
+//! endpoints. Unhealthy endpoints are still tried last, so a request only fails if no
+//! endpoint can serve it.
+//!
+//! Reads that return a node-side error, such as a rate limit or a missing block, are
+//! also retried on the next endpoint. Errors that every node would return, such as
+//! execution reverts, and errors of transaction submissions are returned as is.
+//!
+//! Optionally, block and log reads require a quorum of endpoints to agree.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy_json_rpc::{ErrorPayload, Id, Request, RequestPacket, ResponsePacket, ResponsePayload};
+use alloy_primitives::U64;
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use futures_util::future;
+use serde::Deserialize;
+use tower::Service;
+use tracing::{debug, warn};
+
+/// Methods that require a quorum of endpoints to agree, if a quorum is configured
+const QUORUM_METHODS: &[&str] = &["eth_getBlockByNumber", "eth_getBlockByHash", "eth_getLogs"];
+
+/// Prefixes of methods that change state, their error responses are never retried
+const WRITE_METHOD_PREFIXES: &[&str] = &["eth_send", "eth_cancel"];
+
+/// Error messages of node-side failures, in addition to the rate limits recognized by
+/// alloy, that another endpoint may not have
+const NODE_ERROR_MESSAGES: &[&str] = &["missing trie node", "unknown block", "block not found"];
+
+/// Configuration of a provider backed by multiple node endpoints
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiNodeConfig {
+    /// Node endpoints, in order of preference
+    pub endpoints: Vec<NodeEndpointConfig>,
+    /// Maximum number of blocks an endpoint's head may lag behind the highest head of
+    /// the endpoints before it is considered unhealthy
+    #[serde(default = "default_max_head_lag")]
+    pub max_head_lag: u64,
+    /// Interval between checks of the endpoints' heads, in milliseconds
+    #[serde(default = "default_head_check_interval_millis")]
+    pub head_check_interval_millis: u64,
+    /// Time an endpoint is considered unhealthy after a transport error, in milliseconds
+    #[serde(default = "default_unhealthy_cooldown_millis")]
+    pub unhealthy_cooldown_millis: u64,
+    /// If set, the number of endpoints that must return the same result for block and
+    /// log reads
+    #[serde(default)]
+    pub quorum: Option<usize>,
+}
+
+/// Configuration of a single node endpoint
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeEndpointConfig {
+    /// HTTP URL of the node
+    pub url: String,
+    /// If set, only these methods are sent to the endpoint
+    #[serde(default)]
+    pub methods: Option<Vec<String>>,
+    /// Methods that are never sent to the endpoint
+    #[serde(default)]
+    pub exclude_methods: Vec<String>,
+}
+
+fn default_max_head_lag() -> u64 {
+    5
+}
+
+fn default_head_check_interval_millis() -> u64 {
+    2_000
+}
+
+fn default_unhealthy_cooldown_millis() -> u64 {
+    10_000
+}
+
+impl MultiNodeConfig {
+    /// Configuration with a single endpoint that serves every method
+    pub fn single(url: impl Into<String>) -> Self {
+        Self {
+            endpoints: vec![NodeEndpointConfig {
+                url: url.into(),
+                methods: None,
+                exclude_methods: vec![],
+            }],
+            max_head_lag: default_max_head_lag(),
+            head_check_interval_millis: default_head_check_interval_millis(),
+            unhealthy_cooldown_millis: default_unhealthy_cooldown_millis(),
+            quorum: None,
+        }
+    }
+
+    /// Returns an error if the configuration can't be used
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.endpoints.is_empty() {
+            anyhow::bail!("at least one node endpoint must be configured");
+        }
+        if let Some(quorum) = self.quorum {
+            if quorum == 0 || quorum > self.endpoints.len() {
+                anyhow::bail!(
+                    "quorum {quorum} must be between 1 and the number of endpoints {}",
+                    self.endpoints.len()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Transport that routes requests over multiple node endpoints, see the module docs
+#[derive(Debug)]
+pub struct MultiNodeTransport<S> {
+    inner: Arc<Inner<S>>,
+}
+
+impl<S> Clone for MultiNodeTransport<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner<S> {
+    endpoints: Vec<Endpoint<S>>,
+    max_head_lag: u64,
+    head_check_interval: Duration,
+    unhealthy_cooldown: Duration,
+    quorum: Option<usize>,
+    last_head_check: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+struct Endpoint<S> {
+    // host of the endpoint's URL, the full URL may contain an API key
+    name: String,
+    service: S,
+    methods: Option<HashSet<String>>,
+    exclude_methods: HashSet<String>,
+    state: Mutex<EndpointState>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    unhealthy_until: Option<Instant>,
+    head: Option<u64>,
+}
+
+impl<S> MultiNodeTransport<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    /// Create a new transport from the configuration and a service for each of its endpoints
+    pub(crate) fn new(config: &MultiNodeConfig, services: Vec<(String, S)>) -> Self {
+        let endpoints = config
+            .endpoints
+            .iter()
+            .zip(services)
+            .map(|(endpoint, (name, service))| Endpoint {
+                name,
+                service,
+                methods: endpoint
+                    .methods
+                    .as_ref()
+                    .map(|methods| methods.iter().cloned().collect()),
+                exclude_methods: endpoint.exclude_methods.iter().cloned().collect(),
+                state: Mutex::new(EndpointState::default()),
+            })
+            .collect();
+
+        Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                max_head_lag: config.max_head_lag,
+                head_check_interval: Duration::from_millis(config.head_check_interval_millis),
+                unhealthy_cooldown: Duration::from_millis(config.unhealthy_cooldown_millis),
+                quorum: config.quorum,
+                last_head_check: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl<S> Service<RequestPacket> for MultiNodeTransport<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // each request is sent on a clone of an endpoint's service
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let inner = self.inner.clone();
+        inner.maybe_check_heads();
+        Box::pin(async move { inner.route(request).await })
+    }
+}
+
+impl<S> Inner<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    async fn route(&self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let methods = request_methods(&request);
+        let candidates = self.candidates(&methods);
+        if candidates.is_empty() {
+            return Err(TransportErrorKind::custom_str(&format!(
+                "no node endpoint supports {methods:?}"
+            )));
+        }
+
+        if let Some(quorum) = self.quorum {
+            if let RequestPacket::Single(req) = &request {
+                if QUORUM_METHODS.contains(&req.method()) {
+                    return self.quorum_request(request, candidates, quorum).await;
+                }
+            }
+        }
+
+        let is_read = methods.iter().all(|method| is_read_method(method));
+        let mut last_result = None;
+        for index in candidates {
+            let endpoint = &self.endpoints[index];
+            match endpoint.send(request.clone()).await {
+                Ok(response) => {
+                    if is_read {
+                        if let Some(error) = node_error(&response) {
+                            warn!(
+                                "Node endpoint {} returned error for {methods:?}, failing over: {} {}",
+                                endpoint.name, error.code, error.message
+                            );
+                            endpoint.mark_unhealthy(self.unhealthy_cooldown);
+                            last_result = Some(Ok(response));
+                            continue;
+                        }
+                    }
+                    endpoint.mark_healthy();
+                    return Ok(response);
+                }
+                Err(error) => {
+                    warn!(
+                        "Node endpoint {} failed {methods:?}, failing over: {error}",
+                        endpoint.name
+                    );
+                    endpoint.mark_unhealthy(self.unhealthy_cooldown);
+                    last_result = Some(Err(error));
+                }
+            }
+        }
+        last_result.expect("should have tried at least one endpoint")
+    }
+
+    // Sends the request to every candidate and returns the response that at least `quorum`
+    // of them agree on
+    async fn quorum_request(
+        &self,
+        request: RequestPacket,
+        candidates: Vec<usize>,
+        quorum: usize,
+    ) -> Result<ResponsePacket, TransportError> {
+        let results = future::join_all(
+            candidates
+                .iter()
+                .map(|&index| self.endpoints[index].send(request.clone())),
+        )
+        .await;
+
+        let mut groups: Vec<(String, ResponsePacket, usize)> = vec![];
+        for (index, result) in candidates.into_iter().zip(results) {
+            let endpoint = &self.endpoints[index];
+            let response = match result {
+                Ok(response) => {
+                    if let Some(error) = node_error(&response) {
+                        warn!(
+                            "Node endpoint {} returned error for quorum read: {} {}",
+                            endpoint.name, error.code, error.message
+                        );
+                        endpoint.mark_unhealthy(self.unhealthy_cooldown);
+                        continue;
+                    }
+                    endpoint.mark_healthy();
+                    response
+                }
+                Err(error) => {
+                    warn!(
+                        "Node endpoint {} failed quorum read: {error}",
+                        endpoint.name
+                    );
+                    endpoint.mark_unhealthy(self.unhealthy_cooldown);
+                    continue;
+                }
+            };
+            let Some(key) = quorum_key(&response) else {
+                continue;
+            };
+            match groups.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, count)) => *count += 1,
+                None => groups.push((key, response, 1)),
+            }
+        }
+
+        groups
+            .into_iter()
+            .filter(|(_, _, count)| *count >= quorum)
+            .max_by_key(|(_, _, count)| *count)
+            .map(|(_, response, _)| response)
+            .ok_or_else(|| {
+                TransportErrorKind::custom_str(&format!(
+                    "node endpoints did not reach a quorum of {quorum}"
+                ))
+            })
+    }
+
+    // Indexes of the endpoints that support all of the methods, healthy endpoints first
+    fn candidates(&self, methods: &[String]) -> Vec<usize> {
+        let now = Instant::now();
+        let best_head = self
+            .endpoints
+            .iter()
+            .filter_map(|endpoint| endpoint.state.lock().unwrap().head)
+            .max();
+
+        let (mut healthy, unhealthy): (Vec<_>, Vec<_>) = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| methods.iter().all(|method| endpoint.supports(method)))
+            .partition(|(_, endpoint)| endpoint.is_healthy(now, best_head, self.max_head_lag));
+        healthy.extend(unhealthy);
+        healthy.into_iter().map(|(index, _)| index).collect()
+    }
+
+    // Starts a check of the endpoints' heads if one is due. There is nothing to compare
+    // with a single endpoint.
+    fn maybe_check_heads(self: &Arc<Self>) {
+        if self.endpoints.len() < 2 {
+            return;
+        }
+        {
+            let mut last_head_check = self.last_head_check.lock().unwrap();
+            if last_head_check.is_some_and(|last| last.elapsed() < self.head_check_interval) {
+                return;
+            }
+            *last_head_check = Some(Instant::now());
+        }
+
+        let inner = self.clone();
+        tokio::spawn(async move {
+            future::join_all(inner.endpoints.iter().map(|endpoint| async {
+                match endpoint.get_block_number().await {
+                    Ok(head) => {
+                        debug!("Node endpoint {} head is {head}", endpoint.name);
+                        endpoint.state.lock().unwrap().head = Some(head);
+                    }
+                    Err(error) => {
+                        warn!("Node endpoint {} failed head check: {error}", endpoint.name);
+                        endpoint.mark_unhealthy(inner.unhealthy_cooldown);
+                    }
+                }
+            }))
+            .await;
+        });
+    }
+}
+
+impl<S> Endpoint<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError> + Clone,
+{
+    async fn send(&self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        self.service.clone().call(request).await
+    }
+
+    async fn get_block_number(&self) -> Result<u64, TransportError> {
+        let request = Request::new("eth_blockNumber", Id::Number(0), ())
+            .serialize()
+            .map_err(TransportError::ser_err)?;
+        let response = self.send(RequestPacket::Single(request)).await?;
+        let ResponsePacket::Single(response) = response else {
+            return Err(TransportErrorKind::custom_str(
+                "unexpected batch response to eth_blockNumber",
+            ));
+        };
+        match response.payload.try_success_as::<U64>() {
+            Some(Ok(block_number)) => Ok(block_number.to()),
+            Some(Err(error)) => Err(TransportError::deser_err(error, "eth_blockNumber")),
+            None => Err(TransportErrorKind::custom_str(
+                "eth_blockNumber returned an error",
+            )),
+        }
+    }
+
+    fn supports(&self, method: &str) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.contains(method))
+            && !self.exclude_methods.contains(method)
+    }
+
+    fn is_healthy(&self, now: Instant, best_head: Option<u64>, max_head_lag: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        if state.unhealthy_until.is_some_and(|until| now < until) {
+            return false;
+        }
+        match (state.head, best_head) {
+            (Some(head), Some(best_head)) => head + max_head_lag >= best_head,
+            _ => true,
+        }
+    }
+
+    fn mark_healthy(&self) {
+        self.state.lock().unwrap().unhealthy_until = None;
+    }
+
+    fn mark_unhealthy(&self, cooldown: Duration) {
+        self.state.lock().unwrap().unhealthy_until = Some(Instant::now() + cooldown);
+    }
+}
+
+fn request_methods(request: &RequestPacket) -> Vec<String> {
+    match request {
+        RequestPacket::Single(req) => vec![req.method().to_string()],
+        RequestPacket::Batch(reqs) => reqs.iter().map(|req| req.method().to_string()).collect(),
+    }
+}
+
+fn is_read_method(method: &str) -> bool {
+    !WRITE_METHOD_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+}
+
+// Returns the first error in the response that is specific to the node that returned it,
+// as opposed to errors that any node would return for the request
+fn node_error(response: &ResponsePacket) -> Option<&ErrorPayload> {
+    let is_node_error = |error: &ErrorPayload| {
+        error.is_retry_err()
+            || error.code == -32601
+            || NODE_ERROR_MESSAGES
+                .iter()
+                .any(|message| error.message.contains(message))
+    };
+    match response {
+        ResponsePacket::Single(response) => {
+            response.payload.as_error().filter(|e| is_node_error(e))
+        }
+        ResponsePacket::Batch(responses) => responses
+            .iter()
+            .filter_map(|response| response.payload.as_error())
+            .find(|e| is_node_error(e)),
+    }
+}
+
+// Identifies the result of a block or log read, ignoring fields that nodes may return
+// differently: blocks by their hash and logs by their block hash and index. Errors are
+// identified by their code.
+fn quorum_key(response: &ResponsePacket) -> Option<String> {
+    let ResponsePacket::Single(response) = response else {
+        return None;
+    };
+    let raw = match &response.payload {
+        ResponsePayload::Success(raw) => raw,
+        ResponsePayload::Failure(error) => return Some(format!("error {}", error.code)),
+    };
+    let value: serde_json::Value = serde_json::from_str(raw.get()).ok()?;
+    let key = match &value {
+        serde_json::Value::Array(logs) => logs
+            .iter()
+            .map(|log| format!("{}:{}", log["blockHash"], log["logIndex"]))
+            .collect::<Vec<_>>()
+            .join(","),
+        serde_json::Value::Object(block) => block.get("hash")?.to_string(),
+        other => other.to_string(),
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use alloy_json_rpc::{Response, SerializedRequest};
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    /// Service that answers every request with a fixed result or error, or fails
+    #[derive(Clone, Debug)]
+    struct MockService {
+        result: Option<&'static str>,
+        error: Option<(i64, &'static str)>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl MockService {
+        fn new(result: Option<&'static str>) -> Self {
+            Self {
+                result,
+                error: None,
+                calls: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn error(code: i64, message: &'static str) -> Self {
+            Self {
+                error: Some((code, message)),
+                ..Self::new(None)
+            }
+        }
+    }
+
+    impl Service<RequestPacket> for MockService {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = Pin<Box<dyn Future<Output = Result<ResponsePacket, TransportError>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let result = self.result;
+            let error = self.error;
+            Box::pin(async move {
+                let RequestPacket::Single(request) = request else {
+                    unimplemented!()
+                };
+                if let Some((code, message)) = error {
+                    return Ok(ResponsePacket::Single(Response {
+                        id: request.id().clone(),
+                        payload: ResponsePayload::Failure(ErrorPayload {
+                            code,
+                            message: message.into(),
+                            data: None,
+                        }),
+                    }));
+                }
+                match result {
+                    Some(result) => Ok(ResponsePacket::Single(Response {
+                        id: request.id().clone(),
+                        payload: ResponsePayload::Success(
+                            RawValue::from_string(result.to_string()).unwrap(),
+                        ),
+                    })),
+                    None => Err(TransportErrorKind::custom_str("connection refused")),
+                }
+            })
+        }
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        let request: SerializedRequest =
+            Request::new(method, Id::Number(1), ()).serialize().unwrap();
+        RequestPacket::Single(request)
+    }
+
+    fn result(response: ResponsePacket) -> String {
+        let ResponsePacket::Single(response) = response else {
+            panic!("expected single response");
+        };
+        let ResponsePayload::Success(raw) = response.payload else {
+            panic!("expected success");
+        };
+        raw.get().to_string()
+    }
+
+    fn transport(
+        config: MultiNodeConfig,
+        services: Vec<MockService>,
+    ) -> MultiNodeTransport<MockService> {
+        let services = services
+            .into_iter()
+            .enumerate()
+            .map(|(i, service)| (format!("node{i}"), service))
+            .collect();
+        let transport = MultiNodeTransport::new(&config, services);
+        // no head checks during tests
+        *transport.inner.last_head_check.lock().unwrap() = Some(Instant::now());
+        transport
+    }
+
+    fn config(endpoints: usize) -> MultiNodeConfig {
+        MultiNodeConfig {
+            endpoints: (0..endpoints)
+                .map(|i| NodeEndpointConfig {
+                    url: format!("http://node{i}"),
+                    methods: None,
+                    exclude_methods: vec![],
+                })
+                .collect(),
+            ..MultiNodeConfig::single("")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let failing = MockService::new(None);
+        let working = MockService::new(Some("\"0x1\""));
+        let mut transport = transport(config(2), vec![failing.clone(), working.clone()]);
+
+        let response = transport.call(request("eth_chainId")).await.unwrap();
+        assert_eq!(result(response), "\"0x1\"");
+
+        // the failed endpoint is skipped during its cooldown
+        transport.call(request("eth_chainId")).await.unwrap();
+        assert_eq!(failing.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(working.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_all_endpoints_fail() {
+        let mut transport = transport(
+            config(2),
+            vec![MockService::new(None), MockService::new(None)],
+        );
+        assert!(transport.call(request("eth_chainId")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_fails_over_on_node_error() {
+        let lagging = MockService::error(-32000, "header not found");
+        let limited = MockService::error(429, "too many requests");
+        let working = MockService::new(Some("\"0x1\""));
+        let mut transport = transport(
+            config(3),
+            vec![lagging.clone(), limited.clone(), working.clone()],
+        );
+
+        let response = transport
+            .call(request("eth_getBlockByNumber"))
+            .await
+            .unwrap();
+        assert_eq!(result(response), "\"0x1\"");
+        assert_eq!(lagging.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(limited.calls.load(Ordering::SeqCst), 1);
+
+        // the endpoints are skipped during their cooldown
+        transport.call(request("eth_call")).await.unwrap();
+        assert_eq!(lagging.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(working.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_errors_not_retried() {
+        let working = MockService::new(Some("\"0x\""));
+        let mut reads = transport(
+            config(2),
+            vec![MockService::error(3, "execution reverted"), working.clone()],
+        );
+        let mut sends = transport(
+            config(2),
+            vec![
+                MockService::error(-32005, "limit exceeded"),
+                working.clone(),
+            ],
+        );
+
+        // errors that every node would return are returned as is
+        let response = reads.call(request("eth_call")).await.unwrap();
+        assert!(response.as_error().is_some());
+        // as are node errors of submissions
+        let response = sends.call(request("eth_sendRawTransaction")).await.unwrap();
+        assert!(response.as_error().is_some());
+
+        assert_eq!(working.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_node_error_returned_when_all_fail() {
+        let mut transport = transport(
+            config(2),
+            vec![
+                MockService::error(-32000, "header not found"),
+                MockService::error(-32000, "header not found"),
+            ],
+        );
+        let response = transport.call(request("eth_getLogs")).await.unwrap();
+        assert_eq!(response.as_error().unwrap().message, "header not found");
+    }
+
+    #[tokio::test]
+    async fn test_method_routing() {
+        let mut config = config(2);
+        config.endpoints[0].exclude_methods = vec!["debug_traceCall".to_string()];
+        config.endpoints[1].methods = Some(vec!["debug_traceCall".to_string()]);
+        let mut transport = transport(
+            config,
+            vec![
+                MockService::new(Some("\"primary\"")),
+                MockService::new(Some("\"tracer\"")),
+            ],
+        );
+
+        let response = transport.call(request("debug_traceCall")).await.unwrap();
+        assert_eq!(result(response), "\"tracer\"");
+        let response = transport.call(request("eth_call")).await.unwrap();
+        assert_eq!(result(response), "\"primary\"");
+    }
+
+    #[test]
+    fn test_head_lag() {
+        let transport = transport(
+            config(2),
+            vec![MockService::new(None), MockService::new(None)],
+        );
+        transport.inner.endpoints[0].state.lock().unwrap().head = Some(100);
+        transport.inner.endpoints[1].state.lock().unwrap().head = Some(110);
+
+        // the lagging endpoint is tried last
+        assert_eq!(
+            transport.inner.candidates(&["eth_call".to_string()]),
+            vec![1, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quorum() {
+        let mut config = config(3);
+        config.quorum = Some(2);
+        let mut transport = transport(
+            config,
+            vec![
+                MockService::new(Some(r#"{"hash":"0x01","number":"0x1"}"#)),
+                MockService::new(Some(r#"{"hash":"0x02","number":"0x1"}"#)),
+                MockService::new(Some(r#"{"hash":"0x02","number":"0x1","extra":true}"#)),
+            ],
+        );
+
+        let response = transport
+            .call(request("eth_getBlockByNumber"))
+            .await
+            .unwrap();
+        assert!(result(response).contains("0x02"));
+    }
+
+    #[tokio::test]
+    async fn test_quorum_not_reached() {
+        let mut config = config(2);
+        config.quorum = Some(2);
+        let mut transport = transport(
+            config,
+            vec![
+                MockService::new(Some(r#"{"hash":"0x01"}"#)),
+                MockService::new(Some(r#"{"hash":"0x02"}"#)),
+            ],
+        );
+
+        assert!(transport.call(request("eth_getLogs")).await.is_err());
+        // other methods don't require a quorum
+        assert!(transport.call(request("eth_call")).await.is_ok());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(config(2).validate().is_ok());
+        assert!(config(0).validate().is_err());
+        let mut config = config(2);
+        config.quorum = Some(3);
+        assert!(config.validate().is_err());
+    }
+}
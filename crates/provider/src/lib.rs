@@ -31,7 +31,9 @@ pub use alloy::{
         },
     },
     evm::AlloyEvmProvider,
-    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider,
+    multi_node::{MultiNodeConfig, MultiNodeTransport, NodeEndpointConfig},
+    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_multi_node_provider,
+    new_alloy_provider, subscribe_new_heads,
 };
 
 mod traits;
@@ -71,6 +73,9 @@ pub trait Providers: Send + Sync + Clone {
     /// The entry point provider for v0.7.
     type EntryPointV0_7: EntryPointProvider<UserOperationV0_7> + Clone;
 
+    /// The entry point provider for v0.8, which uses the v0.7 user operation type.
+    type EntryPointV0_8: EntryPointProvider<UserOperationV0_7> + Clone;
+
     /// The DA gas oracle sync provider.
     type DAGasOracleSync: DAGasOracleSync + Clone;
 
@@ -83,6 +88,9 @@ pub trait Providers: Send + Sync + Clone {
     /// Returns the entry point provider for v0.7.
     fn ep_v0_7(&self) -> &Option<Self::EntryPointV0_7>;
 
+    /// Returns the entry point provider for v0.8.
+    fn ep_v0_8(&self) -> &Option<Self::EntryPointV0_8>;
+
     /// Returns the DA gas oracle sync provider.
     fn da_gas_oracle_sync(&self) -> &Option<Self::DAGasOracleSync>;
 
@@ -125,6 +133,26 @@ pub trait Providers: Send + Sync + Clone {
             _phantom: PhantomData,
         })
     }
+
+    /// Returns the providers with the entry point for v0.8.
+    #[allow(clippy::type_complexity)]
+    fn ep_v0_8_providers(
+        &self,
+    ) -> Option<
+        ProvidersWithEntryPoint<
+            UserOperationV0_7,
+            Self::Evm,
+            Self::EntryPointV0_8,
+            Self::DAGasOracleSync,
+        >,
+    > {
+        self.ep_v0_8().as_ref().map(|ep| ProvidersWithEntryPoint {
+            evm: self.evm().clone(),
+            ep: ep.clone(),
+            da_gas_oracle_sync: self.da_gas_oracle_sync().clone(),
+            _phantom: PhantomData,
+        })
+    }
 }
 
 /// Trait for providers with a specific entry point.
@@ -144,6 +144,34 @@ pub trait BundleHandler: Send + Sync {
         gas_limit: Option<u64>,
     ) -> ProviderResult<HandleOpsOut>;
 
+    /// Call the entry point contract's `handleOps` function on top of a pending state,
+    /// constructed by first executing `pending_txs` in order on the latest block.
+    ///
+    /// Requires the node to support `eth_simulateV1`.
+    /// If `gas_limit` is `None`, the maximum gas limit is used.
+    async fn call_handle_ops_pending(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        pending_txs: Vec<TransactionRequest>,
+    ) -> ProviderResult<HandleOpsOut>;
+
+    /// Call the entry point contract's `handleOps` function on top of `state_override` at
+    /// `block_id`, and return `state_override` updated with the state changes of the call,
+    /// so that later calls see the state left by the operations.
+    ///
+    /// Requires the node to support the `prestateTracer` of `debug_traceCall`.
+    /// If `gas_limit` is `None`, the maximum gas limit is used.
+    async fn get_handle_ops_state_override(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> ProviderResult<StateOverride>;
+
     /// Construct the transaction to send a bundle of operations to the entry point contract
     fn get_send_bundle_transaction(
         &self,
@@ -186,20 +214,30 @@ pub trait SimulationProvider: Send + Sync {
     type UO: UserOperation;
 
     /// Construct a call for the entry point contract's `simulateValidation` function
+    /// on top of the given state overrides
     fn get_tracer_simulate_validation_call(
         &self,
         user_op: Self::UO,
+        state_override: StateOverride,
     ) -> ProviderResult<(TransactionRequest, StateOverride)>;
 
-    /// Call the entry point contract's `simulateValidation` function.
+    /// Call the entry point contract's `simulateValidation` function
+    /// with a spoofed state
     async fn simulate_validation(
         &self,
         user_op: Self::UO,
         block_id: Option<BlockId>,
+        state_override: StateOverride,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
 
     /// Get call data and state overrides needed to call `simulateHandleOp`
-    fn get_simulate_handle_op_call(&self, op: Self::UO, state_override: StateOverride) -> EvmCall;
+    fn get_simulate_handle_op_call(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        state_override: StateOverride,
+    ) -> EvmCall;
 
     /// Call the entry point contract's `simulateHandleOp` function
     /// with a spoofed state
@@ -217,6 +255,11 @@ pub trait SimulationProvider: Send + Sync {
         revert_data: &Bytes,
     ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
 
+    /// Decode the return data from a successful call to `simulateHandleOp`
+    fn decode_simulate_handle_op_output(
+        output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+
     /// Returns true if this entry point uses reverts to communicate simulation
     /// results.
     fn simulation_should_revert(&self) -> bool;
@@ -40,5 +40,20 @@ impl From<ContractError> for ProviderError {
     }
 }
 
+/// JSON-RPC error code for a method that does not exist or is not available
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+impl ProviderError {
+    /// Returns true if the node does not support the called RPC method
+    pub fn is_method_unsupported(&self) -> bool {
+        match self {
+            ProviderError::RPC(TransportError::ErrorResp(resp)) => {
+                resp.code == METHOD_NOT_FOUND_CODE || resp.message.contains("not supported")
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Result of a provider method call
 pub type ProviderResult<T> = Result<T, ProviderError>;
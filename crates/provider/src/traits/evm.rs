@@ -109,6 +109,21 @@ pub trait EvmProvider: Send + Sync {
     /// Get the nonce/transaction count of an address
     async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64>;
 
+    /// Get the nonce/transaction count of an address at a given block
+    async fn get_transaction_count_at(
+        &self,
+        address: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<u64>;
+
+    /// Get the storage value at a given address and slot
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: U256,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256>;
+
     /// Get the logs matching a filter
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>>;
 
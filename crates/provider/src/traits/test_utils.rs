@@ -93,6 +93,19 @@ mockall::mock! {
 
         async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64>;
 
+        async fn get_transaction_count_at(
+            &self,
+            address: Address,
+            block: Option<BlockId>,
+        ) -> ProviderResult<u64>;
+
+        async fn get_storage_at(
+            &self,
+            address: Address,
+            slot: U256,
+            block: Option<BlockId>,
+        ) -> ProviderResult<U256>;
+
         async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>>;
 
         async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult>;
@@ -144,15 +157,19 @@ mockall::mock! {
         fn get_tracer_simulate_validation_call(
             &self,
             user_op: v0_6::UserOperation,
+            state_override: StateOverride,
         ) -> ProviderResult<(TransactionRequest, StateOverride)>;
         async fn simulate_validation(
             &self,
             user_op: v0_6::UserOperation,
-            block_id: Option<BlockId>
+            block_id: Option<BlockId>,
+            state_override: StateOverride,
         ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
         fn get_simulate_handle_op_call(
             &self,
             op: v0_6::UserOperation,
+            target: Address,
+            target_call_data: Bytes,
             state_override: StateOverride,
         ) -> crate::EvmCall;
         async fn simulate_handle_op(
@@ -166,6 +183,9 @@ mockall::mock! {
         fn decode_simulate_handle_ops_revert(
             revert_data: &Bytes,
         ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+        fn decode_simulate_handle_op_output(
+            output: &Bytes,
+        ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
         fn simulation_should_revert(&self) -> bool;
     }
 
@@ -189,6 +209,21 @@ mockall::mock! {
             beneficiary: Address,
             gas_limit: Option<u64>,
         ) -> ProviderResult<HandleOpsOut>;
+        async fn call_handle_ops_pending(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
+            beneficiary: Address,
+            gas_limit: Option<u64>,
+            pending_txs: Vec<TransactionRequest>,
+        ) -> ProviderResult<HandleOpsOut>;
+        async fn get_handle_ops_state_override(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
+            beneficiary: Address,
+            gas_limit: Option<u64>,
+            block_id: BlockId,
+            state_override: StateOverride,
+        ) -> ProviderResult<StateOverride>;
         fn get_send_bundle_transaction(
             &self,
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
@@ -234,15 +269,19 @@ mockall::mock! {
         fn get_tracer_simulate_validation_call(
             &self,
             user_op: v0_7::UserOperation,
+            state_override: StateOverride,
         ) -> ProviderResult<(TransactionRequest, StateOverride)>;
         async fn simulate_validation(
             &self,
             user_op: v0_7::UserOperation,
-            block_id: Option<BlockId>
+            block_id: Option<BlockId>,
+            state_override: StateOverride,
         ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
         fn get_simulate_handle_op_call(
             &self,
             op: v0_7::UserOperation,
+            target: Address,
+            target_call_data: Bytes,
             state_override: StateOverride,
         ) -> crate::EvmCall;
         async fn simulate_handle_op(
@@ -256,6 +295,9 @@ mockall::mock! {
         fn decode_simulate_handle_ops_revert(
             revert_data: &Bytes,
         ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+        fn decode_simulate_handle_op_output(
+            output: &Bytes,
+        ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
         fn simulation_should_revert(&self) -> bool;
     }
 
@@ -279,6 +321,21 @@ mockall::mock! {
             beneficiary: Address,
             gas_limit: Option<u64>,
         ) -> ProviderResult<HandleOpsOut>;
+        async fn call_handle_ops_pending(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
+            beneficiary: Address,
+            gas_limit: Option<u64>,
+            pending_txs: Vec<TransactionRequest>,
+        ) -> ProviderResult<HandleOpsOut>;
+        async fn get_handle_ops_state_override(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
+            beneficiary: Address,
+            gas_limit: Option<u64>,
+            block_id: BlockId,
+            state_override: StateOverride,
+        ) -> ProviderResult<StateOverride>;
         fn get_send_bundle_transaction(
             &self,
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
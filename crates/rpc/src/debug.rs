@@ -16,15 +16,19 @@ use anyhow::Context;
 use async_trait::async_trait;
 use futures_util::StreamExt;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use rundler_provider::StateOverride;
 use rundler_types::{
     builder::{Builder, BundlingMode},
+    chain::ChainSpec,
     pool::Pool,
+    UserOperationVariant,
 };
 
 use crate::{
+    eth::{EntryPointRouter, EthResult},
     types::{
-        RpcDebugPaymasterBalance, RpcReputationInput, RpcReputationOutput, RpcStakeInfo,
-        RpcStakeStatus, RpcUserOperation,
+        FromRpc, RpcDebugPaymasterBalance, RpcReputationInput, RpcReputationOutput, RpcStakeInfo,
+        RpcStakeStatus, RpcUserOperation, RpcValidationTrace,
     },
     utils::{self, InternalRpcResult},
 };
@@ -87,16 +91,40 @@ pub trait DebugApi {
     /// Clear the reputations of pool.
     #[method(name = "bundler_clearReputation")]
     async fn bundler_clear_reputation(&self) -> RpcResult<String>;
+
+    /// Traces the validation of a user operation, returning the tracer output of
+    /// each phase and every rule violation found.
+    ///
+    /// The operation is traced on the latest block, on top of the optional state overrides.
+    #[method(name = "bundler_traceUserOperationValidation")]
+    async fn bundler_trace_user_operation_validation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<RpcValidationTrace>;
 }
 
 pub(crate) struct DebugApi<P, B> {
+    chain_spec: ChainSpec,
     pool: P,
     builder: B,
+    entry_point_router: EntryPointRouter,
 }
 
 impl<P, B> DebugApi<P, B> {
-    pub(crate) fn new(pool: P, builder: B) -> Self {
-        Self { pool, builder }
+    pub(crate) fn new(
+        chain_spec: ChainSpec,
+        pool: P,
+        builder: B,
+        entry_point_router: EntryPointRouter,
+    ) -> Self {
+        Self {
+            chain_spec,
+            pool,
+            builder,
+            entry_point_router,
+        }
     }
 }
 
@@ -196,6 +224,24 @@ where
         )
         .await
     }
+
+    async fn bundler_trace_user_operation_validation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<RpcValidationTrace> {
+        utils::safe_call_rpc_handler(
+            "bundler_traceUserOperationValidation",
+            DebugApi::bundler_trace_user_operation_validation(
+                self,
+                op,
+                entry_point,
+                state_override,
+            ),
+        )
+        .await
+    }
 }
 
 impl<P, B> DebugApi<P, B>
@@ -386,4 +432,19 @@ where
 
         Ok("ok".to_string())
     }
+
+    async fn bundler_trace_user_operation_validation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> EthResult<RpcValidationTrace> {
+        let uo = UserOperationVariant::from_rpc(op, &entry_point, &self.chain_spec);
+
+        Ok(self
+            .entry_point_router
+            .trace_validation(&entry_point, uo, state_override)
+            .await?
+            .into())
+    }
 }
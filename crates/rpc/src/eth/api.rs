@@ -18,6 +18,7 @@ use futures_util::future;
 use rundler_provider::StateOverride;
 use rundler_types::{
     chain::ChainSpec, pool::Pool, UserOperation, UserOperationOptionalGas, UserOperationVariant,
+    BUNDLE_BYTE_OVERHEAD, USER_OP_OFFSET_WORD_SIZE,
 };
 use rundler_utils::log::LogOnError;
 use tracing::Level;
@@ -84,6 +85,34 @@ where
             .log_on_error_level(Level::DEBUG, "failed to add op to the mempool")
     }
 
+    pub(crate) async fn send_user_operation_group(
+        &self,
+        ops: Vec<UserOperationVariant>,
+        entry_point: Address,
+    ) -> EthResult<Vec<B256>> {
+        let bundle_size = BUNDLE_BYTE_OVERHEAD
+            + ops
+                .iter()
+                .map(|op| op.abi_encoded_size() + USER_OP_OFFSET_WORD_SIZE)
+                .sum::<usize>();
+        if bundle_size > self.chain_spec.max_transaction_size_bytes {
+            return Err(EthRpcError::InvalidParams(format!(
+                "User operation group in bundle size {} exceeds max transaction size {}",
+                bundle_size, self.chain_spec.max_transaction_size_bytes
+            )));
+        }
+
+        for op in &ops {
+            self.router.check_and_get_route(&entry_point, op)?;
+        }
+
+        self.pool
+            .add_op_group(entry_point, ops)
+            .await
+            .map_err(EthRpcError::from)
+            .log_on_error_level(Level::DEBUG, "failed to add op group to the mempool")
+    }
+
     pub(crate) async fn estimate_user_operation_gas(
         &self,
         op: UserOperationOptionalGas,
@@ -218,6 +247,8 @@ mod tests {
             account_is_staked: false,
             entity_infos: EntityInfos::default(),
             da_gas_data: rundler_types::da::DAGasUOData::Empty,
+            atomic_group: None,
+            mempools: vec![],
         };
 
         let mut pool = MockPool::default();
@@ -365,7 +396,12 @@ mod tests {
             .v0_6(EntryPointRouteImpl::new(
                 ep.clone(),
                 gas_estimator,
-                UserOperationEventProviderV0_6::new(chain_spec.clone(), provider.clone(), None),
+                UserOperationEventProviderV0_6::new(
+                    chain_spec.clone(),
+                    provider.clone(),
+                    None,
+                    None,
+                ),
             ))
             .build();
 
@@ -23,6 +23,7 @@ use rundler_provider::ProviderError;
 use rundler_sim::GasEstimationError;
 use rundler_types::{
     pool::{MempoolError, PoolError, PrecheckViolation, SimulationViolation},
+    revert::{RevertDecoder, RevertPhase, RevertSource},
     Entity, EntityType, Opcode, Timestamp, ValidationRevert,
 };
 use serde::Serialize;
@@ -130,12 +131,14 @@ pub enum EthRpcError {
     SimulationFailed(SimulationViolation),
     #[error("validation reverted: {0}")]
     ValidationRevert(ValidationRevertData),
-    #[error("{0}")]
-    ExecutionReverted(String),
-    #[error("execution reverted")]
-    ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData),
+    #[error("{}", .0.reason.as_deref().unwrap_or("execution reverted"))]
+    ExecutionReverted(ExecutionRevertedData),
     #[error("operation rejected by mempool: {0}")]
     OperationRejected(String),
+    /// The paymaster service declined to sponsor the operation, the service's
+    /// error is returned to the caller as-is
+    #[error("paymaster service error: {}", .0.message())]
+    PaymasterServiceRejected(ErrorObjectOwned),
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -193,6 +196,35 @@ pub struct ValidationRevertData {
     reason: Option<String>,
     inner_reason: Option<String>,
     revert_data: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity: Option<EntityType>,
+    phase: RevertPhase,
+}
+
+impl ValidationRevertData {
+    /// Converts a validation revert, decoding revert data that the entry point
+    /// could not with the custom errors known to the decoder
+    pub(crate) fn decode(revert: ValidationRevert, decoder: &RevertDecoder) -> Self {
+        let decoded = match &revert {
+            ValidationRevert::Operation {
+                inner_revert_data,
+                inner_revert_reason: None,
+                ..
+            } => decoder.decode_reason(inner_revert_data),
+            ValidationRevert::Unknown(data) => decoder.decode_reason(data),
+            _ => None,
+        };
+
+        let mut data = Self::from(revert);
+        if let Some(decoded) = decoded {
+            if data.reason.is_none() {
+                data.reason = Some(decoded);
+            } else {
+                data.inner_reason = Some(decoded);
+            }
+        }
+        data
+    }
 }
 
 impl Display for ValidationRevertData {
@@ -209,11 +241,14 @@ impl Display for ValidationRevertData {
 
 impl From<ValidationRevert> for ValidationRevertData {
     fn from(value: ValidationRevert) -> Self {
+        let RevertSource { entity, phase } = RevertSource::from_validation_revert(&value);
         match value {
             ValidationRevert::EntryPoint(reason) => Self {
                 reason: Some(reason),
                 inner_reason: None,
                 revert_data: None,
+                entity,
+                phase,
             },
             ValidationRevert::Operation {
                 entry_point_reason,
@@ -223,16 +258,22 @@ impl From<ValidationRevert> for ValidationRevertData {
                 reason: Some(entry_point_reason),
                 inner_reason: inner_revert_reason,
                 revert_data: Some(inner_revert_data),
+                entity,
+                phase,
             },
             ValidationRevert::Unknown(data) => Self {
                 reason: None,
                 inner_reason: None,
                 revert_data: Some(data),
+                entity,
+                phase,
             },
             ValidationRevert::Panic(data) => Self {
                 reason: Some(format!("evm panicked: {}", data.code)),
                 inner_reason: None,
                 revert_data: None,
+                entity,
+                phase,
             },
         }
     }
@@ -261,8 +302,26 @@ pub struct UnsupportedAggregatorData {
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ExecutionRevertedWithBytesData {
-    pub revert_data: Bytes,
+pub struct ExecutionRevertedData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_data: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<EntityType>,
+    pub phase: RevertPhase,
+}
+
+impl ExecutionRevertedData {
+    fn new(reason: Option<String>, revert_data: Option<Bytes>) -> Self {
+        let RevertSource { entity, phase } = RevertSource::EXECUTION;
+        Self {
+            reason,
+            revert_data,
+            entity,
+            phase,
+        }
+    }
 }
 
 impl From<PoolError> for EthRpcError {
@@ -317,6 +376,9 @@ impl From<MempoolError> for EthRpcError {
             MempoolError::CallGasLimitEfficiencyTooLow(_, _) => {
                 Self::InvalidParams(value.to_string())
             }
+            MempoolError::InvalidAtomicGroup(_) | MempoolError::AtomicGroupReplacement => {
+                Self::InvalidParams(value.to_string())
+            }
         }
     }
 }
@@ -426,14 +488,14 @@ impl From<EthRpcError> for ErrorObjectOwned {
                 rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg)
             }
             EthRpcError::PrecheckFailed(_) => rpc_err(CALL_EXECUTION_FAILED_CODE, msg),
-            EthRpcError::ExecutionReverted(_) => rpc_err(EXECUTION_REVERTED, msg),
-            EthRpcError::ExecutionRevertedWithBytes(data) => {
+            EthRpcError::ExecutionReverted(data) => {
                 rpc_err_with_data(EXECUTION_REVERTED, msg, data)
             }
             EthRpcError::ValidationRevert(data) => {
                 rpc_err_with_data(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg, data)
             }
             EthRpcError::OperationRejected(_) => rpc_err(INVALID_PARAMS_CODE, msg),
+            EthRpcError::PaymasterServiceRejected(error) => error,
         }
     }
 }
@@ -501,15 +563,32 @@ impl From<ProviderError> for ProviderErrorWithContext {
         }
     }
 }
+
+impl EthRpcError {
+    /// Converts a gas estimation error, decoding reverts with the custom errors
+    /// known to the decoder
+    pub(crate) fn from_estimation_error(e: GasEstimationError, decoder: &RevertDecoder) -> Self {
+        match e {
+            GasEstimationError::RevertInValidation(revert) => {
+                Self::ValidationRevert(ValidationRevertData::decode(revert, decoder))
+            }
+            GasEstimationError::RevertInCallWithBytes(b) => Self::ExecutionReverted(
+                ExecutionRevertedData::new(decoder.decode_reason(&b), Some(b)),
+            ),
+            e => e.into(),
+        }
+    }
+}
+
 impl From<GasEstimationError> for EthRpcError {
     fn from(e: GasEstimationError) -> Self {
         match e {
             GasEstimationError::RevertInValidation(revert) => Self::ValidationRevert(revert.into()),
             GasEstimationError::RevertInCallWithMessage(message) => {
-                Self::ExecutionReverted(message)
+                Self::ExecutionReverted(ExecutionRevertedData::new(Some(message), None))
             }
             GasEstimationError::RevertInCallWithBytes(b) => {
-                Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData { revert_data: b })
+                Self::ExecutionReverted(ExecutionRevertedData::new(None, Some(b)))
             }
             error @ GasEstimationError::GasUsedTooLarge => {
                 Self::EntryPointValidationRejected(error.to_string())
@@ -520,6 +599,9 @@ impl From<GasEstimationError> for EthRpcError {
             error @ GasEstimationError::GasFieldTooLarge(_, _) => {
                 Self::InvalidParams(error.to_string())
             }
+            GasEstimationError::UnsupportedAggregator(a) => {
+                Self::UnsupportedAggregator(UnsupportedAggregatorData { aggregator: a })
+            }
             GasEstimationError::ProviderError(provider_error) => {
                 EthRpcError::from(ProviderErrorWithContext::from(provider_error))
             }
@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::VecDeque, marker::PhantomData};
+use std::{collections::VecDeque, marker::PhantomData, sync::Arc};
 
 use alloy_consensus::Transaction;
 use alloy_primitives::{Address, Bytes, B256, U256};
@@ -21,7 +21,7 @@ use rundler_provider::{
     EvmProvider, Filter, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingOptions,
     GethTrace, Log, TransactionReceipt,
 };
-use rundler_types::{chain::ChainSpec, UserOperation, UserOperationVariant};
+use rundler_types::{chain::ChainSpec, pool::Pool, UserOperation, UserOperationVariant};
 use rundler_utils::log::LogOnError;
 
 use super::UserOperationEventProvider;
@@ -32,6 +32,8 @@ pub(crate) struct UserOperationEventProviderImpl<P, F> {
     chain_spec: ChainSpec,
     provider: P,
     event_block_distance: Option<u64>,
+    // Pool whose mined operation index is checked before scanning logs
+    pool: Option<Arc<dyn Pool>>,
     _f_type: PhantomData<F>,
 }
 
@@ -164,16 +166,27 @@ where
         chain_spec: ChainSpec,
         provider: P,
         event_block_distance: Option<u64>,
+        pool: Option<Arc<dyn Pool>>,
     ) -> Self {
         Self {
             chain_spec,
             provider,
             event_block_distance,
+            pool,
             _f_type: PhantomData,
         }
     }
 
     async fn get_event_by_hash(&self, hash: B256) -> anyhow::Result<Option<Log>> {
+        // A failed index lookup is not fatal, the logs are scanned instead
+        if let Ok(Some(log)) = self
+            .get_indexed_event_by_hash(hash)
+            .await
+            .log_on_error("should have looked up user op event in the pool's index")
+        {
+            return Ok(Some(log));
+        }
+
         let to_block = self.provider.get_block_number().await?;
 
         let from_block = match self.event_block_distance {
@@ -192,6 +205,44 @@ where
         Ok(logs.into_iter().next())
     }
 
+    /// Finds the event using the pool's mined operation index. Returns `None` if the
+    /// operation is not indexed, or if the indexed transaction is no longer in the
+    /// indexed block.
+    async fn get_indexed_event_by_hash(&self, hash: B256) -> anyhow::Result<Option<Log>> {
+        let Some(pool) = &self.pool else {
+            return Ok(None);
+        };
+        let entry_point = E::address(&self.chain_spec);
+        let Some(location) = pool.get_mined_op_location(hash).await? else {
+            return Ok(None);
+        };
+        if location.entry_point != entry_point {
+            return Ok(None);
+        }
+
+        let Some(tx_receipt) = self
+            .provider
+            .get_transaction_receipt(location.transaction_hash)
+            .await?
+        else {
+            return Ok(None);
+        };
+        if tx_receipt.block_hash != Some(location.block_hash) {
+            return Ok(None);
+        }
+
+        Ok(tx_receipt
+            .inner
+            .logs()
+            .iter()
+            .find(|log| {
+                log.address() == entry_point
+                    && log.topics().first() == Some(&E::UserOperationEvent::SIGNATURE_HASH)
+                    && log.topics().get(1) == Some(&hash)
+            })
+            .cloned())
+    }
+
     fn decode_user_operation_event(&self, log: Log) -> anyhow::Result<E::UserOperationEvent> {
         log.log_decode::<E::UserOperationEvent>()
             .map(|l| l.inner.data)
@@ -23,6 +23,8 @@ mod v0_6;
 pub(crate) use v0_6::UserOperationEventProviderV0_6;
 mod v0_7;
 pub(crate) use v0_7::UserOperationEventProviderV0_7;
+mod v0_8;
+pub(crate) use v0_8::UserOperationEventProviderV0_8;
 
 #[async_trait::async_trait]
 pub(crate) trait UserOperationEventProvider: Send + Sync {
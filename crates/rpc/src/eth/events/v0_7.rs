@@ -20,6 +20,7 @@ use rundler_provider::{Log, TransactionReceipt};
 use rundler_types::{
     chain::ChainSpec,
     v0_7::{UserOperation, UserOperationBuilder},
+    EntryPointVersion,
 };
 
 use super::common::{EntryPointEvents, UserOperationEventProviderImpl};
@@ -77,39 +78,48 @@ impl EntryPointEvents for EntryPointFiltersV0_7 {
     }
 
     fn get_user_operations_from_tx_data(tx_data: Bytes, chain_spec: &ChainSpec) -> Vec<Self::UO> {
-        let entry_point_calls = match IEntryPointCalls::abi_decode(&tx_data, false) {
-            Ok(entry_point_calls) => entry_point_calls,
-            Err(_) => return vec![],
-        };
-
-        match entry_point_calls {
-            IEntryPointCalls::handleOps(handle_ops_call) => handle_ops_call
-                .ops
-                .into_iter()
-                .filter_map(|op| {
-                    UserOperationBuilder::from_packed(op, chain_spec)
-                        .ok()
-                        .map(|uo| uo.build())
-                })
-                .collect(),
-            IEntryPointCalls::handleAggregatedOps(handle_aggregated_ops_call) => {
-                handle_aggregated_ops_call
-                    .opsPerAggregator
-                    .into_iter()
-                    .flat_map(|ops| {
-                        ops.userOps.into_iter().filter_map(|op| {
-                            UserOperationBuilder::from_packed(op, chain_spec)
-                                .ok()
-                                .map(|uo| uo.build())
-                        })
-                    })
-                    .collect()
-            }
-            _ => vec![],
-        }
+        user_operations_from_tx_data(tx_data, chain_spec, EntryPointVersion::V0_7)
     }
 
     fn address(chain_spec: &ChainSpec) -> Address {
         chain_spec.entry_point_address_v0_7
     }
 }
+
+// Shared with v0.8, which keeps the v0.7 calls
+pub(super) fn user_operations_from_tx_data(
+    tx_data: Bytes,
+    chain_spec: &ChainSpec,
+    entry_point_version: EntryPointVersion,
+) -> Vec<UserOperation> {
+    let entry_point_calls = match IEntryPointCalls::abi_decode(&tx_data, false) {
+        Ok(entry_point_calls) => entry_point_calls,
+        Err(_) => return vec![],
+    };
+
+    match entry_point_calls {
+        IEntryPointCalls::handleOps(handle_ops_call) => handle_ops_call
+            .ops
+            .into_iter()
+            .filter_map(|op| {
+                UserOperationBuilder::from_packed(op, chain_spec)
+                    .ok()
+                    .map(|uo| uo.entry_point_version(entry_point_version).build())
+            })
+            .collect(),
+        IEntryPointCalls::handleAggregatedOps(handle_aggregated_ops_call) => {
+            handle_aggregated_ops_call
+                .opsPerAggregator
+                .into_iter()
+                .flat_map(|ops| {
+                    ops.userOps.into_iter().filter_map(|op| {
+                        UserOperationBuilder::from_packed(op, chain_spec)
+                            .ok()
+                            .map(|uo| uo.entry_point_version(entry_point_version).build())
+                    })
+                })
+                .collect()
+        }
+        _ => vec![],
+    }
+}
@@ -0,0 +1,53 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes, B256};
+use rundler_contracts::v0_7::IEntryPoint::{UserOperationEvent, UserOperationRevertReason};
+use rundler_provider::{Log, TransactionReceipt};
+use rundler_types::{chain::ChainSpec, v0_7::UserOperation, EntryPointVersion};
+
+use super::{
+    common::{EntryPointEvents, UserOperationEventProviderImpl},
+    v0_7::{self, EntryPointFiltersV0_7},
+};
+use crate::types::RpcUserOperationReceipt;
+
+pub(crate) type UserOperationEventProviderV0_8<P> =
+    UserOperationEventProviderImpl<P, EntryPointFiltersV0_8>;
+
+/// Entry point v0.8 emits the v0.7 events and accepts the v0.7 calls
+pub(crate) struct EntryPointFiltersV0_8;
+
+impl EntryPointEvents for EntryPointFiltersV0_8 {
+    type UO = UserOperation;
+    type UserOperationEvent = UserOperationEvent;
+    type UserOperationRevertReason = UserOperationRevertReason;
+
+    fn construct_receipt(
+        event: Self::UserOperationEvent,
+        hash: B256,
+        entry_point: Address,
+        logs: Vec<Log>,
+        tx_receipt: TransactionReceipt,
+    ) -> RpcUserOperationReceipt {
+        EntryPointFiltersV0_7::construct_receipt(event, hash, entry_point, logs, tx_receipt)
+    }
+
+    fn get_user_operations_from_tx_data(tx_data: Bytes, chain_spec: &ChainSpec) -> Vec<Self::UO> {
+        v0_7::user_operations_from_tx_data(tx_data, chain_spec, EntryPointVersion::V0_8)
+    }
+
+    fn address(chain_spec: &ChainSpec) -> Address {
+        chain_spec.entry_point_address_v0_8
+    }
+}
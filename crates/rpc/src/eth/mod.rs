@@ -21,8 +21,13 @@ pub(crate) use router::*;
 mod error;
 pub(crate) use error::{EthResult, EthRpcError};
 mod events;
-pub(crate) use events::{UserOperationEventProviderV0_6, UserOperationEventProviderV0_7};
+pub(crate) use events::{
+    UserOperationEventProviderV0_6, UserOperationEventProviderV0_7, UserOperationEventProviderV0_8,
+};
 mod server;
+mod subscription;
+pub use subscription::EthSubscriptionApiClient;
+pub(crate) use subscription::{EthSubscriptionApi, EthSubscriptionApiServer};
 
 use alloy_primitives::{Address, B256, U64};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
@@ -45,6 +50,15 @@ pub trait EthApi {
         entry_point: Address,
     ) -> RpcResult<B256>;
 
+    /// Sends an ordered group of user operations to the pool, to be bundled together
+    /// in order or not at all.
+    #[method(name = "sendUserOperationGroup")]
+    async fn send_user_operation_group(
+        &self,
+        ops: Vec<RpcUserOperation>,
+        entry_point: Address,
+    ) -> RpcResult<Vec<B256>>;
+
     /// Estimates the gas fields for a user operation.
     #[method(name = "estimateUserOperationGas")]
     async fn estimate_user_operation_gas(
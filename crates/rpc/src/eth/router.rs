@@ -11,13 +11,14 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+use std::{marker::PhantomData, sync::Arc};
 
 use alloy_primitives::{Address, B256};
-use rundler_provider::{EntryPoint, SimulationProvider, StateOverride};
-use rundler_sim::{GasEstimationError, GasEstimator};
+use rundler_provider::{BlockId, EntryPoint, SimulationProvider, StateOverride};
+use rundler_sim::{GasEstimationError, GasEstimator, SimulationError, Simulator, ValidationTrace};
 use rundler_types::{
-    EntryPointVersion, GasEstimate, UserOperation, UserOperationOptionalGas, UserOperationVariant,
+    pool::MempoolError, revert::RevertDecoder, EntryPointVersion, GasEstimate, UserOperation,
+    UserOperationOptionalGas, UserOperationVariant,
 };
 
 use super::events::UserOperationEventProvider;
@@ -34,9 +35,16 @@ pub(crate) struct EntryPointRouterBuilder {
     entry_points: Vec<Address>,
     v0_6: Option<(Address, Arc<dyn EntryPointRoute>)>,
     v0_7: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    v0_8: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    revert_decoder: Arc<RevertDecoder>,
 }
 
 impl EntryPointRouterBuilder {
+    pub(crate) fn revert_decoder(mut self, revert_decoder: RevertDecoder) -> Self {
+        self.revert_decoder = Arc::new(revert_decoder);
+        self
+    }
+
     pub(crate) fn v0_6<R>(mut self, route: R) -> Self
     where
         R: EntryPointRoute + 'static,
@@ -69,11 +77,29 @@ impl EntryPointRouterBuilder {
         self
     }
 
+    pub(crate) fn v0_8<R>(mut self, route: R) -> Self
+    where
+        R: EntryPointRoute + 'static,
+    {
+        if route.version() != EntryPointVersion::V0_8 {
+            panic!(
+                "Invalid entry point version for route: {:?}",
+                route.version()
+            );
+        }
+
+        self.entry_points.push(route.address());
+        self.v0_8 = Some((route.address(), Arc::new(route)));
+        self
+    }
+
     pub(crate) fn build(self) -> EntryPointRouter {
         EntryPointRouter {
             entry_points: self.entry_points,
             v0_6: self.v0_6,
             v0_7: self.v0_7,
+            v0_8: self.v0_8,
+            revert_decoder: self.revert_decoder,
         }
     }
 }
@@ -83,6 +109,8 @@ pub(crate) struct EntryPointRouter {
     entry_points: Vec<Address>,
     v0_6: Option<(Address, Arc<dyn EntryPointRoute>)>,
     v0_7: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    v0_8: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    revert_decoder: Arc<RevertDecoder>,
 }
 
 impl EntryPointRouter {
@@ -114,6 +142,15 @@ impl EntryPointRouter {
                 }
                 Ok(&self.v0_7.as_ref().unwrap().1)
             }
+            EntryPointVersion::V0_8 => {
+                if !matches!(uo, UserOperationVariant::V0_7(_)) {
+                    return Err(EthRpcError::InvalidParams(format!(
+                        "Invalid user operation for entry point: {:?}",
+                        entry_point
+                    )));
+                }
+                Ok(&self.v0_8.as_ref().unwrap().1)
+            }
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
         }
     }
@@ -146,7 +183,27 @@ impl EntryPointRouter {
         uo: UserOperationOptionalGas,
         state_override: Option<StateOverride>,
     ) -> EthResult<RpcGasEstimate> {
-        match self.get_ep_version(entry_point)? {
+        let version = self.get_ep_version(entry_point)?;
+        let e = self
+            .estimate_op_gas(entry_point, uo, state_override)
+            .await?;
+
+        match version {
+            EntryPointVersion::V0_6 => Ok(RpcGasEstimateV0_6::from(e).into()),
+            EntryPointVersion::V0_7 | EntryPointVersion::V0_8 => {
+                Ok(RpcGasEstimateV0_7::from(e).into())
+            }
+            EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
+        }
+    }
+
+    pub(crate) async fn estimate_op_gas(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationOptionalGas,
+        state_override: Option<StateOverride>,
+    ) -> EthResult<GasEstimate> {
+        let route = match self.get_ep_version(entry_point)? {
             EntryPointVersion::V0_6 => {
                 if !matches!(uo, UserOperationOptionalGas::V0_6(_)) {
                     return Err(EthRpcError::InvalidParams(format!(
@@ -154,16 +211,7 @@ impl EntryPointRouter {
                         entry_point
                     )));
                 }
-
-                let e = self
-                    .v0_6
-                    .as_ref()
-                    .unwrap()
-                    .1
-                    .estimate_gas(uo, state_override)
-                    .await?;
-
-                Ok(RpcGasEstimateV0_6::from(e).into())
+                &self.v0_6.as_ref().unwrap().1
             }
             EntryPointVersion::V0_7 => {
                 if !matches!(uo, UserOperationOptionalGas::V0_7(_)) {
@@ -172,19 +220,24 @@ impl EntryPointRouter {
                         entry_point
                     )));
                 }
-
-                let e = self
-                    .v0_7
-                    .as_ref()
-                    .unwrap()
-                    .1
-                    .estimate_gas(uo, state_override)
-                    .await?;
-
-                Ok(RpcGasEstimateV0_7::from(e).into())
+                &self.v0_7.as_ref().unwrap().1
+            }
+            EntryPointVersion::V0_8 => {
+                if !matches!(uo, UserOperationOptionalGas::V0_7(_)) {
+                    return Err(EthRpcError::InvalidParams(format!(
+                        "Invalid user operation for entry point: {:?}",
+                        entry_point
+                    )));
+                }
+                &self.v0_8.as_ref().unwrap().1
             }
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
-        }
+        };
+
+        route
+            .estimate_gas(uo, state_override)
+            .await
+            .map_err(|e| EthRpcError::from_estimation_error(e, &self.revert_decoder))
     }
 
     pub(crate) async fn check_signature(
@@ -198,6 +251,18 @@ impl EntryPointRouter {
             .map_err(Into::into)
     }
 
+    pub(crate) async fn trace_validation(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationVariant,
+        state_override: Option<StateOverride>,
+    ) -> EthResult<ValidationTrace> {
+        self.check_and_get_route(entry_point, &uo)?
+            .trace_validation(uo, state_override.unwrap_or_default())
+            .await
+            .map_err(|e| MempoolError::from(e).into())
+    }
+
     fn get_ep_version(&self, entry_point: &Address) -> EthResult<EntryPointVersion> {
         if let Some((addr, _)) = self.v0_6 {
             if addr == *entry_point {
@@ -209,6 +274,11 @@ impl EntryPointRouter {
                 return Ok(EntryPointVersion::V0_7);
             }
         }
+        if let Some((addr, _)) = self.v0_8 {
+            if addr == *entry_point {
+                return Ok(EntryPointVersion::V0_8);
+            }
+        }
 
         Err(EthRpcError::InvalidParams(format!(
             "No entry point found for address: {:?}",
@@ -222,6 +292,7 @@ impl EntryPointRouter {
         match ep {
             EntryPointVersion::V0_6 => Ok(&self.v0_6.as_ref().unwrap().1),
             EntryPointVersion::V0_7 => Ok(&self.v0_7.as_ref().unwrap().1),
+            EntryPointVersion::V0_8 => Ok(&self.v0_8.as_ref().unwrap().1),
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
         }
     }
@@ -245,13 +316,20 @@ pub(crate) trait EntryPointRoute: Send + Sync {
     ) -> Result<GasEstimate, GasEstimationError>;
 
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool>;
+
+    async fn trace_validation(
+        &self,
+        uo: UserOperationVariant,
+        state_override: StateOverride,
+    ) -> Result<ValidationTrace, SimulationError>;
 }
 
-#[derive(Debug)]
 pub(crate) struct EntryPointRouteImpl<UO, E, G, EV> {
+    version: EntryPointVersion,
     entry_point: E,
     gas_estimator: G,
     event_provider: EV,
+    simulator: Option<Arc<dyn Simulator<UO = UO>>>,
     _uo_type: PhantomData<UO>,
 }
 
@@ -265,7 +343,7 @@ where
     EV: UserOperationEventProvider,
 {
     fn version(&self) -> EntryPointVersion {
-        UO::entry_point_version()
+        self.version
     }
 
     fn address(&self) -> Address {
@@ -296,20 +374,76 @@ where
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool> {
         let output = self
             .entry_point
-            .simulate_validation(uo.into(), None)
+            .simulate_validation(uo.into(), None, StateOverride::default())
             .await??;
 
         Ok(!output.return_info.account_sig_failed)
     }
+
+    async fn trace_validation(
+        &self,
+        uo: UserOperationVariant,
+        state_override: StateOverride,
+    ) -> Result<ValidationTrace, SimulationError> {
+        let Some(simulator) = &self.simulator else {
+            return Err(anyhow::anyhow!(
+                "validation tracing is not enabled for entry point {:?}",
+                self.entry_point.address()
+            )
+            .into());
+        };
+
+        simulator
+            .trace_validation(uo.into(), BlockId::latest(), state_override)
+            .await
+    }
 }
 
-impl<UO, E, G, EP> EntryPointRouteImpl<UO, E, G, EP> {
+impl<UO, E, G, EP> EntryPointRouteImpl<UO, E, G, EP>
+where
+    UO: UserOperation,
+{
     pub(crate) fn new(entry_point: E, gas_estimator: G, event_provider: EP) -> Self {
+        Self::new_with_version(
+            UO::entry_point_version(),
+            entry_point,
+            gas_estimator,
+            event_provider,
+        )
+    }
+
+    /// Creates a route for entry point v0.8, which uses the v0.7 user operation type
+    pub(crate) fn new_v0_8(entry_point: E, gas_estimator: G, event_provider: EP) -> Self {
+        Self::new_with_version(
+            EntryPointVersion::V0_8,
+            entry_point,
+            gas_estimator,
+            event_provider,
+        )
+    }
+
+    fn new_with_version(
+        version: EntryPointVersion,
+        entry_point: E,
+        gas_estimator: G,
+        event_provider: EP,
+    ) -> Self {
         Self {
+            version,
             entry_point,
             gas_estimator,
             event_provider,
+            simulator: None,
             _uo_type: PhantomData,
         }
     }
+
+    /// Enables validation tracing on this route with the given simulator
+    pub(crate) fn with_simulator<S>(mut self, simulator: S) -> Self
+    where
+        S: Simulator<UO = UO> + 'static,
+    {
+        self.simulator = Some(Arc::new(simulator));
+        self
+    }
 }
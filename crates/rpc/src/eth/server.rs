@@ -39,7 +39,25 @@ where
             "eth_sendUserOperation",
             EthApi::send_user_operation(
                 self,
-                UserOperationVariant::from_rpc(op, &self.chain_spec),
+                UserOperationVariant::from_rpc(op, &entry_point, &self.chain_spec),
+                entry_point,
+            ),
+        )
+        .await
+    }
+
+    async fn send_user_operation_group(
+        &self,
+        ops: Vec<RpcUserOperation>,
+        entry_point: Address,
+    ) -> RpcResult<Vec<B256>> {
+        utils::safe_call_rpc_handler(
+            "eth_sendUserOperationGroup",
+            EthApi::send_user_operation_group(
+                self,
+                ops.into_iter()
+                    .map(|op| UserOperationVariant::from_rpc(op, &entry_point, &self.chain_spec))
+                    .collect(),
                 entry_point,
             ),
         )
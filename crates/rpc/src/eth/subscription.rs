@@ -0,0 +1,213 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::future::Future;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use jsonrpsee::{
+    core::SubscriptionResult, proc_macros::rpc, PendingSubscriptionSink, SubscriptionMessage,
+};
+use rundler_types::pool::{OpStatus, OpStatusUpdate, Pool};
+use tokio::sync::broadcast;
+
+use super::EthRpcError;
+use crate::types::{
+    RpcSubscriptionKind, RpcSubscriptionResult, RpcUserOperationStatus,
+    RpcUserOperationStatusFilter,
+};
+
+/// Eth subscription API, only available over WebSocket
+#[rpc(client, server, namespace = "eth")]
+pub trait EthSubscriptionApi {
+    /// Subscribes to user operation events.
+    ///
+    /// `newPendingUserOperations` notifies the hash of each operation added to the pool.
+    /// `rundler_userOperationStatus` notifies status updates of the operations matching
+    /// the filter.
+    #[subscription(
+        name = "subscribe" => "subscription",
+        unsubscribe = "unsubscribe",
+        item = RpcSubscriptionResult
+    )]
+    async fn subscribe(
+        &self,
+        kind: RpcSubscriptionKind,
+        filter: Option<RpcUserOperationStatusFilter>,
+    ) -> SubscriptionResult;
+}
+
+pub(crate) struct EthSubscriptionApi {
+    op_status: broadcast::Sender<OpStatusUpdate>,
+}
+
+impl EthSubscriptionApi {
+    /// Creates the API, the returned future forwards the pool's status updates to every
+    /// subscription and must be spawned.
+    ///
+    /// A single pool subscription is shared by all clients, as remote pools limit the
+    /// number of subscriptions.
+    pub(crate) fn new<P: Pool + 'static>(
+        pool: P,
+        capacity: usize,
+    ) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let (op_status, _) = broadcast::channel(capacity);
+        let sender = op_status.clone();
+        let forward = async move {
+            let mut updates = match pool.subscribe_op_status().await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    tracing::error!("failed to subscribe to op status updates: {e:?}");
+                    return;
+                }
+            };
+            while let Some(update) = updates.next().await {
+                // no subscribers is not an error
+                let _ = sender.send(update);
+            }
+            tracing::error!("op status subscription ended");
+        };
+        (Self { op_status }, forward)
+    }
+}
+
+#[async_trait]
+impl EthSubscriptionApiServer for EthSubscriptionApi {
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: RpcSubscriptionKind,
+        filter: Option<RpcUserOperationStatusFilter>,
+    ) -> SubscriptionResult {
+        let filter = match (kind, filter) {
+            (RpcSubscriptionKind::NewPendingUserOperations, _) => None,
+            (RpcSubscriptionKind::UserOperationStatus, Some(filter))
+                if filter.user_op_hash.is_some() || filter.sender.is_some() =>
+            {
+                Some(filter)
+            }
+            (RpcSubscriptionKind::UserOperationStatus, _) => {
+                pending
+                    .reject(EthRpcError::InvalidParams(
+                        "userOpHash or sender filter is required".to_string(),
+                    ))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        let mut updates = self.op_status.subscribe();
+        let sink = pending.accept().await?;
+
+        loop {
+            let update = tokio::select! {
+                _ = sink.closed() => break,
+                update = updates.recv() => update,
+            };
+            let update = match update {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(c)) => {
+                    tracing::warn!("eth_subscribe subscriber lagged {c} op status updates");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let Some(result) = notification(update, filter.as_ref()) else {
+                continue;
+            };
+            sink.send(SubscriptionMessage::from_json(&result)?).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the notification for a status update, if the subscription is interested in it.
+///
+/// A `None` filter is a `newPendingUserOperations` subscription.
+fn notification(
+    update: OpStatusUpdate,
+    filter: Option<&RpcUserOperationStatusFilter>,
+) -> Option<RpcSubscriptionResult> {
+    let Some(filter) = filter else {
+        return (update.status == OpStatus::Pending)
+            .then_some(RpcSubscriptionResult::NewPendingUserOperation(update.hash));
+    };
+
+    let matches = filter.user_op_hash.map_or(true, |h| h == update.hash)
+        && filter.sender.map_or(true, |s| s == update.sender)
+        && filter
+            .entry_point
+            .map_or(true, |ep| ep == update.entry_point);
+    matches
+        .then(|| RpcSubscriptionResult::UserOperationStatus(RpcUserOperationStatus::from(update)))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, B256, U256};
+    use rundler_types::pool::OpDropReason;
+
+    use super::*;
+
+    fn update(status: OpStatus) -> OpStatusUpdate {
+        OpStatusUpdate {
+            hash: B256::random(),
+            entry_point: Address::random(),
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_new_pending_notification() {
+        let pending = update(OpStatus::Pending);
+        let hash = pending.hash;
+        assert!(matches!(
+            notification(pending, None),
+            Some(RpcSubscriptionResult::NewPendingUserOperation(h)) if h == hash
+        ));
+        assert!(notification(update(OpStatus::Unmined), None).is_none());
+    }
+
+    #[test]
+    fn test_status_notification_filter() {
+        let dropped = update(OpStatus::Dropped(OpDropReason::Expired));
+
+        let by_sender = RpcUserOperationStatusFilter {
+            sender: Some(dropped.sender),
+            ..Default::default()
+        };
+        let Some(RpcSubscriptionResult::UserOperationStatus(status)) =
+            notification(dropped.clone(), Some(&by_sender))
+        else {
+            panic!("expected status notification");
+        };
+        assert_eq!(status.user_op_hash, dropped.hash);
+
+        let by_hash_wrong_entry_point = RpcUserOperationStatusFilter {
+            user_op_hash: Some(dropped.hash),
+            entry_point: Some(Address::random()),
+            ..Default::default()
+        };
+        assert!(notification(dropped.clone(), Some(&by_hash_wrong_entry_point)).is_none());
+
+        let other_sender = RpcUserOperationStatusFilter {
+            sender: Some(Address::random()),
+            ..Default::default()
+        };
+        assert!(notification(dropped, Some(&other_sender)).is_none());
+    }
+}
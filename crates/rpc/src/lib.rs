@@ -28,10 +28,12 @@ pub use admin::AdminApiClient;
 mod error;
 
 mod eth;
-pub use eth::{EthApiClient, EthApiSettings};
+pub use eth::{EthApiClient, EthApiSettings, EthSubscriptionApiClient};
 
 mod health;
 
+mod paymaster;
+
 mod rundler;
 pub use rundler::{RundlerApiClient, Settings as RundlerApiSettings};
 
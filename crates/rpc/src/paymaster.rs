@@ -0,0 +1,430 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::future::Future;
+
+use alloy_primitives::{Address, U64};
+use anyhow::Context;
+use jsonrpsee::{
+    core::{ClientError, RpcResult},
+    http_client::{HttpClient, HttpClientBuilder},
+    proc_macros::rpc,
+};
+use rundler_types::{
+    chain::ChainSpec, GasEstimate, UserOperationOptionalGas, UserOperationVariant,
+};
+use serde_json::Value;
+
+use crate::{
+    eth::{EthResult, EthRpcError},
+    types::{RpcPaymasterData, RpcSponsoredUserOperation, RpcUserOperation},
+};
+
+/// ERC-7677 paymaster web service API
+#[rpc(client, namespace = "pm")]
+pub(crate) trait PaymasterService {
+    /// Returns paymaster fields suitable for gas estimation
+    #[method(name = "getPaymasterStubData")]
+    async fn get_paymaster_stub_data(
+        &self,
+        user_op: RpcUserOperation,
+        entry_point: Address,
+        chain_id: U64,
+        context: Value,
+    ) -> RpcResult<RpcPaymasterData>;
+
+    /// Returns the final paymaster fields for a user operation with its gas set
+    #[method(name = "getPaymasterData")]
+    async fn get_paymaster_data(
+        &self,
+        user_op: RpcUserOperation,
+        entry_point: Address,
+        chain_id: U64,
+        context: Value,
+    ) -> RpcResult<RpcPaymasterData>;
+}
+
+/// Sponsors user operations using the chain's paymaster service
+pub(crate) struct PaymasterServiceSponsor {
+    chain_spec: ChainSpec,
+    client: HttpClient,
+}
+
+impl PaymasterServiceSponsor {
+    pub(crate) fn new(chain_spec: ChainSpec, url: &str) -> anyhow::Result<Self> {
+        let client = HttpClientBuilder::default()
+            .build(url)
+            .context("should build paymaster service client")?;
+        Ok(Self { chain_spec, client })
+    }
+
+    /// Runs the ERC-7677 flow for `op`: fills the stub paymaster fields, estimates
+    /// gas with `estimate`, then fills the final paymaster fields unless the stub
+    /// fields are final.
+    ///
+    /// The operation's fees are signed over by the paymaster, so they must be set.
+    pub(crate) async fn sponsor<F, Fut>(
+        &self,
+        mut op: UserOperationOptionalGas,
+        entry_point: Address,
+        context: Value,
+        estimate: F,
+    ) -> EthResult<RpcSponsoredUserOperation>
+    where
+        F: FnOnce(UserOperationOptionalGas) -> Fut,
+        Fut: Future<Output = EthResult<GasEstimate>>,
+    {
+        let has_fees = match &op {
+            UserOperationOptionalGas::V0_6(op) => {
+                op.max_fee_per_gas.is_some() && op.max_priority_fee_per_gas.is_some()
+            }
+            UserOperationOptionalGas::V0_7(op) => {
+                op.max_fee_per_gas.is_some() && op.max_priority_fee_per_gas.is_some()
+            }
+        };
+        if !has_fees {
+            return Err(EthRpcError::InvalidParams(
+                "maxFeePerGas and maxPriorityFeePerGas are required for sponsorship".to_string(),
+            ));
+        }
+        let chain_id = U64::from(self.chain_spec.id);
+
+        let stub = self
+            .client
+            .get_paymaster_stub_data(
+                self.fill(&op, None).into(),
+                entry_point,
+                chain_id,
+                context.clone(),
+            )
+            .await
+            .map_err(service_error)?;
+        apply_paymaster_data(&mut op, &stub)?;
+
+        let gas = estimate(op.clone()).await?;
+        let mut sponsored = self.fill(&op, Some(&gas));
+
+        if !stub.is_final {
+            let data = self
+                .client
+                .get_paymaster_data(sponsored.into(), entry_point, chain_id, context)
+                .await
+                .map_err(service_error)?;
+            apply_paymaster_data(&mut op, &data)?;
+            sponsored = self.fill(&op, Some(&gas));
+        }
+
+        Ok(RpcSponsoredUserOperation {
+            user_operation: sponsored.into(),
+            sponsor: stub.sponsor,
+        })
+    }
+
+    /// Builds the full user operation, taking gas limits from `gas` if set. Unset
+    /// gas limits are zero.
+    fn fill(
+        &self,
+        op: &UserOperationOptionalGas,
+        gas: Option<&GasEstimate>,
+    ) -> UserOperationVariant {
+        match op.clone() {
+            UserOperationOptionalGas::V0_6(op) => {
+                let mut builder = op.into_user_operation_builder(&self.chain_spec, 0, 0);
+                if let Some(gas) = gas {
+                    builder = builder
+                        .call_gas_limit(gas.call_gas_limit)
+                        .verification_gas_limit(gas.verification_gas_limit)
+                        .pre_verification_gas(gas.pre_verification_gas);
+                }
+                UserOperationVariant::V0_6(builder.build())
+            }
+            UserOperationOptionalGas::V0_7(op) => {
+                let has_paymaster = op.paymaster.is_some();
+                let mut builder = op.into_user_operation_builder(&self.chain_spec, 0, 0, 0);
+                if let Some(gas) = gas {
+                    builder = builder
+                        .call_gas_limit(gas.call_gas_limit)
+                        .verification_gas_limit(gas.verification_gas_limit)
+                        .pre_verification_gas(gas.pre_verification_gas);
+                    if let Some(limit) = gas
+                        .paymaster_verification_gas_limit
+                        .filter(|_| has_paymaster)
+                    {
+                        builder = builder.paymaster_verification_gas_limit(limit);
+                    }
+                }
+                UserOperationVariant::V0_7(builder.build())
+            }
+        }
+    }
+}
+
+/// Sets the paymaster fields returned by the paymaster service on the operation.
+/// Gas limits are only returned with stub data, and are kept if unset.
+fn apply_paymaster_data(
+    op: &mut UserOperationOptionalGas,
+    data: &RpcPaymasterData,
+) -> EthResult<()> {
+    match op {
+        UserOperationOptionalGas::V0_6(op) => {
+            op.paymaster_and_data = data
+                .paymaster_and_data
+                .clone()
+                .context("paymaster service should return paymasterAndData for entry point v0.6")?;
+        }
+        UserOperationOptionalGas::V0_7(op) => {
+            op.paymaster = Some(
+                data.paymaster
+                    .context("paymaster service should return paymaster for entry point v0.7")?,
+            );
+            op.paymaster_data = data.paymaster_data.clone().unwrap_or_default();
+            if let Some(limit) = data.paymaster_verification_gas_limit {
+                op.paymaster_verification_gas_limit = Some(limit.to());
+            }
+            if let Some(limit) = data.paymaster_post_op_gas_limit {
+                op.paymaster_post_op_gas_limit = Some(limit.to());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn service_error(error: ClientError) -> EthRpcError {
+    match error {
+        ClientError::Call(error) => EthRpcError::PaymasterServiceRejected(error),
+        error => EthRpcError::Internal(
+            anyhow::Error::new(error).context("should have called paymaster service"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use alloy_primitives::{bytes, Bytes, U256};
+    use jsonrpsee::{
+        server::{RpcModule, Server, ServerHandle},
+        types::ErrorObjectOwned,
+    };
+    use rundler_types::{v0_6, v0_7};
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::FromRpc;
+
+    const PAYMASTER: Address = Address::repeat_byte(0xaa);
+
+    /// A local paymaster service answering each method with a fixed response and
+    /// recording the operations it receives.
+    async fn paymaster_service_stand_in(
+        stub: Value,
+        data: Result<Value, ErrorObjectOwned>,
+    ) -> (String, ServerHandle, Arc<Mutex<Vec<(String, Value)>>>) {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut module = RpcModule::new(calls.clone());
+        module
+            .register_method("pm_getPaymasterStubData", move |params, calls, _| {
+                let params: Vec<Value> = params.parse().unwrap();
+                calls
+                    .lock()
+                    .unwrap()
+                    .push(("stub".to_string(), params[0].clone()));
+                stub.clone()
+            })
+            .unwrap();
+        module
+            .register_method("pm_getPaymasterData", move |params, calls, _| {
+                let params: Vec<Value> = params.parse().unwrap();
+                calls
+                    .lock()
+                    .unwrap()
+                    .push(("data".to_string(), params[0].clone()));
+                data.clone()
+            })
+            .unwrap();
+
+        let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        (url, server.start(module), calls)
+    }
+
+    fn chain_spec() -> ChainSpec {
+        ChainSpec {
+            id: 1,
+            ..Default::default()
+        }
+    }
+
+    fn op_v0_7() -> UserOperationOptionalGas {
+        UserOperationOptionalGas::V0_7(v0_7::UserOperationOptionalGas {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            call_data: Bytes::new(),
+            signature: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_priority_fee_per_gas: Some(1),
+            max_fee_per_gas: Some(2),
+            factory: None,
+            factory_data: Bytes::new(),
+            paymaster: None,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            paymaster_data: Bytes::new(),
+            authorization_contract: None,
+        })
+    }
+
+    fn gas() -> GasEstimate {
+        GasEstimate {
+            pre_verification_gas: 100,
+            call_gas_limit: 200,
+            verification_gas_limit: 300,
+            paymaster_verification_gas_limit: Some(400),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_v0_7() {
+        let (url, _handle, calls) = paymaster_service_stand_in(
+            json!({
+                "paymaster": PAYMASTER,
+                "paymasterData": "0x01",
+                "paymasterPostOpGasLimit": "0x10",
+                "sponsor": { "name": "Sponsor" },
+            }),
+            Ok(json!({ "paymaster": PAYMASTER, "paymasterData": "0x0202" })),
+        )
+        .await;
+        let sponsor = PaymasterServiceSponsor::new(chain_spec(), &url).unwrap();
+
+        let estimated = Arc::new(Mutex::new(None));
+        let estimated_op = estimated.clone();
+        let sponsored = sponsor
+            .sponsor(op_v0_7(), Address::ZERO, json!({}), |op| async move {
+                *estimated_op.lock().unwrap() = Some(op);
+                Ok(gas())
+            })
+            .await
+            .unwrap();
+
+        // estimation sees the stub paymaster fields
+        let Some(UserOperationOptionalGas::V0_7(estimated)) = estimated.lock().unwrap().take()
+        else {
+            panic!("expected v0.7 op to be estimated");
+        };
+        assert_eq!(estimated.paymaster, Some(PAYMASTER));
+        assert_eq!(estimated.paymaster_data, bytes!("01"));
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].0, "data");
+        assert_eq!(calls[1].1["callGasLimit"], json!("0xc8"));
+        assert_eq!(calls[1].1["paymasterData"], json!("0x01"));
+
+        let RpcUserOperation::V0_7(op) = sponsored.user_operation else {
+            panic!("expected v0.7 op");
+        };
+        let op = v0_7::UserOperation::from_rpc(op, &Address::ZERO, &chain_spec());
+        assert_eq!(op.paymaster, Some(PAYMASTER));
+        assert_eq!(op.paymaster_data, bytes!("0202"));
+        assert_eq!(op.paymaster_verification_gas_limit, 400);
+        assert_eq!(op.paymaster_post_op_gas_limit, 16);
+        assert_eq!(op.pre_verification_gas, 100);
+        assert_eq!(op.max_fee_per_gas, 2);
+        assert_eq!(sponsored.sponsor.unwrap().name, "Sponsor");
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_final_stub_v0_6() {
+        let (url, _handle, calls) = paymaster_service_stand_in(
+            json!({ "paymasterAndData": "0xabcd", "isFinal": true }),
+            Ok(json!({})),
+        )
+        .await;
+        let sponsor = PaymasterServiceSponsor::new(chain_spec(), &url).unwrap();
+
+        let op = UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: Some(2),
+            max_priority_fee_per_gas: Some(1),
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+            authorization_contract: None,
+        });
+        let sponsored = sponsor
+            .sponsor(op, Address::ZERO, json!({}), |_| async { Ok(gas()) })
+            .await
+            .unwrap();
+
+        assert_eq!(calls.lock().unwrap().len(), 1);
+        let RpcUserOperation::V0_6(op) = sponsored.user_operation else {
+            panic!("expected v0.6 op");
+        };
+        let op = v0_6::UserOperation::from_rpc(op, &Address::ZERO, &chain_spec());
+        assert_eq!(op.paymaster_and_data, bytes!("abcd"));
+        assert_eq!(op.call_gas_limit, 200);
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_rejected() {
+        let (url, _handle, _) = paymaster_service_stand_in(
+            json!({ "paymaster": PAYMASTER }),
+            Err(ErrorObjectOwned::owned(
+                -32000,
+                "policy rejected",
+                None::<()>,
+            )),
+        )
+        .await;
+        let sponsor = PaymasterServiceSponsor::new(chain_spec(), &url).unwrap();
+
+        let err = sponsor
+            .sponsor(op_v0_7(), Address::ZERO, json!({}), |_| async { Ok(gas()) })
+            .await
+            .unwrap_err();
+        let EthRpcError::PaymasterServiceRejected(err) = err else {
+            panic!("expected paymaster service error, got {err:?}");
+        };
+        assert_eq!(err.code(), -32000);
+        assert_eq!(err.message(), "policy rejected");
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_requires_fees() {
+        let sponsor = PaymasterServiceSponsor::new(chain_spec(), "http://127.0.0.1:1").unwrap();
+        let UserOperationOptionalGas::V0_7(mut op) = op_v0_7() else {
+            unreachable!()
+        };
+        op.max_fee_per_gas = None;
+
+        let err = sponsor
+            .sponsor(
+                UserOperationOptionalGas::V0_7(op),
+                Address::ZERO,
+                json!({}),
+                |_| async { Ok(gas()) },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EthRpcError::InvalidParams(_)));
+    }
+}
@@ -17,10 +17,12 @@ use async_trait::async_trait;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use rundler_sim::{gas, FeeEstimator};
 use rundler_types::{chain::ChainSpec, pool::Pool, UserOperation, UserOperationVariant};
+use serde_json::Value;
 
 use crate::{
     eth::{EntryPointRouter, EthResult, EthRpcError},
-    types::{FromRpc, RpcUserOperation},
+    paymaster::PaymasterServiceSponsor,
+    types::{FromRpc, RpcSponsoredUserOperation, RpcUserOperation, RpcUserOperationOptionalGas},
     utils,
 };
 
@@ -54,6 +56,21 @@ pub trait RundlerApi {
         uo: RpcUserOperation,
         entry_point: Address,
     ) -> RpcResult<Option<B256>>;
+
+    /// Sponsors a user operation using the chain's ERC-7677 paymaster service.
+    ///
+    /// Fills the paymaster stub data, estimates gas, then fills the final paymaster data.
+    /// `context` is passed to the paymaster service as-is, and is typically used to
+    /// select a sponsorship policy.
+    ///
+    /// Returns the user operation ready to be signed by the sender.
+    #[method(name = "sponsorUserOperation")]
+    async fn sponsor_user_operation(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        context: Option<Value>,
+    ) -> RpcResult<RpcSponsoredUserOperation>;
 }
 
 pub(crate) struct RundlerApi<P, F> {
@@ -61,6 +78,7 @@ pub(crate) struct RundlerApi<P, F> {
     fee_estimator: F,
     pool_server: P,
     entry_point_router: EntryPointRouter,
+    paymaster_sponsor: Option<PaymasterServiceSponsor>,
 }
 
 #[async_trait]
@@ -88,6 +106,19 @@ where
         )
         .await
     }
+
+    async fn sponsor_user_operation(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        context: Option<Value>,
+    ) -> RpcResult<RpcSponsoredUserOperation> {
+        utils::safe_call_rpc_handler(
+            "rundler_sponsorUserOperation",
+            RundlerApi::sponsor_user_operation(self, op, entry_point, context),
+        )
+        .await
+    }
 }
 
 impl<P, F> RundlerApi<P, F>
@@ -100,13 +131,20 @@ where
         entry_point_router: EntryPointRouter,
         pool_server: P,
         fee_estimator: F,
-    ) -> Self {
-        Self {
+    ) -> anyhow::Result<Self> {
+        let paymaster_sponsor = chain_spec
+            .paymaster_service_url
+            .as_deref()
+            .map(|url| PaymasterServiceSponsor::new(chain_spec.clone(), url))
+            .transpose()?;
+
+        Ok(Self {
             chain_spec: chain_spec.clone(),
             entry_point_router,
             pool_server,
             fee_estimator,
-        }
+            paymaster_sponsor,
+        })
     }
 
     async fn max_priority_fee_per_gas(&self) -> EthResult<U128> {
@@ -127,7 +165,7 @@ where
         user_op: RpcUserOperation,
         entry_point: Address,
     ) -> EthResult<Option<B256>> {
-        let uo = UserOperationVariant::from_rpc(user_op, &self.chain_spec);
+        let uo = UserOperationVariant::from_rpc(user_op, &entry_point, &self.chain_spec);
         let id = uo.id();
 
         if uo.pre_verification_gas() != 0
@@ -160,4 +198,28 @@ where
 
         Ok(ret)
     }
+    async fn sponsor_user_operation(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        context: Option<Value>,
+    ) -> EthResult<RpcSponsoredUserOperation> {
+        let Some(sponsor) = &self.paymaster_sponsor else {
+            return Err(EthRpcError::InvalidParams(
+                "no paymaster service is configured for this chain".to_string(),
+            ));
+        };
+
+        sponsor
+            .sponsor(
+                op.into(),
+                entry_point,
+                context.unwrap_or_else(|| Value::Object(Default::default())),
+                |op| {
+                    self.entry_point_router
+                        .estimate_op_gas(&entry_point, op, None)
+                },
+            )
+            .await
+    }
 }
@@ -23,13 +23,17 @@ use jsonrpsee::{
 use rundler_provider::Providers as ProvidersT;
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl, FeeOracle},
-    EstimationSettings, FeeEstimator, GasEstimatorV0_6, GasEstimatorV0_7, PrecheckSettings,
+    simulation, EstimationSettings, FeeEstimator, GasEstimatorV0_6, GasEstimatorV0_7,
+    MempoolConfigs, PrecheckSettings, SimulationSettings,
 };
 use rundler_task::{
     server::{format_socket_addr, HealthCheck},
     TaskSpawner,
 };
-use rundler_types::{builder::Builder as BuilderT, chain::ChainSpec, pool::Pool as PoolT};
+use rundler_types::{
+    aggregator::AggregatorConfigs, builder::Builder as BuilderT, chain::ChainSpec,
+    pool::Pool as PoolT, revert::RevertDecoder,
+};
 use tracing::info;
 
 use crate::{
@@ -37,7 +41,9 @@ use crate::{
     debug::{DebugApi, DebugApiServer},
     eth::{
         EntryPointRouteImpl, EntryPointRouter, EntryPointRouterBuilder, EthApi, EthApiServer,
-        EthApiSettings, UserOperationEventProviderV0_6, UserOperationEventProviderV0_7,
+        EthApiSettings, EthSubscriptionApi, EthSubscriptionApiServer,
+        UserOperationEventProviderV0_6, UserOperationEventProviderV0_7,
+        UserOperationEventProviderV0_8,
     },
     health::{HealthChecker, SystemApiServer},
     rpc_metrics::{HttpMetricMiddlewareLayer, RpcMetricsMiddlewareLayer},
@@ -45,6 +51,9 @@ use crate::{
     types::ApiNamespace,
 };
 
+/// Capacity of the channel sharing op status updates between `eth_subscribe` subscriptions
+const OP_STATUS_CHANNEL_CAPACITY: usize = 1024;
+
 /// RPC server arguments.
 #[derive(Debug)]
 pub struct Args {
@@ -76,8 +85,18 @@ pub struct Args {
     pub entry_point_v0_6_enabled: bool,
     /// Whether to enable entry point v0.7.
     pub entry_point_v0_7_enabled: bool,
+    /// Whether to enable entry point v0.8.
+    pub entry_point_v0_8_enabled: bool,
     /// What domains to use in the corsdomain
     pub corsdomain: Option<Vec<HeaderValue>>,
+    /// Trusted signature aggregators, used for gas estimation
+    pub aggregators: AggregatorConfigs,
+    /// Decoder for reverts in gas estimation errors
+    pub revert_decoder: RevertDecoder,
+    /// Simulation settings, used to trace the validation of user operations
+    pub sim_settings: SimulationSettings,
+    /// Alternative mempool configurations, used to trace the validation of user operations
+    pub mempool_configs: MempoolConfigs,
 }
 
 /// JSON-RPC server task.
@@ -112,7 +131,8 @@ where
         let addr: SocketAddr = format_socket_addr(&self.args.host, self.args.port).parse()?;
         tracing::info!("Starting rpc server on {}", addr);
 
-        let mut router_builder = EntryPointRouterBuilder::default();
+        let mut router_builder =
+            EntryPointRouterBuilder::default().revert_decoder(self.args.revert_decoder.clone());
         let fee_oracle = Arc::<dyn FeeOracle>::from(gas::get_fee_oracle(
             &self.args.chain_spec,
             self.providers.evm().clone(),
@@ -127,6 +147,9 @@ where
                 .bundle_priority_fee_overhead_percent,
         );
 
+        // The pool's mined operation index is used for lookups by hash when enabled
+        let mined_op_index: Arc<dyn PoolT> = Arc::new(self.pool.clone());
+
         if self.args.entry_point_v0_6_enabled {
             let ep = self
                 .providers
@@ -134,7 +157,7 @@ where
                 .clone()
                 .context("entry point v0.6 not supplied")?;
 
-            router_builder = router_builder.v0_6(EntryPointRouteImpl::new(
+            let mut route = EntryPointRouteImpl::new(
                 ep.clone(),
                 GasEstimatorV0_6::new(
                     self.args.chain_spec.clone(),
@@ -142,15 +165,28 @@ where
                     ep.clone(),
                     self.args.estimation_settings,
                     fee_estimator.clone(),
-                ),
+                )
+                .with_aggregators(self.args.aggregators.clone()),
                 UserOperationEventProviderV0_6::new(
                     self.args.chain_spec.clone(),
                     self.providers.evm().clone(),
                     self.args
                         .eth_api_settings
                         .user_operation_event_block_distance,
+                    Some(mined_op_index.clone()),
                 ),
-            ));
+            );
+            if !self.args.unsafe_mode {
+                route = route.with_simulator(simulation::new_v0_6_simulator(
+                    self.providers.evm().clone(),
+                    ep.clone(),
+                    self.args.sim_settings.clone(),
+                    self.args
+                        .mempool_configs
+                        .get_for_entry_point(self.args.chain_spec.entry_point_address_v0_6),
+                ));
+            }
+            router_builder = router_builder.v0_6(route);
         }
 
         if self.args.entry_point_v0_7_enabled {
@@ -160,7 +196,7 @@ where
                 .clone()
                 .context("entry point v0.7 not supplied")?;
 
-            router_builder = router_builder.v0_7(EntryPointRouteImpl::new(
+            let mut route = EntryPointRouteImpl::new(
                 ep.clone(),
                 GasEstimatorV0_7::new(
                     self.args.chain_spec.clone(),
@@ -168,15 +204,69 @@ where
                     ep.clone(),
                     self.args.estimation_settings,
                     fee_estimator.clone(),
-                ),
+                )
+                .with_aggregators(self.args.aggregators.clone()),
                 UserOperationEventProviderV0_7::new(
                     self.args.chain_spec.clone(),
                     self.providers.evm().clone(),
                     self.args
                         .eth_api_settings
                         .user_operation_event_block_distance,
+                    Some(mined_op_index.clone()),
                 ),
-            ));
+            );
+            if !self.args.unsafe_mode {
+                route = route.with_simulator(simulation::new_v0_7_simulator(
+                    self.providers.evm().clone(),
+                    ep.clone(),
+                    &self.args.chain_spec,
+                    self.args.sim_settings.clone(),
+                    self.args
+                        .mempool_configs
+                        .get_for_entry_point(self.args.chain_spec.entry_point_address_v0_7),
+                ));
+            }
+            router_builder = router_builder.v0_7(route);
+        }
+
+        if self.args.entry_point_v0_8_enabled {
+            let ep = self
+                .providers
+                .ep_v0_8()
+                .clone()
+                .context("entry point v0.8 not supplied")?;
+
+            let mut route = EntryPointRouteImpl::new_v0_8(
+                ep.clone(),
+                GasEstimatorV0_7::new_v0_8(
+                    self.args.chain_spec.clone(),
+                    self.providers.evm().clone(),
+                    ep.clone(),
+                    self.args.estimation_settings,
+                    fee_estimator.clone(),
+                )
+                .with_aggregators(self.args.aggregators.clone()),
+                UserOperationEventProviderV0_8::new(
+                    self.args.chain_spec.clone(),
+                    self.providers.evm().clone(),
+                    self.args
+                        .eth_api_settings
+                        .user_operation_event_block_distance,
+                    Some(mined_op_index.clone()),
+                ),
+            );
+            if !self.args.unsafe_mode {
+                route = route.with_simulator(simulation::new_v0_7_simulator(
+                    self.providers.evm().clone(),
+                    ep.clone(),
+                    &self.args.chain_spec,
+                    self.args.sim_settings.clone(),
+                    self.args
+                        .mempool_configs
+                        .get_for_entry_point(self.args.chain_spec.entry_point_address_v0_8),
+                ));
+            }
+            router_builder = router_builder.v0_8(route);
         }
 
         // create the entry point router
@@ -185,6 +275,13 @@ where
         let mut module = RpcModule::new(());
         self.attach_namespaces(router, fee_estimator, &mut module)?;
 
+        if self.args.api_namespaces.contains(&ApiNamespace::Eth) {
+            let (subscription_api, forward_op_status) =
+                EthSubscriptionApi::new(self.pool.clone(), OP_STATUS_CHANNEL_CAPACITY);
+            task_spawner.spawn(forward_op_status.boxed());
+            module.merge(subscription_api.into_rpc())?;
+        }
+
         let servers: Vec<Box<dyn HealthCheck>> =
             vec![Box::new(self.pool.clone()), Box::new(self.builder.clone())];
         let health_checker = HealthChecker::new(servers);
@@ -234,7 +331,6 @@ where
                     .try_into()
                     .expect("max_transaction_size_bytes * 2 overflowed u32"),
             )
-            .http_only()
             .build(addr)
             .await?;
 
@@ -272,7 +368,15 @@ where
         }
 
         if self.args.api_namespaces.contains(&ApiNamespace::Debug) {
-            module.merge(DebugApi::new(self.pool.clone(), self.builder.clone()).into_rpc())?;
+            module.merge(
+                DebugApi::new(
+                    self.args.chain_spec.clone(),
+                    self.pool.clone(),
+                    self.builder.clone(),
+                    entry_point_router.clone(),
+                )
+                .into_rpc(),
+            )?;
         }
 
         if self.args.api_namespaces.contains(&ApiNamespace::Admin) {
@@ -286,7 +390,7 @@ where
                     entry_point_router,
                     self.pool.clone(),
                     fee_estimator,
-                )
+                )?
                 .into_rpc(),
             )?;
         }
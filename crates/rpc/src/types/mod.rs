@@ -11,14 +11,17 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, B256, U128, U256, U64};
+use std::collections::{BTreeSet, HashMap};
+
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
 use rundler_provider::{Log, TransactionReceipt};
+use rundler_sim::{PhaseTrace, ValidationTrace};
 use rundler_types::{
     chain::ChainSpec,
-    pool::{Reputation, ReputationStatus},
+    pool::{OpStatus, OpStatusUpdate, Reputation, ReputationStatus},
     v0_6::UserOperation as UserOperationV0_6,
     v0_7::UserOperation as UserOperationV0_7,
-    UserOperationOptionalGas, UserOperationVariant,
+    EntityType, Opcode, UserOperationOptionalGas, UserOperationVariant,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -45,7 +48,7 @@ pub enum ApiNamespace {
 
 /// Conversion trait for RPC types adding the context of the entry point and chain id
 pub(crate) trait FromRpc<R> {
-    fn from_rpc(rpc: R, chain_spec: &ChainSpec) -> Self;
+    fn from_rpc(rpc: R, entry_point: &Address, chain_spec: &ChainSpec) -> Self;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -115,13 +118,13 @@ impl From<UserOperationVariant> for RpcUserOperation {
 }
 
 impl FromRpc<RpcUserOperation> for UserOperationVariant {
-    fn from_rpc(op: RpcUserOperation, chain_spec: &ChainSpec) -> Self {
+    fn from_rpc(op: RpcUserOperation, entry_point: &Address, chain_spec: &ChainSpec) -> Self {
         match op {
             RpcUserOperation::V0_6(op) => {
-                UserOperationVariant::V0_6(UserOperationV0_6::from_rpc(op, chain_spec))
+                UserOperationVariant::V0_6(UserOperationV0_6::from_rpc(op, entry_point, chain_spec))
             }
             RpcUserOperation::V0_7(op) => {
-                UserOperationVariant::V0_7(UserOperationV0_7::from_rpc(op, chain_spec))
+                UserOperationVariant::V0_7(UserOperationV0_7::from_rpc(op, entry_point, chain_spec))
             }
         }
     }
@@ -178,6 +181,48 @@ impl From<RpcGasEstimateV0_7> for RpcGasEstimate {
     }
 }
 
+/// Paymaster fields returned by an ERC-7677 paymaster service, from either
+/// `pm_getPaymasterStubData` or `pm_getPaymasterData`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcPaymasterData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sponsor: Option<RpcSponsorInfo>,
+    /// v0.7 paymaster fields
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) paymaster: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) paymaster_data: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) paymaster_verification_gas_limit: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) paymaster_post_op_gas_limit: Option<U128>,
+    /// v0.6 paymaster field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) paymaster_and_data: Option<Bytes>,
+    /// If true, the stub data is final and `pm_getPaymasterData` is not called
+    #[serde(default)]
+    pub(crate) is_final: bool,
+}
+
+/// Sponsor of a user operation, as reported by the paymaster service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct RpcSponsorInfo {
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) icon: Option<String>,
+}
+
+/// A user operation with gas estimated and paymaster data filled by a paymaster service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcSponsoredUserOperation {
+    /// The user operation, ready to be signed by the sender
+    pub(crate) user_operation: RpcUserOperation,
+    /// The sponsor reported by the paymaster service, if any
+    pub(crate) sponsor: Option<RpcSponsorInfo>,
+}
+
 /// User operation receipt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -206,6 +251,109 @@ pub struct RpcUserOperationReceipt {
     pub receipt: TransactionReceipt,
 }
 
+/// Kind of an `eth_subscribe` subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpcSubscriptionKind {
+    /// Hashes of user operations added to the pool
+    #[serde(rename = "newPendingUserOperations")]
+    NewPendingUserOperations,
+    /// Status updates of user operations matching a filter
+    #[serde(rename = "rundler_userOperationStatus")]
+    UserOperationStatus,
+}
+
+/// Filter for `rundler_userOperationStatus` subscriptions
+///
+/// At least one of `userOpHash` or `sender` must be set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperationStatusFilter {
+    /// Only match the operation with this hash
+    pub user_op_hash: Option<B256>,
+    /// Only match operations from this sender
+    pub sender: Option<Address>,
+    /// Only match operations sent to this entry point
+    pub entry_point: Option<Address>,
+}
+
+/// Notification sent to an `eth_subscribe` subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcSubscriptionResult {
+    /// Hash of a new pending user operation
+    NewPendingUserOperation(B256),
+    /// Status update of a user operation
+    UserOperationStatus(RpcUserOperationStatus),
+}
+
+/// Status update of a user operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperationStatus {
+    /// The hash of the user operation
+    pub user_op_hash: B256,
+    /// The entry point address this operation was sent to
+    pub entry_point: RpcAddress,
+    /// The sender of this user operation
+    pub sender: RpcAddress,
+    /// The nonce of this user operation
+    pub nonce: U256,
+    /// The new status
+    #[serde(flatten)]
+    pub status: RpcOpStatus,
+}
+
+/// Status of a user operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum RpcOpStatus {
+    /// Added to the pool
+    Pending,
+    /// Removed from the pool without being mined
+    Dropped {
+        /// Why the operation was dropped
+        reason: String,
+    },
+    /// Mined, as of the given head block
+    Mined {
+        /// Head block number when the operation was seen mined
+        #[serde(rename = "blockNumber")]
+        block_number: U64,
+        /// Head block hash when the operation was seen mined
+        #[serde(rename = "blockHash")]
+        block_hash: B256,
+    },
+    /// The block containing the operation was reorged away
+    Unmined,
+}
+
+impl From<OpStatusUpdate> for RpcUserOperationStatus {
+    fn from(update: OpStatusUpdate) -> Self {
+        let status = match update.status {
+            OpStatus::Pending => RpcOpStatus::Pending,
+            OpStatus::Dropped(reason) => RpcOpStatus::Dropped {
+                reason: reason.to_string(),
+            },
+            OpStatus::Mined {
+                block_number,
+                block_hash,
+            } => RpcOpStatus::Mined {
+                block_number: U64::from(block_number),
+                block_hash,
+            },
+            OpStatus::Unmined => RpcOpStatus::Unmined,
+        };
+
+        RpcUserOperationStatus {
+            user_op_hash: update.hash,
+            entry_point: update.entry_point.into(),
+            sender: update.sender.into(),
+            nonce: update.nonce,
+            status,
+        }
+    }
+}
+
 /// Reputation of an entity
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -289,3 +437,151 @@ pub struct RpcDebugPaymasterBalance {
     /// Paymaster confirmed balance onchain
     pub confirmed_balance: U256,
 }
+
+/// Trace of the validation of a user operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidationTrace {
+    /// Tracer output of each validation phase, in execution order
+    pub phases: Vec<RpcPhaseTrace>,
+    /// Contracts accessed during validation, and the opcode used to access them
+    pub accessed_contracts: HashMap<Address, Opcode>,
+    /// Storage slots associated with each address
+    pub associated_slots: HashMap<Address, BTreeSet<U256>>,
+    /// Whether the factory called CREATE2 more than once
+    pub factory_called_create2_twice: bool,
+    /// Every violation found during validation, sorted by importance
+    pub violations: Vec<RpcViolation>,
+    /// Mempools that allow all of the violations, empty if the operation is rejected
+    pub mempools: Vec<B256>,
+}
+
+/// Tracer output of a single validation phase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPhaseTrace {
+    /// Entity validated in this phase, if any
+    pub entity: Option<EntityType>,
+    /// Forbidden opcodes used
+    pub forbidden_opcodes_used: Vec<RpcOpcodeUse>,
+    /// Forbidden precompiles called
+    pub forbidden_precompiles_used: Vec<RpcPrecompileUse>,
+    /// Storage slots accessed
+    pub storage_accesses: Vec<RpcStorageAccess>,
+    /// Whether an entry point method other than `depositTo` was called
+    pub called_banned_entry_point_method: bool,
+    /// Whether a contract other than the entry point was called with value
+    pub called_non_entry_point_with_value: bool,
+    /// Whether the phase ran out of gas
+    pub ran_out_of_gas: bool,
+    /// Addresses without code that were accessed
+    pub undeployed_contract_accesses: Vec<Address>,
+    /// Addresses whose code was accessed, and the opcode used to access it
+    pub ext_code_accesses: HashMap<Address, Opcode>,
+}
+
+/// Use of a forbidden opcode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcOpcodeUse {
+    /// Contract that used the opcode
+    pub contract: Address,
+    /// Opcode used
+    pub opcode: Opcode,
+}
+
+/// Call to a forbidden precompile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPrecompileUse {
+    /// Contract that called the precompile
+    pub contract: Address,
+    /// Precompile address
+    pub precompile: Address,
+}
+
+/// Storage slot accessed during validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStorageAccess {
+    /// Address of the accessed storage
+    pub address: Address,
+    /// Accessed slot
+    pub slot: U256,
+    /// Value of the slot before it was first read, if it was read
+    pub read_value: Option<U256>,
+    /// Number of writes to the slot
+    pub writes: U64,
+    /// Whether the slot is associated with the sender
+    pub sender_associated: bool,
+    /// Whether the slot is associated with the accessing entity
+    pub entity_associated: bool,
+}
+
+/// Violation of a validation rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcViolation {
+    /// ERC-7562 rule code, e.g. `OP-011`, if the violation breaks one
+    pub rule: Option<String>,
+    /// Description of the violation
+    pub message: String,
+}
+
+impl From<ValidationTrace> for RpcValidationTrace {
+    fn from(trace: ValidationTrace) -> Self {
+        RpcValidationTrace {
+            phases: trace.phases.into_iter().map(Into::into).collect(),
+            accessed_contracts: trace.accessed_contracts,
+            associated_slots: trace.associated_slots,
+            factory_called_create2_twice: trace.factory_called_create2_twice,
+            violations: trace
+                .violations
+                .into_iter()
+                .map(|violation| RpcViolation {
+                    rule: violation.erc7562_rule().map(str::to_string),
+                    message: violation.to_string(),
+                })
+                .collect(),
+            mempools: trace.mempools,
+        }
+    }
+}
+
+impl From<PhaseTrace> for RpcPhaseTrace {
+    fn from(phase: PhaseTrace) -> Self {
+        RpcPhaseTrace {
+            entity: phase.entity,
+            forbidden_opcodes_used: phase
+                .forbidden_opcodes_used
+                .into_iter()
+                .map(|(contract, opcode)| RpcOpcodeUse { contract, opcode })
+                .collect(),
+            forbidden_precompiles_used: phase
+                .forbidden_precompiles_used
+                .into_iter()
+                .map(|(contract, precompile)| RpcPrecompileUse {
+                    contract,
+                    precompile,
+                })
+                .collect(),
+            storage_accesses: phase
+                .storage_accesses
+                .into_iter()
+                .map(|access| RpcStorageAccess {
+                    address: access.address,
+                    slot: access.slot,
+                    read_value: access.read_value,
+                    writes: U64::from(access.writes),
+                    sender_associated: access.sender_associated,
+                    entity_associated: access.entity_associated,
+                })
+                .collect(),
+            called_banned_entry_point_method: phase.called_banned_entry_point_method,
+            called_non_entry_point_with_value: phase.called_non_entry_point_with_value,
+            ran_out_of_gas: phase.ran_out_of_gas,
+            undeployed_contract_accesses: phase.undeployed_contract_accesses,
+            ext_code_accesses: phase.ext_code_accesses,
+        }
+    }
+}
@@ -63,7 +63,7 @@ impl From<UserOperation> for RpcUserOperation {
 }
 
 impl FromRpc<RpcUserOperation> for UserOperation {
-    fn from_rpc(def: RpcUserOperation, chain_spec: &ChainSpec) -> Self {
+    fn from_rpc(def: RpcUserOperation, _entry_point: &Address, chain_spec: &ChainSpec) -> Self {
         UserOperationBuilder::new(
             chain_spec,
             UserOperationRequiredFields {
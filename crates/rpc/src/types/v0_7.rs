@@ -18,7 +18,7 @@ use rundler_types::{
     v0_7::{
         UserOperation, UserOperationBuilder, UserOperationOptionalGas, UserOperationRequiredFields,
     },
-    GasEstimate,
+    EntryPointVersion, GasEstimate,
 };
 use serde::{Deserialize, Serialize};
 
@@ -94,7 +94,7 @@ impl From<UserOperation> for RpcUserOperation {
 }
 
 impl FromRpc<RpcUserOperation> for UserOperation {
-    fn from_rpc(def: RpcUserOperation, chain_spec: &ChainSpec) -> Self {
+    fn from_rpc(def: RpcUserOperation, entry_point: &Address, chain_spec: &ChainSpec) -> Self {
         let mut builder = UserOperationBuilder::new(
             chain_spec,
             UserOperationRequiredFields {
@@ -127,6 +127,9 @@ impl FromRpc<RpcUserOperation> for UserOperation {
         if def.authorization_tuple.is_some() {
             builder = builder.authorization_tuple(def.authorization_tuple);
         }
+        if *entry_point == chain_spec.entry_point_address_v0_8 {
+            builder = builder.entry_point_version(EntryPointVersion::V0_8);
+        }
         builder.build()
     }
 }
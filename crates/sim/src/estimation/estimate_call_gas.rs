@@ -6,11 +6,14 @@ use rundler_contracts::{
     v0_6::CallGasEstimationProxy::TestCallGasResult,
     v0_7::CallGasEstimationProxy::CallGasEstimationProxyErrors,
 };
-use rundler_provider::{EntryPoint, SimulationProvider, StateOverride};
-use rundler_types::UserOperation;
+use rundler_provider::{EntryPoint, EvmProvider, SimulationProvider, StateOverride};
+use rundler_types::{chain::ChainSpec, UserOperation};
 use rundler_utils::authorization_utils;
 
-use super::Settings;
+use super::{
+    simulator::{HandleOpSimulator, SearchRunner},
+    Settings,
+};
 use crate::GasEstimationError;
 /// Gas estimates will be rounded up to the next multiple of this. Increasing
 /// this value reduces the number of rounds of `eth_call` needed in binary
@@ -51,10 +54,12 @@ pub trait CallGasEstimator: Send + Sync {
 /// Implementation of a call gas estimator which performs a binary search with
 /// the `target` and `targetData` arguments to `simulateHandleOp`
 #[derive(Debug)]
-pub struct CallGasEstimatorImpl<E, S> {
+pub struct CallGasEstimatorImpl<P, E, S> {
+    provider: P,
     entry_point: E,
     settings: Settings,
     specialization: S,
+    search_runner: SearchRunner,
 }
 
 /// Functions associated with a particular user operation version that
@@ -87,9 +92,10 @@ pub trait CallGasEstimatorSpecialization: Send + Sync {
 }
 
 #[async_trait]
-impl<UO, E, S> CallGasEstimator for CallGasEstimatorImpl<E, S>
+impl<UO, P, E, S> CallGasEstimator for CallGasEstimatorImpl<P, E, S>
 where
     UO: UserOperation,
+    P: EvmProvider,
     E: EntryPoint + SimulationProvider<UO = UO>,
     S: CallGasEstimatorSpecialization<UO = UO>,
 {
@@ -101,7 +107,6 @@ where
         block_hash: B256,
         mut state_override: StateOverride,
     ) -> Result<u128, GasEstimationError> {
-        let timer = std::time::Instant::now();
         self.specialization
             .add_proxy_to_overrides(*self.entry_point.address(), &mut state_override);
 
@@ -115,6 +120,92 @@ where
                 authorization_contract,
             );
         }
+
+        self.search_runner
+            .run(
+                &self.provider,
+                &self.entry_point,
+                block_hash,
+                &op,
+                |simulator| self.binary_search(simulator, &callless_op, &state_override),
+            )
+            .await
+    }
+
+    async fn simulate_handle_op_with_result(
+        &self,
+        op: Self::UO,
+        block_hash: B256,
+        mut state_override: StateOverride,
+    ) -> Result<(), GasEstimationError> {
+        self.specialization
+            .add_proxy_to_overrides(*self.entry_point.address(), &mut state_override);
+
+        let call_gas_limit = op.call_gas_limit();
+        let callless_op = self.specialization.get_op_with_no_call_gas(op);
+        let target_call_data = self
+            .specialization
+            .get_test_call_gas_calldata(callless_op.clone(), call_gas_limit);
+
+        let target_revert_data = self
+            .entry_point
+            .simulate_handle_op(
+                callless_op,
+                *self.entry_point.address(),
+                target_call_data,
+                block_hash.into(),
+                state_override.clone(),
+            )
+            .await?
+            .map_err(GasEstimationError::RevertInValidation)?
+            .target_result;
+
+        let result = TestCallGasResult::abi_decode(&target_revert_data, false)
+            .context("should decode revert data as TestCallGasResult")?;
+        if result.success {
+            Ok(())
+        } else {
+            let error = if let Ok(revert) = Revert::abi_decode(&result.revertData, false) {
+                GasEstimationError::RevertInCallWithMessage(revert.reason)
+            } else {
+                GasEstimationError::RevertInCallWithBytes(result.revertData)
+            };
+            Err(error)
+        }
+    }
+}
+
+impl<UO, P, E, S> CallGasEstimatorImpl<P, E, S>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UO>,
+    S: CallGasEstimatorSpecialization<UO = UO>,
+{
+    /// Creates a new call gas estimator
+    pub fn new(
+        chain_spec: ChainSpec,
+        provider: P,
+        entry_point: E,
+        settings: Settings,
+        specialization: S,
+    ) -> Self {
+        Self {
+            provider,
+            entry_point,
+            settings,
+            specialization,
+            search_runner: SearchRunner::new(&settings, &chain_spec, "call_gas"),
+        }
+    }
+
+    async fn binary_search(
+        &self,
+        mut simulator: HandleOpSimulator<'_, P, E>,
+        callless_op: &UO,
+        state_override: &StateOverride,
+    ) -> Result<u128, GasEstimationError> {
+        let timer = std::time::Instant::now();
         let mut min_gas = 0;
         let mut max_gas = self.settings.max_call_gas;
         let mut is_continuation = false;
@@ -127,13 +218,11 @@ where
                 GAS_ROUNDING.into(),
                 is_continuation,
             );
-            let target_revert_data = self
-                .entry_point
+            let target_revert_data = simulator
                 .simulate_handle_op(
                     callless_op.clone(),
                     *self.entry_point.address(),
                     target_call_data,
-                    block_hash.into(),
                     state_override.clone(),
                 )
                 .await?
@@ -202,62 +291,4 @@ where
             }
         }
     }
-
-    async fn simulate_handle_op_with_result(
-        &self,
-        op: Self::UO,
-        block_hash: B256,
-        mut state_override: StateOverride,
-    ) -> Result<(), GasEstimationError> {
-        self.specialization
-            .add_proxy_to_overrides(*self.entry_point.address(), &mut state_override);
-
-        let call_gas_limit = op.call_gas_limit();
-        let callless_op = self.specialization.get_op_with_no_call_gas(op);
-        let target_call_data = self
-            .specialization
-            .get_test_call_gas_calldata(callless_op.clone(), call_gas_limit);
-
-        let target_revert_data = self
-            .entry_point
-            .simulate_handle_op(
-                callless_op,
-                *self.entry_point.address(),
-                target_call_data,
-                block_hash.into(),
-                state_override.clone(),
-            )
-            .await?
-            .map_err(GasEstimationError::RevertInValidation)?
-            .target_result;
-
-        let result = TestCallGasResult::abi_decode(&target_revert_data, false)
-            .context("should decode revert data as TestCallGasResult")?;
-        if result.success {
-            Ok(())
-        } else {
-            let error = if let Ok(revert) = Revert::abi_decode(&result.revertData, false) {
-                GasEstimationError::RevertInCallWithMessage(revert.reason)
-            } else {
-                GasEstimationError::RevertInCallWithBytes(result.revertData)
-            };
-            Err(error)
-        }
-    }
-}
-
-impl<UO, E, S> CallGasEstimatorImpl<E, S>
-where
-    UO: UserOperation,
-    E: EntryPoint + SimulationProvider<UO = UO>,
-    S: CallGasEstimatorSpecialization<UO = UO>,
-{
-    /// Creates a new call gas estimator
-    pub fn new(entry_point: E, settings: Settings, specialization: S) -> Self {
-        Self {
-            entry_point,
-            settings,
-            specialization,
-        }
-    }
 }
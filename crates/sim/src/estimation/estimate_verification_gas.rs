@@ -5,7 +5,10 @@ use rundler_provider::{EntryPoint, EvmProvider, SimulationProvider, StateOverrid
 use rundler_types::{chain::ChainSpec, UserOperation};
 use rundler_utils::authorization_utils;
 
-use super::Settings;
+use super::{
+    simulator::{HandleOpSimulator, SearchRunner},
+    Settings,
+};
 use crate::GasEstimationError;
 
 /// Gas estimation will stop when the binary search bounds are within
@@ -55,6 +58,7 @@ pub struct VerificationGasEstimatorImpl<P, E> {
     provider: P,
     entry_point: E,
     settings: Settings,
+    search_runner: SearchRunner,
 }
 
 #[async_trait]
@@ -75,7 +79,6 @@ where
         get_op_with_limit: F,
     ) -> Result<u128, GasEstimationError> {
         let mut local_state_override = state_override.clone();
-        let timer = std::time::Instant::now();
         let paymaster_gas_fee = self.settings.verification_estimation_gas_fee;
         if let Some(au) = &op.authorization_tuple() {
             authorization_utils::apply_7702_overrides(
@@ -105,16 +108,61 @@ where
             get_op_with_limit(op.clone(), GetOpWithLimitArgs { gas, fee })
         };
 
+        let mut min_success_gas = self
+            .search_runner
+            .run(
+                &self.provider,
+                &self.entry_point,
+                block_hash,
+                op,
+                |simulator| {
+                    self.binary_search(simulator, &local_state_override, max_guess, &get_op)
+                },
+            )
+            .await?;
+
+        // If not using a paymaster, always add the cost of a native transfer to the verification gas.
+        // This may cause an over estimation when the account does have enough deposit to pay for the
+        // max cost, but it is better to overestimate than underestimate.
+        if op.paymaster().is_none() {
+            min_success_gas += self.chain_spec.deposit_transfer_overhead();
+        }
+
+        Ok(min_success_gas)
+    }
+}
+
+impl<UO, P, E> VerificationGasEstimatorImpl<P, E>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UO>,
+{
+    /// Create a new instance
+    pub fn new(chain_spec: ChainSpec, provider: P, entry_point: E, settings: Settings) -> Self {
+        let search_runner = SearchRunner::new(&settings, &chain_spec, "verification_gas");
+        Self {
+            chain_spec,
+            provider,
+            entry_point,
+            settings,
+            search_runner,
+        }
+    }
+
+    async fn binary_search<G: Send + Sync + Fn(u128) -> UO>(
+        &self,
+        mut simulator: HandleOpSimulator<'_, P, E>,
+        state_override: &StateOverride,
+        max_guess: u128,
+        get_op: &G,
+    ) -> Result<u128, GasEstimationError> {
+        let timer = std::time::Instant::now();
+
         // Make one attempt at max gas, to see if success is possible.
         // Capture the gas usage of this attempt and use as the initial guess in the binary search
-        let initial_op = get_op(max_guess);
-        let call = self
-            .entry_point
-            .get_simulate_handle_op_call(initial_op, local_state_override.clone());
-
-        let gas_used = self
-            .provider
-            .get_gas_used(call)
+        let gas_used = simulator
+            .get_gas_used(get_op(max_guess), state_override.clone())
             .await
             .context("failed to run initial guess")?;
 
@@ -128,35 +176,6 @@ where
             return Err(GasEstimationError::RevertInValidation(revert));
         }
 
-        let run_attempt_returning_error = |gas: u128, state_override: StateOverride| async move {
-            let op = get_op(gas);
-            let revert = self
-                .entry_point
-                .simulate_handle_op(
-                    op,
-                    Address::ZERO,
-                    Bytes::new(),
-                    block_hash.into(),
-                    state_override.clone(),
-                )
-                .await?
-                .err();
-
-            if let Some(revert) = revert {
-                if let Some(error_code) = revert.entry_point_error_code() {
-                    if OUT_OF_GAS_ERROR_CODES.contains(&error_code) {
-                        // This error occurs when out of gas, return false.
-                        return Ok(false);
-                    }
-                }
-                // This is a different error, return it
-                Err(GasEstimationError::RevertInValidation(revert))
-            } else {
-                // This succeeded, return true
-                Ok(true)
-            }
-        };
-
         let mut max_failure_gas = 1;
 
         let mut min_success_gas = self.settings.max_verification_gas;
@@ -172,10 +191,29 @@ where
             > (1.0 + GAS_ESTIMATION_ERROR_MARGIN)
         {
             num_rounds += 1;
-            if run_attempt_returning_error(guess, local_state_override.clone()).await? {
-                min_success_gas = guess;
-            } else {
-                max_failure_gas = guess;
+            let revert = simulator
+                .simulate_handle_op(
+                    get_op(guess),
+                    Address::ZERO,
+                    Bytes::new(),
+                    state_override.clone(),
+                )
+                .await?
+                .err();
+
+            match revert {
+                // This succeeded
+                None => min_success_gas = guess,
+                // This error occurs when out of gas
+                Some(revert)
+                    if revert
+                        .entry_point_error_code()
+                        .is_some_and(|code| OUT_OF_GAS_ERROR_CODES.contains(&code)) =>
+                {
+                    max_failure_gas = guess
+                }
+                // This is a different error, return it
+                Some(revert) => return Err(GasEstimationError::RevertInValidation(revert)),
             }
             guess = max_failure_gas.saturating_add(min_success_gas) / 2;
         }
@@ -185,30 +223,6 @@ where
             timer.elapsed().as_millis()
         );
 
-        // If not using a paymaster, always add the cost of a native transfer to the verification gas.
-        // This may cause an over estimation when the account does have enough deposit to pay for the
-        // max cost, but it is better to overestimate than underestimate.
-        if op.paymaster().is_none() {
-            min_success_gas += self.chain_spec.deposit_transfer_overhead();
-        }
-
         Ok(min_success_gas)
     }
 }
-
-impl<UO, P, E> VerificationGasEstimatorImpl<P, E>
-where
-    UO: UserOperation,
-    P: EvmProvider,
-    E: EntryPoint + SimulationProvider<UO = UO>,
-{
-    /// Create a new instance
-    pub fn new(chain_spec: ChainSpec, provider: P, entry_point: E, settings: Settings) -> Self {
-        Self {
-            chain_spec,
-            provider,
-            entry_point,
-            settings,
-        }
-    }
-}
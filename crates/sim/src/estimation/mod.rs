@@ -11,11 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::Bytes;
+use alloy_primitives::{Address, Bytes, B256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{ProviderError, StateOverride};
-use rundler_types::{GasEstimate, ValidationRevert};
+use rundler_provider::{ProviderError, SimulationProvider, StateOverride};
+use rundler_types::{aggregator::AggregatorConfigs, GasEstimate, ValidationRevert};
 
 use crate::precheck::MIN_CALL_GAS_LIMIT;
 
@@ -25,6 +25,7 @@ mod estimate_call_gas;
 pub use estimate_call_gas::{
     CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization,
 };
+mod simulator;
 
 /// Gas estimation module for Entry Point v0.6
 mod v0_6;
@@ -58,6 +59,9 @@ pub enum GasEstimationError {
     /// The total amount of gas used by the UO is greater than allowed
     #[error("total gas used by the user operation {0} is greater than the allowed limit: {1}")]
     GasTotalTooLarge(u128, u128),
+    /// The user operation uses an aggregator that is not trusted
+    #[error("aggregator {0} is not supported")]
+    UnsupportedAggregator(Address),
     /// Error from provider
     #[error(transparent)]
     ProviderError(#[from] ProviderError),
@@ -82,6 +86,37 @@ pub trait GasEstimator: Send + Sync {
     ) -> Result<GasEstimate, GasEstimationError>;
 }
 
+/// Returns the gas an op must add to its pre-verification gas for its share of the
+/// signature validation of its aggregator, or zero if it doesn't use one.
+///
+/// Errors if the op uses an aggregator that is not trusted. Skips the validation call when
+/// no aggregators are trusted, as aggregated ops are then rejected by the pool anyway.
+async fn aggregator_validation_gas<E: SimulationProvider>(
+    entry_point: &E,
+    aggregators: &AggregatorConfigs,
+    op: E::UO,
+    block_hash: B256,
+    state_override: StateOverride,
+) -> Result<u128, GasEstimationError> {
+    if aggregators.is_empty() {
+        return Ok(0);
+    }
+
+    let output = entry_point
+        .simulate_validation(op, Some(block_hash.into()), state_override)
+        .await?
+        .map_err(GasEstimationError::RevertInValidation)?;
+    let Some(aggregator_info) = output.aggregator_info else {
+        return Ok(0);
+    };
+    aggregators
+        .get(&aggregator_info.address)
+        .map(|config| config.validation_gas_per_op)
+        .ok_or(GasEstimationError::UnsupportedAggregator(
+            aggregator_info.address,
+        ))
+}
+
 /// Settings for gas estimation
 #[derive(Clone, Copy, Debug)]
 pub struct Settings {
@@ -103,6 +138,22 @@ pub struct Settings {
     /// gas price.
     /// Clients can use state overrides to set the balance of the fee-payer to at least this value.
     pub verification_estimation_gas_fee: u128,
+    /// Where the simulations of the gas limit binary searches are run
+    pub estimation_backend: EstimationBackend,
+}
+
+/// Where the simulations of the gas limit binary searches are run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum EstimationBackend {
+    /// Call `simulateHandleOp` on the node with `eth_call` for every step of the search
+    #[default]
+    Rpc,
+    /// Fork the state of the node once per search and run each step in an embedded EVM.
+    ///
+    /// Falls back to `Rpc` if the embedded EVM fails. Executes with Ethereum mainnet
+    /// rules, so a search that calls a chain specific precompile also falls back.
+    Local,
 }
 
 impl Settings {
@@ -0,0 +1,518 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Backends that run the `simulateHandleOp` calls of the gas limit binary searches.
+
+use std::{future::Future, time::Instant};
+
+use alloy_primitives::{address, Address, Bytes, TxKind, B256, U256};
+use alloy_sol_types::SolCall;
+use anyhow::{anyhow, Context};
+use metrics::{Counter, Histogram};
+use metrics_derive::Metrics;
+use revm::{
+    db::CacheDB,
+    primitives::{ExecutionResult as EvmExecutionResult, TxEnv},
+};
+use rundler_contracts::utils::GetGasUsed;
+use rundler_provider::{
+    AccountOverride, EntryPoint, EvmCall, EvmProvider, ExecutionResult, GasUsedResult,
+    ProviderResult, SimulationProvider, StateOverride,
+};
+use rundler_types::{chain::ChainSpec, UserOperation, ValidationRevert};
+
+use super::{EstimationBackend, Settings};
+use crate::{
+    local_evm::{self, BlockContext, EvmConfig, PrecompileInspector, ProviderDb},
+    GasEstimationError,
+};
+
+/// Address of the `GetGasUsed` helper in the embedded EVM. Never deployed on chain.
+const GET_GAS_USED_ADDRESS: Address = address!("3a5a6c3b4bd5a1cb0ee6c1bf7bd5b13e93bd6d1d");
+
+/// Runs `simulateHandleOp` for the steps of a single binary search, all at the same block.
+pub(crate) enum HandleOpSimulator<'a, P, E> {
+    /// Each step is an `eth_call` to the node
+    Rpc {
+        provider: &'a P,
+        entry_point: &'a E,
+        block_hash: B256,
+    },
+    /// Each step runs in an embedded EVM, which keeps the state loaded by earlier steps
+    Local(Box<LocalSimulator<'a, P, E>>),
+}
+
+pub(crate) struct LocalSimulator<'a, P, E> {
+    entry_point: &'a E,
+    db: CacheDB<ProviderDb<'a, P>>,
+    context: BlockContext,
+    gas_limit: u64,
+}
+
+impl<'a, UO, P, E> HandleOpSimulator<'a, P, E>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UO>,
+{
+    /// Forks the state of the node at `block_hash` into an embedded EVM
+    async fn local(
+        provider: &'a P,
+        entry_point: &'a E,
+        evm_config: &EvmConfig,
+        block_hash: B256,
+        gas_limit: u64,
+    ) -> anyhow::Result<Self> {
+        let block_id = block_hash.into();
+        let context = local_evm::load_block_context(provider, evm_config, block_id).await?;
+        Ok(Self::Local(Box::new(LocalSimulator {
+            entry_point,
+            db: CacheDB::new(ProviderDb::new(provider, block_id)),
+            context,
+            gas_limit,
+        })))
+    }
+
+    /// Calls `simulateHandleOp`, returning the execution result or the validation revert
+    pub(crate) async fn simulate_handle_op(
+        &mut self,
+        op: UO,
+        target: Address,
+        target_call_data: Bytes,
+        state_override: StateOverride,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        match self {
+            Self::Rpc {
+                entry_point,
+                block_hash,
+                ..
+            } => {
+                entry_point
+                    .simulate_handle_op(
+                        op,
+                        target,
+                        target_call_data,
+                        (*block_hash).into(),
+                        state_override,
+                    )
+                    .await
+            }
+            Self::Local(local) => {
+                let call = local.entry_point.get_simulate_handle_op_call(
+                    op,
+                    target,
+                    target_call_data,
+                    state_override,
+                );
+                match local.call(call)? {
+                    Ok(output) => E::decode_simulate_handle_op_output(&output),
+                    Err(revert_data) => E::decode_simulate_handle_ops_revert(&revert_data),
+                }
+            }
+        }
+    }
+
+    /// Measures the gas used by `simulateHandleOp` without a target
+    pub(crate) async fn get_gas_used(
+        &mut self,
+        op: UO,
+        state_override: StateOverride,
+    ) -> ProviderResult<GasUsedResult> {
+        match self {
+            Self::Rpc {
+                provider,
+                entry_point,
+                ..
+            } => {
+                let call = entry_point.get_simulate_handle_op_call(
+                    op,
+                    Address::ZERO,
+                    Bytes::new(),
+                    state_override,
+                );
+                provider.get_gas_used(call).await
+            }
+            Self::Local(local) => {
+                let EvmCall {
+                    to,
+                    data,
+                    value,
+                    mut state_override,
+                } = local.entry_point.get_simulate_handle_op_call(
+                    op,
+                    Address::ZERO,
+                    Bytes::new(),
+                    state_override,
+                );
+                state_override.insert(
+                    GET_GAS_USED_ADDRESS,
+                    AccountOverride {
+                        code: Some(GetGasUsed::DEPLOYED_BYTECODE.clone()),
+                        ..Default::default()
+                    },
+                );
+                let call = EvmCall {
+                    to: GET_GAS_USED_ADDRESS,
+                    data: GetGasUsed::getGasCall {
+                        target: to,
+                        value,
+                        data,
+                    }
+                    .abi_encode()
+                    .into(),
+                    value: U256::ZERO,
+                    state_override,
+                };
+
+                let output = local
+                    .call(call)?
+                    .map_err(|_| anyhow!("getGas should not revert"))?;
+                let ret = GetGasUsed::getGasCall::abi_decode_returns(&output, false)
+                    .context("should decode getGas output")?;
+                Ok(ret._0)
+            }
+        }
+    }
+}
+
+impl<P: EvmProvider, E> LocalSimulator<'_, P, E> {
+    /// Executes `call` on top of the forked state, returning its output or revert data.
+    ///
+    /// Overrides of earlier calls stay applied, so all calls of a search must use the
+    /// same overrides. Fails if the call reaches a precompile that the embedded EVM
+    /// doesn't implement, so that the search falls back to rpc.
+    fn call(&mut self, call: EvmCall) -> anyhow::Result<Result<Bytes, Bytes>> {
+        let tx_env = TxEnv {
+            transact_to: TxKind::Call(call.to),
+            data: call.data,
+            value: call.value,
+            gas_limit: self.gas_limit,
+            ..Default::default()
+        };
+
+        local_evm::run_blocking(|| {
+            local_evm::apply_state_override(&mut self.db, &call.state_override)?;
+            let mut inspector = PrecompileInspector::default();
+            let result =
+                local_evm::transact(&mut self.db, self.context.clone(), tx_env, &mut inspector)
+                    .context("local simulation should execute")?;
+            inspector.check()?;
+
+            match result {
+                EvmExecutionResult::Success { output, .. } => Ok(Ok(output.into_data())),
+                EvmExecutionResult::Revert { output, .. } => Ok(Err(output)),
+                EvmExecutionResult::Halt { reason, .. } => {
+                    Err(anyhow!("local simulation halted: {reason:?}"))
+                }
+            }
+        })
+    }
+}
+
+/// Runs binary searches on the configured backend, recording the latency of each backend
+#[derive(Debug)]
+pub(crate) struct SearchRunner {
+    backend: EstimationBackend,
+    evm_config: EvmConfig,
+    gas_limit: u64,
+    metrics: EstimationMetrics,
+}
+
+impl SearchRunner {
+    /// Creates a runner for the searches of the estimator named `estimator`
+    pub(crate) fn new(
+        settings: &Settings,
+        chain_spec: &ChainSpec,
+        estimator: &'static str,
+    ) -> Self {
+        Self {
+            backend: settings.estimation_backend,
+            evm_config: EvmConfig::new(chain_spec),
+            gas_limit: settings.max_simulate_handle_ops_gas,
+            metrics: EstimationMetrics::new_with_labels(&[("estimator", estimator)]),
+        }
+    }
+
+    /// Runs `search` at `block_hash` for `op`.
+    ///
+    /// With the local backend, the search is run again over RPC if the embedded EVM or
+    /// the requests loading its state fail.
+    pub(crate) async fn run<'a, UO, P, E, T, F, Fut>(
+        &self,
+        provider: &'a P,
+        entry_point: &'a E,
+        block_hash: B256,
+        op: &UO,
+        search: F,
+    ) -> Result<T, GasEstimationError>
+    where
+        UO: UserOperation,
+        P: EvmProvider,
+        E: EntryPoint + SimulationProvider<UO = UO>,
+        F: Fn(HandleOpSimulator<'a, P, E>) -> Fut,
+        Fut: Future<Output = Result<T, GasEstimationError>>,
+    {
+        // EIP-7702 delegations are not supported by the embedded EVM
+        if self.backend == EstimationBackend::Local && op.authorization_tuple().is_none() {
+            let timer = Instant::now();
+            let ret = match HandleOpSimulator::local(
+                provider,
+                entry_point,
+                &self.evm_config,
+                block_hash,
+                self.gas_limit,
+            )
+            .await
+            {
+                Ok(simulator) => search(simulator).await,
+                Err(error) => Err(error.into()),
+            };
+
+            match ret {
+                Err(
+                    error @ (GasEstimationError::ProviderError(_) | GasEstimationError::Other(_)),
+                ) => {
+                    tracing::warn!("local gas estimation failed, falling back to rpc: {error:?}");
+                    self.metrics.local_fallbacks.increment(1);
+                }
+                ret => {
+                    self.metrics
+                        .local_latency_ms
+                        .record(timer.elapsed().as_millis() as f64);
+                    return ret;
+                }
+            }
+        }
+
+        let timer = Instant::now();
+        let ret = search(HandleOpSimulator::Rpc {
+            provider,
+            entry_point,
+            block_hash,
+        })
+        .await;
+        self.metrics
+            .rpc_latency_ms
+            .record(timer.elapsed().as_millis() as f64);
+        ret
+    }
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "gas_estimation")]
+struct EstimationMetrics {
+    #[metric(describe = "the duration of binary searches run in the embedded EVM in ms.")]
+    local_latency_ms: Histogram,
+    #[metric(describe = "the duration of binary searches run over rpc in ms.")]
+    rpc_latency_ms: Histogram,
+    #[metric(describe = "the count of local binary searches that fell back to rpc.")]
+    local_fallbacks: Counter,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::bytes;
+    use alloy_provider::{Provider, ProviderBuilder};
+    use rundler_provider::{
+        new_alloy_da_gas_oracle, AlloyEntryPointV0_6, AlloyEntryPointV0_7, AlloyEvmProvider,
+        BlockId,
+    };
+    use rundler_types::{v0_6, v0_7};
+
+    use super::*;
+
+    /// Account that accepts any operation, with no deposit needed at zero fees
+    const SENDER: Address = address!("1111111111111111111111111111111111111111");
+    /// Target that returns its storage slot 0
+    const TARGET: Address = address!("2222222222222222222222222222222222222222");
+
+    fn state_override() -> StateOverride {
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            SENDER,
+            AccountOverride {
+                code: Some(bytes!("60206000f3")),
+                ..Default::default()
+            },
+        );
+        state_override.insert(
+            TARGET,
+            AccountOverride {
+                code: Some(bytes!("60005460005260206000f3")),
+                state_diff: Some(
+                    [(B256::ZERO, B256::from(U256::from(42)))]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+        );
+        state_override
+    }
+
+    /// Runs the same calls over rpc and in the embedded EVM, and checks that they agree
+    async fn assert_local_matches_rpc<UO, P, E>(
+        provider: &P,
+        entry_point: &E,
+        chain_spec: &ChainSpec,
+        op: UO,
+        state_override: StateOverride,
+    ) where
+        UO: UserOperation,
+        P: EvmProvider,
+        E: EntryPoint + SimulationProvider<UO = UO>,
+    {
+        let block_hash = provider
+            .get_block(BlockId::latest())
+            .await
+            .unwrap()
+            .unwrap()
+            .header
+            .hash;
+        let mut rpc = HandleOpSimulator::Rpc {
+            provider,
+            entry_point,
+            block_hash,
+        };
+        let mut local = HandleOpSimulator::local(
+            provider,
+            entry_point,
+            &EvmConfig::new(chain_spec),
+            block_hash,
+            10_000_000,
+        )
+        .await
+        .unwrap();
+
+        let rpc_result = rpc
+            .simulate_handle_op(op.clone(), TARGET, Bytes::new(), state_override.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let local_result = local
+            .simulate_handle_op(op.clone(), TARGET, Bytes::new(), state_override.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(local_result.pre_op_gas, rpc_result.pre_op_gas);
+        assert_eq!(local_result.paid, rpc_result.paid);
+        assert_eq!(local_result.valid_after, rpc_result.valid_after);
+        assert_eq!(local_result.valid_until, rpc_result.valid_until);
+        assert!(rpc_result.target_success);
+        assert_eq!(local_result.target_success, rpc_result.target_success);
+        assert_eq!(
+            rpc_result.target_result,
+            Bytes::from(B256::from(U256::from(42)))
+        );
+        assert_eq!(local_result.target_result, rpc_result.target_result);
+
+        let rpc_gas = rpc
+            .get_gas_used(op.clone(), state_override.clone())
+            .await
+            .unwrap();
+        let local_gas = local.get_gas_used(op, state_override).await.unwrap();
+        assert_eq!(local_gas.gasUsed, rpc_gas.gasUsed);
+        assert_eq!(local_gas.success, rpc_gas.success);
+        assert_eq!(local_gas.result, rpc_gas.result);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_local_matches_rpc_v0_6() {
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        let chain_spec = ChainSpec {
+            id: alloy_provider.get_chain_id().await.unwrap(),
+            ..Default::default()
+        };
+        let provider = AlloyEvmProvider::new(alloy_provider.clone());
+        let da_gas_oracle = new_alloy_da_gas_oracle(&chain_spec, alloy_provider.clone()).0;
+        let entry_point = AlloyEntryPointV0_6::new(
+            chain_spec.clone(),
+            1_000_000,
+            10_000_000,
+            1_000_000,
+            alloy_provider,
+            da_gas_oracle,
+        );
+
+        // v0.6 is not deployed on anvil
+        let mut state_override = state_override();
+        state_override.insert(
+            chain_spec.entry_point_address_v0_6,
+            AccountOverride {
+                code: Some(rundler_contracts::v0_6::ENTRY_POINT_V0_6_DEPLOYED_BYTECODE.clone()),
+                ..Default::default()
+            },
+        );
+
+        let op = v0_6::UserOperationBuilder::new(
+            &chain_spec,
+            v0_6::UserOperationRequiredFields {
+                sender: SENDER,
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: bytes!("12345678"),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 0,
+                max_priority_fee_per_gas: 0,
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            },
+            v0_6::ExtendedUserOperation {
+                authorization_tuple: None,
+            },
+        )
+        .build();
+
+        assert_local_matches_rpc(&provider, &entry_point, &chain_spec, op, state_override).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_local_matches_rpc_v0_7() {
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        let chain_spec = ChainSpec {
+            id: alloy_provider.get_chain_id().await.unwrap(),
+            ..Default::default()
+        };
+        let provider = AlloyEvmProvider::new(alloy_provider.clone());
+        let da_gas_oracle = new_alloy_da_gas_oracle(&chain_spec, alloy_provider.clone()).0;
+        let entry_point = AlloyEntryPointV0_7::new(
+            chain_spec.clone(),
+            1_000_000,
+            10_000_000,
+            1_000_000,
+            alloy_provider,
+            da_gas_oracle,
+        );
+
+        // the simulations override also installs the entry point code
+        let op = v0_7::UserOperationBuilder::new(
+            &chain_spec,
+            v0_7::UserOperationRequiredFields {
+                sender: SENDER,
+                nonce: U256::ZERO,
+                call_data: bytes!("12345678"),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 0,
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+
+        assert_local_matches_rpc(&provider, &entry_point, &chain_spec, op, state_override()).await;
+    }
+}
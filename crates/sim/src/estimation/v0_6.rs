@@ -27,6 +27,7 @@ use rundler_provider::{
     AccountOverride, DAGasProvider, EntryPoint, EvmProvider, SimulationProvider, StateOverride,
 };
 use rundler_types::{
+    aggregator::AggregatorConfigs,
     chain::ChainSpec,
     v0_6::{UserOperation, UserOperationBuilder, UserOperationOptionalGas},
     GasEstimate, UserOperation as _,
@@ -52,6 +53,7 @@ pub struct GasEstimator<P, E, VGE, CGE, F> {
     fee_estimator: F,
     verification_gas_estimator: VGE,
     call_gas_estimator: CGE,
+    aggregators: AggregatorConfigs,
 }
 
 #[async_trait::async_trait]
@@ -96,7 +98,8 @@ where
 
         let verification_future =
             self.estimate_verification_gas(&op, &full_op, block_hash, local_override.clone());
-        let call_future = self.estimate_call_gas(&op, full_op.clone(), block_hash, local_override);
+        let call_future =
+            self.estimate_call_gas(&op, full_op.clone(), block_hash, local_override.clone());
 
         // Not try_join! because then the output is nondeterministic if both
         // verification and call estimation fail.
@@ -107,10 +110,33 @@ where
         let verification_gas_limit = verification_gas_limit?;
         let call_gas_limit = call_gas_limit?;
 
-        // Verify total gas limit
         let mut op_with_gas = full_op;
         op_with_gas.verification_gas_limit = verification_gas_limit;
         op_with_gas.call_gas_limit = call_gas_limit;
+
+        // aggregated ops pay for their share of the aggregator's signature validation
+        let pre_verification_gas = if op.pre_verification_gas.is_some_and(|pvg| pvg != 0) {
+            pre_verification_gas
+        } else {
+            // no fees, so that validation doesn't require a prefund
+            let validation_op = UserOperation {
+                max_fee_per_gas: 0,
+                max_priority_fee_per_gas: 0,
+                ..op_with_gas.clone()
+            };
+            pre_verification_gas
+                + super::aggregator_validation_gas(
+                    &self.entry_point,
+                    &self.aggregators,
+                    validation_op,
+                    block_hash,
+                    local_override,
+                )
+                .await?
+        };
+        op_with_gas.pre_verification_gas = pre_verification_gas;
+
+        // Verify total gas limit
         // require that this can fit in a bundle of size 1
         let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
         if gas_limit > self.settings.max_total_execution_gas {
@@ -134,7 +160,7 @@ impl<P, E, F>
         P,
         E,
         VerificationGasEstimatorImpl<P, E>,
-        CallGasEstimatorImpl<E, CallGasEstimatorSpecializationV06>,
+        CallGasEstimatorImpl<P, E, CallGasEstimatorSpecializationV06>,
         F,
     >
 where
@@ -164,6 +190,8 @@ where
             settings,
         );
         let call_gas_estimator = CallGasEstimatorImpl::new(
+            chain_spec.clone(),
+            provider.clone(),
             entry_point.clone(),
             settings,
             CallGasEstimatorSpecializationV06 {
@@ -178,8 +206,16 @@ where
             fee_estimator,
             verification_gas_estimator,
             call_gas_estimator,
+            aggregators: AggregatorConfigs::default(),
         }
     }
+
+    /// Set the trusted signature aggregators, whose per-op validation gas is added to the
+    /// estimated pre-verification gas of the ops that use them
+    pub fn with_aggregators(mut self, aggregators: AggregatorConfigs) -> Self {
+        self.aggregators = aggregators;
+        self
+    }
 }
 
 impl<P, E, VGE, CGE, F> GasEstimator<P, E, VGE, CGE, F>
@@ -459,7 +495,7 @@ mod tests {
             VERIFICATION_GAS_BUFFER_PERCENT,
         },
         simulation::v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
-        VerificationGasEstimatorImpl,
+        EstimationBackend, VerificationGasEstimatorImpl,
     };
 
     // Due to https://github.com/asomers/mockall/blob/master/mockall/examples/synchronization.rs
@@ -475,8 +511,11 @@ mod tests {
     // Alises for complex types (which also satisfy Clippy)
     type VerificationGasEstimatorWithMocks =
         VerificationGasEstimatorImpl<Arc<MockEvmProvider>, Arc<MockEntryPointV0_6>>;
-    type CallGasEstimatorWithMocks =
-        CallGasEstimatorImpl<Arc<MockEntryPointV0_6>, CallGasEstimatorSpecializationV06>;
+    type CallGasEstimatorWithMocks = CallGasEstimatorImpl<
+        Arc<MockEvmProvider>,
+        Arc<MockEntryPointV0_6>,
+        CallGasEstimatorSpecializationV06,
+    >;
     type GasEstimatorWithMocks = GasEstimator<
         Arc<MockEvmProvider>,
         Arc<MockEntryPointV0_6>,
@@ -491,13 +530,12 @@ mod tests {
 
         // Fill in concrete implementations of call data and
         // `simulation_should_revert`
-        entry
-            .expect_get_simulate_handle_op_call()
-            .returning(|op, state_override| {
+        entry.expect_get_simulate_handle_op_call().returning(
+            |op, target, target_call_data, state_override| {
                 let data = IEntryPoint::simulateHandleOpCall {
                     op: op.into(),
-                    target: Address::ZERO,
-                    targetCallData: Bytes::new(),
+                    target,
+                    targetCallData: target_call_data,
                 }
                 .abi_encode()
                 .into();
@@ -508,7 +546,8 @@ mod tests {
                     value: U256::ZERO,
                     state_override,
                 }
-            });
+            },
+        );
         entry.expect_simulation_should_revert().return_const(true);
 
         entry.expect_address().return_const(Address::ZERO);
@@ -548,6 +587,7 @@ mod tests {
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
             max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
             verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Rpc,
         };
         let estimator = create_custom_estimator(
             ChainSpec::default(),
@@ -652,6 +692,7 @@ mod tests {
             max_total_execution_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Rpc,
         };
 
         // Chose arbitrum
@@ -728,6 +769,7 @@ mod tests {
             max_total_execution_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Rpc,
         };
 
         // Chose OP
@@ -1117,6 +1159,62 @@ mod tests {
         assert_eq!(estimation, expected);
     }
 
+    #[tokio::test]
+    async fn test_estimate_call_gas_local_falls_back_to_rpc() {
+        let (mut entry, mut provider) = create_base_config();
+
+        let gas_estimate = 100_000;
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(gas_estimate),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        // the embedded EVM can't fork the block, so the search is run over rpc
+        provider
+            .expect_get_block()
+            .times(1)
+            .returning(|_a| Ok(None));
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let settings = Settings {
+            max_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_call_gas: TEST_MAX_GAS_LIMITS,
+            max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
+            max_total_execution_gas: TEST_MAX_GAS_LIMITS,
+            max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Local,
+        };
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+        let optional_op = demo_user_op_optional_gas(None);
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .estimate_call_gas(&optional_op, user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(estimation, gas_estimate + CALL_GAS_BUFFER_VALUE);
+    }
+
     #[tokio::test]
     async fn test_estimate_call_gas_error() {
         let (mut entry, mut provider) = create_base_config();
@@ -1309,6 +1407,7 @@ mod tests {
             max_total_execution_gas: 10,
             max_simulate_handle_ops_gas: 10,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Rpc,
         };
 
         create_custom_estimator(
@@ -16,20 +16,24 @@ use std::{cmp, ops::Add};
 use alloy_primitives::{Address, Bytes, B256, U256};
 use alloy_sol_types::SolInterface;
 use rand::Rng;
-use rundler_contracts::v0_7::{
-    CallGasEstimationProxy::{
-        estimateCallGasCall, testCallGasCall, CallGasEstimationProxyCalls, EstimateCallGasArgs,
+use rundler_contracts::{
+    v0_7::{
+        CallGasEstimationProxy::{
+            estimateCallGasCall, testCallGasCall, CallGasEstimationProxyCalls, EstimateCallGasArgs,
+        },
+        CALL_GAS_ESTIMATION_PROXY_V0_7_DEPLOYED_BYTECODE,
+        ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE,
     },
-    CALL_GAS_ESTIMATION_PROXY_V0_7_DEPLOYED_BYTECODE,
-    ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE,
+    v0_8::ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE,
 };
 use rundler_provider::{
     AccountOverride, DAGasProvider, EntryPoint, EvmProvider, SimulationProvider, StateOverride,
 };
 use rundler_types::{
+    aggregator::AggregatorConfigs,
     chain::ChainSpec,
     v0_7::{UserOperation, UserOperationBuilder, UserOperationOptionalGas},
-    GasEstimate, UserOperation as _,
+    EntryPointVersion, GasEstimate, UserOperation as _,
 };
 use rundler_utils::math;
 use tokio::join;
@@ -40,15 +44,17 @@ use crate::{
     VerificationGasEstimator, VerificationGasEstimatorImpl, MIN_CALL_GAS_LIMIT,
 };
 
-/// Gas estimator for entry point v0.7
+/// Gas estimator for entry point v0.7, also used for entry point v0.8
 pub struct GasEstimator<P, E, VGE, CGE, F> {
     chain_spec: ChainSpec,
+    entry_point_version: EntryPointVersion,
     provider: P,
     entry_point: E,
     settings: Settings,
     fee_estimator: F,
     verification_gas_estimator: VGE,
     call_gas_estimator: CGE,
+    aggregators: AggregatorConfigs,
 }
 
 #[async_trait::async_trait]
@@ -90,6 +96,7 @@ where
                 settings.max_verification_gas,
                 settings.max_paymaster_verification_gas,
             )
+            .entry_point_version(self.entry_point_version)
             .pre_verification_gas(pre_verification_gas)
             .build();
 
@@ -103,7 +110,7 @@ where
             state_override.clone(),
         );
         let call_gas_future =
-            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override);
+            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override.clone());
 
         // Not try_join! because then the output is nondeterministic if multiple calls fail.
         let timer = std::time::Instant::now();
@@ -118,12 +125,34 @@ where
         let paymaster_verification_gas_limit = paymaster_verification_gas_limit?;
         let call_gas_limit = call_gas_limit?;
 
-        // check the total gas limit
         let mut op_with_gas = full_op;
         op_with_gas.pre_verification_gas = pre_verification_gas;
         op_with_gas.call_gas_limit = call_gas_limit;
         op_with_gas.verification_gas_limit = verification_gas_limit;
         op_with_gas.paymaster_verification_gas_limit = paymaster_verification_gas_limit;
+
+        // aggregated ops pay for their share of the aggregator's signature validation
+        let pre_verification_gas = if op.pre_verification_gas.is_some_and(|pvg| pvg != 0) {
+            pre_verification_gas
+        } else {
+            // no fees, so that validation doesn't require a prefund
+            let validation_op = UserOperationBuilder::from_uo(op_with_gas.clone(), &self.chain_spec)
+                .max_fee_per_gas(0)
+                .max_priority_fee_per_gas(0)
+                .build();
+            pre_verification_gas
+                + super::aggregator_validation_gas(
+                    &self.entry_point,
+                    &self.aggregators,
+                    validation_op,
+                    block_hash,
+                    state_override,
+                )
+                .await?
+        };
+        op_with_gas.pre_verification_gas = pre_verification_gas;
+
+        // check the total gas limit
         // require that this can fit in a bundle of size 1
         let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
         if gas_limit > self.settings.max_total_execution_gas {
@@ -149,7 +178,7 @@ impl<P, E, F>
         P,
         E,
         VerificationGasEstimatorImpl<P, E>,
-        CallGasEstimatorImpl<E, CallGasEstimatorSpecializationV07>,
+        CallGasEstimatorImpl<P, E, CallGasEstimatorSpecializationV07>,
         F,
     >
 where
@@ -167,6 +196,42 @@ where
         entry_point: E,
         settings: Settings,
         fee_estimator: F,
+    ) -> Self {
+        Self::new_with_version(
+            EntryPointVersion::V0_7,
+            chain_spec,
+            provider,
+            entry_point,
+            settings,
+            fee_estimator,
+        )
+    }
+
+    /// Create a new gas estimator for entry point v0.8
+    pub fn new_v0_8(
+        chain_spec: ChainSpec,
+        provider: P,
+        entry_point: E,
+        settings: Settings,
+        fee_estimator: F,
+    ) -> Self {
+        Self::new_with_version(
+            EntryPointVersion::V0_8,
+            chain_spec,
+            provider,
+            entry_point,
+            settings,
+            fee_estimator,
+        )
+    }
+
+    fn new_with_version(
+        entry_point_version: EntryPointVersion,
+        chain_spec: ChainSpec,
+        provider: P,
+        entry_point: E,
+        settings: Settings,
+        fee_estimator: F,
     ) -> Self {
         if let Some(err) = settings.validate() {
             panic!("Invalid gas estimator settings: {}", err);
@@ -179,22 +244,34 @@ where
             settings,
         );
         let call_gas_estimator = CallGasEstimatorImpl::new(
+            chain_spec.clone(),
+            provider.clone(),
             entry_point.clone(),
             settings,
             CallGasEstimatorSpecializationV07 {
                 chain_spec: chain_spec.clone(),
+                entry_point_version,
             },
         );
         Self {
             chain_spec,
+            entry_point_version,
             provider,
             entry_point,
             settings,
             fee_estimator,
             verification_gas_estimator,
             call_gas_estimator,
+            aggregators: AggregatorConfigs::default(),
         }
     }
+
+    /// Set the trusted signature aggregators, whose per-op validation gas is added to the
+    /// estimated pre-verification gas of the ops that use them
+    pub fn with_aggregators(mut self, aggregators: AggregatorConfigs) -> Self {
+        self.aggregators = aggregators;
+        self
+    }
 }
 
 impl<P, E, VGE, CGE, F> GasEstimator<P, E, VGE, CGE, F>
@@ -408,10 +485,11 @@ where
 }
 
 /// Implementation of functions that specialize the call gas estimator to the
-/// v0.7 entry point.
+/// v0.7 and v0.8 entry points.
 #[derive(Debug)]
 pub struct CallGasEstimatorSpecializationV07 {
     chain_spec: ChainSpec,
+    entry_point_version: EntryPointVersion,
 }
 
 impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV07 {
@@ -424,11 +502,15 @@ impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV07 {
         // intentionally get bad estimates by interacting with the hardcoded
         // address.
         let moved_entry_point_address: Address = rand::thread_rng().gen();
+        let simulations_bytecode = match self.entry_point_version {
+            EntryPointVersion::V0_8 => ENTRY_POINT_SIMULATIONS_V0_8_DEPLOYED_BYTECODE.clone(),
+            _ => ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone(),
+        };
 
         state_override.insert(
             moved_entry_point_address,
             AccountOverride {
-                code: Some(ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone()),
+                code: Some(simulations_bytecode),
                 ..Default::default()
             },
         );
@@ -509,18 +591,26 @@ mod tests {
         CallGasEstimationProxy::TestCallGasResult, IEntryPointSimulations,
     };
     use rundler_provider::{EvmCall, ExecutionResult, MockEntryPointV0_7, MockEvmProvider};
-    use rundler_types::v0_7::UserOperationOptionalGas;
+    use rundler_types::{
+        aggregator::{AggregatorConfig, AggregatorType},
+        v0_7::UserOperationOptionalGas,
+        AggregatorInfo, StakeInfo, ValidationOutput, ValidationReturnInfo,
+    };
 
     use super::*;
     use crate::{
-        estimation::estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, GasEstimator as _,
+        estimation::estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, EstimationBackend,
+        GasEstimator as _,
     };
 
     // Alises for complex types (which also satisfy Clippy)
     type VerificationGasEstimatorWithMocks =
         VerificationGasEstimatorImpl<Arc<MockEvmProvider>, Arc<MockEntryPointV0_7>>;
-    type CallGasEstimatorWithMocks =
-        CallGasEstimatorImpl<Arc<MockEntryPointV0_7>, CallGasEstimatorSpecializationV07>;
+    type CallGasEstimatorWithMocks = CallGasEstimatorImpl<
+        Arc<MockEvmProvider>,
+        Arc<MockEntryPointV0_7>,
+        CallGasEstimatorSpecializationV07,
+    >;
     type GasEstimatorWithMocks = GasEstimator<
         Arc<MockEvmProvider>,
         Arc<MockEntryPointV0_7>,
@@ -535,13 +625,12 @@ mod tests {
 
         // Fill in concrete implementations of call data and
         // `simulation_should_revert`
-        entry
-            .expect_get_simulate_handle_op_call()
-            .returning(|op, state_override| {
+        entry.expect_get_simulate_handle_op_call().returning(
+            |op, target, target_call_data, state_override| {
                 let data = IEntryPointSimulations::simulateHandleOpCall {
                     op: op.pack(),
-                    target: Address::ZERO,
-                    targetCallData: Bytes::new(),
+                    target,
+                    targetCallData: target_call_data,
                 }
                 .abi_encode()
                 .into();
@@ -552,7 +641,8 @@ mod tests {
                     value: U256::ZERO,
                     state_override,
                 }
-            });
+            },
+        );
         entry.expect_simulation_should_revert().return_const(true);
 
         entry.expect_address().return_const(Address::ZERO);
@@ -590,6 +680,7 @@ mod tests {
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
             max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
             verification_estimation_gas_fee: 1_000_000_000_000,
+            estimation_backend: EstimationBackend::Rpc,
         };
         let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
         (estimator, settings)
@@ -757,6 +848,98 @@ mod tests {
         );
     }
 
+    fn create_aggregator_config(
+        aggregator: Option<Address>,
+    ) -> (MockEntryPointV0_7, MockEvmProvider) {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+        entry
+            .expect_simulate_validation()
+            .returning(move |_a, _b, _c| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo::default(),
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: aggregator.map(|address| AggregatorInfo {
+                        address,
+                        stake_info: StakeInfo::default(),
+                    }),
+                }))
+            });
+
+        (entry, provider)
+    }
+
+    #[tokio::test]
+    async fn test_aggregator_validation_gas() {
+        let aggregator = Address::random();
+        let aggregators = AggregatorConfigs::from(vec![AggregatorConfig {
+            address: aggregator,
+            aggregator_type: AggregatorType::Bls,
+            validation_gas_per_op: 10_000,
+        }]);
+
+        let mut optional_op = demo_user_op_optional_gas(None);
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+
+        let (entry, provider) = create_aggregator_config(None);
+        let (estimator, _) = create_estimator(entry, provider);
+        let unaggregated = estimator
+            .with_aggregators(aggregators.clone())
+            .estimate_op_gas(optional_op.clone(), StateOverride::default())
+            .await
+            .unwrap();
+
+        let (entry, provider) = create_aggregator_config(Some(aggregator));
+        let (estimator, _) = create_estimator(entry, provider);
+        let aggregated = estimator
+            .with_aggregators(aggregators.clone())
+            .estimate_op_gas(optional_op.clone(), StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            aggregated.pre_verification_gas,
+            unaggregated.pre_verification_gas + 10_000
+        );
+
+        let untrusted = Address::random();
+        let (entry, provider) = create_aggregator_config(Some(untrusted));
+        let (estimator, _) = create_estimator(entry, provider);
+        let err = estimator
+            .with_aggregators(aggregators)
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err,
+            GasEstimationError::UnsupportedAggregator(address) if address == untrusted
+        ));
+    }
+
     #[tokio::test]
     async fn test_provided_reverts() {
         let (mut entry, mut provider) = create_base_config();
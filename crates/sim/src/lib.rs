@@ -35,9 +35,9 @@ mod estimation;
 #[cfg(feature = "test-utils")]
 pub use estimation::MockGasEstimator;
 pub use estimation::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
-    GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7, Settings as EstimationSettings,
-    VerificationGasEstimator, VerificationGasEstimatorImpl,
+    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, EstimationBackend,
+    GasEstimationError, GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7,
+    Settings as EstimationSettings, VerificationGasEstimator, VerificationGasEstimatorImpl,
 };
 
 pub mod gas;
@@ -45,6 +45,8 @@ pub mod gas;
 pub use gas::MockFeeEstimator;
 pub use gas::{FeeEstimator, PriorityFeeMode};
 
+mod local_evm;
+
 mod precheck;
 #[cfg(feature = "test-utils")]
 pub use precheck::MockPrechecker;
@@ -58,8 +60,8 @@ pub mod simulation;
 #[cfg(feature = "test-utils")]
 pub use simulation::MockSimulator;
 pub use simulation::{
-    MempoolConfig, MempoolConfigs, Settings as SimulationSettings, SimulationError,
-    SimulationResult, Simulator,
+    MempoolConfig, MempoolConfigs, PhaseTrace, Settings as SimulationSettings, SimulationError,
+    SimulationResult, Simulator, StorageAccessTrace, ValidationTrace, ValidationTracer,
 };
 
 mod types;
@@ -0,0 +1,500 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Execution of calls in an embedded EVM, backed by state loaded from the node.
+
+use std::future::Future;
+
+use alloy_primitives::{address, Address, B256, U256};
+use anyhow::{bail, Context};
+use revm::{
+    db::CacheDB,
+    inspector_handle_register,
+    interpreter::{CallInputs, CallOutcome},
+    primitives::{
+        AccountInfo, BlobExcessGasAndPrice, BlockEnv, Bytecode, EVMError, ExecutionResult, SpecId,
+        TxEnv, TxKind,
+    },
+    Database, DatabaseRef, Evm, EvmContext, GetInspector, Inspector,
+};
+use rundler_provider::{
+    BlockHeader, BlockId, EvmProvider, ProviderError, StateOverride, TransactionRequest,
+};
+use rundler_types::chain::ChainSpec;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Highest address of the range where chains place their precompiles and system contracts,
+/// e.g. Arbitrum's precompiles from 0x64 and RIP-7212's P256 verifier at 0x100.
+const MAX_PRECOMPILE_ADDRESS: Address = address!("000000000000000000000000000000000000ffff");
+
+/// Read-only database that loads accounts and storage from the node at a fixed block.
+///
+/// Every lookup blocks on a request to the node, so it must only be used inside
+/// [`run_blocking`]. Wrap in a [`CacheDB`] so each value is only fetched once.
+pub(crate) struct ProviderDb<'a, P> {
+    provider: &'a P,
+    block_id: BlockId,
+    handle: Handle,
+}
+
+impl<'a, P: EvmProvider> ProviderDb<'a, P> {
+    pub(crate) fn new(provider: &'a P, block_id: BlockId) -> Self {
+        Self {
+            provider,
+            block_id,
+            handle: Handle::current(),
+        }
+    }
+
+    fn block_on<F: Future>(&self, f: F) -> F::Output {
+        self.handle.block_on(f)
+    }
+}
+
+impl<P: EvmProvider> DatabaseRef for ProviderDb<'_, P> {
+    type Error = ProviderError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let block = Some(self.block_id);
+        let (balance, nonce, code) = self.block_on(async {
+            tokio::try_join!(
+                self.provider.get_balance(address, block),
+                self.provider.get_transaction_count_at(address, block),
+                self.provider.get_code(address, block),
+            )
+        })?;
+        let code = Bytecode::new_raw(code);
+        Ok(Some(AccountInfo::new(
+            balance,
+            nonce,
+            code.hash_slow(),
+            code,
+        )))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is always loaded together with its account, so a lookup by hash
+        // means the account was never loaded.
+        Err(ProviderError::Other(anyhow::anyhow!(
+            "code with hash {code_hash:?} was not loaded"
+        )))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.block_on(
+            self.provider
+                .get_storage_at(address, index, Some(self.block_id)),
+        )
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let block = self.block_on(self.provider.get_block(BlockId::number(number)))?;
+        Ok(block.map(|b| b.header.hash).unwrap_or_default())
+    }
+}
+
+/// Runs `f`, which may block on requests to the node, without stalling other
+/// tasks on the runtime.
+///
+/// Requires a multi-threaded tokio runtime.
+pub(crate) fn run_blocking<F, R>(f: F) -> anyhow::Result<R>
+where
+    F: FnOnce() -> anyhow::Result<R>,
+{
+    if Handle::current().runtime_flavor() == RuntimeFlavor::CurrentThread {
+        bail!("local EVM execution requires a multi-threaded runtime");
+    }
+    tokio::task::block_in_place(f)
+}
+
+/// Chain level configuration of the embedded EVM.
+#[derive(Clone, Debug)]
+pub(crate) struct EvmConfig {
+    chain_id: u64,
+    hardfork: Option<String>,
+}
+
+impl EvmConfig {
+    pub(crate) fn new(chain_spec: &ChainSpec) -> Self {
+        Self {
+            chain_id: chain_spec.id,
+            hardfork: chain_spec.evm_hardfork.clone(),
+        }
+    }
+
+    /// Hardfork to execute `header`'s block with
+    fn spec_id(&self, header: &BlockHeader) -> anyhow::Result<SpecId> {
+        let Some(name) = &self.hardfork else {
+            return Ok(header_spec_id(header));
+        };
+        match SpecId::from(name.as_str()) {
+            // unknown names are parsed as the latest hardfork
+            SpecId::LATEST => bail!("unknown evm_hardfork {name} in chain spec"),
+            spec_id => Ok(spec_id),
+        }
+    }
+}
+
+/// Environment of the block that calls are executed on top of.
+#[derive(Clone, Debug)]
+pub(crate) struct BlockContext {
+    chain_id: u64,
+    spec_id: SpecId,
+    block_env: BlockEnv,
+}
+
+#[cfg(test)]
+impl BlockContext {
+    pub(crate) fn new(spec_id: SpecId, block_env: BlockEnv) -> Self {
+        Self {
+            chain_id: 1,
+            spec_id,
+            block_env,
+        }
+    }
+}
+
+/// Loads the environment of `block_id` from the node.
+///
+/// The hardfork is taken from the chain spec if set, and otherwise derived from the
+/// header of the block.
+pub(crate) async fn load_block_context<P: EvmProvider>(
+    provider: &P,
+    config: &EvmConfig,
+    block_id: BlockId,
+) -> anyhow::Result<BlockContext> {
+    let block = provider
+        .get_block(block_id)
+        .await?
+        .with_context(|| format!("block {block_id:?} should exist"))?;
+    let header = &block.header;
+
+    let block_env = BlockEnv {
+        number: U256::from(header.number),
+        coinbase: header.beneficiary,
+        timestamp: U256::from(header.timestamp),
+        gas_limit: U256::from(header.gas_limit),
+        basefee: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+        difficulty: header.difficulty,
+        prevrandao: Some(header.mix_hash),
+        blob_excess_gas_and_price: Some(BlobExcessGasAndPrice::new(
+            header.excess_blob_gas.unwrap_or_default(),
+        )),
+    };
+    Ok(BlockContext {
+        chain_id: config.chain_id,
+        spec_id: config.spec_id(header)?,
+        block_env,
+    })
+}
+
+/// Latest hardfork whose header fields are present in `header`.
+///
+/// Chains that don't add these fields to their headers must set `evm_hardfork` in
+/// their chain spec.
+fn header_spec_id(header: &BlockHeader) -> SpecId {
+    if header.requests_hash.is_some() {
+        SpecId::PRAGUE
+    } else if header.parent_beacon_block_root.is_some() || header.excess_blob_gas.is_some() {
+        SpecId::CANCUN
+    } else if header.withdrawals_root.is_some() {
+        SpecId::SHANGHAI
+    } else if header.base_fee_per_gas.is_some() {
+        if header.difficulty.is_zero() {
+            SpecId::MERGE
+        } else {
+            SpecId::LONDON
+        }
+    } else {
+        SpecId::BERLIN
+    }
+}
+
+/// Converts a call request into a transaction environment with the same
+/// semantics as `eth_call`: no gas is charged and the nonce is not checked.
+pub(crate) fn tx_env(tx: &TransactionRequest) -> anyhow::Result<TxEnv> {
+    let Some(TxKind::Call(to)) = tx.to else {
+        bail!("local EVM execution requires a call target");
+    };
+
+    Ok(TxEnv {
+        caller: tx.from.unwrap_or_default(),
+        transact_to: TxKind::Call(to),
+        gas_limit: tx.gas.context("call should have a gas limit")?,
+        gas_price: U256::ZERO,
+        value: tx.value.unwrap_or_default(),
+        data: tx.input.input().cloned().unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// Applies `eth_call` style state overrides to the database.
+pub(crate) fn apply_state_override<DB: DatabaseRef>(
+    db: &mut CacheDB<DB>,
+    state_override: &StateOverride,
+) -> Result<(), DB::Error> {
+    for (address, account) in state_override {
+        let mut info = db.basic(*address)?.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce;
+        }
+        if let Some(code) = &account.code {
+            let code = Bytecode::new_raw(code.clone());
+            info.code_hash = code.hash_slow();
+            info.code = Some(code);
+        }
+        db.insert_account_info(*address, info);
+
+        if let Some(state) = &account.state {
+            db.replace_account_storage(
+                *address,
+                state
+                    .iter()
+                    .map(|(slot, value)| {
+                        (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))
+                    })
+                    .collect(),
+            )?;
+        }
+        if let Some(state_diff) = &account.state_diff {
+            for (slot, value) in state_diff {
+                db.insert_account_storage(
+                    *address,
+                    U256::from_be_bytes(slot.0),
+                    U256::from_be_bytes(value.0),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `address` is in the range reserved for precompiles but isn't a
+/// precompile of the embedded EVM, so a call to it likely targets a chain specific
+/// precompile and would succeed without executing any code.
+fn is_unknown_precompile<DB: Database>(context: &EvmContext<DB>, address: Address) -> bool {
+    !address.is_zero()
+        && address <= MAX_PRECOMPILE_ADDRESS
+        && !context.precompiles.contains(&address)
+}
+
+/// Records the first call to a precompile that the embedded EVM doesn't implement.
+#[derive(Debug, Default)]
+pub(crate) struct PrecompileInspector {
+    unknown_precompile: Option<Address>,
+}
+
+impl PrecompileInspector {
+    /// Fails if an unknown precompile was called, as the result of the execution
+    /// doesn't match the chain's.
+    pub(crate) fn check(&self) -> anyhow::Result<()> {
+        if let Some(address) = self.unknown_precompile {
+            bail!("call to precompile {address} not supported by the embedded EVM");
+        }
+        Ok(())
+    }
+}
+
+impl<DB: Database> Inspector<DB> for PrecompileInspector {
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if self.unknown_precompile.is_none()
+            && is_unknown_precompile(context, inputs.bytecode_address)
+        {
+            self.unknown_precompile = Some(inputs.bytecode_address);
+        }
+        None
+    }
+}
+
+/// Executes a transaction with `inspector` attached, without committing its state changes.
+pub(crate) fn transact<DB, I>(
+    db: DB,
+    context: BlockContext,
+    tx_env: TxEnv,
+    inspector: I,
+) -> Result<ExecutionResult, EVMError<DB::Error>>
+where
+    DB: Database,
+    I: GetInspector<DB>,
+{
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_external_context(inspector)
+        .with_spec_id(context.spec_id)
+        .modify_cfg_env(|cfg| {
+            cfg.chain_id = context.chain_id;
+            cfg.disable_base_fee = true;
+            cfg.disable_block_gas_limit = true;
+        })
+        .modify_block_env(|block| *block = context.block_env)
+        .modify_tx_env(|tx| *tx = tx_env)
+        .append_handler_register(inspector_handle_register)
+        .build();
+
+    Ok(evm.transact()?.result)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{bytes, B256};
+    use revm::{db::EmptyDB, inspectors::NoOpInspector};
+    use rundler_provider::{Block, MockEvmProvider};
+
+    use super::*;
+
+    #[test]
+    fn test_header_spec_id() {
+        let mut header: BlockHeader = BlockHeader::default();
+        header.inner.difficulty = U256::from(1);
+        assert_eq!(header_spec_id(&header), SpecId::BERLIN);
+
+        header.inner.base_fee_per_gas = Some(1);
+        assert_eq!(header_spec_id(&header), SpecId::LONDON);
+
+        header.inner.difficulty = U256::ZERO;
+        assert_eq!(header_spec_id(&header), SpecId::MERGE);
+
+        header.inner.withdrawals_root = Some(B256::ZERO);
+        assert_eq!(header_spec_id(&header), SpecId::SHANGHAI);
+
+        header.inner.excess_blob_gas = Some(0);
+        header.inner.parent_beacon_block_root = Some(B256::ZERO);
+        assert_eq!(header_spec_id(&header), SpecId::CANCUN);
+
+        header.inner.requests_hash = Some(B256::ZERO);
+        assert_eq!(header_spec_id(&header), SpecId::PRAGUE);
+    }
+
+    #[test]
+    fn test_chain_spec_hardfork() {
+        let mut header: BlockHeader = BlockHeader::default();
+        header.inner.requests_hash = Some(B256::ZERO);
+
+        let config = EvmConfig::new(&ChainSpec::default());
+        assert_eq!(config.spec_id(&header).unwrap(), SpecId::PRAGUE);
+
+        let config = EvmConfig::new(&ChainSpec {
+            evm_hardfork: Some("Shanghai".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(config.spec_id(&header).unwrap(), SpecId::SHANGHAI);
+
+        let config = EvmConfig::new(&ChainSpec {
+            evm_hardfork: Some("shanghai".to_string()),
+            ..Default::default()
+        });
+        assert!(config.spec_id(&header).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_block_context() {
+        let mut block: Block = Block::default();
+        block.header.inner.number = 100;
+        block.header.inner.base_fee_per_gas = Some(7);
+        block.header.inner.excess_blob_gas = Some(1 << 20);
+        block.header.inner.parent_beacon_block_root = Some(B256::ZERO);
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .returning(move |_| Ok(Some(block.clone())));
+        let config = EvmConfig::new(&ChainSpec {
+            id: 10,
+            ..Default::default()
+        });
+
+        let context = load_block_context(&provider, &config, BlockId::latest())
+            .await
+            .unwrap();
+
+        assert_eq!(context.chain_id, 10);
+        assert_eq!(context.spec_id, SpecId::CANCUN);
+        assert_eq!(context.block_env.number, U256::from(100));
+        assert_eq!(context.block_env.basefee, U256::from(7));
+        assert_eq!(context.block_env.get_blob_excess_gas(), Some(1 << 20));
+    }
+
+    #[test]
+    fn test_unknown_precompile() {
+        let target = address!("1111111111111111111111111111111111111111");
+        let run = |precompile: Address| {
+            let mut db = CacheDB::new(EmptyDB::default());
+            // pop(staticcall(gas(), precompile, 0, 0, 0, 0)) stop()
+            let code = Bytecode::new_raw(
+                [
+                    &[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73][..],
+                    precompile.as_slice(),
+                    &[0x5a, 0xfa, 0x50, 0x00],
+                ]
+                .concat()
+                .into(),
+            );
+            db.insert_account_info(
+                target,
+                AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code),
+            );
+            let tx_env = TxEnv {
+                transact_to: TxKind::Call(target),
+                gas_limit: 100_000,
+                ..Default::default()
+            };
+            let mut inspector = PrecompileInspector::default();
+            let context = BlockContext::new(SpecId::CANCUN, BlockEnv::default());
+            let result = transact(&mut db, context, tx_env, &mut inspector).unwrap();
+            assert!(result.is_success());
+            inspector.check()
+        };
+
+        // ecrecover
+        assert!(run(address!("0000000000000000000000000000000000000001")).is_ok());
+        // ArbSys on Arbitrum
+        assert!(run(address!("0000000000000000000000000000000000000064")).is_err());
+        // P256 verifier of RIP-7212
+        assert!(run(address!("0000000000000000000000000000000000000100")).is_err());
+        // not in the precompile range
+        assert!(run(address!("0000000000000000000000000000000000010000")).is_ok());
+    }
+
+    #[test]
+    fn test_transact_uses_spec_id() {
+        let target = address!("1111111111111111111111111111111111111111");
+        let mut db = CacheDB::new(EmptyDB::default());
+        // push0() stop()
+        let code = Bytecode::new_raw(bytes!("5f00"));
+        db.insert_account_info(
+            target,
+            AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code),
+        );
+
+        let tx_env = TxEnv {
+            transact_to: TxKind::Call(target),
+            gas_limit: 100_000,
+            ..Default::default()
+        };
+
+        let context = BlockContext::new(SpecId::MERGE, BlockEnv::default());
+        let result = transact(&mut db, context, tx_env.clone(), NoOpInspector).unwrap();
+        assert!(matches!(result, ExecutionResult::Halt { .. }));
+
+        let context = BlockContext::new(SpecId::SHANGHAI, BlockEnv::default());
+        let result = transact(&mut db, context, tx_env, NoOpInspector).unwrap();
+        assert!(result.is_success());
+    }
+}
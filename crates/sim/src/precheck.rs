@@ -14,11 +14,15 @@
 use std::{marker::PhantomData, sync::RwLock};
 
 use alloy_primitives::{Address, U256};
+use alloy_sol_types::SolCall;
 use anyhow::Context;
 use arrayvec::ArrayVec;
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{BlockHashOrNumber, DAGasProvider, EntryPoint, EvmProvider};
+use rundler_contracts::v0_7::IEntryPoint;
+use rundler_provider::{
+    BlockHashOrNumber, DAGasProvider, EntryPoint, EvmProvider, StateOverride, TransactionRequest,
+};
 use rundler_types::{
     chain::ChainSpec,
     da::DAGasUOData,
@@ -62,11 +66,13 @@ pub trait Prechecker: Send + Sync {
     /// The user operation type
     type UO: UserOperation;
 
-    /// Run the precheck on the given operation and return an error if it fails.
+    /// Run the precheck on the given operation on top of `state_override` and return an
+    /// error if it fails.
     async fn check(
         &self,
         op: &Self::UO,
         block: BlockHashOrNumber,
+        state_override: StateOverride,
     ) -> Result<PrecheckReturn, PrecheckError>;
 
     /// Update and return the bundle fees.
@@ -173,8 +179,9 @@ where
         &self,
         op: &Self::UO,
         block: BlockHashOrNumber,
+        state_override: StateOverride,
     ) -> Result<PrecheckReturn, PrecheckError> {
-        let async_data = self.load_async_data(op, block).await?;
+        let async_data = self.load_async_data(op, block, &state_override).await?;
         let mut violations: Vec<PrecheckViolation> = vec![];
         violations.extend(self.check_init_code(op, &async_data));
         violations.extend(self.check_gas(op, &async_data));
@@ -244,6 +251,10 @@ where
             }
             return violations;
         }
+        if op.has_eip7702_init_code() {
+            violations.push(PrecheckViolation::MissingEip7702Authorization(op.sender()));
+            return violations;
+        }
         if op.factory().is_none() {
             if !sender_exists {
                 violations.push(PrecheckViolation::SenderIsNotContractAndNoInitCode(
@@ -372,6 +383,7 @@ where
         &self,
         op: &UO,
         block: BlockHashOrNumber,
+        state_override: &StateOverride,
     ) -> anyhow::Result<AsyncData> {
         let FeeUpdate { base_fee, .. } = self.get_fees().await?;
 
@@ -382,10 +394,10 @@ where
             payer_funds,
             (min_pre_verification_gas, da_gas_data),
         ) = tokio::try_join!(
-            self.is_contract(op.factory()),
-            self.is_contract(Some(op.sender())),
-            self.is_contract(op.paymaster()),
-            self.get_payer_funds(op),
+            self.is_contract(op.factory(), state_override),
+            self.is_contract(Some(op.sender()), state_override),
+            self.is_contract(op.paymaster(), state_override),
+            self.get_payer_funds(op, state_override),
             self.get_required_pre_verification_gas(op.clone(), block, base_fee)
         )?;
         Ok(AsyncData {
@@ -399,10 +411,17 @@ where
         })
     }
 
-    async fn is_contract(&self, address: Option<Address>) -> anyhow::Result<bool> {
+    async fn is_contract(
+        &self,
+        address: Option<Address>,
+        state_override: &StateOverride,
+    ) -> anyhow::Result<bool> {
         let Some(address) = address else {
             return Ok(false);
         };
+        if let Some(code) = state_override.get(&address).and_then(|a| a.code.as_ref()) {
+            return Ok(!code.is_empty());
+        }
         let bytecode = self
             .provider
             .get_code(address, None)
@@ -411,28 +430,67 @@ where
         Ok(!bytecode.is_empty())
     }
 
-    async fn get_payer_funds(&self, op: &UO) -> anyhow::Result<U256> {
-        let (deposit, balance) =
-            tokio::try_join!(self.get_payer_deposit(op), self.get_payer_balance(op),)?;
+    async fn get_payer_funds(
+        &self,
+        op: &UO,
+        state_override: &StateOverride,
+    ) -> anyhow::Result<U256> {
+        let (deposit, balance) = tokio::try_join!(
+            self.get_payer_deposit(op, state_override),
+            self.get_payer_balance(op, state_override),
+        )?;
         Ok(deposit + balance)
     }
 
-    async fn get_payer_deposit(&self, op: &UO) -> anyhow::Result<U256> {
+    async fn get_payer_deposit(
+        &self,
+        op: &UO,
+        state_override: &StateOverride,
+    ) -> anyhow::Result<U256> {
         let payer = match op.paymaster() {
             Some(paymaster) => paymaster,
             None => op.sender(),
         };
-        self.entry_point
-            .balance_of(payer, None)
+        if state_override.is_empty() {
+            return self
+                .entry_point
+                .balance_of(payer, None)
+                .await
+                .context("precheck should get payer balance");
+        }
+
+        // `balanceOf` is the same for all entry point versions
+        let tx = TransactionRequest::default()
+            .to(*self.entry_point.address())
+            .input(
+                IEntryPoint::balanceOfCall { account: payer }
+                    .abi_encode()
+                    .into(),
+            );
+        let output = self
+            .provider
+            .call(&tx, None, state_override)
             .await
-            .context("precheck should get payer balance")
+            .context("precheck should get payer balance")?;
+        Ok(
+            IEntryPoint::balanceOfCall::abi_decode_returns(&output, false)
+                .context("precheck should decode payer balance")?
+                ._0,
+        )
     }
 
-    async fn get_payer_balance(&self, op: &UO) -> anyhow::Result<U256> {
+    async fn get_payer_balance(
+        &self,
+        op: &UO,
+        state_override: &StateOverride,
+    ) -> anyhow::Result<U256> {
         if op.paymaster().is_some() {
             // Paymasters must deposit eth, and cannot pay with their own.
             return Ok(U256::ZERO);
         }
+        if let Some(balance) = state_override.get(&op.sender()).and_then(|a| a.balance) {
+            return Ok(balance);
+        }
         self.provider
             .get_balance(op.sender(), None)
             .await
@@ -469,12 +527,16 @@ mod tests {
 
     use alloy_primitives::{address, bytes, Bytes};
     use gas::MockFeeEstimator;
-    use rundler_provider::{MockEntryPointV0_6, MockEvmProvider};
+    use rundler_provider::{
+        AccountOverride, MockEntryPointV0_6, MockEntryPointV0_7, MockEvmProvider,
+    };
     use rundler_types::{
+        authorization::Authorization,
         v0_6::{
             ExtendedUserOperation, UserOperation, UserOperationBuilder, UserOperationRequiredFields,
         },
-        UserOperation as _,
+        v0_7::{self, INIT_CODE_EIP7702_MARKER},
+        EntryPointVersion, UserOperation as _,
     };
 
     use super::*;
@@ -545,6 +607,52 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[tokio::test]
+    async fn test_check_init_code_eip7702_without_authorization() {
+        let cs = ChainSpec::default();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(MockEvmProvider::new()),
+            MockEntryPointV0_7::new(),
+            MockFeeEstimator::new(),
+            Settings::default(),
+        );
+        let sender = address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d");
+        let builder = || {
+            v0_7::UserOperationBuilder::new(
+                &cs,
+                v0_7::UserOperationRequiredFields {
+                    sender,
+                    nonce: U256::ZERO,
+                    call_data: Bytes::default(),
+                    call_gas_limit: 0,
+                    verification_gas_limit: 0,
+                    pre_verification_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    max_fee_per_gas: 0,
+                    signature: Bytes::default(),
+                },
+            )
+            .entry_point_version(EntryPointVersion::V0_8)
+            .factory(INIT_CODE_EIP7702_MARKER, Bytes::default())
+        };
+
+        let op = builder().build();
+        let res = prechecker.check_init_code(&op, &get_test_async_data());
+        let mut expected = ArrayVec::new();
+        expected.push(PrecheckViolation::MissingEip7702Authorization(sender));
+        assert_eq!(res, expected);
+
+        let op = builder()
+            .authorization_tuple(Some(Authorization {
+                address: Address::random(),
+                ..Default::default()
+            }))
+            .build();
+        let res = prechecker.check_init_code(&op, &get_test_async_data());
+        assert!(res.is_empty());
+    }
+
     #[tokio::test]
     async fn test_check_gas() {
         let test_settings = Settings {
@@ -794,4 +902,46 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    #[tokio::test]
+    async fn test_payer_funds_state_override() {
+        let (cs, mut provider, mut entry_point, fee_estimator) = create_base_config();
+        let entry_point_address = Address::random();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        // the deposit is read on top of the state override
+        provider
+            .expect_call()
+            .withf(move |tx, _, state_override| {
+                tx.to == Some(entry_point_address.into()) && !state_override.is_empty()
+            })
+            .returning(|_, _, _| Ok(U256::from(1_000).to_be_bytes_vec().into()));
+        let prechecker = PrecheckerImpl::new(
+            cs,
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        let op = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            op.sender,
+            AccountOverride {
+                balance: Some(U256::from(2_000)),
+                ..Default::default()
+            },
+        );
+
+        let funds = prechecker
+            .get_payer_funds(&op, &state_override)
+            .await
+            .unwrap();
+        assert_eq!(funds, U256::from(3_000));
+    }
 }
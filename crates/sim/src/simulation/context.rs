@@ -15,7 +15,7 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 
 use alloy_primitives::{Address, U256};
 use anyhow::Context;
-use rundler_provider::BlockId;
+use rundler_provider::{BlockId, StateOverride};
 use rundler_types::{
     pool::SimulationViolation, EntityInfos, EntityType, Opcode, StakeInfo, UserOperation,
     ValidationOutput,
@@ -107,11 +107,13 @@ pub trait ValidationContextProvider: Send + Sync {
     /// The user operation type this provider targets.
     type UO: UserOperation;
 
-    /// Get the validation context for a user operation.
+    /// Get the validation context for a user operation, simulated on top of the
+    /// given state overrides.
     async fn get_context(
         &self,
         op: Self::UO,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>>;
 
     /// Get the violations specific to the particular entry point this provider targets.
@@ -0,0 +1,241 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Output of the native ERC-7562 tracer (`erc7562Tracer`) offered by geth and its forks.
+//!
+//! The tracer returns a tree of call frames, each annotated with the opcodes, storage
+//! slots and contracts accessed directly within it. The entry point specific modules
+//! convert this tree into their `TracerOutput`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use alloy_primitives::{hex, Address, Bytes, U256, U64};
+use rundler_provider::{
+    BlockId, EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions,
+    GethDebugTracingOptions, StateOverride, TransactionRequest,
+};
+use rundler_types::Opcode;
+use serde::{Deserialize, Deserializer};
+
+use crate::ExpectedStorage;
+
+/// Name of the native tracer on the node
+const ERC7562_TRACER: &str = "erc7562Tracer";
+
+/// Runs the native ERC-7562 tracer on `tx` with `debug_traceCall`.
+pub(crate) async fn debug_trace_call<P: EvmProvider>(
+    provider: &P,
+    tx: TransactionRequest,
+    block_id: BlockId,
+    state_override: StateOverride,
+    tracer_timeout: String,
+) -> anyhow::Result<CallFrame> {
+    let options = GethDebugTracingCallOptions {
+        tracing_options: GethDebugTracingOptions {
+            // native tracers are selected by name, the same as javascript tracers are
+            // passed as source code
+            tracer: Some(GethDebugTracerType::JsTracer(ERC7562_TRACER.to_string())),
+            timeout: Some(tracer_timeout),
+            ..Default::default()
+        },
+        state_overrides: Some(state_override),
+        block_overrides: None,
+    };
+
+    // Requested directly, as the node's response would be mistaken for a
+    // `callTracer` frame and lose the ERC-7562 fields.
+    Ok(provider
+        .request("debug_traceCall", (tx, block_id, options))
+        .await?)
+}
+
+/// A call frame of the native ERC-7562 tracer
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CallFrame {
+    #[serde(rename = "type")]
+    pub(crate) call_type: Opcode,
+    pub(crate) from: Address,
+    #[serde(default)]
+    pub(crate) to: Option<Address>,
+    pub(crate) gas: U64,
+    pub(crate) gas_used: U64,
+    #[serde(default)]
+    pub(crate) input: Bytes,
+    #[serde(default)]
+    pub(crate) output: Bytes,
+    #[serde(default)]
+    pub(crate) error: Option<String>,
+    #[serde(default)]
+    pub(crate) value: Option<U256>,
+    #[serde(default)]
+    pub(crate) accessed_slots: AccessedSlots,
+    #[serde(default)]
+    pub(crate) ext_code_access_info: Vec<Address>,
+    #[serde(default, deserialize_with = "deserialize_used_opcodes")]
+    pub(crate) used_opcodes: HashMap<Opcode, u64>,
+    #[serde(default)]
+    pub(crate) contract_size: HashMap<Address, ContractSize>,
+    #[serde(default)]
+    pub(crate) out_of_gas: bool,
+    /// Keccak preimages of the whole transaction, only set on the root frame
+    #[serde(default)]
+    pub(crate) keccak: Vec<Bytes>,
+    #[serde(default)]
+    pub(crate) logs: Vec<CallLog>,
+    #[serde(default)]
+    pub(crate) calls: Vec<CallFrame>,
+}
+
+/// Storage slots accessed directly within a call frame
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AccessedSlots {
+    /// Values seen by each read, in order
+    #[serde(default)]
+    pub(crate) reads: HashMap<U256, Vec<U256>>,
+    #[serde(default)]
+    pub(crate) writes: HashMap<U256, u64>,
+    #[serde(default)]
+    pub(crate) transient_reads: HashMap<U256, u64>,
+    #[serde(default)]
+    pub(crate) transient_writes: HashMap<U256, u64>,
+}
+
+/// Code size of a contract accessed by a call or EXT* opcode
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ContractSize {
+    pub(crate) contract_size: u64,
+    #[serde(deserialize_with = "deserialize_opcode")]
+    pub(crate) opcode: Opcode,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct CallLog {
+    #[serde(default)]
+    pub(crate) topics: Vec<U256>,
+    #[serde(default)]
+    pub(crate) data: Bytes,
+}
+
+impl CallFrame {
+    /// The address whose storage is accessed by this frame
+    pub(crate) fn context_address(&self) -> Address {
+        match self.call_type {
+            Opcode::DELEGATECALL | Opcode::CALLCODE => self.from,
+            _ => self.to.unwrap_or_default(),
+        }
+    }
+
+    /// The 4 byte method selector of the call, zero padded
+    pub(crate) fn method_sig(&self) -> String {
+        let mut selector = [0; 4];
+        let len = self.input.len().min(4);
+        selector[..len].copy_from_slice(&self.input[..len]);
+        hex::encode_prefixed(selector)
+    }
+
+    /// Whether this frame reverted or failed
+    pub(crate) fn is_reverted(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// This frame and all of its descendants, in depth-first order
+    pub(crate) fn frames(&self) -> Vec<&CallFrame> {
+        let mut frames = vec![self];
+        let mut i = 0;
+        while i < frames.len() {
+            let frame = frames[i];
+            frames.splice(i + 1..i + 1, frame.calls.iter());
+            i += 1;
+        }
+        frames
+    }
+
+    /// Values of persistent storage slots that the trace depends on.
+    ///
+    /// The tracer does not report the order of reads and writes across frames, so
+    /// only slots that are read and never written are included. For those the
+    /// value seen by the first read is the value before the transaction.
+    pub(crate) fn expected_storage(&self) -> ExpectedStorage {
+        let frames = self.frames();
+        let written: HashSet<(Address, U256)> = frames
+            .iter()
+            .flat_map(|f| {
+                let address = f.context_address();
+                f.accessed_slots
+                    .writes
+                    .keys()
+                    .map(move |slot| (address, *slot))
+            })
+            .collect();
+
+        let mut expected_storage = ExpectedStorage::default();
+        for frame in frames {
+            let address = frame.context_address();
+            for (slot, values) in &frame.accessed_slots.reads {
+                if let Some(value) = values.first() {
+                    if !written.contains(&(address, *slot)) {
+                        expected_storage.insert(address, *slot, *value);
+                    }
+                }
+            }
+        }
+        expected_storage
+    }
+}
+
+// The tracer serializes opcodes as their numeric values, while some forks use names.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawOpcode {
+    Number(u8),
+    String(String),
+}
+
+fn parse_opcode(s: &str) -> Option<Opcode> {
+    let value = match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    };
+    match value {
+        Some(value) => Opcode::try_from(value).ok(),
+        None => Opcode::from_str(s).ok(),
+    }
+}
+
+fn deserialize_opcode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Opcode, D::Error> {
+    let opcode = match RawOpcode::deserialize(deserializer)? {
+        RawOpcode::Number(value) => Opcode::try_from(value).ok(),
+        RawOpcode::String(s) => parse_opcode(&s),
+    };
+    opcode.ok_or_else(|| serde::de::Error::custom("unknown opcode"))
+}
+
+// Opcodes not known to rundler (i.e. invalid opcodes) are not subject to any rule, so are dropped
+fn deserialize_used_opcodes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Opcode, u64>, D::Error> {
+    let raw = HashMap::<String, u64>::deserialize(deserializer)?;
+    let mut opcodes = HashMap::new();
+    for (opcode, count) in raw {
+        if let Some(opcode) = parse_opcode(&opcode) {
+            *opcodes.entry(opcode).or_default() += count;
+        }
+    }
+    Ok(opcodes)
+}
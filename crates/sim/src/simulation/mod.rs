@@ -18,7 +18,7 @@ use alloy_primitives::uint;
 use alloy_primitives::{Address, B256, U256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{AggregatorSimOut, ProviderError};
+use rundler_provider::{AggregatorSimOut, BlockId, ProviderError, StateOverride};
 use rundler_types::{
     pool::{MempoolError, SimulationViolation},
     EntityInfos, UserOperation, ValidTimeRange,
@@ -27,12 +27,17 @@ use rundler_types::{
 mod context;
 pub use context::ValidationContextProvider;
 
+mod erc7562;
+
 mod mempool;
 pub use mempool::{MempoolConfig, MempoolConfigs};
 
 mod simulator;
 pub use simulator::{new_v0_6_simulator, new_v0_7_simulator, SimulatorImpl};
 
+mod trace;
+pub use trace::{PhaseTrace, StorageAccessTrace, ValidationTrace};
+
 mod unsafe_sim;
 pub use unsafe_sim::UnsafeSimulator;
 
@@ -145,14 +150,25 @@ pub trait Simulator: Send + Sync {
     /// The type of user operation that this simulator can handle
     type UO: UserOperation;
 
-    /// Simulate a user operation, returning simulation information
-    /// upon success, or simulation violations.
+    /// Simulate a user operation on top of the given state overrides, returning
+    /// simulation information upon success, or simulation violations.
     async fn simulate_validation(
         &self,
         op: Self::UO,
         block_hash: B256,
         expected_code_hash: Option<B256>,
+        state_override: StateOverride,
     ) -> Result<SimulationResult, SimulationError>;
+
+    /// Trace the validation of a user operation on top of the given state overrides,
+    /// returning the tracer output of each phase and every violation found, instead of
+    /// failing on the first violation that no mempool allows.
+    async fn trace_validation(
+        &self,
+        op: Self::UO,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> Result<ValidationTrace, SimulationError>;
 }
 
 /// Simulation Settings
@@ -167,19 +183,43 @@ pub struct Settings {
     /// The max duration of the custom javascript tracer. Must be in a format parseable by the
     /// ParseDuration function on an ethereum node. See Docs: https://pkg.go.dev/time#ParseDuration
     pub tracer_timeout: String,
+    /// How `simulateValidation` is traced
+    pub validation_tracer: ValidationTracer,
 }
 
 impl Settings {
     /// Create new settings
-    pub fn new(min_unstake_delay: u32, min_stake_value: U256, tracer_timeout: String) -> Self {
+    pub fn new(
+        min_unstake_delay: u32,
+        min_stake_value: U256,
+        tracer_timeout: String,
+        validation_tracer: ValidationTracer,
+    ) -> Self {
         Self {
             min_unstake_delay,
             min_stake_value,
             tracer_timeout,
+            validation_tracer,
         }
     }
 }
 
+/// Where the validation of a user operation is traced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ValidationTracer {
+    /// Run the bundler's javascript tracer on the node with `debug_traceCall`
+    #[default]
+    Javascript,
+    /// Run the node's native ERC-7562 tracer (`erc7562Tracer`) with `debug_traceCall`
+    Native,
+    /// Execute in an embedded EVM, loading state from the node as it is accessed.
+    ///
+    /// Does not require the node to support `debug_traceCall`. Only supported for
+    /// entry point v0.7, v0.6 must be disabled.
+    Local,
+}
+
 #[cfg(any(test, feature = "test-utils"))]
 impl Default for Settings {
     fn default() -> Self {
@@ -189,6 +229,7 @@ impl Default for Settings {
             // 10^18 wei = 1 eth
             min_stake_value: uint!(1_000_000_000_000_000_000_U256),
             tracer_timeout: "10s".to_string(),
+            validation_tracer: ValidationTracer::Javascript,
         }
     }
 }
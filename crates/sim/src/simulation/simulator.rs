@@ -21,10 +21,11 @@ use anyhow::Context;
 use async_trait::async_trait;
 use futures_util::TryFutureExt;
 use rundler_provider::{
-    AggregatorOut, AggregatorSimOut, EntryPoint, EvmProvider, SignatureAggregator,
-    SimulationProvider,
+    AggregatorOut, AggregatorSimOut, BlockId, EntryPoint, EvmProvider, SignatureAggregator,
+    SimulationProvider, StateOverride,
 };
 use rundler_types::{
+    chain::ChainSpec,
     pool::{NeedsStakeInformation, SimulationViolation},
     v0_6::UserOperation as UserOperationV0_6,
     v0_7::UserOperation as UserOperationV0_7,
@@ -40,7 +41,7 @@ use crate::{
         mempool::{self, AllowEntity, AllowRule, MempoolConfig, MempoolMatchResult},
         v0_6::ValidationContextProvider as ValidationContextProviderV0_6,
         v0_7::ValidationContextProvider as ValidationContextProviderV0_7,
-        Settings, Simulator,
+        Settings, Simulator, ValidationTrace,
     },
     types::ViolationError,
     SimulationError, SimulationResult,
@@ -73,6 +74,7 @@ where
 pub fn new_v0_7_simulator<P, E>(
     provider: P,
     entry_point: E,
+    chain_spec: &ChainSpec,
     sim_settings: Settings,
     mempool_configs: HashMap<B256, MempoolConfig>,
 ) -> impl Simulator<UO = UserOperationV0_7>
@@ -86,7 +88,7 @@ where
     SimulatorImpl::new(
         provider.clone(),
         entry_point.clone(),
-        ValidationContextProviderV0_7::new(provider, entry_point, sim_settings.clone()),
+        ValidationContextProviderV0_7::new(provider, entry_point, chain_spec, sim_settings.clone()),
         sim_settings,
         mempool_configs,
     )
@@ -467,11 +469,12 @@ where
         op: UO,
         block_hash: B256,
         expected_code_hash: Option<B256>,
+        state_override: StateOverride,
     ) -> Result<SimulationResult, SimulationError> {
         let block_id = block_hash.into();
         let mut context = match self
             .validation_context_provider
-            .get_context(op.clone(), block_id)
+            .get_context(op.clone(), block_id, state_override)
             .await
         {
             Ok(context) => context,
@@ -540,6 +543,27 @@ where
             entity_infos: context.entity_infos,
         })
     }
+
+    async fn trace_validation(
+        &self,
+        op: UO,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> Result<ValidationTrace, SimulationError> {
+        let mut context = self
+            .validation_context_provider
+            .get_context(op, block_id, state_override)
+            .await?;
+
+        let mut violations = self.gather_context_violations(&mut context)?;
+        violations.sort();
+        let mempools = match mempool::match_mempools(&self.mempool_configs, &violations) {
+            MempoolMatchResult::Matches(pools) => pools,
+            MempoolMatchResult::NoMatch(_) => vec![],
+        };
+
+        Ok(ValidationTrace::new(&context, violations, mempools)?)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -679,6 +703,7 @@ mod tests {
 
     use self::context::{Phase, TracerOutput};
     use super::*;
+    use crate::simulation::StorageAccessTrace;
 
     mockall::mock! {
         ValidationContextProviderV0_6 {}
@@ -690,6 +715,7 @@ mod tests {
                 &self,
                 op: UserOperationV0_6,
                 block_id: rundler_provider::BlockId,
+                state_override: StateOverride,
             ) -> Result<ValidationContext<UserOperationV0_6>, ViolationError<SimulationViolation>>;
             fn get_specific_violations(
                 &self,
@@ -873,7 +899,7 @@ mod tests {
 
         context
             .expect_get_context()
-            .returning(move |_, _| Ok(get_test_context()));
+            .returning(move |_, _, _| Ok(get_test_context()));
         context
             .expect_get_specific_violations()
             .returning(|_| Ok(vec![]));
@@ -902,7 +928,7 @@ mod tests {
 
         let simulator = create_simulator(provider, entry_point, context);
         let res = simulator
-            .simulate_validation(user_operation, B256::ZERO, None)
+            .simulate_validation(user_operation, B256::ZERO, None, StateOverride::default())
             .await;
         assert!(res.is_ok());
     }
@@ -990,6 +1016,85 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_trace_validation() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        context_provider.expect_get_context().returning(|_, _, _| {
+            let mut context = get_test_context();
+            context.tracer_out.phases[1].forbidden_opcodes_used = vec![String::from(
+                "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE",
+            )];
+            context.tracer_out.phases[1].storage_accesses.insert(
+                address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+                AccessInfo {
+                    reads: HashMap::new(),
+                    writes: HashMap::from([(U256::from(1), 2)]),
+                },
+            );
+            Ok(context)
+        });
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let trace = simulator
+            .trace_validation(
+                UserOperation::default(),
+                BlockId::Number(BlockNumberOrTag::Latest),
+                StateOverride::default(),
+            )
+            .await
+            .unwrap();
+
+        let account = Entity {
+            kind: EntityType::Account,
+            address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+        };
+        // every violation is returned, and no mempool allows them
+        assert_eq!(
+            trace.violations,
+            vec![
+                SimulationViolation::UsedForbiddenOpcode(
+                    account,
+                    account.address,
+                    ViolationOpCode(Opcode::GASPRICE),
+                ),
+                SimulationViolation::InvalidStorageAccess(
+                    account,
+                    StorageSlot {
+                        address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+                        slot: U256::from(1),
+                    }
+                ),
+            ]
+        );
+        assert_eq!(trace.violations[0].erc7562_rule(), Some("OP-011"));
+        assert!(trace.mempools.is_empty());
+
+        assert_eq!(trace.phases.len(), 3);
+        let phase = &trace.phases[1];
+        assert_eq!(phase.entity, Some(EntityType::Account));
+        assert_eq!(
+            phase.forbidden_opcodes_used,
+            vec![(account.address, Opcode::GASPRICE)]
+        );
+        assert_eq!(
+            phase.storage_accesses,
+            vec![StorageAccessTrace {
+                address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+                slot: U256::from(1),
+                read_value: None,
+                writes: 2,
+                sender_associated: false,
+                entity_associated: false,
+            }]
+        );
+    }
+
     #[tokio::test]
     async fn test_op_080() {
         let (provider, ep, mut context_provider) = create_base_config();
@@ -0,0 +1,153 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::{BTreeSet, HashMap};
+
+use alloy_primitives::{Address, B256, U256};
+use rundler_types::{pool::SimulationViolation, EntityType, Opcode, UserOperation};
+
+use super::context::{self, ValidationContext};
+
+/// The output of tracing the validation of a user operation, used to debug why
+/// an operation is rejected.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationTrace {
+    /// The tracer output of each validation phase, in execution order
+    pub phases: Vec<PhaseTrace>,
+    /// Contracts accessed during validation, and the opcode used to access them
+    pub accessed_contracts: HashMap<Address, Opcode>,
+    /// Storage slots associated with each address, found by the tracer from keccak preimages
+    pub associated_slots: HashMap<Address, BTreeSet<U256>>,
+    /// Whether the factory called CREATE2 more than once
+    pub factory_called_create2_twice: bool,
+    /// Every violation found during validation, sorted by importance
+    pub violations: Vec<SimulationViolation>,
+    /// The mempools that allow all of the violations, empty if the operation is rejected
+    pub mempools: Vec<B256>,
+}
+
+/// The tracer output of a single validation phase
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTrace {
+    /// The entity validated in this phase, if the phase validates an entity
+    pub entity: Option<EntityType>,
+    /// Forbidden opcodes used, and the contracts that used them
+    pub forbidden_opcodes_used: Vec<(Address, Opcode)>,
+    /// Forbidden precompiles called, and the contracts that called them
+    pub forbidden_precompiles_used: Vec<(Address, Address)>,
+    /// Storage slots accessed, sorted by address and slot
+    pub storage_accesses: Vec<StorageAccessTrace>,
+    /// Whether an entry point method other than `depositTo` was called
+    pub called_banned_entry_point_method: bool,
+    /// Whether a contract other than the entry point was called with value
+    pub called_non_entry_point_with_value: bool,
+    /// Whether the phase ran out of gas
+    pub ran_out_of_gas: bool,
+    /// Addresses without code that were accessed
+    pub undeployed_contract_accesses: Vec<Address>,
+    /// Addresses whose code was accessed, and the opcode used to access it
+    pub ext_code_accesses: HashMap<Address, Opcode>,
+}
+
+/// A storage slot accessed during validation, and whether it is associated with
+/// the sender or the accessing entity
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageAccessTrace {
+    /// The address of the accessed storage
+    pub address: Address,
+    /// The accessed slot
+    pub slot: U256,
+    /// The value of the slot before it was first read, if it was read
+    pub read_value: Option<U256>,
+    /// The number of writes to the slot
+    pub writes: u64,
+    /// Whether the slot is associated with the sender
+    pub sender_associated: bool,
+    /// Whether the slot is associated with the accessing entity
+    pub entity_associated: bool,
+}
+
+impl ValidationTrace {
+    pub(crate) fn new<UO: UserOperation>(
+        context: &ValidationContext<UO>,
+        violations: Vec<SimulationViolation>,
+        mempools: Vec<B256>,
+    ) -> anyhow::Result<Self> {
+        let tracer_out = &context.tracer_out;
+        let slots_by_address = &tracer_out.associated_slots_by_address;
+        let sender = context.entity_infos.sender_address();
+
+        let mut phases = vec![];
+        for (index, phase) in tracer_out.phases.iter().enumerate() {
+            let entity = context::entity_type_from_simulation_phase(index);
+            let entity_address = entity
+                .and_then(|kind| context.entity_infos.get(kind))
+                .map(|ei| ei.entity.address);
+
+            let mut storage_accesses = vec![];
+            for (&address, access_info) in &phase.storage_accesses {
+                let slots = access_info
+                    .reads
+                    .keys()
+                    .chain(access_info.writes.keys())
+                    .collect::<BTreeSet<_>>();
+                for &slot in slots {
+                    storage_accesses.push(StorageAccessTrace {
+                        address,
+                        slot,
+                        read_value: access_info.reads.get(&slot).copied(),
+                        writes: access_info.writes.get(&slot).copied().unwrap_or_default(),
+                        sender_associated: slots_by_address.is_associated_slot(sender, slot),
+                        entity_associated: entity_address.is_some_and(|entity_address| {
+                            slots_by_address.is_associated_slot(entity_address, slot)
+                        }),
+                    });
+                }
+            }
+            storage_accesses.sort_by_key(|access| (access.address, access.slot));
+
+            phases.push(PhaseTrace {
+                entity,
+                forbidden_opcodes_used: phase
+                    .forbidden_opcodes_used
+                    .iter()
+                    .map(|s| context::parse_combined_context_str(s))
+                    .collect::<anyhow::Result<_>>()?,
+                forbidden_precompiles_used: phase
+                    .forbidden_precompiles_used
+                    .iter()
+                    .map(|s| context::parse_combined_context_str(s))
+                    .collect::<anyhow::Result<_>>()?,
+                storage_accesses,
+                called_banned_entry_point_method: phase.called_banned_entry_point_method,
+                called_non_entry_point_with_value: phase.called_non_entry_point_with_value,
+                ran_out_of_gas: phase.ran_out_of_gas,
+                undeployed_contract_accesses: phase.undeployed_contract_accesses.clone(),
+                ext_code_accesses: phase.ext_code_access_info.clone(),
+            });
+        }
+
+        Ok(Self {
+            phases,
+            accessed_contracts: tracer_out
+                .accessed_contracts
+                .iter()
+                .map(|(address, info)| (*address, info.opcode))
+                .collect(),
+            associated_slots: slots_by_address.0.clone(),
+            factory_called_create2_twice: tracer_out.factory_called_create2_twice,
+            violations,
+            mempools,
+        })
+    }
+}
@@ -14,10 +14,12 @@
 use std::marker::PhantomData;
 
 use alloy_primitives::B256;
-use rundler_provider::{AggregatorOut, EntryPoint, SignatureAggregator, SimulationProvider};
+use rundler_provider::{
+    AggregatorOut, BlockId, EntryPoint, SignatureAggregator, SimulationProvider, StateOverride,
+};
 use rundler_types::{pool::SimulationViolation, EntityInfos, UserOperation, ValidTimeRange};
 
-use crate::{SimulationError, SimulationResult, Simulator, ViolationError};
+use crate::{SimulationError, SimulationResult, Simulator, ValidationTrace, ViolationError};
 
 /// An unsafe simulator that can be used in place of a regular simulator
 /// to extract the information needed from simulation while avoiding the use
@@ -56,13 +58,14 @@ where
         op: UO,
         block_hash: B256,
         _expected_code_hash: Option<B256>,
+        state_override: StateOverride,
     ) -> Result<SimulationResult, SimulationError> {
         tracing::info!("Performing unsafe simulation");
 
         // simulate the validation
         let validation_result = self
             .entry_point
-            .simulate_validation(op.clone(), Some(block_hash.into()))
+            .simulate_validation(op.clone(), Some(block_hash.into()), state_override)
             .await?;
 
         let validation_result = match validation_result {
@@ -143,4 +146,13 @@ where
             })
         }
     }
+
+    async fn trace_validation(
+        &self,
+        _op: UO,
+        _block_id: BlockId,
+        _state_override: StateOverride,
+    ) -> Result<ValidationTrace, SimulationError> {
+        Err(anyhow::anyhow!("validation tracing is not supported by the unsafe simulator").into())
+    }
 }
@@ -17,7 +17,7 @@ use alloy_primitives::hex;
 use alloy_sol_types::SolError;
 use anyhow::Context;
 use rundler_contracts::v0_6::IEntryPoint::FailedOp;
-use rundler_provider::{BlockId, EvmProvider, SimulationProvider};
+use rundler_provider::{BlockId, EvmProvider, SimulationProvider, StateOverride};
 use rundler_types::{
     pool::SimulationViolation, v0_6::UserOperation, EntityType,
     UserOperation as UserOperationTrait, ValidationOutput,
@@ -52,13 +52,14 @@ where
         &self,
         op: Self::UO,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>> {
         let factory_address = op.factory();
         let sender_address = op.sender;
         let paymaster_address = op.paymaster();
         let tracer_out = self
             .simulate_validation_tracer
-            .trace_simulate_validation(op.clone(), block_id)
+            .trace_simulate_validation(op.clone(), block_id, state_override)
             .await?;
         let num_phases = tracer_out.phases.len() as u32;
         // Check if there are too many phases here, then check too few at the
@@ -187,6 +188,7 @@ where
                 provider,
                 entry_point,
                 sim_settings.tracer_timeout.clone(),
+                sim_settings.validation_tracer,
             ),
             sim_settings,
         }
@@ -307,6 +309,7 @@ mod tests {
                 &self,
                 op: UserOperation,
                 block_id: BlockId,
+                state_override: StateOverride,
             ) -> anyhow::Result<TracerOutput>;
         }
     }
@@ -315,17 +318,19 @@ mod tests {
     async fn test_create_context_two_phases_unintended_revert() {
         let mut tracer = MockTracer::new();
 
-        tracer.expect_trace_simulate_validation().returning(|_, _| {
-            let mut tracer_output = get_test_tracer_output();
-            tracer_output.revert_data = Some(hex::encode(
-                FailedOp {
-                    opIndex: U256::from(100),
-                    reason: "AA23 reverted (or OOG)".to_string(),
-                }
-                .abi_encode(),
-            ));
-            Ok(tracer_output)
-        });
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.revert_data = Some(hex::encode(
+                    FailedOp {
+                        opIndex: U256::from(100),
+                        reason: "AA23 reverted (or OOG)".to_string(),
+                    }
+                    .abi_encode(),
+                ));
+                Ok(tracer_output)
+            });
 
         let user_operation = UserOperationBuilder::new(&ChainSpec::default(),UserOperationRequiredFields {
             sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
@@ -351,7 +356,11 @@ mod tests {
         };
 
         let res = context
-            .get_context(user_operation.clone(), BlockId::Number(0.into()))
+            .get_context(
+                user_operation.clone(),
+                BlockId::Number(0.into()),
+                StateOverride::default(),
+            )
             .await;
 
         assert!(matches!(
@@ -10,18 +10,59 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{convert::TryFrom, fmt::Debug};
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    fmt::Debug,
+};
 
+use alloy_primitives::{address, hex, keccak256, Address, U256};
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use rundler_provider::{
     BlockId, EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethTrace, SimulationProvider,
+    GethDebugTracingOptions, GethTrace, SimulationProvider, StateOverride,
 };
-use rundler_types::v0_6::UserOperation;
+use rundler_types::{v0_6::UserOperation, Opcode};
 use serde::Deserialize;
 
-use crate::simulation::context::TracerOutput;
+use crate::simulation::{
+    context::{AccessInfo, AssociatedSlotsByAddress, ContractInfo, Phase, TracerOutput},
+    erc7562::{self, CallFrame},
+    ValidationTracer,
+};
+
+// Opcodes banned during validation, see the javascript tracer
+//
+// CREATE2 is only allowed for the factory and has special handling.
+const FORBIDDEN_OPCODES: &[Opcode] = &[
+    Opcode::GAS,
+    Opcode::GASPRICE,
+    Opcode::GASLIMIT,
+    Opcode::DIFFICULTY,
+    Opcode::TIMESTAMP,
+    Opcode::BASEFEE,
+    Opcode::BLOCKHASH,
+    Opcode::BLOBBASEFEE,
+    Opcode::BLOBHASH,
+    Opcode::NUMBER,
+    Opcode::SELFBALANCE,
+    Opcode::BALANCE,
+    Opcode::ORIGIN,
+    Opcode::CREATE,
+    Opcode::COINBASE,
+    Opcode::SELFDESTRUCT,
+];
+
+// Pre calculated method signatures of the calls made by the entry point, in phase order
+const PHASE_METHODS: [&str; 3] = [
+    "0x570e1a36", // SenderCreator.createSender
+    "0x3a871cdd", // IAccount.validateUserOp
+    "0xf465c77e", // IPaymaster.validatePaymasterUserOp
+];
+const DEPOSIT_TO_METHOD: &str = "0xb760faf9";
+// Max precompile address 0x10000
+const MAX_PRECOMPILE_ADDRESS: Address = address!("0000000000000000000000000000000000010000");
 
 impl TryFrom<GethTrace> for TracerOutput {
     type Error = anyhow::Error;
@@ -35,14 +76,147 @@ impl TryFrom<GethTrace> for TracerOutput {
     }
 }
 
+impl From<CallFrame> for TracerOutput {
+    fn from(root: CallFrame) -> Self {
+        // the root frame is `simulateValidation`, which always reverts with its result
+        let entry_point = root.to.unwrap_or_default();
+        let revert_data = root
+            .is_reverted()
+            .then(|| hex::encode_prefixed(&root.output));
+
+        let mut phases = vec![Phase::default(); 3];
+        let mut num_phases = 1;
+        let mut accessed_contracts = HashMap::new();
+        let mut factory_create2_count = 0;
+        for call in &root.calls {
+            let method = call.method_sig();
+            let Some(index) = PHASE_METHODS.iter().position(|m| *m == method) else {
+                continue;
+            };
+            num_phases = num_phases.max(index + 1);
+            let phase = &mut phases[index];
+
+            for frame in call.frames() {
+                let contract = hex::encode_prefixed(frame.context_address());
+                for (opcode, count) in &frame.used_opcodes {
+                    if *opcode == Opcode::CREATE2 && index == 0 {
+                        factory_create2_count += count;
+                    } else if *opcode == Opcode::CREATE2 || FORBIDDEN_OPCODES.contains(opcode) {
+                        phase
+                            .forbidden_opcodes_used
+                            .push(format!("{contract}:{opcode}"));
+                    }
+                }
+
+                let slots = &frame.accessed_slots;
+                let access = phase
+                    .storage_accesses
+                    .entry(frame.context_address())
+                    .or_insert_with(|| AccessInfo {
+                        reads: HashMap::new(),
+                        writes: HashMap::new(),
+                    });
+                for (slot, values) in &slots.reads {
+                    if let Some(value) = values.first() {
+                        access.reads.entry(*slot).or_insert(*value);
+                    }
+                }
+                for slot in slots.transient_reads.keys() {
+                    access.reads.entry(*slot).or_default();
+                }
+                for (slot, count) in slots.writes.iter().chain(&slots.transient_writes) {
+                    *access.writes.entry(*slot).or_default() += count;
+                }
+
+                for (address, size) in &frame.contract_size {
+                    if size.contract_size == 0 {
+                        if *address < MAX_PRECOMPILE_ADDRESS {
+                            // allowed precompiles are not reported by the tracer
+                            phase
+                                .forbidden_precompiles_used
+                                .push(format!("{contract}:{}", hex::encode_prefixed(address)));
+                            continue;
+                        }
+                        phase.undeployed_contract_accesses.push(*address);
+                    }
+                    accessed_contracts.insert(
+                        *address,
+                        ContractInfo {
+                            // the code header is not reported by the native tracer
+                            header: "0x".to_string(),
+                            opcode: size.opcode,
+                            length: size.contract_size,
+                        },
+                    );
+                }
+                // the native tracer doesn't report which EXT* opcode accessed the address
+                for address in &frame.ext_code_access_info {
+                    phase
+                        .ext_code_access_info
+                        .insert(*address, Opcode::EXTCODESIZE);
+                }
+                phase.ran_out_of_gas |= frame.out_of_gas;
+
+                if frame.from == entry_point {
+                    continue;
+                }
+                let to = frame.to.unwrap_or_default();
+                // Calling entry point methods other than `depositTo` is banned, though
+                // calling with no calldata is allowed as it is equivalent to `depositTo`.
+                if to == entry_point
+                    && !frame.input.is_empty()
+                    && frame.method_sig() != DEPOSIT_TO_METHOD
+                {
+                    phase.called_banned_entry_point_method = true;
+                }
+                if to != entry_point && frame.value.is_some_and(|v| v != U256::ZERO) {
+                    phase.called_non_entry_point_with_value = true;
+                }
+            }
+        }
+        phases.truncate(num_phases);
+        for phase in &mut phases {
+            dedup(&mut phase.forbidden_opcodes_used);
+            dedup(&mut phase.forbidden_precompiles_used);
+            dedup(&mut phase.undeployed_contract_accesses);
+        }
+
+        // slots whose keccak preimage starts with an address are associated with it
+        let mut associated_slots_by_address: HashMap<Address, BTreeSet<U256>> = HashMap::new();
+        for preimage in &root.keccak {
+            if preimage.len() >= 32 && preimage[..12].iter().all(|b| *b == 0) {
+                associated_slots_by_address
+                    .entry(Address::from_slice(&preimage[12..32]))
+                    .or_default()
+                    .insert(keccak256(preimage).into());
+            }
+        }
+
+        TracerOutput {
+            phases,
+            revert_data,
+            accessed_contracts,
+            associated_slots_by_address: AssociatedSlotsByAddress(associated_slots_by_address),
+            factory_called_create2_twice: factory_create2_count > 1,
+            expected_storage: root.expected_storage(),
+        }
+    }
+}
+
+fn dedup<T: Ord>(items: &mut Vec<T>) {
+    items.sort();
+    items.dedup();
+}
+
 /// Trait for tracing the simulation of a user operation.
 #[async_trait]
 pub(super) trait SimulateValidationTracer: Send + Sync {
-    /// Traces the simulation of a user operation.
+    /// Traces the simulation of a user operation on top of the given state overrides.
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> anyhow::Result<TracerOutput>;
 }
 
@@ -52,6 +226,7 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
     provider: P,
     entry_point: E,
     tracer_timeout: String,
+    validation_tracer: ValidationTracer,
 }
 
 /// Runs the bundler's custom tracer on the entry point's `simulateValidation`
@@ -67,12 +242,26 @@ where
         &self,
         op: UserOperation,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> anyhow::Result<TracerOutput> {
         let (tx, state_override) = self
             .entry_point
-            .get_tracer_simulate_validation_call(op)
+            .get_tracer_simulate_validation_call(op, state_override)
             .context("should get simulate validation call")?;
 
+        if self.validation_tracer == ValidationTracer::Native {
+            let frame = erc7562::debug_trace_call(
+                &self.provider,
+                tx,
+                block_id,
+                state_override,
+                self.tracer_timeout.clone(),
+            )
+            .await?;
+            return Ok(TracerOutput::from(frame));
+        }
+
+        // local tracing is only supported for v0.7, so it uses the javascript tracer as well
         TracerOutput::try_from(
             self.provider
                 .debug_trace_call(
@@ -97,11 +286,17 @@ where
 
 impl<P, E> SimulateValidationTracerImpl<P, E> {
     /// Creates a new instance of the bundler's custom tracer.
-    pub(crate) fn new(provider: P, entry_point: E, tracer_timeout: String) -> Self {
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        tracer_timeout: String,
+        validation_tracer: ValidationTracer,
+    ) -> Self {
         Self {
             provider,
             entry_point,
             tracer_timeout,
+            validation_tracer,
         }
     }
 }
@@ -109,3 +304,97 @@ impl<P, E> SimulateValidationTracerImpl<P, E> {
 fn validation_tracer_js() -> &'static str {
     include_str!("../../../tracer/dist/validationTracerV0_6.js").trim_end_matches(";export{};")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY_POINT: Address = address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789");
+    const FACTORY: Address = address!("f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1f1");
+    const SENDER: Address = address!("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1");
+    const PAYMASTER: Address = address!("c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1");
+
+    fn tracer_output() -> TracerOutput {
+        let frame: CallFrame =
+            serde_json::from_str(include_str!("../testdata/erc7562_v0_6.json")).unwrap();
+        TracerOutput::from(frame)
+    }
+
+    #[test]
+    fn test_native_phases() {
+        let out = tracer_output();
+
+        assert_eq!(out.phases.len(), 3);
+        assert_eq!(
+            out.revert_data.as_deref(),
+            Some("0xe0cff05f0000000000000000000000000000000000000000000000000000000000000080")
+        );
+
+        // factory may use CREATE2, but only once
+        let factory = &out.phases[0];
+        assert!(factory.forbidden_opcodes_used.is_empty());
+        assert!(out.factory_called_create2_twice);
+        assert_eq!(factory.storage_accesses[&FACTORY].writes[&U256::ZERO], 1);
+
+        let account = &out.phases[1];
+        assert_eq!(
+            account.forbidden_opcodes_used,
+            vec![format!("{}:TIMESTAMP", hex::encode_prefixed(SENDER))]
+        );
+        assert_eq!(
+            account.storage_accesses[&SENDER].reads[&U256::from(1)],
+            U256::from(5)
+        );
+        // depositTo with value is allowed, value sent elsewhere is not
+        assert!(!account.called_banned_entry_point_method);
+        assert!(account.called_non_entry_point_with_value);
+        assert_eq!(
+            account.storage_accesses[&ENTRY_POINT].writes[&U256::from(4)],
+            1
+        );
+
+        let paymaster = &out.phases[2];
+        assert_eq!(
+            paymaster.forbidden_opcodes_used,
+            vec![format!("{}:TIMESTAMP", hex::encode_prefixed(PAYMASTER))]
+        );
+        assert_eq!(
+            paymaster.forbidden_precompiles_used,
+            vec![format!(
+                "{}:0x000000000000000000000000000000000000000a",
+                hex::encode_prefixed(PAYMASTER)
+            )]
+        );
+        assert!(paymaster.called_banned_entry_point_method);
+        assert!(!paymaster.called_non_entry_point_with_value);
+    }
+
+    #[test]
+    fn test_native_contracts_and_slots() {
+        let out = tracer_output();
+
+        assert_eq!(out.accessed_contracts.len(), 1);
+        assert_eq!(out.accessed_contracts[&FACTORY].length, 1200);
+        assert_eq!(out.accessed_contracts[&FACTORY].opcode, Opcode::CALL);
+
+        let mut preimage = [0; 64];
+        preimage[12..32].copy_from_slice(SENDER.as_slice());
+        let slot: U256 = keccak256(preimage).into();
+        assert!(out
+            .associated_slots_by_address
+            .is_associated_slot(SENDER, slot));
+
+        let expected = &out.expected_storage.0[&SENDER];
+        assert_eq!(expected.len(), 1);
+    }
+
+    #[test]
+    fn test_native_phases_without_paymaster() {
+        let mut frame: CallFrame =
+            serde_json::from_str(include_str!("../testdata/erc7562_v0_6.json")).unwrap();
+        frame.calls.truncate(2);
+
+        let out = TracerOutput::from(frame);
+        assert_eq!(out.phases.len(), 2);
+    }
+}
@@ -21,10 +21,10 @@ use alloy_primitives::{
 use alloy_sol_types::SolType;
 use anyhow::{bail, Context};
 use rundler_contracts::v0_7::ValidationResult;
-use rundler_provider::{BlockId, EntryPoint, EvmProvider, SimulationProvider};
+use rundler_provider::{BlockId, EntryPoint, EvmProvider, SimulationProvider, StateOverride};
 use rundler_types::{
-    pool::SimulationViolation, v0_7::UserOperation, EntityInfos, EntityType, Opcode,
-    UserOperation as UserOperationTrait, ValidationOutput, ValidationRevert,
+    chain::ChainSpec, pool::SimulationViolation, v0_7::UserOperation, EntityInfos, EntityType,
+    Opcode, UserOperation as UserOperationTrait, ValidationOutput, ValidationRevert,
 };
 
 use super::tracer::{
@@ -65,6 +65,7 @@ const BANNED_OPCODES: &[Opcode] = &[
 // Pre calculated method signatures
 const SIMULATE_VALIDATION_METHOD: &str = "0xee219423";
 const CREATE_SENDER_METHOD: &str = "0x570e1a36";
+const INIT_EIP7702_SENDER_METHOD: &str = "0xc09ad0d9";
 const VALIDATE_USER_OP_METHOD: &str = "0x19822f7c";
 const VALIDATE_PAYMASTER_USER_OP_METHOD: &str = "0x52b7512c";
 const DEPOSIT_TO_METHOD: &str = "0xb760faf9";
@@ -89,10 +90,11 @@ where
         &self,
         op: Self::UO,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>> {
         let tracer_out = self
             .simulate_validation_tracer
-            .trace_simulate_validation(op.clone(), block_id)
+            .trace_simulate_validation(op.clone(), block_id, state_override)
             .await?;
 
         let call_stack = self.parse_call_stack(tracer_out.calls.clone())?;
@@ -303,13 +305,16 @@ impl<T> ValidationContextProvider<T> {
             }
         }
 
-        // Check account
-        if let Some(call_from_entry_point) = tracer_out
-            .calls_from_entry_point
-            .iter()
-            .find(|c| c.top_level_method_sig == VALIDATE_USER_OP_METHOD)
-        {
-            phases[1] = Self::parse_call_to_phase(call_from_entry_point, EntityType::Account);
+        // Check account, including the initialization of an EIP-7702 delegated sender (v0.8)
+        for method in [INIT_EIP7702_SENDER_METHOD, VALIDATE_USER_OP_METHOD] {
+            if let Some(call_from_entry_point) = tracer_out
+                .calls_from_entry_point
+                .iter()
+                .find(|c| c.top_level_method_sig == method)
+            {
+                let phase = Self::parse_call_to_phase(call_from_entry_point, EntityType::Account);
+                Self::merge_phase(&mut phases[1], phase);
+            }
         }
 
         // Check paymaster
@@ -361,6 +366,38 @@ impl<T> ValidationContextProvider<T> {
         })
     }
 
+    // Merges `other` into `phase`. Reads keep the value seen first, write counts are summed.
+    fn merge_phase(phase: &mut Phase, other: Phase) {
+        phase
+            .forbidden_opcodes_used
+            .extend(other.forbidden_opcodes_used);
+        phase
+            .forbidden_precompiles_used
+            .extend(other.forbidden_precompiles_used);
+        for (address, info) in other.storage_accesses {
+            let access = phase
+                .storage_accesses
+                .entry(address)
+                .or_insert_with(|| AccessInfo {
+                    reads: HashMap::new(),
+                    writes: HashMap::new(),
+                });
+            for (slot, value) in info.reads {
+                access.reads.entry(slot).or_insert(value);
+            }
+            for (slot, count) in info.writes {
+                *access.writes.entry(slot).or_default() += count;
+            }
+        }
+        phase.ran_out_of_gas |= other.ran_out_of_gas;
+        phase
+            .undeployed_contract_accesses
+            .extend(other.undeployed_contract_accesses);
+        phase
+            .ext_code_access_info
+            .extend(other.ext_code_access_info);
+    }
+
     fn parse_call_to_phase(call: &TopLevelCallInfo, entity_type: EntityType) -> Phase {
         // [OP-011] - banned opcodes
         // [OP-012] - tracer will not add GAS to list if followed by *CALL
@@ -462,13 +499,20 @@ where
     E: EntryPoint + SimulationProvider<UO = UserOperation>,
 {
     /// Creates a new `ValidationContextProvider` for entry point v0.7 with the given provider and entry point.
-    pub(crate) fn new(provider: P, entry_point: E, sim_settings: SimulationSettings) -> Self {
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        chain_spec: &ChainSpec,
+        sim_settings: SimulationSettings,
+    ) -> Self {
         Self {
             entry_point_address: *entry_point.address(),
             simulate_validation_tracer: SimulateValidationTracerImpl::new(
                 provider,
                 entry_point,
+                chain_spec,
                 sim_settings.tracer_timeout.clone(),
+                sim_settings.validation_tracer,
             ),
             sim_settings,
         }
@@ -0,0 +1,804 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Native port of the bundler's javascript validation tracer, run in an embedded EVM.
+//!
+//! Produces the same [`TracerOutput`] as `validationTracerV0_7.ts`, so the output is
+//! parsed and checked exactly as if it had come from `debug_traceCall`.
+
+use std::collections::{HashMap, VecDeque};
+
+use alloy_primitives::{hex, uint, Address, Bytes, U256};
+use anyhow::Context;
+use revm::{
+    db::CacheDB,
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, CreateScheme,
+        InstructionResult, Interpreter,
+    },
+    primitives::KECCAK_EMPTY,
+    Database, EvmContext, Inspector,
+};
+use rundler_provider::{BlockId, EvmProvider, StateOverride, TransactionRequest};
+use rundler_types::Opcode;
+
+use super::tracer::{
+    AccessInfo, CallInfo, ExitInfo, ExitType, LogInfo, MethodInfo, TopLevelCallInfo, TracerOutput,
+};
+use crate::{
+    local_evm::{self, EvmConfig},
+    simulation::context::ContractInfo,
+    ExpectedStorage,
+};
+
+/// keccak("BeforeExecution()"), emitted by the entry point once validation is complete
+const STOP_COLLECTING_TOPIC: U256 =
+    uint!(0xbb47ee3e183a558b1a2ff0874b079f3fc5478b7454eacf2bfc5af2ff5878f972_U256);
+
+/// Call data and return data are truncated to this many hex characters, as in the
+/// javascript tracer.
+const MAX_HEX_DATA_LEN: usize = 4000;
+
+/// Executes the tracer call for `simulateValidation` in an embedded EVM on top of
+/// `block_id`, loading state from `provider` as it is accessed.
+pub(super) async fn trace_simulate_validation<P: EvmProvider>(
+    provider: &P,
+    evm_config: &EvmConfig,
+    tx: TransactionRequest,
+    block_id: BlockId,
+    state_override: StateOverride,
+) -> anyhow::Result<TracerOutput> {
+    let context = local_evm::load_block_context(provider, evm_config, block_id).await?;
+    let tx_env = local_evm::tx_env(&tx)?;
+
+    local_evm::run_blocking(|| {
+        let mut db = CacheDB::new(local_evm::ProviderDb::new(provider, block_id));
+        local_evm::apply_state_override(&mut db, &state_override)?;
+
+        let mut inspector = ValidationInspector::default();
+        local_evm::transact(db, context, tx_env, &mut inspector)
+            .context("local validation simulation should execute")?;
+        inspector.into_tracer_output()
+    })
+}
+
+/// Collects the information needed to check the ERC-7562 validation rules.
+///
+/// Depth is counted like the javascript tracer's `getDepth`: the entry point's
+/// `simulateValidation` frame is at depth 1, and each call it makes starts a new
+/// top-level call (level).
+#[derive(Debug, Default)]
+pub(super) struct ValidationInspector {
+    depth: usize,
+    calls_from_entry_point: Vec<TopLevelCallInfo>,
+    keccak: Vec<String>,
+    calls: Vec<CallInfo>,
+    logs: Vec<LogInfo>,
+    last_op: Option<u8>,
+    last_three_opcodes: VecDeque<(u8, U256)>,
+    stop_collecting: bool,
+    // initial value of every slot accessed, or `None` if it was written before being read
+    all_storage_accesses: HashMap<Address, HashMap<U256, Option<U256>>>,
+    error: Option<anyhow::Error>,
+}
+
+impl ValidationInspector {
+    pub(super) fn into_tracer_output(self) -> anyhow::Result<TracerOutput> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let mut expected_storage = ExpectedStorage::default();
+        for (address, slots) in self.all_storage_accesses {
+            for (slot, value) in slots {
+                if let Some(value) = value {
+                    expected_storage.insert(address, slot, value);
+                }
+            }
+        }
+
+        Ok(TracerOutput {
+            calls_from_entry_point: self.calls_from_entry_point,
+            keccak: self.keccak,
+            calls: self.calls,
+            expected_storage,
+            logs: self.logs,
+            debug: None,
+        })
+    }
+
+    fn record_error<E: std::fmt::Debug>(&mut self, error: E) {
+        self.error
+            .get_or_insert_with(|| anyhow::anyhow!("failed to load state: {error:?}"));
+    }
+
+    fn level(&mut self) -> Option<&mut TopLevelCallInfo> {
+        self.calls_from_entry_point.last_mut()
+    }
+
+    fn count_opcode(&mut self, opcode: Opcode) {
+        if let Some(level) = self.level() {
+            *level.opcodes.entry(opcode).or_default() += 1;
+        }
+    }
+
+    fn set_oog(&mut self) {
+        if let Some(level) = self.level() {
+            level.oog = Some(true);
+        }
+    }
+
+    fn push_exit(&mut self, result: InstructionResult, gas_used: u64, output: &Bytes) {
+        let exit_type = if result.is_ok() {
+            ExitType::Return
+        } else {
+            ExitType::Revert
+        };
+        // the final exit is parsed for the result of `simulateValidation`, so keep it whole
+        let mut data = hex::encode_prefixed(output);
+        if self.depth > 0 {
+            data.truncate(MAX_HEX_DATA_LEN);
+        }
+        self.calls.push(CallInfo::Exit(ExitInfo {
+            exit_type,
+            gas_used,
+            data,
+        }));
+    }
+
+    fn on_storage_access<DB: Database>(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        address: Address,
+        slot: U256,
+        is_read: bool,
+    ) -> Result<(), DB::Error> {
+        let Some(level) = self.calls_from_entry_point.last_mut() else {
+            return Ok(());
+        };
+        let access = level.access.entry(address).or_insert_with(|| AccessInfo {
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+        });
+        let initial_values = self.all_storage_accesses.entry(address).or_default();
+
+        if is_read {
+            // read slot values before this user operation was created
+            // (so saving it if it was written before the first read)
+            let needs_read =
+                !access.reads.contains_key(&slot) && !access.writes.contains_key(&slot);
+            if needs_read || !initial_values.contains_key(&slot) {
+                let value = storage(context, address, slot)?;
+                if needs_read {
+                    access.reads.insert(slot, value);
+                }
+                initial_values.entry(slot).or_insert(Some(value));
+            }
+        } else {
+            *access.writes.entry(slot).or_default() += 1;
+            initial_values.entry(slot).or_insert(None);
+        }
+        Ok(())
+    }
+
+    fn on_contract_access<DB: Database>(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        address: Address,
+        opcode: Opcode,
+    ) -> Result<(), DB::Error> {
+        let Some(level) = self.calls_from_entry_point.last() else {
+            return Ok(());
+        };
+        // [OP-062]
+        if level.contract_info.contains_key(&address) || is_allowed_precompile(address) {
+            return Ok(());
+        }
+
+        let code = code(context, address)?;
+        if let Some(level) = self.level() {
+            level.contract_info.insert(
+                address,
+                ContractInfo {
+                    header: hex::encode_prefixed(&code[..code.len().min(3)]),
+                    opcode,
+                    length: code.len() as u64,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<DB> Inspector<DB> for ValidationInspector
+where
+    DB: Database,
+    DB::Error: std::fmt::Debug,
+{
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if self.stop_collecting {
+            return;
+        }
+        let op = interp.current_opcode();
+        let stack = |n: usize| interp.stack.peek(n).unwrap_or_default();
+
+        self.last_three_opcodes.push_back((op, stack(0)));
+        if self.last_three_opcodes.len() > 3 {
+            self.last_three_opcodes.pop_front();
+        }
+
+        // special rule for SSTORE with gas metering
+        if op == Opcode::SSTORE as u8 && interp.gas.remaining() < 2300 {
+            self.set_oog();
+        }
+
+        if op == Opcode::RETURN as u8 || op == Opcode::REVERT as u8 {
+            // NOTE: flushing all history after RETURN
+            self.last_three_opcodes.clear();
+        }
+
+        if self.depth == 1 {
+            // new levels are started by `call`
+            if op == Opcode::LOG1 as u8 && stack(2) == STOP_COLLECTING_TOPIC {
+                self.stop_collecting = true;
+            }
+            self.last_op = None;
+            return;
+        }
+
+        let opcode = Opcode::try_from(op).ok();
+
+        // store all addresses touched by EXTCODE* opcodes
+        if let Some(&(last_op, last_stack_top)) = self
+            .last_three_opcodes
+            .len()
+            .checked_sub(2)
+            .and_then(|i| self.last_three_opcodes.get(i))
+        {
+            if is_ext_code_opcode(last_op) {
+                let address = Address::from_word(last_stack_top.into());
+                // [OP-051]
+                let is_existence_check = self.last_three_opcodes.len() == 3
+                    && last_op == Opcode::EXTCODESIZE as u8
+                    && op == Opcode::ISZERO as u8;
+                if !is_existence_check {
+                    if let (Some(opcode), Some(level)) = (opcode, self.level()) {
+                        level.ext_code_access_info.insert(address, opcode);
+                    }
+                }
+            }
+        }
+
+        let Some(opcode) = opcode else {
+            self.last_op = None;
+            return;
+        };
+
+        // [OP-041]
+        let target_index = if is_ext_code_opcode(op) {
+            Some(0)
+        } else if is_call_opcode(op) {
+            Some(1)
+        } else {
+            None
+        };
+        if let Some(index) = target_index {
+            let address = Address::from_word(stack(index).into());
+            if let Err(error) = self.on_contract_access(context, address, opcode) {
+                self.record_error(error);
+            }
+        }
+
+        // [OP-012]
+        if self.last_op == Some(Opcode::GAS as u8) && !is_call_opcode(op) {
+            // count "GAS" opcode only if not followed by "CALL"
+            self.count_opcode(Opcode::GAS);
+        }
+        if opcode != Opcode::GAS && !is_ignored_opcode(opcode) {
+            self.count_opcode(opcode);
+        }
+        self.last_op = Some(op);
+
+        match opcode {
+            // [OP-070] - Treat TLOAD and TSTORE as SLOAD and SSTORE
+            Opcode::SLOAD | Opcode::SSTORE | Opcode::TLOAD | Opcode::TSTORE => {
+                let is_read = matches!(opcode, Opcode::SLOAD | Opcode::TLOAD);
+                let address = interp.contract.target_address;
+                if let Err(error) = self.on_storage_access(context, address, stack(0), is_read) {
+                    self.record_error(error);
+                }
+            }
+            Opcode::SHA3 => {
+                // collect keccak on 64-byte blocks
+                let len = stack(1);
+                if len > U256::from(20) && len < U256::from(512) {
+                    let data = memory_slice(interp, stack(0), len);
+                    self.keccak.push(hex::encode_prefixed(data));
+                }
+            }
+            Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => {
+                let count = (op - Opcode::LOG0 as u8) as usize;
+                let topics = (0..count).map(|i| format!("{:#x}", stack(2 + i))).collect();
+                let data = hex::encode_prefixed(memory_slice(interp, stack(0), stack(1)));
+                self.logs.push(LogInfo { topics, data });
+            }
+            _ => {}
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if self.stop_collecting {
+            return;
+        }
+        if matches!(
+            interp.instruction_result,
+            InstructionResult::OutOfGas
+                | InstructionResult::MemoryOOG
+                | InstructionResult::MemoryLimitOOG
+                | InstructionResult::PrecompileOOG
+                | InstructionResult::InvalidOperandOOG
+        ) {
+            self.set_oog();
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let depth = self.depth;
+        self.depth += 1;
+        if self.stop_collecting || depth == 0 {
+            return None;
+        }
+
+        let method_type = match inputs.scheme {
+            CallScheme::CallCode => Opcode::CALLCODE,
+            CallScheme::DelegateCall => Opcode::DELEGATECALL,
+            CallScheme::StaticCall => Opcode::STATICCALL,
+            _ => Opcode::CALL,
+        };
+        let selector = &inputs.input[..inputs.input.len().min(4)];
+
+        if depth == 1 && matches!(method_type, Opcode::CALL | Opcode::STATICCALL) {
+            let mut method_sig = [0; 4];
+            method_sig[..selector.len()].copy_from_slice(selector);
+            self.calls_from_entry_point.push(TopLevelCallInfo {
+                top_level_method_sig: hex::encode_prefixed(method_sig),
+                top_level_target_address: hex::encode_prefixed(inputs.target_address),
+                opcodes: HashMap::new(),
+                access: HashMap::new(),
+                contract_info: HashMap::new(),
+                ext_code_access_info: HashMap::new(),
+                oog: None,
+            });
+        }
+
+        self.calls.push(CallInfo::Method(MethodInfo {
+            method_type,
+            from: inputs.caller,
+            to: inputs.target_address,
+            method: hex::encode_prefixed(selector),
+            value: inputs.transfer_value(),
+            gas: inputs.gas_limit,
+        }));
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.depth -= 1;
+        // exits of the top-level frame are always recorded, as the javascript tracer
+        // reconstructs them from the final RETURN/REVERT
+        if !self.stop_collecting || self.depth == 0 {
+            self.push_exit(
+                outcome.result.result,
+                outcome.result.gas.spent(),
+                &outcome.result.output,
+            );
+        }
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let depth = self.depth;
+        self.depth += 1;
+        if self.stop_collecting || depth == 0 {
+            return None;
+        }
+
+        let method_type = match inputs.scheme {
+            CreateScheme::Create2 { .. } => Opcode::CREATE2,
+            _ => Opcode::CREATE,
+        };
+        let nonce = context
+            .journaled_state
+            .state
+            .get(&inputs.caller)
+            .map(|account| account.info.nonce)
+            .unwrap_or_default();
+        let init_code = &inputs.init_code;
+
+        self.calls.push(CallInfo::Method(MethodInfo {
+            method_type,
+            from: inputs.caller,
+            to: inputs.created_address(nonce),
+            method: hex::encode_prefixed(&init_code[..init_code.len().min(4)]),
+            value: Some(inputs.value),
+            gas: inputs.gas_limit,
+        }));
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.depth -= 1;
+        if !self.stop_collecting {
+            self.push_exit(
+                outcome.result.result,
+                outcome.result.gas.spent(),
+                &outcome.result.output,
+            );
+        }
+        outcome
+    }
+}
+
+fn is_call_opcode(op: u8) -> bool {
+    op == Opcode::CALL as u8
+        || op == Opcode::CALLCODE as u8
+        || op == Opcode::DELEGATECALL as u8
+        || op == Opcode::STATICCALL as u8
+}
+
+fn is_ext_code_opcode(op: u8) -> bool {
+    op == Opcode::EXTCODESIZE as u8
+        || op == Opcode::EXTCODECOPY as u8
+        || op == Opcode::EXTCODEHASH as u8
+}
+
+// "unimportant" opcodes that are not counted
+fn is_ignored_opcode(opcode: Opcode) -> bool {
+    let op = opcode as u8;
+    (Opcode::PUSH1 as u8..=Opcode::SWAP16 as u8).contains(&op)
+        || matches!(
+            opcode,
+            Opcode::PUSH0
+                | Opcode::POP
+                | Opcode::ADD
+                | Opcode::SUB
+                | Opcode::MUL
+                | Opcode::DIV
+                | Opcode::EQ
+                | Opcode::LT
+                | Opcode::GT
+                | Opcode::SLT
+                | Opcode::SGT
+                | Opcode::SHL
+                | Opcode::SHR
+                | Opcode::AND
+                | Opcode::OR
+                | Opcode::NOT
+                | Opcode::ISZERO
+        )
+}
+
+// not using revm's precompile list to only allow the ones defined by ERC-4337 as
+// stateless precompiles, plus RIP-7212 at 0x100
+fn is_allowed_precompile(address: Address) -> bool {
+    let address = U256::from_be_bytes(address.into_word().0);
+    (address > U256::ZERO && address < U256::from(10)) || address == U256::from(256)
+}
+
+// reads `len` bytes of memory at `offset`, zero-padded past the end of memory
+fn memory_slice(interp: &Interpreter, offset: U256, len: U256) -> Vec<u8> {
+    let (Ok(offset), Ok(len)) = (usize::try_from(offset), usize::try_from(len)) else {
+        return vec![];
+    };
+    let memory = interp.shared_memory.context_memory();
+    let mut data = vec![0; len];
+    if let Some(available) = memory.get(offset..) {
+        let n = available.len().min(len);
+        data[..n].copy_from_slice(&available[..n]);
+    }
+    data
+}
+
+// current value of a storage slot, without warming it
+fn storage<DB: Database>(
+    context: &mut EvmContext<DB>,
+    address: Address,
+    slot: U256,
+) -> Result<U256, DB::Error> {
+    let loaded = context
+        .journaled_state
+        .state
+        .get(&address)
+        .and_then(|account| account.storage.get(&slot));
+    match loaded {
+        Some(value) => Ok(value.present_value),
+        None => context.db.storage(address, slot),
+    }
+}
+
+// current code of an account, without warming it
+fn code<DB: Database>(context: &mut EvmContext<DB>, address: Address) -> Result<Bytes, DB::Error> {
+    let info = match context.journaled_state.state.get(&address) {
+        Some(account) => account.info.clone(),
+        None => match context.db.basic(address)? {
+            Some(info) => info,
+            None => return Ok(Bytes::new()),
+        },
+    };
+    match info.code {
+        Some(code) => Ok(code.original_bytes()),
+        None if info.code_hash == KECCAK_EMPTY => Ok(Bytes::new()),
+        None => Ok(context.db.code_by_hash(info.code_hash)?.original_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, bytes, B256};
+    use revm::{
+        db::EmptyDB,
+        primitives::{AccountInfo, BlockEnv, Bytecode, SpecId, TxEnv, TxKind},
+    };
+    use rundler_provider::{Block, MockEvmProvider};
+    use rundler_types::chain::ChainSpec;
+
+    use super::*;
+    use crate::local_evm::BlockContext;
+
+    const ENTRY_POINT: Address = address!("0000000071727de22e5e9d8baf0edac6f37da032");
+    const ACCOUNT: Address = address!("1111111111111111111111111111111111111111");
+
+    fn insert_code(db: &mut CacheDB<EmptyDB>, address: Address, code: Bytes) {
+        let code = Bytecode::new_raw(code);
+        db.insert_account_info(
+            address,
+            AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code),
+        );
+    }
+
+    fn trace(db: CacheDB<EmptyDB>) -> TracerOutput {
+        let tx_env = TxEnv {
+            transact_to: TxKind::Call(ENTRY_POINT),
+            gas_limit: 1_000_000,
+            ..Default::default()
+        };
+
+        let mut inspector = ValidationInspector::default();
+        let context = BlockContext::new(SpecId::CANCUN, BlockEnv::default());
+        local_evm::transact(db, context, tx_env, &mut inspector).unwrap();
+        inspector.into_tracer_output().unwrap()
+    }
+
+    // mstore(0, shl(224, 0x19822f7c))
+    // pop(call(gas(), ACCOUNT, 0, 0, 4, 0, 0))
+    // return(0, 0)
+    const ENTRY_POINT_CODE: Bytes = bytes!(
+        "6319822f7c60e01b600052600060006004600060007311111111111111111111111111111111111111115af15060006000f3"
+    );
+
+    #[test]
+    fn test_collects_top_level_call() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, ENTRY_POINT, ENTRY_POINT_CODE);
+        // pop(timestamp()) pop(sload(1)) sstore(2, 3) stop()
+        insert_code(&mut db, ACCOUNT, bytes!("425060015450600360025500"));
+        db.insert_account_storage(ACCOUNT, U256::from(1), U256::from(5))
+            .unwrap();
+
+        let out = trace(db);
+
+        assert_eq!(out.calls_from_entry_point.len(), 1);
+        let level = &out.calls_from_entry_point[0];
+        assert_eq!(level.top_level_method_sig, "0x19822f7c");
+        assert_eq!(
+            level.top_level_target_address,
+            hex::encode_prefixed(ACCOUNT)
+        );
+        assert_eq!(level.opcodes.get(&Opcode::TIMESTAMP), Some(&1));
+        assert_eq!(level.opcodes.get(&Opcode::SLOAD), Some(&1));
+        assert_eq!(level.opcodes.get(&Opcode::POP), None);
+        assert_eq!(level.oog, None);
+
+        let access = &level.access[&ACCOUNT];
+        assert_eq!(access.reads[&U256::from(1)], U256::from(5));
+        assert_eq!(access.writes[&U256::from(2)], 1);
+
+        // only slots read before being written are expected
+        let expected = &out.expected_storage.0[&ACCOUNT];
+        assert_eq!(expected.len(), 1);
+        assert_eq!(
+            expected[&B256::from(U256::from(1))],
+            B256::from(U256::from(5))
+        );
+
+        // call to the account, its exit, then the exit of the entry point
+        assert_eq!(out.calls.len(), 3);
+        assert!(
+            matches!(&out.calls[0], CallInfo::Method(m) if m.to == ACCOUNT && m.method == "0x19822f7c")
+        );
+        assert!(
+            matches!(&out.calls[1], CallInfo::Exit(e) if matches!(e.exit_type, ExitType::Return))
+        );
+        assert!(
+            matches!(&out.calls[2], CallInfo::Exit(e) if matches!(e.exit_type, ExitType::Return) && e.data == "0x")
+        );
+    }
+
+    #[test]
+    fn test_ext_code_access() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, ENTRY_POINT, ENTRY_POINT_CODE);
+        // pop(extcodesize(0x22..22)) stop()
+        insert_code(
+            &mut db,
+            ACCOUNT,
+            bytes!("7322222222222222222222222222222222222222223b5000"),
+        );
+
+        let out = trace(db);
+
+        let level = &out.calls_from_entry_point[0];
+        let target = address!("2222222222222222222222222222222222222222");
+        assert_eq!(level.ext_code_access_info[&target], Opcode::POP);
+        let info = &level.contract_info[&target];
+        assert_eq!(info.length, 0);
+        assert_eq!(info.header, "0x");
+        assert_eq!(info.opcode, Opcode::EXTCODESIZE);
+    }
+
+    #[test]
+    fn test_gas_counted_unless_followed_by_call() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, ENTRY_POINT, ENTRY_POINT_CODE);
+        // pop(gas()) pop(call(gas(), 0x33..33, 0, 0, 0, 0, 0)) stop()
+        insert_code(
+            &mut db,
+            ACCOUNT,
+            bytes!("5a50600060006000600060007333333333333333333333333333333333333333335af15000"),
+        );
+
+        let out = trace(db);
+
+        let level = &out.calls_from_entry_point[0];
+        assert_eq!(level.opcodes.get(&Opcode::GAS), Some(&1));
+        assert_eq!(level.opcodes.get(&Opcode::CALL), Some(&1));
+        let target = address!("3333333333333333333333333333333333333333");
+        assert_eq!(level.contract_info[&target].opcode, Opcode::CALL);
+    }
+
+    #[test]
+    fn test_collects_keccak_and_logs() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, ENTRY_POINT, ENTRY_POINT_CODE);
+        // mstore(0, 1) mstore(32, 2) pop(keccak256(0, 64)) log1(0, 32, 5) stop()
+        insert_code(
+            &mut db,
+            ACCOUNT,
+            bytes!("6001600052600260205260406000205060056020" "6000a100"),
+        );
+
+        let out = trace(db);
+
+        let word = |n: u64| hex::encode(B256::from(U256::from(n)));
+        assert_eq!(out.keccak, vec![format!("0x{}{}", word(1), word(2))]);
+        assert_eq!(out.logs.len(), 1);
+        assert_eq!(out.logs[0].topics, vec!["0x5".to_string()]);
+        assert_eq!(out.logs[0].data, format!("0x{}", word(1)));
+    }
+
+    #[test]
+    fn test_oog_on_sstore_with_low_gas() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        // same as ENTRY_POINT_CODE, but calls the account with 2000 gas
+        insert_code(
+            &mut db,
+            ENTRY_POINT,
+            bytes!("6319822f7c60e01b60005260006000600460006000731111111111111111111111111111111111111111" "6107d0f15060006000f3"),
+        );
+        // sstore(0, 1) stop()
+        insert_code(&mut db, ACCOUNT, bytes!("600160005500"));
+
+        let out = trace(db);
+
+        let level = &out.calls_from_entry_point[0];
+        assert_eq!(level.oog, Some(true));
+        assert!(
+            matches!(&out.calls[1], CallInfo::Exit(e) if matches!(e.exit_type, ExitType::Revert))
+        );
+    }
+
+    #[test]
+    fn test_stops_collecting_after_before_execution() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        // log1(0, 0, STOP_COLLECTING_TOPIC), then ENTRY_POINT_CODE
+        insert_code(
+            &mut db,
+            ENTRY_POINT,
+            bytes!("7fbb47ee3e183a558b1a2ff0874b079f3fc5478b7454eacf2bfc5af2ff5878f97260006000a1" "6319822f7c60e01b600052600060006004600060007311111111111111111111111111111111111111115af15060006000f3"),
+        );
+        insert_code(&mut db, ACCOUNT, bytes!("425000"));
+
+        let out = trace(db);
+
+        assert!(out.calls_from_entry_point.is_empty());
+        // only the exit of the entry point is recorded
+        assert_eq!(out.calls.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_trace_loads_state_from_provider() {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_block().returning(|_| {
+            let mut block: Block = Block::default();
+            block.header.inner.parent_beacon_block_root = Some(B256::ZERO);
+            Ok(Some(block))
+        });
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+        provider
+            .expect_get_transaction_count_at()
+            .returning(|_, _| Ok(1));
+        provider.expect_get_code().returning(|address, _| {
+            Ok(match address {
+                ENTRY_POINT => ENTRY_POINT_CODE,
+                // tstore(0, 1) pop(sload(1)) stop()
+                ACCOUNT => bytes!("600160005d6001545000"),
+                _ => Bytes::new(),
+            })
+        });
+        provider
+            .expect_get_storage_at()
+            .returning(|_, slot, _| Ok(slot + U256::from(1)));
+
+        let tx = TransactionRequest {
+            to: Some(TxKind::Call(ENTRY_POINT)),
+            gas: Some(1_000_000),
+            ..Default::default()
+        };
+        let out = trace_simulate_validation(
+            &provider,
+            &EvmConfig::new(&ChainSpec::default()),
+            tx,
+            BlockId::latest(),
+            StateOverride::default(),
+        )
+        .await
+        .unwrap();
+
+        // TSTORE is only executed with the Cancun hardfork derived from the header
+        let level = &out.calls_from_entry_point[0];
+        assert_eq!(level.opcodes.get(&Opcode::TSTORE), Some(&1));
+        let access = &level.access[&ACCOUNT];
+        assert_eq!(access.writes[&U256::ZERO], 1);
+        assert_eq!(access.reads[&U256::from(1)], U256::from(2));
+    }
+}
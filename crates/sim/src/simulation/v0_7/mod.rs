@@ -14,4 +14,5 @@
 mod context;
 pub(crate) use context::ValidationContextProvider;
 
+mod local_tracer;
 mod tracer;
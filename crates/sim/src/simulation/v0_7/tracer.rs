@@ -12,17 +12,22 @@
 
 use std::{collections::HashMap, convert::TryFrom, fmt::Debug};
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{hex, Address, U256};
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use rundler_provider::{
     BlockId, EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethTrace, SimulationProvider,
+    GethDebugTracingOptions, GethTrace, SimulationProvider, StateOverride,
 };
-use rundler_types::{v0_7::UserOperation, Opcode};
+use rundler_types::{chain::ChainSpec, v0_7::UserOperation, Opcode};
 use serde::Deserialize;
 
-use crate::{simulation::context::ContractInfo, ExpectedStorage};
+use super::local_tracer;
+use crate::{
+    local_evm::EvmConfig,
+    simulation::{context::ContractInfo, erc7562, erc7562::CallFrame, ValidationTracer},
+    ExpectedStorage,
+};
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -109,14 +114,138 @@ impl TryFrom<GethTrace> for TracerOutput {
     }
 }
 
+impl From<CallFrame> for TracerOutput {
+    fn from(root: CallFrame) -> Self {
+        // the root frame is `simulateValidation`, each call it makes is a top-level call
+        let calls_from_entry_point = root
+            .calls
+            .iter()
+            .filter(|c| matches!(c.call_type, Opcode::CALL | Opcode::STATICCALL))
+            .map(top_level_call_info)
+            .collect();
+
+        let mut calls = vec![];
+        for call in &root.calls {
+            push_calls(call, &mut calls);
+        }
+        // the final exit is parsed for the result of `simulateValidation`
+        calls.push(CallInfo::Exit(exit_info(&root)));
+
+        let logs = root
+            .frames()
+            .into_iter()
+            .skip(1)
+            .flat_map(|f| &f.logs)
+            .map(|log| LogInfo {
+                topics: log.topics.iter().map(|t| format!("{t:#x}")).collect(),
+                data: hex::encode_prefixed(&log.data),
+            })
+            .collect();
+
+        TracerOutput {
+            calls_from_entry_point,
+            keccak: root.keccak.iter().map(hex::encode_prefixed).collect(),
+            calls,
+            expected_storage: root.expected_storage(),
+            logs,
+            debug: None,
+        }
+    }
+}
+
+fn top_level_call_info(call: &CallFrame) -> TopLevelCallInfo {
+    let mut info = TopLevelCallInfo {
+        top_level_method_sig: call.method_sig(),
+        top_level_target_address: hex::encode_prefixed(call.to.unwrap_or_default()),
+        opcodes: HashMap::new(),
+        access: HashMap::new(),
+        contract_info: HashMap::new(),
+        ext_code_access_info: HashMap::new(),
+        oog: None,
+    };
+
+    for frame in call.frames() {
+        for (opcode, count) in &frame.used_opcodes {
+            *info.opcodes.entry(*opcode).or_default() += count;
+        }
+
+        let slots = &frame.accessed_slots;
+        let access = info
+            .access
+            .entry(frame.context_address())
+            .or_insert_with(|| AccessInfo {
+                reads: HashMap::new(),
+                writes: HashMap::new(),
+            });
+        for (slot, values) in &slots.reads {
+            if let Some(value) = values.first() {
+                access.reads.entry(*slot).or_insert(*value);
+            }
+        }
+        // [OP-070] - Treat TLOAD and TSTORE as SLOAD and SSTORE
+        for slot in slots.transient_reads.keys() {
+            access.reads.entry(*slot).or_default();
+        }
+        for (slot, count) in slots.writes.iter().chain(&slots.transient_writes) {
+            *access.writes.entry(*slot).or_default() += count;
+        }
+
+        for (address, size) in &frame.contract_size {
+            info.contract_info.entry(*address).or_insert(ContractInfo {
+                // the code header is not reported by the native tracer
+                header: "0x".to_string(),
+                opcode: size.opcode,
+                length: size.contract_size,
+            });
+        }
+        // the native tracer doesn't report which EXT* opcode accessed the address
+        for address in &frame.ext_code_access_info {
+            info.ext_code_access_info
+                .insert(*address, Opcode::EXTCODESIZE);
+        }
+        if frame.out_of_gas {
+            info.oog = Some(true);
+        }
+    }
+    info
+}
+
+fn push_calls(frame: &CallFrame, calls: &mut Vec<CallInfo>) {
+    calls.push(CallInfo::Method(MethodInfo {
+        method_type: frame.call_type,
+        from: frame.from,
+        to: frame.to.unwrap_or_default(),
+        method: hex::encode_prefixed(&frame.input[..frame.input.len().min(4)]),
+        value: frame.value,
+        gas: frame.gas.to(),
+    }));
+    for call in &frame.calls {
+        push_calls(call, calls);
+    }
+    calls.push(CallInfo::Exit(exit_info(frame)));
+}
+
+fn exit_info(frame: &CallFrame) -> ExitInfo {
+    ExitInfo {
+        exit_type: if frame.is_reverted() {
+            ExitType::Revert
+        } else {
+            ExitType::Return
+        },
+        gas_used: frame.gas_used.to(),
+        data: hex::encode_prefixed(&frame.output),
+    }
+}
+
 /// Trait for tracing the simulation of a user operation.
 #[async_trait]
 pub(super) trait SimulateValidationTracer: Send + Sync {
-    /// Traces the simulation of a user operation.
+    /// Traces the simulation of a user operation on top of the given state overrides.
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> anyhow::Result<TracerOutput>;
 }
 
@@ -126,6 +255,8 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
     provider: P,
     entry_point: E,
     tracer_timeout: String,
+    validation_tracer: ValidationTracer,
+    evm_config: EvmConfig,
 }
 
 /// Runs the bundler's custom tracer on the entry point's `simulateValidation`
@@ -141,12 +272,38 @@ where
         &self,
         op: UserOperation,
         block_id: BlockId,
+        state_override: StateOverride,
     ) -> anyhow::Result<TracerOutput> {
         let (tx, state_override) = self
             .entry_point
-            .get_tracer_simulate_validation_call(op)
+            .get_tracer_simulate_validation_call(op, state_override)
             .context("should get tracer simulate validation call")?;
 
+        match self.validation_tracer {
+            ValidationTracer::Javascript => {}
+            ValidationTracer::Native => {
+                let frame = erc7562::debug_trace_call(
+                    &self.provider,
+                    tx,
+                    block_id,
+                    state_override,
+                    self.tracer_timeout.clone(),
+                )
+                .await?;
+                return Ok(TracerOutput::from(frame));
+            }
+            ValidationTracer::Local => {
+                return local_tracer::trace_simulate_validation(
+                    &self.provider,
+                    &self.evm_config,
+                    tx,
+                    block_id,
+                    state_override,
+                )
+                .await;
+            }
+        }
+
         let out = self
             .provider
             .debug_trace_call(
@@ -172,11 +329,19 @@ where
 
 impl<P, E> SimulateValidationTracerImpl<P, E> {
     /// Creates a new instance of the bundler's custom tracer.
-    pub(crate) fn new(provider: P, entry_point: E, tracer_timeout: String) -> Self {
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        chain_spec: &ChainSpec,
+        tracer_timeout: String,
+        validation_tracer: ValidationTracer,
+    ) -> Self {
         Self {
             provider,
             entry_point,
             tracer_timeout,
+            validation_tracer,
+            evm_config: EvmConfig::new(chain_spec),
         }
     }
 }
@@ -184,3 +349,118 @@ impl<P, E> SimulateValidationTracerImpl<P, E> {
 fn validation_tracer_js() -> &'static str {
     include_str!("../../../tracer/dist/validationTracerV0_7.js").trim_end_matches(";export{};")
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, B256};
+
+    use super::*;
+
+    const ENTRY_POINT: Address = address!("0000000071727de22e5e9d8baf0edac6f37da032");
+    const SENDER: Address = address!("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1");
+
+    fn tracer_output() -> TracerOutput {
+        let frame: CallFrame =
+            serde_json::from_str(include_str!("../testdata/erc7562_v0_7.json")).unwrap();
+        TracerOutput::from(frame)
+    }
+
+    #[test]
+    fn test_native_top_level_calls() {
+        let out = tracer_output();
+
+        let sigs: Vec<_> = out
+            .calls_from_entry_point
+            .iter()
+            .map(|c| c.top_level_method_sig.as_str())
+            .collect();
+        assert_eq!(sigs, vec!["0x570e1a36", "0x19822f7c", "0x52b7512c"]);
+
+        let factory = &out.calls_from_entry_point[0];
+        assert_eq!(factory.opcodes.get(&Opcode::CREATE2), Some(&1));
+        assert_eq!(factory.opcodes.get(&Opcode::CALL), Some(&1));
+
+        let account = &out.calls_from_entry_point[1];
+        assert_eq!(
+            account.top_level_target_address,
+            hex::encode_prefixed(SENDER)
+        );
+        assert_eq!(account.opcodes.get(&Opcode::TIMESTAMP), Some(&1));
+        assert_eq!(account.opcodes.get(&Opcode::SLOAD), Some(&3));
+        assert_eq!(account.oog, None);
+
+        // delegate calls access the storage of the caller
+        let access = &account.access[&SENDER];
+        assert_eq!(access.reads[&U256::from(1)], U256::from(5));
+        assert_eq!(access.reads[&U256::from(3)], U256::from(7));
+        assert_eq!(access.reads[&U256::from(9)], U256::ZERO);
+        assert_eq!(access.writes[&U256::from(2)], 1);
+        assert_eq!(account.access.len(), 1);
+
+        let undeployed = address!("deaddeaddeaddeaddeaddeaddeaddeaddeaddead");
+        assert_eq!(account.contract_info[&undeployed].length, 0);
+        assert_eq!(
+            account.contract_info[&undeployed].opcode,
+            Opcode::EXTCODESIZE
+        );
+        assert!(account.ext_code_access_info.contains_key(&ENTRY_POINT));
+
+        let paymaster = &out.calls_from_entry_point[2];
+        assert_eq!(paymaster.oog, Some(true));
+    }
+
+    #[test]
+    fn test_native_call_stack() {
+        let out = tracer_output();
+
+        let kinds: Vec<_> = out
+            .calls
+            .iter()
+            .map(|c| match c {
+                CallInfo::Method(m) => m.method.clone(),
+                CallInfo::Exit(e) => format!("{:?}", e.exit_type),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "0x570e1a36",
+                "0x5fbfb9cf",
+                "Return",
+                "Return",
+                "0x19822f7c",
+                "0x19822f7c",
+                "Return",
+                "Return",
+                "0x52b7512c",
+                "Revert",
+                "Return",
+            ]
+        );
+
+        let CallInfo::Exit(last) = out.calls.last().unwrap() else {
+            panic!("last call should be an exit");
+        };
+        assert_eq!(
+            last.data,
+            "0x00000000000000000000000000000000000000000000000000000000000000a0"
+        );
+        assert_eq!(last.gas_used, 120_000);
+    }
+
+    #[test]
+    fn test_native_keccak_and_expected_storage() {
+        let out = tracer_output();
+
+        assert_eq!(out.keccak.len(), 2);
+        assert!(out.keccak[0].starts_with(&format!("0x000000000000000000000000{SENDER:x}")));
+
+        // slot 2 is written so its value at the first read is unknown
+        let expected = &out.expected_storage.0[&SENDER];
+        assert_eq!(
+            expected.keys().copied().collect::<Vec<_>>(),
+            vec![B256::from(U256::from(1)), B256::from(U256::from(3))]
+        );
+        assert_eq!(out.logs.len(), 1);
+    }
+}
@@ -16,10 +16,16 @@
 use std::time::Duration;
 
 use alloy_primitives::B256;
-use rundler_provider::{Block, BlockId, EvmProvider};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use rundler_provider::{Block, BlockHeader, BlockId, EvmProvider};
 use rundler_utils::retry::{self, UnlimitedRetryOpts};
-use tokio::time;
-use tracing::error;
+use tokio::time::{self, Instant};
+use tracing::{error, info, warn};
+
+/// Time to wait before reconnecting a dropped `newHeads` subscription
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Time without a new head after which the subscription is considered stale and reconnected
+const STALE_SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Wait for a new block (by hash) to be discovered and return it.
 ///
@@ -31,18 +37,8 @@ pub async fn wait_for_new_block(
     poll_interval: Duration,
 ) -> (B256, Block) {
     loop {
-        let block = retry::with_unlimited_retries(
-            "watch latest block",
-            || provider.get_block(BlockId::latest()),
-            UnlimitedRetryOpts::default(),
-        )
-        .await;
-        let Some(block) = block else {
-            error!("Latest block should be present when waiting for new block.");
-            continue;
-        };
-        if last_block_hash != block.header.hash {
-            return (block.header.hash, block);
+        if let Some(found) = poll_new_block(provider, last_block_hash).await {
+            return found;
         }
         time::sleep(poll_interval).await;
     }
@@ -70,3 +66,305 @@ pub async fn wait_for_new_block_number(
         time::sleep(poll_interval).await;
     }
 }
+
+type Subscribe = Box<
+    dyn Fn() -> BoxFuture<'static, anyhow::Result<BoxStream<'static, BlockHeader>>> + Send + Sync,
+>;
+
+/// Watches for new blocks with an `eth_subscribe("newHeads")` websocket subscription.
+///
+/// The subscription is reconnected when it drops or goes stale, and the provider is polled
+/// while it is disconnected. Blocks skipped by the subscription are not returned, callers
+/// must backfill from the returned block's ancestors.
+pub struct NewHeadsWatcher {
+    subscribe: Subscribe,
+    subscription: Option<BoxStream<'static, BlockHeader>>,
+    next_connect: Instant,
+}
+
+impl std::fmt::Debug for NewHeadsWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NewHeadsWatcher")
+            .field("connected", &self.subscription.is_some())
+            .field("next_connect", &self.next_connect)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NewHeadsWatcher {
+    /// Create a new watcher subscribing to the node at `ws_url`. The subscription is
+    /// connected on first use.
+    pub fn new(ws_url: String) -> Self {
+        Self::with_subscribe(Box::new(move || {
+            let ws_url = ws_url.clone();
+            async move { rundler_provider::subscribe_new_heads(&ws_url).await }.boxed()
+        }))
+    }
+
+    fn with_subscribe(subscribe: Subscribe) -> Self {
+        Self {
+            subscribe,
+            subscription: None,
+            next_connect: Instant::now(),
+        }
+    }
+
+    /// Wait for a new block (by hash) to be discovered and return it.
+    ///
+    /// Returns the block of the newest head available from the subscription, skipping any
+    /// older heads that are already queued. While the subscription is disconnected the
+    /// provider is polled every `poll_interval` instead, with unlimited retries.
+    pub async fn wait_for_new_block(
+        &mut self,
+        provider: &impl EvmProvider,
+        last_block_hash: B256,
+        poll_interval: Duration,
+    ) -> (B256, Block) {
+        loop {
+            if self.subscription.is_none() {
+                if Instant::now() >= self.next_connect {
+                    self.connect().await;
+                }
+                // catch up on any blocks produced while disconnected
+                if let Some(found) = poll_new_block(provider, last_block_hash).await {
+                    return found;
+                }
+            }
+
+            let Some(subscription) = self.subscription.as_mut() else {
+                time::sleep(poll_interval).await;
+                continue;
+            };
+
+            match time::timeout(STALE_SUBSCRIPTION_TIMEOUT, subscription.next()).await {
+                Ok(Some(mut header)) => {
+                    // skip to the newest queued head, the caller backfills the skipped blocks
+                    let mut closed = false;
+                    while let Some(next) = subscription.next().now_or_never() {
+                        match next {
+                            Some(next) => header = next,
+                            None => {
+                                closed = true;
+                                break;
+                            }
+                        }
+                    }
+                    if closed {
+                        warn!("New heads subscription closed, falling back to polling");
+                        self.disconnect();
+                    }
+
+                    if header.hash == last_block_hash {
+                        continue;
+                    }
+                    match provider.get_block(header.hash.into()).await {
+                        Ok(Some(block)) => return (block.header.hash, block),
+                        // the head may have been reorged out, wait for the next one
+                        Ok(None) => warn!("Block {:?} from new heads not found", header.hash),
+                        Err(error) => {
+                            warn!(
+                                "Failed to load block {:?} from new heads: {error:?}",
+                                header.hash
+                            )
+                        }
+                    }
+                }
+                Ok(None) => {
+                    warn!("New heads subscription closed, falling back to polling");
+                    self.disconnect();
+                }
+                Err(_) => {
+                    warn!(
+                        "No new heads for {STALE_SUBSCRIPTION_TIMEOUT:?}, reconnecting subscription"
+                    );
+                    self.disconnect();
+                }
+            }
+        }
+    }
+
+    async fn connect(&mut self) {
+        match (self.subscribe)().await {
+            Ok(subscription) => {
+                info!("Subscribed to new heads");
+                self.subscription = Some(subscription);
+            }
+            Err(error) => {
+                warn!("Failed to subscribe to new heads, falling back to polling: {error:?}");
+                self.next_connect = Instant::now() + RECONNECT_DELAY;
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.subscription = None;
+        self.next_connect = Instant::now() + RECONNECT_DELAY;
+    }
+}
+
+/// Check the provider once for a latest block different from `last_block_hash`, with
+/// unlimited retries.
+async fn poll_new_block(
+    provider: &impl EvmProvider,
+    last_block_hash: B256,
+) -> Option<(B256, Block)> {
+    let block = retry::with_unlimited_retries(
+        "watch latest block",
+        || provider.get_block(BlockId::latest()),
+        UnlimitedRetryOpts::default(),
+    )
+    .await;
+    let Some(block) = block else {
+        error!("Latest block should be present when waiting for new block.");
+        return None;
+    };
+    (last_block_hash != block.header.hash).then(|| (block.header.hash, block))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use futures::stream;
+    use rundler_provider::{BlockNumberOrTag, MockEvmProvider};
+
+    use super::*;
+
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn header(n: u8) -> BlockHeader {
+        BlockHeader {
+            hash: B256::repeat_byte(n),
+            ..Default::default()
+        }
+    }
+
+    fn block(hash: B256) -> Block {
+        Block {
+            header: BlockHeader {
+                hash,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // Returns a provider whose latest block is read from `latest`, and that records the
+    // hashes of the blocks loaded by hash
+    fn provider(latest: Arc<Mutex<B256>>, loaded: Arc<Mutex<Vec<B256>>>) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_block().returning(move |id| match id {
+            BlockId::Hash(hash) => {
+                loaded.lock().unwrap().push(hash.block_hash);
+                Ok(Some(block(hash.block_hash)))
+            }
+            BlockId::Number(BlockNumberOrTag::Latest) => Ok(Some(block(*latest.lock().unwrap()))),
+            _ => panic!("unexpected block id {id:?}"),
+        });
+        provider
+    }
+
+    // Returns a watcher that uses the subscriptions from `subscriptions` in order, and fails
+    // to subscribe once they run out
+    fn watcher(
+        subscriptions: Vec<BoxStream<'static, BlockHeader>>,
+        subscribes: Arc<AtomicUsize>,
+    ) -> NewHeadsWatcher {
+        let subscriptions = Mutex::new(subscriptions.into_iter());
+        NewHeadsWatcher::with_subscribe(Box::new(move || {
+            subscribes.fetch_add(1, Ordering::SeqCst);
+            let subscription = subscriptions.lock().unwrap().next();
+            async move { subscription.ok_or_else(|| anyhow::anyhow!("connection refused")) }.boxed()
+        }))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_subscription_closed_falls_back_to_polling() {
+        let latest = Arc::new(Mutex::new(header(0).hash));
+        let provider = provider(latest.clone(), Arc::default());
+        let subscribes = Arc::new(AtomicUsize::new(0));
+        let mut watcher = watcher(vec![stream::iter([header(1)]).boxed()], subscribes.clone());
+
+        let (hash, _) = watcher
+            .wait_for_new_block(&provider, header(0).hash, POLL_INTERVAL)
+            .await;
+        assert_eq!(hash, header(1).hash);
+
+        // the closed subscription is not reconnected until the reconnect delay has passed,
+        // the new block is found by polling
+        *latest.lock().unwrap() = header(2).hash;
+        let (hash, _) = watcher
+            .wait_for_new_block(&provider, header(1).hash, POLL_INTERVAL)
+            .await;
+        assert_eq!(hash, header(2).hash);
+        assert_eq!(subscribes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_subscription_reconnects() {
+        let latest = Arc::new(Mutex::new(header(0).hash));
+        let provider = provider(latest, Arc::default());
+        let subscribes = Arc::new(AtomicUsize::new(0));
+        let mut watcher = watcher(
+            vec![
+                stream::pending().boxed(),
+                stream::iter([header(1)]).chain(stream::pending()).boxed(),
+            ],
+            subscribes.clone(),
+        );
+
+        let start = Instant::now();
+        let (hash, _) = watcher
+            .wait_for_new_block(&provider, header(0).hash, POLL_INTERVAL)
+            .await;
+        assert_eq!(hash, header(1).hash);
+        assert_eq!(subscribes.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= STALE_SUBSCRIPTION_TIMEOUT + RECONNECT_DELAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_duplicate_head_skipped() {
+        let latest = Arc::new(Mutex::new(header(0).hash));
+        let loaded = Arc::new(Mutex::new(vec![]));
+        let provider = provider(latest, loaded.clone());
+        let delayed = stream::once(async {
+            time::sleep(POLL_INTERVAL).await;
+            header(1)
+        });
+        let mut watcher = watcher(
+            vec![stream::iter([header(0)])
+                .chain(delayed)
+                .chain(stream::pending())
+                .boxed()],
+            Arc::default(),
+        );
+
+        let (hash, _) = watcher
+            .wait_for_new_block(&provider, header(0).hash, POLL_INTERVAL)
+            .await;
+        assert_eq!(hash, header(1).hash);
+        assert_eq!(*loaded.lock().unwrap(), vec![header(1).hash]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skips_to_newest_queued_head() {
+        let latest = Arc::new(Mutex::new(header(0).hash));
+        let loaded = Arc::new(Mutex::new(vec![]));
+        let provider = provider(latest, loaded.clone());
+        let mut watcher = watcher(
+            vec![stream::iter([header(1), header(2), header(3)])
+                .chain(stream::pending())
+                .boxed()],
+            Arc::default(),
+        );
+
+        let (hash, _) = watcher
+            .wait_for_new_block(&provider, header(0).hash, POLL_INTERVAL)
+            .await;
+        assert_eq!(hash, header(3).hash);
+        assert_eq!(*loaded.lock().unwrap(), vec![header(3).hash]);
+    }
+}
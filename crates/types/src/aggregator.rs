@@ -0,0 +1,109 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Types associated with signature aggregators
+
+use std::collections::HashMap;
+
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// How the signatures of a trusted aggregator are aggregated when building a bundle
+#[derive(Clone, Copy, Debug, Deserialize, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AggregatorType {
+    /// Call the aggregator contract's `aggregateSignatures` function
+    #[default]
+    Contract,
+    /// BN254 BLS signatures, compatible with the reference `BLSSignatureAggregator`.
+    ///
+    /// Signatures are aggregated by the builder without calling the contract.
+    Bls,
+}
+
+/// Configuration of a trusted signature aggregator
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregatorConfig {
+    /// Address of the aggregator contract
+    pub address: Address,
+    /// How signatures are aggregated
+    #[serde(default, rename = "type")]
+    pub aggregator_type: AggregatorType,
+    /// Gas charged to each operation, through its pre-verification gas, for its share of
+    /// the aggregator's `validateSignatures` call
+    #[serde(default)]
+    pub validation_gas_per_op: u128,
+}
+
+/// The allowlist of trusted signature aggregators, keyed by address.
+///
+/// Operations using an aggregator that is not in this list are rejected.
+///
+/// Typically read from a JSON file containing a list of `AggregatorConfig`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(from = "Vec<AggregatorConfig>")]
+pub struct AggregatorConfigs(HashMap<Address, AggregatorConfig>);
+
+impl AggregatorConfigs {
+    /// Get the configuration of a trusted aggregator
+    pub fn get(&self, address: &Address) -> Option<&AggregatorConfig> {
+        self.0.get(address)
+    }
+
+    /// Returns true if no aggregators are trusted
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<AggregatorConfig>> for AggregatorConfigs {
+    fn from(configs: Vec<AggregatorConfig>) -> Self {
+        Self(
+            configs
+                .into_iter()
+                .map(|config| (config.address, config))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_aggregator_configs() {
+        let json = r#"[
+            {
+                "address": "0x0000000000000000000000000000000000000001",
+                "type": "BLS",
+                "validationGasPerOp": 45000
+            },
+            {
+                "address": "0x0000000000000000000000000000000000000002"
+            }
+        ]"#;
+        let configs: AggregatorConfigs = serde_json::from_str(json).unwrap();
+
+        let bls = configs.get(&Address::with_last_byte(1)).unwrap();
+        assert_eq!(bls.aggregator_type, AggregatorType::Bls);
+        assert_eq!(bls.validation_gas_per_op, 45000);
+
+        let contract = configs.get(&Address::with_last_byte(2)).unwrap();
+        assert_eq!(contract.aggregator_type, AggregatorType::Contract);
+        assert_eq!(contract.validation_gas_per_op, 0);
+
+        assert!(configs.get(&Address::with_last_byte(3)).is_none());
+    }
+}
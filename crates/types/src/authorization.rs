@@ -45,3 +45,16 @@ impl From<Authorization> for alloy_eips::eip7702::SignedAuthorization {
         SignedAuthorization::new_unchecked(authorization, value.y_parity, value.r, value.s)
     }
 }
+
+impl Authorization {
+    /// Recovers the address of the account that signed the authorization.
+    ///
+    /// Returns `None` if the signature is invalid.
+    pub fn recover_authority(&self) -> Option<Address> {
+        let signed = SignedAuthorization::from(self.clone());
+        let signature = signed.signature().ok()?;
+        signature
+            .recover_address_from_prehash(&signed.signature_hash())
+            .ok()
+    }
+}
@@ -22,6 +22,7 @@ use crate::da::DAGasOracleType;
 
 const ENTRY_POINT_ADDRESS_V6_0: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
 const ENTRY_POINT_ADDRESS_V7_0: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+const ENTRY_POINT_ADDRESS_V8_0: &str = "0x4337084D9E255Ff0702461CF8895CE9E3b5Ff108";
 
 /// Chain specification for Rundler
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -37,6 +38,8 @@ pub struct ChainSpec {
     pub entry_point_address_v0_6: Address,
     /// entry point address for v0_7
     pub entry_point_address_v0_7: Address,
+    /// entry point address for v0_8
+    pub entry_point_address_v0_8: Address,
     /// Overhead when preforming gas estimation to account for the deposit storage
     /// and transfer overhead.
     ///
@@ -60,6 +63,14 @@ pub struct ChainSpec {
     pub calldata_zero_byte_gas: u64,
     /// Gas cost for a non-zero byte in calldata
     pub calldata_non_zero_byte_gas: u64,
+    /// Hardfork of the embedded EVM used by local simulation, by its revm name, e.g. "Cancun".
+    /// If unset, it is derived from the fields present in each block header.
+    pub evm_hardfork: Option<String>,
+    /// True if calls on this chain can be executed in the embedded EVM used by local
+    /// simulation, i.e. its precompiles and opcodes behave as on Ethereum mainnet, apart from
+    /// extra precompiles at addresses up to 0xffff. Local simulation is rejected at startup
+    /// otherwise.
+    pub embedded_evm_supported: bool,
 
     /*
      * Gas estimation
@@ -74,6 +85,9 @@ pub struct ChainSpec {
     /// true if Data Availability (DA) calldata gas should be included in the gas limit
     /// only applies when da_pre_verification_gas is true
     pub include_da_gas_in_gas_limit: bool,
+    /// URL of an ERC-7677 paymaster service used to sponsor user operations,
+    /// must be set to use `rundler_sponsorUserOperation`
+    pub paymaster_service_url: Option<String>,
 
     /*
      * Fee estimation
@@ -138,6 +152,7 @@ impl Default for ChainSpec {
             id: 0,
             entry_point_address_v0_6: Address::from_str(ENTRY_POINT_ADDRESS_V6_0).unwrap(),
             entry_point_address_v0_7: Address::from_str(ENTRY_POINT_ADDRESS_V7_0).unwrap(),
+            entry_point_address_v0_8: Address::from_str(ENTRY_POINT_ADDRESS_V8_0).unwrap(),
             deposit_transfer_overhead: 30_000,
             transaction_intrinsic_gas: 21_000,
             per_user_op_v0_6_gas: 18_300,
@@ -146,11 +161,14 @@ impl Default for ChainSpec {
             per_user_op_word_gas: 4,
             calldata_zero_byte_gas: 4,
             calldata_non_zero_byte_gas: 16,
+            evm_hardfork: None,
+            embedded_evm_supported: true,
             eip1559_enabled: true,
             da_pre_verification_gas: false,
             da_gas_oracle_type: DAGasOracleType::default(),
             da_gas_oracle_contract_address: Address::ZERO,
             include_da_gas_in_gas_limit: false,
+            paymaster_service_url: None,
             priority_fee_oracle_type: PriorityFeeOracleType::default(),
             min_max_priority_fee_per_gas: 0,
             max_max_priority_fee_per_gas: u64::MAX,
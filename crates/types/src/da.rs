@@ -30,6 +30,14 @@ pub enum DAGasOracleType {
     LocalBedrock,
     /// Cached Nitro type gas oracle
     CachedNitro,
+    /// Scroll L1 gas price oracle
+    ScrollL1,
+    /// Local Scroll type gas oracle
+    LocalScroll,
+    /// Local Mantle type gas oracle, with fees converted by the oracle's token ratio
+    LocalMantle,
+    /// ZK Stack (zkSync Era style) pubdata gas oracle
+    ZkStackPubdata,
 }
 
 /// Data associated with a user operation for Nitro DA gas calculations
@@ -49,6 +57,28 @@ pub struct BedrockDAGasUOData {
     pub uo_units: u64,
 }
 
+/// Data associated with a user operation for Scroll DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrollDAGasUOData {
+    /// The length of the user operation data, in bytes
+    pub uo_units: u64,
+}
+
+/// Data associated with a user operation for Mantle DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MantleDAGasUOData {
+    /// The L1 calldata gas of the user operation data, including the padding for
+    /// the transaction signature
+    pub uo_units: u64,
+}
+
+/// Data associated with a user operation for ZK Stack DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZkStackDAGasUOData {
+    /// The number of pubdata bytes published for the user operation data
+    pub uo_units: u64,
+}
+
 /// Data associated with a user operation for DA gas calculations
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum DAGasUOData {
@@ -59,6 +89,12 @@ pub enum DAGasUOData {
     Nitro(NitroDAGasUOData),
     /// Bedrock DA
     Bedrock(BedrockDAGasUOData),
+    /// Scroll DA
+    Scroll(ScrollDAGasUOData),
+    /// Mantle DA
+    Mantle(MantleDAGasUOData),
+    /// ZK Stack DA
+    ZkStack(ZkStackDAGasUOData),
 }
 
 /// Data associated with a block for DA gas calculations
@@ -71,6 +107,12 @@ pub enum DAGasBlockData {
     Nitro(NitroDAGasBlockData),
     /// Bedrock DA
     Bedrock(BedrockDAGasBlockData),
+    /// Scroll DA
+    Scroll(ScrollDAGasBlockData),
+    /// Mantle DA
+    Mantle(MantleDAGasBlockData),
+    /// ZK Stack DA
+    ZkStack(ZkStackDAGasBlockData),
 }
 
 /// Data associated with a block for Nitro DA gas calculations
@@ -94,3 +136,39 @@ pub struct BedrockDAGasBlockData {
     /// Blob base fee retrieved from the bedrock gas oracle.
     pub blob_base_fee: u64,
 }
+
+/// Data associated with a block for Scroll DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrollDAGasBlockData {
+    /// L1 base fee retrieved from the scroll gas oracle.
+    pub l1_base_fee: u128,
+    /// L1 blob base fee retrieved from the scroll gas oracle.
+    pub l1_blob_base_fee: u128,
+    /// Commit scalar retrieved from the scroll gas oracle.
+    pub commit_scalar: u128,
+    /// Blob scalar retrieved from the scroll gas oracle.
+    pub blob_scalar: u128,
+}
+
+/// Data associated with a block for Mantle DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MantleDAGasBlockData {
+    /// L1 base fee retrieved from the mantle gas oracle.
+    pub l1_base_fee: u128,
+    /// Fixed L1 gas overhead retrieved from the mantle gas oracle.
+    pub overhead: u64,
+    /// Fee scalar retrieved from the mantle gas oracle.
+    pub scalar: u64,
+    /// Decimals of the fee scalar retrieved from the mantle gas oracle.
+    pub decimals: u32,
+    /// Ratio of the price of ETH to the price of MNT retrieved from the mantle gas oracle.
+    pub token_ratio: u128,
+}
+
+/// Data associated with a block for ZK Stack DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZkStackDAGasBlockData {
+    /// Price of a byte of pubdata, in the chain's base token, retrieved from the node's
+    /// fee parameters.
+    pub pubdata_price: u128,
+}
@@ -12,9 +12,11 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use rundler_utils::math;
+use serde::Serialize;
 
 /// Gas fees for a user operation or transaction
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GasFees {
     /// EIP-1559 max fee per gas
     pub max_fee_per_gas: u128,
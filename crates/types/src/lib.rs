@@ -20,6 +20,8 @@
 
 //! Rundler common types
 
+pub mod aggregator;
+
 pub mod builder;
 
 pub mod chain;
@@ -37,6 +39,8 @@ pub use gas::GasFees;
 
 pub mod pool;
 
+pub mod revert;
+
 mod timestamp;
 pub use timestamp::{Timestamp, ValidTimeRange};
 
@@ -100,6 +100,12 @@ pub enum MempoolError {
     /// Call gas limit efficiency too low
     #[error("Call gas limit efficiency too low. Required: {0}, Actual: {1}")]
     CallGasLimitEfficiencyTooLow(f32, f32),
+    /// The operations of an atomic group can't be added to the pool together
+    #[error("Invalid atomic group: {0}")]
+    InvalidAtomicGroup(String),
+    /// Operation would replace an operation that is part of an atomic group
+    #[error("Operation would replace an operation of an atomic group, which can't be replaced")]
+    AtomicGroupReplacement,
 }
 
 /// Precheck violation enumeration
@@ -148,6 +154,9 @@ pub enum PrecheckViolation {
     /// The Uo contains both factory and authorization tuple.
     #[display("Factory must be empty when authorization contract is set")]
     FactoryMustBeEmpty(Address),
+    /// The Uo has EIP-7702 init code but no authorization tuple.
+    #[display("sender {0:?} has EIP-7702 initCode but no authorization tuple")]
+    MissingEip7702Authorization(Address),
 }
 
 /// All possible simulation violations
@@ -257,3 +266,46 @@ pub struct NeedsStakeInformation {
     /// Minumum delay after an unstake event
     pub min_unstake_delay: u32,
 }
+
+impl SimulationViolation {
+    /// The [ERC-7562](https://eips.ethereum.org/EIPS/eip-7562) rule broken by this
+    /// violation, e.g. `"OP-011"`, if it breaks one.
+    pub fn erc7562_rule(&self) -> Option<&'static str> {
+        let rule = match self {
+            Self::UsedForbiddenOpcode(..) => "OP-011",
+            Self::UsedForbiddenPrecompile(..) => "OP-062",
+            Self::AccessedUndeployedContract(..) => "OP-041",
+            Self::FactoryCalledCreate2Twice(_) => "OP-031",
+            Self::CalledBannedEntryPointMethod(_) => "OP-054",
+            Self::CallHadValue(_) => "OP-061",
+            Self::OutOfGas(_) => "OP-020",
+            Self::InvalidStorageAccess(..) => "STO-033",
+            Self::AssociatedStorageDuringDeploy(..) => "STO-022",
+            Self::NotStaked(info) => {
+                if info.accessed_address == info.needs_stake.address {
+                    "STO-031"
+                } else if info.accessed_entity.is_some() {
+                    "STO-032"
+                } else {
+                    "STO-033"
+                }
+            }
+            Self::CodeHashChanged => "COD-010",
+            Self::UnstakedPaymasterContext => "EREP-050",
+            Self::UnstakedAggregator => "EREP-040",
+            Self::InvalidSignature
+            | Self::InvalidAccountSignature
+            | Self::InvalidTimeRange(..)
+            | Self::InvalidPaymasterSignature
+            | Self::UnintendedRevertWithMessage(..)
+            | Self::UnintendedRevert(..)
+            | Self::ValidationRevert(_)
+            | Self::DidNotRevert
+            | Self::WrongNumberOfPhases(_)
+            | Self::AggregatorValidationFailed
+            | Self::VerificationGasLimitBufferTooLow(..)
+            | Self::AccessedUnsupportedContractType(..) => return None,
+        };
+        Some(rule)
+    }
+}
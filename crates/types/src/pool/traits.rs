@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::pin::Pin;
+use std::{fmt::Debug, pin::Pin};
 
 use alloy_primitives::{Address, B256};
 use futures_util::Stream;
@@ -20,7 +20,10 @@ use mockall::automock;
 
 use super::{
     error::PoolError,
-    types::{NewHead, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus},
+    types::{
+        MinedOpLocation, NewHead, OpStatusUpdate, PaymasterMetadata, PoolOperation, Reputation,
+        ReputationStatus, StakeStatus,
+    },
 };
 use crate::{EntityUpdate, UserOperationId, UserOperationVariant};
 
@@ -30,13 +33,23 @@ pub type PoolResult<T> = std::result::Result<T, PoolError>;
 /// Pool server trait
 #[cfg_attr(feature = "test-utils", automock)]
 #[async_trait::async_trait]
-pub trait Pool: Send + Sync {
+pub trait Pool: Send + Sync + Debug {
     /// Get the supported entry points of the pool
     async fn get_supported_entry_points(&self) -> PoolResult<Vec<Address>>;
 
     /// Add an operation to the pool
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256>;
 
+    /// Add an ordered group of operations to the pool, to be bundled together or not at all.
+    ///
+    /// Either all operations are added or none are. Returns the hashes of the operations
+    /// in the order they were given.
+    async fn add_op_group(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+    ) -> PoolResult<Vec<B256>>;
+
     /// Get operations from the pool
     async fn get_ops(
         &self,
@@ -50,6 +63,10 @@ pub trait Pool: Send + Sync {
     /// Returns None if the operation is not found
     async fn get_op_by_hash(&self, hash: B256) -> PoolResult<Option<PoolOperation>>;
 
+    /// Get where an operation was mined from the pool's mined operation index
+    /// Returns None if the operation is not indexed, or if the index is disabled
+    async fn get_mined_op_location(&self, hash: B256) -> PoolResult<Option<MinedOpLocation>>;
+
     /// Remove operations from the pool by hash
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()>;
 
@@ -73,6 +90,14 @@ pub trait Pool: Send + Sync {
     /// has processed all operations up to that head.
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>>;
 
+    /// Subscribe to status updates of user operations from the pool.
+    ///
+    /// The pool will notify the subscriber when an operation is added to the pool, dropped
+    /// from the pool, mined, or unmined in a reorg.
+    async fn subscribe_op_status(
+        &self,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = OpStatusUpdate> + Send>>>;
+
     /// Get reputation status given entrypoint and address
     async fn get_reputation_status(
         &self,
@@ -28,6 +28,76 @@ pub struct NewHead {
     pub block_number: u64,
 }
 
+/// An update to the status of a user operation, as viewed by the pool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpStatusUpdate {
+    /// The hash of the operation
+    pub hash: B256,
+    /// The entry point of the operation
+    pub entry_point: Address,
+    /// The sender of the operation
+    pub sender: Address,
+    /// The nonce of the operation
+    pub nonce: U256,
+    /// The new status of the operation
+    pub status: OpStatus,
+}
+
+/// The status of a user operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpStatus {
+    /// The operation was added to the pool
+    Pending,
+    /// The operation was removed from the pool without being mined
+    Dropped(OpDropReason),
+    /// The operation was mined.
+    ///
+    /// The block is the head of the chain when the pool saw the operation mined, if the pool
+    /// processed several blocks at once the operation may have been mined in an earlier block.
+    Mined {
+        /// The number of the block
+        block_number: u64,
+        /// The hash of the block
+        block_hash: B256,
+    },
+    /// The block the operation was mined in was reorged away. If the operation is still
+    /// valid, it is returned to the pool.
+    Unmined,
+}
+
+/// The reason a user operation was dropped from the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum OpDropReason {
+    /// Removal was requested by the sender or a bundler
+    Requested,
+    /// Replaced by an operation with the same sender and nonce
+    Replaced,
+    /// The operation's valid time range expired
+    Expired,
+    /// An entity of the operation was throttled or banned
+    Entity,
+    /// The pool was full and the operation had the lowest priority
+    PoolSizeExceeded,
+    /// Another operation of the operation's atomic group was dropped
+    AtomicGroup,
+}
+
+/// Where a user operation was mined, as recorded by the pool's mined operation index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinedOpLocation {
+    /// The hash of the operation
+    pub hash: B256,
+    /// The entry point of the operation
+    pub entry_point: Address,
+    /// The hash of the transaction the operation was mined in
+    pub transaction_hash: B256,
+    /// The number of the block the operation was mined in
+    pub block_number: u64,
+    /// The hash of the block the operation was mined in
+    pub block_hash: B256,
+}
+
 /// The reputation of an entity
 #[derive(Debug, Clone)]
 pub struct Reputation {
@@ -122,6 +192,11 @@ pub struct PoolOperation {
     pub entity_infos: EntityInfos,
     /// The DA gas data for this operation
     pub da_gas_data: DAGasUOData,
+    /// Hashes of all operations of the atomic group this operation belongs to, in the
+    /// order they must be bundled. Operations of a group are bundled together or not at all.
+    pub atomic_group: Option<Vec<B256>>,
+    /// The ids of the mempools this operation was matched to during simulation
+    pub mempools: Vec<B256>,
 }
 
 impl PoolOperation {
@@ -154,6 +229,11 @@ impl PoolOperation {
 
     /// Compute the amount of heap memory the PoolOperation takes up.
     pub fn mem_size(&self) -> usize {
-        std::mem::size_of::<Self>() + self.uo.heap_size()
+        std::mem::size_of::<Self>()
+            + self.uo.heap_size()
+            + self
+                .atomic_group
+                .as_ref()
+                .map_or(0, |group| group.len() * std::mem::size_of::<B256>())
     }
 }
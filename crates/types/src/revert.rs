@@ -0,0 +1,332 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Decoding of user operation reverts into human-readable errors
+
+use std::collections::HashMap;
+
+use alloy_dyn_abi::{DynSolValue, ErrorExt};
+use alloy_json_abi::Error as AbiError;
+use alloy_primitives::{hex, Selector};
+use alloy_sol_types::{Panic, Revert, SolError};
+use serde::Serialize;
+
+use crate::{EntityType, ValidationRevert};
+
+/// Custom errors of popular accounts and paymasters, and of the libraries they are built on,
+/// that are always decoded
+const BUILT_IN_ERRORS: &[&str] = &[
+    // OpenZeppelin Contracts v5
+    "error OwnableUnauthorizedAccount(address account)",
+    "error OwnableInvalidOwner(address owner)",
+    "error ECDSAInvalidSignature()",
+    "error ECDSAInvalidSignatureLength(uint256 length)",
+    "error ECDSAInvalidSignatureS(bytes32 s)",
+    "error AddressEmptyCode(address target)",
+    "error FailedInnerCall()",
+    "error ReentrancyGuardReentrantCall()",
+    "error EnforcedPause()",
+    "error SafeERC20FailedOperation(address token)",
+    "error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed)",
+    "error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed)",
+    // Solady
+    "error Unauthorized()",
+    "error TransferFailed()",
+    "error TransferFromFailed()",
+    "error ETHTransferFailed()",
+    // Alchemy LightAccount
+    "error NotAuthorized(address caller)",
+    "error InvalidSignatureType()",
+    "error ArrayLengthMismatch()",
+];
+
+/// The phase of a user operation in which it reverted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RevertPhase {
+    /// Deployment of the sender, or validation by the account, paymaster or aggregator
+    Validation,
+    /// Execution of the user operation's call
+    Execution,
+    /// The paymaster's `postOp` call, or the entry point's checks after execution
+    PostOp,
+}
+
+/// Where a user operation reverted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RevertSource {
+    /// The entity that reverted, if the revert can be attributed to one
+    pub entity: Option<EntityType>,
+    /// The phase of the user operation in which it reverted
+    pub phase: RevertPhase,
+}
+
+impl RevertSource {
+    /// A revert of the user operation's call
+    pub const EXECUTION: Self = Self {
+        entity: Some(EntityType::Account),
+        phase: RevertPhase::Execution,
+    };
+
+    /// Attributes an entry point error code, e.g. `"AA23"`, to an entity and phase.
+    ///
+    /// Returns `None` if the code is not a known entry point error code.
+    pub fn from_entry_point_error_code(code: &str) -> Option<Self> {
+        let (entity, phase) = match code.get(..3)? {
+            "AA1" => (Some(EntityType::Factory), RevertPhase::Validation),
+            "AA2" => (Some(EntityType::Account), RevertPhase::Validation),
+            "AA3" => (Some(EntityType::Paymaster), RevertPhase::Validation),
+            "AA4" => (None, RevertPhase::Validation),
+            "AA5" if code == "AA50" => (Some(EntityType::Paymaster), RevertPhase::PostOp),
+            "AA5" => (None, RevertPhase::PostOp),
+            "AA9" if code == "AA96" => (Some(EntityType::Aggregator), RevertPhase::Validation),
+            "AA9" => (None, RevertPhase::Validation),
+            _ => return None,
+        };
+        Some(Self { entity, phase })
+    }
+
+    /// Attributes a validation revert to an entity and phase, using the entry point's error
+    /// code if it has one.
+    pub fn from_validation_revert(revert: &ValidationRevert) -> Self {
+        revert
+            .entry_point_error_code()
+            .and_then(Self::from_entry_point_error_code)
+            .unwrap_or(Self {
+                entity: None,
+                phase: RevertPhase::Validation,
+            })
+    }
+}
+
+/// Decodes revert data into human-readable reasons.
+///
+/// Understands `Error(string)`, `Panic(uint256)`, and a registry of custom errors made of
+/// [built-in](BUILT_IN_ERRORS) errors and any configured error signatures.
+#[derive(Clone, Debug)]
+pub struct RevertDecoder {
+    errors: HashMap<Selector, AbiError>,
+}
+
+impl Default for RevertDecoder {
+    fn default() -> Self {
+        Self::new::<&str>(&[]).expect("built-in errors should parse")
+    }
+}
+
+impl RevertDecoder {
+    /// Creates a decoder for the built-in custom errors and the given human-readable error
+    /// signatures, e.g. `"error InvalidSigner(address signer)"`.
+    ///
+    /// Configured errors replace built-in errors with the same selector.
+    pub fn new<S: AsRef<str>>(custom_errors: &[S]) -> anyhow::Result<Self> {
+        let mut errors = HashMap::new();
+        let signatures = BUILT_IN_ERRORS
+            .iter()
+            .copied()
+            .chain(custom_errors.iter().map(AsRef::as_ref));
+        for signature in signatures {
+            let error = AbiError::parse(signature)
+                .map_err(|e| anyhow::anyhow!("invalid error signature {signature:?}: {e}"))?;
+            errors.insert(error.selector(), error);
+        }
+        Ok(Self { errors })
+    }
+
+    /// Decodes revert data into a human-readable reason, or returns `None` if the data is
+    /// not a known error.
+    pub fn decode_reason(&self, data: &[u8]) -> Option<String> {
+        if let Ok(revert) = Revert::abi_decode(data, false) {
+            return Some(revert.reason);
+        }
+        if let Ok(panic) = Panic::abi_decode(data, false) {
+            return Some(match panic.kind() {
+                Some(kind) => format!("panic: {} ({:#x})", kind.as_str(), panic.code),
+                None => format!("panic: {:#x}", panic.code),
+            });
+        }
+
+        let selector = Selector::try_from(data.get(..4)?).ok()?;
+        let error = self.errors.get(&selector)?;
+        let decoded = error.decode_error(data).ok()?;
+        let args = error
+            .inputs
+            .iter()
+            .zip(&decoded.body)
+            .map(|(param, value)| {
+                if param.name.is_empty() {
+                    format_value(value)
+                } else {
+                    format!("{}: {}", param.name, format_value(value))
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(format!("{}({})", error.name, args.join(", ")))
+    }
+}
+
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Address(address) => address.to_string(),
+        DynSolValue::Bool(b) => b.to_string(),
+        DynSolValue::Int(i, _) => i.to_string(),
+        DynSolValue::Uint(u, _) => u.to_string(),
+        DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+        DynSolValue::Bytes(bytes) => hex::encode_prefixed(bytes),
+        DynSolValue::String(s) => format!("{s:?}"),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            format!("[{}]", format_values(values))
+        }
+        DynSolValue::Tuple(values) => format!("({})", format_values(values)),
+        _ => format!("{value:?}"),
+    }
+}
+
+fn format_values(values: &[DynSolValue]) -> String {
+    values
+        .iter()
+        .map(format_value)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, Bytes, U256};
+    use alloy_sol_types::sol;
+
+    use super::*;
+
+    sol! {
+        error NotAuthorized(address caller);
+        error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+        error LimitExceeded(uint256 limit, string name);
+    }
+
+    #[test]
+    fn test_decode_revert_string() {
+        let data = Revert {
+            reason: "not allowed".to_string(),
+        }
+        .abi_encode();
+        assert_eq!(
+            RevertDecoder::default().decode_reason(&data),
+            Some("not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_panic() {
+        let data = Panic {
+            code: U256::from(0x11),
+        }
+        .abi_encode();
+        assert_eq!(
+            RevertDecoder::default().decode_reason(&data),
+            Some("panic: arithmetic underflow or overflow (0x11)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_built_in_error() {
+        let decoder = RevertDecoder::default();
+
+        let data = NotAuthorized {
+            caller: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+        }
+        .abi_encode();
+        assert_eq!(
+            decoder.decode_reason(&data),
+            Some("NotAuthorized(caller: 0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266)".to_string())
+        );
+
+        let data = ERC20InsufficientBalance {
+            sender: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+            balance: U256::from(1),
+            needed: U256::from(2),
+        }
+        .abi_encode();
+        assert_eq!(
+            decoder.decode_reason(&data),
+            Some("ERC20InsufficientBalance(sender: 0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266, balance: 1, needed: 2)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_configured_error() {
+        let data = LimitExceeded {
+            limit: U256::from(100),
+            name: "daily".to_string(),
+        }
+        .abi_encode();
+
+        assert_eq!(RevertDecoder::default().decode_reason(&data), None);
+
+        let decoder =
+            RevertDecoder::new(&["error LimitExceeded(uint256 limit, string name)"]).unwrap();
+        assert_eq!(
+            decoder.decode_reason(&data),
+            Some("LimitExceeded(limit: 100, name: \"daily\")".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        let decoder = RevertDecoder::default();
+        assert_eq!(decoder.decode_reason(&[]), None);
+        assert_eq!(decoder.decode_reason(&[0xde, 0xad, 0xbe, 0xef, 0x01]), None);
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        assert!(RevertDecoder::new(&["error Broken(uint256"]).is_err());
+    }
+
+    #[test]
+    fn test_revert_source() {
+        assert_eq!(
+            RevertSource::from_validation_revert(&ValidationRevert::EntryPoint(
+                "AA13 initCode failed or OOG".to_string()
+            )),
+            RevertSource {
+                entity: Some(EntityType::Factory),
+                phase: RevertPhase::Validation,
+            }
+        );
+        assert_eq!(
+            RevertSource::from_validation_revert(&ValidationRevert::Operation {
+                entry_point_reason: "AA33 reverted".to_string(),
+                inner_revert_data: Bytes::new(),
+                inner_revert_reason: None,
+            }),
+            RevertSource {
+                entity: Some(EntityType::Paymaster),
+                phase: RevertPhase::Validation,
+            }
+        );
+        assert_eq!(
+            RevertSource::from_entry_point_error_code("AA50"),
+            Some(RevertSource {
+                entity: Some(EntityType::Paymaster),
+                phase: RevertPhase::PostOp,
+            })
+        );
+        assert_eq!(
+            RevertSource::from_validation_revert(&ValidationRevert::Unknown(Bytes::new())),
+            RevertSource {
+                entity: None,
+                phase: RevertPhase::Validation,
+            }
+        );
+    }
+}
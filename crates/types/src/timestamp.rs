@@ -155,7 +155,8 @@ impl Display for TimestampTooLarge {
 impl Error for TimestampTooLarge {}
 
 /// Represents a `[valid_after, valid_until)` pair as seen in ERC-4337 validity checks.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidTimeRange {
     /// The earliest time at which the operation is valid, inclusive.
     pub valid_after: Timestamp,
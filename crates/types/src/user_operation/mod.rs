@@ -49,6 +49,8 @@ pub enum EntryPointVersion {
     V0_6,
     /// Version 0.7
     V0_7,
+    /// Version 0.8
+    V0_8,
 }
 
 /// Unique identifier for a user operation from a given sender
@@ -60,6 +62,20 @@ pub struct UserOperationId {
     pub nonce: U256,
 }
 
+impl UserOperationId {
+    /// Id of the operation from the same sender with the same nonce key and the
+    /// previous sequence number, if any
+    pub fn previous(&self) -> Option<Self> {
+        if self.nonce & U256::from(u64::MAX) == U256::ZERO {
+            return None;
+        }
+        Some(Self {
+            sender: self.sender,
+            nonce: self.nonce - U256::from(1),
+        })
+    }
+}
+
 /// User operation trait
 pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Optional gas type
@@ -89,6 +105,9 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Get the user operation calldata
     fn call_data(&self) -> &Bytes;
 
+    /// Get the user operation signature
+    fn signature(&self) -> &Bytes;
+
     /// Returns the call gas limit
     fn call_gas_limit(&self) -> u128;
 
@@ -113,6 +132,11 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Return the authorization list of the UO. empty if it is not 7702 txn.
     fn authorization_tuple(&self) -> Option<Authorization>;
 
+    /// Returns true if the init code marks an EIP-7702 delegated sender instead of a factory
+    fn has_eip7702_init_code(&self) -> bool {
+        false
+    }
+
     /*
      * Enhanced functions
      */
@@ -155,10 +179,11 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// This does NOT include any shared gas costs for a bundle (i.e. intrinsic gas)
     fn static_pre_verification_gas(&self, chain_spec: &ChainSpec) -> u128;
 
-    /// Clear the signature field of the user op
+    /// Replace the signature field of the user op
     ///
-    /// Used when a user op is using a signature aggregator prior to being submitted
-    fn clear_signature(&mut self);
+    /// Used when a user op is using a signature aggregator prior to being submitted, with
+    /// the signature the aggregator returned for the op
+    fn replace_signature(&mut self, signature: Bytes);
 
     /// Abi encode size of the user operation
     fn abi_encoded_size(&self) -> usize;
@@ -396,6 +421,13 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn signature(&self) -> &Bytes {
+        match self {
+            UserOperationVariant::V0_6(op) => op.signature(),
+            UserOperationVariant::V0_7(op) => op.signature(),
+        }
+    }
+
     fn max_gas_cost(&self) -> U256 {
         match self {
             UserOperationVariant::V0_6(op) => op.max_gas_cost(),
@@ -473,10 +505,10 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
-    fn clear_signature(&mut self) {
+    fn replace_signature(&mut self, signature: Bytes) {
         match self {
-            UserOperationVariant::V0_6(op) => op.clear_signature(),
-            UserOperationVariant::V0_7(op) => op.clear_signature(),
+            UserOperationVariant::V0_6(op) => op.replace_signature(signature),
+            UserOperationVariant::V0_7(op) => op.replace_signature(signature),
         }
     }
 
@@ -493,6 +525,13 @@ impl UserOperation for UserOperationVariant {
             UserOperationVariant::V0_7(op) => op.authorization_tuple(),
         }
     }
+
+    fn has_eip7702_init_code(&self) -> bool {
+        match self {
+            UserOperationVariant::V0_6(op) => op.has_eip7702_init_code(),
+            UserOperationVariant::V0_7(op) => op.has_eip7702_init_code(),
+        }
+    }
 }
 
 impl UserOperationVariant {
@@ -514,7 +553,7 @@ impl UserOperationVariant {
     pub fn uo_type(&self) -> EntryPointVersion {
         match self {
             UserOperationVariant::V0_6(_) => EntryPointVersion::V0_6,
-            UserOperationVariant::V0_7(_) => EntryPointVersion::V0_7,
+            UserOperationVariant::V0_7(op) => op.entry_point_version,
         }
     }
 }
@@ -634,4 +673,18 @@ mod tests {
         let b = Bytes::from(vec![0u8; 33]);
         assert_eq!(byte_array_abi_len(&b), 64);
     }
+
+    #[test]
+    fn test_user_operation_id_previous() {
+        let sender = Address::random();
+        let id = |nonce: U256| UserOperationId { sender, nonce };
+
+        assert_eq!(id(U256::ZERO).previous(), None);
+        assert_eq!(id(U256::from(2)).previous(), Some(id(U256::from(1))));
+
+        // the first sequence number of a key has no previous operation
+        let key = U256::from(5) << 64;
+        assert_eq!(id(key).previous(), None);
+        assert_eq!(id(key + U256::from(1)).previous(), Some(id(key)));
+    }
 }
@@ -199,6 +199,10 @@ impl UserOperationTrait for UserOperation {
         &self.call_data
     }
 
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         let mul: u128 = if self.paymaster().is_some() { 3 } else { 1 };
         U256::from(
@@ -268,8 +272,8 @@ impl UserOperationTrait for UserOperation {
             })
     }
 
-    fn clear_signature(&mut self) {
-        self.signature = Bytes::default();
+    fn replace_signature(&mut self, signature: Bytes) {
+        self.signature = signature;
     }
 
     fn abi_encoded_size(&self) -> usize {
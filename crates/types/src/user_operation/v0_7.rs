@@ -11,8 +11,10 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{ruint::FromUintError, Address, Bytes, FixedBytes, B256, U256};
-use alloy_sol_types::{sol, SolValue};
+use alloy_primitives::{
+    address, b256, ruint::FromUintError, Address, Bytes, FixedBytes, B256, U256,
+};
+use alloy_sol_types::{sol, Eip712Domain, SolValue};
 use rundler_contracts::v0_7::PackedUserOperation;
 
 use super::{
@@ -24,6 +26,16 @@ use crate::{authorization::Authorization, chain::ChainSpec, Entity, EntryPointVe
 /// Gas overhead required by the entry point contract for the inner call
 pub const ENTRY_POINT_INNER_GAS_OVERHEAD: u128 = 10_000;
 
+/// Factory address marking an EIP-7702 delegated sender in entry point v0.8.
+///
+/// The sender is not deployed by a factory. Any init code following the marker is
+/// called on the sender to initialize it.
+pub const INIT_CODE_EIP7702_MARKER: Address = address!("7702000000000000000000000000000000000000");
+
+/// EIP-712 type hash of `PackedUserOperation`, used by entry point v0.8
+const PACKED_USER_OPERATION_TYPE_HASH: B256 =
+    b256!("29a0bca4af4be3421398da00295e58e6d7de38cb492214754cb6a47507dd6f8e");
+
 /// Number of bytes in the fixed size portion of an ABI encoded user operation
 /// sender = 32 bytes
 /// nonce = 32 bytes
@@ -40,6 +52,9 @@ const ABI_ENCODED_USER_OPERATION_FIXED_LEN: usize = 416;
 
 /// User Operation for Entry Point v0.7
 ///
+/// Entry point v0.8 uses the same packed format and is represented by this type,
+/// see `entry_point_version` for the entry point the operation is for.
+///
 /// Offchain version, must be packed before sending onchain
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive] // Prevent instantiation except with UserOperationBuilder
@@ -90,6 +105,8 @@ pub struct UserOperation {
      */
     /// Entry point address
     pub entry_point: Address,
+    /// Entry point version, either v0.7 or v0.8
+    pub entry_point_version: EntryPointVersion,
     /// Chain id
     pub chain_id: u64,
     /// The hash of the user operation
@@ -131,13 +148,17 @@ impl UserOperationTrait for UserOperation {
     }
 
     fn factory(&self) -> Option<Address> {
-        self.factory
+        self.factory.filter(|_| !self.has_eip7702_init_code())
     }
 
     fn call_data(&self) -> &Bytes {
         &self.call_data
     }
 
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         U256::from(
             self.max_fee_per_gas
@@ -151,7 +172,7 @@ impl UserOperationTrait for UserOperation {
 
     fn entities(&self) -> Vec<Entity> {
         let mut ret = vec![Entity::account(self.sender)];
-        if let Some(factory) = self.factory {
+        if let Some(factory) = self.factory() {
             ret.push(Entity::factory(factory));
         }
         if let Some(paymaster) = self.paymaster {
@@ -197,7 +218,7 @@ impl UserOperationTrait for UserOperation {
     fn static_pre_verification_gas(&self, chain_spec: &ChainSpec) -> u128 {
         self.calldata_gas_cost
             + chain_spec.per_user_op_v0_7_gas()
-            + (if self.factory.is_some() {
+            + (if self.factory().is_some() {
                 chain_spec.per_user_op_deploy_overhead_gas()
             } else {
                 0
@@ -221,10 +242,16 @@ impl UserOperationTrait for UserOperation {
                 / 63)
     }
 
-    fn clear_signature(&mut self) {
-        self.signature = Bytes::new();
+    fn replace_signature(&mut self, signature: Bytes) {
+        self.signature = signature;
         self.packed = pack_user_operation(self.clone());
-        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+        self.hash = hash_user_operation(
+            &self.packed,
+            self.entry_point_version,
+            self.entry_point,
+            self.chain_id,
+            self.eip7702_delegate(),
+        );
     }
 
     fn abi_encoded_size(&self) -> usize {
@@ -238,6 +265,12 @@ impl UserOperationTrait for UserOperation {
     fn authorization_tuple(&self) -> Option<Authorization> {
         self.authorization_tuple.clone()
     }
+
+    /// Only entry point v0.8 supports this marker.
+    fn has_eip7702_init_code(&self) -> bool {
+        self.entry_point_version == EntryPointVersion::V0_8
+            && self.factory == Some(INIT_CODE_EIP7702_MARKER)
+    }
 }
 
 impl UserOperation {
@@ -250,6 +283,13 @@ impl UserOperation {
     pub fn packed(&self) -> &PackedUserOperation {
         &self.packed
     }
+
+    // The delegate of a 7702 sender is part of the v0.8 hash, it is taken from the
+    // authorization tuple as the sender's code isn't known offchain. Operations with
+    // the 7702 marker but without an authorization are rejected during precheck.
+    fn eip7702_delegate(&self) -> Option<Address> {
+        self.authorization_tuple.as_ref().map(|a| a.address)
+    }
 }
 
 impl From<UserOperationVariant> for UserOperation {
@@ -525,6 +565,9 @@ pub struct UserOperationBuilder<'a> {
     // chain spec
     chain_spec: &'a ChainSpec,
 
+    // entry point version, v0.7 or v0.8
+    entry_point_version: EntryPointVersion,
+
     // required fields
     required: UserOperationRequiredFields,
 
@@ -568,6 +611,7 @@ impl<'a> UserOperationBuilder<'a> {
     pub fn new(chain_spec: &'a ChainSpec, required: UserOperationRequiredFields) -> Self {
         Self {
             chain_spec,
+            entry_point_version: EntryPointVersion::V0_7,
             required,
             factory: None,
             factory_data: Bytes::new(),
@@ -631,6 +675,7 @@ impl<'a> UserOperationBuilder<'a> {
     pub fn from_uo(uo: UserOperation, chain_spec: &'a ChainSpec) -> Self {
         Self {
             chain_spec,
+            entry_point_version: uo.entry_point_version,
             required: UserOperationRequiredFields {
                 sender: uo.sender,
                 nonce: uo.nonce,
@@ -653,6 +698,23 @@ impl<'a> UserOperationBuilder<'a> {
         }
     }
 
+    /// Sets the entry point version of the operation, v0.7 unless set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the version is not v0.7 or v0.8
+    pub fn entry_point_version(mut self, entry_point_version: EntryPointVersion) -> Self {
+        assert!(
+            matches!(
+                entry_point_version,
+                EntryPointVersion::V0_7 | EntryPointVersion::V0_8
+            ),
+            "invalid entry point version for packed user operation: {entry_point_version:?}"
+        );
+        self.entry_point_version = entry_point_version;
+        self
+    }
+
     /// Sets the factory and factory data
     pub fn factory(mut self, factory: Address, factory_data: Bytes) -> Self {
         self.factory = Some(factory);
@@ -734,6 +796,10 @@ impl<'a> UserOperationBuilder<'a> {
 
     /// Builds the UserOperation
     pub fn build(self) -> UserOperation {
+        let entry_point = match self.entry_point_version {
+            EntryPointVersion::V0_8 => self.chain_spec.entry_point_address_v0_8,
+            _ => self.chain_spec.entry_point_address_v0_7,
+        };
         let uo = UserOperation {
             sender: self.required.sender,
             nonce: self.required.nonce,
@@ -751,7 +817,8 @@ impl<'a> UserOperationBuilder<'a> {
             paymaster_data: self.paymaster_data,
             authorization_tuple: self.authorization_tuple,
             signature: self.required.signature,
-            entry_point: self.chain_spec.entry_point_address_v0_7,
+            entry_point,
+            entry_point_version: self.entry_point_version,
             chain_id: self.chain_spec.id,
             hash: B256::ZERO,
             packed: PackedUserOperation::default(),
@@ -761,10 +828,12 @@ impl<'a> UserOperationBuilder<'a> {
         let packed = self
             .packed_uo
             .unwrap_or_else(|| pack_user_operation(uo.clone()));
-        let hash = hash_packed_user_operation(
+        let hash = hash_user_operation(
             &packed,
-            self.chain_spec.entry_point_address_v0_7,
-            self.chain_spec.id,
+            uo.entry_point_version,
+            uo.entry_point,
+            uo.chain_id,
+            uo.eip7702_delegate(),
         );
         let calldata_gas_cost = super::op_calldata_gas_cost(
             packed.clone(),
@@ -845,6 +914,35 @@ sol! {
         bytes32 gasFees;
         bytes32 hashPaymasterAndData;
     }
+
+    #[allow(missing_docs)]
+    #[derive(Default, Debug, PartialEq, Eq)]
+    struct UserOperationPackedForEip712Hash {
+        bytes32 typeHash;
+        address sender;
+        uint256 nonce;
+        bytes32 hashInitCode;
+        bytes32 hashCallData;
+        bytes32 accountGasLimits;
+        uint256 preVerificationGas;
+        bytes32 gasFees;
+        bytes32 hashPaymasterAndData;
+    }
+}
+
+fn hash_user_operation(
+    puo: &PackedUserOperation,
+    entry_point_version: EntryPointVersion,
+    entry_point: Address,
+    chain_id: u64,
+    eip7702_delegate: Option<Address>,
+) -> B256 {
+    match entry_point_version {
+        EntryPointVersion::V0_8 => {
+            eip712_hash_packed_user_operation(puo, entry_point, chain_id, eip7702_delegate)
+        }
+        _ => hash_packed_user_operation(puo, entry_point, chain_id),
+    }
 }
 
 fn hash_packed_user_operation(
@@ -878,6 +976,54 @@ fn hash_packed_user_operation(
     alloy_primitives::keccak256(encoded.abi_encode())
 }
 
+// Entry point v0.8 hashes operations as EIP-712 typed data. Init code carrying the
+// 7702 marker is hashed with the marker replaced by the sender's delegate. Without a
+// delegate the hash can't match the entry point's, such operations fail precheck.
+fn eip712_hash_packed_user_operation(
+    puo: &PackedUserOperation,
+    entry_point: Address,
+    chain_id: u64,
+    eip7702_delegate: Option<Address>,
+) -> B256 {
+    let hash_init_code =
+        if puo.initCode.len() >= 20 && puo.initCode[..20] == INIT_CODE_EIP7702_MARKER[..] {
+            let mut init_code = eip7702_delegate.unwrap_or_default().to_vec();
+            init_code.extend_from_slice(&puo.initCode[20..]);
+            alloy_primitives::keccak256(init_code)
+        } else {
+            alloy_primitives::keccak256(&puo.initCode)
+        };
+
+    let struct_hash = alloy_primitives::keccak256(
+        UserOperationPackedForEip712Hash {
+            typeHash: PACKED_USER_OPERATION_TYPE_HASH,
+            sender: puo.sender,
+            nonce: puo.nonce,
+            hashInitCode: hash_init_code,
+            hashCallData: alloy_primitives::keccak256(&puo.callData),
+            accountGasLimits: puo.accountGasLimits,
+            preVerificationGas: puo.preVerificationGas,
+            gasFees: puo.gasFees,
+            hashPaymasterAndData: alloy_primitives::keccak256(&puo.paymasterAndData),
+        }
+        .abi_encode(),
+    );
+
+    let domain = Eip712Domain::new(
+        Some("ERC4337".into()),
+        Some("1".into()),
+        Some(U256::from(chain_id)),
+        Some(entry_point),
+        None,
+    );
+
+    let mut digest = Vec::with_capacity(66);
+    digest.extend_from_slice(&[0x19, 0x01]);
+    digest.extend_from_slice(domain.separator().as_slice());
+    digest.extend_from_slice(struct_hash.as_slice());
+    alloy_primitives::keccak256(digest)
+}
+
 fn concat_u128_be(a: u128, b: u128) -> [u8; 32] {
     let a = a.to_be_bytes();
     let b = b.to_be_bytes();
@@ -923,6 +1069,35 @@ mod tests {
         assert_eq!(uo, unpacked);
     }
 
+    #[test]
+    fn test_replace_signature() {
+        let cs = ChainSpec::default();
+        let mut uo = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender: Address::ZERO,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 0,
+                verification_gas_limit: 0,
+                pre_verification_gas: 0,
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+                signature: bytes!("0x1234"),
+            },
+        )
+        .build();
+        let hash = uo.hash(Address::ZERO, 0);
+
+        uo.replace_signature(bytes!("0x5678"));
+
+        assert_eq!(uo.signature, bytes!("0x5678"));
+        // the packed op is submitted in bundles, so must also be updated
+        assert_eq!(uo.packed().signature, bytes!("0x5678"));
+        // the hash does not cover the signature
+        assert_eq!(uo.hash(Address::ZERO, 0), hash);
+    }
+
     #[test]
     fn test_pack_unpack_2() {
         let cs = ChainSpec::default();
@@ -978,6 +1153,72 @@ mod tests {
         assert_eq!(uo.hash(cs.entry_point_address_v0_7, cs.id), hash);
     }
 
+    fn zero_required_fields() -> UserOperationRequiredFields {
+        UserOperationRequiredFields {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            call_data: Bytes::new(),
+            call_gas_limit: 0,
+            verification_gas_limit: 0,
+            pre_verification_gas: 0,
+            max_priority_fee_per_gas: 0,
+            max_fee_per_gas: 0,
+            signature: Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn test_hash_v0_8() {
+        let cs = ChainSpec {
+            id: 1,
+            ..Default::default()
+        };
+
+        let uo = UserOperationBuilder::new(&cs, zero_required_fields())
+            .entry_point_version(EntryPointVersion::V0_8)
+            .build();
+
+        assert_eq!(uo.entry_point, cs.entry_point_address_v0_8);
+        assert_eq!(
+            uo.hash,
+            b256!("6d44ee30044a405a7c47823e632efda75477ef5b4767ada9097b2265d44741b4")
+        );
+    }
+
+    #[test]
+    fn test_hash_v0_8_eip7702_init_code() {
+        let cs = ChainSpec {
+            id: 1,
+            ..Default::default()
+        };
+
+        let uo = UserOperationBuilder::new(&cs, zero_required_fields())
+            .entry_point_version(EntryPointVersion::V0_8)
+            .factory(INIT_CODE_EIP7702_MARKER, bytes!("deadbeef"))
+            .authorization_tuple(Some(Authorization {
+                address: address!("1111111111111111111111111111111111111111"),
+                ..Default::default()
+            }))
+            .build();
+
+        // the marker is replaced by the delegate when hashing
+        assert_eq!(
+            uo.hash,
+            b256!("deab0fb8c65367aedf6835c9548fb054943f152f7cd229434308abd8970054f9")
+        );
+        assert!(uo.has_eip7702_init_code());
+        assert_eq!(uo.factory(), None);
+        assert_eq!(uo.entities(), vec![Entity::account(Address::ZERO)]);
+
+        // the round trip through the packed format keeps the version and hash
+        let unpacked = UserOperationBuilder::from_packed(uo.clone().pack(), &cs)
+            .unwrap()
+            .entry_point_version(EntryPointVersion::V0_8)
+            .authorization_tuple(uo.authorization_tuple.clone())
+            .build();
+        assert_eq!(uo, unpacked);
+    }
+
     #[test]
     fn test_builder() {
         let factory_address = Address::random();
@@ -16,6 +16,7 @@
 use std::fmt::Display;
 
 use alloy_primitives::Address;
+use serde::Serialize;
 use tokio::sync::broadcast::{self, error::RecvError};
 use tracing::{info, warn};
 
@@ -27,7 +28,8 @@ pub const EVENT_CHANNEL_CAPACITY: usize = 1000;
 
 /// A wrapper for an event that also contains the entry point that it
 /// is associated with.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct WithEntryPoint<T> {
     /// Entry point address associated with the event
     pub entry_point: Address,
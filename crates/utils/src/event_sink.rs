@@ -0,0 +1,443 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Sinks for exporting serialized events to external systems
+
+use std::{future::Future, mem, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{self, error::TrySendError},
+    },
+    time,
+};
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::retry::{self, RetryOpts};
+
+/// A destination for batches of serialized events
+pub trait EventSink: Send + Sync {
+    /// Writes a batch of events to the sink. May be called again with the same
+    /// batch if it fails.
+    fn write(&self, batch: &[Value]) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// Appends events to a file, one JSON object per line
+#[derive(Clone, Debug)]
+pub struct JsonLinesFileSink {
+    path: PathBuf,
+}
+
+impl JsonLinesFileSink {
+    /// Create a sink appending to the file at `path`, which is created if missing
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl EventSink for JsonLinesFileSink {
+    async fn write(&self, batch: &[Value]) -> anyhow::Result<()> {
+        let mut lines = Vec::new();
+        for event in batch {
+            serde_json::to_writer(&mut lines, event)?;
+            lines.push(b'\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("should open event file {}", self.path.display()))?;
+        file.write_all(&lines).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Posts each batch of events to an HTTP endpoint as a JSON array
+#[derive(Clone, Debug)]
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl WebhookSink {
+    /// Create a sink posting to `url`, failing requests that take longer than `timeout`
+    pub fn new(url: Url, timeout: Duration) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("should build webhook client")?;
+        Ok(Self { client, url })
+    }
+}
+
+impl EventSink for WebhookSink {
+    async fn write(&self, batch: &[Value]) -> anyhow::Result<()> {
+        self.client
+            .post(self.url.clone())
+            .json(batch)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Settings for batching events written to a sink
+#[derive(Clone, Copy, Debug)]
+pub struct EventSinkSettings {
+    /// Maximum number of events in a batch
+    pub batch_size: usize,
+    /// Maximum time an event is held before its batch is written
+    pub flush_interval: Duration,
+    /// Maximum number of batches waiting to be written, further batches are
+    /// dropped while the queue is full
+    pub max_queued_batches: usize,
+    /// Retries for failed writes, the batch is dropped once they are exhausted
+    pub retry: RetryOpts,
+}
+
+impl Default for EventSinkSettings {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            max_queued_batches: 10,
+            retry: RetryOpts {
+                max_attempts: 5,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Receive events from a broadcast channel, convert them to records and write
+/// them to the sink in batches.
+///
+/// Events for which `to_record` returns `None` are not exported. Batches are
+/// written, and retried, separately from receiving so a slow sink does not
+/// cause the event stream to lag. Pending events are written when the channel
+/// closes.
+pub async fn receive_events_to_sink<T, R, S>(
+    description: &'static str,
+    rx: broadcast::Receiver<T>,
+    to_record: impl Fn(&T) -> Option<R> + Send + 'static,
+    sink: S,
+    settings: EventSinkSettings,
+) where
+    T: Clone + Send + 'static,
+    R: Serialize,
+    S: EventSink,
+{
+    let (batch_tx, batch_rx) = mpsc::channel(settings.max_queued_batches.max(1));
+    tokio::join!(
+        receive_batches(description, rx, to_record, batch_tx, settings),
+        write_batches(description, &sink, batch_rx, settings.retry),
+    );
+    info!("Event stream for {description} closed. Export complete");
+}
+
+async fn receive_batches<T, R>(
+    description: &'static str,
+    mut rx: broadcast::Receiver<T>,
+    to_record: impl Fn(&T) -> Option<R>,
+    batch_tx: mpsc::Sender<Vec<Value>>,
+    settings: EventSinkSettings,
+) where
+    T: Clone,
+    R: Serialize,
+{
+    let mut batch = Vec::with_capacity(settings.batch_size);
+    let mut flush_interval = time::interval_at(
+        time::Instant::now() + settings.flush_interval,
+        settings.flush_interval,
+    );
+    flush_interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) => {
+                    let Some(record) = to_record(&event) else {
+                        continue;
+                    };
+                    match serde_json::to_value(record) {
+                        Ok(value) => batch.push(value),
+                        Err(e) => error!("Failed to serialize {description} event: {e:?}"),
+                    }
+                    if batch.len() >= settings.batch_size {
+                        queue_batch(description, &batch_tx, &mut batch, settings.batch_size);
+                    }
+                }
+                Err(RecvError::Closed) => {
+                    if !batch.is_empty() {
+                        // the writer only stops once this sender is dropped
+                        let _ = batch_tx.send(batch).await;
+                    }
+                    break;
+                }
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Event stream for {description} lagged. {count} events were not exported.")
+                }
+            },
+            _ = flush_interval.tick() => {
+                queue_batch(description, &batch_tx, &mut batch, settings.batch_size);
+            }
+        }
+    }
+}
+
+fn queue_batch(
+    description: &str,
+    batch_tx: &mpsc::Sender<Vec<Value>>,
+    batch: &mut Vec<Value>,
+    batch_size: usize,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let records = mem::replace(batch, Vec::with_capacity(batch_size));
+    if let Err(TrySendError::Full(records)) = batch_tx.try_send(records) {
+        error!(
+            "Export queue for {description} is full, dropping {} events",
+            records.len()
+        );
+    }
+}
+
+async fn write_batches<S: EventSink>(
+    description: &str,
+    sink: &S,
+    mut batch_rx: mpsc::Receiver<Vec<Value>>,
+    retry: RetryOpts,
+) {
+    while let Some(batch) = batch_rx.recv().await {
+        if let Err(e) = retry::with_retries(
+            &format!("export {description} events"),
+            || sink.write(&batch),
+            retry,
+        )
+        .await
+        {
+            error!(
+                "Failed to export {} {description} events, dropping them: {e:?}",
+                batch.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+    use tokio::{
+        io::AsyncReadExt,
+        net::{TcpListener, TcpStream},
+        sync::Semaphore,
+    };
+
+    use super::*;
+
+    fn settings(batch_size: usize) -> EventSinkSettings {
+        EventSinkSettings {
+            batch_size,
+            flush_interval: Duration::from_secs(60),
+            max_queued_batches: 10,
+            retry: RetryOpts {
+                max_attempts: 3,
+                min_nonzero_wait: Duration::from_millis(1),
+                max_wait: Duration::from_millis(1),
+                max_jitter: Duration::from_millis(1),
+            },
+        }
+    }
+
+    /// A local HTTP endpoint that responds to each request with the next status in
+    /// `statuses`, then 200, and records the body of each request.
+    async fn webhook_stand_in(statuses: Vec<u16>) -> (Url, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/events", listener.local_addr().unwrap())).unwrap();
+        let bodies = Arc::new(Mutex::new(vec![]));
+
+        let received = bodies.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let body = read_request_body(&mut stream).await;
+                received
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_slice(&body).unwrap());
+                let status = statuses.next().unwrap_or(200);
+                let response = format!(
+                    "HTTP/1.1 {status} OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, bodies)
+    }
+
+    async fn read_request_body(stream: &mut TcpStream) -> Vec<u8> {
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let Some(header_end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+                continue;
+            };
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .map(|l| l.trim().parse::<usize>().unwrap())
+                .unwrap_or(0);
+            let body_start = header_end + 4;
+            if request.len() >= body_start + content_length {
+                return request[body_start..body_start + content_length].to_vec();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_lines() {
+        let path =
+            std::env::temp_dir().join(format!("rundler-events-{}.jsonl", rand::random::<u64>()));
+        let sink = JsonLinesFileSink::new(path.clone());
+
+        sink.write(&[json!({"a": 1}), json!({"b": 2})])
+            .await
+            .unwrap();
+        sink.write(&[json!({"c": 3})]).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(contents, "{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_batches_and_flushes_on_close() {
+        let (url, bodies) = webhook_stand_in(vec![]).await;
+        let sink = WebhookSink::new(url, Duration::from_secs(5)).unwrap();
+        let (tx, rx) = broadcast::channel(16);
+
+        for i in 0..5u64 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        receive_events_to_sink(
+            "test",
+            rx,
+            |i: &u64| (*i != 3).then(|| json!({ "i": i })),
+            sink,
+            settings(2),
+        )
+        .await;
+
+        assert_eq!(
+            *bodies.lock().unwrap(),
+            vec![json!([{"i": 0}, {"i": 1}]), json!([{"i": 2}, {"i": 4}]),]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_failed_batch() {
+        let (url, bodies) = webhook_stand_in(vec![500, 503]).await;
+        let sink = WebhookSink::new(url, Duration::from_secs(5)).unwrap();
+        let (tx, rx) = broadcast::channel(16);
+
+        tx.send(1u64).unwrap();
+        drop(tx);
+        receive_events_to_sink("test", rx, |i: &u64| Some(*i), sink, settings(10)).await;
+
+        // two failures, then success
+        assert_eq!(*bodies.lock().unwrap(), vec![json!([1]); 3]);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_drops_batch_after_retries() {
+        let (url, bodies) = webhook_stand_in(vec![500; 10]).await;
+        let sink = WebhookSink::new(url, Duration::from_secs(5)).unwrap();
+        let (tx, rx) = broadcast::channel(16);
+
+        tx.send(1u64).unwrap();
+        tx.send(2u64).unwrap();
+        drop(tx);
+        receive_events_to_sink("test", rx, |i: &u64| Some(*i), sink, settings(1)).await;
+
+        // both batches are attempted `max_attempts` times
+        assert_eq!(bodies.lock().unwrap().len(), 6);
+    }
+
+    /// Records written batches, holding each write until a permit is released
+    struct GatedSink {
+        permits: Arc<Semaphore>,
+        written: Arc<Mutex<Vec<Vec<Value>>>>,
+    }
+
+    impl EventSink for GatedSink {
+        async fn write(&self, batch: &[Value]) -> anyhow::Result<()> {
+            self.permits.acquire().await?.forget();
+            self.written.lock().unwrap().push(batch.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_sink_does_not_lag_stream() {
+        let permits = Arc::new(Semaphore::new(0));
+        let written = Arc::new(Mutex::new(vec![]));
+        let sink = GatedSink {
+            permits: permits.clone(),
+            written: written.clone(),
+        };
+        let (tx, rx) = broadcast::channel(2);
+        let export = tokio::spawn(receive_events_to_sink(
+            "test",
+            rx,
+            |i: &u64| Some(*i),
+            sink,
+            settings(1),
+        ));
+
+        // the first write is stuck, later events are still received and queued
+        for i in 0..6u64 {
+            tx.send(i).unwrap();
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(written.lock().unwrap().is_empty());
+
+        permits.add_permits(6);
+        drop(tx);
+        export.await.unwrap();
+
+        assert_eq!(
+            *written.lock().unwrap(),
+            (0..6u64).map(|i| vec![json!(i)]).collect::<Vec<_>>()
+        );
+    }
+}
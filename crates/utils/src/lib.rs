@@ -24,8 +24,10 @@ pub mod authorization_utils;
 pub mod cache;
 pub mod emit;
 pub mod eth;
+pub mod event_sink;
 pub mod guard_timer;
 pub mod log;
 pub mod math;
+pub mod nonce_utils;
 pub mod retry;
 pub mod strs;
@@ -0,0 +1,91 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Utilities for entry point nonces
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+use alloy_rpc_types_eth::state::StateOverride;
+
+/// Storage slot of the entry point's `nonceSequenceNumber` mapping.
+///
+/// The same for all supported entry point versions.
+const NONCE_SEQUENCE_NUMBER_SLOT: u64 = 1;
+
+/// Apply an override to `StateOverride` that sets the entry point's expected nonce
+/// for `sender` to `nonce`, as if the operations with the earlier sequence numbers
+/// of the nonce's key were already executed.
+pub fn apply_nonce_override(
+    state_override: &mut StateOverride,
+    entry_point: Address,
+    sender: Address,
+    nonce: U256,
+) {
+    let key = nonce >> 64;
+    let sequence = nonce & U256::from(u64::MAX);
+    state_override
+        .entry(entry_point)
+        .or_default()
+        .state_diff
+        .get_or_insert_with(Default::default)
+        .insert(nonce_sequence_slot(sender, key), sequence.into());
+}
+
+/// Storage slot of `nonceSequenceNumber[sender][key]`
+fn nonce_sequence_slot(sender: Address, key: U256) -> B256 {
+    let mut buf = [0; 64];
+    buf[12..32].copy_from_slice(sender.as_slice());
+    buf[32..].copy_from_slice(&U256::from(NONCE_SEQUENCE_NUMBER_SLOT).to_be_bytes::<32>());
+    let inner = keccak256(buf);
+
+    buf[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    buf[32..].copy_from_slice(inner.as_slice());
+    keccak256(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, b256};
+
+    use super::*;
+
+    #[test]
+    fn test_apply_nonce_override() {
+        let entry_point = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+        let sender = address!("1111111111111111111111111111111111111111");
+        let mut state_override = StateOverride::default();
+
+        apply_nonce_override(
+            &mut state_override,
+            entry_point,
+            sender,
+            (U256::from(3) << 64) | U256::from(7),
+        );
+
+        let state_diff = state_override[&entry_point].state_diff.as_ref().unwrap();
+        assert_eq!(
+            state_diff[&nonce_sequence_slot(sender, U256::from(3))],
+            B256::from(U256::from(7))
+        );
+    }
+
+    #[test]
+    fn test_nonce_sequence_slot() {
+        assert_eq!(
+            nonce_sequence_slot(
+                address!("1111111111111111111111111111111111111111"),
+                U256::ZERO
+            ),
+            b256!("53576231d24dd226f8944f9473fe29cd80ca059775ce9d983d1726c8fe5174a6")
+        );
+    }
+}